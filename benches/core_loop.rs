@@ -0,0 +1,138 @@
+//! Core loop benchmarks
+//!
+//! Measures instruction decode, execute and full `Simulator::step()`
+//! throughput. The step benchmarks run representative firmware, a nested
+//! delay loop and a Timer1 interrupt firing every few dozen cycles, and
+//! report instructions per second, so `cargo bench` gives a MIPS figure.
+//! `step/observed` repeats the delay loop with an event observer
+//! subscribed, as the GUI and CLI run it.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use pic_simulator::{Cpu, Executor, InstructionDecoder, Simulator};
+
+/// Instructions executed per step benchmark iteration
+const STEPS: u64 = 10_000;
+
+/// Nested busy-wait loop, as in most blink firmware
+const DELAY_LOOP: &str = "
+        ORG     0
+main    movlw   0x10
+        movwf   0x21
+outer   movlw   0xFF
+        movwf   0x20
+inner   clrwdt
+        decfsz  0x20, F
+        goto    inner
+        decfsz  0x21, F
+        goto    outer
+        goto    main
+        END
+";
+
+/// Timer1 reloaded to overflow every ~32 cycles; the ISR counts
+/// interrupts while the main loop spins
+const ISR_HEAVY: &str = "
+        ORG     0
+        goto    init
+        ORG     4
+isr     movwf   0x70
+        swapf   STATUS, W
+        movwf   0x71
+        incf    0x20, F
+        movlw   0xFF
+        movwf   TMR1H
+        movlw   0xE0
+        movwf   TMR1L
+        bcf     PIR1, TMR1IF
+        swapf   0x71, W
+        movwf   STATUS
+        swapf   0x70, F
+        swapf   0x70, W
+        retfie
+init    bsf     STATUS, RP0
+        bsf     PIE1, TMR1IE
+        bcf     STATUS, RP0
+        movlw   0xFF
+        movwf   TMR1H
+        movlw   0x01
+        movwf   T1CON
+        movlw   0xC0
+        movwf   INTCON
+loop    clrwdt
+        incf    0x21, F
+        goto    loop
+        END
+";
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(0x4000));
+    group.bench_function("all_words", |b| {
+        b.iter(|| {
+            for word in 0..0x4000u16 {
+                let _ = black_box(InstructionDecoder::decode(black_box(word)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_execute(c: &mut Criterion) {
+    // MOVLW, ADDWF, MOVWF, BSF, BTFSC, DECFSZ, INCF, CLRWDT
+    let words = [0x3005, 0x07A0, 0x00A1, 0x1421, 0x1821, 0x0BA2, 0x0AA3, 0x0064];
+    let instructions: Vec<_> = words.iter()
+        .map(|&word| InstructionDecoder::decode(word).unwrap())
+        .collect();
+    let mut cpu = Cpu::new();
+    cpu.reset();
+
+    let mut group = c.benchmark_group("execute");
+    group.throughput(Throughput::Elements(instructions.len() as u64));
+    group.bench_function("mixed", |b| {
+        b.iter(|| {
+            for &instruction in &instructions {
+                black_box(Executor::execute(&mut cpu, black_box(instruction)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.throughput(Throughput::Elements(STEPS));
+    for (name, source) in [("delay_loop", DELAY_LOOP), ("isr_heavy", ISR_HEAVY)] {
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_asm_string(source, name).unwrap();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for _ in 0..STEPS {
+                    black_box(sim.step().unwrap());
+                }
+            })
+        });
+    }
+
+    // Front-ends subscribe to events, which turns on register access
+    // tracking for every instruction
+    let mut sim = Simulator::new();
+    sim.reset();
+    sim.load_asm_string(DELAY_LOOP, "delay_loop").unwrap();
+    sim.subscribe(|event| {
+        black_box(event);
+    });
+    group.bench_function("observed", |b| {
+        b.iter(|| {
+            for _ in 0..STEPS {
+                black_box(sim.step().unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode, bench_execute, bench_step);
+criterion_main!(benches);
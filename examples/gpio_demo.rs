@@ -102,7 +102,7 @@ fn main() {
     for i in 0..6 {
         let pc = sim.cpu().get_pc();
         let word = sim.cpu().memory().read_program(pc);
-        let asm = Debugger::disassemble(word);
+        let asm = Debugger::disassemble(sim.device().core(), word);
         
         sim.step().unwrap();
         
@@ -25,7 +25,10 @@ fn main() {
     let result = HexLoader::load_file(path);
     let program = match result {
         Ok(p) => {
-            println!("✓ HEX file parsed successfully!");
+            println!("✓ HEX file parsed successfully! ({} words)", p.report.words_loaded);
+            for warning in &p.report.warnings {
+                println!("  ⚠ {}", warning);
+            }
             p
         }
         Err(e) => {
@@ -46,7 +49,7 @@ fn main() {
     for i in 0..6 {
         let pc = sim.cpu().get_pc();
         let word = sim.cpu().memory().read_program(pc);
-        let asm = Debugger::disassemble(word);
+        let asm = Debugger::disassemble(sim.device().core(), word);
 
         sim.step().unwrap();
         println!("Step {}: 0x{:04X} {}", i + 1, pc, asm);
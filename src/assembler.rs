@@ -0,0 +1,790 @@
+//! PIC midrange assembler
+//!
+//! Assembles MPASM/gpasm-style source for the 14-bit core into a
+//! `HexProgram` plus a `SymbolTable` holding the labels and the source line
+//! of every word, so assembled code can be stepped through in the source
+//! view like a loaded listing.
+//!
+//! Supported:
+//! - all midrange instructions; the destination defaults to `F`
+//! - labels in column 1 or ending in `:`, `;` comments
+//! - `EQU`/`SET`, `#define NAME value`, `CBLOCK`/`ENDC`, `ORG`, `END`
+//! - `DW` (raw words), `DT` (RETLW tables), `BANKSEL`, `__CONFIG`, `RADIX`
+//! - numbers as `0x1F`, `1Fh`, `H'1F'`, `B'0101'`, `D'10'`, `.10`, `O'17'`
+//!   and `'A'`, plain numbers in the current radix (decimal unless `RADIX HEX`)
+//! - expressions with `+ - * / % & | ^ << >> ~`, parentheses, `$`, `HIGH`
+//!   and `LOW`
+//!
+//! SFR and SFR bit names are predefined, as if the device include file
+//! were included; `LIST`, `PROCESSOR` and `#include` lines are ignored.
+//! Symbols are case-insensitive.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::cpu::{registers, status_bits};
+use crate::error::AsmError;
+use crate::hexloader::{HexProgram, LoadReport};
+use crate::instruction::Instruction;
+use crate::symbols::SymbolTable;
+
+/// First address past program memory
+const PROGRAM_END: i64 = 0x2000;
+
+/// Address the configuration word is programmed at
+const CONFIG_ADDRESS: i64 = 0x2007;
+
+/// Directives that take no label and are ignored
+const IGNORED: &[&str] = &[
+    "LIST", "NOLIST", "PROCESSOR", "#INCLUDE", "INCLUDE", "ERRORLEVEL", "TITLE", "SUBTITLE",
+    "__MAXRAM", "__BADRAM", "EXPAND", "NOEXPAND", "PAGE", "SPACE", "PAGESEL",
+];
+
+/// Result of assembling a source file
+#[derive(Debug, Clone)]
+pub struct Assembly {
+    pub program: HexProgram,
+    /// Code labels and the source line of every program word
+    pub symbols: SymbolTable,
+}
+
+/// Operands an instruction takes
+#[derive(Debug, Clone, Copy)]
+enum Operands {
+    None(Instruction),
+    File(fn(u8) -> Instruction),
+    FileDest(fn(u8, u8) -> Instruction),
+    FileBit(fn(u8, u8) -> Instruction),
+    Literal(fn(u8) -> Instruction),
+    Address(fn(u16) -> Instruction),
+}
+
+fn operands_of(mnemonic: &str) -> Option<Operands> {
+    use Instruction as I;
+    use Operands::*;
+    Some(match mnemonic {
+        "ADDWF" => FileDest(|f, d| I::ADDWF { f, d }),
+        "ANDWF" => FileDest(|f, d| I::ANDWF { f, d }),
+        "CLRF" => File(|f| I::CLRF { f }),
+        "CLRW" => None(I::CLRW),
+        "COMF" => FileDest(|f, d| I::COMF { f, d }),
+        "DECF" => FileDest(|f, d| I::DECF { f, d }),
+        "DECFSZ" => FileDest(|f, d| I::DECFSZ { f, d }),
+        "INCF" => FileDest(|f, d| I::INCF { f, d }),
+        "INCFSZ" => FileDest(|f, d| I::INCFSZ { f, d }),
+        "IORWF" => FileDest(|f, d| I::IORWF { f, d }),
+        "MOVF" => FileDest(|f, d| I::MOVF { f, d }),
+        "MOVWF" => File(|f| I::MOVWF { f }),
+        "NOP" => None(I::NOP),
+        "RLF" => FileDest(|f, d| I::RLF { f, d }),
+        "RRF" => FileDest(|f, d| I::RRF { f, d }),
+        "SUBWF" => FileDest(|f, d| I::SUBWF { f, d }),
+        "SWAPF" => FileDest(|f, d| I::SWAPF { f, d }),
+        "XORWF" => FileDest(|f, d| I::XORWF { f, d }),
+        "BCF" => FileBit(|f, b| I::BCF { f, b }),
+        "BSF" => FileBit(|f, b| I::BSF { f, b }),
+        "BTFSC" => FileBit(|f, b| I::BTFSC { f, b }),
+        "BTFSS" => FileBit(|f, b| I::BTFSS { f, b }),
+        "ADDLW" => Literal(|k| I::ADDLW { k }),
+        "ANDLW" => Literal(|k| I::ANDLW { k }),
+        "CALL" => Address(|k| I::CALL { k }),
+        "CLRWDT" => None(I::CLRWDT),
+        "GOTO" => Address(|k| I::GOTO { k }),
+        "IORLW" => Literal(|k| I::IORLW { k }),
+        "MOVLW" => Literal(|k| I::MOVLW { k }),
+        "RETFIE" => None(I::RETFIE),
+        "RETLW" => Literal(|k| I::RETLW { k }),
+        "RETURN" => None(I::RETURN),
+        "SLEEP" => None(I::SLEEP),
+        "SUBLW" => Literal(|k| I::SUBLW { k }),
+        "XORLW" => Literal(|k| I::XORLW { k }),
+        _ => return Option::None,
+    })
+}
+
+/// Whether `word` (upper case) starts a statement rather than naming a label
+fn is_keyword(word: &str) -> bool {
+    operands_of(word).is_some()
+        || IGNORED.contains(&word)
+        || matches!(word, "ORG" | "END" | "EQU" | "SET" | "CBLOCK" | "ENDC" | "DW" | "DT"
+            | "BANKSEL" | "__CONFIG" | "RADIX" | "#DEFINE")
+}
+
+/// Two-pass assembler state
+struct Assembler<'a> {
+    file: &'a str,
+    /// 1 while collecting label addresses, 2 while emitting code
+    pass: u8,
+    radix: u32,
+    address: i64,
+    /// Next CBLOCK address while inside a CBLOCK
+    cblock: Option<i64>,
+    symbols: HashMap<String, i64>,
+    /// Names the source defined, to catch duplicate labels
+    defined: HashSet<String>,
+    labels: Vec<(String, u16)>,
+    words: BTreeMap<u16, (u16, usize)>,
+    config: Option<u16>,
+    errors: Vec<AsmError>,
+    line: usize,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(file: &'a str) -> Self {
+        let mut symbols = HashMap::new();
+        for &(address, name) in registers::NAMES {
+            symbols.insert(name.to_string(), i64::from(address));
+        }
+        for (_, bits) in registers::BIT_NAMES {
+            for (bit, name) in bits.iter().enumerate().filter(|(_, name)| !name.is_empty()) {
+                symbols.insert(name.to_string(), bit as i64);
+            }
+        }
+        symbols.insert("W".to_string(), 0);
+        symbols.insert("F".to_string(), 1);
+        Self {
+            file,
+            pass: 1,
+            radix: 10,
+            address: 0,
+            cblock: None,
+            symbols,
+            defined: HashSet::new(),
+            labels: Vec::new(),
+            words: BTreeMap::new(),
+            config: None,
+            errors: Vec::new(),
+            line: 0,
+        }
+    }
+
+    fn error(&mut self, message: impl Into<String>) {
+        if self.pass == 2 {
+            self.errors.push(AsmError { line: self.line, message: message.into() });
+        }
+    }
+
+    /// Define `name`; redefinitions are reported once, in the first pass
+    fn define(&mut self, name: &str, value: i64, label: bool) {
+        let key = name.to_ascii_uppercase();
+        if self.pass == 1 && !self.defined.insert(key.clone()) && label {
+            self.errors.push(AsmError { line: self.line, message: format!("Duplicate label '{}'", name) });
+        } else if self.pass == 1 && label {
+            self.labels.push((name.to_string(), value as u16));
+        }
+        self.symbols.insert(key, value);
+    }
+
+    fn emit(&mut self, word: u16) {
+        if !(0..PROGRAM_END).contains(&self.address) {
+            self.error(format!("Address 0x{:X} is outside program memory", self.address));
+        } else if self.pass == 2 {
+            let address = self.address as u16;
+            if self.words.insert(address, (word, self.line)).is_some() {
+                self.error(format!("Overwriting code at 0x{:03X}", address));
+            }
+        }
+        self.address += 1;
+    }
+
+    fn assemble_line(&mut self, text: &str) -> bool {
+        let code = strip_comment(text);
+        if code.trim().is_empty() {
+            return true;
+        }
+
+        // Inside CBLOCK every name gets the next address
+        if let Some(mut next) = self.cblock {
+            if code.trim().eq_ignore_ascii_case("ENDC") {
+                self.cblock = None;
+                return true;
+            }
+            for entry in code.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                let (name, size) = match entry.split_once(':') {
+                    Some((name, size)) => (name.trim(), self.eval(size).unwrap_or(1)),
+                    None => (entry, 1),
+                };
+                if !is_identifier(name) {
+                    self.error(format!("Invalid CBLOCK name '{}'", name));
+                    continue;
+                }
+                self.define(name, next, false);
+                next += size;
+            }
+            self.cblock = Some(next);
+            return true;
+        }
+
+        // Label in column 1 or ending in ':'
+        let mut rest = code.trim_start();
+        let mut label = None;
+        let first = rest.split_whitespace().next().unwrap_or("");
+        let column_one = !code.starts_with(char::is_whitespace);
+        if let Some(name) = first.strip_suffix(':') {
+            label = Some(name);
+            rest = rest[first.len()..].trim_start();
+        } else if column_one && !is_keyword(&first.to_ascii_uppercase()) {
+            label = Some(first);
+            rest = rest[first.len()..].trim_start();
+        }
+
+        let (op, operands) = match rest.split_once(char::is_whitespace) {
+            Some((op, operands)) => (op, operands.trim()),
+            None => (rest, ""),
+        };
+        let op = op.to_ascii_uppercase();
+
+        match op.as_str() {
+            "EQU" | "SET" | "=" => {
+                let Some(name) = label else {
+                    self.error(format!("{} needs a name", op));
+                    return true;
+                };
+                if let Some(value) = self.eval(operands) {
+                    self.define(name, value, false);
+                }
+                return true;
+            }
+            "#DEFINE" => {
+                let (name, value) = operands.split_once(char::is_whitespace).unwrap_or((operands, "1"));
+                if let Some(value) = self.eval(value) {
+                    self.define(name, value, false);
+                }
+                return true;
+            }
+            _ => {}
+        }
+
+        if let Some(name) = label {
+            if !is_identifier(name) {
+                self.error(format!("Invalid label '{}'", name));
+            } else {
+                self.define(name, self.address, true);
+            }
+        }
+
+        match op.as_str() {
+            "" => {}
+            "END" => return false,
+            "ORG" => {
+                if let Some(address) = self.eval(operands) {
+                    self.address = address;
+                }
+            }
+            "CBLOCK" => {
+                let start = if operands.is_empty() { Some(0) } else { self.eval(operands) };
+                self.cblock = Some(start.unwrap_or(0));
+            }
+            "ENDC" => self.error("ENDC without CBLOCK"),
+            "RADIX" => match operands.to_ascii_uppercase().as_str() {
+                "HEX" => self.radix = 16,
+                "DEC" => self.radix = 10,
+                "OCT" => self.radix = 8,
+                other => self.error(format!("Unknown radix '{}'", other)),
+            },
+            "__CONFIG" => {
+                let operands: Vec<&str> = split_operands(operands);
+                // `__CONFIG address, value` or `__CONFIG value`
+                let value = match operands.as_slice() {
+                    [value] => self.eval(value),
+                    [address, value] => {
+                        if self.eval(address).is_some_and(|address| address != CONFIG_ADDRESS) {
+                            self.error("Only the configuration word at 0x2007 is supported");
+                        }
+                        self.eval(value)
+                    }
+                    _ => {
+                        self.error("__CONFIG takes a value");
+                        None
+                    }
+                };
+                if let Some(value) = value {
+                    self.config = Some(value as u16 & 0x3FFF);
+                }
+            }
+            "DW" => {
+                for operand in split_operands(operands) {
+                    let word = self.eval(operand).unwrap_or(0);
+                    self.emit(word as u16 & 0x3FFF);
+                }
+            }
+            "DT" => {
+                for operand in split_operands(operands) {
+                    if let Some(text) = operand.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                        for byte in text.bytes() {
+                            self.emit(Instruction::RETLW { k: byte }.encode());
+                        }
+                    } else {
+                        let k = self.eval(operand).map_or(0, |value| self.literal(value));
+                        self.emit(Instruction::RETLW { k }.encode());
+                    }
+                }
+            }
+            "BANKSEL" => {
+                // One bank select bit on the 12F6xx parts
+                let address = self.eval(operands).unwrap_or(0);
+                let f = registers::STATUS;
+                let b = status_bits::RP0;
+                let instruction = if address & 0x80 != 0 { Instruction::BSF { f, b } } else { Instruction::BCF { f, b } };
+                self.emit(instruction.encode());
+            }
+            op if IGNORED.contains(&op) => {}
+            op => match operands_of(op) {
+                Some(kind) => {
+                    let word = self.instruction(op, kind, operands).map_or(0, |i| i.encode());
+                    self.emit(word);
+                }
+                None => {
+                    self.error(format!("Unknown instruction or directive '{}'", op));
+                    // Assume one word so later labels stay in place
+                    self.address += 1;
+                }
+            },
+        }
+        true
+    }
+
+    fn instruction(&mut self, op: &str, kind: Operands, operands: &str) -> Option<Instruction> {
+        let args = split_operands(operands);
+        let expected = match kind {
+            Operands::None(_) => 0..=0,
+            Operands::FileDest(_) => 1..=2,
+            Operands::FileBit(_) => 2..=2,
+            _ => 1..=1,
+        };
+        if !expected.contains(&args.len()) {
+            let count = if expected.start() == expected.end() {
+                expected.start().to_string()
+            } else {
+                format!("{} or {}", expected.start(), expected.end())
+            };
+            self.error(format!("{} takes {} operands, got {}", op, count, args.len()));
+            return None;
+        }
+
+        let values: Vec<i64> = args.iter().map(|arg| self.eval(arg)).collect::<Option<_>>()?;
+        let file = |value: i64| (value & 0x7F) as u8;
+        Some(match kind {
+            Operands::None(instruction) => instruction,
+            Operands::File(make) => make(file(values[0])),
+            Operands::FileDest(make) => {
+                let d = values.get(1).copied().unwrap_or(1);
+                if !(0..=1).contains(&d) {
+                    self.error(format!("Destination must be W or F, got {}", d));
+                }
+                make(file(values[0]), d as u8 & 1)
+            }
+            Operands::FileBit(make) => {
+                if !(0..=7).contains(&values[1]) {
+                    self.error(format!("Bit number {} out of range", values[1]));
+                }
+                make(file(values[0]), values[1] as u8 & 7)
+            }
+            Operands::Literal(make) => make(self.literal(values[0])),
+            Operands::Address(make) => {
+                if !(0..PROGRAM_END).contains(&values[0]) {
+                    self.error(format!("Branch target 0x{:X} out of range", values[0]));
+                }
+                make(values[0] as u16 & 0x7FF)
+            }
+        })
+    }
+
+    /// An 8-bit literal; negative values down to -128 wrap
+    fn literal(&mut self, value: i64) -> u8 {
+        if !(-128..=255).contains(&value) {
+            self.error(format!("Literal {} does not fit in 8 bits", value));
+        }
+        value as u8
+    }
+
+    /// Evaluate an expression; undefined symbols are errors in the second pass
+    fn eval(&mut self, text: &str) -> Option<i64> {
+        let mut parser = ExprParser { text: text.trim(), pos: 0, asm: self, undefined: None };
+        let result = parser.expression(0);
+        let trailing = parser.text[parser.pos..].trim();
+        match (result, parser.undefined) {
+            (_, Some(name)) => {
+                self.error(format!("Undefined symbol '{}'", name));
+                // Labels defined later read as 0 during the first pass
+                if self.pass == 1 { Some(0) } else { None }
+            }
+            (Ok(_), None) if !trailing.is_empty() => {
+                self.error(format!("Unexpected '{}' in expression", trailing));
+                None
+            }
+            (Ok(value), None) => Some(value),
+            (Err(message), None) => {
+                self.error(message);
+                None
+            }
+        }
+    }
+}
+
+/// Expression parser over one operand
+struct ExprParser<'t, 'a, 'b> {
+    text: &'t str,
+    pos: usize,
+    asm: &'b Assembler<'a>,
+    /// First undefined symbol seen
+    undefined: Option<String>,
+}
+
+/// Binary operators by precedence, lowest first
+const BINARY_OPERATORS: &[&[&str]] = &[&["|"], &["^"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/", "%"]];
+
+impl ExprParser<'_, '_, '_> {
+    fn skip_space(&mut self) {
+        while self.text[self.pos..].starts_with(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_space();
+        if self.text[self.pos..].starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expression(&mut self, level: usize) -> Result<i64, String> {
+        let Some(operators) = BINARY_OPERATORS.get(level) else {
+            return self.unary();
+        };
+        let mut value = self.expression(level + 1)?;
+        'operators: loop {
+            for &operator in *operators {
+                if self.eat(operator) {
+                    let rhs = self.expression(level + 1)?;
+                    value = match operator {
+                        "|" => value | rhs,
+                        "^" => value ^ rhs,
+                        "&" => value & rhs,
+                        "<<" => value.checked_shl(rhs as u32).unwrap_or(0),
+                        ">>" => value.checked_shr(rhs as u32).unwrap_or(0),
+                        "+" => value.wrapping_add(rhs),
+                        "-" => value.wrapping_sub(rhs),
+                        "*" => value.wrapping_mul(rhs),
+                        _ if rhs == 0 => return Err("Division by zero".to_string()),
+                        "/" => value / rhs,
+                        _ => value % rhs,
+                    };
+                    continue 'operators;
+                }
+            }
+            return Ok(value);
+        }
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        if self.eat("-") {
+            return Ok(-self.unary()?);
+        }
+        if self.eat("~") {
+            return Ok(!self.unary()?);
+        }
+        if self.eat("(") {
+            let value = self.expression(0)?;
+            if !self.eat(")") {
+                return Err("Missing ')'".to_string());
+            }
+            return Ok(value);
+        }
+        if self.eat("$") {
+            return Ok(self.asm.address);
+        }
+        self.skip_space();
+
+        let rest = &self.text[self.pos..];
+        // Quoted forms: H'1F', B'0101', D'10', O'17', A'c' and 'c'
+        let upper = rest.get(..2).map(str::to_ascii_uppercase);
+        if let Some(prefix) = upper.filter(|p| matches!(p.as_str(), "H'" | "B'" | "D'" | "O'" | "A'")) {
+            let body = &rest[2..];
+            let end = body.find('\'').ok_or("Missing closing quote")?;
+            self.pos += 2 + end + 1;
+            let digits = &body[..end];
+            return match &prefix[..1] {
+                "A" => digits.chars().next().map(|c| c as i64).ok_or_else(|| "Empty character".to_string()),
+                radix => parse_digits(digits, match radix { "H" => 16, "B" => 2, "D" => 10, _ => 8 }),
+            };
+        }
+        if let Some(body) = rest.strip_prefix('\'') {
+            let mut chars = body.chars();
+            let (Some(c), Some('\'')) = (chars.next(), chars.next()) else {
+                return Err("Invalid character literal".to_string());
+            };
+            self.pos += 2 + c.len_utf8();
+            return Ok(c as i64);
+        }
+
+        let length = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(rest.len());
+        let token = &rest[..length];
+        if token.is_empty() {
+            return Err(if rest.is_empty() { "Missing operand".to_string() } else { format!("Unexpected '{}'", rest) });
+        }
+        self.pos += length;
+
+        let upper = token.to_ascii_uppercase();
+        if upper == "HIGH" || upper == "LOW" {
+            let value = self.unary()?;
+            return Ok(if upper == "HIGH" { (value >> 8) & 0xFF } else { value & 0xFF });
+        }
+        if let Some(decimal) = token.strip_prefix('.') {
+            return parse_digits(decimal, 10);
+        }
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            return parse_number(token, self.asm.radix);
+        }
+        match self.asm.symbols.get(&upper) {
+            Some(&value) => Ok(value),
+            None => {
+                self.undefined.get_or_insert_with(|| token.to_string());
+                Ok(0)
+            }
+        }
+    }
+}
+
+/// Parse a number starting with a digit
+fn parse_number(token: &str, radix: u32) -> Result<i64, String> {
+    let lower = token.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        parse_digits(hex, 16)
+    } else if let Some(binary) = lower.strip_prefix("0b").filter(|_| radix != 16) {
+        parse_digits(binary, 2)
+    } else if let Some(hex) = lower.strip_suffix('h') {
+        parse_digits(hex, 16)
+    } else {
+        parse_digits(&lower, radix)
+    }
+}
+
+fn parse_digits(digits: &str, radix: u32) -> Result<i64, String> {
+    i64::from_str_radix(digits, radix).map_err(|_| format!("Invalid number '{}'", digits))
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// The line without its `;` comment (a `;` inside quotes is kept)
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (';', None) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Split operands on commas outside quotes
+fn split_operands(operands: &str) -> Vec<&str> {
+    if operands.trim().is_empty() {
+        return Vec::new();
+    }
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in operands.char_indices() {
+        match (c, quote) {
+            ('"', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (',', None) => {
+                parts.push(operands[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(operands[start..].trim());
+    parts
+}
+
+/// Assemble `source`, naming it `file` in the symbol table
+///
+/// All errors are returned, in line order.
+pub fn assemble(source: &str, file: &str) -> Result<Assembly, Vec<AsmError>> {
+    let mut asm = Assembler::new(file);
+    for pass in 1..=2 {
+        asm.pass = pass;
+        asm.address = 0;
+        asm.radix = 10;
+        asm.cblock = None;
+        for (index, text) in source.lines().enumerate() {
+            asm.line = index + 1;
+            if !asm.assemble_line(text) {
+                break;
+            }
+        }
+    }
+    if !asm.errors.is_empty() {
+        asm.errors.sort_by_key(|e| e.line);
+        return Err(asm.errors);
+    }
+
+    let mut symbols = SymbolTable::new();
+    for (name, address) in &asm.labels {
+        symbols.add_label(name, *address);
+    }
+    for (index, text) in source.lines().enumerate() {
+        symbols.add_source_line(asm.file, index + 1, text);
+    }
+    let end = asm.words.keys().next_back().map_or(0, |&last| last as usize + 1);
+    let mut program = vec![0x3FFF; end];
+    for (&address, &(word, line)) in &asm.words {
+        program[address as usize] = word;
+        symbols.add_line(address, asm.file, line);
+    }
+
+    let report = LoadReport { words_loaded: asm.words.len(), warnings: Vec::new() };
+    Ok(Assembly {
+        program: HexProgram {
+            program,
+            eeprom: Vec::new(),
+            config: asm.config,
+            id_locations: None,
+            start_address: None,
+            report,
+        },
+        symbols,
+    })
+}
+
+/// Assemble one instruction line to be placed at `address`, for patching
+/// program memory
+///
+/// Operands may use SFR names, numbers in any supported syntax and the
+/// labels in `symbols`, e.g. `goto loop` or `bcf GPIO, GP2`. The line must
+/// produce exactly one word.
+pub fn assemble_instruction(text: &str, address: u16, symbols: &SymbolTable) -> Result<u16, String> {
+    // Labels become EQUs ahead of the instruction, which is on `line`
+    let mut source: String = symbols.labels()
+        .filter(|(_, name)| is_identifier(name))
+        .map(|(label_address, name)| format!("{} EQU 0x{:X}\n", name, label_address))
+        .collect();
+    let line = source.lines().count() + 2;
+    source.push_str(&format!(" ORG 0x{:X}\n {}\n END\n", address, text.trim()));
+
+    let assembly = assemble(&source, "patch").map_err(|errors| {
+        errors.iter()
+            .map(|e| if e.line == line { e.message.clone() } else { e.to_string() })
+            .collect::<Vec<_>>()
+            .join("; ")
+    })?;
+    match assembly.symbols.labels().next() {
+        Some((_, label)) => return Err(format!("Unexpected label '{}'", label)),
+        None if assembly.program.report.words_loaded != 1 => {
+            return Err(format!("'{}' must assemble to one instruction word", text.trim()));
+        }
+        None => {}
+    }
+    Ok(assembly.program.program[address as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debugger::Debugger;
+    use crate::cpu::Cpu;
+
+    const BLINK: &str = "\
+        LIST    P=12F629
+        __CONFIG 0x3FC4
+        RADIX   DEC
+
+        CBLOCK  0x20
+count, delay:2
+        ENDC
+
+        ORG     0
+start:  BANKSEL TRISIO
+        movlw   ~(1 << GP2) & 0xFF
+        movwf   TRISIO          ; GP2 output
+        BANKSEL GPIO
+loop    incf    count, f
+        movf    delay+1, W
+        btfss   STATUS, Z
+        goto    $-2
+        call    table
+        goto    loop
+table   addwf   PCL, F
+        dt      \"Hi\", H'0A', 'x'
+        dw      0x0064
+        END
+        nop                     ; after END, ignored
+";
+
+    #[test]
+    fn test_assemble() {
+        let assembly = assemble(BLINK, "blink.asm").unwrap();
+        let expected = [
+            0x1683, // BSF STATUS, RP0
+            0x30FB, // MOVLW 0xFB
+            0x0085, // MOVWF TRISIO
+            0x1283, // BCF STATUS, RP0
+            0x0AA0, // INCF 0x20, F
+            0x0822, // MOVF 0x22, W
+            0x1D03, // BTFSS STATUS, Z
+            0x2805, // GOTO $-2
+            0x200A, // CALL table
+            0x2804, // GOTO loop
+            0x0782, // ADDWF PCL, F
+            0x3448, 0x3469, 0x340A, 0x3478,
+            0x0064,
+        ];
+        assert_eq!(assembly.program.program, expected);
+        assert_eq!(assembly.program.config, Some(0x3FC4));
+        assert_eq!(assembly.program.report.words_loaded, expected.len());
+        assert_eq!(assembly.symbols.address_of("loop"), Some(4));
+        assert_eq!(assembly.symbols.location_of(2).map(|l| l.line), Some(12));
+    }
+
+    #[test]
+    fn test_errors() {
+        let source = "\
+        movlw   300
+        goto    nowhere
+        bsf     GPIO
+        frob    1
+dup     nop
+dup     nop
+";
+        let errors = assemble(source, "bad.asm").unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![1, 2, 3, 4, 6]);
+        assert_eq!(errors[1].to_string(), "Line 2: Undefined symbol 'nowhere'");
+    }
+
+    #[test]
+    fn test_assemble_instruction() {
+        let mut symbols = SymbolTable::new();
+        symbols.add_label("loop", 0x003);
+        assert_eq!(assemble_instruction("nop", 0x010, &symbols), Ok(0x0000));
+        assert_eq!(assemble_instruction("goto loop", 0x010, &symbols), Ok(0x2803));
+        assert_eq!(assemble_instruction("bcf GPIO, GP2", 0x000, &symbols), Ok(0x1105));
+        assert_eq!(assemble_instruction("movlw 0x2A ; answer", 0x3FF, &symbols), Ok(0x302A));
+        assert_eq!(assemble_instruction("goto nowhere", 0, &symbols), Err("Undefined symbol 'nowhere'".to_string()));
+        assert!(assemble_instruction("dw 1, 2", 0, &symbols).is_err());
+        assert!(assemble_instruction("", 0, &symbols).is_err());
+    }
+
+    #[test]
+    fn test_listing_round_trip() {
+        // A disassembly listing assembles back to the same program
+        let mut cpu = Cpu::new();
+        let program = assemble(BLINK, "blink.asm").unwrap().program.program;
+        cpu.memory_mut().load_program(&program);
+        let listing = Debugger::format_listing(&cpu, &SymbolTable::new());
+        assert_eq!(assemble(&listing, "listing.asm").unwrap().program.program, program);
+    }
+}
@@ -0,0 +1,205 @@
+//! Non-interactive batch runs
+//!
+//! `pic_simulator --load fw.hex --run-cycles 1000000 --dump 0x20..0x40`
+//! loads firmware, runs it and prints the requested memory without
+//! starting the REPL, so it can be used from scripts and CI. The outcome
+//! maps to the process exit code:
+//!
+//! - 0: ran the requested cycles
+//! - 1: the firmware failed to load or execution failed (e.g. an invalid
+//!   opcode)
+//! - 2: invalid command-line arguments
+//! - 3: stopped at a breakpoint (with `--exit-on-breakpoint`)
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::Debugger;
+use crate::error::SimError;
+use crate::simulator::{RunOutcome, Simulator};
+
+/// What a batch run does, in order
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    /// Firmware file to load (HEX, S-record, ELF, COFF or assembly)
+    pub load: Option<PathBuf>,
+    /// Instruction cycles to run
+    pub run_cycles: u64,
+    /// Breakpoint addresses (hex) or program labels
+    pub breakpoints: Vec<String>,
+    /// Stop at the first breakpoint hit instead of continuing
+    pub exit_on_breakpoint: bool,
+    /// Data memory printed after running
+    pub dump: Option<Range<u16>>,
+}
+
+/// How a batch run ended
+#[derive(Debug)]
+pub enum BatchOutcome {
+    /// All requested cycles ran
+    Completed,
+    /// Stopped at a breakpoint at this address
+    Breakpoint(u16),
+    /// Loading or execution failed
+    Error(SimError),
+}
+
+impl BatchOutcome {
+    /// Process exit code for this outcome
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            BatchOutcome::Completed => 0,
+            BatchOutcome::Error(_) => 1,
+            BatchOutcome::Breakpoint(_) => 3,
+        }
+    }
+}
+
+impl Batch {
+    /// Load, run and dump, printing progress to stdout
+    pub fn run(&self, simulator: &mut Simulator) -> BatchOutcome {
+        if let Some(path) = &self.load {
+            match simulator.load_firmware_file(path) {
+                Ok(report) => {
+                    println!("Loaded {} ({} words)", path.display(), report.words_loaded);
+                    for warning in &report.warnings {
+                        println!("Warning: {}", warning);
+                    }
+                }
+                Err(e) => return BatchOutcome::Error(e),
+            }
+        }
+
+        for breakpoint in &self.breakpoints {
+            match resolve_address(simulator, breakpoint) {
+                Some(address) => simulator.add_breakpoint(address),
+                None => return BatchOutcome::Error(SimError::UnknownAddress(breakpoint.to_string())),
+            }
+        }
+
+        let outcome = self.execute(simulator);
+        let stats = simulator.stats();
+        println!(
+            "Ran {} cycles ({} instructions), PC=0x{:04X}",
+            stats.cycles_elapsed,
+            stats.instructions_executed,
+            simulator.cpu().get_pc(),
+        );
+
+        if let Some(range) = &self.dump {
+            let start = range.start.min(0xFF) as u8;
+            let count = range.end.min(0x100).saturating_sub(range.start);
+            Debugger::dump_memory(simulator.cpu(), start, count);
+        }
+        outcome
+    }
+
+    /// Run the requested cycles, reporting breakpoints passed on the way
+    fn execute(&self, simulator: &mut Simulator) -> BatchOutcome {
+        let end = simulator.stats().cycles_elapsed + self.run_cycles;
+        loop {
+            let remaining = end.saturating_sub(simulator.stats().cycles_elapsed);
+            match simulator.run_until(|_| false, remaining) {
+                RunOutcome::Breakpoint(pc) if self.exit_on_breakpoint => {
+                    println!("Breakpoint at 0x{:04X}", pc);
+                    return BatchOutcome::Breakpoint(pc);
+                }
+                RunOutcome::Breakpoint(pc) => println!("Breakpoint at 0x{:04X} (continuing)", pc),
+                RunOutcome::Watchpoint(hit) => println!("Watchpoint: {} (continuing)", hit),
+                RunOutcome::Error(e) => return BatchOutcome::Error(e),
+                RunOutcome::CycleLimit | RunOutcome::PredicateMet => return BatchOutcome::Completed,
+            }
+        }
+    }
+}
+
+/// Parse a data memory range: `0x20..0x40` (end excluded), `0x20..=0x3F`
+/// or a single address
+pub fn parse_range(text: &str) -> Result<Range<u16>, String> {
+    let parse = |s: &str| {
+        let digits = s.trim().trim_start_matches("0x").trim_start_matches("0X");
+        u16::from_str_radix(digits, 16)
+            .ok()
+            .filter(|&address| address <= 0xFF)
+            .ok_or_else(|| format!("Invalid address: {}", s.trim()))
+    };
+    let range = if let Some((start, end)) = text.split_once("..=") {
+        parse(start)?..parse(end)? + 1
+    } else if let Some((start, end)) = text.split_once("..") {
+        parse(start)?..parse(end)?
+    } else {
+        let address = parse(text)?;
+        address..address + 1
+    };
+    if range.is_empty() {
+        return Err(format!("Empty range: {}", text));
+    }
+    Ok(range)
+}
+
+/// Program address of a label or hex number
+fn resolve_address(simulator: &Simulator, text: &str) -> Option<u16> {
+    simulator.symbols().address_of(text).or_else(|| {
+        let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+        u16::from_str_radix(digits, 16).ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("0x20..0x40"), Ok(0x20..0x40));
+        assert_eq!(parse_range("20..=3F"), Ok(0x20..0x40));
+        assert_eq!(parse_range("0x7F"), Ok(0x7F..0x80));
+        assert_eq!(parse_range("0x00..=0xFF"), Ok(0x00..0x100));
+        assert_eq!(parse_range("0xF0..=0xFF"), Ok(0xF0..0x100));
+        assert!(parse_range("0x40..0x20").is_err());
+        assert!(parse_range("0x20..0x100").is_err());
+        assert!(parse_range("GPIO").is_err());
+    }
+
+    #[test]
+    fn test_batch_run() {
+        let path = std::env::temp_dir().join(format!("pic_batch_test_{}.asm", std::process::id()));
+        std::fs::write(&path, "\
+        ORG     0
+start   movlw   0x42
+        movwf   0x20
+loop    incf    0x21, F
+        goto    loop
+        END
+").unwrap();
+
+        let mut batch = Batch {
+            load: Some(path.clone()),
+            run_cycles: 100,
+            breakpoints: vec!["loop".to_string()],
+            exit_on_breakpoint: true,
+            dump: Some(0x20..0x22),
+        };
+        let mut sim = Simulator::new();
+        let outcome = batch.run(&mut sim);
+        assert!(matches!(outcome, BatchOutcome::Breakpoint(0x0002)));
+        assert_eq!(outcome.exit_code(), 3);
+        assert_eq!(sim.cpu().read_register(0x20), 0x42);
+
+        // Without stopping, breakpoints are passed until the cycles run out
+        batch.exit_on_breakpoint = false;
+        let mut sim = Simulator::new();
+        let outcome = batch.run(&mut sim);
+        assert!(matches!(outcome, BatchOutcome::Completed));
+        assert!(sim.stats().cycles_elapsed >= 100);
+        assert!(sim.cpu().read_register(0x21) > 1);
+
+        // A dump reaching the last data memory address
+        batch.dump = Some(parse_range("0x20..=0xFF").unwrap());
+        assert!(matches!(batch.run(&mut Simulator::new()), BatchOutcome::Completed));
+
+        batch.load = Some(path.with_extension("missing"));
+        assert_eq!(batch.run(&mut Simulator::new()).exit_code(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
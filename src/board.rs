@@ -0,0 +1,333 @@
+//! Multi-MCU boards
+//!
+//! A `Board` holds several simulators whose GPIO pins are wired together
+//! through `Net`s, and advances them in lock-step by cycle count. After
+//! every scheduler step each net is resolved from the pins driving it and
+//! the resulting level is fed back to every connected pin as its external
+//! input. This is enough to simulate two PICs talking over a bit-banged
+//! link such as a single-wire or open-drain bus.
+
+use crate::gpio::PinState;
+use crate::simulator::Simulator;
+use crate::error::{BoardError, SimError};
+
+/// A pin on one of the board's MCUs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinRef {
+    pub mcu: usize,
+    pub pin: u8,
+}
+
+/// Pins wired together
+#[derive(Debug, Clone)]
+pub struct Net {
+    pub name: String,
+    pins: Vec<PinRef>,
+    /// Level of the net when nothing drives it (pull-up or pull-down)
+    idle: bool,
+    level: bool,
+}
+
+impl Net {
+    /// Create a net with a pull-up
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            pins: Vec::new(),
+            idle: true,
+            level: true,
+        }
+    }
+
+    /// Set the level of the undriven net
+    pub fn with_idle_level(mut self, level: bool) -> Self {
+        self.idle = level;
+        self.level = level;
+        self
+    }
+
+    /// Connect a pin to the net
+    pub fn connect(&mut self, mcu: usize, pin: u8) {
+        let pin_ref = PinRef { mcu, pin };
+        if !self.pins.contains(&pin_ref) {
+            self.pins.push(pin_ref);
+        }
+    }
+
+    /// Pins connected to the net
+    pub fn pins(&self) -> &[PinRef] {
+        &self.pins
+    }
+
+    /// Level after the last resolution
+    pub fn level(&self) -> bool {
+        self.level
+    }
+
+    /// Level of the undriven net
+    pub fn idle_level(&self) -> bool {
+        self.idle
+    }
+}
+
+/// Two or more pins driving a net to different levels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetConflict {
+    pub net: usize,
+    pub cycle: u64,
+    /// Pins driving high
+    pub high: Vec<PinRef>,
+    /// Pins driving low
+    pub low: Vec<PinRef>,
+}
+
+/// Several simulators with interconnected pins
+pub struct Board {
+    mcus: Vec<Simulator>,
+    nets: Vec<Net>,
+    conflicts: Vec<NetConflict>,
+    cycle: u64,
+    stop_on_conflict: bool,
+}
+
+impl Board {
+    pub fn new() -> Self {
+        Self {
+            mcus: Vec::new(),
+            nets: Vec::new(),
+            conflicts: Vec::new(),
+            cycle: 0,
+            stop_on_conflict: false,
+        }
+    }
+
+    /// Add a simulator to the board, returning its index
+    pub fn add_mcu(&mut self, sim: Simulator) -> usize {
+        self.mcus.push(sim);
+        self.mcus.len() - 1
+    }
+
+    /// Get an MCU by index
+    pub fn mcu(&self, index: usize) -> &Simulator {
+        &self.mcus[index]
+    }
+
+    /// Get mutable reference to an MCU by index
+    pub fn mcu_mut(&mut self, index: usize) -> &mut Simulator {
+        &mut self.mcus[index]
+    }
+
+    /// Number of MCUs on the board
+    pub fn mcu_count(&self) -> usize {
+        self.mcus.len()
+    }
+
+    /// Add a net, returning its index
+    /// Fails if a pin refers to an unknown MCU or a pin its device lacks
+    pub fn add_net(&mut self, net: Net) -> Result<usize, BoardError> {
+        for pin in &net.pins {
+            if pin.mcu >= self.mcus.len() {
+                return Err(BoardError::NoSuchMcu { net: net.name.clone(), mcu: pin.mcu });
+            }
+            let device = self.mcus[pin.mcu].device();
+            if !device.model().has_pin(pin.pin) {
+                return Err(BoardError::NoSuchPin { net: net.name.clone(), pin: pin.pin, device });
+            }
+        }
+        self.nets.push(net);
+        self.resolve_nets();
+        Ok(self.nets.len() - 1)
+    }
+
+    /// Wire `pins` together on a new pulled-up net
+    pub fn connect(&mut self, name: &str, pins: &[(usize, u8)]) -> Result<usize, BoardError> {
+        let mut net = Net::new(name);
+        for &(mcu, pin) in pins {
+            net.connect(mcu, pin);
+        }
+        self.add_net(net)
+    }
+
+    /// Get all nets
+    pub fn nets(&self) -> &[Net] {
+        &self.nets
+    }
+
+    /// Find a net by name
+    pub fn net(&self, name: &str) -> Option<&Net> {
+        self.nets.iter().find(|net| net.name == name)
+    }
+
+    /// Drive conflicts detected so far
+    pub fn conflicts(&self) -> &[NetConflict] {
+        &self.conflicts
+    }
+
+    /// Clear the recorded drive conflicts
+    pub fn clear_conflicts(&mut self) {
+        self.conflicts.clear();
+    }
+
+    /// Stop `run_cycles` at the first drive conflict
+    pub fn set_stop_on_conflict(&mut self, stop: bool) {
+        self.stop_on_conflict = stop;
+    }
+
+    /// Board time in cycles; every MCU has executed at least this far
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    /// Reset every MCU and the board time
+    pub fn reset(&mut self) {
+        for sim in &mut self.mcus {
+            sim.reset();
+        }
+        self.conflicts.clear();
+        self.cycle = 0;
+        self.resolve_nets();
+    }
+
+    /// Advance the board by one cycle
+    ///
+    /// Each MCU that is behind the new board time executes one instruction,
+    /// so MCUs never drift apart by more than one instruction. Nets are
+    /// resolved afterwards and their levels applied to the connected pins.
+    pub fn step(&mut self) -> Result<(), SimError> {
+        let target = self.cycle + 1;
+        for sim in &mut self.mcus {
+            if sim.stats().cycles_elapsed < target {
+                sim.step()?;
+            }
+        }
+        self.cycle = target;
+        self.resolve_nets();
+        Ok(())
+    }
+
+    /// Advance the board by `n` cycles, returning the number executed
+    /// Stops early at a drive conflict if `set_stop_on_conflict` is enabled.
+    pub fn run_cycles(&mut self, n: u64) -> Result<u64, SimError> {
+        let start = self.cycle;
+        while self.cycle - start < n {
+            let conflicts = self.conflicts.len();
+            self.step()?;
+            if self.stop_on_conflict && self.conflicts.len() > conflicts {
+                break;
+            }
+        }
+        Ok(self.cycle - start)
+    }
+
+    /// Compute each net's level and feed it to the connected pins
+    ///
+    /// Output pins drive the net; input pins leave it to the idle level.
+    /// When drivers disagree the conflict is recorded and low wins.
+    fn resolve_nets(&mut self) {
+        for (index, net) in self.nets.iter_mut().enumerate() {
+            let mut high = Vec::new();
+            let mut low = Vec::new();
+            for &pin_ref in &net.pins {
+                match self.mcus[pin_ref.mcu].cpu().gpio().get_pin_state(pin_ref.pin) {
+                    PinState::High => high.push(pin_ref),
+                    PinState::Low => low.push(pin_ref),
+                    PinState::HighZ => {}
+                }
+            }
+
+            net.level = match (high.is_empty(), low.is_empty()) {
+                (true, true) => net.idle,
+                (false, true) => true,
+                (true, false) => false,
+                (false, false) => {
+                    self.conflicts.push(NetConflict { net: index, cycle: self.cycle, high, low });
+                    false
+                }
+            };
+
+            for pin_ref in &net.pins {
+                self.mcus[pin_ref.mcu].cpu_mut().gpio_mut().set_external_pin(pin_ref.pin, net.level);
+            }
+        }
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mcu(program: &[u16]) -> Simulator {
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(program);
+        sim
+    }
+
+    #[test]
+    fn test_pin_link() {
+        let mut board = Board::new();
+        // Sender: GP0 output, toggled forever
+        let tx = board.add_mcu(mcu(&[
+            0x1683,  // 0x000: BSF STATUS, RP0
+            0x1005,  // 0x001: BCF TRISIO, 0
+            0x1283,  // 0x002: BCF STATUS, RP0
+            0x3001,  // 0x003: MOVLW 0x01
+            0x0685,  // 0x004: XORWF GPIO, F
+            0x2804,  // 0x005: GOTO 0x004
+        ]));
+        // Receiver: copy GP1 into 0x20
+        let rx = board.add_mcu(mcu(&[
+            0x0805,  // 0x000: MOVF GPIO, W
+            0x00A0,  // 0x001: MOVWF 0x20
+            0x2800,  // 0x002: GOTO 0x000
+        ]));
+        board.connect("link", &[(tx, 0), (rx, 1)]).unwrap();
+
+        // Undriven net idles high
+        assert!(board.net("link").unwrap().level());
+
+        // GP0 becomes a low output, then XORWF drives it high
+        board.run_cycles(4).unwrap();
+        assert!(!board.nets()[0].level());
+        assert!(!board.mcu(rx).cpu().gpio().get_external_pin(1));
+        board.run_cycles(1).unwrap();
+        assert!(board.mcu(rx).cpu().gpio().get_external_pin(1));
+
+        // MCUs stay in lock-step with the board
+        board.run_cycles(20).unwrap();
+        assert!(board.mcu(tx).stats().cycles_elapsed >= board.cycle());
+        assert!(board.mcu(rx).stats().cycles_elapsed >= board.cycle());
+        assert!(board.conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_drive_conflict() {
+        let mut board = Board::new();
+        // Both drive GP0: one high, one low
+        let a = board.add_mcu(mcu(&[0x1683, 0x1005, 0x1283, 0x1405, 0x2804]));
+        let b = board.add_mcu(mcu(&[0x1683, 0x1005, 0x1283, 0x1005, 0x2804]));
+        board.connect("bus", &[(a, 0), (b, 0)]).unwrap();
+        board.set_stop_on_conflict(true);
+
+        let ran = board.run_cycles(100).unwrap();
+        assert!(ran < 100);
+        let conflict = &board.conflicts()[0];
+        assert_eq!(conflict.high, vec![PinRef { mcu: a, pin: 0 }]);
+        assert_eq!(conflict.low, vec![PinRef { mcu: b, pin: 0 }]);
+        assert!(!board.nets()[0].level());
+    }
+
+    #[test]
+    fn test_invalid_net() {
+        let mut board = Board::new();
+        board.add_mcu(Simulator::new());
+        assert!(matches!(board.connect("bad", &[(0, 0), (1, 0)]), Err(BoardError::NoSuchMcu { mcu: 1, .. })));
+        assert!(matches!(board.connect("bad", &[(0, 6)]), Err(BoardError::NoSuchPin { pin: 6, .. })));
+    }
+}
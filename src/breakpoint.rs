@@ -0,0 +1,170 @@
+//! Program breakpoints
+//!
+//! A breakpoint stops execution before the instruction at its address runs.
+//! Breakpoints can be disabled without being deleted, count how often they
+//! stopped execution and can carry a condition (see `expr`), so "stop at
+//! `loop` once `[0x20] == 10`" needs no manual stepping.
+//!
+//! Breakpoint sets are saved as text, one breakpoint per line, located by
+//! label where there is one so the file still applies after the program is
+//! reassembled:
+//!
+//! ```text
+//! main_loop on
+//! 0x0010 off [0x20] == 3
+//! ```
+//!
+//! The GUI session saves its breakpoints in the same format, by address.
+
+use std::fmt;
+
+use crate::error::BreakpointError;
+use crate::expr::Expr;
+use crate::symbols::SymbolTable;
+
+/// A breakpoint on a program address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    pub enabled: bool,
+
+    /// Times this breakpoint stopped execution
+    pub hits: u64,
+
+    /// Stop only when this evaluates to non-zero
+    pub condition: Option<Expr>,
+}
+
+impl Breakpoint {
+    /// An enabled, unconditional breakpoint
+    pub fn new(address: u16) -> Self {
+        Self {
+            address,
+            enabled: true,
+            hits: 0,
+            condition: None,
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:04X}", self.address)?;
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition)?;
+        }
+        if !self.enabled {
+            write!(f, " (disabled)")?;
+        }
+        write!(f, ", {} hit{}", self.hits, if self.hits == 1 { "" } else { "s" })
+    }
+}
+
+/// A breakpoint as saved in a breakpoint file, before its location and
+/// condition are resolved against a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedBreakpoint {
+    /// Label, `file:line` or hex address
+    pub location: String,
+    pub enabled: bool,
+    /// Condition source text
+    pub condition: Option<String>,
+}
+
+impl SavedBreakpoint {
+    /// Save `bp` located by its address
+    pub fn new(bp: &Breakpoint) -> Self {
+        Self {
+            location: format!("0x{:04X}", bp.address),
+            enabled: bp.enabled,
+            condition: bp.condition.as_ref().map(|condition| condition.to_string()),
+        }
+    }
+
+    /// Save `bp` located by its label in `symbols`, if it has one
+    pub fn labelled(bp: &Breakpoint, symbols: &SymbolTable) -> Self {
+        let mut saved = Self::new(bp);
+        if let Some(label) = symbols.label_at(bp.address) {
+            saved.location = label.to_string();
+        }
+        saved
+    }
+
+    /// The address of a breakpoint located by hex address
+    pub fn address(&self) -> Option<u16> {
+        u16::from_str_radix(self.location.strip_prefix("0x")?, 16).ok()
+    }
+
+    /// Parse `main_loop on [0x20] == 3`; the state defaults to on
+    pub fn parse(text: &str) -> Result<Self, BreakpointError> {
+        let mut parts = text.trim().splitn(3, char::is_whitespace);
+        let location = parts.next().unwrap_or_default().to_string();
+        let enabled = match parts.next() {
+            Some("on") | None => true,
+            Some("off") => false,
+            Some(other) => return Err(BreakpointError::InvalidState(other.to_string())),
+        };
+        let condition = parts.next()
+            .map(str::trim)
+            .filter(|condition| !condition.is_empty())
+            .map(str::to_string);
+        Ok(Self { location, enabled, condition })
+    }
+}
+
+impl fmt::Display for SavedBreakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.location, if self.enabled { "on" } else { "off" })?;
+        if let Some(condition) = &self.condition {
+            write!(f, " {}", condition)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format breakpoints for a breakpoint file
+pub fn format_breakpoints(breakpoints: &[Breakpoint], symbols: &SymbolTable) -> String {
+    breakpoints.iter()
+        .map(|bp| format!("{}\n", SavedBreakpoint::labelled(bp, symbols)))
+        .collect()
+}
+
+/// Parse a breakpoint file; blank lines and `#` comments are skipped
+pub fn parse_breakpoints(text: &str) -> Result<Vec<SavedBreakpoint>, BreakpointError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim().starts_with('#'))
+        .map(|(index, line)| SavedBreakpoint::parse(line).map_err(|e| BreakpointError::Line {
+            line: index + 1,
+            source: Box::new(e),
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakpoint_file_round_trip() {
+        let mut symbols = SymbolTable::new();
+        symbols.add_label("main_loop", 0x0004);
+        let mut conditional = Breakpoint::new(0x0010);
+        conditional.enabled = false;
+        conditional.condition = Some(Expr::parse("[0x20] == 3", &symbols).unwrap());
+
+        let text = format_breakpoints(&[Breakpoint::new(0x0004), conditional], &symbols);
+        assert_eq!(text, "main_loop on\n0x0010 off [0x20] == 3\n");
+
+        let saved = parse_breakpoints(&format!("# saved\n{}\nmain.asm:12\n", text)).unwrap();
+        assert_eq!(saved, vec![
+            SavedBreakpoint { location: "main_loop".to_string(), enabled: true, condition: None },
+            SavedBreakpoint { location: "0x0010".to_string(), enabled: false, condition: Some("[0x20] == 3".to_string()) },
+            SavedBreakpoint { location: "main.asm:12".to_string(), enabled: true, condition: None },
+        ]);
+        assert_eq!(saved[0].address(), None);
+        assert_eq!(saved[1].address(), Some(0x0010));
+        let err = parse_breakpoints("main_loop maybe").unwrap_err();
+        assert_eq!(err.to_string(), "Line 1: expected 'on' or 'off', found 'maybe'");
+    }
+}
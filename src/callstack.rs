@@ -0,0 +1,169 @@
+//! Logical call stack tracking
+//!
+//! The hardware stack only holds 8 return addresses and carries no
+//! information about where a call came from. This module follows
+//! CALL/RETURN/RETLW/RETFIE and interrupt entry to maintain a logical
+//! call stack with call-site information and the cycle each frame
+//! was entered.
+
+/// Maximum number of logical frames kept (oldest frames are dropped)
+pub const MAX_CALL_DEPTH: usize = 64;
+
+/// How a frame was entered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    /// Entered with a CALL instruction
+    Call,
+    /// Entered by taking the interrupt vector
+    Interrupt,
+}
+
+/// A single logical call frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallFrame {
+    /// How the frame was entered
+    pub kind: FrameKind,
+
+    /// Address of the CALL instruction, or the PC that was interrupted
+    pub caller_pc: u16,
+
+    /// Entry address of the called routine
+    pub callee: u16,
+
+    /// Address execution resumes at when the frame returns
+    pub return_address: u16,
+
+    /// Cycle count when the frame was entered
+    pub entry_cycle: u64,
+}
+
+impl CallFrame {
+    /// Cycles spent in this frame so far (including nested calls)
+    pub fn cycles_spent(&self, current_cycle: u64) -> u64 {
+        current_cycle.saturating_sub(self.entry_cycle)
+    }
+}
+
+/// Logical call stack
+#[derive(Debug, Clone, Default)]
+pub struct CallStack {
+    frames: Vec<CallFrame>,
+}
+
+impl CallStack {
+    pub fn new() -> Self {
+        Self { frames: Vec::new() }
+    }
+
+    /// Record a CALL instruction at `caller_pc` to `callee`
+    pub fn on_call(&mut self, caller_pc: u16, callee: u16, cycle: u64) {
+        self.push(CallFrame {
+            kind: FrameKind::Call,
+            caller_pc,
+            callee,
+            return_address: caller_pc.wrapping_add(1) & 0x1FFF,
+            entry_cycle: cycle,
+        });
+    }
+
+    /// Record interrupt entry while executing at `interrupted_pc`
+    pub fn on_interrupt(&mut self, interrupted_pc: u16, vector: u16, cycle: u64) {
+        self.push(CallFrame {
+            kind: FrameKind::Interrupt,
+            caller_pc: interrupted_pc,
+            callee: vector,
+            return_address: interrupted_pc,
+            entry_cycle: cycle,
+        });
+    }
+
+    /// Record a RETURN or RETLW instruction
+    pub fn on_return(&mut self) -> Option<CallFrame> {
+        self.frames.pop()
+    }
+
+    /// Record a RETFIE instruction
+    /// Unwinds to (and including) the innermost interrupt frame
+    pub fn on_retfie(&mut self) -> Option<CallFrame> {
+        match self.frames.iter().rposition(|f| f.kind == FrameKind::Interrupt) {
+            Some(index) => {
+                let frame = self.frames[index];
+                self.frames.truncate(index);
+                Some(frame)
+            }
+            None => self.frames.pop(),
+        }
+    }
+
+    fn push(&mut self, frame: CallFrame) {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            self.frames.remove(0);
+        }
+        self.frames.push(frame);
+    }
+
+    /// Frames from outermost to innermost
+    pub fn frames(&self) -> &[CallFrame] {
+        &self.frames
+    }
+
+    /// Current logical call depth
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Innermost frame
+    pub fn current(&self) -> Option<&CallFrame> {
+        self.frames.last()
+    }
+
+    /// Remove all frames (on reset)
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_and_return() {
+        let mut stack = CallStack::new();
+
+        stack.on_call(0x006, 0x00B, 10);
+        stack.on_call(0x00C, 0x020, 15);
+        assert_eq!(stack.depth(), 2);
+        assert_eq!(stack.current().unwrap().return_address, 0x00D);
+
+        let frame = stack.on_return().unwrap();
+        assert_eq!(frame.callee, 0x020);
+        assert_eq!(frame.cycles_spent(25), 10);
+        assert_eq!(stack.depth(), 1);
+    }
+
+    #[test]
+    fn test_retfie_unwinds_to_interrupt_frame() {
+        let mut stack = CallStack::new();
+
+        stack.on_call(0x010, 0x030, 0);
+        stack.on_interrupt(0x031, 0x004, 5);
+        stack.on_call(0x005, 0x040, 7);
+
+        let frame = stack.on_retfie().unwrap();
+        assert_eq!(frame.kind, FrameKind::Interrupt);
+        assert_eq!(frame.return_address, 0x031);
+        assert_eq!(stack.depth(), 1);
+    }
+
+    #[test]
+    fn test_depth_is_bounded() {
+        let mut stack = CallStack::new();
+        for i in 0..(MAX_CALL_DEPTH as u16 + 10) {
+            stack.on_call(i, 0x100, 0);
+        }
+
+        assert_eq!(stack.depth(), MAX_CALL_DEPTH);
+        assert_eq!(stack.frames()[0].caller_pc, 10);
+    }
+}
@@ -0,0 +1,282 @@
+//! PIC12F683 Timer2 and CCP (PWM) module
+//!
+//! Reference: PIC12F683 Data Sheet (DS41211D)
+//! - Section 7.0 - Timer2 Module (Page 55-56)
+//! - Section 11.3 - PWM Mode (Page 81-85)
+//!
+//! Timer2 counts instruction cycles through a 1:1, 1:4 or 1:16 prescaler
+//! up to PR2 and then resets; each reset advances a 1:1-1:16 postscaler
+//! that sets TMR2IF. In PWM mode (CCP1M = 11xx) the CCP drives GP2/CCP1
+//! high at the start of each period and low once the 10-bit duty cycle
+//! (CCPR1L:DC1B, in oscillator periods times the prescale) has elapsed.
+//! The duty cycle is latched into CCPR1H at the start of every period.
+//! Output edges fall on instruction-cycle boundaries. Capture and compare
+//! modes are not modelled.
+
+use crate::cpu::registers;
+use crate::error::StateError;
+use crate::state::StateRecord;
+
+/// Timer2 and the CCP module
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ccp {
+    tmr2: u8,
+    pr2: u8,
+    t2con: u8,
+    /// Cycles counted towards the next TMR2 increment
+    prescaler: u16,
+    /// Periods counted towards the next TMR2IF
+    postscaler: u8,
+    ccpr1l: u8,
+    ccpr1h: u8,
+    ccp1con: u8,
+    /// Duty cycle latched for the current period (10-bit)
+    duty: u16,
+    /// Level driven on GP2 in PWM mode
+    output: bool,
+}
+
+impl Ccp {
+    pub fn new() -> Self {
+        Self {
+            pr2: 0xFF,
+            ..Self::default()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// TMR2 increments per instruction cycle divisor (T2CKPS<1:0>)
+    fn prescale(&self) -> u16 {
+        match self.t2con & 0x03 {
+            0 => 1,
+            1 => 4,
+            _ => 16,
+        }
+    }
+
+    /// Periods per TMR2IF (TOUTPS<3:0> + 1)
+    fn postscale(&self) -> u8 {
+        ((self.t2con >> 3) & 0x0F) + 1
+    }
+
+    /// Check if Timer2 is running (TMR2ON)
+    pub fn is_running(&self) -> bool {
+        self.t2con & 0x04 != 0
+    }
+
+    /// Check if the CCP is in PWM mode
+    pub fn is_pwm(&self) -> bool {
+        self.ccp1con & 0x0C == 0x0C
+    }
+
+    /// Level driven on GP2, or None if the CCP doesn't own the pin
+    pub fn output(&self) -> Option<bool> {
+        self.is_pwm().then_some(self.output)
+    }
+
+    /// PWM period in instruction cycles
+    pub fn period_cycles(&self) -> u64 {
+        (self.pr2 as u64 + 1) * self.prescale() as u64
+    }
+
+    /// Latched duty cycle as a fraction of the period
+    pub fn duty_ratio(&self) -> f64 {
+        let period_tosc = self.period_cycles() * 4;
+        (self.duty as u64 * self.prescale() as u64).min(period_tosc) as f64 / period_tosc as f64
+    }
+
+    /// Read TMR2, T2CON, PR2, CCPR1L, CCPR1H or CCP1CON
+    pub fn read(&self, address: u8) -> u8 {
+        match address {
+            registers::TMR2 => self.tmr2,
+            registers::T2CON => self.t2con,
+            registers::PR2 => self.pr2,
+            registers::CCPR1L => self.ccpr1l,
+            registers::CCPR1H => self.ccpr1h,
+            registers::CCP1CON => self.ccp1con,
+            _ => 0,
+        }
+    }
+
+    /// Write one of the module's registers
+    ///
+    /// Writing TMR2 or T2CON clears the prescaler and postscaler counts.
+    /// CCPR1H is read-only in PWM mode.
+    pub fn write(&mut self, address: u8, value: u8) {
+        match address {
+            registers::TMR2 => {
+                self.tmr2 = value;
+                self.prescaler = 0;
+                self.postscaler = 0;
+            }
+            registers::T2CON => {
+                self.t2con = value & 0x7F;
+                self.prescaler = 0;
+                self.postscaler = 0;
+            }
+            registers::PR2 => self.pr2 = value,
+            registers::CCPR1L => self.ccpr1l = value,
+            registers::CCPR1H if !self.is_pwm() => self.ccpr1h = value,
+            registers::CCP1CON => {
+                self.ccp1con = value & 0x3F;
+                if !self.is_pwm() {
+                    self.output = false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Position in the current period, in oscillator periods
+    fn position_tosc(&self) -> u64 {
+        (self.tmr2 as u64 * self.prescale() as u64 + self.prescaler as u64) * 4
+    }
+
+    /// Oscillator periods the output stays high after the period starts
+    fn high_tosc(&self) -> u64 {
+        self.duty as u64 * self.prescale() as u64
+    }
+
+    /// Start a new PWM period: latch the duty cycle and drive the pin high
+    fn start_period(&mut self) {
+        self.ccpr1h = self.ccpr1l;
+        self.duty = ((self.ccpr1l as u16) << 2) | ((self.ccp1con as u16 >> 4) & 0x03);
+        self.output = self.is_pwm() && self.duty > 0;
+    }
+
+    /// Tick once per instruction cycle
+    /// Returns (TMR2IF raised, period restarted or output changed)
+    pub fn tick(&mut self) -> (bool, bool) {
+        if !self.is_running() {
+            return (false, false);
+        }
+
+        let mut flag = false;
+        let mut event = false;
+        self.prescaler += 1;
+        if self.prescaler >= self.prescale() {
+            self.prescaler = 0;
+            if self.tmr2 == self.pr2 {
+                self.tmr2 = 0;
+                self.postscaler += 1;
+                if self.postscaler >= self.postscale() {
+                    self.postscaler = 0;
+                    flag = true;
+                }
+                self.start_period();
+                event = true;
+            } else {
+                self.tmr2 = self.tmr2.wrapping_add(1);
+            }
+        }
+
+        if self.output && self.position_tosc() >= self.high_tosc() {
+            self.output = false;
+            event = true;
+        }
+        (flag, event)
+    }
+
+    /// Number of ticks until the next one that restarts the period or
+    /// changes the output, or None if Timer2 is stopped
+    pub fn cycles_until_event(&self) -> Option<u64> {
+        if !self.is_running() {
+            return None;
+        }
+
+        let rate = self.prescale() as u64;
+        let increments = self.pr2.wrapping_sub(self.tmr2) as u64 + 1;
+        let restart = (increments - 1) * rate + (rate - self.prescaler as u64);
+        if !self.output {
+            return Some(restart);
+        }
+        let elapsed = self.position_tosc() / 4;
+        let fall = self.high_tosc().div_ceil(4).saturating_sub(elapsed).max(1);
+        Some(restart.min(fall))
+    }
+
+    /// Apply `cycles` ticks at once; must be fewer than `cycles_until_event`
+    pub fn advance(&mut self, cycles: u64) {
+        if !self.is_running() {
+            return;
+        }
+        let rate = self.prescale() as u64;
+        let total = self.prescaler as u64 + cycles;
+        self.tmr2 = self.tmr2.wrapping_add((total / rate) as u8);
+        self.prescaler = (total % rate) as u16;
+    }
+
+    /// Save Timer2 and CCP registers and counters
+    pub fn save_state(&self, record: &mut StateRecord) {
+        record.set("ccp.tmr2", self.tmr2);
+        record.set("ccp.pr2", self.pr2);
+        record.set("ccp.t2con", self.t2con);
+        record.set("ccp.prescaler", self.prescaler);
+        record.set("ccp.postscaler", self.postscaler);
+        record.set("ccp.ccpr1l", self.ccpr1l);
+        record.set("ccp.ccpr1h", self.ccpr1h);
+        record.set("ccp.ccp1con", self.ccp1con);
+        record.set("ccp.duty", self.duty);
+        record.set("ccp.output", self.output);
+    }
+
+    /// Restore state saved with `save_state`
+    pub fn load_state(&mut self, record: &StateRecord) -> Result<(), StateError> {
+        self.tmr2 = record.get("ccp.tmr2")?;
+        self.pr2 = record.get("ccp.pr2")?;
+        self.t2con = record.get("ccp.t2con")?;
+        self.prescaler = record.get("ccp.prescaler")?;
+        self.postscaler = record.get("ccp.postscaler")?;
+        self.ccpr1l = record.get("ccp.ccpr1l")?;
+        self.ccpr1h = record.get("ccp.ccpr1h")?;
+        self.ccp1con = record.get("ccp.ccp1con")?;
+        self.duty = record.get("ccp.duty")?;
+        self.output = record.get("ccp.output")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pwm_waveform() {
+        let mut ccp = Ccp::new();
+        // Period (PR2 + 1) * 4 = 40 cycles at 1:4; duty 10 * 4 Tosc = 10 cycles
+        ccp.write(registers::PR2, 9);
+        ccp.write(registers::CCPR1L, 2);
+        ccp.write(registers::CCP1CON, 0x2C);
+        ccp.write(registers::T2CON, 0x05);
+        assert_eq!(ccp.period_cycles(), 40);
+
+        // Record the output over a few periods, in bulk and cycle by cycle
+        let mut stepped = ccp.clone();
+        let mut levels = Vec::new();
+        let mut flags = 0;
+        for _ in 0..200 {
+            let (flag, _) = stepped.tick();
+            flags += flag as u32;
+            levels.push(stepped.output().unwrap());
+        }
+        let high = levels[40..200].iter().filter(|&&level| level).count();
+        assert_eq!(high, 4 * 10);
+        assert_eq!(flags, 5);
+        assert_eq!(stepped.duty_ratio(), 0.25);
+
+        let mut bulk = ccp;
+        let mut cycle = 0;
+        while cycle < 200 {
+            let ticks = bulk.cycles_until_event().unwrap().min(200 - cycle);
+            bulk.advance(ticks - 1);
+            bulk.tick();
+            cycle += ticks;
+            assert_eq!(bulk.output().unwrap(), levels[cycle as usize - 1]);
+        }
+        assert_eq!(bulk.read(registers::TMR2), stepped.read(registers::TMR2));
+    }
+}
@@ -0,0 +1,408 @@
+//! Circuit description files
+//!
+//! A circuit lists the external components around the MCU and the pins
+//! they connect to. Files use a TOML subset: one `[[component]]` table per
+//! part, each with a `type`, an optional `name` and type-specific
+//! parameters. Values are strings, integers (decimal or 0x hex), floats,
+//! booleans, or arrays of these.
+//!
+//! ```text
+//! # Blinky board
+//! [[component]]
+//! type = "led"
+//! name = "D1"
+//! pin = "GP0"
+//!
+//! [[component]]
+//! type = "seven_segment"
+//! bcd = ["GP0", "GP1", "GP2", "GP4"]   # or segments = [a, b, ... g]
+//! latch = "GP5"
+//!
+//! [[component]]
+//! type = "button"
+//! pin = "GP3"
+//! bounce_cycles = 2000     # chatter after each press and release
+//! bounce_transitions = 6
+//!
+//! [[component]]
+//! type = "potentiometer"
+//! pin = "GP0"
+//! position = 0.25          # wiper at 1.25 V
+//!
+//! [[component]]
+//! type = "uart"
+//! rx = "GP0"               # firmware transmits here
+//! tx = "GP1"               # firmware receives here
+//! baud = 9600
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::error::CircuitError;
+use crate::gpio;
+use crate::component::{Button, Component, Led, Potentiometer, SegmentDrive, SevenSegment};
+use crate::uart::SoftUart;
+
+/// A parameter value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "\"{}\"", s),
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Array(values) => {
+                let items: Vec<String> = values.iter().map(Value::to_string).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+/// One component entry of a circuit
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentSpec {
+    pub kind: String,
+    pub name: String,
+    pub params: BTreeMap<String, Value>,
+    /// Line of the `[[component]]` header, for error messages
+    pub line: usize,
+}
+
+impl ComponentSpec {
+    fn error(&self, error: CircuitError) -> CircuitError {
+        CircuitError::Component {
+            line: self.line,
+            kind: self.kind.clone(),
+            name: self.name.clone(),
+            source: Box::new(error),
+        }
+    }
+
+    fn wrong_type(&self, key: &str, expected: &'static str) -> CircuitError {
+        self.error(CircuitError::WrongType { key: key.to_string(), expected })
+    }
+
+    fn param(&self, key: &str) -> Result<&Value, CircuitError> {
+        self.params.get(key)
+            .ok_or_else(|| self.error(CircuitError::MissingParam(key.to_string())))
+    }
+
+    /// A pin given as `"GP2"` or `2`
+    pub fn pin(&self, key: &str) -> Result<u8, CircuitError> {
+        parse_pin(self.param(key)?).map_err(|e| self.error(e))
+    }
+
+    /// A list of pins
+    pub fn pins(&self, key: &str) -> Result<Vec<u8>, CircuitError> {
+        match self.param(key)? {
+            Value::Array(values) => values.iter()
+                .map(|value| parse_pin(value).map_err(|e| self.error(e)))
+                .collect(),
+            _ => Err(self.wrong_type(key, "an array of pins")),
+        }
+    }
+
+    /// An integer parameter, or `default` if absent
+    pub fn integer(&self, key: &str, default: i64) -> Result<i64, CircuitError> {
+        match self.params.get(key) {
+            None => Ok(default),
+            Some(Value::Integer(n)) => Ok(*n),
+            Some(_) => Err(self.wrong_type(key, "an integer")),
+        }
+    }
+
+    /// A numeric parameter, or `default` if absent
+    pub fn float(&self, key: &str, default: f64) -> Result<f64, CircuitError> {
+        match self.params.get(key) {
+            None => Ok(default),
+            Some(Value::Float(x)) => Ok(*x),
+            Some(Value::Integer(n)) => Ok(*n as f64),
+            Some(_) => Err(self.wrong_type(key, "a number")),
+        }
+    }
+
+    /// A boolean parameter, or `default` if absent
+    pub fn flag(&self, key: &str, default: bool) -> Result<bool, CircuitError> {
+        match self.params.get(key) {
+            None => Ok(default),
+            Some(Value::Bool(b)) => Ok(*b),
+            Some(_) => Err(self.wrong_type(key, "true or false")),
+        }
+    }
+
+    /// A string parameter, or `default` if absent
+    pub fn string<'a>(&'a self, key: &str, default: &'a str) -> Result<&'a str, CircuitError> {
+        match self.params.get(key) {
+            None => Ok(default),
+            Some(Value::String(s)) => Ok(s),
+            Some(_) => Err(self.wrong_type(key, "a string")),
+        }
+    }
+
+    /// Create the component described by this entry
+    pub fn build(&self) -> Result<Box<dyn Component>, CircuitError> {
+        match self.kind.as_str() {
+            "led" => Ok(Box::new(Led::new(&self.name, self.pin("pin")?))),
+            "button" => {
+                let cycles = self.integer("bounce_cycles", 0)?;
+                let transitions = self.integer("bounce_transitions", 4)?;
+                let (Ok(cycles), Ok(transitions)) = (u64::try_from(cycles), u32::try_from(transitions)) else {
+                    return Err(self.error(CircuitError::NegativeBounce));
+                };
+                Ok(Box::new(Button::new(&self.name, self.pin("pin")?).with_bounce(cycles, transitions)))
+            }
+            "potentiometer" | "pot" => {
+                let mut pot = Potentiometer::new(&self.name, self.pin("pin")?);
+                pot.set_position(self.float("position", 0.5)? as f32);
+                Ok(Box::new(pot))
+            }
+            "uart" => {
+                let baud = self.integer("baud", 9600)?;
+                let baud = u32::try_from(baud).ok().filter(|&baud| baud > 0)
+                    .ok_or_else(|| self.error(CircuitError::InvalidBaud(baud)))?;
+                let mut uart = SoftUart::new(&self.name, baud);
+                if self.params.contains_key("rx") {
+                    uart = uart.with_rx(self.pin("rx")?);
+                }
+                if self.params.contains_key("tx") {
+                    uart = uart.with_tx(self.pin("tx")?);
+                }
+                Ok(Box::new(uart))
+            }
+            "seven_segment" | "7seg" => {
+                let drive = if self.params.contains_key("bcd") {
+                    let pins = self.pins("bcd")?;
+                    let pins = pins.try_into()
+                        .map_err(|_| self.error(CircuitError::PinCount { key: "bcd", expected: "4" }))?;
+                    SegmentDrive::Bcd(pins)
+                } else {
+                    let pins = self.pins("segments")?;
+                    if pins.is_empty() || pins.len() > 7 {
+                        return Err(self.error(CircuitError::PinCount { key: "segments", expected: "1 to 7" }));
+                    }
+                    SegmentDrive::Direct(pins)
+                };
+                let mut display = SevenSegment::new(&self.name, drive)
+                    .with_active_low(self.flag("common_anode", false)?);
+                if self.params.contains_key("latch") {
+                    display = display.with_latch(self.pin("latch")?);
+                }
+                Ok(Box::new(display))
+            }
+            _ => Err(CircuitError::Line {
+                line: self.line,
+                source: Box::new(CircuitError::UnknownType(self.kind.clone())),
+            }),
+        }
+    }
+}
+
+/// Components and their pin connections
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Circuit {
+    pub components: Vec<ComponentSpec>,
+}
+
+impl Circuit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a circuit description
+    pub fn parse(text: &str) -> Result<Self, CircuitError> {
+        let mut circuit = Self::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let at = |error| CircuitError::Line { line: line_number, source: Box::new(error) };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if line == "[[component]]" {
+                circuit.components.push(ComponentSpec {
+                    kind: String::new(),
+                    name: String::new(),
+                    params: BTreeMap::new(),
+                    line: line_number,
+                });
+                continue;
+            }
+            if line.starts_with('[') {
+                return Err(at(CircuitError::UnknownTable(line.to_string())));
+            }
+
+            let spec = circuit.components.last_mut()
+                .ok_or_else(|| at(CircuitError::MissingComponent))?;
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| at(CircuitError::Syntax))?;
+            let key = key.trim();
+            let value = parse_value(value.trim())
+                .map_err(at)?;
+
+            match (key, value) {
+                ("type", Value::String(kind)) => spec.kind = kind.to_ascii_lowercase(),
+                ("name", Value::String(name)) => spec.name = name,
+                ("type" | "name", _) => {
+                    return Err(at(CircuitError::WrongType { key: key.to_string(), expected: "a string" }));
+                }
+                (key, value) => {
+                    spec.params.insert(key.to_string(), value);
+                }
+            }
+        }
+
+        for (index, spec) in circuit.components.iter_mut().enumerate() {
+            if spec.kind.is_empty() {
+                return Err(CircuitError::Line { line: spec.line, source: Box::new(CircuitError::MissingType) });
+            }
+            if spec.name.is_empty() {
+                spec.name = format!("{}{}", spec.kind, index + 1);
+            }
+        }
+
+        Ok(circuit)
+    }
+
+    /// Load a circuit description from a file
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self, CircuitError> {
+        let content = fs::read_to_string(path)
+            .map_err(CircuitError::Read)?;
+        Self::parse(&content)
+    }
+
+    /// Create all components, failing on the first invalid entry
+    pub fn build(&self) -> Result<Vec<Box<dyn Component>>, CircuitError> {
+        self.components.iter().map(ComponentSpec::build).collect()
+    }
+}
+
+/// Remove a `#` comment that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(s: &str) -> Result<Value, CircuitError> {
+    if let Some(inner) = s.strip_prefix('"') {
+        return inner.strip_suffix('"')
+            .filter(|text| !text.contains('"'))
+            .map(|text| Value::String(text.to_string()))
+            .ok_or_else(|| CircuitError::InvalidString(s.to_string()));
+    }
+    if let Some(inner) = s.strip_prefix('[') {
+        let inner = inner.strip_suffix(']')
+            .ok_or_else(|| CircuitError::UnterminatedArray(s.to_string()))?;
+        return inner.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(parse_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array);
+    }
+    match s {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+
+    let number = s.replace('_', "");
+    let (negative, digits) = match number.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, number.as_str()),
+    };
+    let integer = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => digits.parse::<i64>().ok(),
+    };
+    if let Some(n) = integer {
+        return Ok(Value::Integer(if negative { -n } else { n }));
+    }
+    number.parse::<f64>()
+        .map(Value::Float)
+        .map_err(|_| CircuitError::InvalidValue(s.to_string()))
+}
+
+/// Parse a pin given as `"GP2"`, `"RB0"`, `"2"` or `2`
+fn parse_pin(value: &Value) -> Result<u8, CircuitError> {
+    let pin = match value {
+        Value::Integer(n) => u8::try_from(*n).ok(),
+        Value::String(s) => gpio::parse_pin(s),
+        _ => None,
+    };
+    pin.ok_or_else(|| CircuitError::InvalidPin(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_circuit() {
+        let circuit = Circuit::parse(
+            "# Test board\n\
+             [[component]]\n\
+             type = \"LED\"\n\
+             name = \"D1 # status\"  # comment\n\
+             pin = \"GP0\"\n\
+             \n\
+             [[component]]\n\
+             type = \"led\"\n\
+             pin = 0x02\n\
+             pins = [1, \"GP4\"]\n\
+             level = -1.5\n",
+        ).unwrap();
+
+        assert_eq!(circuit.components.len(), 2);
+        let d1 = &circuit.components[0];
+        assert_eq!(d1.kind, "led");
+        assert_eq!(d1.name, "D1 # status");
+        assert_eq!(d1.pin("pin").unwrap(), 0);
+
+        let led2 = &circuit.components[1];
+        assert_eq!(led2.name, "led2");
+        assert_eq!(led2.line, 7);
+        assert_eq!(led2.pin("pin").unwrap(), 2);
+        assert_eq!(led2.pins("pins").unwrap(), vec![1, 4]);
+        assert_eq!(led2.float("level", 0.0).unwrap(), -1.5);
+        assert_eq!(led2.integer("missing", 7).unwrap(), 7);
+
+        let components = circuit.build().unwrap();
+        assert_eq!(components[1].pins(), vec![2]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(Circuit::parse("pin = 1\n"),
+            Err(CircuitError::Line { line: 1, source }) if matches!(*source, CircuitError::MissingComponent)));
+        assert!(Circuit::parse("[[component]]\npin = 1\n").is_err());
+        assert!(Circuit::parse("[[component]]\ntype = \"led\"\npin x\n").is_err());
+
+        let circuit = Circuit::parse("[[component]]\ntype = \"led\"\npin = \"GP8\"\n").unwrap();
+        assert!(circuit.build().is_err());
+        let circuit = Circuit::parse("[[component]]\ntype = \"relay\"\n").unwrap();
+        let err = circuit.components[0].build().err().unwrap();
+        assert!(matches!(err, CircuitError::Line { source, .. } if matches!(*source, CircuitError::UnknownType(_))));
+    }
+}
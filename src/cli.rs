@@ -1,17 +1,65 @@
-/// Interactive command-line interface for the simulator
+//! Interactive command-line interface for the simulator
 
+use std::cell::{Cell, RefCell};
+use std::fmt;
 use std::io::{self, Write};
-use crate::{Simulator, Debugger};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+use crate::{Simulator, Debugger, RunOutcome, Core, ResetReason};
+use crate::assembler;
+use crate::breakpoint;
+use crate::stimulus::{Stimulus, StimulusRule};
+use crate::cpu::registers;
+use crate::golden::GoldenTrace;
+use crate::gpio;
+use crate::halt::HaltPolicy;
+use crate::watchpoint::{self, Watchpoint, WatchKind};
+use crate::wdt::{WdtDistribution, WdtJitter};
+use crate::device::Device;
+use crate::events::{ObserverId, SimEvent};
+use crate::tracefile::TraceFile;
+use crate::macros::{Expansion, Macros};
+use crate::error::{AsmError, BreakpointError, SimError};
+use crate::debugger::json_string;
+use crate::hexloader::LoadReport;
+use crate::expr::Expr;
+
+/// Nesting limit for `source` and macros, so a script sourcing itself or
+/// a macro running itself stops
+const MAX_SCRIPT_DEPTH: usize = 8;
+
+/// Cycle limit for `until` without `max`, so a condition that never
+/// holds cannot hang a script
+const DEFAULT_UNTIL_CYCLES: u64 = 10_000_000;
 
 pub struct Cli {
     simulator: Simulator,
+    /// Set when the current command reports an error
+    failed: Cell<bool>,
+    /// Set by `quit`; ends the REPL and any running script
+    quit: bool,
+    /// Scripts currently being sourced
+    script_depth: usize,
+    /// Expressions printed whenever execution stops (`watch add`)
+    displays: Vec<Expr>,
+    /// Instruction trace being streamed to a file and its event observer
+    trace_file: Option<(ObserverId, Rc<RefCell<TraceFile>>)>,
+    /// State queries (`reg`, `dump`, `info`, `gpio`) print JSON
+    json: bool,
+    /// `step` lists the registers each instruction changed
+    step_info_full: bool,
+    /// Observer printing pin transitions (`gpio log on`)
+    gpio_log: Option<ObserverId>,
+    /// Aliases and macros (`alias`, `define`)
+    macros: Macros,
+    /// File the aliases and macros are saved to when they change
+    macros_path: Option<PathBuf>,
 }
 
 impl Cli {
     pub fn new() -> Self {
-        Self {
-            simulator: Simulator::new(),
-        }
+        Self { simulator: Simulator::new(), failed: Cell::new(false), quit: false, script_depth: 0, displays: Vec::new(), trace_file: None, json: false, step_info_full: false, gpio_log: None, macros: Macros::new(), macros_path: None }
     }
     
     /// Main REPL loop
@@ -20,14 +68,82 @@ impl Cli {
         println!("Type 'help' for available commands\n");
         
         self.simulator.reset();
+        if let Some(path) = macros_path() {
+            self.use_macros_file(path);
+        }
+        
+        #[cfg(feature = "readline")]
+        self.run_readline();
+        #[cfg(not(feature = "readline"))]
+        self.run_stdin();
+        
+        println!("Goodbye!");
+    }
+    
+    /// Prompt with line editing, history search and tab completion; the
+    /// history is kept between sessions in `~/.pic_simulator_history`
+    #[cfg(feature = "readline")]
+    fn run_readline(&mut self) {
+        use rustyline::error::ReadlineError;
+        use crate::completion::ReplHelper;
+        
+        let mut editor = match rustyline::Editor::<ReplHelper, rustyline::history::DefaultHistory>::new() {
+            Ok(editor) => editor,
+            Err(e) => {
+                println!("Line editing unavailable ({}), reading plain input", e);
+                return self.run_stdin();
+            }
+        };
+        editor.set_helper(Some(ReplHelper::new()));
+        let history = history_path();
+        if let Some(path) = &history {
+            let _ = editor.load_history(path);
+        }
         
+        loop {
+            if let Some(helper) = editor.helper_mut() {
+                helper.completions.refresh(&self.simulator);
+                helper.completions.set_user_commands(self.macros.names());
+            }
+            
+            let input = match editor.readline("pic> ") {
+                Ok(input) => input,
+                // Ctrl+C clears the line, Ctrl+D quits
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    break;
+                }
+            };
+            
+            let input = input.trim();
+            if input.is_empty() {
+                continue;
+            }
+            let _ = editor.add_history_entry(input);
+            
+            self.handle_command(input);
+            if self.quit {
+                break;
+            }
+        }
+        
+        if let Some(path) = &history {
+            let _ = editor.save_history(path);
+        }
+    }
+    
+    /// Prompt reading plain lines from stdin
+    fn run_stdin(&mut self) {
         loop {
             print!("pic> ");
             io::stdout().flush().unwrap();
             
             let mut input = String::new();
-            if io::stdin().read_line(&mut input).is_err() {
-                break;
+            match io::stdin().read_line(&mut input) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
             }
             
             let input = input.trim();
@@ -35,67 +151,369 @@ impl Cli {
                 continue;
             }
             
-            if input == "quit" || input == "exit" {
+            self.handle_command(input);
+            if self.quit {
                 break;
             }
-            
-            self.handle_command(input);
         }
+    }
+    
+    /// Run the commands in a script file, one per line; blank lines and
+    /// lines starting with `#` are skipped
+    /// 
+    /// With `echo`, each command is printed before it runs. With
+    /// `stop_on_error`, the first failing command ends the script. Returns
+    /// false if any command failed or the file could not be read.
+    pub fn source(&mut self, path: &Path, echo: bool, stop_on_error: bool) -> bool {
+        if self.script_depth >= MAX_SCRIPT_DEPTH {
+            self.fail(format_args!("Error: scripts nested more than {} deep", MAX_SCRIPT_DEPTH));
+            return false;
+        }
+        let script = match std::fs::read_to_string(path) {
+            Ok(script) => script,
+            Err(e) => {
+                self.fail(format_args!("Error: Failed to read {}: {}", path.display(), e));
+                return false;
+            }
+        };
         
-        println!("Goodbye!");
+        self.script_depth += 1;
+        let mut ok = true;
+        for (index, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if echo {
+                println!("pic> {}", line);
+            }
+            if !self.handle_command(line) {
+                ok = false;
+                if stop_on_error {
+                    println!("{}:{}: stopped at failed command '{}'", path.display(), index + 1, line);
+                    break;
+                }
+            }
+            if self.quit {
+                break;
+            }
+        }
+        self.script_depth -= 1;
+        ok
+    }
+    
+    /// Load aliases and macros from `path` if it exists, and save them
+    /// there whenever they change
+    pub fn use_macros_file(&mut self, path: PathBuf) {
+        if path.exists() {
+            match Macros::load(&path) {
+                Ok(macros) => self.macros = macros,
+                Err(e) => println!("Warning: {}", e),
+            }
+        }
+        self.macros_path = Some(path);
+    }
+    
+    /// Make `reg`, `dump`, `info` and `gpio` print one line of JSON instead
+    /// of tables, for scripts that parse the output
+    pub fn set_json_output(&mut self, json: bool) {
+        self.json = json;
+    }
+    
+    /// Print a command error and mark the command as failed
+    fn fail(&self, message: fmt::Arguments) {
+        println!("{}", message);
+        self.failed.set(true);
     }
     
-    fn handle_command(&mut self, input: &str) {
+    /// Parse a pin of the selected device by name (`GP2`, `RB0`) or
+    /// number, reporting one the device does not have
+    fn parse_pin(&self, name: &str) -> Option<u8> {
+        let model = self.simulator.device().model();
+        let pin = gpio::parse_pin(name).filter(|&pin| model.has_pin(pin));
+        if pin.is_none() {
+            let pins: Vec<String> = model.pins().filter_map(|pin| model.pin_name(pin)).collect();
+            self.fail(format_args!("Invalid pin: {} (the {} has {})", name, self.simulator.device(), pins.join(", ")));
+        }
+        pin
+    }
+    
+    /// Name of a pin of the selected device
+    fn pin_name(&self, pin: u8) -> String {
+        self.simulator.device().model().pin_name(pin).unwrap_or_else(|| gpio::pin_label(pin))
+    }
+    
+    /// Run one command line, returning false if it reported an error
+    fn handle_command(&mut self, input: &str) -> bool {
         let parts: Vec<&str> = input.split_whitespace().collect();
         if parts.is_empty() {
-            return;
+            return true;
         }
         
+        self.failed.set(false);
+        if let Some(expansion) = self.macros.expand(input) {
+            self.run_user_command(expansion);
+            return !self.failed.get();
+        }
         match parts[0] {
+            "quit" | "exit" => self.quit = true,
+            "source" => self.cmd_source(&parts[1..]),
+            "alias" => self.cmd_alias(input),
+            "define" => self.cmd_define(input),
+            "unalias" | "undefine" => self.cmd_undefine(parts.get(1)),
             "help" | "h" => self.cmd_help(),
-            "reset" | "r" => self.cmd_reset(),
+            "reset" | "r" => self.cmd_reset(parts.get(1)),
             "step" | "s" => self.cmd_step(parts.get(1)),
-            "run" => self.cmd_run(),
+            "rstep" => self.cmd_rstep(parts.get(1)),
+            "run" => self.cmd_run(parts.get(1)),
             "continue" | "c" => self.cmd_continue(),
-            "break" | "b" => self.cmd_break(parts.get(1)),
+            "until" | "u" => self.cmd_until(&parts[1..]),
+            "break" | "b" => match &parts[1..] {
+                ["save", path] => self.cmd_break_save(path),
+                ["load", path] => self.cmd_break_load(path),
+                ["clear"] => {
+                    self.simulator.clear_breakpoints();
+                    println!("All breakpoints deleted");
+                }
+                _ => self.cmd_break(parts.get(1)),
+            },
             "delete" | "d" => self.cmd_delete(parts.get(1)),
+            "sfrbreak" | "sb" => self.cmd_sfrbreak(&parts[1..]),
+            "stopwatch" | "sw" => self.cmd_stopwatch(parts.get(1), parts.get(2)),
+            "fosc" => self.cmd_fosc(parts.get(1)),
+            "state" => self.cmd_state(parts.get(1), parts.get(2)),
             "info" | "i" => self.cmd_info(parts.get(1)),
+            "disasm" if parts.get(1) == Some(&"save") => self.cmd_disasm_save(parts.get(2)),
             "disasm" => self.cmd_disasm(parts.get(1), parts.get(2)),
+            "asm" => self.cmd_asm(input),
             "dump" => self.cmd_dump(parts.get(1), parts.get(2)),
             "load" => self.cmd_load(&parts[1..]),
+            "loadhex" => match parts.get(1) {
+                Some(path) => {
+                    self.load_firmware(Path::new(path));
+                }
+                None => self.fail(format_args!("Usage: loadhex <file>")),
+            },
+            "loadasm" => match parts.get(1) {
+                Some(path) => self.load_assembly(Path::new(path)),
+                None => self.fail(format_args!("Usage: loadasm <file>")),
+            },
+            "export" => self.cmd_export(parts.get(1)),
+            "verify" => self.cmd_verify(parts.get(1)),
             "reg" => self.cmd_registers(),
             "pc" => self.cmd_pc(parts.get(1)),
-            "gpio" => self.cmd_gpio(parts.get(1), parts.get(2)),
+            "set" => self.cmd_set(&parts[1..]),
+            "watch" => self.cmd_watch(input),
+            command if matches!(command.split('/').next(), Some("print" | "p")) => self.cmd_print(input),
+            "gpio" => self.cmd_gpio(&parts[1..]),
             "setpin" => self.cmd_setpin(parts.get(1), parts.get(2)),
-            "interrupt" => self.cmd_interrupt(),
-            _ => println!("Unknown command: {}", parts[0]),
+            "interrupt" => self.cmd_interrupt(parts.get(1)),
+            "bt" | "backtrace" => self.cmd_backtrace(),
+            "profile" => self.cmd_profile(parts.get(1), parts.get(2)),
+            "coverage" => self.cmd_coverage(parts.get(1), parts.get(2)),
+            "symbols" => self.cmd_symbols(parts.get(1)),
+            "list" | "l" => self.cmd_list(parts.get(1)),
+            "vcd" => self.cmd_vcd(parts.get(1), parts.get(2)),
+            "trace" => self.cmd_trace(&parts[1..]),
+            "stim" => self.cmd_stimulus(&parts[1..]),
+            "logic" => self.cmd_logic(parts.get(1), parts.get(2)),
+            "circuit" => self.cmd_circuit(&parts[1..]),
+            "golden" => self.cmd_golden(&parts[1..]),
+            "record" => self.cmd_record(parts.get(1), parts.get(2)),
+            "replay" => self.cmd_replay(parts.get(1)),
+            "halt" => self.cmd_halt(parts.get(1), parts.get(2)),
+            "power" => self.cmd_power(parts.get(1)),
+            "wdt" => self.cmd_wdt(&parts[1..]),
+            "device" => self.cmd_device(parts.get(1)),
+            _ => self.fail(format_args!("Unknown command: {}", parts[0])),
+        }
+        !self.failed.get()
+    }
+    
+    /// Run an alias or macro; a failing command ends the macro
+    fn run_user_command(&mut self, expansion: Expansion) {
+        if self.script_depth >= MAX_SCRIPT_DEPTH {
+            self.fail(format_args!("Error: macros nested more than {} deep", MAX_SCRIPT_DEPTH));
+            return;
+        }
+        self.script_depth += 1;
+        let ok = match expansion {
+            Expansion::Alias(line) => self.handle_command(&line),
+            Expansion::Macro(commands) => {
+                let mut ok = true;
+                for command in &commands {
+                    ok = self.handle_command(command);
+                    if !ok || self.quit {
+                        break;
+                    }
+                }
+                ok
+            }
+        };
+        self.script_depth -= 1;
+        self.failed.set(!ok);
+    }
+    
+    fn cmd_alias(&mut self, input: &str) {
+        match split_definition(input) {
+            None => {
+                if self.macros.aliases().next().is_none() {
+                    println!("No aliases");
+                }
+                for (name, command) in self.macros.aliases() {
+                    println!("  {} = {}", name, command);
+                }
+            }
+            Some((name, Some(command))) => match self.macros.add_alias(name, command) {
+                Ok(()) => {
+                    println!("Alias {} = {}", name, command);
+                    self.save_macros();
+                }
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            },
+            Some(_) => self.fail(format_args!("Usage: alias [<name> <command>]")),
+        }
+    }
+    
+    fn cmd_define(&mut self, input: &str) {
+        match split_definition(input) {
+            None => {
+                if self.macros.macros().next().is_none() {
+                    println!("No macros");
+                }
+                for (name, commands) in self.macros.macros() {
+                    println!("  {}: {}", name, commands.join("; "));
+                }
+            }
+            Some((name, Some(commands))) => match self.macros.add_macro(name, commands) {
+                Ok(()) => {
+                    println!("Defined {}", name);
+                    self.save_macros();
+                }
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            },
+            Some(_) => self.fail(format_args!("Usage: define [<name> <command>; <command>...]")),
+        }
+    }
+    
+    fn cmd_undefine(&mut self, name: Option<&&str>) {
+        match name {
+            Some(name) if self.macros.remove(name) => {
+                println!("Removed {}", name);
+                self.save_macros();
+            }
+            Some(name) => self.fail(format_args!("No alias or macro named {}", name)),
+            None => self.fail(format_args!("Usage: unalias <name> | undefine <name>")),
+        }
+    }
+    
+    /// Write the aliases and macros to the macro file, if there is one
+    fn save_macros(&self) {
+        if let Some(path) = &self.macros_path
+            && let Err(e) = self.macros.save(path)
+        {
+            println!("Warning: {}", e);
+        }
+    }
+    
+    fn cmd_source(&mut self, args: &[&str]) {
+        let echo = args.contains(&"echo");
+        let stop_on_error = args.contains(&"stop");
+        let paths: Vec<&&str> = args.iter().filter(|&&arg| arg != "echo" && arg != "stop").collect();
+        match paths.as_slice() {
+            [path] => {
+                // Failures inside the script fail the command
+                if !self.source(Path::new(path), echo, stop_on_error) {
+                    self.failed.set(true);
+                }
+            }
+            _ => self.fail(format_args!("Usage: source <file> [echo] [stop]")),
         }
     }
     
     fn cmd_help(&self) {
         println!("Available commands:");
         println!("  help, h              - Show this help");
-        println!("  reset, r             - Reset the simulator");
+        println!("  reset, r [por|mclr|wdt|bor] - Reset the simulator (power-on by default)");
         println!("  step [n], s [n]      - Execute n instructions (default: 1)");
-        println!("  run                  - Run until breakpoint or error");
+        println!("  rstep [n]            - Step back n instructions (default: 1)");
+        println!("  run [time]           - Run until breakpoint or error, or for a simulated time (e.g. 10ms)");
         println!("  continue, c          - Continue execution");
-        println!("  break <addr>, b      - Set breakpoint at address");
+        println!("  until <addr|expr> [max <cycles|time>], u - Run until PC reaches addr or expr holds, or the limit (default 10M cycles)");
+        println!("  break <addr>, b      - Set breakpoint at address, label or file:line");
+        println!("  break save|load <file> - Save the breakpoints to a file or add those in a file");
+        println!("  break clear          - Delete all breakpoints");
         println!("  delete <addr>, d     - Delete breakpoint");
+        println!("  sfrbreak <reg> [read|write|change|access], sb - Break on register access");
+        println!("  sfrbreak delete <reg|all> - Delete register breakpoint");
+        println!("  stopwatch <start> <stop>, sw - Time execution between two addresses");
+        println!("  stopwatch [off]      - Show or disarm the stopwatch");
+        println!("  fosc [freq]          - Show/set oscillator frequency (e.g. 4MHz, 32768)");
         println!("  info <what>, i       - Show info (breakpoints, stack, etc.)");
         println!("  disasm [addr] [n]    - Disassemble n instructions from addr");
+        println!("  disasm save <file>   - Save a re-assemblable listing of program memory");
+        println!("  asm <addr> <instr>   - Assemble one instruction into program memory (e.g. asm loop nop)");
         println!("  dump [addr] [n]      - Dump n bytes of memory from addr");
         println!("  load <hex> <hex>...  - Load program (hex words)");
+        println!("  loadhex <file>       - Load a HEX, S-record, ELF or COFF firmware file");
+        println!("  loadasm <file>       - Assemble a source file and load it");
+        println!("  export <file>        - Write program memory, EEPROM and config as Intel HEX");
+        println!("  verify <file>        - Compare program memory, EEPROM and config with a HEX file");
         println!("  reg                  - Show registers");
         println!("  pc [addr]            - Show/set program counter");
+        println!("  print[/x|/d|/b] <expr>, p - Evaluate an expression, e.g. print/x (TMR1H<<8)|TMR1L");
+        println!("  watch [add <expr>|delete <n|all>] - List, add or delete expressions shown whenever execution stops");
+        println!("  set w|pc <value>     - Set W or the program counter");
+        println!("  set reg <reg> <value> - Write a register by name or address");
+        println!("  set mem <addr> <value> - Write a data memory location");
+        println!("  set output <json|text> - Print reg, dump, info and gpio as JSON or tables");
+        println!("  set stepinfo <full|brief> - Show the registers each step changed, or only the instruction");
+        println!("  source <file> [echo] [stop] - Run commands from a file (echo them, stop at the first error)");
         println!("  quit, exit           - Exit simulator");
         println!("  gpio [show]          - Show GPIO state");
+        println!("  gpio tris <value>    - Set pin directions (TRISIO, 1 = input)");
+        println!("  gpio pulse <pin> <low|high> <cycles> - Drive an input pin for a number of cycles, then back");
+        println!("  gpio log <on|off>    - Print every pin transition");
+        println!("  alias [<name> <command>] - List aliases or make name run command (extra words are appended)");
+        println!("  define [<name> <cmd>; <cmd>...] - List macros or define one running several commands");
+        println!("  unalias <name>, undefine <name> - Remove an alias or macro");
         println!("  setpin <pin> <0|1>   - Set external pin state");
         println!("  int, interrupt       - Show interrupt status");
+        println!("  interrupt timing     - Show interrupt latency and ISR time per source");
+        println!("  bt, backtrace        - Show logical call stack");
+        println!("  profile <on|off|report [n]|reset> - Control the cycle profiler");
+        println!("  coverage [report|json [file]|reset] - Show or export code coverage");
+        println!("  symbols <file>       - Load symbols from a .lst, .map or .cod file");
+        println!("  list [loc], l        - Show source around PC, label or file:line");
+        println!("  state save <file>    - Save the complete machine state");
+        println!("  state load <file>    - Restore a saved machine state");
+        println!("  vcd start <file>     - Record a VCD waveform (vcd stop to finish)");
+        println!("  trace start <file> [--regs] - Log every executed instruction, with register changes (trace stop to finish)");
+        println!("  stim [load <file>|add <rule>|clear] - Show or change pin stimulus");
+        println!("  logic <pin> [n]      - Show last n transitions and timing of a pin");
+        println!("  logic clear          - Clear the logic analyzer capture");
+        println!("  circuit [load <file>|clear] - Show or load the attached components");
+        println!("  golden record <file> <n> - Reset and record a golden trace of n instructions");
+        println!("  golden check <file>  - Reset and compare execution against a golden trace");
+        println!("  record start         - Record pin changes and resets (record stop <file> to save)");
+        println!("  replay <file>|stop   - Reset and replay recorded inputs");
+        println!("  halt [strict|default|<condition> <on|off>] - Show/set the conditions that halt execution");
+        println!("  power [reset]        - Show/reset the estimated supply current and charge");
+        println!("  wdt                  - Show WDT state and period");
+        println!("  device [name]        - Show/select the simulated part (resets the CPU)");
+        println!("  wdt jitter <off|shortest|longest|uniform|triangular> [seed] [min_us max_us] - Vary WDT periods");
     }
     
-    fn cmd_reset(&mut self) {
-        self.simulator.reset();
-        println!("Simulator reset");
+    fn cmd_reset(&mut self, kind: Option<&&str>) {
+        let reason = match kind {
+            None => ResetReason::PowerOn,
+            Some(name) => match ResetReason::from_name(name) {
+                Some(reason) => reason,
+                None => return self.fail(format_args!("Unknown reset kind: {} (use: por, mclr, wdt or bor)", name)),
+            },
+        };
+        self.simulator.reset_with(reason);
+        println!("Simulator reset ({})", reason);
     }
     
     fn cmd_step(&mut self, count_str: Option<&&str>) {
@@ -106,14 +524,30 @@ impl Cli {
         for _ in 0..count {
             let pc = self.simulator.cpu().get_pc();
             let word = self.simulator.cpu().memory().read_program(pc);
+            let before = self.step_info_full.then(|| {
+                (self.simulator.cpu().read_w(), Debugger::register_snapshot(self.simulator.cpu()))
+            });
             
             match self.simulator.step() {
                 Ok(cycles) => {
-                    let asm = Debugger::disassemble(word);
+                    let asm = Debugger::disassemble_with_symbols(self.simulator.device().core(), word, self.simulator.symbols());
                     println!("0x{:04X}: {} ({} cycles)", pc, asm, cycles);
+                    if let Some((w, registers)) = before {
+                        let cpu = self.simulator.cpu();
+                        let mut changes = Debugger::register_changes(cpu, &registers);
+                        if cpu.read_w() != w {
+                            changes.insert(0, format!("W 0x{:02X} → 0x{:02X}", w, cpu.read_w()));
+                        }
+                        if !changes.is_empty() {
+                            println!("        {}", changes.join(", "));
+                        }
+                    }
+                    if self.report_stop() {
+                        break;
+                    }
                 }
                 Err(e) => {
-                    println!("Error: {}", e);
+                    self.fail(format_args!("Error: {}", e));
                     break;
                 }
             }
@@ -124,52 +558,347 @@ impl Cli {
             self.simulator.cpu().read_w(),
             self.simulator.stats().cycles_elapsed
         );
+        self.show_displays();
     }
     
-    fn cmd_run(&mut self) {
-        println!("Running...");
-        match self.simulator.run() {
-            Ok(_) => println!("Stopped at breakpoint or completion"),
-            Err(e) => println!("Error: {}", e),
+    fn cmd_rstep(&mut self, count_str: Option<&&str>) {
+        let count: u64 = count_str
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1);
+        
+        match self.simulator.step_back(count) {
+            Ok(undone) => {
+                if undone < count {
+                    println!("History only reaches back {} instructions", undone);
+                }
+                println!("Stepped back {} instructions", undone);
+            }
+            Err(e) => self.fail(format_args!("Error: {}", e)),
+        }
+        
+        println!("\nPC = 0x{:04X}, W = 0x{:02X}, Cycles = {}",
+            self.simulator.cpu().get_pc(),
+            self.simulator.cpu().read_w(),
+            self.simulator.stats().cycles_elapsed
+        );
+        self.show_displays();
+    }
+    
+    fn cmd_run(&mut self, time_str: Option<&&str>) {
+        if let Some(time_str) = time_str {
+            let Some(duration) = parse_duration(time_str) else {
+                self.fail(format_args!("Invalid time: {} (use e.g. 10ms, 500us, 1s)", time_str));
+                return;
+            };
+            match self.simulator.run_for(duration) {
+                Ok(elapsed) => {
+                    self.report_stop();
+                    println!("Ran {:.3} ms", elapsed.as_secs_f64() * 1000.0);
+                }
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            }
+        } else {
+            println!("Running...");
+            match self.simulator.run() {
+                Ok(_) => {
+                    if !self.report_stop() {
+                        println!("Stopped at breakpoint or completion");
+                    }
+                }
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            }
+        }
+        
+        println!("PC = 0x{:04X}, Cycles = {}",
+            self.simulator.cpu().get_pc(),
+            self.simulator.stats().cycles_elapsed
+        );
+        self.show_displays();
+    }
+    
+    /// Report a watchpoint or stopwatch stop; returns whether there was one
+    fn report_stop(&self) -> bool {
+        if let Some(hit) = self.simulator.watch_hit() {
+            println!("Watchpoint: {}", hit);
+            true
+        } else if let Some(reading) = self.simulator.stopwatch_hit() {
+            println!("Stopwatch: 0x{:04X} -> 0x{:04X}: {} cycles ({:.2} µs)",
+                reading.start, reading.stop, reading.cycles, reading.micros(self.simulator.clock()));
+            true
+        } else {
+            false
+        }
+    }
+    
+    /// Print the watched expressions, numbered as `watch delete` takes them
+    fn show_displays(&self) {
+        for (index, expr) in self.displays.iter().enumerate() {
+            let value = expr.eval(&self.simulator);
+            println!("{}: {} = {} (0x{:X})", index + 1, expr, value, value);
+        }
+    }
+    
+    fn cmd_watch(&mut self, input: &str) {
+        let args: Vec<&str> = input.split_whitespace().skip(1).collect();
+        match args.as_slice() {
+            [] | ["list"] if self.displays.is_empty() => println!("No watch expressions"),
+            [] | ["list"] => self.show_displays(),
+            ["add", ..] => {
+                let text = input.trim_start()["watch".len()..].trim_start()["add".len()..].trim();
+                match Expr::parse(text, self.simulator.symbols()) {
+                    Ok(expr) => {
+                        let value = expr.eval(&self.simulator);
+                        println!("{}: {} = {} (0x{:X})", self.displays.len() + 1, expr, value, value);
+                        self.displays.push(expr);
+                    }
+                    Err(e) => self.fail(format_args!("Error: {}", e)),
+                }
+            }
+            ["delete" | "d", "all"] => {
+                self.displays.clear();
+                println!("All watch expressions deleted");
+            }
+            ["delete" | "d", n] => match n.parse::<usize>() {
+                Ok(n) if (1..=self.displays.len()).contains(&n) => {
+                    let expr = self.displays.remove(n - 1);
+                    println!("Deleted watch {}: {}", n, expr);
+                }
+                _ => self.fail(format_args!("No watch expression {}", n)),
+            },
+            _ => self.fail(format_args!("Usage: watch [list | add <expr> | delete <n|all>]")),
+        }
+    }
+    
+    /// Run until the PC reaches an address or label, or an expression
+    /// holds, giving up after a cycle count or simulated time
+    ///
+    /// The condition is checked after each instruction, so `until loop`
+    /// while at `loop` runs one more iteration.
+    fn cmd_until(&mut self, args: &[&str]) {
+        let (condition, limit) = match args {
+            [condition @ .., "max", limit] if !condition.is_empty() => (condition.join(" "), Some(*limit)),
+            [] => return self.fail(format_args!("Usage: until <addr|expr> [max <cycles|time>]")),
+            condition => (condition.join(" "), None),
+        };
+        let max_cycles = match limit {
+            None => DEFAULT_UNTIL_CYCLES,
+            Some(limit) => match limit.parse::<u64>().ok().or_else(|| {
+                parse_duration(limit).map(|duration| self.simulator.clock().duration_to_cycles(duration))
+            }) {
+                Some(cycles) => cycles,
+                None => return self.fail(format_args!("Invalid limit: {} (use a cycle count or a time such as 10ms)", limit)),
+            },
+        };
+        
+        let target = match self.resolve_address(&condition) {
+            Some(addr) => Ok(addr),
+            None => match Expr::parse(&condition, self.simulator.symbols()) {
+                Ok(expr) => Err(expr),
+                Err(e) => return self.fail(format_args!("Error: {}", e)),
+            },
+        };
+        let start = self.simulator.stats().cycles_elapsed;
+        let mut first = true;
+        let outcome = self.simulator.run_until(|sim| {
+            let check = !first;
+            first = false;
+            check && match &target {
+                Ok(addr) => sim.cpu().get_pc() == *addr,
+                Err(expr) => expr.is_true(sim),
+            }
+        }, max_cycles);
+        let elapsed = self.simulator.stats().cycles_elapsed - start;
+        
+        match outcome {
+            RunOutcome::PredicateMet => println!("Reached {} after {} cycles", condition, elapsed),
+            RunOutcome::CycleLimit => self.fail(format_args!("Limit reached after {} cycles without reaching {}", elapsed, condition)),
+            RunOutcome::Breakpoint(pc) => println!("Breakpoint at 0x{:04X}", pc),
+            RunOutcome::Watchpoint(hit) => println!("Watchpoint: {}", hit),
+            RunOutcome::Error(e) => self.fail(format_args!("Error: {}", e)),
         }
         
         println!("PC = 0x{:04X}, Cycles = {}",
             self.simulator.cpu().get_pc(),
             self.simulator.stats().cycles_elapsed
         );
+        self.show_displays();
     }
     
     fn cmd_continue(&mut self) {
-        self.cmd_run();
+        self.cmd_run(None);
     }
     
     fn cmd_break(&mut self, addr_str: Option<&&str>) {
         if let Some(addr_str) = addr_str {
-            if let Ok(addr) = parse_hex(addr_str) {
-                self.simulator.add_breakpoint(addr as u16);
-                println!("Breakpoint set at 0x{:04X}", addr);
+            if let Some(addr) = self.resolve_address(addr_str) {
+                self.simulator.add_breakpoint(addr);
+                match self.simulator.symbols().label_at(addr) {
+                    Some(label) => println!("Breakpoint set at 0x{:04X} ({})", addr, label),
+                    None => println!("Breakpoint set at 0x{:04X}", addr),
+                }
             } else {
-                println!("Invalid address: {}", addr_str);
+                self.fail(format_args!("Invalid address: {}", addr_str));
             }
         } else {
-            println!("Usage: break <address>");
+            self.fail(format_args!("Usage: break <address|label>"));
         }
     }
     
+    fn cmd_break_save(&self, path: &str) {
+        let text = breakpoint::format_breakpoints(self.simulator.breakpoints(), self.simulator.symbols());
+        match std::fs::write(path, text) {
+            Ok(_) => println!("{} breakpoints saved to {}", self.simulator.breakpoints().len(), path),
+            Err(e) => self.fail(format_args!("Error: Failed to write {}: {}", path, e)),
+        }
+    }
+    
+    /// Add the breakpoints in a file; ones whose location or condition
+    /// does not resolve in the loaded program are reported and skipped
+    fn cmd_break_load(&mut self, path: &str) {
+        let saved = std::fs::read_to_string(path)
+            .map_err(BreakpointError::Read)
+            .and_then(|text| breakpoint::parse_breakpoints(&text));
+        let saved = match saved {
+            Ok(saved) => saved,
+            Err(e) => return self.fail(format_args!("Error: Failed to load {}: {}", path, e)),
+        };
+        let mut added = 0;
+        for bp in saved {
+            let Some(addr) = self.resolve_address(&bp.location) else {
+                self.fail(format_args!("Skipped {}: unknown location", bp.location));
+                continue;
+            };
+            let condition = match bp.condition.as_deref().map(|text| Expr::parse(text, self.simulator.symbols())) {
+                Some(Err(e)) => {
+                    self.fail(format_args!("Skipped {}: {}", bp.location, e));
+                    continue;
+                }
+                Some(Ok(expr)) => Some(expr),
+                None => None,
+            };
+            self.simulator.add_breakpoint(addr);
+            self.simulator.set_breakpoint_enabled(addr, bp.enabled);
+            self.simulator.set_breakpoint_condition(addr, condition);
+            added += 1;
+        }
+        println!("{} breakpoints loaded from {}", added, path);
+    }
+    
     fn cmd_delete(&mut self, addr_str: Option<&&str>) {
         if let Some(addr_str) = addr_str {
-            if let Ok(addr) = parse_hex(addr_str) {
-                self.simulator.remove_breakpoint(addr as u16);
+            if let Some(addr) = self.resolve_address(addr_str) {
+                self.simulator.remove_breakpoint(addr);
                 println!("Breakpoint deleted at 0x{:04X}", addr);
             } else {
-                println!("Invalid address: {}", addr_str);
+                self.fail(format_args!("Invalid address: {}", addr_str));
+            }
+        } else {
+            self.fail(format_args!("Usage: delete <address|label>"));
+        }
+    }
+    
+    fn cmd_sfrbreak(&mut self, args: &[&str]) {
+        match args {
+            [] => self.show_watchpoints(),
+            ["delete" | "d", "all"] => {
+                self.simulator.clear_watchpoints();
+                println!("All register breakpoints deleted");
+            }
+            ["delete" | "d", reg] => match watchpoint::parse_register(reg) {
+                Some(addr) => {
+                    self.simulator.remove_watchpoint(addr);
+                    println!("Register breakpoint deleted on {}", watchpoint::register_name(addr as u16));
+                }
+                None => self.fail(format_args!("Unknown register: {}", reg)),
+            },
+            [reg, rest @ ..] if rest.len() <= 1 => {
+                let Some(addr) = watchpoint::parse_register(reg) else {
+                    self.fail(format_args!("Unknown register: {}", reg));
+                    return;
+                };
+                let kind = match rest.first() {
+                    None => WatchKind::Write,
+                    Some(s) => match WatchKind::parse(s) {
+                        Some(kind) => kind,
+                        None => {
+                            self.fail(format_args!("Invalid access kind: {} (read, write, change or access)", s));
+                            return;
+                        }
+                    },
+                };
+                let wp = Watchpoint::new(addr, kind);
+                self.simulator.add_watchpoint(wp);
+                println!("Register breakpoint set: {}", wp);
             }
+            _ => self.fail(format_args!("Usage: sfrbreak [<reg> [read|write|change|access] | delete <reg|all>]")),
+        }
+    }
+    
+    fn show_watchpoints(&self) {
+        let wps = self.simulator.watchpoints();
+        if wps.is_empty() {
+            println!("No register breakpoints set");
         } else {
-            println!("Usage: delete <address>");
+            println!("Register breakpoints:");
+            for wp in wps {
+                println!("  {}", wp);
+            }
+        }
+    }
+    
+    fn cmd_stopwatch(&mut self, start_str: Option<&&str>, stop_str: Option<&&str>) {
+        match (start_str, stop_str) {
+            (None, _) => {
+                let stopwatch = self.simulator.stopwatch();
+                let clock = self.simulator.clock();
+                match stopwatch.points() {
+                    Some((start, stop)) => println!("Stopwatch armed: 0x{:04X} -> 0x{:04X}", start, stop),
+                    None => println!("Stopwatch not armed"),
+                }
+                if let Some(elapsed) = stopwatch.elapsed(self.simulator.stats().cycles_elapsed) {
+                    println!("Running: {} cycles", elapsed);
+                }
+                if let Some(reading) = stopwatch.last_reading() {
+                    println!("Last: {} cycles ({:.2} µs at {} Hz)", reading.cycles, reading.micros(clock), clock.fosc_hz());
+                }
+            }
+            (Some(&"off"), _) => {
+                self.simulator.disarm_stopwatch();
+                println!("Stopwatch disarmed");
+            }
+            (Some(start_str), Some(stop_str)) => {
+                match (self.resolve_address(start_str), self.resolve_address(stop_str)) {
+                    (Some(start), Some(stop)) => {
+                        self.simulator.arm_stopwatch(start, stop);
+                        println!("Stopwatch armed: 0x{:04X} -> 0x{:04X}", start, stop);
+                    }
+                    _ => self.fail(format_args!("Invalid address")),
+                }
+            }
+            _ => self.fail(format_args!("Usage: stopwatch <start> <stop> | stopwatch off")),
         }
     }
     
+    fn cmd_fosc(&mut self, freq_str: Option<&&str>) {
+        if let Some(freq_str) = freq_str {
+            match parse_frequency(freq_str) {
+                Some(hz) if hz > 0 => self.simulator.set_fosc(hz),
+                _ => {
+                    self.fail(format_args!("Invalid frequency: {}", freq_str));
+                    return;
+                }
+            }
+        }
+        let clock = self.simulator.clock();
+        println!("Fosc = {} Hz, {} ({:.3} µs per instruction cycle)",
+            clock.fosc_hz(), clock.source(), clock.cycles_to_micros(1));
+        println!("Elapsed: {:.3} ms", self.simulator.elapsed_time().as_secs_f64() * 1000.0);
+    }
+    
     fn cmd_info(&self, what: Option<&&str>) {
+        if self.json && self.info_json(what) {
+            return;
+        }
         match what {
             Some(&"breakpoints") | Some(&"b") => {
                 let bps = self.simulator.breakpoints();
@@ -178,37 +907,238 @@ impl Cli {
                 } else {
                     println!("Breakpoints:");
                     for bp in bps {
-                        println!("  0x{:04X}", bp);
+                        match self.simulator.symbols().label_at(bp.address) {
+                            Some(label) => println!("  {}  {}", bp, label),
+                            None => println!("  {}", bp),
+                        }
                     }
                 }
             }
+            Some(&"watchpoints") | Some(&"w") => self.show_watchpoints(),
             Some(&"stack") | Some(&"s") => {
                 Debugger::display_stack(self.simulator.cpu());
             }
+            Some(&"calls") => self.cmd_backtrace(),
+            Some(&"symbols") => {
+                let symbols = self.simulator.symbols();
+                if symbols.is_empty() {
+                    println!("No symbols loaded");
+                } else {
+                    println!("Symbols:");
+                    for (addr, label) in symbols.labels() {
+                        println!("  0x{:04X}  {}", addr, label);
+                    }
+                }
+            }
             Some(&"stats") => {
                 let stats = self.simulator.stats();
                 println!("Instructions: {}", stats.instructions_executed);
                 println!("Cycles:       {}", stats.cycles_elapsed);
             }
+            Some(&"config") | Some(&"c") => {
+                match self.simulator.config_word() {
+                    Some(config) => println!("Config:  0x{:04X}", config),
+                    None => println!("Config:  not set"),
+                }
+                match self.simulator.id_locations() {
+                    Some(ids) => println!("User ID: {:04X} {:04X} {:04X} {:04X}", ids[0], ids[1], ids[2], ids[3]),
+                    None => println!("User ID: not set"),
+                }
+            }
             _ => {
-                println!("Usage: info <what>");
+                self.fail(format_args!("Usage: info <what>"));
                 println!("  breakpoints, b - Show breakpoints");
+                println!("  watchpoints, w - Show register breakpoints");
                 println!("  stack, s       - Show stack");
+                println!("  calls          - Show logical call stack");
+                println!("  symbols        - Show loaded labels");
                 println!("  stats          - Show statistics");
+                println!("  config, c      - Show configuration word and user IDs");
             }
         }
     }
     
+    /// Print `info` as JSON; returns false for sections only shown as text
+    fn info_json(&self, what: Option<&&str>) -> bool {
+        let json = match what {
+            Some(&"breakpoints") | Some(&"b") => {
+                let bps: Vec<String> = self.simulator.breakpoints().iter().map(|bp| {
+                    let label = self.simulator.symbols().label_at(bp.address)
+                        .map_or("null".to_string(), json_string);
+                    let condition = bp.condition.as_ref()
+                        .map_or("null".to_string(), |expr| json_string(&expr.to_string()));
+                    format!("{{\"address\":{},\"label\":{},\"enabled\":{},\"hits\":{},\"condition\":{}}}",
+                        bp.address, label, bp.enabled, bp.hits, condition)
+                }).collect();
+                format!("{{\"breakpoints\":[{}]}}", bps.join(","))
+            }
+            Some(&"stack") | Some(&"s") => Debugger::stack_json(self.simulator.cpu()),
+            Some(&"symbols") => {
+                let labels: Vec<String> = self.simulator.symbols().labels()
+                    .map(|(addr, label)| format!("{{\"address\":{},\"label\":{}}}", addr, json_string(label)))
+                    .collect();
+                format!("{{\"symbols\":[{}]}}", labels.join(","))
+            }
+            Some(&"stats") => {
+                let stats = self.simulator.stats();
+                format!("{{\"instructions\":{},\"cycles\":{}}}", stats.instructions_executed, stats.cycles_elapsed)
+            }
+            Some(&"config") | Some(&"c") => {
+                let config = self.simulator.config_word().map_or("null".to_string(), |config| config.to_string());
+                let ids = self.simulator.id_locations().map_or("null".to_string(), |ids| {
+                    format!("[{},{},{},{}]", ids[0], ids[1], ids[2], ids[3])
+                });
+                format!("{{\"config\":{},\"user_id\":{}}}", config, ids)
+            }
+            _ => return false,
+        };
+        println!("{}", json);
+        true
+    }
+    
     fn cmd_disasm(&self, addr_str: Option<&&str>, count_str: Option<&&str>) {
         let addr = addr_str
-            .and_then(|s| parse_hex(s).ok())
-            .unwrap_or(self.simulator.cpu().get_pc() as u32) as u16;
+            .and_then(|s| self.resolve_address(s))
+            .unwrap_or(self.simulator.cpu().get_pc());
         
         let count = count_str
             .and_then(|s| s.parse().ok())
             .unwrap_or(10);
         
-        Debugger::disassemble_range(self.simulator.cpu(), addr, count);
+        Debugger::disassemble_range_with_symbols(
+            self.simulator.cpu(),
+            self.simulator.symbols(),
+            addr,
+            count,
+        );
+    }
+    
+    /// Assemble one instruction over the word at an address or label
+    fn cmd_asm(&mut self, input: &str) {
+        // The instruction is the text after the command and the address
+        let text = after_first_word(input).and_then(after_first_word);
+        let (Some(addr_str), Some(text)) = (input.split_whitespace().nth(1), text) else {
+            return self.fail(format_args!("Usage: asm <addr> <mnemonic> [operands]"));
+        };
+        let Some(addr) = self.resolve_address(addr_str) else {
+            return self.fail(format_args!("Invalid address: {}", addr_str));
+        };
+        if self.simulator.device().model().core != Core::Midrange {
+            return self.fail(format_args!("asm supports 14-bit core devices only"));
+        }
+        match assembler::assemble_instruction(text, addr, self.simulator.symbols()) {
+            Ok(word) => {
+                let old = self.simulator.patch_program(addr, word);
+                let symbols = self.simulator.symbols();
+                println!("0x{:04X}: {:04X} {} -> {:04X} {}", addr,
+                    old, Debugger::disassemble_with_symbols(Core::Midrange, old, symbols),
+                    word, Debugger::disassemble_with_symbols(Core::Midrange, word, symbols));
+            }
+            Err(e) => self.fail(format_args!("Error: {}", e)),
+        }
+    }
+    
+    fn cmd_disasm_save(&self, path: Option<&&str>) {
+        let Some(path) = path else {
+            self.fail(format_args!("Usage: disasm save <file>"));
+            return;
+        };
+        
+        match Debugger::disassemble_to_file_with_symbols(self.simulator.cpu(), self.simulator.symbols(), path) {
+            Ok(_) => println!("Listing written to {}", path),
+            Err(e) => self.fail(format_args!("Error: {}", e)),
+        }
+    }
+    
+    fn cmd_export(&self, path: Option<&&str>) {
+        let Some(path) = path else {
+            self.fail(format_args!("Usage: export <file.hex>"));
+            return;
+        };
+        
+        match self.simulator.export_hex(path) {
+            Ok(_) => println!("HEX written to {}", path),
+            Err(e) => self.fail(format_args!("Error: {}", e)),
+        }
+    }
+    
+    /// Load a HEX, S-record, ELF, COFF or assembly file and print a
+    /// summary, returning false if it failed to load
+    pub fn load_firmware(&mut self, path: &Path) -> bool {
+        match self.simulator.load_firmware_file(path) {
+            Ok(report) => {
+                self.print_load_summary(path, &report);
+                true
+            }
+            Err(SimError::Assemble(errors)) => {
+                self.print_asm_errors(path, &errors);
+                false
+            }
+            Err(e) => {
+                self.fail(format_args!("Error: {}", e));
+                false
+            }
+        }
+    }
+    
+    /// Assemble a source file and load it, whatever its extension
+    pub fn load_assembly(&mut self, path: &Path) {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                self.fail(format_args!("Error: Failed to read {}: {}", path.display(), e));
+                return;
+            }
+        };
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        match self.simulator.load_asm_string(&source, &file) {
+            Ok(report) => self.print_load_summary(path, &report),
+            Err(SimError::Assemble(errors)) => self.print_asm_errors(path, &errors),
+            Err(e) => self.fail(format_args!("Error: {}", e)),
+        }
+    }
+    
+    /// Print assembler errors as `file:line: message`
+    fn print_asm_errors(&self, path: &Path, errors: &[AsmError]) {
+        for error in errors {
+            println!("{}:{}: {}", path.display(), error.line, error.message);
+        }
+        self.fail(format_args!("Assembly failed: {} error(s)", errors.len()));
+    }
+    
+    /// Words loaded, the configuration applied, symbols and warnings
+    fn print_load_summary(&self, path: &Path, report: &LoadReport) {
+        println!("Loaded {}: {} words, start at 0x{:04X}",
+            path.display(), report.words_loaded, self.simulator.cpu().get_pc());
+        let clock = self.simulator.clock();
+        match self.simulator.config_word() {
+            Some(config) => println!("  Config:   0x{:04X} ({}, Fosc = {} Hz)", config, clock.source(), clock.fosc_hz()),
+            None => println!("  Config:   none (keeping {}, Fosc = {} Hz)", clock.source(), clock.fosc_hz()),
+        }
+        if !self.simulator.symbols().is_empty() {
+            println!("  Symbols:  {} labels", self.simulator.symbols().len());
+        }
+        for warning in &report.warnings {
+            println!("  Warning: {}", warning);
+        }
+    }
+    
+    fn cmd_verify(&self, path: Option<&&str>) {
+        let Some(path) = path else {
+            self.fail(format_args!("Usage: verify <file.hex>"));
+            return;
+        };
+        
+        match self.simulator.verify_hex_file(path) {
+            Ok(differences) if differences.is_empty() => println!("Verify OK: memory matches {}", path),
+            Ok(differences) => {
+                for difference in &differences {
+                    println!("  {}", difference);
+                }
+                self.fail(format_args!("Verify failed: {} difference(s)", differences.len()));
+            }
+            Err(e) => self.fail(format_args!("Error: {}", e)),
+        }
     }
     
     fn cmd_dump(&self, addr_str: Option<&&str>, count_str: Option<&&str>) {
@@ -220,7 +1150,11 @@ impl Cli {
             .and_then(|s| s.parse().ok())
             .unwrap_or(64);
         
-        Debugger::dump_memory(self.simulator.cpu(), addr, count);
+        if self.json {
+            println!("{}", Debugger::memory_json(self.simulator.cpu(), addr, count));
+        } else {
+            Debugger::dump_memory(self.simulator.cpu(), addr, count);
+        }
     }
     
     fn cmd_load(&mut self, words: &[&str]) {
@@ -230,7 +1164,7 @@ impl Cli {
             if let Ok(word) = parse_hex(word_str) {
                 program.push(word as u16);
             } else {
-                println!("Invalid hex value: {}", word_str);
+                self.fail(format_args!("Invalid hex value: {}", word_str));
                 return;
             }
         }
@@ -240,52 +1174,608 @@ impl Cli {
     }
     
     fn cmd_registers(&self) {
-        Debugger::display_registers(self.simulator.cpu());
+        if self.json {
+            println!("{}", Debugger::registers_json(self.simulator.cpu()));
+        } else {
+            Debugger::display_registers(self.simulator.cpu());
+        }
     }
     
     fn cmd_pc(&mut self, addr_str: Option<&&str>) {
         if let Some(addr_str) = addr_str {
-            if let Ok(addr) = parse_hex(addr_str) {
-                self.simulator.cpu_mut().set_pc(addr as u16);
+            if let Some(addr) = self.resolve_address(addr_str) {
+                self.simulator.cpu_mut().set_pc(addr);
                 println!("PC set to 0x{:04X}", addr);
             } else {
-                println!("Invalid address: {}", addr_str);
+                self.fail(format_args!("Invalid address: {}", addr_str));
             }
         } else {
             println!("PC = 0x{:04X}", self.simulator.cpu().get_pc());
         }
     }
 
-    fn cmd_gpio(&self, subcmd: Option<&&str>, _arg: Option<&&str>) {
-        match subcmd {
-            None | Some(&"show") => {
+    /// Evaluate an expression and print it in hex (`/x`), decimal (`/d`),
+    /// binary (`/b`) or, without a selector, all three
+    fn cmd_print(&self, input: &str) {
+        let (command, text) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let format = command.split_once('/').map(|(_, format)| format);
+        if !matches!(format, None | Some("x" | "d" | "b")) || text.trim().is_empty() {
+            return self.fail(format_args!("Usage: print[/x|/d|/b] <expr>"));
+        }
+        let expr = match Expr::parse(text, self.simulator.symbols()) {
+            Ok(expr) => expr,
+            Err(e) => return self.fail(format_args!("Error: {}", e)),
+        };
+        let value = expr.eval(&self.simulator);
+        match format {
+            Some("x") => println!("{} = 0x{:X}", expr, value),
+            Some("d") => println!("{} = {}", expr, value),
+            Some(_) => println!("{} = 0b{:b}", expr, value),
+            None => println!("{} = {} (0x{:X}, 0b{:b})", expr, value, value, value),
+        }
+    }
+    
+    /// Write W, the PC, a register or data memory; register and memory
+    /// writes go through the CPU, so mirrored SFRs and shared RAM update
+    /// in every bank
+    fn cmd_set(&mut self, args: &[&str]) {
+        let parse_byte = |s: &str| parse_hex(s).ok().and_then(|value| u8::try_from(value).ok());
+        match args {
+            ["w" | "W", value] => match parse_byte(value) {
+                Some(value) => {
+                    self.simulator.cpu_mut().write_w(value);
+                    println!("W = 0x{:02X}", value);
+                }
+                None => self.fail(format_args!("Invalid value: {} (must be 0x00-0xFF)", value)),
+            },
+            ["pc" | "PC", addr] => self.cmd_pc(Some(addr)),
+            ["stepinfo", mode @ ("full" | "brief")] => {
+                self.step_info_full = *mode == "full";
+                println!("Step info: {}", mode);
+            }
+            ["output", format @ ("json" | "text")] => {
+                self.json = *format == "json";
+                println!("Output format: {}", format);
+            }
+            ["reg", reg, value] => {
+                let Some(addr) = watchpoint::parse_register(reg) else {
+                    return self.fail(format_args!("Unknown register: {}", reg));
+                };
+                let Some(value) = parse_byte(value) else {
+                    return self.fail(format_args!("Invalid value: {} (must be 0x00-0xFF)", value));
+                };
+                self.simulator.cpu_mut().write_register(addr, value);
+                println!("{} (0x{:02X}) = 0x{:02X}", watchpoint::register_name(addr as u16), addr, value);
+            }
+            ["mem", addr, value] => {
+                let Some(addr) = parse_byte(addr) else {
+                    return self.fail(format_args!("Invalid address: {} (must be 0x00-0xFF)", addr));
+                };
+                if !self.simulator.device().model().is_implemented(addr) {
+                    return self.fail(format_args!("0x{:02X} is not implemented on the {}", addr, self.simulator.device().name()));
+                }
+                let Some(value) = parse_byte(value) else {
+                    return self.fail(format_args!("Invalid value: {} (must be 0x00-0xFF)", value));
+                };
+                self.simulator.cpu_mut().write_register(addr, value);
+                println!("[0x{:02X}] = 0x{:02X}", addr, value);
+            }
+            _ => self.fail(format_args!("Usage: set w <value> | set pc <addr> | set reg <reg> <value> | set mem <addr> <value> | set output <json|text> | set stepinfo <full|brief>")),
+        }
+    }
+
+    fn cmd_gpio(&mut self, args: &[&str]) {
+        match args {
+            [] | ["show"] if self.json => {
+                println!("{}", Debugger::gpio_json(self.simulator.cpu()));
+            }
+            [] | ["show"] => {
                 Debugger::display_gpio(self.simulator.cpu());
             }
-            _ => println!("Usage: gpio [show]"),
+            ["tris", value] => match parse_hex(value).ok().and_then(|v| u8::try_from(v).ok()) {
+                Some(value) => {
+                    self.simulator.cpu_mut().write_register(registers::TRISIO, value);
+                    println!("TRISIO = 0x{:02X}", self.simulator.cpu().gpio().read_tris());
+                }
+                None => self.fail(format_args!("Invalid TRIS value: {}", value)),
+            },
+            ["pulse", pin, level @ ("low" | "high" | "0" | "1"), cycles] => {
+                let Some(pin) = self.parse_pin(pin) else {
+                    return;
+                };
+                let Some(duration) = cycles.parse::<u64>().ok().filter(|&cycles| cycles > 0) else {
+                    return self.fail(format_args!("Invalid cycle count: {}", cycles));
+                };
+                let level = matches!(*level, "high" | "1");
+                let at = self.simulator.stats().cycles_elapsed;
+                let rule = StimulusRule::Set { pin, level, at, duration: Some(duration) };
+                let mut stimulus = self.simulator.stimulus().clone();
+                stimulus.add_rule(rule);
+                self.simulator.set_stimulus(stimulus);
+                println!("Added: {}", rule);
+            }
+            ["log", "on"] => {
+                if self.gpio_log.is_none() {
+                    self.gpio_log = Some(self.simulator.subscribe(gpio_logger()));
+                }
+                println!("Pin transitions are logged");
+            }
+            ["log", "off"] => {
+                if let Some(id) = self.gpio_log.take() {
+                    self.simulator.unsubscribe(id);
+                }
+                println!("Pin logging stopped");
+            }
+            _ => self.fail(format_args!("Usage: gpio [show|tris <value>|pulse <pin> <low|high> <cycles>|log <on|off>]")),
         }
     }
 
     fn cmd_setpin(&mut self, pin_str: Option<&&str>, value_str: Option<&&str>) {
         if let (Some(pin_str), Some(value_str)) = (pin_str, value_str) {
-            if let Ok(pin) = pin_str.parse::<u8>() {
-                if pin < 6 {
-                    let value = *value_str == "1" || value_str.to_lowercase() == "high";
-                    self.simulator.cpu_mut().gpio_mut().set_external_pin(pin, value);
-                    println!("Set external pin GP{} to {}", pin, if value { "HIGH" } else { "LOW" });
-                } else {
-                    println!("Invalid pin number (must be 0-5)");
-                }
-            } else {
-                println!("Invalid pin number");
+            if let Some(pin) = self.parse_pin(pin_str) {
+                let value = *value_str == "1" || value_str.to_lowercase() == "high";
+                self.simulator.set_external_pin(pin, value);
+                println!("Set external pin {} to {}", self.pin_name(pin), if value { "HIGH" } else { "LOW" });
             }
         } else {
-            println!("Usage: setpin <pin> <0|1>");
+            self.fail(format_args!("Usage: setpin <pin> <0|1>"));
         }
     }
 
-    fn cmd_interrupt(&self) {
-        Debugger::display_interrupts(self.simulator.cpu());
-    }    
+    fn cmd_interrupt(&self, what: Option<&&str>) {
+        match what {
+            Some(&"timing") => Debugger::display_interrupt_timing(self.simulator.interrupt_timing()),
+            Some(other) => self.fail(format_args!("Unknown option: {} (use: interrupt [timing])", other)),
+            None => Debugger::display_interrupts(self.simulator.cpu()),
+        }
+    }
+
+    fn cmd_power(&mut self, action: Option<&&str>) {
+        match action {
+            None => Debugger::display_power(self.simulator.power()),
+            Some(&"reset") => {
+                self.simulator.power_mut().clear();
+                println!("Power estimate reset");
+            }
+            Some(other) => self.fail(format_args!("Unknown option: {} (use: power [reset])", other)),
+        }
+    }
+
+    fn cmd_wdt(&mut self, args: &[&str]) {
+        match args {
+            [] => {
+                let wdt = self.simulator.cpu().wdt();
+                println!("WDT: {}", if wdt.is_enabled() { "enabled" } else { "disabled" });
+                println!("  Counter: {} / {}", wdt.get_counter(), wdt.get_timeout_period());
+                println!("  Period:  {} us (without prescaler)", wdt.period_us());
+                match wdt.jitter() {
+                    Some(jitter) => println!("  Jitter:  {} over {}-{} us, seed {}",
+                        jitter.distribution, jitter.min_us, jitter.max_us, jitter.seed()),
+                    None => println!("  Jitter:  off (nominal period)"),
+                }
+            }
+            ["jitter", "off"] => {
+                self.simulator.cpu_mut().wdt_mut().set_jitter(None);
+                println!("WDT jitter off");
+            }
+            ["jitter", name, rest @ ..] => {
+                let Some(distribution) = WdtDistribution::from_name(name) else {
+                    self.fail(format_args!("Unknown distribution: {}", name));
+                    return;
+                };
+                let numbers: Result<Vec<u64>, _> = rest.iter().map(|s| s.parse::<u64>()).collect();
+                let jitter = match numbers.as_deref() {
+                    Ok([]) => WdtJitter::new(distribution, 0),
+                    Ok([seed]) => WdtJitter::new(distribution, *seed),
+                    Ok([seed, min, max]) if min <= max && *max <= u32::MAX as u64 => {
+                        WdtJitter::new(distribution, *seed).with_range(*min as u32, *max as u32)
+                    }
+                    _ => {
+                        self.fail(format_args!("Usage: wdt jitter <distribution> [seed] [min_us max_us]"));
+                        return;
+                    }
+                };
+                self.simulator.cpu_mut().wdt_mut().set_jitter(Some(jitter));
+                println!("WDT jitter: {} over {}-{} us, seed {}",
+                    distribution, jitter.min_us, jitter.max_us, jitter.seed());
+            }
+            _ => self.fail(format_args!("Usage: wdt [jitter <off|shortest|longest|uniform|triangular> [seed] [min_us max_us]]")),
+        }
+    }
+
+    fn cmd_device(&mut self, name: Option<&&str>) {
+        match name {
+            None => {
+                let device = self.simulator.device();
+                let model = device.model();
+                println!("Device: {}", device);
+                println!("  Program memory: {} words", model.program_words);
+                let ram: Vec<String> = model.gpr.iter()
+                    .map(|range| format!("0x{:02X}-0x{:02X}", range.start(), range.end()))
+                    .collect();
+                println!("  RAM:            {}", ram.join(", "));
+                println!("  EEPROM:         {} bytes", model.eeprom_bytes);
+                println!("  Stack:          {} levels", model.stack_depth);
+                let ports: Vec<&str> = model.ports.iter().map(|port| port.name).collect();
+                println!("  Ports:          {}", ports.join(", "));
+                println!("  ADC:            {}", if model.peripherals.adc { "yes" } else { "no" });
+                println!("  CCP/PWM:        {}", if model.peripherals.ccp { "yes" } else { "no" });
+                let names: Vec<String> = Device::ALL.iter().map(|d| d.to_string()).collect();
+                println!("Available: {}", names.join(", "));
+            }
+            Some(name) => match Device::from_name(name) {
+                Some(device) => {
+                    self.simulator.set_device(device);
+                    println!("Device set to {} (CPU reset)", device);
+                }
+                None => self.fail(format_args!("Unknown device: {}", name)),
+            },
+        }
+    }
+
+    fn cmd_backtrace(&self) {
+        Debugger::display_call_stack(
+            self.simulator.call_stack(),
+            self.simulator.stats().cycles_elapsed,
+        );
+    }
+    
+    fn cmd_profile(&mut self, action: Option<&&str>, count_str: Option<&&str>) {
+        match action {
+            Some(&"on") => {
+                self.simulator.profiler_mut().set_enabled(true);
+                println!("Profiler enabled");
+            }
+            Some(&"off") => {
+                self.simulator.profiler_mut().set_enabled(false);
+                println!("Profiler disabled");
+            }
+            Some(&"report") => {
+                let count = count_str
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(10);
+                Debugger::display_profile(self.simulator.cpu(), self.simulator.profiler(), count);
+            }
+            Some(&"reset") => {
+                self.simulator.profiler_mut().clear();
+                println!("Profile data cleared");
+            }
+            _ => {
+                self.fail(format_args!("Usage: profile <on|off|report [n]|reset>"));
+                println!("Profiler is {}",
+                    if self.simulator.profiler().is_enabled() { "on" } else { "off" });
+            }
+        }
+    }
+    
+    fn cmd_symbols(&mut self, path: Option<&&str>) {
+        match path {
+            Some(path) => match self.simulator.load_symbol_file(path) {
+                Ok(count) => println!("Loaded {} symbols from {}", count, path),
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            },
+            None => self.fail(format_args!("Usage: symbols <file.lst|file.map|file.cod>")),
+        }
+    }
+    
+    fn cmd_state(&mut self, action: Option<&&str>, path: Option<&&str>) {
+        match (action, path) {
+            (Some(&"save"), Some(path)) => match self.simulator.save_state_file(path) {
+                Ok(_) => println!("Machine state saved to {}", path),
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            },
+            (Some(&"load"), Some(path)) => match self.simulator.restore_state_file(path) {
+                Ok(_) => println!("Machine state restored from {} (PC = 0x{:04X}, Cycles = {})",
+                    path, self.simulator.cpu().get_pc(), self.simulator.stats().cycles_elapsed),
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            },
+            _ => self.fail(format_args!("Usage: state <save|load> <file>")),
+        }
+    }
+    
+    fn cmd_trace(&mut self, args: &[&str]) {
+        match args {
+            ["start", path] | ["start", path, "--regs"] => {
+                if self.trace_file.is_some() {
+                    return self.fail(format_args!("A trace is already being written (trace stop first)"));
+                }
+                let regs = args.len() == 3;
+                match TraceFile::create(path, &self.simulator, regs) {
+                    Ok(trace) => {
+                        let trace = Rc::new(RefCell::new(trace));
+                        let writer = Rc::clone(&trace);
+                        let id = self.simulator.subscribe(move |event| writer.borrow_mut().on_event(event));
+                        self.trace_file = Some((id, trace));
+                        println!("Tracing instructions to {}{}", path, if regs { " with register changes" } else { "" });
+                    }
+                    Err(e) => self.fail(format_args!("Error: Failed to create {}: {}", path, e)),
+                }
+            }
+            ["stop"] => {
+                let Some((id, trace)) = self.trace_file.take() else {
+                    println!("No trace is being written");
+                    return;
+                };
+                self.simulator.unsubscribe(id);
+                let Ok(trace) = Rc::try_unwrap(trace) else {
+                    unreachable!("the trace observer was removed");
+                };
+                match trace.into_inner().finish() {
+                    Ok(lines) => println!("Trace stopped: {} instructions written", lines),
+                    Err(e) => self.fail(format_args!("Error: {}", e)),
+                }
+            }
+            [] => match &self.trace_file {
+                Some((_, trace)) => println!("Tracing: {} instructions written", trace.borrow().lines()),
+                None => println!("No trace is being written"),
+            },
+            _ => self.fail(format_args!("Usage: trace start <file> [--regs] | trace stop")),
+        }
+    }
+    
+    fn cmd_vcd(&mut self, action: Option<&&str>, path: Option<&&str>) {
+        match (action, path) {
+            (Some(&"start"), Some(path)) => match self.simulator.start_vcd(path) {
+                Ok(_) => println!("Recording waveform to {}", path),
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            },
+            (Some(&"stop"), _) => {
+                if !self.simulator.is_recording_vcd() {
+                    println!("No waveform is being recorded");
+                    return;
+                }
+                match self.simulator.stop_vcd() {
+                    Ok(_) => println!("Waveform capture stopped"),
+                    Err(e) => self.fail(format_args!("Error: {}", e)),
+                }
+            }
+            _ => self.fail(format_args!("Usage: vcd start <file.vcd> | vcd stop")),
+        }
+    }
+    
+    fn cmd_stimulus(&mut self, args: &[&str]) {
+        match args {
+            [] => {
+                let stimulus = self.simulator.stimulus();
+                if stimulus.is_empty() {
+                    println!("No stimulus rules");
+                    return;
+                }
+                println!("Stimulus rules:");
+                for rule in stimulus.rules() {
+                    println!("  {}", rule);
+                }
+                if let Some(cycle) = stimulus.next_change() {
+                    println!("Next change at cycle {}", cycle);
+                }
+            }
+            ["load", path] => match self.simulator.load_stimulus_file(path) {
+                Ok(count) => println!("Loaded {} stimulus rules from {}", count, path),
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            },
+            ["add", rule @ ..] if !rule.is_empty() => {
+                match Stimulus::parse(&rule.join(" ")) {
+                    Ok(parsed) => {
+                        let mut stimulus = self.simulator.stimulus().clone();
+                        for rule in parsed.rules() {
+                            stimulus.add_rule(*rule);
+                            println!("Added: {}", rule);
+                        }
+                        self.simulator.set_stimulus(stimulus);
+                    }
+                    Err(e) => self.fail(format_args!("Error: {}", e)),
+                }
+            }
+            ["clear"] => {
+                self.simulator.clear_stimulus();
+                println!("Stimulus cleared");
+            }
+            _ => {
+                self.fail(format_args!("Usage: stim [load <file>|add <rule>|clear]"));
+                println!("  e.g. stim add GP2: toggle every 1000 cycles");
+            }
+        }
+    }
+    
+    fn cmd_circuit(&mut self, args: &[&str]) {
+        match args {
+            [] => {
+                let components = self.simulator.components();
+                if components.is_empty() {
+                    println!("No components attached");
+                    return;
+                }
+                println!("Components:");
+                for (id, component) in components.iter() {
+                    println!("  [{}] {}", id, component.describe());
+                }
+            }
+            ["load", path] => match self.simulator.load_circuit_file(path) {
+                Ok(count) => println!("Attached {} components from {}", count, path),
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            },
+            ["clear"] => {
+                self.simulator.components_mut().clear();
+                println!("Components removed");
+            }
+            _ => self.fail(format_args!("Usage: circuit [load <file>|clear]")),
+        }
+    }
+    
+    fn cmd_golden(&mut self, args: &[&str]) {
+        match args {
+            ["record", path, count] => {
+                let Ok(count) = count.parse::<u64>() else {
+                    self.fail(format_args!("Invalid instruction count: {}", count));
+                    return;
+                };
+                self.simulator.reset();
+                let result = GoldenTrace::record(&mut self.simulator, count)
+                    .and_then(|trace| Ok(trace.save_file(path)?));
+                match result {
+                    Ok(_) => println!("Recorded {} instructions to {}", count, path),
+                    Err(e) => self.fail(format_args!("Error: {}", e)),
+                }
+            }
+            ["check", path] => {
+                let golden = match GoldenTrace::load_file(path) {
+                    Ok(golden) => golden,
+                    Err(e) => {
+                        self.fail(format_args!("Error: {}", e));
+                        return;
+                    }
+                };
+                self.simulator.reset();
+                match golden.compare(&mut self.simulator) {
+                    Ok(None) => println!("All {} instructions match {}", golden.len(), path),
+                    Ok(Some(divergence)) => self.fail(format_args!("{}", divergence)),
+                    Err(e) => self.fail(format_args!("Error: {}", e)),
+                }
+            }
+            _ => self.fail(format_args!("Usage: golden <record <file> <n>|check <file>>")),
+        }
+    }
+    
+    fn cmd_record(&mut self, action: Option<&&str>, path: Option<&&str>) {
+        match (action, path) {
+            (Some(&"start"), None) => {
+                self.simulator.start_input_recording();
+                println!("Recording inputs");
+            }
+            (Some(&"stop"), Some(path)) => match self.simulator.stop_input_recording() {
+                Some(log) => match log.save_file(path) {
+                    Ok(_) => println!("Saved {} input events to {}", log.len(), path),
+                    Err(e) => self.fail(format_args!("Error: {}", e)),
+                },
+                None => println!("Inputs are not being recorded"),
+            },
+            _ => self.fail(format_args!("Usage: record <start|stop <file>>")),
+        }
+    }
+    
+    fn cmd_replay(&mut self, arg: Option<&&str>) {
+        match arg {
+            Some(&"stop") => {
+                self.simulator.stop_replay();
+                println!("Replay stopped");
+            }
+            Some(path) => match self.simulator.load_replay_file(path) {
+                Ok(count) => println!("Replaying {} input events from {}", count, path),
+                Err(e) => self.fail(format_args!("Error: {}", e)),
+            },
+            None => match self.simulator.replay() {
+                Some(replay) => println!("Replaying: {} events remaining", replay.remaining()),
+                None => self.fail(format_args!("Usage: replay <file>|stop")),
+            },
+        }
+    }
+    
+    fn cmd_halt(&mut self, arg: Option<&&str>, state_str: Option<&&str>) {
+        let mut policy = *self.simulator.halt_policy();
+        match (arg, state_str) {
+            (None, _) => {}
+            (Some(&"strict"), None) => policy = HaltPolicy::strict(),
+            (Some(&"default"), None) => policy = HaltPolicy::default(),
+            (Some(name), Some(&("on" | "off"))) => {
+                if let Err(e) = policy.set(name, state_str == Some(&"on")) {
+                    self.fail(format_args!("Error: {} (use {})", e, HaltPolicy::NAMES.join(", ")));
+                    return;
+                }
+            }
+            _ => {
+                self.fail(format_args!("Usage: halt [strict|default|<condition> <on|off>]"));
+                return;
+            }
+        }
+        self.simulator.set_halt_policy(policy);
+        
+        println!("Halt on:");
+        for name in HaltPolicy::NAMES {
+            let enabled = policy.get(name).unwrap_or(false);
+            println!("  {:<14} {}", name, if enabled { "on" } else { "off" });
+        }
+    }
+    
+    fn cmd_logic(&mut self, arg: Option<&&str>, count_str: Option<&&str>) {
+        match arg {
+            Some(&"clear") => {
+                self.simulator.logic_analyzer_mut().clear();
+                println!("Logic analyzer capture cleared");
+            }
+            Some(pin_str) => {
+                if let Some(pin) = self.parse_pin(pin_str) {
+                    let count = count_str
+                        .and_then(|s| s.parse().ok())
+                        .unwrap_or(10);
+                    Debugger::display_pin_capture(self.simulator.logic_analyzer(), pin, count);
+                }
+            }
+            None => {
+                self.fail(format_args!("Usage: logic <pin> [n] | logic clear"));
+                println!("{} transitions captured", self.simulator.logic_analyzer().len());
+            }
+        }
+    }
+    
+    fn cmd_list(&self, location: Option<&&str>) {
+        let symbols = self.simulator.symbols();
+        let pc = self.simulator.cpu().get_pc();
+        let current = symbols.location_of(pc);
+        
+        let target = match location {
+            None => current.map(|loc| (loc.file.clone(), loc.line)),
+            Some(s) => match parse_file_line(s) {
+                Some((file, line)) => Some((file.to_string(), line)),
+                None => self.resolve_address(s)
+                    .and_then(|addr| symbols.location_of(addr))
+                    .map(|loc| (loc.file.clone(), loc.line)),
+            },
+        };
+        
+        match target {
+            Some((file, line)) => {
+                let current_line = current
+                    .filter(|loc| loc.file == file)
+                    .map(|loc| loc.line);
+                Debugger::display_source(symbols, &file, line, current_line);
+            }
+            None => println!("No source information (load a listing with 'symbols <file.lst>')"),
+        }
+    }
+    
+    /// Resolve a label, file:line or hex address
+    fn resolve_address(&self, s: &str) -> Option<u16> {
+        let symbols = self.simulator.symbols();
+        if let Some((file, line)) = parse_file_line(s) {
+            return symbols.address_of_line(file, line);
+        }
+        
+        symbols.address_of(s)
+            .or_else(|| parse_hex(s).ok().map(|addr| addr as u16))
+    }
+    
+    fn cmd_coverage(&mut self, action: Option<&&str>, path: Option<&&str>) {
+        match action {
+            None | Some(&"report") => {
+                print!("{}", self.simulator.coverage().to_text());
+            }
+            Some(&"json") => {
+                let json = self.simulator.coverage().to_json();
+                match path {
+                    Some(path) => match std::fs::write(path, json) {
+                        Ok(_) => println!("Coverage written to {}", path),
+                        Err(e) => self.fail(format_args!("Error: {}", e)),
+                    },
+                    None => println!("{}", json),
+                }
+            }
+            Some(&"reset") => {
+                self.simulator.coverage_mut().clear();
+                println!("Coverage data cleared");
+            }
+            _ => self.fail(format_args!("Usage: coverage [report|json [file]|reset]")),
+        }
+    }
 }
 
 impl Default for Cli {
@@ -294,8 +1784,327 @@ impl Default for Cli {
     }
 }
 
+/// REPL history file in the home directory
+#[cfg(feature = "readline")]
+fn history_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(Path::new(&home).join(".pic_simulator_history"))
+}
+
+/// Aliases and macros file in the home directory
+fn macros_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(Path::new(&home).join(".pic_simulator_macros"))
+}
+
+/// `text` without its first word and the whitespace after it
+fn after_first_word(text: &str) -> Option<&str> {
+    let (_, rest) = text.trim_start().split_once(char::is_whitespace)?;
+    Some(rest.trim_start()).filter(|rest| !rest.is_empty())
+}
+
+/// Name and body of `alias <name> <body>` or `define <name> <body>`
+fn split_definition(input: &str) -> Option<(&str, Option<&str>)> {
+    let definition = after_first_word(input)?;
+    let name = definition.split_whitespace().next()?;
+    Some((name, after_first_word(definition)))
+}
+
+/// Parse a `file:line` location
+fn parse_file_line(s: &str) -> Option<(&str, usize)> {
+    let (file, line) = s.rsplit_once(':')?;
+    let line = line.parse().ok()?;
+    (!file.is_empty()).then_some((file, line))
+}
+
+/// Parse a frequency such as `4MHz`, `32.768kHz` or `20000000`
+fn parse_frequency(s: &str) -> Option<u32> {
+    let lower = s.to_ascii_lowercase();
+    let number = lower.strip_suffix("hz").unwrap_or(&lower);
+    let (number, scale) = match number.chars().last()? {
+        'k' => (&number[..number.len() - 1], 1e3),
+        'm' => (&number[..number.len() - 1], 1e6),
+        _ => (number, 1.0),
+    };
+    let hz = number.parse::<f64>().ok()? * scale;
+    (hz >= 1.0 && hz <= u32::MAX as f64).then_some(hz.round() as u32)
+}
+
+/// Parse a simulated time such as "10ms", "500us", "2.5s" or "100ns"
+fn parse_duration(s: &str) -> Option<Duration> {
+    let lower = s.to_ascii_lowercase();
+    let (number, scale) = [("ns", 1e-9), ("us", 1e-6), ("µs", 1e-6), ("ms", 1e-3), ("s", 1.0)]
+        .iter()
+        .find_map(|&(suffix, scale)| lower.strip_suffix(suffix).map(|n| (n, scale)))?;
+    let secs = number.parse::<f64>().ok()? * scale;
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// Event observer printing each GPIO pin transition with the cycle it
+/// happened at
+fn gpio_logger() -> impl FnMut(&SimEvent) + 'static {
+    let mut cycle = 0;
+    move |event| match *event {
+        SimEvent::InstructionExecuted { cycle: start, cycles, .. } => cycle = start + cycles as u64,
+        SimEvent::GpioChanged { old, new } => {
+            for pin in (0..6).filter(|pin| (old ^ new) & (1 << pin) != 0) {
+                let level = |value: u8| if value & (1 << pin) != 0 { "HIGH" } else { "LOW" };
+                println!("[{}] GP{}: {} → {}", cycle, pin, level(old), level(new));
+            }
+        }
+        _ => {}
+    }
+}
+
 /// Parse hex string (with or without 0x prefix)
 fn parse_hex(s: &str) -> Result<u32, std::num::ParseIntError> {
     let s = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
     u32::from_str_radix(s, 16)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_script() {
+        let dir = std::env::temp_dir();
+        let script = dir.join(format!("pic_cli_script_{}.cmd", std::process::id()));
+        std::fs::write(&script, "\
+# Load two words and step them
+load 3042 00A0
+step 2
+bogus
+pc 0
+").unwrap();
+
+        let mut cli = Cli::new();
+        assert!(!cli.source(&script, false, false));
+        assert_eq!(cli.simulator.cpu().read_register(0x20), 0x42);
+        assert_eq!(cli.simulator.cpu().get_pc(), 0);
+
+        // Stopping at the failed command leaves the PC where the steps left it
+        let mut cli = Cli::new();
+        assert!(!cli.source(&script, true, true));
+        assert_eq!(cli.simulator.cpu().get_pc(), 2);
+
+        // A script sourcing itself stops at the nesting limit
+        std::fs::write(&script, format!("source {}\n", script.display())).unwrap();
+        assert!(!Cli::new().source(&script, false, true));
+        std::fs::remove_file(&script).unwrap();
+    }
+
+    #[test]
+    fn test_set_command() {
+        let mut cli = Cli::new();
+        assert!(cli.handle_command("set w 0x20"));
+        assert!(cli.handle_command("set pc 0x100"));
+        assert!(cli.handle_command("set reg STATUS 0x18"));
+        assert!(cli.handle_command("set mem 0x25 0xFF"));
+        let cpu = cli.simulator.cpu();
+        assert_eq!(cpu.read_w(), 0x20);
+        assert_eq!(cpu.get_pc(), 0x100);
+        assert_eq!(cpu.read_register(0x03), 0x18);
+        // STATUS is mirrored in bank 1
+        assert_eq!(cpu.read_register(0x83), 0x18);
+        assert_eq!(cpu.read_register(0x25), 0xFF);
+
+        assert!(!cli.handle_command("set w 0x100"));
+        assert!(!cli.handle_command("set reg BOGUS 1"));
+        assert!(!cli.handle_command("set mem 0x70 1"));
+        assert!(!cli.handle_command("set x 1"));
+    }
+
+    #[test]
+    fn test_reset_command() {
+        let mut cli = Cli::new();
+        cli.handle_command("set mem 0x20 0x42");
+        assert!(cli.handle_command("reset mclr"));
+        assert_eq!(cli.simulator.cpu().read_register(0x20), 0x42);
+        assert!(cli.handle_command("reset"));
+        assert_eq!(cli.simulator.cpu().read_register(0x20), 0x00);
+        assert!(!cli.handle_command("reset cold"));
+    }
+
+    #[test]
+    fn test_gpio_commands() {
+        let mut cli = Cli::new();
+        assert!(cli.handle_command("gpio tris 0x3E"));
+        assert_eq!(cli.simulator.cpu().gpio().read_tris(), 0x3E);
+        assert!(!cli.handle_command("gpio tris 0x100"));
+
+        cli.simulator.load_program(&[0x2800]); // GOTO 0
+        cli.handle_command("setpin 2 1");
+        assert!(cli.handle_command("gpio pulse 2 low 10"));
+        cli.simulator.run_n_cycles(5).unwrap();
+        assert!(!cli.simulator.cpu().gpio().get_external_pin(2));
+        cli.simulator.run_n_cycles(10).unwrap();
+        assert!(cli.simulator.cpu().gpio().get_external_pin(2));
+        assert!(!cli.handle_command("gpio pulse 7 low 10"));
+        assert!(!cli.handle_command("setpin 8 1"));
+
+        assert!(cli.handle_command("gpio log on"));
+        assert!(cli.gpio_log.is_some());
+        assert!(cli.handle_command("gpio log off"));
+        assert!(cli.gpio_log.is_none());
+    }
+
+    #[test]
+    fn test_aliases_and_macros() {
+        let mut cli = Cli::new();
+        cli.simulator.load_program(&[0x3005, 0x00A0, 0x2800]); // MOVLW 5; MOVWF 0x20; GOTO 0
+        assert!(cli.handle_command("alias sw set w"));
+        assert!(cli.handle_command("sw 0x42"));
+        assert_eq!(cli.simulator.cpu().read_w(), 0x42);
+
+        assert!(cli.handle_command("define two step; step"));
+        assert!(cli.handle_command("two"));
+        assert_eq!(cli.simulator.cpu().get_pc(), 2);
+        assert_eq!(cli.simulator.cpu().read_register(0x20), 0x05);
+
+        // A failing command fails the macro
+        assert!(cli.handle_command("define bad step; set x 1; step"));
+        assert!(!cli.handle_command("bad"));
+        assert_eq!(cli.simulator.cpu().get_pc(), 0);
+        assert!(cli.handle_command("define loop loop"));
+        assert!(!cli.handle_command("loop"));
+        assert!(!cli.handle_command("alias step run"));
+
+        assert!(cli.handle_command("unalias sw"));
+        assert!(!cli.handle_command("sw 1"));
+    }
+
+    #[test]
+    fn test_print_command() {
+        let mut cli = Cli::new();
+        cli.handle_command("set reg TMR1H 0x12");
+        cli.handle_command("set reg TMR1L 0x34");
+        assert!(cli.handle_command("print/x (TMR1H<<8)|TMR1L"));
+        assert!(cli.handle_command("p mem[0x20]+W"));
+        assert!(!cli.handle_command("print/q W"));
+        assert!(!cli.handle_command("print"));
+        assert!(!cli.handle_command("print (W"));
+    }
+
+    #[test]
+    fn test_watch_command() {
+        let mut cli = Cli::new();
+        assert!(cli.handle_command("watch add [0x20] + W"));
+        assert!(cli.handle_command("watch add GPIO.GP2"));
+        assert!(!cli.handle_command("watch add (W"));
+        assert_eq!(cli.displays.len(), 2);
+        assert_eq!(cli.displays[0].to_string(), "[0x20] + W");
+
+        // Stepping prints the displays without failing the command
+        assert!(cli.handle_command("load 3042 00A0"));
+        assert!(cli.handle_command("step 2"));
+        assert_eq!(cli.displays[0].eval(&cli.simulator), 0x84);
+
+        assert!(cli.handle_command("watch delete 1"));
+        assert_eq!(cli.displays[0].to_string(), "GPIO.GP2");
+        assert!(!cli.handle_command("watch delete 5"));
+        assert!(cli.handle_command("watch delete all"));
+        assert!(cli.displays.is_empty());
+    }
+
+    #[test]
+    fn test_until_command() {
+        let mut cli = Cli::new();
+        // loop: incf 0x20, F; goto loop
+        assert!(cli.handle_command("load 0AA0 2800"));
+        assert!(cli.handle_command("until [0x20] == 5"));
+        assert_eq!(cli.simulator.cpu().read_register(0x20), 5);
+        assert!(cli.handle_command("until 0x0001"));
+        assert_eq!(cli.simulator.cpu().get_pc(), 1);
+
+        // A condition that never holds stops at the limit and fails
+        let cycles = cli.simulator.stats().cycles_elapsed;
+        assert!(!cli.handle_command("until W == 1 max 100"));
+        assert!(cli.simulator.stats().cycles_elapsed - cycles >= 100);
+        assert!(!cli.handle_command("until W == 1 max 10ms"));
+        assert!(!cli.handle_command("until W == 1 max soon"));
+        assert!(!cli.handle_command("until (W"));
+    }
+
+    #[test]
+    fn test_json_output() {
+        let mut cli = Cli::new();
+        assert!(cli.handle_command("set output json"));
+        assert!(cli.json);
+        for command in ["reg", "dump 0x20 4", "gpio", "info breakpoints", "info stats", "info config", "info calls"] {
+            assert!(cli.handle_command(command), "{}", command);
+        }
+        assert!(cli.handle_command("set output text"));
+        assert!(!cli.json);
+        assert!(!cli.handle_command("set output xml"));
+    }
+
+    #[test]
+    fn test_asm_command() {
+        let mut cli = Cli::new();
+        // movlw 0x42; goto 0
+        assert!(cli.handle_command("load 3042 2800"));
+        assert!(cli.handle_command("asm 0 movlw 0x17"));
+        assert!(cli.handle_command("asm 1 nop"));
+        assert_eq!(cli.simulator.cpu().memory().read_program(0), 0x3017);
+        assert!(cli.handle_command("step 2"));
+        assert_eq!(cli.simulator.cpu().read_w(), 0x17);
+        assert_eq!(cli.simulator.cpu().get_pc(), 2);
+
+        assert!(!cli.handle_command("asm 0 frob 1"));
+        assert!(!cli.handle_command("asm zz nop"));
+        assert!(!cli.handle_command("asm 0"));
+    }
+
+    #[test]
+    fn test_break_save_and_load() {
+        let path = std::env::temp_dir().join(format!("pic_cli_breaks_{}.txt", std::process::id()));
+        let mut cli = Cli::new();
+        cli.simulator.load_asm_string("main_loop nop\n goto main_loop\n END\n", "main.asm").unwrap();
+        assert!(cli.handle_command("break main_loop"));
+        assert!(cli.handle_command("break 0x0001"));
+        assert!(cli.handle_command(&format!("break save {}", path.display())));
+        assert!(cli.handle_command("break clear"));
+        assert!(cli.simulator.breakpoints().is_empty());
+
+        // Labels are resolved again in the program loaded now
+        cli.simulator.load_asm_string(" nop\nmain_loop nop\n goto main_loop\n END\n", "main.asm").unwrap();
+        assert!(cli.handle_command(&format!("break load {}", path.display())));
+        let addresses: Vec<u16> = cli.simulator.breakpoints().iter().map(|bp| bp.address).collect();
+        assert_eq!(addresses, vec![0x0001]);
+
+        std::fs::write(&path, "missing on\n0x0002\n").unwrap();
+        assert!(!cli.handle_command(&format!("break load {}", path.display())));
+        assert_eq!(cli.simulator.breakpoints().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_step_info() {
+        let mut cli = Cli::new();
+        assert!(cli.handle_command("set stepinfo full"));
+        assert!(cli.step_info_full);
+        assert!(cli.handle_command("load 3042 00A0"));
+        assert!(cli.handle_command("step 2"));
+        assert!(cli.handle_command("set stepinfo brief"));
+        assert!(!cli.step_info_full);
+        assert!(!cli.handle_command("set stepinfo verbose"));
+    }
+
+    #[test]
+    fn test_trace_command() {
+        let path = std::env::temp_dir().join(format!("pic_cli_trace_{}.txt", std::process::id()));
+        let mut cli = Cli::new();
+        assert!(cli.handle_command("load 3042 00A0"));
+        assert!(cli.handle_command(&format!("trace start {} --regs", path.display())));
+        assert!(!cli.handle_command(&format!("trace start {}", path.display())));
+        assert!(cli.handle_command("step 2"));
+        assert!(cli.handle_command("trace stop"));
+        assert!(cli.trace_file.is_none());
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.contains("W 0x00->0x42"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+//! Oscillator and simulated time
+//!
+//! Reference: Section 9.2 - Oscillator Configurations (Page 36-39)
+//!
+//! One instruction cycle takes four oscillator periods (Fosc/4). The clock
+//! converts between cycles and simulated time for the stopwatch, the
+//! watchdog timeout and time-based execution. The WDT runs from its own
+//! on-chip RC oscillator, so its nominal 18 ms period corresponds to more
+//! instruction cycles at higher Fosc.
+
+use std::fmt;
+use std::time::Duration;
+
+/// Internal RC oscillator frequency
+pub const DEFAULT_FOSC_HZ: u32 = 4_000_000;
+
+/// Nominal WDT period without prescaler, in microseconds
+pub const WDT_NOMINAL_PERIOD_US: u64 = 18_000;
+
+/// Oscillator mode, selected by FOSC<2:0> in the configuration word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClockSource {
+    /// Low-power crystal (typically 32.768 kHz)
+    Lp,
+    /// Crystal/resonator up to 4 MHz
+    Xt,
+    /// High-speed crystal/resonator up to 20 MHz
+    Hs,
+    /// External clock on CLKIN
+    Ec,
+    /// Internal 4 MHz RC oscillator
+    IntRc,
+    /// External RC network
+    ExtRc,
+}
+
+impl ClockSource {
+    /// Decode FOSC<2:0> from a configuration word
+    pub fn from_config_word(config: u16) -> Self {
+        match config & 0x07 {
+            0b000 => ClockSource::Lp,
+            0b001 => ClockSource::Xt,
+            0b010 => ClockSource::Hs,
+            0b011 => ClockSource::Ec,
+            0b100 | 0b101 => ClockSource::IntRc,
+            _ => ClockSource::ExtRc,
+        }
+    }
+
+    /// Short name accepted by `from_name`
+    pub fn name(self) -> &'static str {
+        match self {
+            ClockSource::Lp => "lp",
+            ClockSource::Xt => "xt",
+            ClockSource::Hs => "hs",
+            ClockSource::Ec => "ec",
+            ClockSource::IntRc => "intrc",
+            ClockSource::ExtRc => "extrc",
+        }
+    }
+
+    /// Parse a short name such as `hs` (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "lp" => Some(ClockSource::Lp),
+            "xt" => Some(ClockSource::Xt),
+            "hs" => Some(ClockSource::Hs),
+            "ec" => Some(ClockSource::Ec),
+            "intrc" => Some(ClockSource::IntRc),
+            "extrc" => Some(ClockSource::ExtRc),
+            _ => None,
+        }
+    }
+
+    /// Typical frequency for this mode, used until one is set explicitly
+    pub fn default_hz(self) -> u32 {
+        match self {
+            ClockSource::Lp => 32_768,
+            ClockSource::Hs | ClockSource::Ec => 20_000_000,
+            ClockSource::Xt | ClockSource::IntRc | ClockSource::ExtRc => DEFAULT_FOSC_HZ,
+        }
+    }
+}
+
+impl fmt::Display for ClockSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ClockSource::Lp => "LP crystal",
+            ClockSource::Xt => "XT crystal",
+            ClockSource::Hs => "HS crystal",
+            ClockSource::Ec => "external clock",
+            ClockSource::IntRc => "internal RC",
+            ClockSource::ExtRc => "external RC",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Oscillator source and frequency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Clock {
+    source: ClockSource,
+    fosc_hz: u32,
+}
+
+impl Clock {
+    /// Internal 4 MHz RC oscillator
+    pub fn new() -> Self {
+        Self::with_source(ClockSource::IntRc)
+    }
+
+    /// Clock running at the typical frequency of `source`
+    pub fn with_source(source: ClockSource) -> Self {
+        Self { source, fosc_hz: source.default_hz() }
+    }
+
+    /// Clock selected by a configuration word
+    pub fn from_config_word(config: u16) -> Self {
+        Self::with_source(ClockSource::from_config_word(config))
+    }
+
+    pub fn source(&self) -> ClockSource {
+        self.source
+    }
+
+    /// Oscillator frequency in Hz
+    pub fn fosc_hz(&self) -> u32 {
+        self.fosc_hz
+    }
+
+    /// Set the oscillator frequency (e.g. the fitted crystal), minimum 1 Hz
+    pub fn set_fosc_hz(&mut self, hz: u32) {
+        self.fosc_hz = hz.max(1);
+    }
+
+    /// Instruction cycles per second (Fosc/4)
+    pub fn cycles_per_second(&self) -> f64 {
+        self.fosc_hz as f64 / 4.0
+    }
+
+    /// Simulated time of `cycles` instruction cycles in microseconds
+    pub fn cycles_to_micros(&self, cycles: u64) -> f64 {
+        cycles as f64 * 4_000_000.0 / self.fosc_hz as f64
+    }
+
+    /// Simulated time of `cycles` instruction cycles
+    pub fn cycles_to_duration(&self, cycles: u64) -> Duration {
+        let nanos = cycles as u128 * 4_000_000_000 / self.fosc_hz as u128;
+        Duration::from_nanos(nanos.min(u64::MAX as u128) as u64)
+    }
+
+    /// Whole instruction cycles that fit in `micros` microseconds
+    pub fn micros_to_cycles(&self, micros: u64) -> u64 {
+        let cycles = micros as u128 * self.fosc_hz as u128 / 4_000_000;
+        cycles.min(u64::MAX as u128) as u64
+    }
+
+    /// Whole instruction cycles that fit in `duration`
+    pub fn duration_to_cycles(&self, duration: Duration) -> u64 {
+        let cycles = duration.as_nanos() * self.fosc_hz as u128 / 4_000_000_000;
+        cycles.min(u64::MAX as u128) as u64
+    }
+
+    /// Nominal WDT period without prescaler, in instruction cycles
+    pub fn wdt_period_cycles(&self) -> u32 {
+        self.micros_to_cycles(WDT_NOMINAL_PERIOD_US).clamp(1, u32::MAX as u64) as u32
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let mut clock = Clock::new();
+        assert_eq!(clock.cycles_to_micros(1000), 1000.0);
+        assert_eq!(clock.cycles_to_duration(1000), Duration::from_millis(1));
+        assert_eq!(clock.wdt_period_cycles(), 18_000);
+
+        clock.set_fosc_hz(20_000_000);
+        assert_eq!(clock.cycles_to_micros(5), 1.0);
+        assert_eq!(clock.micros_to_cycles(10_000), 50_000);
+        assert_eq!(clock.duration_to_cycles(Duration::from_millis(1)), 5_000);
+        assert_eq!(clock.wdt_period_cycles(), 90_000);
+    }
+
+    #[test]
+    fn test_config_word() {
+        assert_eq!(Clock::from_config_word(0x3FC4).source(), ClockSource::IntRc);
+        assert_eq!(Clock::from_config_word(0x3FC4).fosc_hz(), 4_000_000);
+
+        let hs = Clock::from_config_word(0x3FF2);
+        assert_eq!(hs.source(), ClockSource::Hs);
+        assert_eq!(hs.fosc_hz(), 20_000_000);
+        assert_eq!(Clock::from_config_word(0x0000).fosc_hz(), 32_768);
+    }
+}
@@ -0,0 +1,389 @@
+//! Microchip COFF (.cof) loader
+//!
+//! MPLINK and gplink write linked programs as COFF files with the code,
+//! config and EEPROM sections, the symbol table and per-section line
+//! number tables. Both the original (magic 0x1234) and the MPLAB 8+
+//! (magic 0x1240) layouts are read:
+//!
+//! | Part            | Original | v2 | Notes                                   |
+//! |-----------------|----------|----|-----------------------------------------|
+//! | File header     | 20       | 20 | magic, sections, symbol table offset    |
+//! | Optional header | 16       | 18 | size given by the file header           |
+//! | Section header  | 40       | 40 | byte address, size, data and line ptrs  |
+//! | Symbol          | 18       | 20 | v2 widens the type field to 32 bits     |
+//! | Line number     | 16       | 16 | source `.file` symbol, line, address    |
+//!
+//! Section, symbol and line addresses are byte addresses laid out as in
+//! Intel HEX files, so sections are placed exactly like HEX records and
+//! labels and source lines land at half their address.
+
+use std::path::Path;
+
+use crate::error::CoffError;
+use crate::hexloader::{HexProgram, ImageBuilder, DEFAULT_PROGRAM_WORDS};
+use crate::symbols::SymbolTable;
+
+/// File header magic of the original Microchip COFF layout
+pub const MAGIC_V1: u16 = 0x1234;
+
+/// File header magic of the MPLAB 8+ COFF layout
+pub const MAGIC_V2: u16 = 0x1240;
+
+const FILE_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+const LINE_NUMBER_SIZE: usize = 16;
+
+/// Section holds program memory code
+const STYP_TEXT: u32 = 0x0020;
+/// Section holds initialized data in program memory
+const STYP_DATA_ROM: u32 = 0x0100;
+
+/// External, static and label symbol classes
+const C_EXT: u8 = 2;
+const C_STAT: u8 = 3;
+const C_LABEL: u8 = 6;
+/// Source file symbol; its auxiliary entry names the file
+const C_FILE: u8 = 103;
+
+/// Program image, labels and source lines read from a COFF file
+#[derive(Debug, Clone)]
+pub struct CoffFirmware {
+    pub program: HexProgram,
+
+    /// Code labels and source line locations, at word addresses
+    pub symbols: SymbolTable,
+
+    /// Source files named by the line number tables
+    pub source_files: Vec<String>,
+}
+
+/// Whether `data` starts with a Microchip COFF magic number
+pub fn is_coff(data: &[u8]) -> bool {
+    data.len() >= 2 && matches!(u16::from_le_bytes([data[0], data[1]]), MAGIC_V1 | MAGIC_V2)
+}
+
+/// Little-endian field reader over the file contents
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize, what: &'static str) -> Result<&'a [u8], CoffError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(CoffError::Truncated(what))
+    }
+
+    fn u16(&self, offset: usize, what: &'static str) -> Result<u16, CoffError> {
+        let b = self.bytes(offset, 2, what)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&self, offset: usize, what: &'static str) -> Result<u32, CoffError> {
+        let b = self.bytes(offset, 4, what)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+/// A symbol table entry
+struct Symbol {
+    name: String,
+    value: u32,
+    section: i16,
+    class: u8,
+    /// Offset of the first auxiliary entry
+    aux_offset: usize,
+}
+
+/// The symbol and string tables
+struct SymbolTableReader<'a> {
+    reader: &'a Reader<'a>,
+    offset: usize,
+    count: usize,
+    entry_size: usize,
+    strings: usize,
+}
+
+impl SymbolTableReader<'_> {
+    /// Read the NUL-terminated string at `offset` in the string table
+    fn string(&self, offset: u32) -> Result<String, CoffError> {
+        let start = self.strings + offset as usize;
+        let rest = self.reader.data.get(start..).ok_or(CoffError::Truncated("string table"))?;
+        let end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+
+    /// Read the symbol at `index`
+    fn symbol(&self, index: usize) -> Result<Symbol, CoffError> {
+        let offset = self.offset + index * self.entry_size;
+        let raw = self.reader.bytes(offset, self.entry_size, "symbol table")?;
+        let name = if raw[..4] == [0; 4] {
+            self.string(self.reader.u32(offset + 4, "symbol table")?)?
+        } else {
+            let end = raw[..8].iter().position(|&b| b == 0).unwrap_or(8);
+            String::from_utf8_lossy(&raw[..end]).into_owned()
+        };
+        Ok(Symbol {
+            name,
+            value: self.reader.u32(offset + 8, "symbol table")?,
+            section: self.reader.u16(offset + 12, "symbol table")? as i16,
+            class: raw[self.entry_size - 2],
+            aux_offset: offset + self.entry_size,
+        })
+    }
+
+    /// Iterate over the symbols, skipping their auxiliary entries
+    fn for_each(&self, mut f: impl FnMut(Symbol)) -> Result<(), CoffError> {
+        let mut index = 0;
+        while index < self.count {
+            let symbol = self.symbol(index)?;
+            let aux_count = self.reader.bytes(symbol.aux_offset - 1, 1, "symbol table")?[0] as usize;
+            f(symbol);
+            index += 1 + aux_count;
+        }
+        Ok(())
+    }
+
+    /// The source file named by the `.file` symbol at `index`
+    fn file_name(&self, index: usize) -> Result<Option<String>, CoffError> {
+        let symbol = self.symbol(index)?;
+        if symbol.class != C_FILE {
+            return Ok(None);
+        }
+        let offset = self.reader.u32(symbol.aux_offset, "symbol table")?;
+        self.string(offset).map(Some)
+    }
+}
+
+/// COFF file loader
+pub struct CoffLoader;
+
+impl CoffLoader {
+    /// Load a COFF file, warning about program data past 1K words
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<CoffFirmware, CoffError> {
+        Self::load_file_for(path, DEFAULT_PROGRAM_WORDS)
+    }
+
+    /// Load a COFF file for a device with `program_words` words of program memory
+    ///
+    /// Source files named by the line number tables are read, relative to
+    /// the COFF file when not absolute, so the source view can show them.
+    pub fn load_file_for<P: AsRef<Path>>(path: P, program_words: usize) -> Result<CoffFirmware, CoffError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(CoffError::Open)?;
+        let mut firmware = Self::load_from_bytes_for(&data, program_words)?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        for file in &firmware.source_files {
+            if let Ok(text) = std::fs::read_to_string(base.join(file)) {
+                for (number, line) in text.lines().enumerate() {
+                    firmware.symbols.add_source_line(file, number + 1, line);
+                }
+            }
+        }
+        Ok(firmware)
+    }
+
+    /// Load COFF file contents, warning about program data past 1K words
+    pub fn load_from_bytes(data: &[u8]) -> Result<CoffFirmware, CoffError> {
+        Self::load_from_bytes_for(data, DEFAULT_PROGRAM_WORDS)
+    }
+
+    /// Load COFF file contents for a device with `program_words` words of program memory
+    ///
+    /// Load warnings give the 1-based section number in place of a line number.
+    pub fn load_from_bytes_for(data: &[u8], program_words: usize) -> Result<CoffFirmware, CoffError> {
+        let reader = Reader { data };
+        let magic = reader.u16(0, "file header")?;
+        let symbol_size = match magic {
+            MAGIC_V1 => 18,
+            MAGIC_V2 => 20,
+            other => return Err(CoffError::BadMagic(other)),
+        };
+        let section_count = reader.u16(2, "file header")? as usize;
+        let symbols_offset = reader.u32(8, "file header")? as usize;
+        let symbol_count = reader.u32(12, "file header")? as usize;
+        let optional_size = reader.u16(16, "file header")? as usize;
+
+        let table = SymbolTableReader {
+            reader: &reader,
+            offset: symbols_offset,
+            count: symbol_count,
+            entry_size: symbol_size,
+            strings: symbols_offset + symbol_count * symbol_size,
+        };
+
+        let mut image = ImageBuilder::new(program_words);
+        let mut symbols = SymbolTable::new();
+        let mut code_sections = Vec::new();
+        let mut source_files = Vec::new();
+
+        let headers = FILE_HEADER_SIZE + optional_size;
+        for number in 1..=section_count {
+            let header = headers + (number - 1) * SECTION_HEADER_SIZE;
+            let address = reader.u32(header + 8, "section header")?;
+            let size = reader.u32(header + 16, "section header")? as usize;
+            let data_offset = reader.u32(header + 20, "section header")? as usize;
+            let lines_offset = reader.u32(header + 28, "section header")? as usize;
+            let line_count = reader.u16(header + 34, "section header")? as usize;
+            let flags = reader.u32(header + 36, "section header")?;
+
+            if flags & (STYP_TEXT | STYP_DATA_ROM) == 0 {
+                continue;
+            }
+            if flags & STYP_TEXT != 0 {
+                code_sections.push(number as i16);
+            }
+            if data_offset != 0 && size > 0 {
+                image.add_data(number, address, reader.bytes(data_offset, size, "section data")?);
+            }
+
+            for i in 0..line_count {
+                let entry = lines_offset + i * LINE_NUMBER_SIZE;
+                let source = reader.u16(entry, "line numbers")? as usize;
+                let line = reader.u16(entry + 2, "line numbers")? as usize;
+                let address = reader.u32(entry + 4, "line numbers")?;
+                if let Some(file) = table.file_name(source)? {
+                    symbols.add_line((address / 2) as u16, &file, line);
+                    if !source_files.contains(&file) {
+                        source_files.push(file);
+                    }
+                }
+            }
+        }
+
+        table.for_each(|symbol| {
+            let is_label = matches!(symbol.class, C_EXT | C_STAT | C_LABEL);
+            if is_label && code_sections.contains(&symbol.section) && !symbol.name.starts_with('.') {
+                symbols.add_label(&symbol.name, (symbol.value / 2) as u16);
+            }
+        })?;
+
+        Ok(CoffFirmware {
+            program: image.finish(None),
+            symbols,
+            source_files,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Append a little-endian u16/u32 to `out`
+    fn put16(out: &mut Vec<u8>, value: u16) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn put32(out: &mut Vec<u8>, value: u32) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// A v2 symbol entry with an inline name or a string table offset
+    fn symbol(out: &mut Vec<u8>, name: Result<&str, u32>, value: u32, section: i16, class: u8, aux: u8) {
+        match name {
+            Ok(name) => {
+                let mut raw = [0u8; 8];
+                raw[..name.len()].copy_from_slice(name.as_bytes());
+                out.extend_from_slice(&raw);
+            }
+            Err(offset) => {
+                put32(out, 0);
+                put32(out, offset);
+            }
+        }
+        put32(out, value);
+        put16(out, section as u16);
+        put32(out, 0);
+        out.push(class);
+        out.push(aux);
+    }
+
+    /// Build a v2 COFF with a code section at 0, a config section, a
+    /// `.file` symbol for main.asm and labels `start` and `long_label_name`
+    fn build_coff() -> Vec<u8> {
+        let code: &[u8] = &[0x05, 0x28, 0xFF, 0x3F, 0xFF, 0x3F, 0xFF, 0x3F, 0x09, 0x00, 0x55, 0x30];
+        let config: &[u8] = &[0x84, 0x31];
+        let optional_size = 18;
+        let sections_offset = FILE_HEADER_SIZE + optional_size;
+        let code_offset = sections_offset + 2 * SECTION_HEADER_SIZE;
+        let config_offset = code_offset + code.len();
+        let lines_offset = config_offset + config.len();
+        let symbols_offset = lines_offset + 2 * LINE_NUMBER_SIZE;
+
+        let mut out = Vec::new();
+        put16(&mut out, MAGIC_V2);
+        put16(&mut out, 2);
+        put32(&mut out, 0);
+        put32(&mut out, symbols_offset as u32);
+        put32(&mut out, 4);
+        put16(&mut out, optional_size as u16);
+        put16(&mut out, 0);
+        out.resize(sections_offset, 0);
+
+        let sections = [
+            (".code", 0x0000, code.len(), code_offset, lines_offset, 2, STYP_TEXT),
+            (".config", 0x400E, config.len(), config_offset, 0, 0, STYP_TEXT),
+        ];
+        for (name, address, size, data, lines, line_count, flags) in sections {
+            let mut raw = [0u8; 8];
+            raw[..name.len()].copy_from_slice(name.as_bytes());
+            out.extend_from_slice(&raw);
+            put32(&mut out, address);
+            put32(&mut out, address);
+            put32(&mut out, size as u32);
+            put32(&mut out, data as u32);
+            put32(&mut out, 0);
+            put32(&mut out, lines as u32);
+            put16(&mut out, 0);
+            put16(&mut out, line_count);
+            put32(&mut out, flags);
+        }
+        out.extend_from_slice(code);
+        out.extend_from_slice(config);
+
+        for (line, address) in [(12, 0x0000), (20, 0x000A)] {
+            put16(&mut out, 0);
+            put16(&mut out, line);
+            put32(&mut out, address);
+            put16(&mut out, 0);
+            put32(&mut out, 0);
+            put16(&mut out, 0);
+        }
+
+        // .file with its aux entry, then two labels
+        symbol(&mut out, Ok(".file"), 0, -2, C_FILE, 1);
+        put32(&mut out, 4);
+        out.resize(out.len() + 16, 0);
+        symbol(&mut out, Ok("start"), 0x000A, 1, C_LABEL, 0);
+        symbol(&mut out, Err(13), 0x0008, 1, C_EXT, 0);
+
+        let strings = b"main.asm\0long_label_name\0";
+        put32(&mut out, 4 + strings.len() as u32);
+        out.extend_from_slice(strings);
+        out
+    }
+
+    #[test]
+    fn test_load_coff() {
+        let data = build_coff();
+        assert!(is_coff(&data));
+        assert!(!is_coff(b"\x7FELF"));
+
+        let firmware = CoffLoader::load_from_bytes(&data).unwrap();
+        assert_eq!(firmware.program.program, vec![0x2805, 0x3FFF, 0x3FFF, 0x3FFF, 0x0009, 0x3055]);
+        assert_eq!(firmware.program.config, Some(0x3184));
+        assert_eq!(firmware.symbols.address_of("start"), Some(0x005));
+        assert_eq!(firmware.symbols.label_at(0x004), Some("long_label_name"));
+
+        let location = firmware.symbols.location_of(0x005).unwrap();
+        assert_eq!((location.file.as_str(), location.line), ("main.asm", 20));
+        assert_eq!(firmware.symbols.address_of_line("main.asm", 12), Some(0x000));
+        assert_eq!(firmware.source_files, vec!["main.asm"]);
+
+        assert!(matches!(CoffLoader::load_from_bytes(&data[..30]), Err(CoffError::Truncated(_))));
+        assert!(matches!(CoffLoader::load_from_bytes(&[0x00, 0x00]), Err(CoffError::BadMagic(0))));
+    }
+}
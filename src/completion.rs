@@ -0,0 +1,235 @@
+//! Tab completion for the `pic>` prompt
+//!
+//! The first word completes to a command name. Arguments complete by
+//! command: register names for `sfrbreak` and `print`, set breakpoint
+//! addresses for `delete`, program labels for commands that take a
+//! location, and file names for commands that load or save files. The candidates are taken
+//! from the simulator before each prompt, so labels from newly loaded
+//! firmware and new breakpoints show up straight away.
+
+use crate::Simulator;
+
+/// Command names offered at the start of a line (short aliases are left out)
+pub const COMMANDS: &[&str] = &[
+    "help", "reset", "step", "rstep", "run", "continue", "until", "break", "delete", "sfrbreak",
+    "stopwatch", "fosc", "state", "info", "disasm", "asm", "dump", "load", "loadhex", "loadasm",
+    "export", "verify", "reg", "pc", "set", "print", "watch", "gpio", "setpin", "interrupt",
+    "bt", "backtrace", "profile", "coverage", "symbols", "list", "vcd", "trace", "stim",
+    "logic", "circuit", "golden", "record", "replay", "halt", "power", "wdt", "device",
+    "source", "alias", "unalias", "define", "undefine", "quit", "exit",
+];
+
+/// Commands whose arguments are file names
+const FILE_COMMANDS: &[&str] = &[
+    "loadhex", "loadasm", "source", "export", "verify", "symbols", "state", "vcd", "trace",
+    "circuit", "golden", "record", "replay",
+];
+
+/// Commands that take a program location
+const LOCATION_COMMANDS: &[&str] = &[
+    "until", "u", "break", "b", "stopwatch", "sw", "list", "l", "disasm", "asm", "pc",
+];
+
+/// What the argument under the cursor completes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completion {
+    /// Words from the candidate lists
+    Words,
+    /// A file name
+    File,
+}
+
+/// Candidate words, refreshed from the simulator before each prompt
+#[derive(Debug, Clone, Default)]
+pub struct Completions {
+    registers: Vec<String>,
+    labels: Vec<String>,
+    breakpoints: Vec<String>,
+    /// Alias and macro names
+    user_commands: Vec<String>,
+}
+
+impl Completions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take register names, labels and breakpoints from the simulator
+    pub fn refresh(&mut self, simulator: &Simulator) {
+        self.registers = simulator.device().model().sfr_names()
+            .map(|(_, name)| name.to_string())
+            .collect();
+        self.labels = simulator.symbols().labels()
+            .map(|(_, name)| name.to_string())
+            .collect();
+        self.breakpoints = simulator.breakpoints().iter()
+            .map(|bp| format!("0x{:04X}", bp.address))
+            .collect();
+    }
+
+    /// Offer alias and macro names alongside the commands
+    pub fn set_user_commands(&mut self, names: Vec<String>) {
+        self.user_commands = names;
+    }
+
+    /// Start of the word being completed and the words it can complete to
+    ///
+    /// Returns `Completion::File` when the argument is a file name, which
+    /// the line editor completes from the file system.
+    pub fn complete(&self, line: &str, pos: usize) -> (usize, Completion, Vec<String>) {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &line[start..];
+        let previous: Vec<&str> = line[..start].split_whitespace().collect();
+
+        let candidates: Vec<&str> = match previous.as_slice() {
+            [] => COMMANDS.iter().copied().chain(self.user_commands.iter().map(String::as_str)).collect(),
+            ["unalias" | "undefine"] => self.user_commands.iter().map(String::as_str).collect(),
+            [command, ..] if FILE_COMMANDS.contains(command) && previous.len() > 1 => {
+                return (start, Completion::File, Vec::new());
+            }
+            ["gpio"] => vec!["show", "tris", "pulse", "log"],
+            ["gpio", "pulse", _] => vec!["low", "high"],
+            ["gpio", "log"] => vec!["on", "off"],
+            ["reset" | "r"] => vec!["por", "mclr", "wdt", "bor"],
+            ["state"] => vec!["save", "load"],
+            ["vcd"] => vec!["start", "stop"],
+            ["trace"] => vec!["start", "stop"],
+            ["circuit"] => vec!["load", "clear"],
+            ["golden"] => vec!["record", "check"],
+            ["record"] => vec!["start", "stop"],
+            [command] if FILE_COMMANDS.contains(command) => {
+                return (start, Completion::File, Vec::new());
+            }
+            ["break" | "b"] => {
+                let mut words = vec!["save", "load", "clear"];
+                words.extend(self.labels.iter().map(String::as_str));
+                words
+            }
+            ["break" | "b", "save" | "load"] => return (start, Completion::File, Vec::new()),
+            ["delete" | "d"] => self.breakpoints.iter().chain(&self.labels).map(String::as_str).collect(),
+            ["sfrbreak" | "sb"] => {
+                let mut words = vec!["delete"];
+                words.extend(self.registers.iter().map(String::as_str));
+                words
+            }
+            ["sfrbreak" | "sb", "delete" | "d"] => {
+                let mut words = vec!["all"];
+                words.extend(self.registers.iter().map(String::as_str));
+                words
+            }
+            ["sfrbreak" | "sb", _] => vec!["read", "write", "change", "access"],
+            ["watch"] => vec!["list", "add", "delete"],
+            ["watch", "add", ..] => self.registers.iter().chain(&self.labels).map(String::as_str).collect(),
+            ["set"] => vec!["w", "pc", "reg", "mem", "output", "stepinfo"],
+            ["set", "stepinfo"] => vec!["full", "brief"],
+            ["set", "output"] => vec!["json", "text"],
+            ["set", "reg"] => self.registers.iter().map(String::as_str).collect(),
+            ["set", "pc"] => self.labels.iter().map(String::as_str).collect(),
+            [command, ..] if matches!(command.split('/').next(), Some("print" | "p")) => {
+                self.registers.iter().chain(&self.labels).map(String::as_str).collect()
+            }
+            [command, ..] if LOCATION_COMMANDS.contains(command) => {
+                self.labels.iter().map(String::as_str).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        let lower = word.to_ascii_lowercase();
+        let mut matches: Vec<String> = candidates.into_iter()
+            .filter(|candidate| candidate.to_ascii_lowercase().starts_with(&lower))
+            .map(str::to_string)
+            .collect();
+        matches.dedup();
+        (start, Completion::Words, matches)
+    }
+}
+
+/// Line editor helper completing from `Completions` and the file system
+#[cfg(feature = "readline")]
+pub struct ReplHelper {
+    pub completions: Completions,
+    files: rustyline::completion::FilenameCompleter,
+}
+
+#[cfg(feature = "readline")]
+impl ReplHelper {
+    pub fn new() -> Self {
+        Self {
+            completions: Completions::new(),
+            files: rustyline::completion::FilenameCompleter::new(),
+        }
+    }
+}
+
+#[cfg(feature = "readline")]
+impl Default for ReplHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "readline")]
+impl rustyline::completion::Completer for ReplHelper {
+    type Candidate = rustyline::completion::Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
+        let (start, kind, words) = self.completions.complete(line, pos);
+        if kind == Completion::File {
+            return self.files.complete(line, pos, ctx);
+        }
+        let pairs = words.into_iter()
+            .map(|word| rustyline::completion::Pair { display: word.clone(), replacement: word })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+#[cfg(feature = "readline")]
+impl rustyline::hint::Hinter for ReplHelper {
+    type Hint = String;
+}
+
+#[cfg(feature = "readline")]
+impl rustyline::highlight::Highlighter for ReplHelper {}
+
+#[cfg(feature = "readline")]
+impl rustyline::validate::Validator for ReplHelper {}
+
+#[cfg(feature = "readline")]
+impl rustyline::Helper for ReplHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete() {
+        let mut sim = Simulator::new();
+        sim.load_asm_string("start goto loop\nloop goto start\n END\n", "test.asm").unwrap();
+        sim.add_breakpoint(0x0001);
+        let mut completions = Completions::new();
+        completions.refresh(&sim);
+
+        assert_eq!(completions.complete("loa", 3), (0, Completion::Words, vec![
+            "load".to_string(), "loadhex".to_string(), "loadasm".to_string(),
+        ]));
+        assert_eq!(completions.complete("sb gp", 5).2, vec!["GPIO".to_string()]);
+        assert_eq!(completions.complete("sb GPIO w", 9).2, vec!["write".to_string()]);
+        assert_eq!(completions.complete("delete ", 7).2, vec![
+            "0x0001".to_string(), "start".to_string(), "loop".to_string(),
+        ]);
+        assert_eq!(completions.complete("break lo", 8), (6, Completion::Words, vec![
+            "load".to_string(), "loop".to_string(),
+        ]));
+        assert_eq!(completions.complete("loadhex fw", 10).1, Completion::File);
+        assert_eq!(completions.complete("state save sn", 13).1, Completion::File);
+        assert_eq!(completions.complete("set reg STA", 11).2, vec!["STATUS".to_string()]);
+        assert!(completions.complete("step ", 5).2.is_empty());
+    }
+}
@@ -0,0 +1,763 @@
+//! External components
+//!
+//! A `Component` is a piece of the virtual circuit around the MCU: an LED,
+//! a button, a sensor or a bus peripheral. Components are attached to a
+//! `Simulator`, which ticks them after every instruction with the cycles
+//! it consumed. During a tick a component reads the pins the MCU drives
+//! and sets the external level of the pins it drives itself.
+
+use std::any::Any;
+use std::fmt;
+
+#[cfg(feature = "gui")]
+use eframe::egui;
+
+use crate::clock::Clock;
+use crate::cpu::Cpu;
+use crate::gpio::{pin_label, PinState, VDD};
+
+/// Handle used to look up or detach a component
+pub type ComponentId = usize;
+
+/// Something connected to the MCU's pins
+pub trait Component {
+    /// Display name, e.g. "LED1"
+    fn name(&self) -> &str;
+
+    /// GPIO pins the component is connected to
+    fn pins(&self) -> Vec<u8>;
+
+    /// Advance by `cycles` instruction cycles
+    fn tick(&mut self, cycles: u64, cpu: &mut Cpu);
+
+    /// Return to the power-on state (called on simulator reset)
+    fn reset(&mut self) {}
+
+    /// Take on the simulator clock, for components with real-time timing
+    /// (called on attach and whenever the clock changes)
+    fn set_clock(&mut self, _clock: &Clock) {}
+
+    /// One-line state summary for text front-ends
+    fn describe(&self) -> String {
+        self.name().to_string()
+    }
+
+    /// Draw the component in the GUI; defaults to the text summary
+    #[cfg(feature = "gui")]
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(self.describe());
+    }
+
+    /// Access the concrete type, for `Simulator::component_as`
+    fn as_any(&self) -> &dyn Any;
+
+    /// Mutable access to the concrete type
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Copy the component with its current state, for history snapshots
+    fn clone_box(&self) -> Box<dyn Component>;
+}
+
+/// Attached components
+#[derive(Default)]
+pub struct Components {
+    next_id: ComponentId,
+    entries: Vec<(ComponentId, Box<dyn Component>)>,
+}
+
+impl Clone for Components {
+    fn clone(&self) -> Self {
+        Self {
+            next_id: self.next_id,
+            entries: self.entries.iter()
+                .map(|(id, component)| (*id, component.clone_box()))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Debug for Components {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().map(|(id, component)| (id, component.name())))
+            .finish()
+    }
+}
+
+impl Components {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn attach(&mut self, component: Box<dyn Component>) -> ComponentId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push((id, component));
+        id
+    }
+
+    pub fn detach(&mut self, id: ComponentId) -> Option<Box<dyn Component>> {
+        let index = self.entries.iter().position(|(entry, _)| *entry == id)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn get(&self, id: ComponentId) -> Option<&dyn Component> {
+        self.entries.iter()
+            .find(|(entry, _)| *entry == id)
+            .map(|(_, component)| component.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: ComponentId) -> Option<&mut (dyn Component + 'static)> {
+        self.entries.iter_mut()
+            .find(|(entry, _)| *entry == id)
+            .map(|(_, component)| component.as_mut())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ComponentId, &dyn Component)> {
+        self.entries.iter().map(|(id, component)| (*id, component.as_ref()))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (ComponentId, &mut (dyn Component + 'static))> {
+        self.entries.iter_mut().map(|(id, component)| (*id, component.as_mut()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn tick(&mut self, cycles: u64, cpu: &mut Cpu) {
+        for (_, component) in &mut self.entries {
+            component.tick(cycles, cpu);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for (_, component) in &mut self.entries {
+            component.reset();
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: &Clock) {
+        for (_, component) in &mut self.entries {
+            component.set_clock(clock);
+        }
+    }
+
+    /// Put attached components back into the state they had in `saved`
+    ///
+    /// Components attached since `saved` was taken return to their power-on
+    /// state; the set of attached components is unchanged.
+    pub fn restore(&mut self, saved: &Components) {
+        for (id, component) in &mut self.entries {
+            match saved.get(*id) {
+                Some(old) => *component = old.clone_box(),
+                None => component.reset(),
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// An LED from a pin to ground (lit while the pin drives high)
+#[derive(Debug, Clone)]
+pub struct Led {
+    name: String,
+    pin: u8,
+    on: bool,
+    /// Cycles spent lit, for brightness of PWM-driven LEDs
+    on_cycles: u64,
+    total_cycles: u64,
+}
+
+impl Led {
+    pub fn new(name: &str, pin: u8) -> Self {
+        Self {
+            name: name.to_string(),
+            pin,
+            on: false,
+            on_cycles: 0,
+            total_cycles: 0,
+        }
+    }
+
+    /// Check if the LED is currently lit
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Fraction of time lit since reset (0.0 - 1.0)
+    pub fn duty(&self) -> f64 {
+        if self.total_cycles == 0 {
+            0.0
+        } else {
+            self.on_cycles as f64 / self.total_cycles as f64
+        }
+    }
+}
+
+impl Component for Led {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn pins(&self) -> Vec<u8> {
+        vec![self.pin]
+    }
+
+    fn tick(&mut self, cycles: u64, cpu: &mut Cpu) {
+        self.on = cpu.gpio().get_pin_state(self.pin) == PinState::High;
+        self.total_cycles += cycles;
+        if self.on {
+            self.on_cycles += cycles;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.on = false;
+        self.on_cycles = 0;
+        self.total_cycles = 0;
+    }
+
+    fn describe(&self) -> String {
+        format!("{} ({}): {}", self.name, pin_label(self.pin), if self.on { "ON" } else { "off" })
+    }
+
+    #[cfg(feature = "gui")]
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let color = if self.on { egui::Color32::RED } else { egui::Color32::DARK_GRAY };
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("⏺").color(color).size(18.0));
+            ui.label(format!("{} ({})", self.name, pin_label(self.pin)));
+        });
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// How a 7-segment display's inputs select its segments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmentDrive {
+    /// One pin per segment, in the order a, b, c, d, e, f, g; segments
+    /// without a pin stay dark
+    Direct(Vec<u8>),
+    /// A BCD decoder (like a 4511) fed by four pins, least significant
+    /// first; codes above 9 blank the display
+    Bcd([u8; 4]),
+}
+
+/// Segment patterns for the digits 0-9, bit 0 = segment a
+const DIGIT_SEGMENTS: [u8; 10] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+
+/// A single-digit 7-segment display
+///
+/// With a latch pin the display only takes on its inputs on a rising edge
+/// of that pin, so firmware can change the data pins without flicker.
+#[derive(Debug, Clone)]
+pub struct SevenSegment {
+    name: String,
+    drive: SegmentDrive,
+    latch: Option<u8>,
+    /// Segments light while their pin is low (common anode)
+    active_low: bool,
+    latch_level: bool,
+    /// Lit segments, bit 0 = segment a
+    segments: u8,
+}
+
+impl SevenSegment {
+    pub fn new(name: &str, drive: SegmentDrive) -> Self {
+        Self {
+            name: name.to_string(),
+            drive,
+            latch: None,
+            active_low: false,
+            latch_level: false,
+            segments: 0,
+        }
+    }
+
+    /// Only update the display on rising edges of `pin`
+    pub fn with_latch(mut self, pin: u8) -> Self {
+        self.latch = Some(pin);
+        self
+    }
+
+    /// Light segments while their pin is low
+    pub fn with_active_low(mut self, active_low: bool) -> Self {
+        self.active_low = active_low;
+        self
+    }
+
+    /// Lit segments, bit 0 = segment a through bit 6 = segment g
+    pub fn segments(&self) -> u8 {
+        self.segments
+    }
+
+    /// The digit shown, if the lit segments form one
+    pub fn digit(&self) -> Option<u8> {
+        DIGIT_SEGMENTS.iter().position(|&pattern| pattern == self.segments).map(|digit| digit as u8)
+    }
+
+    /// Segments selected by the data pins right now
+    fn read_segments(&self, cpu: &Cpu) -> u8 {
+        let active = |pin: u8| (cpu.gpio().get_pin_state(pin) == PinState::High) != self.active_low;
+        match &self.drive {
+            SegmentDrive::Direct(pins) => pins.iter()
+                .enumerate()
+                .filter(|&(_, &pin)| active(pin))
+                .fold(0, |segments, (segment, _)| segments | (1 << segment)),
+            SegmentDrive::Bcd(pins) => {
+                // The decoder inputs are plain logic levels
+                let code = pins.iter()
+                    .enumerate()
+                    .filter(|&(_, &pin)| cpu.gpio().get_pin_state(pin) == PinState::High)
+                    .fold(0, |code, (bit, _)| code | (1 << bit));
+                DIGIT_SEGMENTS.get(code).copied().unwrap_or(0)
+            }
+        }
+    }
+}
+
+impl Component for SevenSegment {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn pins(&self) -> Vec<u8> {
+        let mut pins = match &self.drive {
+            SegmentDrive::Direct(pins) => pins.clone(),
+            SegmentDrive::Bcd(pins) => pins.to_vec(),
+        };
+        pins.extend(self.latch);
+        pins
+    }
+
+    fn tick(&mut self, _cycles: u64, cpu: &mut Cpu) {
+        match self.latch {
+            Some(pin) => {
+                let level = cpu.gpio().get_pin_state(pin) == PinState::High;
+                if level && !self.latch_level {
+                    self.segments = self.read_segments(cpu);
+                }
+                self.latch_level = level;
+            }
+            None => self.segments = self.read_segments(cpu),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.latch_level = false;
+        self.segments = 0;
+    }
+
+    fn describe(&self) -> String {
+        let shown = match self.digit() {
+            Some(digit) => digit.to_string(),
+            None if self.segments == 0 => "blank".to_string(),
+            None => format!("segments 0b{:07b}", self.segments),
+        };
+        format!("{}: {}", self.name, shown)
+    }
+
+    #[cfg(feature = "gui")]
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(40.0, 64.0), egui::Sense::hover());
+            let painter = ui.painter();
+            painter.rect_filled(rect, 4.0, egui::Color32::from_gray(20));
+
+            // Segment end points in a 0-1 unit box: a, b, c, d, e, f, g
+            let lines = [
+                ((0.25, 0.1), (0.75, 0.1)),
+                ((0.8, 0.15), (0.8, 0.45)),
+                ((0.8, 0.55), (0.8, 0.85)),
+                ((0.25, 0.9), (0.75, 0.9)),
+                ((0.2, 0.55), (0.2, 0.85)),
+                ((0.2, 0.15), (0.2, 0.45)),
+                ((0.25, 0.5), (0.75, 0.5)),
+            ];
+            let point = |(x, y): (f32, f32)| rect.min + egui::vec2(x * rect.width(), y * rect.height());
+            for (segment, &(from, to)) in lines.iter().enumerate() {
+                let lit = self.segments & (1 << segment) != 0;
+                let color = if lit { egui::Color32::RED } else { egui::Color32::from_gray(45) };
+                painter.line_segment([point(from), point(to)], egui::Stroke::new(5.0, color));
+            }
+            ui.label(&self.name);
+        });
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// A push-button from a pin to ground, read through a pull-up
+///
+/// The pin reads low while the button is pressed. With bounce enabled,
+/// each press and release makes the contact chatter for a while before it
+/// settles, as a real switch does, so debounce code can be tested.
+#[derive(Debug, Clone)]
+pub struct Button {
+    name: String,
+    pin: u8,
+    /// Whether the button is held down
+    pressed: bool,
+    /// Whether the contact is currently closed
+    closed: bool,
+    bounce_cycles: u64,
+    /// Contact changes during each bounce
+    bounce_transitions: u32,
+    /// Cycles since reset
+    now: u64,
+    /// Pending contact changes, in cycles since reset
+    edges: Vec<u64>,
+    /// xorshift state for the bounce timing
+    seed: u32,
+}
+
+impl Button {
+    pub fn new(name: &str, pin: u8) -> Self {
+        Self {
+            name: name.to_string(),
+            pin,
+            pressed: false,
+            closed: false,
+            bounce_cycles: 0,
+            bounce_transitions: 0,
+            now: 0,
+            edges: Vec::new(),
+            seed: 0x2545_F491,
+        }
+    }
+
+    /// Chatter for `cycles` cycles after each press or release, with
+    /// `transitions` extra contact changes (rounded up to an even number
+    /// so the contact settles where the button is)
+    pub fn with_bounce(mut self, cycles: u64, transitions: u32) -> Self {
+        self.bounce_cycles = cycles;
+        self.bounce_transitions = transitions.next_multiple_of(2);
+        self
+    }
+
+    pub fn press(&mut self) {
+        self.set_pressed(true);
+    }
+
+    pub fn release(&mut self) {
+        self.set_pressed(false);
+    }
+
+    /// Press or release the button
+    pub fn set_pressed(&mut self, pressed: bool) {
+        if pressed == self.pressed {
+            return;
+        }
+        self.pressed = pressed;
+        self.closed = pressed;
+        self.edges.clear();
+        if self.bounce_cycles == 0 {
+            return;
+        }
+        for _ in 0..self.bounce_transitions {
+            let offset = u64::from(self.next_random()) % self.bounce_cycles + 1;
+            self.edges.push(self.now + offset);
+        }
+        self.edges.sort_unstable();
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Whether the contact is closed right now, including bounce
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Whether the contact is still bouncing
+    pub fn is_bouncing(&self) -> bool {
+        !self.edges.is_empty()
+    }
+
+    fn next_random(&mut self) -> u32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed
+    }
+}
+
+impl Component for Button {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn pins(&self) -> Vec<u8> {
+        vec![self.pin]
+    }
+
+    fn tick(&mut self, cycles: u64, cpu: &mut Cpu) {
+        self.now += cycles;
+        let due = self.edges.partition_point(|&edge| edge <= self.now);
+        if due % 2 == 1 {
+            self.closed = !self.closed;
+        }
+        self.edges.drain(..due);
+        cpu.gpio_mut().set_external_pin(self.pin, !self.closed);
+    }
+
+    fn reset(&mut self) {
+        self.pressed = false;
+        self.closed = false;
+        self.now = 0;
+        self.edges.clear();
+    }
+
+    fn describe(&self) -> String {
+        let state = match (self.pressed, self.is_bouncing()) {
+            (_, true) => "bouncing",
+            (true, false) => "pressed",
+            (false, false) => "released",
+        };
+        format!("{} ({}): {}", self.name, pin_label(self.pin), state)
+    }
+
+    #[cfg(feature = "gui")]
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            // Pressed while the mouse button is held down on it
+            let response = ui.add(egui::Button::new(&self.name).selected(self.pressed));
+            let held = response.is_pointer_button_down_on();
+            if held != self.pressed {
+                self.set_pressed(held);
+            }
+            ui.label(pin_label(self.pin));
+            if self.is_bouncing() {
+                ui.colored_label(egui::Color32::YELLOW, "bouncing");
+            }
+        });
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+/// A potentiometer between VDD and ground with its wiper on a pin
+#[derive(Debug, Clone)]
+pub struct Potentiometer {
+    name: String,
+    pin: u8,
+    /// Wiper position, 0.0 (ground) - 1.0 (VDD)
+    position: f32,
+}
+
+impl Potentiometer {
+    pub fn new(name: &str, pin: u8) -> Self {
+        Self {
+            name: name.to_string(),
+            pin,
+            position: 0.5,
+        }
+    }
+
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    /// Move the wiper (clamped to 0.0 - 1.0)
+    pub fn set_position(&mut self, position: f32) {
+        self.position = position.clamp(0.0, 1.0);
+    }
+
+    /// Voltage at the wiper
+    pub fn voltage(&self) -> f32 {
+        self.position * VDD
+    }
+}
+
+impl Component for Potentiometer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn pins(&self) -> Vec<u8> {
+        vec![self.pin]
+    }
+
+    fn tick(&mut self, _cycles: u64, cpu: &mut Cpu) {
+        cpu.gpio_mut().set_pin_voltage(self.pin, self.voltage());
+    }
+
+    fn describe(&self) -> String {
+        format!("{} ({}): {:.2} V", self.name, pin_label(self.pin), self.voltage())
+    }
+
+    #[cfg(feature = "gui")]
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} ({})", self.name, pin_label(self.pin)));
+            let mut volts = self.voltage();
+            let slider = egui::Slider::new(&mut volts, 0.0..=VDD).suffix(" V").fixed_decimals(2);
+            if ui.add(slider).changed() {
+                self.set_position(volts / VDD);
+            }
+        });
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let mut components = Components::new();
+        let a = components.attach(Box::new(Led::new("LED1", 0)));
+        let b = components.attach(Box::new(Led::new("LED2", 1)));
+        assert_ne!(a, b);
+        assert_eq!(components.len(), 2);
+
+        assert_eq!(components.detach(a).unwrap().name(), "LED1");
+        assert!(components.get(a).is_none());
+        assert_eq!(components.get(b).unwrap().pins(), vec![1]);
+    }
+
+    #[test]
+    fn test_led() {
+        let mut cpu = Cpu::new();
+        let mut led = Led::new("LED1", 0);
+        cpu.gpio_mut().write_tris(0x3E);
+
+        cpu.gpio_mut().write_gpio(0x01);
+        led.tick(3, &mut cpu);
+        assert!(led.is_on());
+        cpu.gpio_mut().write_gpio(0x00);
+        led.tick(1, &mut cpu);
+        assert!(!led.is_on());
+        assert_eq!(led.duty(), 0.75);
+    }
+
+    #[test]
+    fn test_seven_segment() {
+        let mut cpu = Cpu::new();
+        cpu.gpio_mut().write_tris(0x08);
+
+        // Direct drive of segments a-f: "0" without segment g
+        let mut display = SevenSegment::new("DISP", SegmentDrive::Direct(vec![0, 1, 2, 4, 5]));
+        cpu.gpio_mut().write_gpio(0x37);
+        display.tick(1, &mut cpu);
+        assert_eq!(display.segments(), 0x1F);
+        assert_eq!(display.digit(), None);
+
+        // BCD digit 5 on GP0-GP2,GP4, latched by GP5
+        let mut display = SevenSegment::new("DISP", SegmentDrive::Bcd([0, 1, 2, 4])).with_latch(5);
+        cpu.gpio_mut().write_gpio(0x05);
+        display.tick(1, &mut cpu);
+        assert_eq!(display.digit(), None);
+        cpu.gpio_mut().write_gpio(0x25);
+        display.tick(1, &mut cpu);
+        assert_eq!(display.digit(), Some(5));
+        cpu.gpio_mut().write_gpio(0x21);
+        display.tick(1, &mut cpu);
+        assert_eq!(display.describe(), "DISP: 5");
+    }
+
+    #[test]
+    fn test_button() {
+        let mut cpu = Cpu::new();
+        cpu.gpio_mut().write_tris(0x3F);
+
+        let mut button = Button::new("S1", 3);
+        button.tick(1, &mut cpu);
+        assert_eq!(cpu.gpio().read_gpio() & 0x08, 0x08);
+        button.press();
+        button.tick(1, &mut cpu);
+        assert_eq!(cpu.gpio().read_gpio() & 0x08, 0);
+
+        // The contact chatters for up to 100 cycles, then stays open
+        let mut button = Button::new("S2", 3).with_bounce(100, 3);
+        button.press();
+        button.release();
+        assert!(button.is_bouncing());
+        let mut changes = 0;
+        let mut level = true;
+        for _ in 0..100 {
+            button.tick(1, &mut cpu);
+            let state = cpu.gpio().read_gpio() & 0x08 != 0;
+            if state != level {
+                changes += 1;
+                level = state;
+            }
+        }
+        assert!(!button.is_bouncing());
+        assert!(level);
+        assert!(changes > 0 && changes <= 4);
+        assert_eq!(button.describe(), "S2 (GP3): released");
+    }
+
+    #[test]
+    fn test_potentiometer() {
+        let mut cpu = Cpu::new();
+        let mut pot = Potentiometer::new("POT1", 0);
+        pot.set_position(0.3);
+        pot.tick(1, &mut cpu);
+        assert_eq!(cpu.gpio().pin_voltage(0), 1.5);
+        assert_eq!(cpu.gpio().read_gpio() & 0x01, 0);
+        assert_eq!(pot.describe(), "POT1 (GP0): 1.50 V");
+
+        pot.set_position(2.0);
+        pot.tick(1, &mut cpu);
+        assert_eq!(cpu.gpio().pin_voltage(0), VDD);
+        assert_eq!(cpu.gpio().read_gpio() & 0x01, 0x01);
+
+        // An output pin shows its driven level
+        cpu.gpio_mut().write_tris(0x3E);
+        assert_eq!(cpu.gpio().pin_voltage(0), 0.0);
+    }
+}
@@ -0,0 +1,250 @@
+//! Code coverage tracking
+//!
+//! Records which program words have been executed and, for the skip
+//! instructions (BTFSC, BTFSS, DECFSZ, INCFSZ), whether the skip was taken,
+//! not taken, or both. Reports can be exported as plain text or JSON.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::memory::{MAX_PROGRAM_MEMORY_SIZE, PROGRAM_MEMORY_SIZE};
+
+/// Outcomes observed for a single skip instruction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BranchCoverage {
+    /// Number of times the next instruction was skipped
+    pub taken: u64,
+
+    /// Number of times execution fell through
+    pub not_taken: u64,
+}
+
+impl BranchCoverage {
+    /// Check if both outcomes have been observed
+    pub fn is_complete(&self) -> bool {
+        self.taken > 0 && self.not_taken > 0
+    }
+}
+
+/// Executed-word and branch coverage
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    enabled: bool,
+    executed: Vec<bool>,
+    branches: BTreeMap<u16, BranchCoverage>,
+    program_size: usize,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            executed: vec![false; MAX_PROGRAM_MEMORY_SIZE],
+            branches: BTreeMap::new(),
+            program_size: PROGRAM_MEMORY_SIZE,
+        }
+    }
+
+    /// Enable or disable data collection
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Check if coverage is being collected
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Discard all coverage data and measure against `program_size` words
+    pub fn reset(&mut self, program_size: usize) {
+        self.executed.iter_mut().for_each(|e| *e = false);
+        self.branches.clear();
+        self.program_size = program_size.min(MAX_PROGRAM_MEMORY_SIZE);
+    }
+
+    /// Discard all coverage data
+    pub fn clear(&mut self) {
+        self.reset(self.program_size);
+    }
+
+    /// Mark the word at `pc` as executed
+    pub fn record_instruction(&mut self, pc: u16) {
+        if !self.enabled {
+            return;
+        }
+        self.executed[pc as usize % MAX_PROGRAM_MEMORY_SIZE] = true;
+    }
+
+    /// Record the outcome of a skip instruction at `pc`
+    pub fn record_branch(&mut self, pc: u16, taken: bool) {
+        if !self.enabled {
+            return;
+        }
+        let branch = self.branches.entry(pc).or_default();
+        if taken {
+            branch.taken += 1;
+        } else {
+            branch.not_taken += 1;
+        }
+    }
+
+    /// Check if the word at `address` has been executed
+    pub fn is_executed(&self, address: u16) -> bool {
+        self.executed[address as usize % MAX_PROGRAM_MEMORY_SIZE]
+    }
+
+    /// Number of program words coverage is measured against
+    pub fn program_size(&self) -> usize {
+        self.program_size
+    }
+
+    /// Number of executed words within the program
+    pub fn executed_words(&self) -> usize {
+        self.executed[..self.program_size].iter().filter(|&&e| e).count()
+    }
+
+    /// Executed words as a percentage of the program size
+    pub fn percent(&self) -> f64 {
+        if self.program_size == 0 {
+            0.0
+        } else {
+            self.executed_words() as f64 * 100.0 / self.program_size as f64
+        }
+    }
+
+    /// Outcomes of every skip instruction executed, by address
+    pub fn branches(&self) -> &BTreeMap<u16, BranchCoverage> {
+        &self.branches
+    }
+
+    /// Ranges of program words never executed (inclusive bounds)
+    pub fn uncovered_ranges(&self) -> Vec<(u16, u16)> {
+        let mut ranges = Vec::new();
+        let mut start = None;
+
+        for address in 0..self.program_size {
+            match (self.executed[address], start) {
+                (false, None) => start = Some(address),
+                (true, Some(s)) => {
+                    ranges.push((s as u16, address as u16 - 1));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            ranges.push((s as u16, self.program_size as u16 - 1));
+        }
+
+        ranges
+    }
+
+    /// Format a human-readable coverage report
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "Coverage: {}/{} words ({:.1}%)",
+            self.executed_words(), self.program_size, self.percent());
+
+        let complete = self.branches.values().filter(|b| b.is_complete()).count();
+        let _ = writeln!(out, "Branches: {}/{} skip instructions saw both outcomes",
+            complete, self.branches.len());
+
+        let uncovered = self.uncovered_ranges();
+        if !uncovered.is_empty() {
+            let _ = writeln!(out, "\nNot executed:");
+            for (start, end) in uncovered {
+                if start == end {
+                    let _ = writeln!(out, "  0x{:04X}", start);
+                } else {
+                    let _ = writeln!(out, "  0x{:04X}-0x{:04X}", start, end);
+                }
+            }
+        }
+
+        let partial: Vec<_> = self.branches.iter().filter(|(_, b)| !b.is_complete()).collect();
+        if !partial.is_empty() {
+            let _ = writeln!(out, "\nPartially covered branches:");
+            for (address, branch) in partial {
+                let missing = if branch.taken == 0 { "never skipped" } else { "always skipped" };
+                let _ = writeln!(out, "  0x{:04X}  {}", address, missing);
+            }
+        }
+
+        out
+    }
+
+    /// Format the coverage report as JSON
+    pub fn to_json(&self) -> String {
+        let executed: Vec<String> = (0..self.program_size)
+            .filter(|&a| self.executed[a])
+            .map(|a| a.to_string())
+            .collect();
+
+        let branches: Vec<String> = self.branches.iter()
+            .map(|(address, b)| format!(
+                "{{\"address\":{},\"taken\":{},\"not_taken\":{}}}",
+                address, b.taken, b.not_taken))
+            .collect();
+
+        format!(
+            "{{\"program_size\":{},\"executed_words\":{},\"percent\":{:.2},\"executed\":[{}],\"branches\":[{}]}}",
+            self.program_size,
+            self.executed_words(),
+            self.percent(),
+            executed.join(","),
+            branches.join(","),
+        )
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_executed_words_and_ranges() {
+        let mut coverage = Coverage::new();
+        coverage.reset(6);
+
+        coverage.record_instruction(0);
+        coverage.record_instruction(1);
+        coverage.record_instruction(4);
+
+        assert_eq!(coverage.executed_words(), 3);
+        assert_eq!(coverage.percent(), 50.0);
+        assert_eq!(coverage.uncovered_ranges(), vec![(2, 3), (5, 5)]);
+    }
+
+    #[test]
+    fn test_branch_outcomes() {
+        let mut coverage = Coverage::new();
+        coverage.record_branch(0x10, true);
+        coverage.record_branch(0x10, false);
+        coverage.record_branch(0x20, false);
+
+        assert!(coverage.branches()[&0x10].is_complete());
+        assert!(!coverage.branches()[&0x20].is_complete());
+        assert!(coverage.to_text().contains("0x0020  never skipped"));
+    }
+
+    #[test]
+    fn test_json_export() {
+        let mut coverage = Coverage::new();
+        coverage.reset(2);
+        coverage.record_instruction(1);
+        coverage.record_branch(1, true);
+
+        assert_eq!(
+            coverage.to_json(),
+            "{\"program_size\":2,\"executed_words\":1,\"percent\":50.00,\"executed\":[1],\
+             \"branches\":[{\"address\":1,\"taken\":1,\"not_taken\":0}]}"
+        );
+    }
+}
@@ -1,14 +1,26 @@
-/// PIC12F629/675 CPU Core
-/// 
-/// Reference: Data Sheet Section 1.0 - Device Overview
-/// 
-/// CPU Features:
-/// - 8-bit RISC architecture
-/// - 35 instructions (all single cycle except branches)
-/// - 8-level hardware stack
-/// - Direct, indirect, and relative addressing modes
-
-use crate::{gpio::Gpio, memory::Memory, timer::TimerController, interrupt::InterruptController, wdt::Wdt};
+//! PIC12F629/675/683 CPU Core
+//! 
+//! Reference: Data Sheet Section 1.0 - Device Overview
+//! 
+//! CPU Features:
+//! - 8-bit RISC architecture
+//! - 35 instructions (all single cycle except branches)
+//! - 8-level hardware stack
+//! - Direct, indirect, and relative addressing modes
+//!
+//! Baseline (PIC10F2xx) devices run on the same core with a 2-level stack
+//! and no interrupts; their file operands are mapped onto the midrange
+//! register addresses by the device model. Wake-up from SLEEP continues
+//! after the SLEEP instruction rather than resetting the part.
+
+use std::cell::RefCell;
+
+use crate::{gpio::Gpio, memory::{Memory, BANK_SIZE}, timer::TimerController, interrupt::{InterruptController, InterruptSource}, wdt::Wdt};
+use crate::ccp::Ccp;
+use crate::device::Device;
+use crate::instruction::Core;
+use crate::error::StateError;
+use crate::state::StateRecord;
 
 /// Special Function Register addresses
 /// Reference: Section 2.2 - Register File Map (Table 2-1)
@@ -20,6 +32,8 @@ pub mod registers {
     pub const STATUS: u8 = 0x03;    // Status register
     pub const FSR: u8 = 0x04;       // File Select Register (for indirect addressing)
     pub const GPIO: u8 = 0x05;      // General Purpose I/O
+    pub const PORTA: u8 = 0x05;     // Port A (16F84A name for GPIO's address)
+    pub const PORTB: u8 = 0x06;     // Port B (16F84A only)
     pub const PCLATH: u8 = 0x0A;    // Program Counter Latch High
     pub const INTCON: u8 = 0x0B;    // Interrupt Control register
     
@@ -28,6 +42,11 @@ pub mod registers {
     pub const TMR1L: u8 = 0x0E;     // Timer1 Low byte
     pub const TMR1H: u8 = 0x0F;     // Timer1 High byte
     pub const T1CON: u8 = 0x10;     // Timer1 Control register
+    pub const TMR2: u8 = 0x11;      // Timer2 register (12F683 only)
+    pub const T2CON: u8 = 0x12;     // Timer2 Control register (12F683 only)
+    pub const CCPR1L: u8 = 0x13;    // CCP Register Low byte (12F683 only)
+    pub const CCPR1H: u8 = 0x14;    // CCP Register High byte (12F683 only)
+    pub const CCP1CON: u8 = 0x15;   // CCP Control register (12F683 only)
     pub const CMCON: u8 = 0x19;     // Comparator Control register
     pub const ADRESH: u8 = 0x1E;    // ADC Result High byte (12F675 only)
     pub const ADCON0: u8 = 0x1F;    // ADC Control register 0 (12F675 only)
@@ -35,12 +54,93 @@ pub mod registers {
     // Bank 1 specific registers (accessed when RP0=1 in STATUS)
     pub const OPTION_REG: u8 = 0x81;  // Option register
     pub const TRISIO: u8 = 0x85;      // GPIO Tri-state register
+    pub const TRISA: u8 = 0x85;       // Port A Tri-state register (16F84A)
+    pub const TRISB: u8 = 0x86;       // Port B Tri-state register (16F84A only)
     pub const PIE1: u8 = 0x8C;        // Peripheral Interrupt Enable register 1
     pub const PCON: u8 = 0x8E;        // Power Control register
     pub const OSCCAL: u8 = 0x90;      // Oscillator Calibration register
+    pub const PR2: u8 = 0x92;         // Timer2 Period register (12F683 only)
     pub const WPU: u8 = 0x95;         // Weak Pull-Up register (IOC in some docs)
     pub const IOC: u8 = 0x96;         // Interrupt-On-Change register
     pub const ANSEL: u8 = 0x9F;       // Analog Select register (12F675 only)
+
+    /// Register names by (bank-resolved) address
+    pub const NAMES: &[(u8, &str)] = &[
+        (INDF, "INDF"),
+        (TMR0, "TMR0"),
+        (PCL, "PCL"),
+        (STATUS, "STATUS"),
+        (FSR, "FSR"),
+        (GPIO, "GPIO"),
+        (PORTB, "PORTB"),
+        (PCLATH, "PCLATH"),
+        (INTCON, "INTCON"),
+        (PIR1, "PIR1"),
+        (TMR1L, "TMR1L"),
+        (TMR1H, "TMR1H"),
+        (T1CON, "T1CON"),
+        (TMR2, "TMR2"),
+        (T2CON, "T2CON"),
+        (CCPR1L, "CCPR1L"),
+        (CCPR1H, "CCPR1H"),
+        (CCP1CON, "CCP1CON"),
+        (CMCON, "CMCON"),
+        (ADRESH, "ADRESH"),
+        (ADCON0, "ADCON0"),
+        (OPTION_REG, "OPTION_REG"),
+        (TRISIO, "TRISIO"),
+        (TRISB, "TRISB"),
+        (PIE1, "PIE1"),
+        (PCON, "PCON"),
+        (OSCCAL, "OSCCAL"),
+        (PR2, "PR2"),
+        (WPU, "WPU"),
+        (IOC, "IOC"),
+        (ANSEL, "ANSEL"),
+    ];
+
+    /// Name of the register at `address`
+    pub fn name_of(address: u8) -> Option<&'static str> {
+        NAMES.iter().find(|(addr, _)| *addr == address).map(|(_, name)| *name)
+    }
+
+    /// Address of the register called `name` (case-insensitive)
+    pub fn address_of(name: &str) -> Option<u8> {
+        NAMES.iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+            .map(|(addr, _)| *addr)
+    }
+
+    /// Bit names by register, bit 0 first ("" for unimplemented bits)
+    /// Reference: Section 2.2.2 - Special Function Registers
+    pub const BIT_NAMES: &[(u8, [&str; 8])] = &[
+        (STATUS, ["C", "DC", "Z", "NOT_PD", "NOT_TO", "RP0", "RP1", "IRP"]),
+        (GPIO, ["GP0", "GP1", "GP2", "GP3", "GP4", "GP5", "", ""]),
+        (PORTB, ["RB0", "RB1", "RB2", "RB3", "RB4", "RB5", "RB6", "RB7"]),
+        (INTCON, ["GPIF", "INTF", "T0IF", "GPIE", "INTE", "T0IE", "PEIE", "GIE"]),
+        (PIR1, ["TMR1IF", "TMR2IF", "", "CMIF", "", "CCP1IF", "ADIF", "EEIF"]),
+        (T1CON, ["TMR1ON", "TMR1CS", "NOT_T1SYNC", "T1OSCEN", "T1CKPS0", "T1CKPS1", "TMR1GE", ""]),
+        (T2CON, ["T2CKPS0", "T2CKPS1", "TMR2ON", "TOUTPS0", "TOUTPS1", "TOUTPS2", "TOUTPS3", ""]),
+        (CCP1CON, ["CCP1M0", "CCP1M1", "CCP1M2", "CCP1M3", "DC1B0", "DC1B1", "", ""]),
+        (CMCON, ["CM0", "CM1", "CM2", "CIS", "CINV", "", "COUT", ""]),
+        (ADCON0, ["ADON", "GO_DONE", "CHS0", "CHS1", "", "", "VCFG", "ADFM"]),
+        (OPTION_REG, ["PS0", "PS1", "PS2", "PSA", "T0SE", "T0CS", "INTEDG", "NOT_GPPU"]),
+        (TRISIO, ["TRISIO0", "TRISIO1", "TRISIO2", "TRISIO3", "TRISIO4", "TRISIO5", "", ""]),
+        (TRISB, ["TRISB0", "TRISB1", "TRISB2", "TRISB3", "TRISB4", "TRISB5", "TRISB6", "TRISB7"]),
+        (PIE1, ["TMR1IE", "TMR2IE", "", "CMIE", "", "CCP1IE", "ADIE", "EEIE"]),
+        (PCON, ["NOT_BOR", "NOT_POR", "", "", "", "", "", ""]),
+        (WPU, ["WPU0", "WPU1", "WPU2", "", "WPU4", "WPU5", "", ""]),
+        (IOC, ["IOC0", "IOC1", "IOC2", "IOC3", "IOC4", "IOC5", "", ""]),
+        (ANSEL, ["ANS0", "ANS1", "ANS2", "ANS3", "ADCS0", "ADCS1", "ADCS2", ""]),
+    ];
+
+    /// Name of `bit` in the register at `address`
+    pub fn bit_name(address: u8, bit: u8) -> Option<&'static str> {
+        BIT_NAMES.iter()
+            .find(|(addr, _)| *addr == address)
+            .and_then(|(_, bits)| bits.get(bit as usize).copied())
+            .filter(|name| !name.is_empty())
+    }
 }
 
 /// STATUS register bit definitions
@@ -56,8 +156,91 @@ pub mod status_bits {
     pub const C: u8 = 0;     // Carry/Borrow flag
 }
 
+/// PCON bits
+/// Reference: Register 9-3 - PCON: Power Control Register
+pub mod pcon_bits {
+    pub const NOT_POR: u8 = 1;  // Power-on Reset status (0 = a power-on reset occurred)
+    pub const NOT_BOR: u8 = 0;  // Brown-out Reset status (0 = a brown-out reset occurred)
+}
+
+/// What reset the part; firmware tells them apart by STATUS TO/PD and
+/// PCON POR/BOR
+/// Reference: Table 9-4 - Status Bits and Their Significance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResetReason {
+    /// Power-on reset: RAM is cleared, POR reads 0
+    PowerOn,
+    /// MCLR pin pulled low: RAM and PCON are kept, PD reads 0 if asleep
+    Mclr,
+    /// Watchdog time-out while running: TO reads 0
+    Wdt,
+    /// Brown-out reset: BOR reads 0
+    BrownOut,
+}
+
+impl ResetReason {
+    /// Parse `por`, `mclr`, `wdt` or `bor`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "por" => Some(ResetReason::PowerOn),
+            "mclr" => Some(ResetReason::Mclr),
+            "wdt" => Some(ResetReason::Wdt),
+            "bor" => Some(ResetReason::BrownOut),
+            _ => None,
+        }
+    }
+    
+    /// Short name accepted by `from_name`
+    pub fn name(self) -> &'static str {
+        match self {
+            ResetReason::PowerOn => "por",
+            ResetReason::Mclr => "mclr",
+            ResetReason::Wdt => "wdt",
+            ResetReason::BrownOut => "bor",
+        }
+    }
+}
+
+impl std::fmt::Display for ResetReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ResetReason::PowerOn => "power-on reset",
+            ResetReason::Mclr => "MCLR reset",
+            ResetReason::Wdt => "WDT reset",
+            ResetReason::BrownOut => "brown-out reset",
+        })
+    }
+}
+
+/// Kind of register access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A register access made while access tracking is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterAccess {
+    /// Bank-resolved address (INDF is resolved through FSR)
+    pub address: u16,
+    pub kind: AccessKind,
+    /// Value before the access
+    pub old: u8,
+    /// Value read, or value written
+    pub new: u8,
+}
+
 /// PIC12F629/675 CPU
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cpu {
+    /// Part being simulated
+    device: Device,
+    
     /// Memory system
     memory: Memory,
     
@@ -84,38 +267,80 @@ pub struct Cpu {
     /// Watchdog Timer
     wdt: Wdt,
 
+    /// Timer2 and CCP module (12F683 only)
+    ccp: Ccp,
+
     /// Is sleeping or not
     sleeping: bool, 
+
+    /// STATUS register, shadowed from data memory so flag tests and bank
+    /// selection need not read memory; `write_status` keeps both in step
+    status: u8,
+
+    /// Data memory bank selected by RP1:RP0, cached when STATUS is written
+    bank: u16,
+
+    /// Register accesses recorded while tracking is enabled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    access_log: RefCell<Option<Vec<RegisterAccess>>>,
+    
+    /// Emptied access log kept for the next `begin_access_tracking`, so
+    /// tracking every instruction does not allocate
+    #[cfg_attr(feature = "serde", serde(skip))]
+    spare_access_log: Vec<RegisterAccess>,
+    
+    /// Set when timer or WDT state changes other than by counting,
+    /// so the simulator knows to recompute their next events
+    #[cfg_attr(feature = "serde", serde(skip))]
+    peripherals_changed: bool,
 }
 
 impl Cpu {
-    /// Create a new CPU instance
+    /// Create a new CPU instance of the default device
     pub fn new() -> Self {
+        Self::with_device(Device::default())
+    }
+    
+    /// Create a new CPU instance of `device`
+    pub fn with_device(device: Device) -> Self {
+        let model = device.model();
+        let mut memory = Memory::with_layout(model.memory_layout());
+        memory.set_core(model.core);
         Self {
-            memory: Memory::new(),
+            device,
+            memory,
             w: 0,
             pc: 0,
             cycles: 0,
-            gpio: Gpio::new(),
+            gpio: Gpio::with_ports(model.ports),
             timers: TimerController::new(),
             interrupts: InterruptController::new(),
             wdt: Wdt::new(), 
+            ccp: Ccp::new(),
             sleeping: false,
+            status: 0,
+            bank: 0,
+            access_log: RefCell::new(None),
+            spare_access_log: Vec::new(),
+            peripherals_changed: true,
         }
     }
     
     /// Reset the CPU to initial state
     /// Reference: Section 9.3 - Reset
     pub fn reset(&mut self) {
-        self.pc = 0;
+        self.pc = self.device.model().reset_vector();
         self.w = 0;
         self.cycles = 0;
         self.memory.reset();
+        self.sync_status();
         self.gpio.reset();
         self.timers.reset();
         self.interrupts.reset();
         self.wdt.reset();
+        self.ccp.reset();
         self.sleeping = false;
+        self.peripherals_changed = true;
         
         // Initialize STATUS register
         // Reference: Table 9-7 - Power-on Reset values
@@ -125,8 +350,63 @@ impl Cpu {
         self.write_register(registers::PCLATH, 0x00);
         self.write_register(registers::INTCON, 0x00);
         self.write_register(registers::TRISIO, 0x3F); 
-        self.write_register(registers::PIE1, 0x00);
-        self.write_register(registers::PIR1, 0x00);
+        for sfr in [registers::PIE1, registers::PIR1] {
+            if self.device.model().has_sfr(sfr) {
+                self.write_register(sfr, 0x00);
+            }
+        }
+    }
+    
+    /// Reset for `reason`, setting the STATUS and PCON bits firmware
+    /// checks to find out why it restarted
+    /// Reference: Table 9-7 - Initialization Condition for Registers
+    ///
+    /// Only a power-on reset clears RAM; the other resets keep RAM and
+    /// the PCON bits they do not set.
+    pub fn reset_with(&mut self, reason: ResetReason) {
+        let ram: Vec<(u16, u8)> = self.device.model().gpr.iter()
+            .flat_map(|range| range.clone())
+            .map(|address| (address as u16, self.memory.read_data(address as u16)))
+            .collect();
+        let pcon = self.read_sfr(registers::PCON);
+        let asleep = self.sleeping;
+        self.reset();
+        if reason == ResetReason::PowerOn {
+            return;
+        }
+        
+        for (address, value) in ram {
+            self.memory.write_data(address, value);
+        }
+        let (to, pd, pcon) = match reason {
+            ResetReason::PowerOn => unreachable!(),
+            ResetReason::Mclr => (true, !asleep, pcon),
+            ResetReason::Wdt => (false, true, pcon),
+            ResetReason::BrownOut => (true, true, (pcon | 1 << pcon_bits::NOT_POR) & !(1 << pcon_bits::NOT_BOR)),
+        };
+        let status = self.read_register(registers::STATUS) & !(1 << status_bits::TO | 1 << status_bits::PD);
+        self.write_register(registers::STATUS, status | (to as u8) << status_bits::TO | (pd as u8) << status_bits::PD);
+        if self.device.model().has_sfr(registers::PCON) {
+            self.write_register(registers::PCON, pcon);
+        }
+    }
+    
+    /// Get the part being simulated
+    pub fn device(&self) -> Device {
+        self.device
+    }
+    
+    /// Change the part being simulated; takes effect fully after `reset`
+    ///
+    /// Memories are resized to the new part's layout and the I/O ports
+    /// rebuilt.
+    pub fn set_device(&mut self, device: Device) {
+        let model = device.model();
+        self.device = device;
+        self.memory.set_layout(model.memory_layout());
+        self.memory.set_core(model.core);
+        self.gpio = Gpio::with_ports(model.ports);
+        self.sync_status();
     }
     
     /// Get GPIO Reference
@@ -147,6 +427,7 @@ impl Cpu {
     
     // Get a mutable reference to the timer controller
     pub fn timers_mut(&mut self) -> &mut TimerController {
+        self.peripherals_changed = true;
         &mut self.timers
     }
 
@@ -160,16 +441,34 @@ impl Cpu {
         &mut self.interrupts
     }
 
+    /// Sample the INT pin and set INTF on the edge selected by INTEDG
+    pub fn sample_int_pin(&mut self) {
+        let Some(pin) = self.device.model().int_pin else {
+            return;
+        };
+        let level = self.gpio.pin_level(pin);
+        let option_reg = self.read_register(registers::OPTION_REG);
+        if self.interrupts.sample_int_pin(level, option_reg) {
+            let intcon = self.read_register(registers::INTCON);
+            self.write_register(registers::INTCON, intcon | 0x02);
+        }
+    }
+    
     /// Check for pending interrupts and handle them
-    /// Returns true if an interrupt was serviced
-    pub fn check_and_handle_interrupts(&mut self) -> bool {
+    /// Returns the source of the interrupt that was serviced, if any
+    pub fn check_and_handle_interrupts(&mut self) -> Option<InterruptSource> {
+        // The baseline core has no interrupts
+        if self.device.model().core == Core::Baseline {
+            return None;
+        }
+        
         let intcon = self.read_register(registers::INTCON);
-        let pie1 = self.read_register(registers::PIE1);
-        let pir1 = self.read_register(registers::PIR1);
+        let pie1 = self.read_sfr(registers::PIE1);
+        let pir1 = self.read_sfr(registers::PIR1);
         
-        let (should_interrupt, vector) = self.interrupts.check_interrupts(intcon, pie1, pir1);
+        let source = self.interrupts.check_interrupts(intcon, pie1, pir1);
         
-        if should_interrupt && !self.interrupts.in_isr() {
+        if let Some(source) = source && !self.interrupts.in_isr() {
             // Save return address on stack
             self.push_pc();
             
@@ -178,15 +477,15 @@ impl Cpu {
             self.write_register(registers::INTCON, intcon & !0x80);
             
             // Jump to interrupt vector
-            self.set_pc(vector);
+            self.set_pc(self.interrupts.get_vector());
             
             // Mark as in ISR
-            self.interrupts.enter_isr();
+            self.interrupts.enter_isr(source, self.cycles);
             
-            return true;
+            return Some(source);
         }
         
-        false
+        None
     }    
 
     // Get WDT reference
@@ -196,9 +495,67 @@ impl Cpu {
     
     // Get a mutable WDT reference
     pub fn wdt_mut(&mut self) -> &mut Wdt {
+        self.peripherals_changed = true;
         &mut self.wdt
     }
     
+    /// Get the Timer2/CCP module
+    pub fn ccp(&self) -> &Ccp {
+        &self.ccp
+    }
+    
+    /// Get a mutable reference to the Timer2/CCP module
+    pub fn ccp_mut(&mut self) -> &mut Ccp {
+        self.peripherals_changed = true;
+        &mut self.ccp
+    }
+    
+    /// Tick Timer0, Timer1, Timer2 and the WDT once
+    /// Returns (tmr0_overflow, tmr1_overflow, tmr2_match, wdt_timeout)
+    ///
+    /// PWM edges drive GP2 and count as a peripheral change, so the
+    /// simulator schedules the next one.
+    pub fn tick_peripherals(&mut self) -> (bool, bool, bool, bool) {
+        let (tmr0_overflow, tmr1_overflow) = self.timers.tick();
+        let mut tmr2_match = false;
+        if self.device.model().peripherals.ccp {
+            let (matched, ccp_event) = self.ccp.tick();
+            tmr2_match = matched;
+            if ccp_event {
+                self.update_ccp_pin();
+                self.peripherals_changed = true;
+            }
+        }
+        (tmr0_overflow, tmr1_overflow, tmr2_match, self.wdt.tick())
+    }
+    
+    /// Advance Timer0, Timer1, Timer2 and the WDT by `cycles` cycles,
+    /// which must end before any of them overflows, times out or changes
+    /// the PWM output
+    pub fn advance_peripherals(&mut self, cycles: u64) {
+        self.timers.advance(cycles);
+        if self.device.model().peripherals.ccp {
+            self.ccp.advance(cycles);
+        }
+        self.wdt.advance(cycles);
+    }
+    
+    /// Drive GP2 from the CCP while it is in PWM mode and TRISIO2 is clear
+    fn update_ccp_pin(&mut self) {
+        match self.ccp.output() {
+            Some(level) if self.device.model().peripherals.ccp && !self.gpio.is_input(2) => {
+                self.gpio.set_peripheral_control(2, true, level);
+            }
+            _ => self.gpio.set_peripheral_control(2, false, false),
+        }
+    }
+    
+    /// Check and clear whether timer or WDT state was changed other than
+    /// by `tick_peripherals`/`advance_peripherals` since the last call
+    pub fn take_peripherals_changed(&mut self) -> bool {
+        std::mem::take(&mut self.peripherals_changed)
+    }
+    
     // Enter sleep mode
     pub fn enter_sleep(&mut self) {
         self.sleeping = true;
@@ -232,28 +589,96 @@ impl Cpu {
 
     // ==================== Register Access ====================
     
-    /// Read from a register with banking support
+    /// Read the register at a bank-resolved address (0x00-0xFF)
     /// Reference: Section 2.2 - Data Memory Organization
+    ///
+    /// INDF (0x00/0x80) reads through FSR. Instructions address their file
+    /// operand through `read_file`, which applies RP1:RP0 banking.
     pub fn read_register(&self, address: u8) -> u8 {
+        self.read_logged(self.resolve(address as u16))
+    }
+    
+    /// Write the register at a bank-resolved address (0x00-0xFF)
+    pub fn write_register(&mut self, address: u8, value: u8) {
+        self.write_logged(self.resolve(address as u16), value);
+    }
+    
+    /// Read a special function register, or 0 if this device lacks it
+    /// (e.g. PIR1, whose address is general purpose RAM on a 16F84A)
+    pub fn read_sfr(&self, address: u8) -> u8 {
+        if self.device.model().has_sfr(address) {
+            self.read_register(address)
+        } else {
+            0
+        }
+    }
+    
+    /// Read a file register operand (0x00-0x7F) in the selected bank
+    pub fn read_file(&self, f: u8) -> u8 {
+        self.read_logged(self.effective_address(f))
+    }
+    
+    /// Write a file register operand (0x00-0x7F) in the selected bank
+    pub fn write_file(&mut self, f: u8, value: u8) {
+        self.write_logged(self.effective_address(f), value);
+    }
+    
+    fn read_logged(&self, address: u16) -> u8 {
+        let value = self.read_resolved(address);
+        if let Some(log) = self.access_log.borrow_mut().as_mut() {
+            log.push(RegisterAccess {
+                address,
+                kind: AccessKind::Read,
+                old: value,
+                new: value,
+            });
+        }
+        value
+    }
+    
+    fn write_logged(&mut self, address: u16, value: u8) {
+        if self.access_log.get_mut().is_some() {
+            let access = RegisterAccess {
+                address,
+                kind: AccessKind::Write,
+                old: self.read_resolved(address),
+                new: value,
+            };
+            if let Some(log) = self.access_log.get_mut() {
+                log.push(access);
+            }
+        }
+        self.write_resolved(address, value);
+    }
+    
+    fn read_resolved(&self, address: u16) -> u8 {
+        // Banks 2 and 3 hold general purpose RAM only
+        let Ok(register) = u8::try_from(address) else {
+            return self.memory.read_data(address);
+        };
+        if !self.device.model().has_sfr(register) {
+            // General purpose RAM, or a register this device lacks
+            return if self.is_absent(register) { 0 } else { self.memory.read_data(address) };
+        }
+        
         // Handle special registers
-        match address {
-            registers::INDF => {
-                // Indirect addressing: use FSR as address
-                let fsr = self.memory.read_data(registers::FSR);
-                self.memory.read_data(fsr)
-            },
+        match register {
+            // INDF addressing itself through FSR
+            registers::INDF => 0,
             registers::PCL => {
                 // Return low byte of PC
                 (self.pc & 0xFF) as u8
             },
             registers::GPIO => {
-                // Read actual GPIO pin states
+                // Read actual GPIO (PORTA) pin states
                 self.gpio.read_gpio()
             },
+            registers::PORTB => self.gpio.read_port(1),
             registers::TRISIO => {
                 // Read TRIS register (Bank 1)
                 self.gpio.read_tris()
             },
+            registers::TRISB => self.gpio.read_port_tris(1),
             registers::WPU => {
                 // Read Weak Pull-Up register (Bank 1)
                 self.gpio.read_wpu()
@@ -267,129 +692,236 @@ impl Cpu {
                 // Read Timer1 high byte
                 self.timers.timer1.read_high()
             },
-            _ => {
-                // Use banking for other registers
-                let bank = self.get_bank();
-                self.memory.read_data_banked(address, bank)
-            }
+            registers::TMR2 | registers::T2CON | registers::PR2
+            | registers::CCPR1L | registers::CCPR1H | registers::CCP1CON => {
+                self.ccp.read(register)
+            },
+            _ => self.memory.read_data(address),
         }
     }
     
-    /// Write to a register with banking support
-    pub fn write_register(&mut self, address: u8, value: u8) {
-        let bank = self.get_bank();
+    fn write_resolved(&mut self, address: u16, value: u8) {
+        let Ok(register) = u8::try_from(address) else {
+            self.memory.write_data(address, value);
+            return;
+        };
+        if !self.device.model().has_sfr(register) {
+            if !self.is_absent(register) {
+                self.memory.write_data(address, value);
+            }
+            return;
+        }
         
-        match address {
-            registers::INDF => {
-                let fsr = self.memory.read_data(registers::FSR);
-                self.memory.write_data(fsr, value);
+        match register {
+            registers::INDF => {},
+            // FSR<7:5> read as 1 on the baseline core
+            registers::FSR if self.device.model().core == Core::Baseline => {
+                self.memory.write_data(address, value | 0xE0);
             },
+            registers::STATUS => self.write_status(value),
             registers::PCL => {
-                let pclath = self.memory.read_data(registers::PCLATH);
+                let pclath = self.memory.read_data(registers::PCLATH as u16);
                 self.pc = ((pclath as u16) << 8) | (value as u16);
             },
-            registers::TMR0 => {
-                self.memory.write_data_banked(address, value, bank);
-            },
             registers::GPIO => {
-                if bank == 0 {
-                    // Bank 0: GPIO
-                    self.gpio.write_gpio(value);
-                    self.memory.write_data(address, value);
-                } else {
-                    // Bank 1: TRISIO (same address 0x05)
-                    self.gpio.write_tris(value);
-                    self.memory.write_data_banked(address, value, bank);
-                }
+                self.gpio.write_gpio(value);
+                self.memory.write_data(address, value);
+            },
+            registers::PORTB => {
+                self.gpio.write_port(1, value);
+                self.memory.write_data(address, value);
+            },
+            registers::TRISIO => {
+                self.gpio.write_tris(value);
+                self.memory.write_data(address, value);
+                self.update_ccp_pin();
+            },
+            registers::TRISB => {
+                self.gpio.write_port_tris(1, value);
+                self.memory.write_data(address, value);
             },
             registers::WPU => {
                 self.gpio.write_wpu(value);
-                self.memory.write_data_banked(address, value, bank);
+                self.memory.write_data(address, value);
             },
             registers::TMR1L => {
                 self.timers.timer1.write_low(value);
+                self.peripherals_changed = true;
             },
             registers::TMR1H => {
                 self.timers.timer1.write_high(value);
+                self.peripherals_changed = true;
             },
             registers::T1CON => {
                 self.timers.timer1.configure_from_t1con(value);
                 self.memory.write_data(address, value);
+                self.peripherals_changed = true;
             },
             registers::OPTION_REG => {
                 self.timers.timer0.configure_from_option(value);
-                self.memory.write_data_banked(address, value, bank);
+                // NOT_RBPU enables the PORTB pull-ups (16F84A)
+                self.gpio.write_port_wpu(1, if value & 0x80 == 0 { 0xFF } else { 0x00 });
+                // NOT_GPPU is bit 6 on the baseline core
+                if self.device.model().core == Core::Baseline {
+                    self.gpio.write_wpu(if value & 0x40 == 0 { 0xFF } else { 0x00 });
+                }
+                self.memory.write_data(address, value);
+                self.peripherals_changed = true;
             },
-            _ => {
-                self.memory.write_data_banked(address, value, bank);
+            registers::TMR2 | registers::T2CON | registers::PR2
+            | registers::CCPR1L | registers::CCPR1H | registers::CCP1CON => {
+                self.ccp.write(register, value);
+                self.peripherals_changed = true;
+                self.update_ccp_pin();
+            },
+            _ => self.memory.write_data(address, value),
+        }
+    }
+    
+    /// Check if `address` selects a named register this device lacks
+    /// (e.g. the ADC registers on a 12F629), or any unimplemented address
+    /// on the baseline core, which reads as 0
+    fn is_absent(&self, address: u8) -> bool {
+        let model = self.device.model();
+        (registers::name_of(address).is_some() || model.core == Core::Baseline)
+            && !model.is_implemented(address)
+    }
+    
+    /// Resolve a bank-resolved address to the register it accesses:
+    /// INDF goes through FSR (and IRP on four-bank parts), and registers
+    /// and RAM mapped into every bank resolve to their bank 0 address
+    fn resolve(&self, address: u16) -> u16 {
+        let model = self.device.model();
+        let address = if address & 0x7F == registers::INDF as u16 {
+            let fsr = self.memory.read_data(registers::FSR as u16);
+            match model.core {
+                Core::Midrange => {
+                    let irp = self.test_status_bit(status_bits::IRP) as u16;
+                    ((irp << 8) | fsr as u16) % (self.memory.banks() * BANK_SIZE) as u16
+                }
+                Core::Baseline => model.map_file(fsr) as u16,
             }
+        } else {
+            address
+        };
+        if model.core == Core::Baseline {
+            return address;
+        }
+        let low = (address & 0x7F) as u8;
+        let unbanked = matches!(low,
+            registers::INDF | registers::PCL | registers::STATUS
+            | registers::FSR | registers::PCLATH | registers::INTCON);
+        if unbanked || model.shared_ram.contains(&low) {
+            low as u16
+        } else {
+            address
         }
     }
     
-    /// Get current bank selection from STATUS register
-    /// Reference: Section 2.3 - STATUS Register, RP0 bit
-    fn get_bank(&self) -> u8 {
-        let status = self.memory.read_data(registers::STATUS);
-        if status & (1 << status_bits::RP0) != 0 {
-            1 // Bank 1
+    /// Resolve a file register operand to the register it accesses,
+    /// applying RP1:RP0 banking and indirect addressing through FSR
+    pub fn effective_address(&self, f: u8) -> u16 {
+        let model = self.device.model();
+        if model.core == Core::Baseline {
+            self.resolve(model.map_file(f) as u16)
+        } else if f < 0x80 {
+            self.resolve((self.get_bank() * BANK_SIZE as u16) | f as u16)
         } else {
-            0 // Bank 0
+            self.resolve(f as u16)
         }
     }
     
+    /// Start recording register reads and writes
+    pub fn begin_access_tracking(&mut self) {
+        let mut log = std::mem::take(&mut self.spare_access_log);
+        log.clear();
+        *self.access_log.get_mut() = Some(log);
+    }
+    
+    /// Stop recording and return the accesses made since tracking began
+    /// STATUS flag updates made by instructions are not recorded
+    pub fn end_access_tracking(&mut self) -> Vec<RegisterAccess> {
+        self.access_log.get_mut().take().unwrap_or_default()
+    }
+    
+    /// Hand back a log returned by `end_access_tracking` so the next
+    /// tracked instruction reuses its buffer
+    pub fn recycle_access_log(&mut self, log: Vec<RegisterAccess>) {
+        self.spare_access_log = log;
+    }
+    
+    /// Get current bank selection from STATUS register
+    /// Reference: Section 2.3 - STATUS Register, RP0 bit
+    ///
+    /// RP1 selects banks 2 and 3 and is ignored on two-bank parts.
+    fn get_bank(&self) -> u16 {
+        self.bank
+    }
+    
     // ==================== Status Flag Operations ====================
     
+    /// Write STATUS, updating its shadow, the cached bank and data memory
+    fn write_status(&mut self, value: u8) {
+        self.status = value;
+        let bank = (value >> status_bits::RP0) as usize & 0x03;
+        self.bank = (bank % self.memory.banks()) as u16;
+        self.memory.write_data(registers::STATUS as u16, value);
+    }
+    
+    /// Reload the STATUS shadow after data memory was replaced or resized
+    fn sync_status(&mut self) {
+        self.write_status(self.memory.read_data(registers::STATUS as u16));
+    }
+    
+    /// Set `bit` in STATUS to `value`
+    fn put_status_bit(&mut self, bit: u8, value: bool) {
+        let status = (self.status & !(1 << bit)) | ((value as u8) << bit);
+        if status != self.status {
+            self.write_status(status);
+        }
+    }
+    
     /// Set a bit in the STATUS register
     pub fn set_status_bit(&mut self, bit: u8) {
-        let status = self.read_register(registers::STATUS);
-        self.write_register(registers::STATUS, status | (1 << bit));
+        self.put_status_bit(bit, true);
     }
     
     /// Clear a bit in the STATUS register
     pub fn clear_status_bit(&mut self, bit: u8) {
-        let status = self.read_register(registers::STATUS);
-        self.write_register(registers::STATUS, status & !(1 << bit));
+        self.put_status_bit(bit, false);
     }
     
     /// Test a bit in the STATUS register
     pub fn test_status_bit(&self, bit: u8) -> bool {
-        let status = self.memory.read_data(registers::STATUS);
-        (status & (1 << bit)) != 0
+        (self.status & (1 << bit)) != 0
     }
     
     /// Update Zero flag based on result
     pub fn update_zero_flag(&mut self, result: u8) {
-        if result == 0 {
-            self.set_status_bit(status_bits::Z);
-        } else {
-            self.clear_status_bit(status_bits::Z);
-        }
+        self.put_status_bit(status_bits::Z, result == 0);
     }
     
     /// Update Carry flag
     pub fn update_carry_flag(&mut self, carry: bool) {
-        if carry {
-            self.set_status_bit(status_bits::C);
-        } else {
-            self.clear_status_bit(status_bits::C);
-        }
+        self.put_status_bit(status_bits::C, carry);
     }
     
     /// Update Digit Carry flag (for BCD operations)
     pub fn update_digit_carry_flag(&mut self, dc: bool) {
-        if dc {
-            self.set_status_bit(status_bits::DC);
-        } else {
-            self.clear_status_bit(status_bits::DC);
-        }
+        self.put_status_bit(status_bits::DC, dc);
     }
     
-    /// Update all arithmetic flags (Z, C, DC)
+    /// Update all arithmetic flags (Z, C, DC) with a single STATUS write
     /// Reference: Section 10.0 - Instruction Set Summary
     pub fn update_arithmetic_flags(&mut self, result: u8, carry: bool, digit_carry: bool) {
-        self.update_zero_flag(result);
-        self.update_carry_flag(carry);
-        self.update_digit_carry_flag(digit_carry);
+        let flags = ((result == 0) as u8) << status_bits::Z
+            | (carry as u8) << status_bits::C
+            | (digit_carry as u8) << status_bits::DC;
+        let mask = 1 << status_bits::Z | 1 << status_bits::C | 1 << status_bits::DC;
+        let status = (self.status & !mask) | flags;
+        if status != self.status {
+            self.write_status(status);
+        }
     }
     
     // ==================== W Register Access ====================
@@ -413,12 +945,21 @@ impl Cpu {
     
     /// Set PC value (masked to 13 bits)
     pub fn set_pc(&mut self, address: u16) {
-        self.pc = address & 0x1FFF;
+        self.pc = address & self.pc_mask();
     }
     
     /// Increment PC (for normal instruction flow)
     pub fn increment_pc(&mut self) {
-        self.pc = (self.pc + 1) & 0x1FFF;
+        self.pc = (self.pc + 1) & self.pc_mask();
+    }
+    
+    /// Implemented PC bits: 13 on the midrange core; the baseline PC
+    /// wraps at the end of program memory
+    fn pc_mask(&self) -> u16 {
+        match self.device.model().core {
+            Core::Midrange => 0x1FFF,
+            Core::Baseline => self.memory.program_size() as u16 - 1,
+        }
     }
     
     // ==================== Stack Operations ====================
@@ -441,6 +982,9 @@ impl Cpu {
     }
     
     /// Get mutable reference to memory system
+    ///
+    /// STATUS is shadowed in the CPU; change it with `write_register`,
+    /// not by writing data memory directly.
     pub fn memory_mut(&mut self) -> &mut Memory {
         &mut self.memory
     }
@@ -461,6 +1005,40 @@ impl Cpu {
     pub fn fetch_instruction(&self) -> u16 {
         self.memory.read_program(self.pc)
     }
+
+    /// Save the core registers, memory and all peripherals
+    pub fn save_state(&self, record: &mut StateRecord) {
+        record.set("cpu.device", self.device.name());
+        record.set("cpu.w", self.w);
+        record.set("cpu.pc", self.pc);
+        record.set("cpu.cycles", self.cycles);
+        record.set("cpu.sleeping", self.sleeping);
+        self.memory.save_state(record);
+        self.gpio.save_state(record);
+        self.timers.save_state(record);
+        self.interrupts.save_state(record);
+        self.wdt.save_state(record);
+        self.ccp.save_state(record);
+    }
+    
+    /// Restore state saved with `save_state`
+    pub fn load_state(&mut self, record: &StateRecord) -> Result<(), StateError> {
+        let device: String = record.get("cpu.device")?;
+        self.set_device(Device::from_name(&device)
+            .ok_or(StateError::UnknownDevice(device))?);
+        self.w = record.get("cpu.w")?;
+        self.pc = record.get("cpu.pc")?;
+        self.cycles = record.get("cpu.cycles")?;
+        self.sleeping = record.get("cpu.sleeping")?;
+        self.peripherals_changed = true;
+        self.memory.load_state(record)?;
+        self.sync_status();
+        self.gpio.load_state(record)?;
+        self.timers.load_state(record)?;
+        self.interrupts.load_state(record)?;
+        self.wdt.load_state(record)?;
+        self.ccp.load_state(record)
+    }
 }
 
 impl Default for Cpu {
@@ -589,4 +1167,206 @@ mod tests {
         cpu.write_register(registers::WPU, 0x01);
         assert_eq!(cpu.gpio().read_wpu(), 0x01);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_trisio_absolute_write() {
+        let mut cpu = Cpu::new();
+        cpu.reset();
+        
+        // Writing TRISIO by its absolute address reaches the GPIO model
+        // from bank 0, not just data memory
+        cpu.write_register(registers::TRISIO, 0x30);
+        assert_eq!(cpu.gpio().read_tris(), 0x38);
+        assert!(!cpu.gpio().is_input(0));
+        assert_eq!(cpu.read_register(registers::TRISIO), 0x38);
+    }
+    
+    #[test]
+    fn test_access_tracking() {
+        let mut cpu = Cpu::new();
+        cpu.reset();
+        
+        cpu.write_register(registers::GPIO, 0x01);
+        assert!(cpu.end_access_tracking().is_empty());
+        
+        cpu.begin_access_tracking();
+        cpu.set_status_bit(status_bits::RP0);
+        cpu.write_file(registers::GPIO, 0x3E);
+        cpu.read_file(0x20);
+        let accesses = cpu.end_access_tracking();
+        
+        // Flag updates are not recorded; GPIO resolves to TRISIO in bank 1
+        assert_eq!(accesses.len(), 2);
+        assert_eq!(accesses[0].address, registers::TRISIO as u16);
+        assert_eq!(accesses[0].kind, AccessKind::Write);
+        assert_eq!((accesses[0].old, accesses[0].new), (0x3F, 0x3E));
+        assert_eq!(accesses[1].address, 0x20);
+        assert_eq!(accesses[1].kind, AccessKind::Read);
+        
+        assert_eq!(registers::address_of("option_reg"), Some(registers::OPTION_REG));
+        assert_eq!(registers::name_of(registers::TRISIO), Some("TRISIO"));
+    }
+    
+    #[test]
+    fn test_device_registers() {
+        let mut pic675 = Cpu::new();
+        let mut pic629 = Cpu::with_device(Device::Pic12F629);
+        for cpu in [&mut pic675, &mut pic629] {
+            cpu.reset();
+            cpu.write_register(registers::ADCON0, 0x81);
+            cpu.write_register(registers::CMCON, 0x07);
+        }
+        
+        // The 629 has no ADC: its registers read as 0
+        assert_eq!(pic675.read_register(registers::ADCON0), 0x81);
+        assert_eq!(pic629.read_register(registers::ADCON0), 0x00);
+        assert_eq!(pic629.read_register(registers::CMCON), 0x07);
+        
+        let mut record = StateRecord::new();
+        pic629.save_state(&mut record);
+        pic675.load_state(&record).unwrap();
+        assert_eq!(pic675.device(), Device::Pic12F629);
+    }
+    
+    #[test]
+    fn test_pic12f683() {
+        let mut cpu = Cpu::with_device(Device::Pic12F683);
+        cpu.reset();
+        assert_eq!(cpu.memory().program_size(), 2048);
+        
+        // Bank 1 has its own RAM at 0xA0; 0x70-0x7F is shared
+        cpu.set_status_bit(status_bits::RP0);
+        cpu.write_file(0x20, 0x11);
+        cpu.write_file(0x70, 0x22);
+        assert_eq!(cpu.read_register(0xA0), 0x11);
+        assert_eq!(cpu.read_register(0x20), 0x00);
+        assert_eq!(cpu.read_register(0x70), 0x22);
+        
+        // 25% PWM over a 40-cycle period drives GP2 once TRISIO2 is clear
+        cpu.write_file(registers::PR2 & 0x7F, 9);
+        cpu.write_file(registers::TRISIO & 0x7F, 0x3B);
+        cpu.clear_status_bit(status_bits::RP0);
+        cpu.write_file(registers::CCPR1L, 2);
+        cpu.write_file(registers::CCP1CON, 0x2C);
+        cpu.write_file(registers::T2CON, 0x05);
+        let mut high = 0;
+        for cycle in 0..440 {
+            cpu.tick_peripherals();
+            // The first period starts when TMR2 first matches PR2
+            if cycle >= 40 {
+                high += (cpu.read_register(registers::GPIO) >> 2) & 1;
+            }
+        }
+        assert_eq!(high, 100);
+        assert_eq!(cpu.gpio().get_pin_state(2), crate::gpio::PinState::High);
+    }    
+    #[test]
+    fn test_pic16f84a() {
+        let mut cpu = Cpu::with_device(Device::Pic16F84A);
+        cpu.reset();
+        assert_eq!(cpu.gpio().port_count(), 2);
+        
+        // PORTB as outputs via TRISB in bank 1; RAM starts at 0x0C in both banks
+        cpu.set_status_bit(status_bits::RP0);
+        cpu.write_file(registers::TRISB & 0x7F, 0x0F);
+        cpu.write_file(0x0C, 0x5A);
+        cpu.clear_status_bit(status_bits::RP0);
+        cpu.write_file(registers::PORTB, 0xA0);
+        assert_eq!(cpu.read_register(registers::PORTB), 0xAF);
+        assert_eq!(cpu.gpio().get_pin_state(15), crate::gpio::PinState::High);
+        assert_eq!(cpu.read_file(0x0C), 0x5A);
+        assert_eq!(cpu.read_sfr(registers::PIR1), 0x00);
+        
+        // Clearing NOT_RBPU enables the PORTB pull-ups
+        cpu.write_register(registers::OPTION_REG, 0x7F);
+        assert_eq!(cpu.gpio().read_port_wpu(1), 0xFF);
+        
+        // TMR1L's address is general purpose RAM on this part
+        cpu.write_register(registers::TMR1L, 0x33);
+        assert_eq!(cpu.read_register(registers::TMR1L), 0x33);
+        assert!(!cpu.timers().timer1.is_enabled());
+    }
+    
+    #[test]
+    fn test_four_banks() {
+        let mut cpu = Cpu::new();
+        cpu.memory_mut().set_layout(crate::memory::MemoryLayout { banks: 4, ..Default::default() });
+        cpu.reset();
+        
+        // RP1:RP0 = 10 selects bank 2 (0x20-0x5F stays shared with bank 0);
+        // IRP extends FSR to banks 2 and 3
+        cpu.set_status_bit(status_bits::RP1);
+        cpu.write_file(0x60, 0x5A);
+        assert_eq!(cpu.effective_address(0x60), 0x160);
+        assert_eq!(cpu.effective_address(0x20), 0x20);
+        assert_eq!(cpu.memory().read_data(0x160), 0x5A);
+        cpu.clear_status_bit(status_bits::RP1);
+        assert_eq!(cpu.read_file(0x60), 0x00);
+        
+        cpu.write_register(registers::FSR, 0x60);
+        cpu.set_status_bit(status_bits::IRP);
+        assert_eq!(cpu.read_file(registers::INDF), 0x5A);
+        
+        // Two-bank parts ignore RP1
+        let mut cpu = Cpu::new();
+        cpu.reset();
+        cpu.set_status_bit(status_bits::RP1);
+        assert_eq!(cpu.effective_address(0x60), 0x60);
+    }
+    
+    #[test]
+    fn test_status_shadow() {
+        let mut cpu = Cpu::new();
+        cpu.reset();
+        
+        // Writing STATUS as a register selects the bank straight away
+        cpu.write_register(registers::STATUS, 0x18 | 1 << status_bits::RP0);
+        assert_eq!(cpu.effective_address(0x05), 0x85);
+        
+        // Flag updates keep RP0 and land in data memory
+        cpu.update_arithmetic_flags(0, true, false);
+        assert_eq!(cpu.memory().read_data(registers::STATUS as u16), 0x3D);
+        assert!(cpu.test_status_bit(status_bits::Z));
+        cpu.update_zero_flag(1);
+        assert_eq!(cpu.read_register(registers::STATUS), 0x39);
+        
+        // Restored state brings the bank selection with it
+        let mut record = StateRecord::new();
+        cpu.save_state(&mut record);
+        let mut restored = Cpu::new();
+        restored.reset();
+        restored.load_state(&record).unwrap();
+        assert_eq!(restored.effective_address(0x05), 0x85);
+        assert!(restored.test_status_bit(status_bits::C));
+        
+        cpu.reset();
+        assert_eq!(cpu.effective_address(0x05), 0x05);
+    }
+    
+    #[test]
+    fn test_reset_reasons() {
+        let mut cpu = Cpu::new();
+        cpu.reset();
+        cpu.write_register(0x20, 0x55);
+        
+        // MCLR keeps RAM; PD tells a reset from sleep apart
+        cpu.reset_with(ResetReason::Mclr);
+        assert_eq!(cpu.read_register(0x20), 0x55);
+        assert_eq!(cpu.read_register(registers::STATUS) & 0x18, 0x18);
+        cpu.enter_sleep();
+        cpu.reset_with(ResetReason::Mclr);
+        assert_eq!(cpu.read_register(registers::STATUS) & 0x18, 0x10);
+        
+        cpu.reset_with(ResetReason::Wdt);
+        assert_eq!(cpu.read_register(registers::STATUS) & 0x18, 0x08);
+        assert_eq!(cpu.read_register(0x20), 0x55);
+        
+        cpu.reset_with(ResetReason::BrownOut);
+        assert_eq!(cpu.read_register(registers::PCON) & 0x03, 1 << pcon_bits::NOT_POR);
+        
+        cpu.reset_with(ResetReason::PowerOn);
+        assert_eq!(cpu.read_register(0x20), 0x00);
+        assert_eq!(cpu.read_register(registers::PCON) & 0x03, 0x00);
+        assert_eq!(ResetReason::from_name("BOR"), Some(ResetReason::BrownOut));
+    }
+}
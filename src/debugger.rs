@@ -1,47 +1,99 @@
-/// PIC12F629/675 Debugger
-/// 
-/// Provides debugging utilities: disassembler, memory dump, register display
+//! PIC12F629/675 Debugger
+//! 
+//! Provides debugging utilities: disassembler, memory dump, register display
 
-use crate::{Cpu, InstructionDecoder, Instruction};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::{Core, Cpu, InstructionDecoder, Instruction};
 use crate::cpu::{registers, status_bits};
+use crate::callstack::{CallStack, FrameKind};
+use crate::profiler::Profiler;
+use crate::symbols::SymbolTable;
+use crate::logic::LogicAnalyzer;
+use crate::gpio::pin_label;
+use crate::interrupt::InterruptSource;
+use crate::latency::{CycleStats, InterruptTiming};
+use crate::power::PowerMeter;
+use crate::device::Device;
 
 pub struct Debugger;
 
 impl Debugger {
-    /// Disassemble an instruction word
-    pub fn disassemble(word: u16) -> String {
-        match InstructionDecoder::decode(word) {
+    /// Disassemble an instruction word of `core`
+    pub fn disassemble(core: Core, word: u16) -> String {
+        match InstructionDecoder::decode_for(core, word) {
             Ok(inst) => Self::format_instruction(&inst),
             Err(_) => format!("??? 0x{:04X}", word),
         }
     }
     
+    /// Disassemble an instruction word, showing CALL/GOTO targets by label
+    pub fn disassemble_with_symbols(core: Core, word: u16, symbols: &SymbolTable) -> String {
+        Self::disassemble_with_options(core, word, symbols, false)
+    }
+    
+    /// Disassemble an instruction word with labels and, if `sfr_names`
+    /// is set, symbolic SFR and bit names
+    pub fn disassemble_with_options(core: Core, word: u16, symbols: &SymbolTable, sfr_names: bool) -> String {
+        let Ok(inst) = InstructionDecoder::decode_for(core, word) else {
+            return format!("??? 0x{:04X}", word);
+        };
+        let labelled = match inst {
+            Instruction::CALL { k } => symbols.label_at(k).map(|label| format!("CALL {}", label)),
+            Instruction::GOTO { k } => symbols.label_at(k).map(|label| format!("GOTO {}", label)),
+            _ => None,
+        };
+        labelled.unwrap_or_else(|| Self::format_instruction_with(&inst, sfr_names))
+    }
+    
     /// Format an instruction as assembly-like string
     pub fn format_instruction(inst: &Instruction) -> String {
+        Self::format_instruction_with(inst, false)
+    }
+    
+    /// Format an instruction, optionally naming known SFRs and their bits
+    /// (`BSF STATUS, RP0` instead of `BSF 0x03, 5`)
+    pub fn format_instruction_with(inst: &Instruction, sfr_names: bool) -> String {
+        let reg = |f: &u8| match Self::sfr_operand(*f).filter(|_| sfr_names) {
+            Some((_, name)) => name.to_string(),
+            None => format!("0x{:02X}", f),
+        };
+        let bit = |f: &u8, b: &u8| {
+            Self::sfr_operand(*f)
+                .filter(|_| sfr_names)
+                .and_then(|(address, _)| registers::bit_name(address, *b))
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| b.to_string())
+        };
+        let dest = |d: &u8| if *d == 0 { "W" } else { "F" };
+        
         match inst {
-            Instruction::ADDWF { f, d } => format!("ADDWF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::ANDWF { f, d } => format!("ANDWF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::CLRF { f } => format!("CLRF 0x{:02X}", f),
+            Instruction::ADDWF { f, d } => format!("ADDWF {}, {}", reg(f), dest(d)),
+            Instruction::ANDWF { f, d } => format!("ANDWF {}, {}", reg(f), dest(d)),
+            Instruction::CLRF { f } => format!("CLRF {}", reg(f)),
             Instruction::CLRW => "CLRW".to_string(),
-            Instruction::COMF { f, d } => format!("COMF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::DECF { f, d } => format!("DECF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::DECFSZ { f, d } => format!("DECFSZ 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::INCF { f, d } => format!("INCF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::INCFSZ { f, d } => format!("INCFSZ 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::IORWF { f, d } => format!("IORWF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::MOVF { f, d } => format!("MOVF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::MOVWF { f } => format!("MOVWF 0x{:02X}", f),
+            Instruction::COMF { f, d } => format!("COMF {}, {}", reg(f), dest(d)),
+            Instruction::DECF { f, d } => format!("DECF {}, {}", reg(f), dest(d)),
+            Instruction::DECFSZ { f, d } => format!("DECFSZ {}, {}", reg(f), dest(d)),
+            Instruction::INCF { f, d } => format!("INCF {}, {}", reg(f), dest(d)),
+            Instruction::INCFSZ { f, d } => format!("INCFSZ {}, {}", reg(f), dest(d)),
+            Instruction::IORWF { f, d } => format!("IORWF {}, {}", reg(f), dest(d)),
+            Instruction::MOVF { f, d } => format!("MOVF {}, {}", reg(f), dest(d)),
+            Instruction::MOVWF { f } => format!("MOVWF {}", reg(f)),
             Instruction::NOP => "NOP".to_string(),
-            Instruction::RLF { f, d } => format!("RLF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::RRF { f, d } => format!("RRF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::SUBWF { f, d } => format!("SUBWF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::SWAPF { f, d } => format!("SWAPF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
-            Instruction::XORWF { f, d } => format!("XORWF 0x{:02X}, {}", f, if *d == 0 { "W" } else { "F" }),
+            Instruction::RLF { f, d } => format!("RLF {}, {}", reg(f), dest(d)),
+            Instruction::RRF { f, d } => format!("RRF {}, {}", reg(f), dest(d)),
+            Instruction::SUBWF { f, d } => format!("SUBWF {}, {}", reg(f), dest(d)),
+            Instruction::SWAPF { f, d } => format!("SWAPF {}, {}", reg(f), dest(d)),
+            Instruction::XORWF { f, d } => format!("XORWF {}, {}", reg(f), dest(d)),
             
-            Instruction::BCF { f, b } => format!("BCF 0x{:02X}, {}", f, b),
-            Instruction::BSF { f, b } => format!("BSF 0x{:02X}, {}", f, b),
-            Instruction::BTFSC { f, b } => format!("BTFSC 0x{:02X}, {}", f, b),
-            Instruction::BTFSS { f, b } => format!("BTFSS 0x{:02X}, {}", f, b),
+            Instruction::BCF { f, b } => format!("BCF {}, {}", reg(f), bit(f, b)),
+            Instruction::BSF { f, b } => format!("BSF {}, {}", reg(f), bit(f, b)),
+            Instruction::BTFSC { f, b } => format!("BTFSC {}, {}", reg(f), bit(f, b)),
+            Instruction::BTFSS { f, b } => format!("BTFSS {}, {}", reg(f), bit(f, b)),
             
             Instruction::ADDLW { k } => format!("ADDLW 0x{:02X}", k),
             Instruction::ANDLW { k } => format!("ANDLW 0x{:02X}", k),
@@ -56,11 +108,35 @@ impl Debugger {
             Instruction::SLEEP => "SLEEP".to_string(),
             Instruction::SUBLW { k } => format!("SUBLW 0x{:02X}", k),
             Instruction::XORLW { k } => format!("XORLW 0x{:02X}", k),
+            Instruction::OPTION => "OPTION".to_string(),
+            Instruction::TRIS { f } => format!("TRIS 0x{:02X}", f),
         }
     }
     
+    /// SFR named by a 7-bit file operand. The bank is not known statically,
+    /// so the bank 0 register is preferred (GPIO over TRISIO), falling back
+    /// to the bank 1 register when bank 0 has none at that offset. Registers
+    /// of the default device win over those of other parts (WPU over the
+    /// 12F683's CCP1CON).
+    fn sfr_operand(f: u8) -> Option<(u8, &'static str)> {
+        let named = |address| registers::name_of(address).map(|name| (address, name));
+        let model = Device::default().model();
+        [f, f | 0x80].into_iter()
+            .filter(|&address| model.has_sfr(address))
+            .find_map(named)
+            .or_else(|| [f, f | 0x80].into_iter().find_map(named))
+    }
+    
     /// Disassemble a range of program memory
     pub fn disassemble_range(cpu: &Cpu, start: u16, count: u16) {
+        Self::disassemble_range_with_symbols(cpu, &SymbolTable::new(), start, count);
+    }
+    
+    /// Disassemble a range of program memory, showing labels for symbols
+    /// and branch targets
+    pub fn disassemble_range_with_symbols(cpu: &Cpu, symbols: &SymbolTable, start: u16, count: u16) {
+        let labels = Self::branch_labels(cpu, symbols);
+        
         println!("\nDisassembly:");
         println!("Addr   Hex    Assembly");
         println!("------ ------ ----------------");
@@ -68,13 +144,177 @@ impl Debugger {
         for i in 0..count {
             let addr = start + i;
             let word = cpu.memory().read_program(addr);
-            let asm = Self::disassemble(word);
+            let asm = Self::disassemble_with_labels(cpu.device().core(), word, &labels, false);
+            
+            if let Some(label) = labels.get(&addr) {
+                println!("{}:", label);
+            }
             
             let marker = if addr == cpu.get_pc() { ">" } else { " " };
             println!("{} 0x{:04X} 0x{:04X} {}", marker, addr, word, asm);
         }
     }
     
+    /// Write a re-assemblable listing of program memory to `path`
+    pub fn disassemble_to_file<P: AsRef<Path>>(cpu: &Cpu, path: P) -> Result<(), String> {
+        Self::disassemble_to_file_with_symbols(cpu, &SymbolTable::new(), path)
+    }
+    
+    /// Write a re-assemblable listing of program memory to `path`,
+    /// naming branch targets from `symbols`
+    pub fn disassemble_to_file_with_symbols<P: AsRef<Path>>(
+        cpu: &Cpu,
+        symbols: &SymbolTable,
+        path: P,
+    ) -> Result<(), String> {
+        fs::write(path, Self::format_listing(cpu, symbols))
+            .map_err(|e| format!("Failed to write file: {}", e))
+    }
+    
+    /// Format program memory as gpasm/MPASM source
+    ///
+    /// SFRs and their bits are declared with EQU and used by name, branch
+    /// targets are labelled, and blank regions (erased 0x3FFF words and
+    /// unused memory after the program) are skipped with ORG directives.
+    pub fn format_listing(cpu: &Cpu, symbols: &SymbolTable) -> String {
+        let labels = Self::branch_labels(cpu, symbols);
+        let memory = cpu.memory();
+        let mut out = String::new();
+        
+        let _ = writeln!(out, "; Disassembly listing generated by pic_simulator");
+        let _ = writeln!(out, "        LIST    P={}", cpu.device().name());
+        let _ = writeln!(out);
+        let _ = writeln!(out, "; Destination select");
+        let _ = writeln!(out, "{:<15} EQU     0", "W");
+        let _ = writeln!(out, "{:<15} EQU     1", "F");
+        let _ = writeln!(out);
+        let _ = writeln!(out, "; Special function registers");
+        for (address, name) in cpu.device().model().sfr_names() {
+            let _ = writeln!(out, "{:<15} EQU     0x{:02X}", name, address);
+        }
+        let _ = writeln!(out);
+        let _ = writeln!(out, "; Register bits");
+        let model = cpu.device().model();
+        let named_here = |address: u8| model.name_of(address).is_some_and(|name| registers::name_of(address) == Some(name));
+        for (_, bits) in registers::BIT_NAMES.iter().filter(|(address, _)| named_here(*address)) {
+            for (bit, name) in bits.iter().enumerate().filter(|(_, name)| !name.is_empty()) {
+                let _ = writeln!(out, "{:<15} EQU     {}", name, bit);
+            }
+        }
+        
+        let end = (0..memory.program_size() as u16)
+            .rposition(|addr| memory.read_program(addr) != 0)
+            .map_or(0, |last| last as u16 + 1);
+        let mut next = None;
+        
+        for addr in 0..end {
+            let word = memory.read_program(addr);
+            if word == 0x3FFF {
+                continue;
+            }
+            if next != Some(addr) {
+                let _ = writeln!(out);
+                let _ = writeln!(out, "        ORG     0x{:03X}", addr);
+            }
+            if let Some(label) = labels.get(&addr) {
+                let _ = writeln!(out, "{}:", label);
+            }
+            
+            let asm = Self::disassemble_with_labels(cpu.device().core(), word, &labels, true);
+            let line = match asm.split_once(' ') {
+                Some((mnemonic, operands)) => format!("{:<8}{}", mnemonic, operands),
+                None => asm,
+            };
+            let _ = writeln!(out, "        {:<28}; 0x{:03X}  {:04X}", line, addr, word);
+            next = Some(addr + 1);
+        }
+        
+        let _ = writeln!(out);
+        let _ = writeln!(out, "        END");
+        out
+    }
+    
+    /// Scan program memory for GOTO/CALL targets and label them
+    ///
+    /// Targets are named from `symbols` where possible and otherwise get
+    /// an auto-label such as `L_004`. Symbol labels that are not branch
+    /// targets are included too.
+    pub fn branch_labels(cpu: &Cpu, symbols: &SymbolTable) -> BTreeMap<u16, String> {
+        let mut labels: BTreeMap<u16, String> = symbols.labels()
+            .map(|(addr, label)| (addr, label.to_string()))
+            .collect();
+        
+        for addr in 0..cpu.memory().program_size() as u16 {
+            let word = cpu.memory().read_program(addr);
+            if let Ok(Instruction::CALL { k } | Instruction::GOTO { k }) = InstructionDecoder::decode_for(cpu.device().core(), word) {
+                labels.entry(k).or_insert_with(|| format!("L_{:03X}", k));
+            }
+        }
+        
+        labels
+    }
+    
+    /// Disassemble an instruction word, naming CALL/GOTO targets from `labels`
+    pub fn disassemble_with_labels(core: Core, word: u16, labels: &BTreeMap<u16, String>, sfr_names: bool) -> String {
+        let Ok(inst) = InstructionDecoder::decode_for(core, word) else {
+            return format!("??? 0x{:04X}", word);
+        };
+        let labelled = match inst {
+            Instruction::CALL { k } => labels.get(&k).map(|label| format!("CALL {}", label)),
+            Instruction::GOTO { k } => labels.get(&k).map(|label| format!("GOTO {}", label)),
+            _ => None,
+        };
+        labelled.unwrap_or_else(|| Self::format_instruction_with(&inst, sfr_names))
+    }
+    
+    /// Display source lines around `file:line`, marking `current_line`
+    pub fn display_source(symbols: &SymbolTable, file: &str, line: usize, current_line: Option<usize>) {
+        println!("\n{}:", file);
+        
+        let mut shown = false;
+        for n in line.saturating_sub(5).max(1)..=line + 5 {
+            if let Some(text) = symbols.source_line(file, n) {
+                let marker = if Some(n) == current_line { ">" } else { " " };
+                println!("{} {:>5}  {}", marker, n, text);
+                shown = true;
+            }
+        }
+        
+        if !shown {
+            println!("  (no source text for line {})", line);
+        }
+    }
+    
+    /// Display captured transitions and timing measurements for a pin
+    pub fn display_pin_capture(analyzer: &LogicAnalyzer, pin: u8, count: usize) {
+        println!("\n{} capture:", pin_label(pin));
+        
+        let transitions: Vec<_> = analyzer.pin_transitions(pin).collect();
+        if transitions.is_empty() {
+            println!("  No transitions captured");
+            return;
+        }
+        
+        println!("  Cycle         Edge");
+        for t in transitions.iter().skip(transitions.len().saturating_sub(count)) {
+            println!("  {:<12}  {}", t.cycle, if t.level { "↑ rising" } else { "↓ falling" });
+        }
+        
+        println!();
+        if let Some(width) = analyzer.last_pulse_width(pin, true) {
+            println!("  Last high pulse: {} cycles", width);
+        }
+        if let Some(width) = analyzer.last_pulse_width(pin, false) {
+            println!("  Last low pulse:  {} cycles", width);
+        }
+        if let Some(period) = analyzer.average_period(pin) {
+            println!("  Average period:  {:.1} cycles", period);
+        }
+        if let Some(duty) = analyzer.duty_cycle(pin) {
+            println!("  Duty cycle:      {:.1}%", duty * 100.0);
+        }
+    }
+    
     /// Display CPU registers
     pub fn display_registers(cpu: &Cpu) {
         println!("\nRegisters:");
@@ -100,8 +340,11 @@ impl Debugger {
         let intcon = cpu.read_register(registers::INTCON);
         println!("  INTCON = 0x{:02X}", intcon);
         
-        let gpio = cpu.read_register(registers::GPIO);
-        println!("  GPIO   = 0x{:02X} = 0b{:06b}", gpio, gpio & 0x3F);
+        for port in cpu.device().model().ports {
+            let value = cpu.read_register(port.address);
+            let width = 8 - port.pins.leading_zeros() as usize;
+            println!("  {:<6} = 0x{:02X} = 0b{:0width$b}", port.name, value, value & port.pins);
+        }
     }
     
     /// Display special function registers
@@ -110,49 +353,35 @@ impl Debugger {
         println!("  Address  Name       Value");
         println!("  -------  ---------  -----");
         
-        let sfr_list = [
-            (0x00, "INDF"),
-            (0x01, "TMR0"),
-            (0x02, "PCL"),
-            (0x03, "STATUS"),
-            (0x04, "FSR"),
-            (0x05, "GPIO"),
-            (0x0A, "PCLATH"),
-            (0x0B, "INTCON"),
-            (0x0C, "PIR1"),
-            (0x0E, "TMR1L"),
-            (0x0F, "TMR1H"),
-            (0x10, "T1CON"),
-        ];
-        
-        for (addr, name) in sfr_list.iter() {
-            let val = cpu.read_register(*addr);
+        for (addr, name) in cpu.device().model().sfr_names().filter(|(addr, _)| *addr < 0x80) {
+            let val = cpu.read_register(addr);
             println!("  0x{:02X}     {:9}  0x{:02X}", addr, name, val);
         }
     }
     
-    /// Dump memory region
-    pub fn dump_memory(cpu: &Cpu, start: u8, count: u8) {
+    /// Dump `count` bytes of data memory from `start`, stopping at 0xFF
+    pub fn dump_memory(cpu: &Cpu, start: u8, count: u16) {
         println!("\nMemory Dump:");
         println!("Addr  +0 +1 +2 +3 +4 +5 +6 +7  +8 +9 +A +B +C +D +E +F  ASCII");
         println!("----  -----------------------------------------------  ----------------");
         
-        let mut addr = start;
-        while addr < start.saturating_add(count) {
+        let end = memory_end(start, count);
+        let mut addr = start as u16;
+        while addr < end {
             print!("0x{:02X}  ", addr);
             
             // Print hex values
             let mut ascii = String::new();
             for i in 0..16 {
-                if addr.saturating_add(i) >= start.saturating_add(count) {
+                if addr + i >= end {
                     print!("   ");
                     ascii.push(' ');
                 } else {
-                    let val = cpu.read_register(addr + i);
+                    let val = cpu.read_register((addr + i) as u8);
                     print!("{:02X} ", val);
                     
                     // ASCII representation
-                    if val >= 0x20 && val <= 0x7E {
+                    if (0x20..=0x7E).contains(&val) {
                         ascii.push(val as char);
                     } else {
                         ascii.push('.');
@@ -165,7 +394,7 @@ impl Debugger {
             }
             
             println!(" {}", ascii);
-            addr = addr.saturating_add(16);
+            addr += 16;
         }
     }
     
@@ -180,12 +409,81 @@ impl Debugger {
         } else {
             println!("  Level  Address");
             println!("  -----  -------");
-            for i in 0..depth {
-                println!("  {}      0x{:04X}", i, stack[i]);
+            for (i, addr) in stack.iter().enumerate().take(depth) {
+                println!("  {}      0x{:04X}", i, addr);
             }
         }
     }
     
+    /// Display the logical call stack (outermost frame first)
+    pub fn display_call_stack(call_stack: &CallStack, current_cycle: u64) {
+        println!("\nCall Stack:");
+        
+        let frames = call_stack.frames();
+        if frames.is_empty() {
+            println!("  main");
+            return;
+        }
+        
+        println!("  {}", Self::format_call_chain(call_stack));
+        println!();
+        println!("  #   Kind  Caller  Callee  Return  Cycles");
+        println!("  --  ----  ------  ------  ------  ------");
+        for (i, frame) in frames.iter().enumerate().rev() {
+            let kind = match frame.kind {
+                FrameKind::Call => "CALL",
+                FrameKind::Interrupt => "INT ",
+            };
+            println!("  {:<2}  {}  0x{:04X}  0x{:04X}  0x{:04X}  {}",
+                i, kind, frame.caller_pc, frame.callee, frame.return_address,
+                frame.cycles_spent(current_cycle));
+        }
+    }
+    
+    /// Format the call stack as a chain, e.g. "main → 0x000B → 0x0020"
+    pub fn format_call_chain(call_stack: &CallStack) -> String {
+        let mut chain = String::from("main");
+        for frame in call_stack.frames() {
+            match frame.kind {
+                FrameKind::Call => chain.push_str(&format!(" → 0x{:04X}", frame.callee)),
+                FrameKind::Interrupt => chain.push_str(&format!(" → ISR@0x{:04X}", frame.callee)),
+            }
+        }
+        chain
+    }
+    
+    /// Display the profiler report: top `count` addresses and all subroutines
+    pub fn display_profile(cpu: &Cpu, profiler: &Profiler, count: usize) {
+        println!("\nProfile ({} cycles{}):", profiler.total_cycles(),
+            if profiler.is_enabled() { "" } else { ", disabled" });
+        
+        if profiler.total_cycles() == 0 {
+            println!("  No data collected");
+            return;
+        }
+        
+        println!("\n  Hot spots:");
+        println!("  Address  Hits      Cycles    %       Instruction");
+        for spot in profiler.hot_spots().iter().take(count) {
+            let word = cpu.memory().read_program(spot.address);
+            println!("  0x{:04X}   {:<8}  {:<8}  {:>5.1}%  {}",
+                spot.address, spot.hits, spot.cycles,
+                profiler.percent(spot.cycles), Self::disassemble(cpu.device().core(), word));
+        }
+        
+        println!("\n  Subroutines:");
+        println!("  Routine  Calls     Self      %       Total");
+        for routine in profiler.subroutines() {
+            let name = match routine.entry {
+                Some(entry) => format!("0x{:04X}", entry),
+                None => "main  ".to_string(),
+            };
+            println!("  {}   {:<8}  {:<8}  {:>5.1}%  {}",
+                name, routine.calls, routine.self_cycles,
+                profiler.percent(routine.self_cycles), routine.total_cycles);
+        }
+    }
+    
     /// Full state dump
     pub fn dump_state(cpu: &Cpu) {
         Self::display_registers(cpu);
@@ -195,44 +493,59 @@ impl Debugger {
 
     /// Display GPIO pin states with visual representation
     pub fn display_gpio(cpu: &Cpu) {
-        println!("\nGPIO Port State:");
-        println!("┌────┬────┬────┬────┬────┬────┐");
-        println!("│GP5 │GP4 │GP3 │GP2 │GP1 │GP0 │");
-        println!("├────┼────┼────┼────┼────┼────┤");
-        
-        // Show pin states
-        print!("│");
-        for pin in (0..6).rev() {
-            let state = cpu.gpio().get_pin_state(pin);
-            match state {
-                crate::gpio::PinState::High => print!(" ●  │"),
-                crate::gpio::PinState::Low => print!(" ○  │"),
-                crate::gpio::PinState::HighZ => print!(" -  │"),
+        for (index, port) in cpu.device().model().ports.iter().enumerate() {
+            let bits: Vec<u8> = (0..8).rev().filter(|bit| port.pins & (1 << bit) != 0).collect();
+            let line = |left: &str, middle: &str, right: &str| {
+                format!("{}{}{}", left, vec!["────"; bits.len()].join(middle), right)
+            };
+            
+            println!("\n{} Port State:", port.name);
+            println!("{}", line("┌", "┬", "┐"));
+            print!("│");
+            for &bit in &bits {
+                print!("{:<4}│", port.pin_name(bit));
             }
-        }
-        println!();
-        
-        // Show directions
-        print!("│");
-        for pin in (0..6).rev() {
-            if cpu.gpio().is_input(pin) {
-                print!(" IN │");
-            } else {
-                print!("OUT │");
+            println!();
+            println!("{}", line("├", "┼", "┤"));
+            
+            // Show pin states
+            print!("│");
+            for &bit in &bits {
+                let state = cpu.gpio().get_pin_state(index as u8 * 8 + bit);
+                match state {
+                    crate::gpio::PinState::High => print!(" ●  │"),
+                    crate::gpio::PinState::Low => print!(" ○  │"),
+                    crate::gpio::PinState::HighZ => print!(" -  │"),
+                }
+            }
+            println!();
+            
+            // Show directions
+            print!("│");
+            for &bit in &bits {
+                if cpu.gpio().is_input(index as u8 * 8 + bit) {
+                    print!(" IN │");
+                } else {
+                    print!("OUT │");
+                }
+            }
+            println!();
+            
+            println!("{}", line("└", "┴", "┘"));
+            
+            // Show register values
+            let width = bits.len();
+            let port_val = cpu.read_register(port.address);
+            let tris_val = cpu.gpio().read_port_tris(index);
+            let tris_name = cpu.device().model().name_of(port.tris_address).unwrap_or("TRIS");
+            
+            println!("\n{:<6} = 0x{:02X} = 0b{:0width$b}", port.name, port_val, port_val);
+            println!("{:<6} = 0x{:02X} = 0b{:0width$b}", tris_name, tris_val, tris_val);
+            if port.pullups != 0 {
+                let wpu_val = cpu.gpio().read_port_wpu(index);
+                println!("WPU    = 0x{:02X} = 0b{:0width$b}", wpu_val, wpu_val);
             }
         }
-        println!();
-        
-        println!("└────┴────┴────┴────┴────┴────┘");
-        
-        // Show register values
-        let gpio_val = cpu.read_register(crate::cpu::registers::GPIO);
-        let tris_val = cpu.gpio().read_tris();
-        let wpu_val = cpu.gpio().read_wpu();
-        
-        println!("\nGPIO   = 0x{:02X} = 0b{:06b}", gpio_val, gpio_val);
-        println!("TRISIO = 0x{:02X} = 0b{:06b}", tris_val, tris_val);
-        println!("WPU    = 0x{:02X} = 0b{:06b}", wpu_val, wpu_val);
     }
 
     /// Display interrupt status
@@ -240,8 +553,8 @@ impl Debugger {
         println!("\nInterrupt Status:");
         
         let intcon = cpu.read_register(crate::cpu::registers::INTCON);
-        let pie1 = cpu.read_register(crate::cpu::registers::PIE1);
-        let pir1 = cpu.read_register(crate::cpu::registers::PIR1);
+        let pie1 = cpu.read_sfr(crate::cpu::registers::PIE1);
+        let pir1 = cpu.read_sfr(crate::cpu::registers::PIR1);
         
         println!("  INTCON = 0x{:02X}", intcon);
         println!("    GIE   = {} (Global Interrupt Enable)", if intcon & 0x80 != 0 { "1" } else { "0" });
@@ -266,7 +579,160 @@ impl Debugger {
         println!("    TMR1IF = {}", if pir1 & 0x01 != 0 { "1" } else { "0" });
         
         println!("\n  In ISR: {}", if cpu.interrupts().in_isr() { "Yes" } else { "No" });
-    }    
+        
+        let interrupts = cpu.interrupts();
+        println!("\n  Taken: {} total", interrupts.total_count());
+        for source in InterruptSource::ALL {
+            let stats = interrupts.stats(source);
+            if stats.count == 0 {
+                continue;
+            }
+            let last = if interrupts.last_source() == Some(source) { " (last)" } else { "" };
+            println!("    {:<16} {:>8}  last at cycle {}{}",
+                source.name(), stats.count, stats.last_cycle.unwrap_or(0), last);
+        }
+    }
+    
+    /// Display interrupt latency and ISR duration per source (in cycles)
+    pub fn display_interrupt_timing(timing: &InterruptTiming) {
+        fn format_stats(stats: &CycleStats) -> String {
+            format!("{}/{:.1}/{}", stats.min, stats.average(), stats.max)
+        }
+        
+        println!("\nInterrupt timing (cycles, min/avg/max):");
+        println!("  {:<16} {:>6}  {:>18}  {:>18}", "Source", "Count", "Latency", "ISR time");
+        let mut any = false;
+        for source in InterruptSource::ALL {
+            let timing = timing.source(source);
+            if timing.latency.count == 0 {
+                continue;
+            }
+            any = true;
+            println!("  {:<16} {:>6}  {:>18}  {:>18}",
+                source.name(), timing.latency.count,
+                format_stats(&timing.latency), format_stats(&timing.isr));
+        }
+        if !any {
+            println!("  No interrupts taken");
+        }
+    }
+    
+    /// Display the estimated supply current and charge
+    pub fn display_power(power: &PowerMeter) {
+        let total = power.run_cycles() + power.sleep_cycles();
+        let percent = |cycles: u64| if total == 0 { 0.0 } else { cycles as f64 * 100.0 / total as f64 };
+        
+        println!("\nPower estimate (typical, VDD = 5.0 V):");
+        println!("  Time:     {:.6} s", power.seconds());
+        println!("  Running:  {} cycles ({:.1}%)", power.run_cycles(), percent(power.run_cycles()));
+        println!("  Sleeping: {} cycles ({:.1}%)", power.sleep_cycles(), percent(power.sleep_cycles()));
+        println!("  Average:  {:.3} uA", power.average_ua());
+        println!("  Charge:   {:.3} uC ({:.6} mAh)", power.charge_uc(), power.charge_uc() / 3_600_000.0);
+    }
+    
+    /// Values of the registers and RAM an instruction can change, once per
+    /// physical location: INDF, PCL and the bank 1 mirrors of unbanked
+    /// registers and shared RAM are left out
+    pub fn register_snapshot(cpu: &Cpu) -> Vec<(u8, u8)> {
+        let model = cpu.device().model();
+        (0..=0xFF)
+            .filter(|&address| {
+                let low = address & 0x7F;
+                let mirror = address >= 0x80 && (model.shared_ram.contains(&low) || matches!(low,
+                    registers::STATUS | registers::FSR | registers::PCLATH | registers::INTCON));
+                model.is_implemented(address) && !mirror && !matches!(low, registers::INDF | registers::PCL)
+            })
+            .map(|address| (address, cpu.read_register(address)))
+            .collect()
+    }
+    
+    /// Registers whose value differs from a `register_snapshot`, as
+    /// `STATUS 0x18 → 0x1C` (RAM by address: `[0x20] 0x00 → 0x05`)
+    pub fn register_changes(cpu: &Cpu, before: &[(u8, u8)]) -> Vec<String> {
+        before.iter()
+            .filter_map(|&(address, old)| {
+                let new = cpu.read_register(address);
+                let name = match cpu.device().model().name_of(address) {
+                    Some(name) => name.to_string(),
+                    None => format!("[0x{:02X}]", address),
+                };
+                (new != old).then(|| format!("{} 0x{:02X} → 0x{:02X}", name, old, new))
+            })
+            .collect()
+    }
+    
+    // ==================== JSON Output ====================
+    
+    /// W, PC and every named SFR as JSON
+    pub fn registers_json(cpu: &Cpu) -> String {
+        let sfrs: Vec<String> = cpu.device().model().sfr_names()
+            .map(|(addr, name)| format!("{}:{}", json_string(name), cpu.read_register(addr)))
+            .collect();
+        format!("{{\"w\":{},\"pc\":{},\"sfrs\":{{{}}}}}", cpu.read_w(), cpu.get_pc(), sfrs.join(","))
+    }
+    
+    /// `count` bytes of data memory from `start` as JSON, stopping at 0xFF
+    pub fn memory_json(cpu: &Cpu, start: u8, count: u16) -> String {
+        let bytes: Vec<String> = (start as u16..memory_end(start, count))
+            .map(|addr| cpu.read_register(addr as u8).to_string())
+            .collect();
+        format!("{{\"start\":{},\"bytes\":[{}]}}", start, bytes.join(","))
+    }
+    
+    /// Hardware stack contents, oldest return address first, as JSON
+    pub fn stack_json(cpu: &Cpu) -> String {
+        let depth = cpu.memory().stack_depth();
+        let stack: Vec<String> = cpu.memory().get_stack().iter().take(depth).map(u16::to_string).collect();
+        format!("{{\"depth\":{},\"stack\":[{}]}}", depth, stack.join(","))
+    }
+    
+    /// Port registers and pin states as JSON
+    pub fn gpio_json(cpu: &Cpu) -> String {
+        let ports: Vec<String> = cpu.device().model().ports.iter().enumerate().map(|(index, port)| {
+            let pins: Vec<String> = (0..8).filter(|bit| port.pins & (1 << bit) != 0).map(|bit| {
+                let pin = index as u8 * 8 + bit;
+                let state = match cpu.gpio().get_pin_state(pin) {
+                    crate::gpio::PinState::High => "high",
+                    crate::gpio::PinState::Low => "low",
+                    crate::gpio::PinState::HighZ => "z",
+                };
+                let direction = if cpu.gpio().is_input(pin) { "in" } else { "out" };
+                format!("{{\"name\":{},\"state\":\"{}\",\"direction\":\"{}\"}}",
+                    json_string(&port.pin_name(bit)), state, direction)
+            }).collect();
+            format!("{{\"name\":{},\"value\":{},\"tris\":{},\"wpu\":{},\"pins\":[{}]}}",
+                json_string(port.name),
+                cpu.read_register(port.address),
+                cpu.gpio().read_port_tris(index),
+                cpu.gpio().read_port_wpu(index),
+                pins.join(","))
+        }).collect();
+        format!("{{\"ports\":[{}]}}", ports.join(","))
+    }
+}
+
+/// End (exclusive) of a data memory range, clamped to the 256 addresses
+fn memory_end(start: u8, count: u16) -> u16 {
+    (start as u16).saturating_add(count).min(0x100)
+}
+
+/// Quote and escape `text` as a JSON string
+pub fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
 }
 
 #[cfg(test)]
@@ -275,8 +741,106 @@ mod tests {
     
     #[test]
     fn test_disassemble() {
-        assert_eq!(Debugger::disassemble(0x3055), "MOVLW 0x55");
-        assert_eq!(Debugger::disassemble(0x00A0), "MOVWF 0x20");
-        assert_eq!(Debugger::disassemble(0x2900), "GOTO 0x100");
+        assert_eq!(Debugger::disassemble(Core::Midrange, 0x3055), "MOVLW 0x55");
+        assert_eq!(Debugger::disassemble(Core::Midrange, 0x00A0), "MOVWF 0x20");
+        assert_eq!(Debugger::disassemble(Core::Midrange, 0x2900), "GOTO 0x100");
+        assert_eq!(Debugger::disassemble(Core::Baseline, 0x0C0E), "MOVLW 0x0E");
+        assert_eq!(Debugger::disassemble(Core::Baseline, 0x0A05), "GOTO 0x005");
+    }
+    
+    #[test]
+    fn test_symbolic_sfr_names() {
+        let symbols = SymbolTable::new();
+        let symbolic = |word| Debugger::disassemble_with_options(Core::Midrange, word, &symbols, true);
+        
+        assert_eq!(symbolic(0x1683), "BSF STATUS, RP0");
+        assert_eq!(symbolic(0x110B), "BCF INTCON, T0IF");
+        assert_eq!(symbolic(0x0885), "MOVF GPIO, F");
+        assert_eq!(symbolic(0x0095), "MOVWF WPU");
+        assert_eq!(symbolic(0x00A0), "MOVWF 0x20");
+        assert_eq!(symbolic(0x1A20), "BTFSC 0x20, 4");
+        assert_eq!(Debugger::disassemble(Core::Midrange, 0x1683), "BSF 0x03, 5");
+    }
+    
+    #[test]
+    fn test_register_changes() {
+        let mut cpu = Cpu::new();
+        let before = Debugger::register_snapshot(&cpu);
+        assert!(before.iter().all(|&(address, _)| !matches!(address, 0x00 | 0x02 | 0x83)));
+        
+        cpu.write_register(0x20, 5);
+        cpu.write_register(registers::FSR, 0x20);
+        assert_eq!(Debugger::register_changes(&cpu, &before), vec![
+            "FSR 0x00 → 0x20".to_string(),
+            "[0x20] 0x00 → 0x05".to_string(),
+        ]);
+    }
+    
+    #[test]
+    fn test_json_output() {
+        let mut cpu = Cpu::new();
+        cpu.write_w(0x42);
+        cpu.write_register(0x20, 7);
+        
+        let registers: serde_json::Value = serde_json::from_str(&Debugger::registers_json(&cpu)).unwrap();
+        assert_eq!(registers["w"], 0x42);
+        assert_eq!(registers["sfrs"]["STATUS"], cpu.read_register(registers::STATUS));
+        
+        let memory: serde_json::Value = serde_json::from_str(&Debugger::memory_json(&cpu, 0x20, 2)).unwrap();
+        assert_eq!(memory["bytes"], serde_json::json!([7, 0]));
+        let memory: serde_json::Value = serde_json::from_str(&Debugger::memory_json(&cpu, 0xF0, 0x20)).unwrap();
+        assert_eq!(memory["bytes"].as_array().unwrap().len(), 0x10);
+        
+        let gpio: serde_json::Value = serde_json::from_str(&Debugger::gpio_json(&cpu)).unwrap();
+        assert_eq!(gpio["ports"][0]["name"], "GPIO");
+        assert_eq!(gpio["ports"][0]["pins"][0]["direction"], "in");
+        
+        let stack: serde_json::Value = serde_json::from_str(&Debugger::stack_json(&cpu)).unwrap();
+        assert_eq!(stack["depth"], 0);
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+    }
+    
+    #[test]
+    fn test_branch_labels() {
+        let mut cpu = Cpu::new();
+        cpu.memory_mut().load_program(&[
+            0x2004,  // 0x000: CALL 0x004
+            0x2800,  // 0x001: GOTO 0x000
+            0x0000,
+            0x0000,
+            0x0008,  // 0x004: RETURN
+        ]);
+        let mut symbols = SymbolTable::new();
+        symbols.add_label("delay", 0x004);
+        symbols.add_label("unused", 0x002);
+        
+        let labels = Debugger::branch_labels(&cpu, &symbols);
+        assert_eq!(labels.get(&0x000).map(String::as_str), Some("L_000"));
+        assert_eq!(labels.get(&0x004).map(String::as_str), Some("delay"));
+        assert_eq!(labels.get(&0x002).map(String::as_str), Some("unused"));
+        assert_eq!(labels.len(), 3);
+        
+        assert_eq!(Debugger::disassemble_with_labels(Core::Midrange, 0x2004, &labels, false), "CALL delay");
+        assert_eq!(Debugger::disassemble_with_labels(Core::Midrange, 0x2800, &labels, false), "GOTO L_000");
+        assert_eq!(Debugger::disassemble_with_labels(Core::Midrange, 0x2805, &labels, false), "GOTO 0x005");
+    }
+    
+    #[test]
+    fn test_format_listing() {
+        let mut cpu = Cpu::new();
+        cpu.memory_mut().load_program(&[
+            0x1683,  // 0x000: BSF STATUS, RP0
+            0x3FFF,  // 0x001: erased
+            0x3FFF,  // 0x002: erased
+            0x2800,  // 0x003: GOTO 0x000
+        ]);
+        
+        let listing = Debugger::format_listing(&cpu, &SymbolTable::new());
+        assert!(listing.contains("OPTION_REG      EQU     0x81\n"));
+        assert!(listing.contains("RP0             EQU     5\n"));
+        assert!(listing.contains("        ORG     0x000\nL_000:\n        BSF     STATUS, RP0"));
+        assert!(listing.contains("        ORG     0x003\n        GOTO    L_000"));
+        assert!(!listing.contains("0x004"));
+        assert!(listing.ends_with("        END\n"));
     }
 }
\ No newline at end of file
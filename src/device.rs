@@ -0,0 +1,381 @@
+//! Device profiles
+//!
+//! Reference: Section 1.0 - Device Overview, Table 1-1 and Figure 2-2
+//!
+//! Everything that differs between parts sharing the midrange core lives
+//! in a `DeviceModel`: memory sizes, the general purpose RAM range, which
+//! special function registers exist and which peripherals are fitted. The
+//! CPU consults the model of its `Device` instead of assuming one part, so
+//! registers of peripherals a part lacks read as 0 and ignore writes. The
+//! PIC12F675 is the PIC12F629 plus the A/D converter; the PIC12F683 adds
+//! Timer2 and a CCP module with twice the program memory, RAM in bank 1
+//! and a larger EEPROM. The PIC16F84A shares the core but has PORTA and
+//! PORTB in place of GPIO and no Timer1, comparator or ADC.
+//!
+//! The PIC10F200/202 use the 12-bit baseline core: a 2-level stack, no
+//! interrupts, 5-bit file addresses and OPTION/TRIS loaded by instruction.
+//! Their registers are modelled by the 12F6xx register at the same role
+//! (OSCCAL, GPIO, OPTION_REG, TRISIO) through `file_map`, and execution
+//! starts at the last program word, which holds the OSCCAL MOVLW.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::cpu::registers::{self, *};
+use crate::gpio::PortSpec;
+use crate::instruction::Core;
+use crate::memory::MemoryLayout;
+
+/// Peripherals fitted to a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Peripherals {
+    pub timer1: bool,
+    pub comparator: bool,
+    pub adc: bool,
+    pub eeprom: bool,
+    /// Timer2 and the CCP module
+    pub ccp: bool,
+}
+
+/// Device-specific constants
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceModel {
+    /// Part name as used by MPASM's LIST P= directive
+    pub name: &'static str,
+    /// Instruction set of the CPU core
+    pub core: Core,
+    /// Program memory size in words
+    pub program_words: usize,
+    /// Data memory banks selected by RP1:RP0 (1, 2 or 4); baseline parts
+    /// use two to hold OPTION, TRIS and OSCCAL at their bank 1 addresses
+    pub banks: usize,
+    /// General purpose RAM (bank-resolved addresses)
+    pub gpr: &'static [RangeInclusive<u8>],
+    /// RAM mapped into every bank, by bank 0 address
+    pub shared_ram: RangeInclusive<u8>,
+    /// Data EEPROM size in bytes
+    pub eeprom_bytes: usize,
+    /// Hardware stack levels
+    pub stack_depth: usize,
+    /// Bank-resolved addresses of the special function registers present
+    pub sfrs: &'static [u8],
+    /// Names that differ from the 12F6xx register at the same address
+    pub aliases: &'static [(u8, &'static str)],
+    /// File addresses that select a register at another address of the
+    /// midrange map (baseline parts)
+    pub file_map: &'static [(u8, u8)],
+    /// I/O ports; pin numbers count 8 per port
+    pub ports: &'static [PortSpec],
+    /// Pin of the external interrupt input (INT)
+    pub int_pin: Option<u8>,
+    pub peripherals: Peripherals,
+}
+
+/// VRCON and the EEPROM registers; present but not modelled
+const UNMODELLED: [u8; 5] = [0x99, 0x9A, 0x9B, 0x9C, 0x9D];
+
+const PIC12F629_SFRS: &[u8] = &[
+    INDF, TMR0, PCL, STATUS, FSR, GPIO, PCLATH, INTCON, PIR1, TMR1L, TMR1H, T1CON, CMCON,
+    OPTION_REG, TRISIO, PIE1, PCON, OSCCAL, WPU, IOC,
+    UNMODELLED[0], UNMODELLED[1], UNMODELLED[2], UNMODELLED[3], UNMODELLED[4],
+];
+
+/// The 629's registers plus ADRESH, ADCON0, ADRESL (0x9E) and ANSEL
+const PIC12F675_SFRS: &[u8] = &[
+    INDF, TMR0, PCL, STATUS, FSR, GPIO, PCLATH, INTCON, PIR1, TMR1L, TMR1H, T1CON, CMCON,
+    ADRESH, ADCON0,
+    OPTION_REG, TRISIO, PIE1, PCON, OSCCAL, WPU, IOC,
+    UNMODELLED[0], UNMODELLED[1], UNMODELLED[2], UNMODELLED[3], UNMODELLED[4],
+    0x9E, ANSEL,
+];
+
+/// PORTA and PORTB, the EEPROM registers and the shared 68 bytes of RAM
+const PIC16F84A_SFRS: &[u8] = &[
+    INDF, TMR0, PCL, STATUS, FSR, PORTA, PORTB, 0x08, 0x09, PCLATH, INTCON,
+    OPTION_REG, TRISA, TRISB, 0x88, 0x89,
+];
+
+/// WDTCON, CMCON1 and OSCCON; present but not modelled. OSCTUNE sits
+/// at OSCCAL's address and reads as 0.
+const PIC12F683_UNMODELLED: [u8; 3] = [0x18, 0x1A, 0x8F];
+
+/// The 675's registers less OSCCAL, plus Timer2 and the CCP
+const PIC12F683_SFRS: &[u8] = &[
+    INDF, TMR0, PCL, STATUS, FSR, GPIO, PCLATH, INTCON, PIR1, TMR1L, TMR1H, T1CON,
+    TMR2, T2CON, CCPR1L, CCPR1H, CCP1CON, CMCON, ADRESH, ADCON0,
+    OPTION_REG, TRISIO, PIE1, PCON, PR2, WPU, IOC,
+    UNMODELLED[0], UNMODELLED[1], UNMODELLED[2], UNMODELLED[3], UNMODELLED[4],
+    0x9E, ANSEL,
+    PIC12F683_UNMODELLED[0], PIC12F683_UNMODELLED[1], PIC12F683_UNMODELLED[2],
+];
+
+/// The baseline core's registers, by their midrange address
+const PIC10F200_SFRS: &[u8] = &[INDF, TMR0, PCL, STATUS, FSR, OSCCAL, GPIO, OPTION_REG, TRISIO];
+
+impl DeviceModel {
+    pub const PIC12F629: DeviceModel = DeviceModel {
+        name: "12F629",
+        core: Core::Midrange,
+        program_words: 1024,
+        banks: 2,
+        gpr: &[0x20..=0x5F],
+        shared_ram: 0x20..=0x5F,
+        eeprom_bytes: 128,
+        stack_depth: 8,
+        sfrs: PIC12F629_SFRS,
+        aliases: &[],
+        file_map: &[],
+        ports: &[PortSpec::GPIO],
+        int_pin: Some(2),
+        peripherals: Peripherals { timer1: true, comparator: true, adc: false, eeprom: true, ccp: false },
+    };
+
+    pub const PIC12F675: DeviceModel = DeviceModel {
+        name: "12F675",
+        sfrs: PIC12F675_SFRS,
+        peripherals: Peripherals { timer1: true, comparator: true, adc: true, eeprom: true, ccp: false },
+        ..Self::PIC12F629
+    };
+
+    pub const PIC12F683: DeviceModel = DeviceModel {
+        name: "12F683",
+        core: Core::Midrange,
+        program_words: 2048,
+        banks: 2,
+        gpr: &[0x20..=0x7F, 0xA0..=0xBF],
+        shared_ram: 0x70..=0x7F,
+        eeprom_bytes: 256,
+        stack_depth: 8,
+        sfrs: PIC12F683_SFRS,
+        aliases: &[],
+        file_map: &[],
+        ports: &[PortSpec::GPIO],
+        int_pin: Some(2),
+        peripherals: Peripherals { timer1: true, comparator: true, adc: true, eeprom: true, ccp: true },
+    };
+
+    pub const PIC16F84A: DeviceModel = DeviceModel {
+        name: "16F84A",
+        core: Core::Midrange,
+        program_words: 1024,
+        banks: 2,
+        gpr: &[0x0C..=0x4F],
+        shared_ram: 0x0C..=0x4F,
+        eeprom_bytes: 64,
+        stack_depth: 8,
+        sfrs: PIC16F84A_SFRS,
+        aliases: &[
+            (PORTA, "PORTA"), (TRISA, "TRISA"),
+            (0x08, "EEDATA"), (0x09, "EEADR"), (0x88, "EECON1"), (0x89, "EECON2"),
+        ],
+        file_map: &[],
+        ports: &[PortSpec::PORTA, PortSpec::PORTB],
+        // RB0
+        int_pin: Some(8),
+        peripherals: Peripherals { timer1: false, comparator: false, adc: false, eeprom: true, ccp: false },
+    };
+
+    pub const PIC10F200: DeviceModel = DeviceModel {
+        name: "10F200",
+        core: Core::Baseline,
+        program_words: 256,
+        banks: 2,
+        gpr: &[0x10..=0x1F],
+        shared_ram: 0x10..=0x1F,
+        eeprom_bytes: 0,
+        stack_depth: 2,
+        sfrs: PIC10F200_SFRS,
+        aliases: &[],
+        file_map: &[(0x05, OSCCAL), (0x06, GPIO)],
+        ports: &[PortSpec::GPIO_BASELINE],
+        int_pin: None,
+        peripherals: Peripherals { timer1: false, comparator: false, adc: false, eeprom: false, ccp: false },
+    };
+
+    pub const PIC10F202: DeviceModel = DeviceModel {
+        name: "10F202",
+        program_words: 512,
+        gpr: &[0x08..=0x1F],
+        shared_ram: 0x08..=0x1F,
+        ..Self::PIC10F200
+    };
+
+    /// Address of the register or RAM a file operand (or FSR value)
+    /// selects, before banking
+    pub fn map_file(&self, f: u8) -> u8 {
+        match self.core {
+            Core::Midrange => f,
+            Core::Baseline => {
+                let f = f & 0x1F;
+                self.file_map.iter()
+                    .find(|(file, _)| *file == f)
+                    .map_or(f, |(_, address)| *address)
+            }
+        }
+    }
+
+    /// Address execution starts at after a reset
+    pub fn reset_vector(&self) -> u16 {
+        match self.core {
+            Core::Midrange => 0,
+            Core::Baseline => self.program_words as u16 - 1,
+        }
+    }
+
+    /// Check if pin number `pin` (`port * 8 + bit`) exists on this part
+    pub fn has_pin(&self, pin: u8) -> bool {
+        self.ports.get(pin as usize / 8)
+            .is_some_and(|port| port.pins & (1 << (pin % 8)) != 0)
+    }
+    
+    /// Numbers of all pins, port by port
+    pub fn pins(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..self.ports.len() as u8 * 8).filter(|&pin| self.has_pin(pin))
+    }
+    
+    /// Name of pin number `pin` on this part (e.g. "RB0")
+    pub fn pin_name(&self, pin: u8) -> Option<String> {
+        self.has_pin(pin)
+            .then(|| self.ports[pin as usize / 8].pin_name(pin % 8))
+    }
+    
+    /// Sizes of the part's memories
+    pub fn memory_layout(&self) -> MemoryLayout {
+        MemoryLayout {
+            program_words: self.program_words,
+            banks: self.banks,
+            stack_depth: self.stack_depth,
+            eeprom_bytes: self.eeprom_bytes,
+        }
+    }
+
+    /// Check if the special function register at `address` exists
+    pub fn has_sfr(&self, address: u8) -> bool {
+        self.sfrs.contains(&address)
+    }
+
+    /// Check if a (bank-resolved) address has a register or RAM behind it
+    pub fn is_implemented(&self, address: u8) -> bool {
+        self.gpr.iter().any(|range| range.contains(&address)) || self.has_sfr(address)
+    }
+
+    /// Name of the special function register at `address` on this part
+    pub fn name_of(&self, address: u8) -> Option<&'static str> {
+        self.aliases.iter()
+            .find(|(addr, _)| *addr == address)
+            .map(|(_, name)| *name)
+            .or_else(|| registers::name_of(address).filter(|_| self.has_sfr(address)))
+    }
+    
+    /// Names and addresses of the named registers present
+    pub fn sfr_names(&self) -> impl Iterator<Item = (u8, &'static str)> + '_ {
+        self.sfrs.iter()
+            .filter_map(|&address| self.name_of(address).map(|name| (address, name)))
+    }
+}
+
+/// A supported part
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Device {
+    Pic12F629,
+    /// The default: a superset of the 629, so programs for either part run
+    #[default]
+    Pic12F675,
+    Pic12F683,
+    Pic16F84A,
+    Pic10F200,
+    Pic10F202,
+}
+
+impl Device {
+    pub const ALL: [Device; 6] = [
+        Device::Pic12F629, Device::Pic12F675, Device::Pic12F683, Device::Pic16F84A,
+        Device::Pic10F200, Device::Pic10F202,
+    ];
+
+    /// Constants of this part
+    pub fn model(self) -> &'static DeviceModel {
+        match self {
+            Device::Pic12F629 => &DeviceModel::PIC12F629,
+            Device::Pic12F675 => &DeviceModel::PIC12F675,
+            Device::Pic12F683 => &DeviceModel::PIC12F683,
+            Device::Pic16F84A => &DeviceModel::PIC16F84A,
+            Device::Pic10F200 => &DeviceModel::PIC10F200,
+            Device::Pic10F202 => &DeviceModel::PIC10F202,
+        }
+    }
+
+    /// Instruction set of the part's core
+    pub fn core(self) -> Core {
+        self.model().core
+    }
+
+    pub fn name(self) -> &'static str {
+        self.model().name
+    }
+
+    /// Look up a device by name, with or without the "PIC" prefix
+    pub fn from_name(name: &str) -> Option<Self> {
+        let upper = name.to_ascii_uppercase();
+        let name = upper.strip_prefix("PIC").unwrap_or(&upper);
+        Self::ALL.into_iter().find(|device| device.name() == name)
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PIC{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let pic629 = Device::Pic12F629.model();
+        let pic675 = Device::Pic12F675.model();
+        assert_eq!(pic629.program_words, pic675.program_words);
+        assert!(!pic629.peripherals.adc && pic675.peripherals.adc);
+        assert!(!pic629.has_sfr(ADCON0) && pic675.has_sfr(ADCON0));
+        assert!(pic629.sfrs.iter().all(|&sfr| pic675.has_sfr(sfr)));
+
+        assert!(pic629.is_implemented(0x20) && pic629.is_implemented(0x9C));
+        assert!(!pic629.is_implemented(0x60) && !pic629.is_implemented(0x9E));
+        assert!(pic675.sfr_names().all(|(address, _)| address != registers::T2CON));
+
+        let pic683 = Device::Pic12F683.model();
+        assert_eq!(pic683.program_words, 2 * pic675.program_words);
+        assert!(pic683.is_implemented(0xA0) && !pic675.is_implemented(0xA0));
+        assert!(pic683.has_sfr(registers::PR2) && !pic683.has_sfr(registers::OSCCAL));
+        assert_eq!(pic683.sfr_names().count(), registers::NAMES.len() - 3);
+
+        let pic16f84a = Device::Pic16F84A.model();
+        assert_eq!(pic16f84a.name_of(PORTA), Some("PORTA"));
+        assert_eq!(pic16f84a.name_of(PORTB), Some("PORTB"));
+        assert_eq!(pic16f84a.name_of(PIR1), None);
+        assert!(pic16f84a.is_implemented(PIR1) && !pic16f84a.has_sfr(PIR1));
+        assert_eq!(pic16f84a.sfr_names().count(), pic16f84a.sfrs.len());
+        assert!(pic16f84a.has_pin(8) && pic16f84a.has_pin(15) && !pic16f84a.has_pin(5));
+        assert_eq!(pic16f84a.pins().count(), 13);
+        assert_eq!(pic16f84a.pin_name(8).as_deref(), Some("RB0"));
+        assert!(pic675.has_pin(5) && !pic675.has_pin(8));
+
+        let pic10f200 = Device::Pic10F200.model();
+        assert_eq!(pic10f200.map_file(0x06), GPIO);
+        assert_eq!(pic10f200.map_file(0x26), GPIO);
+        assert_eq!(pic10f200.map_file(0x10), 0x10);
+        assert!(!pic10f200.is_implemented(0x08) && Device::Pic10F202.model().is_implemented(0x08));
+        assert_eq!(pic10f200.reset_vector(), 0xFF);
+        assert_eq!(pic10f200.pins().collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!(Device::Pic10F202.model().reset_vector(), 0x1FF);
+
+        assert_eq!(Device::from_name("pic12f629"), Some(Device::Pic12F629));
+        assert_eq!(Device::from_name("12F675"), Some(Device::Pic12F675));
+        assert_eq!(Device::from_name("PIC12F683"), Some(Device::Pic12F683));
+        assert_eq!(Device::from_name("16F877A"), None);
+        assert_eq!(Device::Pic12F629.to_string(), "PIC12F629");
+    }
+}
@@ -0,0 +1,205 @@
+//! ELF firmware loader
+//!
+//! Reads linked ELF files and extracts program memory, EEPROM, user IDs and
+//! the configuration word from their allocated sections, plus code labels
+//! from the symbol table for the symbolic debugger.
+//!
+//! Section and symbol addresses are byte addresses laid out as in Intel HEX
+//! files (program word N at 2N, config at 0x400E, EEPROM from 0x4200), so
+//! the sections are placed exactly like HEX records.
+
+use std::path::Path;
+
+use object::{Object, ObjectSection, ObjectSymbol, SectionKind};
+
+use crate::error::ElfError;
+use crate::hexloader::{HexProgram, ImageBuilder, DEFAULT_PROGRAM_WORDS};
+use crate::symbols::SymbolTable;
+
+/// Magic number at the start of every ELF file
+pub const ELF_MAGIC: &[u8] = b"\x7FELF";
+
+/// Program image and symbols read from an ELF file
+#[derive(Debug, Clone)]
+pub struct ElfFirmware {
+    pub program: HexProgram,
+
+    /// Labels of symbols defined in code sections, at word addresses
+    pub symbols: SymbolTable,
+}
+
+/// Whether `data` starts with the ELF magic number
+pub fn is_elf(data: &[u8]) -> bool {
+    data.starts_with(ELF_MAGIC)
+}
+
+/// ELF file loader
+pub struct ElfLoader;
+
+impl ElfLoader {
+    /// Load an ELF file, warning about program data past 1K words
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<ElfFirmware, ElfError> {
+        Self::load_file_for(path, DEFAULT_PROGRAM_WORDS)
+    }
+
+    /// Load an ELF file for a device with `program_words` words of program memory
+    pub fn load_file_for<P: AsRef<Path>>(path: P, program_words: usize) -> Result<ElfFirmware, ElfError> {
+        let data = std::fs::read(path).map_err(ElfError::Open)?;
+        Self::load_from_bytes_for(&data, program_words)
+    }
+
+    /// Load ELF file contents, warning about program data past 1K words
+    pub fn load_from_bytes(data: &[u8]) -> Result<ElfFirmware, ElfError> {
+        Self::load_from_bytes_for(data, DEFAULT_PROGRAM_WORDS)
+    }
+
+    /// Load ELF file contents for a device with `program_words` words of program memory
+    ///
+    /// Load warnings give the section index in place of a line number.
+    pub fn load_from_bytes_for(data: &[u8], program_words: usize) -> Result<ElfFirmware, ElfError> {
+        let file = object::File::parse(data)?;
+        let mut image = ImageBuilder::new(program_words);
+
+        for section in file.sections() {
+            let loadable = matches!(
+                section.kind(),
+                SectionKind::Text | SectionKind::Data | SectionKind::ReadOnlyData | SectionKind::ReadOnlyString
+            );
+            if !loadable {
+                continue;
+            }
+            let bytes = section.data()?;
+            if !bytes.is_empty() {
+                image.add_data(section.index().0, section.address() as u32, bytes);
+            }
+        }
+
+        let mut symbols = SymbolTable::new();
+        for symbol in file.symbols() {
+            let in_code = symbol.section_index()
+                .and_then(|index| file.section_by_index(index).ok())
+                .is_some_and(|section| section.kind() == SectionKind::Text);
+            let name = symbol.name().unwrap_or_default();
+            if in_code && !name.is_empty() && symbol.address() < 0x4000 {
+                symbols.add_label(name, (symbol.address() / 2) as u16);
+            }
+        }
+
+        // An entry point of 0 means the file has none
+        let start_address = (file.entry() != 0).then(|| (file.entry() / 2) as u16);
+        Ok(ElfFirmware {
+            program: image.finish(start_address),
+            symbols,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::elf;
+    use object::write::elf::{FileHeader, SectionHeader, Sym, Writer};
+    use object::Endianness;
+
+    /// Build a 32-bit ELF with the given (name, address, flags, data) sections
+    /// and (name, section, address) symbols
+    fn build_elf(sections: &[(&str, u64, u32, &[u8])], symbols: &[(&str, usize, u64)]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let mut writer = Writer::new(Endianness::Little, false, &mut buffer);
+        writer.reserve_file_header();
+
+        writer.reserve_null_section_index();
+        let mut reserved = Vec::new();
+        for &(name, _, _, data) in sections {
+            let name = writer.add_section_name(name.as_bytes());
+            let index = writer.reserve_section_index();
+            let offset = writer.reserve(data.len(), 2);
+            reserved.push((name, index, offset));
+        }
+
+        writer.reserve_null_symbol_index();
+        let symbol_names: Vec<_> = symbols.iter()
+            .map(|&(name, section, _)| {
+                writer.reserve_symbol_index(Some(reserved[section].1));
+                writer.add_string(name.as_bytes())
+            })
+            .collect();
+        writer.reserve_symtab_section_index();
+        writer.reserve_symtab();
+        writer.reserve_strtab_section_index();
+        writer.reserve_strtab();
+        writer.reserve_shstrtab_section_index();
+        writer.reserve_shstrtab();
+        writer.reserve_section_headers();
+
+        writer.write_file_header(&FileHeader {
+            os_abi: 0,
+            abi_version: 0,
+            e_type: elf::ET_EXEC,
+            e_machine: elf::EM_NONE,
+            e_entry: 0,
+            e_flags: 0,
+        }).unwrap();
+        for &(_, _, _, data) in sections {
+            writer.write_align(2);
+            writer.write(data);
+        }
+        writer.write_null_symbol();
+        for (&(_, section, address), &name) in symbols.iter().zip(&symbol_names) {
+            writer.write_symbol(&Sym {
+                name: Some(name),
+                section: Some(reserved[section].1),
+                st_info: (elf::STB_GLOBAL << 4) | elf::STT_FUNC,
+                st_other: 0,
+                st_shndx: 0,
+                st_value: address,
+                st_size: 0,
+            });
+        }
+        writer.write_strtab();
+        writer.write_shstrtab();
+
+        writer.write_null_section_header();
+        for (&(_, address, flags, data), &(name, _, offset)) in sections.iter().zip(&reserved) {
+            writer.write_section_header(&SectionHeader {
+                name: Some(name),
+                sh_type: elf::SHT_PROGBITS,
+                sh_flags: flags as u64,
+                sh_addr: address,
+                sh_offset: offset as u64,
+                sh_size: data.len() as u64,
+                sh_link: 0,
+                sh_info: 0,
+                sh_addralign: 2,
+                sh_entsize: 0,
+            });
+        }
+        writer.write_symtab_section_header(1);
+        writer.write_strtab_section_header();
+        writer.write_shstrtab_section_header();
+        buffer
+    }
+
+    #[test]
+    fn test_load_elf() {
+        let code = elf::SHF_ALLOC | elf::SHF_EXECINSTR;
+        let data = build_elf(
+            &[
+                (".text", 0x0000, code, &[0x05, 0x28, 0xFF, 0x3F, 0xFF, 0x3F, 0xFF, 0x3F, 0x09, 0x00, 0x55, 0x30]),
+                (".config", 0x400E, elf::SHF_ALLOC, &[0x84, 0x31]),
+                (".eeprom", 0x4200, elf::SHF_ALLOC, &[0x48, 0x00, 0x69, 0x00]),
+            ],
+            &[("reset", 0, 0x0000), ("isr", 0, 0x0008), ("start", 0, 0x000A)],
+        );
+        assert!(is_elf(&data));
+
+        let firmware = ElfLoader::load_from_bytes(&data).unwrap();
+        assert_eq!(firmware.program.program, vec![0x2805, 0x3FFF, 0x3FFF, 0x3FFF, 0x0009, 0x3055]);
+        assert_eq!(firmware.program.config, Some(0x3184));
+        assert_eq!(firmware.program.eeprom, vec![0x48, 0x69]);
+        assert_eq!(firmware.symbols.address_of("isr"), Some(0x004));
+        assert_eq!(firmware.symbols.label_at(0x005), Some("start"));
+
+        assert!(matches!(ElfLoader::load_from_bytes(b"\x7FELF\x01"), Err(ElfError::Parse(_))));
+    }
+}
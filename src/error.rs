@@ -0,0 +1,480 @@
+//! Error types
+//!
+//! `DecodeError` comes from the instruction decoder, `HexError` from the
+//! Intel HEX loader, `SrecError`, `ElfError` and `CoffError` from the
+//! S-record, ELF and COFF loaders, `AsmError` from the assembler and
+//! `SimError` from the simulator itself. The text file formats have their
+//! own: `SymbolError`, `StimulusError`, `CircuitError`, `StateError`,
+//! `ReplayError`, `GoldenError` and `BreakpointError`. `BoardError` and
+//! `HaltConditionError` reject bad wiring and halt settings. Each carries the PC, address, opcode, key or line
+//! involved so callers can match on the kind of failure instead of
+//! parsing messages.
+
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::device::Device;
+use crate::halt::HaltReason;
+
+/// An instruction word that does not encode a valid instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Unknown instruction: 0x{opcode:04X}")]
+pub struct DecodeError {
+    pub opcode: u16,
+}
+
+/// An assembler error at a (1-based) source line
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Line {line}: {message}")]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Intel HEX parse and load errors
+#[derive(Debug, Error)]
+pub enum HexError {
+    #[error(transparent)]
+    Open(io::Error),
+
+    #[error(transparent)]
+    Read(io::Error),
+
+    #[error(transparent)]
+    Write(io::Error),
+
+    #[error("HEX line must start with ':'")]
+    MissingStartCode,
+
+    #[error("HEX line must have even number of characters")]
+    OddLength,
+
+    #[error("Invalid hex byte: {0}")]
+    InvalidByte(String),
+
+    #[error("HEX line too short")]
+    TooShort,
+
+    #[error("Invalid record type: 0x{0:02X}")]
+    InvalidRecordType(u8),
+
+    #[error("Byte count mismatch at 0x{address:04X}: expected {expected}, got {actual}")]
+    ByteCountMismatch { address: u16, expected: u8, actual: usize },
+
+    #[error("Checksum mismatch at 0x{address:04X}: expected 0x{expected:02X}, got 0x{actual:02X}")]
+    ChecksumMismatch { address: u16, expected: u8, actual: u8 },
+
+    #[error("HEX file has no end-of-file record")]
+    MissingEndOfFile,
+
+    /// An error at a specific (1-based) line and column of the file, with the line's text
+    #[error("Line {line}, column {column}: {source} in \"{text}\"")]
+    Line { line: usize, column: usize, text: String, source: Box<HexError> },
+}
+
+impl HexError {
+    /// The error without its line context
+    pub fn kind(&self) -> &HexError {
+        match self {
+            HexError::Line { source, .. } => source.kind(),
+            other => other,
+        }
+    }
+}
+
+/// Motorola S-record parse and load errors
+#[derive(Debug, Error)]
+pub enum SrecError {
+    #[error(transparent)]
+    Open(io::Error),
+
+    #[error(transparent)]
+    Read(io::Error),
+
+    #[error("S-record must start with 'S'")]
+    MissingStartCode,
+
+    #[error("Invalid S-record type: {0}")]
+    InvalidRecordType(char),
+
+    #[error("S-record must have even number of characters")]
+    OddLength,
+
+    #[error("Invalid hex byte: {0}")]
+    InvalidByte(String),
+
+    #[error("S-record too short")]
+    TooShort,
+
+    #[error("Byte count mismatch: expected {expected}, got {actual}")]
+    ByteCountMismatch { expected: u8, actual: usize },
+
+    #[error("Checksum mismatch at 0x{address:04X}: expected 0x{expected:02X}, got 0x{actual:02X}")]
+    ChecksumMismatch { address: u32, expected: u8, actual: u8 },
+
+    /// An error in a specific (1-based) line of the file
+    #[error("Line {line}: {source}")]
+    Line { line: usize, source: Box<SrecError> },
+}
+
+impl SrecError {
+    /// The error without its line context
+    pub fn kind(&self) -> &SrecError {
+        match self {
+            SrecError::Line { source, .. } => source.kind(),
+            other => other,
+        }
+    }
+}
+
+/// ELF load errors
+#[derive(Debug, Error)]
+pub enum ElfError {
+    #[error(transparent)]
+    Open(io::Error),
+
+    #[error("Invalid ELF file: {0}")]
+    Parse(#[from] object::Error),
+}
+
+/// Microchip COFF load errors
+#[derive(Debug, Error)]
+pub enum CoffError {
+    #[error(transparent)]
+    Open(io::Error),
+
+    #[error("Not a Microchip COFF file (magic 0x{0:04X})")]
+    BadMagic(u16),
+
+    #[error("COFF file truncated in the {0}")]
+    Truncated(&'static str),
+}
+
+/// Symbol file load errors
+#[derive(Debug, Error)]
+pub enum SymbolError {
+    #[error(transparent)]
+    Read(io::Error),
+
+    #[error("Unknown symbol file type: {}", .0.display())]
+    UnknownType(PathBuf),
+
+    #[error("Not a COD file: shorter than one block")]
+    CodTooShort,
+
+    #[error("COD block {0} is past the end of the file")]
+    CodBlockPastEnd(usize),
+
+    #[error("Truncated COD symbol table entry")]
+    CodTruncatedSymbol,
+}
+
+/// Stimulus script errors
+#[derive(Debug, Error)]
+pub enum StimulusError {
+    #[error(transparent)]
+    Read(io::Error),
+
+    #[error("expected '<pin>: <action>'")]
+    MissingAction,
+
+    #[error("invalid pin '{0}'")]
+    InvalidPin(String),
+
+    #[error("invalid cycle count '{0}'")]
+    InvalidCycles(String),
+
+    #[error("toggle period must be at least 1 cycle")]
+    ZeroPeriod,
+
+    #[error("unexpected '{0}'")]
+    Unexpected(String),
+
+    #[error("unknown action '{0}'")]
+    UnknownAction(String),
+
+    /// An error in a specific (1-based) line of the script
+    #[error("Line {line}: {source}")]
+    Line { line: usize, source: Box<StimulusError> },
+}
+
+/// Circuit description errors
+#[derive(Debug, Error)]
+pub enum CircuitError {
+    #[error(transparent)]
+    Read(io::Error),
+
+    #[error("unknown table '{0}'")]
+    UnknownTable(String),
+
+    #[error("expected [[component]] first")]
+    MissingComponent,
+
+    #[error("expected 'key = value'")]
+    Syntax,
+
+    #[error("invalid string {0}")]
+    InvalidString(String),
+
+    #[error("unterminated array {0}")]
+    UnterminatedArray(String),
+
+    #[error("invalid value '{0}'")]
+    InvalidValue(String),
+
+    #[error("component has no type")]
+    MissingType,
+
+    #[error("unknown component type '{0}'")]
+    UnknownType(String),
+
+    #[error("missing '{0}'")]
+    MissingParam(String),
+
+    /// A parameter of the wrong kind, e.g. "must be an integer"
+    #[error("'{key}' must be {expected}")]
+    WrongType { key: String, expected: &'static str },
+
+    #[error("invalid pin {0}")]
+    InvalidPin(String),
+
+    #[error("'{key}' must list {expected} pins")]
+    PinCount { key: &'static str, expected: &'static str },
+
+    #[error("bounce settings must not be negative")]
+    NegativeBounce,
+
+    #[error("invalid baud rate {0}")]
+    InvalidBaud(i64),
+
+    /// An error in a specific (1-based) line of the file
+    #[error("Line {line}: {source}")]
+    Line { line: usize, source: Box<CircuitError> },
+
+    /// An invalid parameter of the component declared at `line`
+    #[error("Line {line}: {kind} '{name}': {source}")]
+    Component { line: usize, kind: String, name: String, source: Box<CircuitError> },
+}
+
+/// Machine state file errors
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error(transparent)]
+    Read(io::Error),
+
+    #[error(transparent)]
+    Write(io::Error),
+
+    #[error("Line {0}: expected 'key = value'")]
+    Syntax(usize),
+
+    #[error("Missing field '{0}'")]
+    MissingField(String),
+
+    #[error("Invalid value for '{key}': {value}")]
+    InvalidValue { key: String, value: String },
+
+    #[error("'{key}' has {actual} values, expected {expected}")]
+    WrongLength { key: String, actual: usize, expected: usize },
+
+    #[error("Unknown device: {0}")]
+    UnknownDevice(String),
+
+    #[error("Invalid stack pointer: {0}")]
+    InvalidStackPointer(usize),
+}
+
+/// Replay file errors
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error(transparent)]
+    Read(io::Error),
+
+    #[error(transparent)]
+    Write(io::Error),
+
+    #[error("empty event")]
+    EmptyEvent,
+
+    #[error("invalid cycle '{0}'")]
+    InvalidCycle(String),
+
+    #[error("invalid reset kind '{0}'")]
+    InvalidReset(String),
+
+    #[error("invalid pin '{0}'")]
+    InvalidPin(String),
+
+    #[error("unknown event '{0}'")]
+    UnknownEvent(String),
+
+    /// An error in a specific (1-based) line of the file
+    #[error("Line {line}: {source}")]
+    Line { line: usize, source: Box<ReplayError> },
+}
+
+/// Golden trace file errors
+#[derive(Debug, Error)]
+pub enum GoldenError {
+    #[error(transparent)]
+    Read(io::Error),
+
+    #[error(transparent)]
+    Write(io::Error),
+
+    #[error("Not a golden trace file")]
+    BadMagic,
+
+    #[error("Truncated golden trace: {0} trailing bytes")]
+    Truncated(usize),
+}
+
+/// Breakpoint file errors
+#[derive(Debug, Error)]
+pub enum BreakpointError {
+    #[error(transparent)]
+    Read(io::Error),
+
+    #[error(transparent)]
+    Write(io::Error),
+
+    #[error("expected 'on' or 'off', found '{0}'")]
+    InvalidState(String),
+
+    /// An error in a specific (1-based) line of the file
+    #[error("Line {line}: {source}")]
+    Line { line: usize, source: Box<BreakpointError> },
+}
+
+/// Board wiring errors
+#[derive(Debug, Error)]
+pub enum BoardError {
+    #[error("Net '{net}': no MCU {mcu}")]
+    NoSuchMcu { net: String, mcu: usize },
+
+    #[error("Net '{net}': the {device} has no pin {pin}")]
+    NoSuchPin { net: String, pin: u8, device: Device },
+}
+
+/// A halt condition name `HaltPolicy` does not know
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("unknown halt condition '{name}'")]
+pub struct HaltConditionError {
+    pub name: String,
+}
+
+/// Simulator errors
+#[derive(Debug, Error)]
+pub enum SimError {
+    #[error("Simulator is halted")]
+    Halted,
+
+    #[error("Decode error at PC=0x{pc:04X}: unknown instruction 0x{opcode:04X}")]
+    InvalidOpcode { pc: u16, opcode: u16 },
+
+    /// A condition enabled in the `HaltPolicy` occurred
+    #[error("Halted at PC=0x{pc:04X}: {reason}")]
+    HaltCondition { pc: u16, reason: HaltReason },
+
+    #[error("No execution history available")]
+    NoHistory,
+
+    #[error(transparent)]
+    Hex(#[from] HexError),
+
+    #[error(transparent)]
+    Srec(#[from] SrecError),
+
+    #[error(transparent)]
+    Elf(#[from] ElfError),
+
+    #[error(transparent)]
+    Coff(#[from] CoffError),
+
+    /// Assembler errors, in line order
+    #[error("{}", assembly_failed(.0))]
+    Assemble(Vec<AsmError>),
+
+    /// Assembling for a device whose core the assembler does not target
+    #[error("The assembler supports 14-bit core devices only, not the {0}")]
+    AsmUnsupported(Device),
+
+    #[error(transparent)]
+    Symbols(#[from] SymbolError),
+
+    #[error(transparent)]
+    Stimulus(#[from] StimulusError),
+
+    #[error(transparent)]
+    Circuit(#[from] CircuitError),
+
+    #[error(transparent)]
+    State(#[from] StateError),
+
+    #[error(transparent)]
+    Replay(#[from] ReplayError),
+
+    #[error(transparent)]
+    Golden(#[from] GoldenError),
+
+    /// A firmware file in none of the supported formats
+    #[error("{} is not a HEX, S-record, ELF or COFF file", .0.display())]
+    UnknownFormat(PathBuf),
+
+    /// A breakpoint given as a label or address that does not resolve
+    #[error("Unknown breakpoint address: {0}")]
+    UnknownAddress(String),
+
+    /// A pin number the selected device does not have
+    #[error("The {device} has no pin {pin}")]
+    NoSuchPin { pin: u8, device: Device },
+
+    #[error("{context}: {source}")]
+    Io { context: &'static str, source: io::Error },
+}
+
+fn assembly_failed(errors: &[AsmError]) -> String {
+    match errors {
+        [error] => format!("Assembly failed: {}", error),
+        [first, rest @ ..] => format!("Assembly failed: {} (and {} more errors)", first, rest.len()),
+        [] => "Assembly failed".to_string(),
+    }
+}
+
+impl SimError {
+    pub(crate) fn io(context: &'static str) -> impl FnOnce(io::Error) -> SimError {
+        move |source| SimError::Io { context, source }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        let err = SimError::InvalidOpcode { pc: 0x12, opcode: 0x3FFF };
+        assert_eq!(err.to_string(), "Decode error at PC=0x0012: unknown instruction 0x3FFF");
+
+        let err = HexError::Line {
+            line: 3,
+            column: 8,
+            text: ":00000007F9".to_string(),
+            source: Box::new(HexError::InvalidRecordType(0x07)),
+        };
+        assert_eq!(err.to_string(), "Line 3, column 8: Invalid record type: 0x07 in \":00000007F9\"");
+        assert!(matches!(err.kind(), HexError::InvalidRecordType(0x07)));
+
+        let err: SimError = err.into();
+        assert!(matches!(err, SimError::Hex(_)));
+
+        // I/O failures report the underlying error once
+        let err = HexError::Open(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+        assert_eq!(err.to_string(), "no such file");
+
+        let err = SimError::UnknownFormat(PathBuf::from("blink.bin"));
+        assert_eq!(err.to_string(), "blink.bin is not a HEX, S-record, ELF or COFF file");
+    }
+}
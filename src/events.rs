@@ -0,0 +1,132 @@
+//! Simulator events
+//!
+//! `Simulator` reports what happens during execution as `SimEvent`s to
+//! registered observers, so front-ends and tools can react to changes
+//! instead of polling registers every frame.
+
+use std::sync::mpsc::Sender;
+
+use crate::interrupt::InterruptSource;
+
+/// Something that happened during a simulator step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimEvent {
+    /// An instruction finished executing
+    InstructionExecuted {
+        /// Cycle count at which the instruction started
+        cycle: u64,
+        pc: u16,
+        word: u16,
+        /// Cycles consumed, including interrupt entry
+        cycles: u8,
+        /// W register after execution
+        w: u8,
+        /// STATUS register after execution
+        status: u8,
+    },
+
+    /// An instruction wrote a register (bank-resolved address)
+    MemoryWritten { addr: u16, old: u8, new: u8 },
+
+    /// GPIO pin levels changed
+    GpioChanged { old: u8, new: u8 },
+
+    /// An interrupt from `source` was taken at `pc`, jumping to `vector`
+    InterruptEntered { pc: u16, vector: u16, source: InterruptSource },
+
+    /// The watchdog timed out at `cycle` while running and reset the CPU
+    WdtReset { cycle: u64, pc: u16 },
+
+    /// SLEEP was executed at `pc`
+    SleepEntered { pc: u16 },
+
+    /// The CPU woke from sleep
+    Woke { by_interrupt: bool },
+}
+
+/// Handle used to remove an observer
+pub type ObserverId = usize;
+
+/// Event callback
+pub type Callback = Box<dyn FnMut(&SimEvent)>;
+
+/// Registered event callbacks
+#[derive(Default)]
+pub struct Observers {
+    next_id: ObserverId,
+    callbacks: Vec<(ObserverId, Callback)>,
+}
+
+impl Observers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a callback, returning its id
+    pub fn subscribe(&mut self, callback: Callback) -> ObserverId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.callbacks.push((id, callback));
+        id
+    }
+
+    /// Register a callback forwarding events to a channel
+    pub fn subscribe_channel(&mut self, sender: Sender<SimEvent>) -> ObserverId {
+        self.subscribe(Box::new(move |event| {
+            let _ = sender.send(*event);
+        }))
+    }
+
+    /// Remove a callback; returns false if `id` is unknown
+    pub fn unsubscribe(&mut self, id: ObserverId) -> bool {
+        let before = self.callbacks.len();
+        self.callbacks.retain(|(observer, _)| *observer != id);
+        self.callbacks.len() != before
+    }
+
+    /// Deliver an event to every callback
+    pub fn emit(&mut self, event: &SimEvent) {
+        for (_, callback) in &mut self.callbacks {
+            callback(event);
+        }
+    }
+
+    /// Check if no callbacks are registered
+    pub fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    /// Number of registered callbacks
+    pub fn len(&self) -> usize {
+        self.callbacks.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_subscribe_and_unsubscribe() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut observers = Observers::new();
+
+        let log = Rc::clone(&seen);
+        let id = observers.subscribe(Box::new(move |event| log.borrow_mut().push(*event)));
+        let (sender, receiver) = mpsc::channel();
+        observers.subscribe_channel(sender);
+        assert_eq!(observers.len(), 2);
+
+        observers.emit(&SimEvent::SleepEntered { pc: 0x10 });
+        assert!(observers.unsubscribe(id));
+        assert!(!observers.unsubscribe(id));
+        observers.emit(&SimEvent::Woke { by_interrupt: true });
+
+        assert_eq!(*seen.borrow(), vec![SimEvent::SleepEntered { pc: 0x10 }]);
+        let received: Vec<SimEvent> = receiver.try_iter().collect();
+        assert_eq!(received.len(), 2);
+    }
+}
@@ -1,6 +1,10 @@
-/// PIC12F629/675 Instruction Executor
-/// 
-/// Reference: Section 10.2 - Instruction Descriptions (Page 73-80)
+//! PIC12F629/675 Instruction Executor
+//! 
+//! Reference: Section 10.2 - Instruction Descriptions (Page 73-80)
+//! 
+//! Baseline (PIC10F2xx) programs run through the same executor: the
+//! instructions the cores share behave alike, and OPTION and TRIS load the
+//! registers the midrange core maps into bank 1.
 
 use crate::cpu::{Cpu, registers, status_bits};
 use crate::instruction::Instruction;
@@ -54,6 +58,11 @@ impl Executor {
             Instruction::SLEEP => Self::sleep(cpu),
             Instruction::SUBLW { k } => Self::sublw(cpu, k),
             Instruction::XORLW { k } => Self::xorlw(cpu, k),
+            
+            // ==================== Baseline Operations ====================
+            
+            Instruction::OPTION => Self::option(cpu),
+            Instruction::TRIS { f } => Self::tris(cpu, f),
         }
     }
     
@@ -62,7 +71,7 @@ impl Executor {
     /// ADDWF: Add W and f
     fn addwf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
         let w = cpu.read_w();
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let result = w.wrapping_add(val);
         
         // Carry flag
@@ -79,7 +88,7 @@ impl Executor {
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
@@ -87,21 +96,21 @@ impl Executor {
     /// ANDWF: AND W with f
     fn andwf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
         let w = cpu.read_w();
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let result = w & val;
         cpu.update_zero_flag(result);
         
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
     
     /// CLRF: Clear f
     fn clrf(cpu: &mut Cpu, f: u8) -> u8 {
-        cpu.write_register(f, 0);
+        cpu.write_file(f, 0);
         cpu.set_status_bit(status_bits::Z);
         1
     }
@@ -115,41 +124,41 @@ impl Executor {
     
     /// COMF: Complement f
     fn comf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let result = !val;
         cpu.update_zero_flag(result);
         
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
     
     /// DECF: Decrement f
     fn decf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let result = val.wrapping_sub(1);
         cpu.update_zero_flag(result);
         
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
     
     /// DECFSZ: Decrement f, Skip if 0
     fn decfsz(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let result = val.wrapping_sub(1);
         
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         
         if result == 0 {
@@ -162,27 +171,27 @@ impl Executor {
     
     /// INCF: Increment f
     fn incf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let result = val.wrapping_add(1);
         cpu.update_zero_flag(result);
         
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
     
     /// INCFSZ: Increment f, Skip if 0
     fn incfsz(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let result = val.wrapping_add(1);
         
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         
         if result == 0 {
@@ -196,27 +205,27 @@ impl Executor {
     /// IORWF: Inclusive OR W with f
     fn iorwf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
         let w = cpu.read_w();
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let result = w | val;
         cpu.update_zero_flag(result);
         
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
     
     /// MOVF: Move f
     fn movf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         cpu.update_zero_flag(val);
         
         if d == 0 {
             cpu.write_w(val);
         } else {
-            cpu.write_register(f, val);
+            cpu.write_file(f, val);
         }
         1
     }
@@ -224,13 +233,13 @@ impl Executor {
     /// MOVWF: Move W to f
     fn movwf(cpu: &mut Cpu, f: u8) -> u8 {
         let w = cpu.read_w();
-        cpu.write_register(f, w);
+        cpu.write_file(f, w);
         1
     }
     
     /// RLF: Rotate Left f through Carry
     fn rlf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let old_carry = if cpu.test_status_bit(status_bits::C) { 1 } else { 0 };
         let result = (val << 1) | old_carry;
         let new_carry = (val & 0x80) != 0;
@@ -240,14 +249,14 @@ impl Executor {
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
     
     /// RRF: Rotate Right f through Carry
     fn rrf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let old_carry = if cpu.test_status_bit(status_bits::C) { 0x80 } else { 0 };
         let result = (val >> 1) | old_carry;
         let new_carry = (val & 0x01) != 0;
@@ -257,7 +266,7 @@ impl Executor {
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
@@ -265,7 +274,7 @@ impl Executor {
     /// SUBWF: Subtract W from f
     fn subwf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
         let w = cpu.read_w();
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let result = val.wrapping_sub(w);
         
         // Carry = 1 if NO borrow (val >= w)
@@ -279,20 +288,20 @@ impl Executor {
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
     
     /// SWAPF: Swap nibbles in f
     fn swapf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
-        let val = cpu.read_register(f);
-        let result = (val << 4) | (val >> 4);
+        let val = cpu.read_file(f);
+        let result = val.rotate_right(4);
         
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
@@ -300,14 +309,14 @@ impl Executor {
     /// XORWF: Exclusive OR W with f
     fn xorwf(cpu: &mut Cpu, f: u8, d: u8) -> u8 {
         let w = cpu.read_w();
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         let result = w ^ val;
         cpu.update_zero_flag(result);
         
         if d == 0 {
             cpu.write_w(result);
         } else {
-            cpu.write_register(f, result);
+            cpu.write_file(f, result);
         }
         1
     }
@@ -316,21 +325,21 @@ impl Executor {
     
     /// BCF: Bit Clear f
     fn bcf(cpu: &mut Cpu, f: u8, b: u8) -> u8 {
-        let val = cpu.read_register(f);
-        cpu.write_register(f, val & !(1 << b));
+        let val = cpu.read_file(f);
+        cpu.write_file(f, val & !(1 << b));
         1
     }
     
     /// BSF: Bit Set f
     fn bsf(cpu: &mut Cpu, f: u8, b: u8) -> u8 {
-        let val = cpu.read_register(f);
-        cpu.write_register(f, val | (1 << b));
+        let val = cpu.read_file(f);
+        cpu.write_file(f, val | (1 << b));
         1
     }
     
     /// BTFSC: Bit Test f, Skip if Clear
     fn btfsc(cpu: &mut Cpu, f: u8, b: u8) -> u8 {
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         if (val & (1 << b)) == 0 {
             cpu.increment_pc();
             2
@@ -341,7 +350,7 @@ impl Executor {
     
     /// BTFSS: Bit Test f, Skip if Set
     fn btfss(cpu: &mut Cpu, f: u8, b: u8) -> u8 {
-        let val = cpu.read_register(f);
+        let val = cpu.read_file(f);
         if (val & (1 << b)) != 0 {
             cpu.increment_pc();
             2
@@ -471,6 +480,29 @@ impl Executor {
         cpu.write_w(result);
         1
     }
+    
+    // ==================== Baseline Implementations ====================
+    
+    /// OPTION: Load OPTION Register
+    fn option(cpu: &mut Cpu) -> u8 {
+        let w = cpu.read_w();
+        cpu.write_register(registers::OPTION_REG, w);
+        1
+    }
+    
+    /// TRIS: Load the TRIS register of the port at file address f
+    /// Ports the device lacks are ignored
+    fn tris(cpu: &mut Cpu, f: u8) -> u8 {
+        let port = cpu.effective_address(f);
+        let tris = cpu.device().model().ports.iter()
+            .find(|spec| spec.address as u16 == port)
+            .map(|spec| spec.tris_address);
+        if let Some(tris) = tris {
+            let w = cpu.read_w();
+            cpu.write_register(tris, w);
+        }
+        1
+    }
 }
 
 #[cfg(test)]
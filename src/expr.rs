@@ -0,0 +1,384 @@
+//! Debugger expressions
+//!
+//! Expressions over the machine state, used by conditional breakpoints and
+//! the GUI watch panel, e.g. `GPIO.GP2 && [0x20] > 10` or `PC == loop`.
+//!
+//! Operands are numbers (`42`, `0x2A`, `0b101010`), `W`, `PC`, `CYCLES`,
+//! register names (`GPIO`, `OPTION_REG`), register bits (`STATUS.Z`,
+//! `INTCON.7`, or a bare bit name such as `GIE`), code labels and data
+//! memory bytes (`[0x20]` or `mem[0x20]`). The operators are those of C, with C
+//! precedence; comparisons and logical operators give 1 or 0.
+
+use std::fmt;
+
+use crate::cpu::registers;
+use crate::simulator::Simulator;
+use crate::symbols::SymbolTable;
+
+/// A parsed expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+    source: String,
+    node: Node,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Literal(i64),
+    W,
+    Pc,
+    Cycles,
+    /// Bank-resolved register address
+    Register(u8),
+    /// Data memory byte at a computed address
+    Memory(Box<Node>),
+    Bit(Box<Node>, u8),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnaryOp {
+    Not,
+    Complement,
+    Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    /// Operator for `token` and its precedence (higher binds tighter)
+    fn from_token(token: &Token) -> Option<(Self, u8)> {
+        let Token::Symbol(symbol) = token else {
+            return None;
+        };
+        let op = match *symbol {
+            "||" => (BinaryOp::Or, 1),
+            "&&" => (BinaryOp::And, 2),
+            "|" => (BinaryOp::BitOr, 3),
+            "^" => (BinaryOp::BitXor, 4),
+            "&" => (BinaryOp::BitAnd, 5),
+            "==" => (BinaryOp::Eq, 6),
+            "!=" => (BinaryOp::Ne, 6),
+            "<" => (BinaryOp::Lt, 7),
+            "<=" => (BinaryOp::Le, 7),
+            ">" => (BinaryOp::Gt, 7),
+            ">=" => (BinaryOp::Ge, 7),
+            "<<" => (BinaryOp::Shl, 8),
+            ">>" => (BinaryOp::Shr, 8),
+            "+" => (BinaryOp::Add, 9),
+            "-" => (BinaryOp::Sub, 9),
+            "*" => (BinaryOp::Mul, 10),
+            "/" => (BinaryOp::Div, 10),
+            "%" => (BinaryOp::Rem, 10),
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn apply(self, a: i64, b: i64) -> i64 {
+        match self {
+            BinaryOp::Or => (a != 0 || b != 0) as i64,
+            BinaryOp::And => (a != 0 && b != 0) as i64,
+            BinaryOp::BitOr => a | b,
+            BinaryOp::BitXor => a ^ b,
+            BinaryOp::BitAnd => a & b,
+            BinaryOp::Eq => (a == b) as i64,
+            BinaryOp::Ne => (a != b) as i64,
+            BinaryOp::Lt => (a < b) as i64,
+            BinaryOp::Le => (a <= b) as i64,
+            BinaryOp::Gt => (a > b) as i64,
+            BinaryOp::Ge => (a >= b) as i64,
+            BinaryOp::Shl => a.wrapping_shl(b as u32),
+            BinaryOp::Shr => a.wrapping_shr(b as u32),
+            BinaryOp::Add => a.wrapping_add(b),
+            BinaryOp::Sub => a.wrapping_sub(b),
+            BinaryOp::Mul => a.wrapping_mul(b),
+            BinaryOp::Div => a.checked_div(b).unwrap_or(0),
+            BinaryOp::Rem => a.checked_rem(b).unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>",
+    "|", "^", "&", "<", ">", "+", "-", "*", "/", "%", "!", "~", "(", ")", "[", "]", ".",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let word_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if word_len > 0 {
+            let word = &rest[..word_len];
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                tokens.push(Token::Number(parse_number(word)?));
+            } else {
+                tokens.push(Token::Name(word.to_string()));
+            }
+            rest = &rest[word_len..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else {
+            return Err(format!("Unexpected character '{}'", rest.chars().next().unwrap_or_default()));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+fn parse_number(word: &str) -> Result<i64, String> {
+    let lower = word.to_ascii_lowercase();
+    let parsed = if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2)
+    } else {
+        lower.parse()
+    };
+    parsed.map_err(|_| format!("Invalid number '{}'", word))
+}
+
+/// Address and bit of a bit name such as `GIE`
+fn find_bit(name: &str) -> Option<(u8, u8)> {
+    registers::BIT_NAMES.iter().find_map(|(address, bits)| {
+        bits.iter()
+            .position(|bit| !bit.is_empty() && bit.eq_ignore_ascii_case(name))
+            .map(|bit| (*address, bit as u8))
+    })
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    symbols: &'a SymbolTable,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Symbol(s)) if s == symbol => Ok(()),
+            _ => Err(format!("Expected '{}'", symbol)),
+        }
+    }
+
+    /// Binary operators binding at least as tightly as `min_precedence`
+    fn binary(&mut self, min_precedence: u8) -> Result<Node, String> {
+        let mut left = self.unary()?;
+        while let Some((op, precedence)) = self.peek().and_then(BinaryOp::from_token) {
+            if precedence < min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let right = self.binary(precedence + 1)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        let op = match self.peek() {
+            Some(Token::Symbol("!")) => UnaryOp::Not,
+            Some(Token::Symbol("~")) => UnaryOp::Complement,
+            Some(Token::Symbol("-")) => UnaryOp::Negate,
+            _ => return self.postfix(),
+        };
+        self.pos += 1;
+        Ok(Node::Unary(op, Box::new(self.unary()?)))
+    }
+
+    /// An operand followed by any `.bit` selections
+    fn postfix(&mut self) -> Result<Node, String> {
+        let mut node = self.primary()?;
+        while self.peek() == Some(&Token::Symbol(".")) {
+            self.pos += 1;
+            let bit = match self.next() {
+                Some(Token::Number(bit @ 0..=7)) => bit as u8,
+                Some(Token::Name(name)) => {
+                    let register = match node {
+                        Node::Register(address) => Some(address),
+                        _ => None,
+                    };
+                    register
+                        .and_then(|address| {
+                            (0..8).find(|&bit| {
+                                registers::bit_name(address, bit).is_some_and(|n| n.eq_ignore_ascii_case(&name))
+                            })
+                        })
+                        .ok_or_else(|| format!("Unknown bit '{}'", name))?
+                }
+                _ => return Err("Expected a bit number (0-7) or name after '.'".to_string()),
+            };
+            node = Node::Bit(Box::new(node), bit);
+        }
+        Ok(node)
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Node::Literal(value)),
+            Some(Token::Symbol("(")) => {
+                let node = self.binary(0)?;
+                self.expect(")")?;
+                Ok(node)
+            }
+            Some(Token::Symbol("[")) => {
+                let node = self.binary(0)?;
+                self.expect("]")?;
+                Ok(Node::Memory(Box::new(node)))
+            }
+            Some(Token::Name(name)) if name.eq_ignore_ascii_case("mem") && self.peek() == Some(&Token::Symbol("[")) => {
+                self.primary()
+            }
+            Some(Token::Name(name)) => self.name(&name),
+            Some(Token::Symbol(symbol)) => Err(format!("Unexpected '{}'", symbol)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+
+    fn name(&self, name: &str) -> Result<Node, String> {
+        let node = match name.to_ascii_uppercase().as_str() {
+            "W" | "WREG" => Node::W,
+            "PC" => Node::Pc,
+            "CYCLES" => Node::Cycles,
+            _ => {
+                if let Some(address) = registers::address_of(name) {
+                    Node::Register(address)
+                } else if let Some(address) = self.symbols.address_of(name) {
+                    Node::Literal(address as i64)
+                } else if let Some((address, bit)) = find_bit(name) {
+                    Node::Bit(Box::new(Node::Register(address)), bit)
+                } else {
+                    return Err(format!("Unknown name '{}'", name));
+                }
+            }
+        };
+        Ok(node)
+    }
+}
+
+impl Expr {
+    /// Parse `text`, resolving code labels through `symbols`
+    pub fn parse(text: &str, symbols: &SymbolTable) -> Result<Self, String> {
+        let mut parser = Parser { tokens: tokenize(text)?, pos: 0, symbols };
+        let node = parser.binary(0)?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("Unexpected {:?} after expression", token));
+        }
+        Ok(Expr { source: text.trim().to_string(), node })
+    }
+
+    /// Evaluate against the current machine state
+    ///
+    /// Division by zero gives 0 rather than failing.
+    pub fn eval(&self, sim: &Simulator) -> i64 {
+        eval_node(&self.node, sim)
+    }
+
+    /// Whether the expression evaluates to a non-zero value
+    pub fn is_true(&self, sim: &Simulator) -> bool {
+        self.eval(sim) != 0
+    }
+}
+
+fn eval_node(node: &Node, sim: &Simulator) -> i64 {
+    let cpu = sim.cpu();
+    match node {
+        Node::Literal(value) => *value,
+        Node::W => cpu.read_w() as i64,
+        Node::Pc => cpu.get_pc() as i64,
+        Node::Cycles => sim.stats().cycles_elapsed as i64,
+        Node::Register(address) => cpu.read_register(*address) as i64,
+        Node::Memory(address) => cpu.read_register(eval_node(address, sim) as u8) as i64,
+        Node::Bit(value, bit) => (eval_node(value, sim) >> bit) & 1,
+        Node::Unary(op, value) => {
+            let value = eval_node(value, sim);
+            match op {
+                UnaryOp::Not => (value == 0) as i64,
+                UnaryOp::Complement => !value,
+                UnaryOp::Negate => value.wrapping_neg(),
+            }
+        }
+        Node::Binary(BinaryOp::And, a, b) => (eval_node(a, sim) != 0 && eval_node(b, sim) != 0) as i64,
+        Node::Binary(BinaryOp::Or, a, b) => (eval_node(a, sim) != 0 || eval_node(b, sim) != 0) as i64,
+        Node::Binary(op, a, b) => op.apply(eval_node(a, sim), eval_node(b, sim)),
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(&[0x3005, 0x00A0, 0x1505, 0x2803]);
+        sim.run_n_instructions(3).unwrap();
+
+        let mut symbols = SymbolTable::new();
+        symbols.add_label("loop", 0x003);
+        let eval = |text: &str| Expr::parse(text, &symbols).unwrap().eval(&sim);
+
+        assert_eq!(eval("W"), 5);
+        assert_eq!(eval("[0x20] * 2 + 1"), 11);
+        assert_eq!(eval("mem[0x20] + W"), 10);
+        assert_eq!(eval("PC == loop && [0x20].2 && !GIE"), 1);
+        assert_eq!(eval("STATUS.Z || !(W & 4)"), 0);
+        assert_eq!(eval("1 + 2 * 3 == 7"), 1);
+        assert_eq!(eval("0b1010 >> 1 | 0x10"), 0x15);
+        assert_eq!(eval("-W / 0"), 0);
+
+        assert!(Expr::parse("GPIO.BOGUS", &symbols).is_err());
+        assert!(Expr::parse("(W", &symbols).is_err());
+        assert!(Expr::parse("W W", &symbols).is_err());
+        assert_eq!(Expr::parse(" W > 3 ", &symbols).unwrap().to_string(), "W > 3");
+    }
+}
@@ -0,0 +1,204 @@
+//! Golden execution traces
+//!
+//! A golden trace records the PC, W, STATUS and GPIO after every executed
+//! instruction. Re-running the same program against it reports the first
+//! instruction whose state differs, which catches regressions in both the
+//! simulator and refactored firmware.
+//!
+//! Files are binary: the `GOLDEN_MAGIC` header followed by one 5-byte
+//! record per instruction (PC little-endian, W, STATUS, GPIO).
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::cpu::registers;
+use crate::error::{GoldenError, SimError};
+use crate::simulator::Simulator;
+
+/// First bytes of a golden trace file
+pub const GOLDEN_MAGIC: &[u8; 8] = b"PICGOLD1";
+
+const RECORD_SIZE: usize = 5;
+
+/// Machine state after one instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GoldenEntry {
+    pub pc: u16,
+    pub w: u8,
+    pub status: u8,
+    pub gpio: u8,
+}
+
+impl GoldenEntry {
+    /// Capture the current state of a simulator
+    pub fn capture(sim: &Simulator) -> Self {
+        let cpu = sim.cpu();
+        Self {
+            pc: cpu.get_pc(),
+            w: cpu.read_w(),
+            status: cpu.read_register(registers::STATUS),
+            gpio: cpu.gpio().read_gpio(),
+        }
+    }
+}
+
+impl fmt::Display for GoldenEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PC=0x{:04X} W=0x{:02X} STATUS=0x{:02X} GPIO=0b{:06b}",
+            self.pc, self.w, self.status, self.gpio)
+    }
+}
+
+/// First instruction whose state differs from the golden trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the instruction (0 = first executed)
+    pub index: usize,
+    /// Cycle count after the instruction
+    pub cycle: u64,
+    pub expected: GoldenEntry,
+    pub actual: GoldenEntry,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Divergence at instruction {} (cycle {}):", self.index, self.cycle)?;
+        writeln!(f, "  expected: {}", self.expected)?;
+        write!(f, "  actual:   {}", self.actual)
+    }
+}
+
+/// Recorded per-instruction states
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoldenTrace {
+    entries: Vec<GoldenEntry>,
+}
+
+impl GoldenTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Execute `instructions` instructions, recording the state after each
+    pub fn record(sim: &mut Simulator, instructions: u64) -> Result<Self, SimError> {
+        let mut trace = Self::new();
+        for _ in 0..instructions {
+            sim.step()?;
+            trace.entries.push(GoldenEntry::capture(sim));
+        }
+        Ok(trace)
+    }
+
+    /// Re-execute the recorded number of instructions, stopping at the
+    /// first state that differs
+    pub fn compare(&self, sim: &mut Simulator) -> Result<Option<Divergence>, SimError> {
+        for (index, expected) in self.entries.iter().enumerate() {
+            sim.step()?;
+            let actual = GoldenEntry::capture(sim);
+            if actual != *expected {
+                return Ok(Some(Divergence {
+                    index,
+                    cycle: sim.stats().cycles_elapsed,
+                    expected: *expected,
+                    actual,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Recorded states
+    pub fn entries(&self) -> &[GoldenEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Encode in the golden file format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(GOLDEN_MAGIC.len() + self.entries.len() * RECORD_SIZE);
+        bytes.extend_from_slice(GOLDEN_MAGIC);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.pc.to_le_bytes());
+            bytes.extend_from_slice(&[entry.w, entry.status, entry.gpio]);
+        }
+        bytes
+    }
+
+    /// Decode the golden file format
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GoldenError> {
+        let records = bytes.strip_prefix(GOLDEN_MAGIC.as_slice())
+            .ok_or(GoldenError::BadMagic)?;
+        if records.len() % RECORD_SIZE != 0 {
+            return Err(GoldenError::Truncated(records.len() % RECORD_SIZE));
+        }
+
+        let entries = records.chunks_exact(RECORD_SIZE)
+            .map(|record| GoldenEntry {
+                pc: u16::from_le_bytes([record[0], record[1]]),
+                w: record[2],
+                status: record[3],
+                gpio: record[4],
+            })
+            .collect();
+        Ok(Self { entries })
+    }
+
+    /// Write the trace to a file
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<(), GoldenError> {
+        fs::write(path, self.to_bytes()).map_err(GoldenError::Write)
+    }
+
+    /// Read a trace from a file
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self, GoldenError> {
+        let bytes = fs::read(path).map_err(GoldenError::Read)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter() -> Simulator {
+        let mut sim = Simulator::new();
+        sim.reset();
+        // INCF 0x20, F; MOVF 0x20, W; GOTO 0x000
+        sim.load_program(&[0x0AA0, 0x0820, 0x2800]);
+        sim
+    }
+
+    #[test]
+    fn test_record_and_compare() {
+        let golden = GoldenTrace::record(&mut counter(), 30).unwrap();
+        assert_eq!(golden.len(), 30);
+        assert_eq!(golden.entries()[1], GoldenEntry { pc: 2, w: 1, status: 0x18, gpio: 0x3F });
+
+        let decoded = GoldenTrace::from_bytes(&golden.to_bytes()).unwrap();
+        assert_eq!(decoded, golden);
+        assert_eq!(decoded.compare(&mut counter()).unwrap(), None);
+
+        // Change the firmware: count from 5
+        let mut sim = counter();
+        sim.cpu_mut().write_register(0x20, 5);
+        let divergence = golden.compare(&mut sim).unwrap().unwrap();
+        assert_eq!(divergence.index, 1);
+        assert_eq!(divergence.actual.w, 6);
+        assert!(divergence.to_string().contains("instruction 1"));
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        assert!(matches!(GoldenTrace::from_bytes(b"PICSTATE"), Err(GoldenError::BadMagic)));
+        let mut bytes = GoldenTrace::new().to_bytes();
+        bytes.push(0);
+        assert!(matches!(GoldenTrace::from_bytes(&bytes), Err(GoldenError::Truncated(1))));
+    }
+}
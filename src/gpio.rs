@@ -1,12 +1,29 @@
-/// PIC12F629/675 GPIO Port Implementation
-/// 
-/// Reference: Section 3.0 - I/O Ports (Page 15-18)
-/// 
-/// The PIC12F629/675 has a 6-bit bidirectional port (GPIO):
-/// - GP5, GP4, GP3, GP2, GP1, GP0
-/// - GP3 is input only (no TRIS control)
-/// - Each pin can be configured as input or output via TRISIO
-/// - Weak pull-ups available on GPIO<0:5> when enabled
+//! I/O Port Implementation
+//!
+//! Reference: Section 3.0 - I/O Ports (Page 15-18)
+//!
+//! The PIC12F629/675 has a 6-bit bidirectional port (GPIO):
+//! - GP5, GP4, GP3, GP2, GP1, GP0
+//! - GP3 is input only (no TRIS control)
+//! - Each pin can be configured as input or output via TRISIO
+//! - Weak pull-ups available on GPIO<0:5> when enabled
+//!
+//! Other parts have several ports (the PIC16F84A has a 5-bit PORTA and an
+//! 8-bit PORTB), described by the `PortSpec`s of their device model. Pins
+//! are numbered across ports as `port * 8 + bit`, so on a single-port part
+//! pin numbers are simply GP0-GP5. The port-less methods (`read_gpio`,
+//! `write_tris`, ...) act on the first port.
+//!
+//! Pins can also be given an analog voltage (see `set_pin_voltage`) for
+//! the analog peripherals; as a digital input such a pin reads high from
+//! VDD/2 up.
+
+use crate::cpu::registers;
+use crate::error::StateError;
+use crate::state::StateRecord;
+
+/// Supply voltage assumed for analog pin levels
+pub const VDD: f32 = 5.0;
 
 /// GPIO pin state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,13 +33,117 @@ pub enum PinState {
     HighZ, // High impedance (input mode)
 }
 
-/// GPIO port controller
+/// Layout of one I/O port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortSpec {
+    /// Port register name
+    pub name: &'static str,
+    /// Pin name prefix (GP, RA, RB)
+    pub pin_prefix: &'static str,
+    /// Port register address
+    pub address: u8,
+    /// TRIS register address
+    pub tris_address: u8,
+    /// Implemented pins
+    pub pins: u8,
+    /// Pins that are always inputs
+    pub input_only: u8,
+    /// Pins with a weak pull-up
+    pub pullups: u8,
+}
+
+impl PortSpec {
+    /// The 6-bit GPIO port of the PIC12F6xx
+    pub const GPIO: PortSpec = PortSpec {
+        name: "GPIO",
+        pin_prefix: "GP",
+        address: registers::GPIO,
+        tris_address: registers::TRISIO,
+        pins: 0x3F,
+        input_only: 0x08,
+        pullups: 0x37, // GP3 and GP5 don't have pull-ups
+    };
+    
+    /// The 4-bit GPIO port of the PIC10F2xx
+    pub const GPIO_BASELINE: PortSpec = PortSpec {
+        name: "GPIO",
+        pin_prefix: "GP",
+        address: registers::GPIO,
+        tris_address: registers::TRISIO,
+        pins: 0x0F,
+        input_only: 0x08,
+        pullups: 0x0B, // GP2 doesn't have a pull-up
+    };
+    
+    /// The 5-bit PORTA of the PIC16F84A
+    pub const PORTA: PortSpec = PortSpec {
+        name: "PORTA",
+        pin_prefix: "RA",
+        address: registers::PORTA,
+        tris_address: registers::TRISA,
+        pins: 0x1F,
+        input_only: 0x00,
+        pullups: 0x00,
+    };
+    
+    /// The 8-bit PORTB of the PIC16F84A, with pull-ups enabled by RBPU
+    pub const PORTB: PortSpec = PortSpec {
+        name: "PORTB",
+        pin_prefix: "RB",
+        address: registers::PORTB,
+        tris_address: registers::TRISB,
+        pins: 0xFF,
+        input_only: 0x00,
+        pullups: 0xFF,
+    };
+    
+    /// Name of `bit` in this port (e.g. "GP2")
+    pub fn pin_name(&self, bit: u8) -> String {
+        format!("{}{}", self.pin_prefix, bit)
+    }
+}
+
+/// Parse a pin given by name (`GP2`, `RA4`, `RB0`) or number (`8`)
+///
+/// Names follow the `port * 8 + bit` numbering: GP and RA pins are on the
+/// first port, RB pins on the second. Whether the pin exists is up to the
+/// device (see `DeviceModel::has_pin`).
+pub fn parse_pin(s: &str) -> Option<u8> {
+    let upper = s.trim().to_ascii_uppercase();
+    let prefixed = [("GP", 0), ("RA", 0), ("RB", 8)].iter()
+        .find_map(|(prefix, base)| upper.strip_prefix(prefix).map(|bit| (bit, *base)));
+    match prefixed {
+        Some((bit, base)) => bit.parse::<u8>().ok().filter(|&bit| bit < 8).map(|bit| base + bit),
+        None => upper.parse().ok(),
+    }
+}
+
+/// Name of a pin number that `parse_pin` reads back: GPn on the first
+/// port and RBn on the second
+pub fn pin_label(pin: u8) -> String {
+    match pin {
+        0..8 => format!("GP{}", pin),
+        _ => format!("RB{}", pin - 8),
+    }
+}
+
+/// State of one port
 #[derive(Debug, Clone)]
-pub struct Gpio {
-    /// Current port value (what's written to GPIO register)
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Port {
+    /// Implemented pins
+    pins: u8,
+    
+    /// Pins that are always inputs
+    input_only: u8,
+    
+    /// Pins with a weak pull-up
+    pullups: u8,
+    
+    /// Current port value (what's written to the port register)
     port_value: u8,
     
-    /// Port direction (TRISIO) - 1 = input, 0 = output
+    /// Port direction (TRIS) - 1 = input, 0 = output
     tris: u8,
     
     /// Weak pull-up enable (WPU) - 1 = enabled, 0 = disabled
@@ -36,192 +157,272 @@ pub struct Gpio {
     
     /// Pin output value (from peripherals)
     peripheral_output_value: u8,
+    
+    /// Analog voltages applied to the pins, by bit
+    voltages: [Option<f32>; 8],
 }
 
-impl Gpio {
-    /// Create new GPIO controller
-    pub fn new() -> Self {
+impl Port {
+    fn new(spec: &PortSpec) -> Self {
         Self {
+            pins: spec.pins,
+            input_only: spec.input_only,
+            pullups: spec.pullups,
             port_value: 0x00,
-            tris: 0x3F,        // All inputs by default
-            weak_pullup: 0x00,  // Pull-ups disabled
-            external_pins: 0x3F, // All high by default
+            tris: spec.pins,          // All inputs by default
+            weak_pullup: 0x00,        // Pull-ups disabled
+            external_pins: spec.pins, // All high by default
             peripheral_output_enable: 0x00,
             peripheral_output_value: 0x00,
+            voltages: [None; 8],
+        }
+    }
+    
+    /// Pin levels considering direction, peripherals and external inputs
+    fn read(&self) -> u8 {
+        // Peripherals override the pin; inputs read the external level
+        // (a pull-up only matters for a floating pin, which reads high);
+        // outputs read the port latch
+        let peripheral = self.peripheral_output_enable & self.peripheral_output_value;
+        let input = !self.peripheral_output_enable & self.tris & self.external_pins;
+        let output = !self.peripheral_output_enable & !self.tris & self.port_value;
+        (peripheral | input | output) & self.pins
+    }
+    
+    fn pin_state(&self, mask: u8) -> PinState {
+        // Check peripheral control first
+        if self.peripheral_output_enable & mask != 0 {
+            if self.peripheral_output_value & mask != 0 {
+                PinState::High
+            } else {
+                PinState::Low
+            }
+        } else if self.tris & mask != 0 {
+            // Input mode
+            PinState::HighZ
+        } else if self.port_value & mask != 0 {
+            PinState::High
+        } else {
+            PinState::Low
+        }
+    }
+}
+
+/// GPIO port controller
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gpio {
+    ports: Vec<Port>,
+}
+
+impl Gpio {
+    /// Create new GPIO controller with the 12F6xx GPIO port
+    pub fn new() -> Self {
+        Self::with_ports(&[PortSpec::GPIO])
+    }
+    
+    /// Create a controller with the given ports
+    pub fn with_ports(specs: &[PortSpec]) -> Self {
+        Self {
+            ports: specs.iter().map(Port::new).collect(),
         }
     }
     
     /// Reset to power-on state
     pub fn reset(&mut self) {
-        self.port_value = 0x00;
-        self.tris = 0x3F;       // All inputs
-        self.weak_pullup = 0x00;
-        self.external_pins = 0x3F;
-        self.peripheral_output_enable = 0x00;
-        self.peripheral_output_value = 0x00;
+        for port in &mut self.ports {
+            port.port_value = 0x00;
+            port.tris = port.pins;       // All inputs
+            port.weak_pullup = 0x00;
+            port.external_pins = port.pins;
+            port.peripheral_output_enable = 0x00;
+            port.peripheral_output_value = 0x00;
+            port.voltages = [None; 8];
+        }
+    }
+    
+    /// Number of ports
+    pub fn port_count(&self) -> usize {
+        self.ports.len()
+    }
+    
+    /// Port and bit mask of a pin number, if the pin exists
+    fn locate(&self, pin: u8) -> Option<(&Port, u8)> {
+        let port = self.ports.get(pin as usize / 8)?;
+        let mask = 1 << (pin % 8);
+        (port.pins & mask != 0).then_some((port, mask))
+    }
+    
+    fn locate_mut(&mut self, pin: u8) -> Option<(&mut Port, u8)> {
+        let port = self.ports.get_mut(pin as usize / 8)?;
+        let mask = 1 << (pin % 8);
+        (port.pins & mask != 0).then_some((port, mask))
     }
     
     /// Write to GPIO register
     pub fn write_gpio(&mut self, value: u8) {
-        self.port_value = value & 0x3F;
+        self.write_port(0, value);
     }
     
     /// Read from GPIO register
     /// Returns the actual pin states considering direction, pull-ups, and external inputs
     pub fn read_gpio(&self) -> u8 {
-        let mut result = 0u8;
-        
-        for bit in 0..6 {
-            let mask = 1 << bit;
-            
-            // Check if pin is controlled by peripheral
-            if self.peripheral_output_enable & mask != 0 {
-                // Peripheral controls this pin
-                if self.peripheral_output_value & mask != 0 {
-                    result |= mask;
-                }
-            } else if self.tris & mask != 0 {
-                // Input mode - read from external pins
-                // Apply weak pull-up if enabled
-                if self.weak_pullup & mask != 0 {
-                    // If external pin is high-Z or high, read as high
-                    if self.external_pins & mask != 0 {
-                        result |= mask;
-                    }
-                } else {
-                    // No pull-up, read external state directly
-                    if self.external_pins & mask != 0 {
-                        result |= mask;
-                    }
-                }
-            } else {
-                // Output mode - read from port latch
-                if self.port_value & mask != 0 {
-                    result |= mask;
-                }
-            }
+        self.read_port(0)
+    }
+    
+    /// Write the latch of port `index`
+    pub fn write_port(&mut self, index: usize, value: u8) {
+        if let Some(port) = self.ports.get_mut(index) {
+            port.port_value = value & port.pins;
         }
-        
-        result
+    }
+    
+    /// Read the pin levels of port `index` (0 for a missing port)
+    pub fn read_port(&self, index: usize) -> u8 {
+        self.ports.get(index).map_or(0, Port::read)
+    }
+    
+    /// Read the pin levels of the first two ports, bit n for pin n
+    pub fn read_pins(&self) -> u16 {
+        u16::from_le_bytes([self.read_port(0), self.read_port(1)])
     }
     
     /// Write to TRISIO register (direction control)
     pub fn write_tris(&mut self, value: u8) {
-        // GP3 is always input
-        self.tris = (value & 0x3F) | 0x08;
+        self.write_port_tris(0, value);
     }
     
     /// Read TRISIO register
     pub fn read_tris(&self) -> u8 {
-        self.tris
+        self.read_port_tris(0)
+    }
+    
+    /// Write the TRIS register of port `index`
+    pub fn write_port_tris(&mut self, index: usize, value: u8) {
+        if let Some(port) = self.ports.get_mut(index) {
+            // Input-only pins (GP3) stay inputs
+            port.tris = (value & port.pins) | port.input_only;
+        }
+    }
+    
+    /// Read the TRIS register of port `index`
+    pub fn read_port_tris(&self, index: usize) -> u8 {
+        self.ports.get(index).map_or(0, |port| port.tris)
     }
     
     /// Write to WPU (Weak Pull-Up) register
     pub fn write_wpu(&mut self, value: u8) {
-        self.weak_pullup = value & 0x37; // GP3 and GP5 don't have pull-ups
+        self.write_port_wpu(0, value);
     }
     
     /// Read WPU register
     pub fn read_wpu(&self) -> u8 {
-        self.weak_pullup
+        self.read_port_wpu(0)
+    }
+    
+    /// Enable the weak pull-ups of port `index` selected by `value`
+    pub fn write_port_wpu(&mut self, index: usize, value: u8) {
+        if let Some(port) = self.ports.get_mut(index) {
+            port.weak_pullup = value & port.pullups;
+        }
+    }
+    
+    /// Weak pull-ups enabled on port `index`
+    pub fn read_port_wpu(&self, index: usize) -> u8 {
+        self.ports.get(index).map_or(0, |port| port.weak_pullup)
     }
     
     /// Set external pin state (for simulation)
     pub fn set_external_pin(&mut self, pin: u8, state: bool) {
-        if pin < 6 {
+        if let Some((port, mask)) = self.locate_mut(pin) {
             if state {
-                self.external_pins |= 1 << pin;
+                port.external_pins |= mask;
             } else {
-                self.external_pins &= !(1 << pin);
+                port.external_pins &= !mask;
             }
         }
     }
     
     /// Get external pin state
     pub fn get_external_pin(&self, pin: u8) -> bool {
-        if pin < 6 {
-            (self.external_pins & (1 << pin)) != 0
-        } else {
-            false
+        self.locate(pin).is_some_and(|(port, mask)| port.external_pins & mask != 0)
+    }
+    
+    /// Apply an analog voltage to a pin (clamped to 0 - VDD)
+    /// 
+    /// The pin's external digital level follows the voltage.
+    pub fn set_pin_voltage(&mut self, pin: u8, volts: f32) {
+        let volts = volts.clamp(0.0, VDD);
+        if let Some((port, mask)) = self.locate_mut(pin) {
+            port.voltages[mask.trailing_zeros() as usize] = Some(volts);
         }
+        self.set_external_pin(pin, volts >= VDD / 2.0);
     }
     
-    /// Set all external pins at once
+    /// Voltage on a pin: the applied analog voltage, or VDD / 0 V for a
+    /// pin that is only driven digitally
+    pub fn pin_voltage(&self, pin: u8) -> f32 {
+        let Some((port, mask)) = self.locate(pin) else {
+            return 0.0;
+        };
+        let digital = if port.read() & mask != 0 { VDD } else { 0.0 };
+        if port.tris & mask == 0 || port.peripheral_output_enable & mask != 0 {
+            return digital;
+        }
+        port.voltages[mask.trailing_zeros() as usize].unwrap_or(digital)
+    }
+    
+    /// Set all external pins of the first port at once
     pub fn set_external_pins(&mut self, value: u8) {
-        self.external_pins = value & 0x3F;
+        if let Some(port) = self.ports.first_mut() {
+            port.external_pins = value & port.pins;
+        }
     }
     
     /// Get current output values (what would be driven if pins are outputs)
     pub fn get_output_values(&self) -> u8 {
-        self.port_value
+        self.ports.first().map_or(0, |port| port.port_value)
     }
     
     /// Get pin direction (true = input, false = output)
     pub fn is_input(&self, pin: u8) -> bool {
-        if pin < 6 {
-            (self.tris & (1 << pin)) != 0
-        } else {
-            false
-        }
+        self.locate(pin).is_some_and(|(port, mask)| port.tris & mask != 0)
     }
     
     /// Get pin state considering all factors
     pub fn get_pin_state(&self, pin: u8) -> PinState {
-        if pin >= 6 {
-            return PinState::HighZ;
-        }
-        
-        let mask = 1 << pin;
-        
-        // Check peripheral control first
-        if self.peripheral_output_enable & mask != 0 {
-            if self.peripheral_output_value & mask != 0 {
-                return PinState::High;
-            } else {
-                return PinState::Low;
-            }
-        }
-        
-        // Check if input or output
-        if self.tris & mask != 0 {
-            // Input mode
-            PinState::HighZ
-        } else {
-            // Output mode
-            if self.port_value & mask != 0 {
-                PinState::High
-            } else {
-                PinState::Low
-            }
-        }
+        self.locate(pin).map_or(PinState::HighZ, |(port, mask)| port.pin_state(mask))
+    }
+    
+    /// Level of a pin as the input buffer sees it, whatever its direction
+    pub fn pin_level(&self, pin: u8) -> bool {
+        self.locate(pin).is_some_and(|(port, mask)| port.read() & mask != 0)
     }
     
     /// Enable peripheral control of a pin (e.g., for comparator output)
     pub fn set_peripheral_control(&mut self, pin: u8, enable: bool, value: bool) {
-        if pin < 6 {
-            let mask = 1 << pin;
-            
+        if let Some((port, mask)) = self.locate_mut(pin) {
             if enable {
-                self.peripheral_output_enable |= mask;
+                port.peripheral_output_enable |= mask;
                 if value {
-                    self.peripheral_output_value |= mask;
+                    port.peripheral_output_value |= mask;
                 } else {
-                    self.peripheral_output_value &= !mask;
+                    port.peripheral_output_value &= !mask;
                 }
             } else {
-                self.peripheral_output_enable &= !mask;
+                port.peripheral_output_enable &= !mask;
             }
         }
     }
     
-    /// Get a visual representation of the port
+    /// Get a visual representation of the first port
     pub fn get_visual_state(&self) -> String {
         let mut result = String::new();
         
         for pin in (0..6).rev() {
             let state = self.get_pin_state(pin);
             let is_input = self.is_input(pin);
-            let has_pullup = (self.weak_pullup & (1 << pin)) != 0;
+            let has_pullup = (self.read_wpu() & (1 << pin)) != 0;
             
             result.push_str(&format!("GP{}: ", pin));
             
@@ -236,7 +437,7 @@ impl Gpio {
                 if has_pullup {
                     result.push_str(" ↑");
                 }
-                result.push_str("]");
+                result.push(']');
             } else {
                 result.push_str(" [OUT]");
             }
@@ -248,6 +449,42 @@ impl Gpio {
         
         result
     }
+    
+    /// Save port latches, directions, pull-ups and external pin levels,
+    /// one byte per port
+    pub fn save_state(&self, record: &mut StateRecord) {
+        let field = |get: fn(&Port) -> u8| -> Vec<u8> { self.ports.iter().map(get).collect() };
+        record.set_bytes("gpio.port_value", &field(|port| port.port_value));
+        record.set_bytes("gpio.tris", &field(|port| port.tris));
+        record.set_bytes("gpio.weak_pullup", &field(|port| port.weak_pullup));
+        record.set_bytes("gpio.external_pins", &field(|port| port.external_pins));
+        record.set_bytes("gpio.peripheral_output_enable", &field(|port| port.peripheral_output_enable));
+        record.set_bytes("gpio.peripheral_output_value", &field(|port| port.peripheral_output_value));
+    }
+    
+    /// Restore state saved with `save_state`; the port count must match
+    pub fn load_state(&mut self, record: &StateRecord) -> Result<(), StateError> {
+        let field = |key: &str| -> Result<Vec<u8>, StateError> {
+            let mut values = vec![0; self.ports.len()];
+            record.get_bytes(key, &mut values)?;
+            Ok(values)
+        };
+        let port_value = field("gpio.port_value")?;
+        let tris = field("gpio.tris")?;
+        let weak_pullup = field("gpio.weak_pullup")?;
+        let external_pins = field("gpio.external_pins")?;
+        let peripheral_output_enable = field("gpio.peripheral_output_enable")?;
+        let peripheral_output_value = field("gpio.peripheral_output_value")?;
+        for (i, port) in self.ports.iter_mut().enumerate() {
+            port.port_value = port_value[i];
+            port.tris = tris[i];
+            port.weak_pullup = weak_pullup[i];
+            port.external_pins = external_pins[i];
+            port.peripheral_output_enable = peripheral_output_enable[i];
+            port.peripheral_output_value = peripheral_output_value[i];
+        }
+        Ok(())
+    }
 }
 
 impl Default for Gpio {
@@ -325,4 +562,38 @@ mod tests {
         // GP3 should still be input
         assert!(gpio.is_input(3));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_multiple_ports() {
+        let mut gpio = Gpio::with_ports(&[PortSpec::PORTA, PortSpec::PORTB]);
+        assert_eq!(gpio.port_count(), 2);
+        assert_eq!((gpio.read_port_tris(0), gpio.read_port_tris(1)), (0x1F, 0xFF));
+        
+        // RB7 is pin 15; PORTA has no bit 5
+        gpio.write_port_tris(1, 0x7F);
+        gpio.write_port(1, 0x80);
+        assert_eq!(gpio.get_pin_state(15), PinState::High);
+        gpio.set_external_pin(8, false);
+        assert_eq!(gpio.read_port(1), 0xFE);
+        assert_eq!(gpio.get_pin_state(5), PinState::HighZ);
+        assert!(!gpio.is_input(5));
+        
+        let mut record = StateRecord::new();
+        gpio.save_state(&mut record);
+        let mut restored = Gpio::with_ports(&[PortSpec::PORTA, PortSpec::PORTB]);
+        restored.load_state(&record).unwrap();
+        assert_eq!(restored.read_port(1), 0xFE);
+        assert!(Gpio::new().load_state(&record).is_err());
+    }
+    
+    #[test]
+    fn test_parse_pin() {
+        assert_eq!(parse_pin("GP2"), Some(2));
+        assert_eq!(parse_pin("ra4"), Some(4));
+        assert_eq!(parse_pin("RB0"), Some(8));
+        assert_eq!(parse_pin("13"), Some(13));
+        assert_eq!(parse_pin("GP8"), None);
+        assert_eq!(parse_pin("RC1"), None);
+        assert_eq!(parse_pin(&pin_label(15)), Some(15));
+    }
+}
@@ -1,7 +1,26 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+
 use eframe::egui;
 
 use crate::{Simulator, Debugger};
+use crate::breakpoint::SavedBreakpoint;
+use crate::simulator::RunOutcome;
+use crate::instruction::{Core, Instruction, InstructionDecoder};
 use crate::cpu::registers;
+use crate::callstack::FrameKind;
+use crate::interrupt::InterruptSource;
+use crate::logic::MarkerKind;
+use crate::watchpoint::{self, Watchpoint, WatchKind};
+use crate::events::SimEvent;
+use crate::expr::Expr;
+use crate::stimulus::StimulusRule;
+use crate::error::SimError;
+use crate::hexloader::LoadWarning;
+use super::console::{Console, LogLevel};
+use super::editor::{Editor, EditorAction};
+use super::session::Session;
 
 /// GUI simulator state
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -11,6 +30,207 @@ pub enum GuiSimulatorState {
     Paused,    // Execution paused
 }
 
+/// Keyboard shortcuts for execution control, shown in the menus
+const RUN_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F5);
+const STEP_OVER_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F10);
+const STEP_INTO_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F11);
+const RESET_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::R);
+const OPEN_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O);
+
+/// Cycles a step over a CALL may run before giving up
+const STEP_OVER_MAX_CYCLES: u64 = 10_000_000;
+
+/// Firmware file extensions the loader recognizes
+const FIRMWARE_EXTENSIONS: [&str; 8] = ["hex", "s19", "s28", "s37", "srec", "mot", "elf", "cof"];
+
+/// How long a toast stays on screen
+const TOAST_DURATION: std::time::Duration = std::time::Duration::from_secs(4);
+
+/// Most recent trace entries shown in the trace panel
+const TRACE_PANEL_ENTRIES: usize = 100;
+
+/// WDT resets listed in the WDT panel
+const MAX_WDT_RESETS: usize = 16;
+
+/// Periods the frequency counter averages over
+const MEASURE_PERIODS: usize = 8;
+
+/// Longest wall time, in seconds, one frame's execution budget covers
+const MAX_FRAME_TIME: f64 = 0.1;
+
+/// Seconds over which the actual execution frequency is averaged
+const RATE_WINDOW: f64 = 0.5;
+
+/// Format a frequency with the largest unit that keeps it above 1
+fn format_hz(hz: f64) -> String {
+    if hz >= 1_000_000.0 {
+        format!("{} MHz", trim_number(hz / 1_000_000.0))
+    } else if hz >= 1_000.0 {
+        format!("{} kHz", trim_number(hz / 1_000.0))
+    } else {
+        format!("{:.0} Hz", hz)
+    }
+}
+
+/// One decimal place, dropped when it is zero
+fn trim_number(value: f64) -> String {
+    let text = format!("{:.1}", value);
+    text.strip_suffix(".0").map_or(text.clone(), str::to_string)
+}
+
+/// Format a speed ratio with about three significant digits
+fn format_ratio(ratio: f64) -> String {
+    if ratio >= 10.0 {
+        format!("{:.0}", ratio)
+    } else if ratio >= 0.01 {
+        format!("{:.2}", ratio)
+    } else {
+        format!("{:.1e}", ratio)
+    }
+}
+
+/// Target of a CALL or GOTO word of `core`
+fn branch_target(core: Core, word: u16) -> Option<u16> {
+    match InstructionDecoder::decode_for(core, word) {
+        Ok(Instruction::CALL { k } | Instruction::GOTO { k }) => Some(k),
+        _ => None,
+    }
+}
+
+/// Name of the SFR at a bank-resolved address, including the core
+/// registers mirrored in bank 1
+fn sfr_name(address: u8) -> Option<&'static str> {
+    registers::name_of(address).or_else(|| match address {
+        0x80 | 0x82 | 0x83 | 0x84 | 0x8A | 0x8B => registers::name_of(address & 0x7F),
+        _ => None,
+    })
+}
+
+/// Draw a small waveform of what `rule` drives around its activity, with
+/// a line at the current cycle when it falls inside
+fn draw_stimulus_preview(ui: &mut egui::Ui, rule: &StimulusRule, now: u64) {
+    let (start, span) = match *rule {
+        StimulusRule::Toggle { period, .. } => (now.saturating_sub(4 * period), 8 * period),
+        StimulusRule::Set { at, duration, .. } => {
+            let width = duration.unwrap_or(100).max(1);
+            (at.saturating_sub(width), 3 * width)
+        }
+    };
+    let initial = match *rule {
+        StimulusRule::Set { level, .. } => !level,
+        StimulusRule::Toggle { .. } => true,
+    };
+    
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(120.0, 16.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(30));
+    let steps = rect.width() as u64;
+    let points: Vec<egui::Pos2> = (0..=steps)
+        .map(|step| {
+            let cycle = start + span * step / steps;
+            let y = if rule.level_at(cycle, initial) { rect.top() + 3.0 } else { rect.bottom() - 3.0 };
+            egui::pos2(rect.left() + step as f32, y)
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, egui::Color32::LIGHT_GREEN)));
+    if (start..start + span).contains(&now) {
+        let x = rect.left() + ((now - start) as f32 / span as f32) * rect.width();
+        painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], egui::Stroke::new(1.0, egui::Color32::YELLOW));
+    }
+}
+
+/// Draw an on/off indicator dot with an optional label
+fn indicator(ui: &mut egui::Ui, label: &str, on: bool, color: egui::Color32) {
+    let color = if on { color } else { egui::Color32::DARK_GRAY };
+    ui.label(egui::RichText::new(format!("⏺{}", label)).color(color));
+}
+
+/// Kind of stimulus the stimulus panel adds
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StimulusKind {
+    Constant,
+    SquareWave,
+    Pulse,
+}
+
+/// Stimulus panel entry; times are in cycles or microseconds
+struct StimulusForm {
+    pin: u8,
+    kind: StimulusKind,
+    high: bool,
+    period: f64,
+    at: f64,
+    width: f64,
+    micros: bool,
+}
+
+impl Default for StimulusForm {
+    fn default() -> Self {
+        Self { pin: 0, kind: StimulusKind::SquareWave, high: true, period: 1000.0, at: 0.0, width: 100.0, micros: false }
+    }
+}
+
+/// A short status message shown in the corner of the window
+struct Toast {
+    text: String,
+    error: bool,
+    shown: std::time::Instant,
+}
+
+/// A failure shown in a modal dialog until dismissed
+struct ErrorDialog {
+    title: String,
+    message: String,
+    /// Program counter the failure happened at
+    pc: u16,
+    cycle: u64,
+}
+
+/// A value that can be edited in place by double-clicking it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EditTarget {
+    W,
+    Pc,
+    /// Register or RAM byte at a bank-resolved address
+    Register(u8),
+    /// TMR1H:TMR1L as one 16-bit value
+    Tmr1,
+    /// EEPROM byte
+    Eeprom(u8),
+}
+
+/// How a watch expression's value is shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchFormat {
+    Hex,
+    Decimal,
+    Binary,
+}
+
+impl WatchFormat {
+    fn format(self, value: i64) -> String {
+        match self {
+            WatchFormat::Hex => format!("0x{:02X}", value),
+            WatchFormat::Decimal => value.to_string(),
+            WatchFormat::Binary => format!("0b{:08b}", value),
+        }
+    }
+}
+
+/// An expression in the watch panel
+struct WatchEntry {
+    expr: Expr,
+    format: WatchFormat,
+}
+
+/// Column the profiler table is sorted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProfileSortColumn {
+    Address,
+    Hits,
+    Cycles,
+}
+
 /// Main GUI application structure
 pub struct SimulatorApp {
     // Core simulator instance
@@ -25,60 +245,296 @@ pub struct SimulatorApp {
     // Disassembly cache: (address, instruction_word, assembly_string)
     disassembly_cache: Vec<(u16, u16, String)>,
     
+    // Labels for symbols and branch targets shown in the disassembly
+    code_labels: BTreeMap<u16, String>,
+    
     // Performance tracking
     last_update_time: std::time::Instant,
-    actual_frequency: f32,  // Actual execution frequency
+    // Cycles owed to (positive) or borrowed from (negative) the next frame
+    cycle_budget: f64,
+    // Cycles executed since the actual frequency was last measured
+    rate_window_start: std::time::Instant,
+    rate_window_cycles: u64,
+    actual_frequency: f32,  // Actual execution frequency in cycles per second
     
     // UI panel visibility
     show_memory_viewer: bool,
     memory_view_address: u8,
+    // Memory viewer bytes as of the last frame, to highlight changes
+    memory_previous: Vec<u8>,
     show_timer_panel: bool,
     show_interrupt_panel: bool,
+    show_wdt_panel: bool,
+    show_call_stack_panel: bool,
+    show_profiler_panel: bool,
+    show_source_view: bool,
+    show_breakpoint_panel: bool,
+    show_sfr_names: bool,
+    show_stopwatch_panel: bool,
+    show_stimulus_panel: bool,
+    show_freq_panel: bool,
+    show_trace_panel: bool,
+    show_eeprom_panel: bool,
+    show_watch_panel: bool,
+    show_wave_panel: bool,
+    show_console: bool,
+    
+    // Log messages shown in the console panel
+    console: Console,
+    
+    // Assembly editor window
+    show_editor: bool,
+    editor: Editor,
+    
+    // Waveform view span, marker display and cursor cycles
+    wave_window_ms: f64,
+    wave_show_markers: bool,
+    wave_cursors: [Option<u64>; 2],
+    
+    // Watch expressions and the one being typed
+    watches: Vec<WatchEntry>,
+    watch_input: String,
+    
+    // Pin stimulus entry
+    stimulus_form: StimulusForm,
+    
+    // Pin measured by the frequency counter
+    freq_pin: u8,
     
-    // Statistics
-    instructions_this_second: u64,
+    // Recent WDT resets as (cycle, PC), oldest first
+    wdt_resets: VecDeque<(u64, u16)>,
+    
+    // Stopwatch entry
+    stopwatch_start_input: String,
+    stopwatch_stop_input: String,
+    
+    // Breakpoint and register breakpoint entry
+    breakpoint_address_input: String,
+    breakpoint_condition_input: String,
+    watch_register_input: String,
+    watch_kind: WatchKind,
+    
+    // Profiler table sorting
+    profile_sort: ProfileSortColumn,
+    profile_sort_descending: bool,
+    
+    // Scroll the code panel to the PC on the next frame
+    scroll_to_pc: bool,
+    
+    // Code panel navigation: whether the listing follows the PC (or shows
+    // the whole program for free scrolling), go-to and search entry, the
+    // selected line and whether to scroll to it on the next frame
+    follow_pc: bool,
+    last_code_pc: Option<u16>,
+    code_goto_input: String,
+    code_search_input: String,
+    code_selected: Option<u16>,
+    scroll_to_selection: bool,
+    
+    // Value being edited in place and its hex text
+    editing: Option<(EditTarget, String)>,
+    
+    // Warnings from the last HEX load
+    load_warnings: Vec<LoadWarning>,
+    
+    // Firmware files loaded, most recent first
+    recent_files: Vec<PathBuf>,
+    
+    // Window size, saved with the session
+    window_size: Option<[f32; 2]>,
+    
+    // Status messages, oldest first
+    toasts: Vec<Toast>,
+    error_dialog: Option<ErrorDialog>,
 
-    // Debug tracking
-    last_gpio: u8, 
+    // Simulator events, drained every frame
+    events: Receiver<SimEvent>,
 }
 
 impl SimulatorApp {
-    /// Create a new simulator app
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+    /// Create a new simulator app, restoring a saved session
+    pub fn new(_cc: &eframe::CreationContext<'_>, session: Session) -> Self {
         let mut sim = Simulator::new();
         sim.reset();
         
+        // Forward only the events reported in the log; every instruction
+        // and memory write would otherwise queue up between frames
+        let (sender, events) = mpsc::channel();
+        sim.subscribe(move |event| {
+            if matches!(event, SimEvent::GpioChanged { .. } | SimEvent::WdtReset { .. }
+                | SimEvent::SleepEntered { .. } | SimEvent::Woke { .. })
+            {
+                let _ = sender.send(*event);
+            }
+        });
+        
         // Initialize disassembly cache to prevent index out of bounds
         let mut cache = Vec::new();
-        for addr in 0..1024u16 {
+        for addr in 0..sim.cpu().memory().program_size() as u16 {
             let word = sim.cpu().memory().read_program(addr);
-            let asm = Debugger::disassemble(word);
+            let asm = Debugger::disassemble_with_options(sim.device().core(), word, sim.symbols(), true);
             cache.push((addr, word, asm));
         }
         
-        Self {
+        let mut app = Self {
             simulator: sim,
             gui_state: GuiSimulatorState::Idle,
             target_frequency: 10,  // 1kHz - easier to observe LED blinking
             disassembly_cache: cache,
+            code_labels: BTreeMap::new(),
             last_update_time: std::time::Instant::now(),
+            cycle_budget: 0.0,
+            rate_window_start: std::time::Instant::now(),
+            rate_window_cycles: 0,
             actual_frequency: 0.0,
             show_memory_viewer: true,
             memory_view_address: 0x20,
+            memory_previous: Vec::new(),
             show_timer_panel: true,
             show_interrupt_panel: true,
-            instructions_this_second: 0,
-            last_gpio: 0,
+            show_wdt_panel: false,
+            show_call_stack_panel: true,
+            show_profiler_panel: false,
+            show_source_view: false,
+            show_breakpoint_panel: false,
+            show_sfr_names: true,
+            show_stopwatch_panel: false,
+            show_stimulus_panel: false,
+            show_freq_panel: false,
+            show_trace_panel: false,
+            show_eeprom_panel: false,
+            show_watch_panel: false,
+            show_wave_panel: false,
+            show_console: true,
+            console: Console::new(),
+            show_editor: false,
+            editor: Editor::new(),
+            wave_window_ms: 10.0,
+            wave_show_markers: true,
+            wave_cursors: [None; 2],
+            watches: Vec::new(),
+            watch_input: String::new(),
+            stimulus_form: StimulusForm::default(),
+            freq_pin: 2,
+            wdt_resets: VecDeque::new(),
+            stopwatch_start_input: String::new(),
+            stopwatch_stop_input: String::new(),
+            breakpoint_address_input: String::new(),
+            breakpoint_condition_input: String::new(),
+            watch_register_input: String::new(),
+            watch_kind: WatchKind::Write,
+            profile_sort: ProfileSortColumn::Cycles,
+            profile_sort_descending: true,
+            scroll_to_pc: false,
+            follow_pc: true,
+            last_code_pc: None,
+            code_goto_input: String::new(),
+            code_search_input: String::new(),
+            code_selected: None,
+            scroll_to_selection: false,
+            editing: None,
+            load_warnings: Vec::new(),
+            recent_files: Vec::new(),
+            window_size: None,
+            toasts: Vec::new(),
+            error_dialog: None,
+            events,
+        };
+        app.apply_session(session);
+        app
+    }
+    
+    /// Panel visibility flags by the names used in the session file
+    fn panel_flags(&mut self) -> [(&'static str, &mut bool); 20] {
+        [
+            ("memory", &mut self.show_memory_viewer),
+            ("timer", &mut self.show_timer_panel),
+            ("interrupt", &mut self.show_interrupt_panel),
+            ("wdt", &mut self.show_wdt_panel),
+            ("call_stack", &mut self.show_call_stack_panel),
+            ("profiler", &mut self.show_profiler_panel),
+            ("source", &mut self.show_source_view),
+            ("breakpoints", &mut self.show_breakpoint_panel),
+            ("stopwatch", &mut self.show_stopwatch_panel),
+            ("stimulus", &mut self.show_stimulus_panel),
+            ("frequency", &mut self.show_freq_panel),
+            ("trace", &mut self.show_trace_panel),
+            ("eeprom", &mut self.show_eeprom_panel),
+            ("watch", &mut self.show_watch_panel),
+            ("waveforms", &mut self.show_wave_panel),
+            ("console", &mut self.show_console),
+            ("editor", &mut self.show_editor),
+            ("sfr_names", &mut self.show_sfr_names),
+            ("wave_markers", &mut self.wave_show_markers),
+            ("follow_pc", &mut self.follow_pc),
+        ]
+    }
+    
+    /// Restore panels, settings, recent files and breakpoints
+    fn apply_session(&mut self, session: Session) {
+        for (name, flag) in self.panel_flags() {
+            if let Some(&visible) = session.panels.get(name) {
+                *flag = visible;
+            }
+        }
+        if let Some(frequency) = session.target_frequency {
+            self.target_frequency = frequency;
+        }
+        if let Some(address) = session.memory_view_address {
+            self.memory_view_address = address;
+        }
+        self.window_size = session.window_size;
+        self.recent_files = session.recent_files;
+        
+        // Conditions are parsed again, so ones using labels of a program
+        // that is not loaded yet are dropped
+        for bp in session.breakpoints {
+            let Some(address) = bp.address() else {
+                continue;
+            };
+            self.simulator.add_breakpoint(address);
+            self.simulator.set_breakpoint_enabled(address, bp.enabled);
+            let condition = match bp.condition.map(|text| (Expr::parse(&text, self.simulator.symbols()), text)) {
+                Some((Ok(expr), _)) => Some(expr),
+                Some((Err(e), text)) => {
+                    self.log(LogLevel::Warn, format!("Dropped breakpoint condition '{}': {}", text, e));
+                    None
+                }
+                None => None,
+            };
+            self.simulator.set_breakpoint_condition(address, condition);
+        }
+        self.update_disassembly_cache();
+    }
+    
+    /// Current panels, settings, recent files and breakpoints
+    fn session(&mut self) -> Session {
+        let panels = self.panel_flags()
+            .into_iter()
+            .map(|(name, flag)| (name.to_string(), *flag))
+            .collect();
+        let breakpoints = self.simulator.breakpoints()
+            .iter()
+            .map(SavedBreakpoint::new)
+            .collect();
+        Session {
+            window_size: self.window_size,
+            target_frequency: Some(self.target_frequency),
+            memory_view_address: Some(self.memory_view_address),
+            panels,
+            recent_files: self.recent_files.clone(),
+            breakpoints,
         }
     }
     
     /// Update disassembly cache after loading a program
     fn update_disassembly_cache(&mut self) {
         self.disassembly_cache.clear();
+        self.code_labels = Debugger::branch_labels(self.simulator.cpu(), self.simulator.symbols());
         
-        for addr in 0..1024u16 {
+        for addr in 0..self.simulator.cpu().memory().program_size() as u16 {
             let word = self.simulator.cpu().memory().read_program(addr);
-            let asm = Debugger::disassemble(word);
+            let asm = Debugger::disassemble_with_labels(self.simulator.device().core(), word, &self.code_labels, self.show_sfr_names);
             self.disassembly_cache.push((addr, word, asm));
         }
     }
@@ -117,28 +573,465 @@ impl SimulatorApp {
         self.gui_state = GuiSimulatorState::Paused;
     }
     
-    /// Load a HEX file using file dialog
-    fn load_hex_file(&mut self) {
+    /// Load a HEX or S-record file using file dialog
+    fn load_firmware_file(&mut self) {
         // Open file dialog
         if let Some(path) = rfd::FileDialog::new()
-            .add_filter("Intel HEX", &["hex"])
+            .add_filter("Firmware", &FIRMWARE_EXTENSIONS)
             .pick_file()
         {
-            match self.simulator.load_hex_file(&path) {
-                Ok(_) => {
+            self.load_firmware_path(path);
+        }
+    }
+    
+    /// Load a firmware file and add it to the recent files
+    pub fn load_firmware_path(&mut self, path: PathBuf) {
+        match self.simulator.load_firmware_file(&path) {
+            Ok(report) => {
+                self.update_disassembly_cache();
+                self.gui_state = GuiSimulatorState::Paused;
+                self.log(LogLevel::Info, format!("Loaded firmware: {:?} ({} words)", path, report.words_loaded));
+                for warning in &report.warnings {
+                    self.log(LogLevel::Warn, warning.to_string());
+                }
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let mut text = format!("Loaded {} ({} words)", name, report.words_loaded);
+                if !report.warnings.is_empty() {
+                    text.push_str(&format!(", {} warnings", report.warnings.len()));
+                }
+                self.notify(text, false);
+                self.load_warnings = report.warnings;
+                Session::remember_file(&mut self.recent_files, path);
+            }
+            Err(e) => {
+                let pc = self.simulator.cpu().get_pc();
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                self.show_error_dialog(&format!("Failed to load {}", name), e.to_string(), pc);
+            }
+        }
+    }
+    
+    /// Load files dropped onto the window
+    fn load_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        for path in dropped.into_iter().filter_map(|file| file.path) {
+            let extension = path.extension()
+                .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+                .unwrap_or_default();
+            if FIRMWARE_EXTENSIONS.contains(&extension.as_str()) {
+                self.load_firmware_path(path);
+            } else if extension == "asm" {
+                self.open_editor_file(&path);
+            } else {
+                self.notify(format!("Unsupported file type: {}", path.display()), true);
+            }
+        }
+    }
+    
+    /// Assemble the editor text and load it, showing errors in the editor
+    fn assemble_editor(&mut self) {
+        let file = self.editor.file_name();
+        match self.simulator.load_asm_string(&self.editor.source, &file) {
+            Ok(report) => {
+                self.editor.set_errors(Vec::new());
+                self.update_disassembly_cache();
+                self.gui_state = GuiSimulatorState::Paused;
+                self.show_source_view = true;
+                self.scroll_to_pc = true;
+                self.load_warnings = report.warnings;
+                self.log(LogLevel::Info, format!("Assembled {} ({} words)", file, report.words_loaded));
+                self.notify(format!("Assembled {} ({} words)", file, report.words_loaded), false);
+            }
+            Err(SimError::Assemble(errors)) => {
+                let count = errors.len();
+                for error in &errors {
+                    self.log(LogLevel::Error, format!("{}: {}", file, error));
+                }
+                self.editor.set_errors(errors);
+                self.notify(format!("{} assembler errors in {}", count, file), true);
+            }
+            Err(e) => self.report_error(e.to_string()),
+        }
+    }
+    
+    /// Open an assembly file in the editor and assemble it
+    fn open_editor_file(&mut self, path: &std::path::Path) {
+        match self.editor.open(path) {
+            Ok(()) => {
+                self.show_editor = true;
+                self.assemble_editor();
+            }
+            Err(e) => self.report_error(e),
+        }
+    }
+    
+    /// Draw the assembly editor window and carry out its toolbar actions
+    fn draw_editor(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_editor;
+        let mut action = None;
+        egui::Window::new("Assembly Editor")
+            .open(&mut open)
+            .default_size([520.0, 560.0])
+            .show(ctx, |ui| action = self.editor.ui(ui));
+        self.show_editor = open;
+        
+        match action {
+            Some(EditorAction::Assemble) => self.assemble_editor(),
+            Some(EditorAction::Open) => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Assembly", &["asm", "inc", "s"])
+                    .pick_file()
+                {
+                    self.open_editor_file(&path);
+                }
+            }
+            Some(EditorAction::Save) => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Assembly", &["asm"])
+                    .set_file_name(self.editor.file_name())
+                    .save_file()
+                {
+                    match self.editor.save(&path) {
+                        Ok(()) => self.log(LogLevel::Info, format!("Saved {:?}", path)),
+                        Err(e) => self.report_error(e),
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+    
+    /// Add a console entry stamped with the current cycle
+    fn log(&mut self, level: LogLevel, message: impl Into<String>) {
+        let cycle = self.simulator.stats().cycles_elapsed;
+        self.console.log(level, cycle, message);
+    }
+    
+    /// Show a status toast
+    fn notify(&mut self, text: String, error: bool) {
+        self.toasts.push(Toast { text, error, shown: std::time::Instant::now() });
+    }
+    
+    /// Log an error and show it as a toast
+    fn report_error(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        self.log(LogLevel::Error, message.clone());
+        self.notify(message, true);
+    }
+    
+    /// Log an error and show it in a modal dialog
+    fn show_error_dialog(&mut self, title: &str, message: String, pc: u16) {
+        self.log(LogLevel::Error, format!("{}: {}", title, message));
+        let cycle = self.simulator.stats().cycles_elapsed;
+        self.error_dialog = Some(ErrorDialog { title: title.to_string(), message, pc, cycle });
+    }
+    
+    /// Stop running after an execution error and show where it happened
+    fn execution_failed(&mut self, error: SimError) {
+        let pc = match error {
+            SimError::InvalidOpcode { pc, .. } | SimError::HaltCondition { pc, .. } => pc,
+            _ => self.simulator.cpu().get_pc(),
+        };
+        self.gui_state = GuiSimulatorState::Paused;
+        self.scroll_to_pc = true;
+        self.show_error_dialog("Execution stopped", error.to_string(), pc);
+    }
+    
+    /// Draw the error dialog over a backdrop that blocks the rest of the window
+    fn draw_error_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &self.error_dialog else {
+            return;
+        };
+        
+        let screen = ctx.screen_rect();
+        egui::Area::new(egui::Id::new("error_backdrop"))
+            .fixed_pos(screen.min)
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.allocate_response(screen.size(), egui::Sense::click_and_drag());
+                ui.painter().rect_filled(screen, 0.0, egui::Color32::from_black_alpha(120));
+            });
+        
+        let word = self.simulator.cpu().memory().read_program(dialog.pc);
+        let label = self.simulator.symbols().label_at(dialog.pc);
+        let mut close = ctx.input(|i| i.key_pressed(egui::Key::Escape) || i.key_pressed(egui::Key::Enter));
+        let mut show_code = false;
+        egui::Window::new(egui::RichText::new(format!("⚠ {}", dialog.title)).color(egui::Color32::from_rgb(255, 100, 100)))
+            .id(egui::Id::new("error_dialog"))
+            .order(egui::Order::Tooltip)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.set_max_width(420.0);
+                ui.label(egui::RichText::new(&dialog.message).monospace());
+                ui.add_space(5.0);
+                egui::Grid::new("error_dialog_grid").show(ui, |ui| {
+                    ui.label("PC");
+                    match label {
+                        Some(label) => ui.label(format!("0x{:04X} ({})", dialog.pc, label)),
+                        None => ui.label(format!("0x{:04X}", dialog.pc)),
+                    };
+                    ui.end_row();
+                    ui.label("Word");
+                    ui.label(format!("0x{:04X}  {}", word, Debugger::disassemble(self.simulator.device().core(), word)));
+                    ui.end_row();
+                    ui.label("Cycle");
+                    ui.label(dialog.cycle.to_string());
+                    ui.end_row();
+                });
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    if ui.button("OK").clicked() {
+                        close = true;
+                    }
+                    if ui.button("Show in Code").clicked() {
+                        show_code = true;
+                    }
+                });
+            });
+        
+        if show_code {
+            let pc = dialog.pc;
+            self.select_code_line(pc);
+            close = true;
+        }
+        if close {
+            self.error_dialog = None;
+        }
+    }
+    
+    /// Draw status toasts in the bottom-right corner and the drop
+    /// target hint while files are dragged over the window
+    fn draw_overlays(&mut self, ctx: &egui::Context) {
+        self.toasts.retain(|toast| toast.shown.elapsed() < TOAST_DURATION);
+        if !self.toasts.is_empty() {
+            egui::Area::new(egui::Id::new("toasts"))
+                .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+                .order(egui::Order::Foreground)
+                .show(ctx, |ui| {
+                    for toast in &self.toasts {
+                        let color = if toast.error { egui::Color32::from_rgb(255, 100, 100) } else { egui::Color32::LIGHT_GREEN };
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.colored_label(color, &toast.text);
+                        });
+                    }
+                });
+            ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        }
+        
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            let screen = ctx.screen_rect();
+            let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_target")));
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop firmware to load",
+                egui::FontId::proportional(24.0),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+    
+    /// Load a symbol file using file dialog
+    fn load_symbol_file(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Listing / Map / COD", &["lst", "map", "cod"])
+            .pick_file()
+        {
+            match self.simulator.load_symbol_file(&path) {
+                Ok(count) => {
                     self.update_disassembly_cache();
-                    self.gui_state = GuiSimulatorState::Paused;
-                    println!("✅ Loaded HEX file: {:?}", path);
+                    self.log(LogLevel::Info, format!("Loaded {} symbols: {:?}", count, path));
                 }
                 Err(e) => {
-                    eprintln!("❌ Failed to load HEX file: {}", e);
+                    self.report_error(format!("Failed to load symbols: {}", e));
+                }
+            }
+        }
+    }
+    
+    /// Load a pin stimulus script using file dialog
+    fn load_stimulus_file(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Stimulus", &["stim", "txt"])
+            .pick_file()
+        {
+            match self.simulator.load_stimulus_file(&path) {
+                Ok(count) => self.log(LogLevel::Info, format!("Loaded {} stimulus rules: {:?}", count, path)),
+                Err(e) => self.report_error(format!("Failed to load stimulus: {}", e)),
+            }
+        }
+    }
+    
+    /// Load a circuit description using file dialog
+    fn load_circuit_file(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Circuit", &["toml", "circuit"])
+            .pick_file()
+        {
+            match self.simulator.load_circuit_file(&path) {
+                Ok(count) => self.log(LogLevel::Info, format!("Attached {} components: {:?}", count, path)),
+                Err(e) => self.report_error(format!("Failed to load circuit: {}", e)),
+            }
+        }
+    }
+    
+    /// Start recording inputs, or stop and save them using file dialog
+    fn toggle_input_recording(&mut self) {
+        if self.simulator.input_recording().is_none() {
+            self.simulator.start_input_recording();
+            self.log(LogLevel::Info, "Recording inputs");
+        } else if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Input Replay", &["replay", "txt"])
+            .save_file()
+            && let Some(log) = self.simulator.stop_input_recording()
+        {
+            match log.save_file(&path) {
+                Ok(_) => self.log(LogLevel::Info, format!("Saved {} input events: {:?}", log.len(), path)),
+                Err(e) => self.report_error(e.to_string()),
+            }
+        }
+    }
+    
+    /// Replay recorded inputs using file dialog
+    fn load_replay_file(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Input Replay", &["replay", "txt"])
+            .pick_file()
+        {
+            match self.simulator.load_replay_file(&path) {
+                Ok(count) => {
+                    self.log(LogLevel::Info, format!("Replaying {} input events: {:?}", count, path));
+                    self.gui_state = GuiSimulatorState::Paused;
+                }
+                Err(e) => self.report_error(format!("Failed to load replay: {}", e)),
+            }
+        }
+    }
+    
+    /// Start or stop VCD waveform capture
+    fn toggle_vcd_capture(&mut self) {
+        if self.simulator.is_recording_vcd() {
+            match self.simulator.stop_vcd() {
+                Ok(_) => self.log(LogLevel::Info, "Waveform capture stopped"),
+                Err(e) => self.report_error(e.to_string()),
+            }
+        } else if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Value Change Dump", &["vcd"])
+            .save_file()
+        {
+            match self.simulator.start_vcd(&path) {
+                Ok(_) => self.log(LogLevel::Info, format!("Recording waveform: {:?}", path)),
+                Err(e) => self.report_error(e.to_string()),
+            }
+        }
+    }
+    
+    /// Export the coverage report using a file dialog
+    /// Files ending in `.json` get the JSON report, anything else the text report
+    fn export_coverage(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .add_filter("Text", &["txt"])
+            .save_file()
+        {
+            let coverage = self.simulator.coverage();
+            let report = if path.extension().is_some_and(|ext| ext == "json") {
+                coverage.to_json()
+            } else {
+                coverage.to_text()
+            };
+            
+            match std::fs::write(&path, report) {
+                Ok(_) => self.log(LogLevel::Info, format!("Saved coverage report: {:?}", path)),
+                Err(e) => self.report_error(format!("Failed to save coverage report: {}", e)),
+            }
+        }
+    }
+    
+    /// Load raw EEPROM contents from a binary file
+    fn load_eeprom_file(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("EEPROM Image", &["bin", "eep"])
+            .pick_file()
+        {
+            match std::fs::read(&path) {
+                Ok(bytes) => {
+                    let memory = self.simulator.cpu_mut().memory_mut();
+                    let count = bytes.len().min(memory.eeprom_size());
+                    for (address, &byte) in bytes[..count].iter().enumerate() {
+                        memory.write_eeprom(address as u8, byte);
+                    }
+                    self.log(LogLevel::Info, format!("Loaded {} EEPROM bytes: {:?}", count, path));
+                }
+                Err(e) => self.report_error(format!("Failed to load EEPROM: {}", e)),
+            }
+        }
+    }
+    
+    /// Save the EEPROM contents to a binary file
+    fn save_eeprom_file(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("EEPROM Image", &["bin", "eep"])
+            .save_file()
+        {
+            let memory = self.simulator.cpu().memory();
+            let bytes: Vec<u8> = (0..memory.eeprom_size())
+                .map(|address| memory.read_eeprom(address as u8))
+                .collect();
+            match std::fs::write(&path, bytes) {
+                Ok(_) => self.log(LogLevel::Info, format!("Saved EEPROM: {:?}", path)),
+                Err(e) => self.report_error(format!("Failed to save EEPROM: {}", e)),
+            }
+        }
+    }
+    
+    /// Save the machine state, with the loaded program and its symbols, to a file
+    fn save_state_file(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Machine State", &["state", "txt"])
+            .set_file_name("snapshot.state")
+            .save_file()
+        {
+            match self.simulator.save_state_file(&path) {
+                Ok(_) => self.log(LogLevel::Info, format!("Saved machine state: {:?}", path)),
+                Err(e) => self.report_error(format!("Failed to save machine state: {}", e)),
+            }
+        }
+    }
+    
+    /// Restore the machine state, with its clock and configuration, from a file
+    fn restore_state_file(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Machine State", &["state", "txt"])
+            .pick_file()
+        {
+            match self.simulator.restore_state_file(&path) {
+                Ok(_) => {
+                    let cycles = self.simulator.stats().cycles_elapsed;
+                    let fosc = format_hz(self.simulator.fosc() as f64);
+                    self.log(LogLevel::Info, format!("Restored machine state at cycle {} @ {}: {:?}", cycles, fosc, path));
+                    self.update_disassembly_cache();
+                    self.code_selected = None;
+                    self.scroll_to_pc = true;
+                    self.gui_state = GuiSimulatorState::Paused;
                 }
+                Err(e) => self.report_error(format!("Failed to restore machine state: {}", e)),
             }
         }
     }
     
-    /// Draw the code panel (disassembly view)
-    fn draw_code_panel(&self, ui: &mut egui::Ui, current_pc: u16) {
+    /// Draw the code panel (source view when enabled and available, otherwise disassembly)
+    /// 
+    /// Clicking a disassembly line toggles a breakpoint on it.
+    fn draw_code_panel(&mut self, ui: &mut egui::Ui, current_pc: u16) {
+        if self.show_source_view && self.simulator.symbols().location_of(current_pc).is_some() {
+            self.draw_source_view(ui, current_pc);
+            return;
+        }
+        
         ui.heading("Disassembly");
         ui.add_space(5.0);
         
@@ -148,9 +1041,29 @@ impl SimulatorApp {
             return;
         }
         
-        // Show only non-zero instructions or PC-nearby code
-        let start = current_pc.saturating_sub(10);
-        let end = (current_pc + 30).min(self.disassembly_cache.len() as u16);
+        self.draw_code_navigation(ui, current_pc);
+        ui.add_space(5.0);
+        
+        // While following, keep the PC line in view as it moves
+        let pc_moved = self.last_code_pc.replace(current_pc) != Some(current_pc);
+        let scroll_to_pc = std::mem::take(&mut self.scroll_to_pc) || (self.follow_pc && pc_moved);
+        let scroll_to_selection = std::mem::take(&mut self.scroll_to_selection);
+        
+        // Branch target of the selected line, and the lines branching to it
+        let selected_word = self.code_selected
+            .and_then(|addr| self.disassembly_cache.get(addr as usize))
+            .map(|&(_, word, _)| word);
+        let core = self.simulator.device().core();
+        let selected_target = selected_word.and_then(|word| branch_target(core, word));
+        
+        // Show only non-zero instructions, near the PC while following it
+        let (start, end) = if self.follow_pc {
+            (current_pc.saturating_sub(10), (current_pc + 30).min(self.disassembly_cache.len() as u16))
+        } else {
+            (0, self.disassembly_cache.len() as u16)
+        };
+        let mut toggled = None;
+        let mut selected = None;
         
         egui::ScrollArea::vertical()
             .max_height(f32::INFINITY)
@@ -170,25 +1083,193 @@ impl SimulatorApp {
                         continue;
                     }
                     
+                    if let Some(label) = self.code_labels.get(&addr) {
+                        ui.label(egui::RichText::new(format!("{}:", label)).monospace().strong());
+                    }
+                    
                     let is_current = addr == current_pc;
+                    let is_selected = self.code_selected == Some(addr);
                     let text = format!("0x{:04X}: {:04X}  {}", addr, word, asm);
                     
-                    if is_current {
-                        ui.colored_label(egui::Color32::RED, format!("▶ {}", text));
-                    } else {
-                        ui.label(text);
-                    }
+                    let response = ui.horizontal(|ui| {
+                        // Breakpoint marker column
+                        let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                        // Disabled breakpoints are drawn hollow
+                        match self.simulator.breakpoints().iter().find(|bp| bp.address == addr) {
+                            Some(bp) if bp.enabled => {
+                                ui.painter().circle_filled(rect.center(), 5.0, egui::Color32::RED);
+                            }
+                            Some(_) => {
+                                ui.painter().circle_stroke(rect.center(), 4.5, egui::Stroke::new(1.0, egui::Color32::RED));
+                            }
+                            None => {}
+                        }
+                        
+                        let mut line = if is_current {
+                            egui::RichText::new(format!("▶ {}", text)).color(egui::Color32::RED)
+                        } else if selected_target == Some(addr) {
+                            egui::RichText::new(text).color(egui::Color32::LIGHT_BLUE)
+                        } else if self.code_selected.is_some() && branch_target(core, *word) == self.code_selected {
+                            egui::RichText::new(text).color(egui::Color32::from_rgb(255, 165, 0))
+                        } else {
+                            egui::RichText::new(text)
+                        };
+                        if is_selected {
+                            line = line.background_color(egui::Color32::from_gray(60));
+                        }
+                        ui.add(egui::Label::new(line).sense(egui::Sense::click()))
+                    }).inner.on_hover_text("Click to toggle breakpoint, right-click to select");
+                    
+                    if response.clicked() {
+                        toggled = Some(addr);
+                    }
+                    if response.secondary_clicked() {
+                        selected = Some(addr);
+                    }
+                    if (is_current && scroll_to_pc) || (is_selected && scroll_to_selection) {
+                        response.scroll_to_me(Some(egui::Align::Center));
+                    }
+                }
+            });
+        
+        if let Some(addr) = toggled {
+            self.simulator.toggle_breakpoint(addr);
+        }
+        if selected.is_some() {
+            self.code_selected = selected;
+        }
+    }
+    
+    /// Draw the go-to-address and search fields of the code panel
+    fn draw_code_navigation(&mut self, ui: &mut egui::Ui, current_pc: u16) {
+        ui.horizontal(|ui| {
+            ui.label("Go to:");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.code_goto_input)
+                .hint_text("0x010 or label")
+                .desired_width(90.0));
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                self.goto_code_input();
+            }
+            
+            ui.label("Find:");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.code_search_input)
+                .hint_text("mnemonic or operand")
+                .desired_width(120.0));
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Next").clicked() || entered {
+                self.find_next_code(current_pc);
+                if entered {
+                    response.request_focus();
+                }
+            }
+            
+            ui.checkbox(&mut self.follow_pc, "Follow PC")
+                .on_hover_text("Off: list the whole program and scroll freely");
+            if ui.button("⌖ PC").on_hover_text("Scroll to the current PC").clicked() {
+                self.scroll_to_pc = true;
+            }
+        });
+        
+        // Describe the selection and its branch highlighting
+        if let Some(addr) = self.code_selected {
+            let core = self.simulator.device().core();
+            let word = self.disassembly_cache.get(addr as usize).map_or(0, |&(_, word, _)| word);
+            let references = self.disassembly_cache.iter()
+                .filter(|&&(_, word, _)| branch_target(core, word) == Some(addr))
+                .count();
+            ui.horizontal(|ui| {
+                ui.label(format!("Selected 0x{:04X}", addr));
+                if let Some(target) = branch_target(core, word) {
+                    ui.colored_label(egui::Color32::LIGHT_BLUE, format!("target 0x{:04X}", target));
+                }
+                ui.colored_label(egui::Color32::from_rgb(255, 165, 0), format!("{} references", references));
+                if ui.small_button("✖").clicked() {
+                    self.code_selected = None;
+                }
+            });
+        }
+    }
+    
+    /// Select a code line and scroll the code panel to it, which stops
+    /// following the PC
+    fn select_code_line(&mut self, addr: u16) {
+        self.code_selected = Some(addr);
+        self.follow_pc = false;
+        self.scroll_to_selection = true;
+    }
+    
+    /// Jump to the address or label typed into the go-to field
+    fn goto_code_input(&mut self) {
+        match self.parse_code_address(&self.code_goto_input) {
+            Some(addr) if (addr as usize) < self.disassembly_cache.len() => self.select_code_line(addr),
+            _ => self.notify(format!("Unknown address: {}", self.code_goto_input.trim()), true),
+        }
+    }
+    
+    /// Select the next line after the selection (or the PC) whose
+    /// disassembly or label contains the search text, wrapping around
+    fn find_next_code(&mut self, current_pc: u16) {
+        let query = self.code_search_input.trim().to_lowercase();
+        if query.is_empty() || self.disassembly_cache.is_empty() {
+            return;
+        }
+        
+        let len = self.disassembly_cache.len();
+        let from = self.code_selected.unwrap_or(current_pc) as usize + 1;
+        let found = (0..len).map(|offset| (from + offset) % len).find(|&index| {
+            let (addr, word, asm) = &self.disassembly_cache[index];
+            // Blank (0x0000) words are hidden in the listing
+            let label = self.code_labels.get(addr).map(|label| label.to_lowercase());
+            *word != 0 && (asm.to_lowercase().contains(&query) || label.is_some_and(|label| label.contains(&query)))
+        });
+        match found {
+            Some(index) => self.select_code_line(index as u16),
+            None => self.notify(format!("No match for '{}'", self.code_search_input.trim()), true),
+        }
+    }
+    
+    /// Draw the source lines around the current PC
+    fn draw_source_view(&self, ui: &mut egui::Ui, current_pc: u16) {
+        let symbols = self.simulator.symbols();
+        let Some(location) = symbols.location_of(current_pc) else {
+            return;
+        };
+        
+        ui.heading(format!("Source — {}", location.file));
+        ui.add_space(5.0);
+        
+        let start = location.line.saturating_sub(10).max(1);
+        let end = location.line + 30;
+        
+        egui::ScrollArea::vertical()
+            .max_height(f32::INFINITY)
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui.set_min_width(400.0);
+                
+                for line in start..end {
+                    let Some(text) = symbols.source_line(&location.file, line) else {
+                        continue;
+                    };
+                    
+                    let row = format!("{:>5}  {}", line, text);
+                    if line == location.line {
+                        ui.colored_label(egui::Color32::RED, egui::RichText::new(format!("▶ {}", row)).monospace());
+                    } else {
+                        ui.label(egui::RichText::new(format!("  {}", row)).monospace());
+                    }
                 }
             });
     }
     
-    /// Draw a single GPIO pin
-    fn draw_gpio_pin(&mut self, ui: &mut egui::Ui, pin: u8, gpio: u8, trisio: u8) {
-        let is_input = (trisio & (1 << pin)) != 0;
-        let is_high = (gpio & (1 << pin)) != 0;
+    /// Draw a single pin of a port
+    fn draw_gpio_pin(&mut self, ui: &mut egui::Ui, pin: u8, name: String, port: u8, tris: u8) {
+        let mask = 1 << (pin % 8);
+        let is_input = (tris & mask) != 0;
+        let is_high = (port & mask) != 0;
         
         ui.vertical(|ui| {
-            ui.label(format!("GP{}", pin));
+            ui.label(name);
             
             // Allocate space for the LED circle
             let (rect, response) = ui.allocate_exact_size(
@@ -199,7 +1280,7 @@ impl SimulatorApp {
             // Input pins can be toggled by clicking
             if is_input && response.clicked() {
                 let current = self.simulator.cpu().gpio().get_external_pin(pin);
-                self.simulator.cpu_mut().gpio_mut().set_external_pin(pin, !current);
+                self.simulator.set_external_pin(pin, !current);
             }
             
             let painter = ui.painter();
@@ -216,239 +1297,1445 @@ impl SimulatorApp {
                     egui::Color32::from_rgba_unmultiplied(30, 60, 100, (255.0 * brightness) as u8)
                 }
             } else {
-                if is_high {
-                    egui::Color32::from_rgba_unmultiplied(50, 255, 50, (255.0 * brightness) as u8)
-                } else {
-                    egui::Color32::from_rgba_unmultiplied(50, 255, 50, (255.0 * brightness) as u8)
+                egui::Color32::from_rgba_unmultiplied(50, 255, 50, (255.0 * brightness) as u8)
+            };
+            
+            // Draw LED circle
+            painter.circle_filled(center, radius, color);
+            
+            // Add glow effect when LED is ON
+            if !is_input && is_high {
+                painter.circle_filled(center, radius + 3.0, 
+                    egui::Color32::from_rgba_premultiplied(50, 255, 50, 50));
+            }
+            
+            painter.circle_stroke(center, radius, 
+                egui::Stroke::new(2.0, egui::Color32::WHITE));
+            
+            // Direction label
+            let dir_text = if is_input { "IN" } else { "OUT" };
+            ui.label(egui::RichText::new(dir_text).small());
+            
+            // State label
+            let state_text = if is_high { "HIGH" } else { "LOW" };
+            ui.label(egui::RichText::new(state_text)
+                .small()
+                .color(if is_high { egui::Color32::GREEN } else { egui::Color32::GRAY }));
+        });
+    }
+    
+    /// Draw the I/O port panel, one row of pins per port of the device
+    fn draw_gpio_panel(&mut self, ui: &mut egui::Ui) {
+        let model = self.simulator.device().model();
+        ui.heading(if model.ports.len() == 1 { "GPIO Port" } else { "I/O Ports" });
+        ui.label(egui::RichText::new("Click input pins to toggle").small().italics());
+        ui.add_space(10.0);
+        
+        for (index, spec) in model.ports.iter().enumerate() {
+            let port = self.simulator.cpu().gpio().read_port(index);
+            let tris = self.simulator.cpu().gpio().read_port_tris(index);
+            
+            ui.horizontal(|ui| {
+                let bits = (0..8u8).filter(|bit| spec.pins & (1 << bit) != 0);
+                for (i, bit) in bits.enumerate() {
+                    if i > 0 {
+                        ui.add_space(10.0);
+                    }
+                    self.draw_gpio_pin(ui, index as u8 * 8 + bit, spec.pin_name(bit), port, tris);
+                }
+            });
+            
+            ui.add_space(10.0);
+            
+            // Show register values
+            let tris_name = model.name_of(spec.tris_address).unwrap_or("TRIS");
+            ui.horizontal(|ui| {
+                ui.label(format!("{:<7} 0b{:08b} (0x{:02X})", format!("{}:", spec.name), port, port));
+            });
+            ui.horizontal(|ui| {
+                ui.label(format!("{:<7} 0b{:08b} (0x{:02X})", format!("{}:", tris_name), tris, tris));
+            });
+            ui.add_space(10.0);
+        }
+    }
+    
+    /// Draw control panel (Run, Pause, Step, Reset buttons)
+    fn draw_control_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Control");
+        ui.add_space(10.0);
+        
+        ui.horizontal(|ui| {
+            // Step Back button - rewind one instruction
+            if ui.button("⏮ Step Back").clicked() {
+                if let Err(e) = self.simulator.step_back(1) {
+                    self.report_error(e.to_string());
+                }
+                self.gui_state = GuiSimulatorState::Paused;
+            }
+            
+            // Step button - execute one instruction
+            if ui.button("⏭ Step").on_hover_text("F11").clicked() {
+                self.step_into();
+            }
+            
+            // Step Over button - run a called subroutine to its return
+            if ui.button("⤵ Step Over").on_hover_text("F10").clicked() {
+                self.step_over();
+            }
+            
+            // Run/Pause button
+            let (run_text, run_color) = match self.gui_state {
+                GuiSimulatorState::Running => ("⏸ Pause", egui::Color32::RED),
+                _ => ("▶ Run", egui::Color32::GREEN),
+            };
+            
+            if ui.button(egui::RichText::new(run_text).color(run_color)).on_hover_text("F5").clicked() {
+                self.toggle_run();
+            }
+            
+            // Reset button
+            if ui.button(egui::RichText::new("⏹ Reset").color(egui::Color32::RED)).clicked() {
+                self.reset_simulator();
+            }
+            
+            // Step 100 button - execute 100 instructions quickly
+            if ui.button("⏭ Step 100").clicked() {
+                for _ in 0..100 {
+                    if let Err(e) = self.simulator.step() {
+                        self.execution_failed(e);
+                        break;
+                    }
+                    if self.simulator.watch_hit().is_some()
+                        || self.simulator.stopwatch_hit().is_some()
+                        || self.simulator.check_breakpoint(self.simulator.cpu().get_pc())
+                    {
+                        break;
+                    }
+                }
+                self.gui_state = GuiSimulatorState::Paused;
+            }
+        });
+        
+        ui.add_space(10.0);
+        
+        // Speed slider
+        ui.horizontal(|ui| {
+            ui.label("Speed:");
+            ui.add(egui::Slider::new(&mut self.target_frequency, 1_000..=10_000_000)
+                .logarithmic(true)
+                .custom_formatter(|n, _| format_hz(n)));
+            // Instruction cycles per second compared with the device's
+            ui.label(format!("{}× real time",
+                format_ratio(self.target_frequency as f64 / self.simulator.clock().cycles_per_second())));
+        });
+        
+        // Statistics
+        ui.add_space(5.0);
+        ui.label(format!("Instructions: {}", self.simulator.stats().instructions_executed));
+        ui.label(format!("Cycles: {}", self.simulator.stats().cycles_elapsed));
+        if let Some(config) = self.simulator.config_word() {
+            ui.label(format!("Config: 0x{:04X}", config));
+        }
+        if let Some(ids) = self.simulator.id_locations() {
+            ui.label(format!("User ID: {:04X} {:04X} {:04X} {:04X}", ids[0], ids[1], ids[2], ids[3]));
+        }
+        for warning in &self.load_warnings {
+            ui.label(egui::RichText::new(format!("⚠ {}", warning)).color(egui::Color32::YELLOW));
+        }
+        
+        if self.gui_state == GuiSimulatorState::Running {
+            let ratio = self.actual_frequency as f64 / self.simulator.clock().cycles_per_second();
+            ui.label(format!("Actual: {:.0} Hz ({}× real time)", self.actual_frequency, format_ratio(ratio)));
+        }
+    }
+    
+    /// Current value of an editable target
+    fn read_target(&self, target: EditTarget) -> u16 {
+        let cpu = self.simulator.cpu();
+        match target {
+            EditTarget::W => cpu.read_w() as u16,
+            EditTarget::Pc => cpu.get_pc(),
+            EditTarget::Register(address) => cpu.read_register(address) as u16,
+            EditTarget::Tmr1 => {
+                ((cpu.read_register(registers::TMR1H) as u16) << 8) | cpu.read_register(registers::TMR1L) as u16
+            }
+            EditTarget::Eeprom(address) => cpu.memory().read_eeprom(address) as u16,
+        }
+    }
+    
+    /// Store a value typed into an editable target
+    fn write_target(&mut self, target: EditTarget, value: u16) {
+        let cpu = self.simulator.cpu_mut();
+        match target {
+            EditTarget::W => cpu.write_w(value as u8),
+            EditTarget::Pc => cpu.set_pc(value),
+            EditTarget::Register(address) => cpu.write_register(address, value as u8),
+            EditTarget::Tmr1 => {
+                cpu.write_register(registers::TMR1H, (value >> 8) as u8);
+                cpu.write_register(registers::TMR1L, value as u8);
+            }
+            EditTarget::Eeprom(address) => cpu.memory_mut().write_eeprom(address, value as u8),
+        }
+    }
+    
+    /// Show `text` for `target`; double-clicking it while paused edits the value in hex
+    /// 
+    /// Enter stores the value, Escape or clicking elsewhere cancels.
+    fn editable_value(&mut self, ui: &mut egui::Ui, target: EditTarget, text: impl Into<egui::WidgetText>) {
+        let edit_id = egui::Id::new("edit_value");
+        if let Some((editing, input)) = &mut self.editing
+            && *editing == target
+        {
+            let response = ui.add(egui::TextEdit::singleline(input)
+                .id(edit_id)
+                .font(egui::TextStyle::Monospace)
+                .desired_width(50.0));
+            if response.lost_focus() {
+                let commit = ui.input(|i| i.key_pressed(egui::Key::Enter));
+                let text = input.trim().to_string();
+                self.editing = None;
+                if commit {
+                    let hex = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(&text);
+                    match u16::from_str_radix(hex, 16) {
+                        Ok(value) => self.write_target(target, value),
+                        Err(_) => self.report_error(format!("Invalid hex value: {}", text)),
+                    }
+                }
+            }
+            return;
+        }
+        
+        let response = ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+        if self.gui_state == GuiSimulatorState::Running {
+            response.on_hover_text("Pause to edit");
+        } else if response.on_hover_text("Double-click to edit").double_clicked() {
+            self.editing = Some((target, format!("{:X}", self.read_target(target))));
+            ui.memory_mut(|memory| memory.request_focus(edit_id));
+        }
+    }
+    
+    /// Draw memory viewer panel
+    fn draw_memory_viewer(&mut self, ui: &mut egui::Ui) {
+        if !self.show_memory_viewer {
+            return;
+        }
+        
+        ui.heading("Memory Viewer");
+        ui.add_space(5.0);
+        
+        // Bank and start address
+        ui.horizontal(|ui| {
+            let bank = self.memory_view_address >> 7;
+            for choice in 0..2u8 {
+                if ui.selectable_label(bank == choice, format!("Bank {}", choice)).clicked() {
+                    self.memory_view_address = (self.memory_view_address & 0x7F) | (choice << 7);
+                }
+            }
+            ui.separator();
+            ui.label("Start Address:");
+            ui.add(egui::DragValue::new(&mut self.memory_view_address)
+                .prefix("0x")
+                .hexadecimal(2, false, true));
+        });
+        ui.label(egui::RichText::new("SFRs in blue, changed bytes highlighted").small().weak());
+        
+        ui.add_space(5.0);
+        
+        let bank_end = (self.memory_view_address as u16 & 0x80) + 0x80;
+        let previous = std::mem::take(&mut self.memory_previous);
+        
+        // Display memory in hex dump format
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Addr");
+                    ui.label("  +0  +1  +2  +3  +4  +5  +6  +7");
+                });
+                ui.separator();
+                
+                for row in 0..8u16 {
+                    let addr = self.memory_view_address as u16 + row * 8;
+                    if addr >= bank_end {
+                        break;
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label(format!("0x{:02X}", addr));
+                        let mut names = Vec::new();
+                        for byte_addr in (addr..addr + 8).filter(|&a| a < bank_end).map(|a| a as u8) {
+                            let value = self.simulator.cpu().read_register(byte_addr);
+                            let name = sfr_name(byte_addr);
+                            let mut text = egui::RichText::new(format!(" {:02X}", value)).monospace();
+                            if name.is_some() {
+                                text = text.color(egui::Color32::LIGHT_BLUE);
+                            }
+                            if previous.get(byte_addr as usize).is_some_and(|&old| old != value) {
+                                text = text.background_color(egui::Color32::from_rgb(90, 80, 0));
+                            }
+                            names.extend(name);
+                            self.editable_value(ui, EditTarget::Register(byte_addr), text);
+                        }
+                        if !names.is_empty() {
+                            ui.label(egui::RichText::new(names.join(" ")).small().weak());
+                        }
+                    });
+                }
+            });
+        
+        self.memory_previous = (0..=255).map(|addr| self.simulator.cpu().read_register(addr)).collect();
+    }
+    
+    /// Draw watch panel: expressions re-evaluated every frame
+    fn draw_watch_panel(&mut self, ui: &mut egui::Ui) {
+        if !self.show_watch_panel {
+            return;
+        }
+        
+        ui.heading("Watch");
+        ui.add_space(5.0);
+        
+        ui.horizontal(|ui| {
+            let response = ui.add(egui::TextEdit::singleline(&mut self.watch_input)
+                .hint_text("GPIO, 0x20, [0x21] + W")
+                .desired_width(150.0));
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("➕ Add").clicked() || entered {
+                self.add_watch_from_input();
+            }
+        });
+        
+        let mut removed = None;
+        egui::Grid::new("watch_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                for (index, watch) in self.watches.iter_mut().enumerate() {
+                    ui.label(egui::RichText::new(watch.expr.to_string()).monospace());
+                    let value = watch.expr.eval(&self.simulator);
+                    ui.label(egui::RichText::new(watch.format.format(value)).monospace().strong());
+                    egui::ComboBox::from_id_salt(("watch_format", index))
+                        .width(50.0)
+                        .selected_text(format!("{:?}", watch.format))
+                        .show_ui(ui, |ui| {
+                            for format in [WatchFormat::Hex, WatchFormat::Decimal, WatchFormat::Binary] {
+                                ui.selectable_value(&mut watch.format, format, format!("{:?}", format));
+                            }
+                        });
+                    if ui.small_button("🗑").clicked() {
+                        removed = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = removed {
+            self.watches.remove(index);
+        }
+    }
+    
+    /// Add the expression typed into the watch panel
+    /// 
+    /// A bare number watches the data memory byte at that address.
+    fn add_watch_from_input(&mut self) {
+        let input = self.watch_input.trim();
+        if input.is_empty() {
+            return;
+        }
+        let text = if input.starts_with(|c: char| c.is_ascii_digit())
+            && input.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            format!("[{}]", input)
+        } else {
+            input.to_string()
+        };
+        
+        match Expr::parse(&text, self.simulator.symbols()) {
+            Ok(expr) => {
+                self.watches.push(WatchEntry { expr, format: WatchFormat::Hex });
+                self.watch_input.clear();
+            }
+            Err(e) => self.report_error(format!("Invalid watch expression: {}", e)),
+        }
+    }
+    
+    /// Switch between running and paused
+    fn toggle_run(&mut self) {
+        self.gui_state = match self.gui_state {
+            GuiSimulatorState::Running => GuiSimulatorState::Paused,
+            _ => GuiSimulatorState::Running,
+        };
+    }
+    
+    /// Execute one instruction, entering subroutines
+    fn step_into(&mut self) {
+        if let Err(e) = self.simulator.step() {
+            self.execution_failed(e);
+        }
+        self.gui_state = GuiSimulatorState::Paused;
+        self.scroll_to_pc = true;
+    }
+    
+    /// Execute one instruction; a CALL runs until it returns, stopping
+    /// early at breakpoints
+    fn step_over(&mut self) {
+        let pc = self.simulator.cpu().get_pc();
+        let is_call = matches!(
+            self.simulator.cpu_mut().memory_mut().read_instruction(pc),
+            Ok(Instruction::CALL { .. })
+        );
+        if !is_call {
+            self.step_into();
+            return;
+        }
+        
+        let return_pc = pc.wrapping_add(1);
+        let depth = self.simulator.call_stack().depth();
+        let outcome = self.simulator.run_until(
+            |sim| sim.cpu().get_pc() == return_pc && sim.call_stack().depth() <= depth,
+            STEP_OVER_MAX_CYCLES,
+        );
+        match outcome {
+            RunOutcome::PredicateMet => {}
+            RunOutcome::Error(e) => self.execution_failed(e),
+            other => self.log(LogLevel::Info, format!("Step over stopped: {:?}", other)),
+        }
+        self.gui_state = GuiSimulatorState::Paused;
+        self.scroll_to_pc = true;
+    }
+    
+    fn reset_simulator(&mut self) {
+        self.simulator.reset();
+        self.gui_state = GuiSimulatorState::Paused;
+        self.scroll_to_pc = true;
+    }
+    
+    /// Handle execution control shortcuts
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.input_mut(|i| i.consume_shortcut(&RUN_SHORTCUT)) {
+            self.toggle_run();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&STEP_OVER_SHORTCUT)) {
+            self.step_over();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&STEP_INTO_SHORTCUT)) {
+            self.step_into();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&RESET_SHORTCUT)) {
+            self.reset_simulator();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&OPEN_SHORTCUT)) {
+            self.load_firmware_file();
+        }
+    }
+    
+    /// Draw waveform panel: timing diagrams of every pin over the last
+    /// `wave_window_ms`, with TMR0 / interrupt markers and two cursors
+    /// (left click sets A, right click sets B)
+    fn draw_wave_panel(&mut self, ui: &mut egui::Ui) {
+        const LABEL_WIDTH: f32 = 48.0;
+        const ROW_HEIGHT: f32 = 22.0;
+        
+        let cycles_per_us = self.simulator.clock().fosc_hz() as f64 / 4_000_000.0;
+        let to_ms = |cycle: u64| cycle as f64 / cycles_per_us / 1000.0;
+        
+        ui.horizontal(|ui| {
+            ui.heading("Waveforms");
+            ui.separator();
+            ui.label("Window:");
+            ui.add(egui::DragValue::new(&mut self.wave_window_ms)
+                .range(0.01..=10_000.0)
+                .speed(0.1)
+                .suffix(" ms"));
+            if ui.button("🔍➖").clicked() {
+                self.wave_window_ms = (self.wave_window_ms * 2.0).min(10_000.0);
+            }
+            if ui.button("🔍➕").clicked() {
+                self.wave_window_ms = (self.wave_window_ms / 2.0).max(0.01);
+            }
+            ui.checkbox(&mut self.wave_show_markers, "TMR0 / IRQ markers");
+            if ui.button("Clear Cursors").clicked() {
+                self.wave_cursors = [None; 2];
+            }
+        });
+        
+        let end = self.simulator.stats().cycles_elapsed;
+        let window = ((self.wave_window_ms * 1000.0 * cycles_per_us) as u64).max(1);
+        let start = end.saturating_sub(window);
+        
+        let model = self.simulator.device().model();
+        let pins: Vec<u8> = model.pins().collect();
+        let rows = pins.len() + usize::from(self.wave_show_markers);
+        let size = egui::vec2(ui.available_width(), ROW_HEIGHT * rows as f32);
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::click());
+        let rect = response.rect;
+        let left = rect.left() + LABEL_WIDTH;
+        let width = (rect.right() - left).max(1.0);
+        let x_of = |cycle: u64| left + cycle.saturating_sub(start) as f32 / window as f32 * width;
+        let cycle_at = |x: f32| start + ((x - left).max(0.0) / width * window as f32) as u64;
+        
+        // Scrolling over the traces zooms
+        if response.hovered() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            if scroll != 0.0 {
+                self.wave_window_ms = (self.wave_window_ms * (1.0 - scroll as f64 * 0.005)).clamp(0.01, 10_000.0);
+            }
+        }
+        if let Some(pos) = response.interact_pointer_pos() {
+            if response.clicked() {
+                self.wave_cursors[0] = Some(cycle_at(pos.x));
+            } else if response.secondary_clicked() {
+                self.wave_cursors[1] = Some(cycle_at(pos.x));
+            }
+        }
+        
+        painter.rect_filled(rect, 2.0, egui::Color32::from_gray(15));
+        let font = egui::FontId::monospace(12.0);
+        let logic = self.simulator.logic_analyzer();
+        
+        for (row, &pin) in pins.iter().enumerate() {
+            let top = rect.top() + row as f32 * ROW_HEIGHT;
+            let y = |level: bool| if level { top + 4.0 } else { top + ROW_HEIGHT - 4.0 };
+            painter.text(
+                egui::pos2(rect.left() + 4.0, top + ROW_HEIGHT / 2.0),
+                egui::Align2::LEFT_CENTER,
+                model.pin_name(pin).unwrap_or_default(),
+                font.clone(),
+                egui::Color32::GRAY,
+            );
+            
+            let Some(from) = logic.start_cycle().map(|first| first.max(start)) else {
+                continue;
+            };
+            let Some(mut level) = logic.level_at(pin, from) else {
+                continue;
+            };
+            let mut points = vec![egui::pos2(x_of(from), y(level))];
+            for transition in logic.pin_transitions(pin).filter(|t| t.cycle > from && t.cycle <= end) {
+                let x = x_of(transition.cycle);
+                points.push(egui::pos2(x, y(level)));
+                points.push(egui::pos2(x, y(transition.level)));
+                level = transition.level;
+            }
+            points.push(egui::pos2(x_of(end), y(level)));
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, egui::Color32::GREEN)));
+        }
+        
+        if self.wave_show_markers {
+            let top = rect.top() + pins.len() as f32 * ROW_HEIGHT;
+            painter.text(
+                egui::pos2(rect.left() + 4.0, top + ROW_HEIGHT / 2.0),
+                egui::Align2::LEFT_CENTER,
+                "EVT",
+                font.clone(),
+                egui::Color32::GRAY,
+            );
+            for marker in logic.markers().filter(|m| m.cycle >= start && m.cycle <= end) {
+                let color = match marker.kind {
+                    MarkerKind::Timer0Overflow => egui::Color32::from_rgb(255, 165, 0),
+                    MarkerKind::InterruptEntered(_) => egui::Color32::LIGHT_BLUE,
+                };
+                let x = x_of(marker.cycle);
+                painter.line_segment(
+                    [egui::pos2(x, top + 3.0), egui::pos2(x, top + ROW_HEIGHT - 3.0)],
+                    egui::Stroke::new(1.5, color),
+                );
+            }
+        }
+        
+        for (cursor, name, color) in [
+            (self.wave_cursors[0], "A", egui::Color32::YELLOW),
+            (self.wave_cursors[1], "B", egui::Color32::from_rgb(0, 200, 255)),
+        ] {
+            if let Some(cycle) = cursor.filter(|&cycle| cycle >= start && cycle <= end) {
+                let x = x_of(cycle);
+                painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], egui::Stroke::new(1.0, color));
+                painter.text(egui::pos2(x + 2.0, rect.top()), egui::Align2::LEFT_TOP, name, font.clone(), color);
+            }
+        }
+        
+        ui.horizontal(|ui| {
+            ui.label(format!("{:.3} ms", to_ms(start)));
+            ui.separator();
+            if self.wave_show_markers {
+                ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "| TMR0 overflow");
+                ui.colored_label(egui::Color32::LIGHT_BLUE, "| interrupt");
+                ui.separator();
+            }
+            for (cursor, name) in self.wave_cursors.iter().zip(["A", "B"]) {
+                if let Some(cycle) = cursor {
+                    ui.label(format!("{}: {:.3} ms", name, to_ms(*cycle)));
+                }
+            }
+            if let [Some(a), Some(b)] = self.wave_cursors {
+                let delta = a.abs_diff(b);
+                let micros = delta as f64 / cycles_per_us;
+                let frequency = if delta > 0 { format!(", {:.1} Hz", 1_000_000.0 / micros) } else { String::new() };
+                ui.label(egui::RichText::new(format!("Δ {} cycles ({:.1} µs{})", delta, micros, frequency)).strong());
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(format!("{:.3} ms", to_ms(end)));
+            });
+        });
+    }
+    
+    /// Draw EEPROM panel: hex grid with ASCII column, editable cells and file load/save
+    fn draw_eeprom_panel(&mut self, ui: &mut egui::Ui) {
+        if !self.show_eeprom_panel {
+            return;
+        }
+        
+        ui.heading("EEPROM");
+        ui.add_space(5.0);
+        
+        let size = self.simulator.cpu().memory().eeprom_size();
+        if size == 0 {
+            ui.label("This device has no EEPROM");
+            return;
+        }
+        
+        ui.horizontal(|ui| {
+            if ui.button("📂 Load...").clicked() {
+                self.load_eeprom_file();
+            }
+            if ui.button("💾 Save...").clicked() {
+                self.save_eeprom_file();
+            }
+        });
+        
+        egui::ScrollArea::vertical()
+            .id_salt("eeprom_scroll")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for row in (0..size).step_by(8) {
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(format!("0x{:02X}", row)).monospace());
+                        let mut ascii = String::new();
+                        for address in row..row + 8 {
+                            let value = self.simulator.cpu().memory().read_eeprom(address as u8);
+                            self.editable_value(ui, EditTarget::Eeprom(address as u8),
+                                egui::RichText::new(format!("{:02X}", value)).monospace());
+                            ascii.push(if value.is_ascii_graphic() || value == b' ' { value as char } else { '.' });
+                        }
+                        ui.label(egui::RichText::new(ascii).monospace().weak());
+                    });
+                }
+            });
+    }
+    
+    /// Draw timer panel (TMR0, TMR1)
+    fn draw_timer_panel(&mut self, ui: &mut egui::Ui) {
+        if !self.show_timer_panel {
+            return;
+        }
+        
+        ui.heading("Timers");
+        ui.add_space(5.0);
+        
+        // Timer0
+        let tmr0 = self.simulator.cpu().read_register(registers::TMR0);
+        self.editable_value(ui, EditTarget::Register(registers::TMR0), format!("TMR0: 0x{:02X} ({})", tmr0, tmr0));
+        
+        // Timer1
+        let tmr1l = self.simulator.cpu().read_register(registers::TMR1L);
+        let tmr1h = self.simulator.cpu().read_register(registers::TMR1H);
+        let tmr1 = ((tmr1h as u16) << 8) | (tmr1l as u16);
+        self.editable_value(ui, EditTarget::Tmr1, format!("TMR1: 0x{:04X} ({})", tmr1, tmr1));
+        
+        // T1CON register
+        let t1con = self.simulator.cpu().read_register(registers::T1CON);
+        ui.label(format!("T1CON: 0b{:08b}", t1con));
+    }
+    
+    /// Draw interrupt panel: enable/flag bits and per-source counters
+    fn draw_interrupt_panel(&mut self, ui: &mut egui::Ui) {
+        if !self.show_interrupt_panel {
+            return;
+        }
+        
+        ui.heading("Interrupts");
+        ui.add_space(5.0);
+        
+        let intcon = self.simulator.cpu().read_register(registers::INTCON);
+        self.editable_value(ui, EditTarget::Register(registers::INTCON),
+            format!("INTCON: 0b{:08b}", intcon));
+        let cpu = self.simulator.cpu();
+        ui.label(format!("PIE1: 0b{:08b}  PIR1: 0b{:08b}",
+            cpu.read_register(registers::PIE1), cpu.read_register(registers::PIR1)));
+        ui.horizontal(|ui| {
+            indicator(ui, "GIE", intcon & 0x80 != 0, egui::Color32::GREEN);
+            indicator(ui, "PEIE", intcon & 0x40 != 0, egui::Color32::GREEN);
+            ui.separator();
+            match self.simulator.interrupt_timing().active() {
+                Some(source) => ui.colored_label(egui::Color32::YELLOW, format!("In ISR ({})", source.name())),
+                None if cpu.interrupts().in_isr() => ui.colored_label(egui::Color32::YELLOW, "In ISR"),
+                None => ui.label("Not in ISR"),
+            };
+        });
+        ui.add_space(5.0);
+        
+        let mut toggled_flag = None;
+        let interrupts = cpu.interrupts();
+        egui::Grid::new("interrupt_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Source");
+                ui.label("IE");
+                ui.label("IF");
+                ui.label("");
+                ui.label("Count");
+                ui.label("Last cycle");
+                ui.label("Latency");
+                ui.label("ISR time");
+                ui.end_row();
+                
+                for source in InterruptSource::ALL {
+                    let stats = interrupts.stats(source);
+                    let timing = self.simulator.interrupt_timing().source(source);
+                    let name = egui::RichText::new(source.name());
+                    if interrupts.last_source() == Some(source) {
+                        ui.label(name.strong());
+                    } else {
+                        ui.label(name);
+                    }
+                    
+                    let (enable_reg, enable_bit) = source.enable_bit();
+                    let (flag_reg, flag_bit) = source.flag_bit();
+                    let enabled = cpu.read_register(enable_reg) & (1 << enable_bit) != 0;
+                    let flagged = cpu.read_register(flag_reg) & (1 << flag_bit) != 0;
+                    indicator(ui, "", enabled, egui::Color32::GREEN);
+                    indicator(ui, "", flagged, egui::Color32::from_rgb(255, 165, 0));
+                    let button = if flagged { "Clear" } else { "Set" };
+                    if ui.small_button(button).on_hover_text("Set or clear the interrupt flag").clicked() {
+                        toggled_flag = Some((flag_reg, flag_bit));
+                    }
+                    
+                    ui.label(format!("{}", stats.count));
+                    ui.label(stats.last_cycle.map_or("-".to_string(), |cycle| cycle.to_string()));
+                    for cycles in [&timing.latency, &timing.isr] {
+                        if cycles.count == 0 {
+                            ui.label("-");
+                        } else {
+                            ui.label(format!("{}/{:.1}/{}", cycles.min, cycles.average(), cycles.max))
+                                .on_hover_text("min/avg/max cycles");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        
+        if let Some((register, bit)) = toggled_flag {
+            let cpu = self.simulator.cpu_mut();
+            let value = cpu.read_register(register);
+            cpu.write_register(register, value ^ (1 << bit));
+        }
+    }
+    
+    /// Draw WDT panel: enable state, progress towards the timeout, prescaler
+    /// assignment and recent resets
+    fn draw_wdt_panel(&mut self, ui: &mut egui::Ui) {
+        if !self.show_wdt_panel {
+            return;
+        }
+        
+        ui.heading("Watchdog Timer");
+        ui.add_space(5.0);
+        
+        let wdt = self.simulator.cpu().wdt();
+        let clock = self.simulator.clock();
+        indicator(ui, " Enabled", wdt.is_enabled(), egui::Color32::GREEN);
+        
+        let counter = wdt.get_counter();
+        let timeout = wdt.get_timeout_period().max(1);
+        let progress = counter as f32 / timeout as f32;
+        let color = if progress > 0.9 {
+            egui::Color32::from_rgb(200, 60, 60)
+        } else if progress > 0.7 {
+            egui::Color32::from_rgb(200, 160, 40)
+        } else {
+            egui::Color32::from_rgb(60, 140, 60)
+        };
+        ui.add(egui::ProgressBar::new(progress)
+            .fill(color)
+            .text(format!("{} / {}", counter, timeout)));
+        match wdt.cycles_until_timeout() {
+            Some(cycles) => ui.label(format!("Timeout in {:.3} ms", clock.cycles_to_micros(cycles) / 1000.0)),
+            None => ui.label("Timeout: never (disabled)"),
+        };
+        
+        let prescaler = if wdt.is_prescaler_assigned() {
+            format!("WDT 1:{}", wdt.prescaler_rate())
+        } else {
+            "Timer0 (WDT 1:1)".to_string()
+        };
+        ui.label(format!("Prescaler: {}", prescaler));
+        ui.label(format!("Period: {} µs × {}", wdt.period_us(), wdt.prescaler_rate()));
+        
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label(format!("Recent resets: {}", self.wdt_resets.len()));
+            if !self.wdt_resets.is_empty() && ui.small_button("Clear").clicked() {
+                self.wdt_resets.clear();
+            }
+        });
+        for &(cycle, pc) in self.wdt_resets.iter().rev() {
+            ui.label(egui::RichText::new(format!(
+                "{:>10}  {:>9.3} ms  PC 0x{:04X}",
+                cycle,
+                clock.cycles_to_micros(cycle) / 1000.0,
+                pc,
+            )).monospace());
+        }
+    }
+    
+    /// Draw call stack panel (innermost frame first)
+    fn draw_call_stack_panel(&self, ui: &mut egui::Ui) {
+        if !self.show_call_stack_panel {
+            return;
+        }
+        
+        ui.heading("Call Stack");
+        ui.add_space(5.0);
+        
+        let call_stack = self.simulator.call_stack();
+        ui.label(egui::RichText::new(Debugger::format_call_chain(call_stack)).monospace());
+        ui.add_space(5.0);
+        
+        let current_cycle = self.simulator.stats().cycles_elapsed;
+        egui::Grid::new("call_stack_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Callee");
+                ui.label("Caller");
+                ui.label("Cycles");
+                ui.end_row();
+                
+                for frame in call_stack.frames().iter().rev() {
+                    let callee = match frame.kind {
+                        FrameKind::Call => format!("0x{:04X}", frame.callee),
+                        FrameKind::Interrupt => format!("ISR@0x{:04X}", frame.callee),
+                    };
+                    ui.label(callee);
+                    ui.label(format!("0x{:04X}", frame.caller_pc));
+                    ui.label(format!("{}", frame.cycles_spent(current_cycle)));
+                    ui.end_row();
+                }
+            });
+    }
+    
+    /// Draw the most recently executed instructions with the W and STATUS
+    /// values they left, newest last
+    fn draw_trace_panel(&mut self, ui: &mut egui::Ui) {
+        let trace = self.simulator.trace();
+        ui.horizontal(|ui| {
+            ui.heading("Instruction Trace");
+            ui.label(egui::RichText::new(format!("last {} of {}", trace.len().min(TRACE_PANEL_ENTRIES), trace.len())).weak());
+        });
+        
+        if trace.is_empty() {
+            ui.label("Nothing executed yet");
+            return;
+        }
+        
+        let skip = trace.len().saturating_sub(TRACE_PANEL_ENTRIES);
+        let last_cycle = trace.last().map(|entry| entry.cycle);
+        let mut selected = None;
+        egui::ScrollArea::vertical()
+            .id_salt("trace_entries")
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                egui::Grid::new("trace_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for title in ["Cycle", "PC", "Instruction", "W", "STATUS"] {
+                            ui.label(egui::RichText::new(title).strong());
+                        }
+                        ui.end_row();
+                        
+                        for entry in trace.iter().skip(skip) {
+                            // The cached line carries the code panel's symbol names
+                            let asm = match self.disassembly_cache.get(entry.pc as usize) {
+                                Some((_, word, asm)) if *word == entry.word => asm.clone(),
+                                _ => Debugger::disassemble_with_symbols(self.simulator.device().core(), entry.word, self.simulator.symbols()),
+                            };
+                            let mut pc = egui::RichText::new(format!("0x{:04X}", entry.pc)).monospace();
+                            if Some(entry.cycle) == last_cycle {
+                                pc = pc.color(egui::Color32::RED);
+                            }
+                            
+                            ui.label(egui::RichText::new(entry.cycle.to_string()).monospace().weak());
+                            if ui.add(egui::Label::new(pc).sense(egui::Sense::click()))
+                                .on_hover_text("Click to show in the code panel")
+                                .clicked()
+                            {
+                                selected = Some(entry.pc);
+                            }
+                            ui.label(egui::RichText::new(asm).monospace());
+                            ui.label(egui::RichText::new(format!("0x{:02X}", entry.w)).monospace());
+                            ui.label(egui::RichText::new(format!(
+                                "0x{:02X} {}{}{}",
+                                entry.status,
+                                if entry.status & 0x04 != 0 { 'Z' } else { '-' },
+                                if entry.status & 0x02 != 0 { 'D' } else { '-' },
+                                if entry.status & 0x01 != 0 { 'C' } else { '-' },
+                            )).monospace());
+                            ui.end_row();
+                        }
+                    });
+            });
+        
+        if let Some(addr) = selected {
+            self.select_code_line(addr);
+        }
+    }
+    
+    /// Draw breakpoint manager: add, enable, list and delete breakpoints and watchpoints
+    fn draw_breakpoint_panel(&mut self, ui: &mut egui::Ui) {
+        if !self.show_breakpoint_panel {
+            return;
+        }
+        
+        ui.heading("Breakpoints");
+        ui.add_space(5.0);
+        
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.breakpoint_address_input)
+                .hint_text("0x010, loop")
+                .desired_width(70.0));
+            ui.add(egui::TextEdit::singleline(&mut self.breakpoint_condition_input)
+                .hint_text("condition")
+                .desired_width(90.0));
+            if ui.button("➕ Add").clicked() {
+                self.add_breakpoint_from_input();
+            }
+        });
+        
+        let mut enabled_changes = Vec::new();
+        let mut removed = None;
+        egui::Grid::new("breakpoint_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                for bp in self.simulator.breakpoints() {
+                    let mut enabled = bp.enabled;
+                    if ui.checkbox(&mut enabled, "").changed() {
+                        enabled_changes.push((bp.address, enabled));
+                    }
+                    let location = match self.simulator.symbols().label_at(bp.address) {
+                        Some(label) => format!("0x{:04X} {}", bp.address, label),
+                        None => format!("0x{:04X}", bp.address),
+                    };
+                    ui.label(egui::RichText::new(location).monospace());
+                    ui.label(bp.condition.as_ref().map_or("-".to_string(), |c| c.to_string()));
+                    ui.label(format!("{} hits", bp.hits));
+                    if ui.small_button("🗑").clicked() {
+                        removed = Some(bp.address);
+                    }
+                    ui.end_row();
+                }
+            });
+        for (address, enabled) in enabled_changes {
+            self.simulator.set_breakpoint_enabled(address, enabled);
+        }
+        if let Some(address) = removed {
+            self.simulator.remove_breakpoint(address);
+        }
+        
+        ui.add_space(5.0);
+        ui.label(egui::RichText::new("Register breakpoints").strong());
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.watch_register_input)
+                .hint_text("GPIO, 0x20")
+                .desired_width(80.0));
+            egui::ComboBox::from_id_salt("watch_kind")
+                .selected_text(self.watch_kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in [WatchKind::Write, WatchKind::Change, WatchKind::Read, WatchKind::Access] {
+                        ui.selectable_value(&mut self.watch_kind, kind, kind.to_string());
+                    }
+                });
+            if ui.button("➕ Add").clicked() {
+                match watchpoint::parse_register(self.watch_register_input.trim()) {
+                    Some(addr) => {
+                        self.simulator.add_watchpoint(Watchpoint::new(addr, self.watch_kind));
+                        self.watch_register_input.clear();
+                    }
+                    None => self.report_error(format!("Unknown register: {}", self.watch_register_input)),
+                }
+            }
+        });
+        
+        let mut enabled_changes = Vec::new();
+        let mut removed = None;
+        egui::Grid::new("watchpoint_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                for wp in self.simulator.watchpoints() {
+                    let mut enabled = wp.enabled;
+                    if ui.checkbox(&mut enabled, "").changed() {
+                        enabled_changes.push((wp.address, enabled));
+                    }
+                    ui.label(egui::RichText::new(wp.to_string()).monospace());
+                    ui.label(format!("{} hits", wp.hits));
+                    if ui.small_button("🗑").clicked() {
+                        removed = Some(wp.address);
+                    }
+                    ui.end_row();
+                }
+            });
+        for (address, enabled) in enabled_changes {
+            self.simulator.set_watchpoint_enabled(address, enabled);
+        }
+        if let Some(addr) = removed {
+            self.simulator.remove_watchpoint(addr);
+        }
+        
+        if let Some(hit) = self.simulator.watch_hit() {
+            ui.add_space(5.0);
+            ui.label(egui::RichText::new(format!("⏸ {}", hit)).color(egui::Color32::YELLOW));
+        }
+    }
+    
+    /// Add the breakpoint typed into the breakpoint panel, with its optional condition
+    fn add_breakpoint_from_input(&mut self) {
+        let Some(address) = self.parse_code_address(&self.breakpoint_address_input) else {
+            self.report_error(format!("Invalid breakpoint address: {}", self.breakpoint_address_input));
+            return;
+        };
+        let condition = self.breakpoint_condition_input.trim();
+        let condition = if condition.is_empty() {
+            None
+        } else {
+            match Expr::parse(condition, self.simulator.symbols()) {
+                Ok(expr) => Some(expr),
+                Err(e) => {
+                    self.report_error(format!("Invalid condition: {}", e));
+                    return;
                 }
-            };
-            
-            // Draw LED circle
-            painter.circle_filled(center, radius, color);
-            
-            // Add glow effect when LED is ON
-            if !is_input && is_high {
-                painter.circle_filled(center, radius + 3.0, 
-                    egui::Color32::from_rgba_premultiplied(50, 255, 50, 50));
             }
-            
-            painter.circle_stroke(center, radius, 
-                egui::Stroke::new(2.0, egui::Color32::WHITE));
-            
-            // Direction label
-            let dir_text = if is_input { "IN" } else { "OUT" };
-            ui.label(egui::RichText::new(dir_text).small());
-            
-            // State label
-            let state_text = if is_high { "HIGH" } else { "LOW" };
-            ui.label(egui::RichText::new(state_text)
-                .small()
-                .color(if is_high { egui::Color32::GREEN } else { egui::Color32::GRAY }));
-        });
+        };
+        
+        self.simulator.add_breakpoint(address);
+        self.simulator.set_breakpoint_condition(address, condition);
+        self.breakpoint_address_input.clear();
+        self.breakpoint_condition_input.clear();
     }
     
-    /// Draw GPIO port panel
-    fn draw_gpio_panel(&mut self, ui: &mut egui::Ui, gpio: u8, trisio: u8) {
-        ui.heading("GPIO Port");
-        ui.label(egui::RichText::new("Click input pins to toggle").small().italics());
-        ui.add_space(10.0);
+    /// Draw stopwatch panel: arm between two addresses and show the timing
+    fn draw_stopwatch_panel(&mut self, ui: &mut egui::Ui) {
+        if !self.show_stopwatch_panel {
+            return;
+        }
+        
+        ui.heading("Stopwatch");
+        ui.add_space(5.0);
         
         ui.horizontal(|ui| {
-            for pin in 0..6 {
-                self.draw_gpio_pin(ui, pin, gpio, trisio);
-                if pin < 5 {
-                    ui.add_space(10.0);
+            ui.label("From");
+            ui.add(egui::TextEdit::singleline(&mut self.stopwatch_start_input)
+                .hint_text("0x000")
+                .desired_width(60.0));
+            ui.label("to");
+            ui.add(egui::TextEdit::singleline(&mut self.stopwatch_stop_input)
+                .hint_text("label")
+                .desired_width(60.0));
+        });
+        
+        ui.horizontal(|ui| {
+            if ui.button("⏱ Arm").clicked() {
+                let start = self.parse_code_address(&self.stopwatch_start_input);
+                let stop = self.parse_code_address(&self.stopwatch_stop_input);
+                match (start, stop) {
+                    (Some(start), Some(stop)) => self.simulator.arm_stopwatch(start, stop),
+                    _ => self.report_error("Invalid stopwatch address"),
                 }
             }
+            if ui.button("Disarm").clicked() {
+                self.simulator.disarm_stopwatch();
+            }
         });
         
+        let clock = *self.simulator.clock();
+        let stopwatch = self.simulator.stopwatch();
+        match stopwatch.points() {
+            Some((start, stop)) => ui.label(format!("Armed: 0x{:04X} → 0x{:04X}", start, stop)),
+            None => ui.label("Not armed"),
+        };
+        if let Some(elapsed) = stopwatch.elapsed(self.simulator.stats().cycles_elapsed) {
+            ui.label(format!("Running: {} cycles", elapsed));
+        }
+        if let Some(reading) = stopwatch.last_reading() {
+            ui.label(egui::RichText::new(format!("{} cycles = {:.2} µs", reading.cycles, reading.micros(&clock)))
+                .monospace()
+                .strong());
+        }
+        ui.label(format!("Fosc: {:.3} MHz ({})", clock.fosc_hz() as f64 / 1_000_000.0, clock.source()));
+    }
+    
+    /// Draw the pin stimulus panel: add constant levels, square waves and
+    /// pulses, and list the rules with a preview of each signal
+    fn draw_stimulus_panel(&mut self, ui: &mut egui::Ui) {
+        if !self.show_stimulus_panel {
+            return;
+        }
+        
         ui.add_space(10.0);
+        ui.heading("Pin Stimulus");
+        ui.add_space(5.0);
         
-        // Show register values
+        let model = self.simulator.device().model();
+        let form = &mut self.stimulus_form;
         ui.horizontal(|ui| {
-            ui.label(format!("GPIO:   0b{:06b} (0x{:02X})", gpio, gpio));
+            egui::ComboBox::from_id_salt("stimulus_pin")
+                .selected_text(model.pin_name(form.pin).unwrap_or_default())
+                .width(60.0)
+                .show_ui(ui, |ui| {
+                    for pin in model.pins() {
+                        ui.selectable_value(&mut form.pin, pin, model.pin_name(pin).unwrap_or_default());
+                    }
+                });
+            ui.selectable_value(&mut form.kind, StimulusKind::Constant, "Constant");
+            ui.selectable_value(&mut form.kind, StimulusKind::SquareWave, "Square wave");
+            ui.selectable_value(&mut form.kind, StimulusKind::Pulse, "Pulse");
         });
+        
+        let unit = if form.micros { " µs" } else { " cycles" };
         ui.horizontal(|ui| {
-            ui.label(format!("TRISIO: 0b{:06b} (0x{:02X})", trisio, trisio));
+            match form.kind {
+                StimulusKind::Constant => {
+                    ui.checkbox(&mut form.high, "High");
+                }
+                StimulusKind::SquareWave => {
+                    ui.label("Period");
+                    ui.add(egui::DragValue::new(&mut form.period).range(2.0..=f64::MAX).suffix(unit));
+                }
+                StimulusKind::Pulse => {
+                    ui.checkbox(&mut form.high, "High");
+                    ui.label("at");
+                    ui.add(egui::DragValue::new(&mut form.at).range(0.0..=f64::MAX).suffix(unit));
+                    ui.label("for");
+                    ui.add(egui::DragValue::new(&mut form.width).range(1.0..=f64::MAX).suffix(unit));
+                }
+            }
+            ui.checkbox(&mut form.micros, "µs");
         });
-    }
-    
-    /// Draw control panel (Run, Pause, Step, Reset buttons)
-    fn draw_control_panel(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Control");
-        ui.add_space(10.0);
         
         ui.horizontal(|ui| {
-            // Step button - execute one instruction
-            if ui.button("⏭ Step").clicked() {
-                let _ = self.simulator.step();
-                self.gui_state = GuiSimulatorState::Paused;
-            }
-            
-            // Run/Pause button
-            let (run_text, run_color) = match self.gui_state {
-                GuiSimulatorState::Running => ("⏸ Pause", egui::Color32::RED),
-                _ => ("▶ Run", egui::Color32::GREEN),
-            };
-            
-            if ui.button(egui::RichText::new(run_text).color(run_color)).clicked() {
-                self.gui_state = match self.gui_state {
-                    GuiSimulatorState::Running => GuiSimulatorState::Paused,
-                    _ => GuiSimulatorState::Running,
-                };
-            }
-            
-            // Reset button
-            if ui.button(egui::RichText::new("⏹ Reset").color(egui::Color32::RED)).clicked() {
-                self.simulator.reset();
-                self.gui_state = GuiSimulatorState::Paused;
+            if ui.button("➕ Add").clicked() {
+                let rule = self.stimulus_rule();
+                self.simulator.add_stimulus_rule(rule);
             }
-            
-            // Step 100 button - execute 100 instructions quickly
-            if ui.button("⏭ Step 100").clicked() {
-                for _ in 0..100 {
-                    let _ = self.simulator.step();
-                }
-                self.gui_state = GuiSimulatorState::Paused;
+            if ui.button("Clear All").clicked() {
+                self.simulator.clear_stimulus();
             }
         });
         
-        ui.add_space(10.0);
+        let now = self.simulator.stats().cycles_elapsed;
+        let mut removed = None;
+        egui::Grid::new("stimulus_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                for (index, rule) in self.simulator.stimulus().rules().iter().enumerate() {
+                    let level = self.simulator.cpu().gpio().get_external_pin(rule.pin());
+                    let color = if level { egui::Color32::GREEN } else { egui::Color32::DARK_GRAY };
+                    ui.colored_label(color, "⏺").on_hover_text(if level { "Pin driven high" } else { "Pin driven low" });
+                    ui.label(egui::RichText::new(rule.to_string()).monospace());
+                    draw_stimulus_preview(ui, rule, now);
+                    if ui.small_button("🗑").clicked() {
+                        removed = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = removed {
+            self.simulator.remove_stimulus_rule(index);
+        }
+    }
+    
+    /// Draw the frequency counter: frequency, duty cycle and pulse widths
+    /// of a pin over its recent periods
+    fn draw_freq_panel(&mut self, ui: &mut egui::Ui) {
+        if !self.show_freq_panel {
+            return;
+        }
         
-        // Speed slider
+        ui.add_space(10.0);
         ui.horizontal(|ui| {
-            ui.label("Speed:");
-            ui.add(egui::Slider::new(&mut self.target_frequency, 1_000..=10_000_000)
-                .logarithmic(true)
-                .custom_formatter(|n, _| {
-                    if n >= 1_000_000.0 {
-                        format!("{:.1} MHz", n / 1_000_000.0)
-                    } else if n >= 1_000.0 {
-                        format!("{:.0} kHz", n / 1_000.0)
-                    } else {
-                        format!("{:.0} Hz", n)
+            ui.heading("Frequency Counter");
+            let model = self.simulator.device().model();
+            egui::ComboBox::from_id_salt("freq_pin")
+                .selected_text(model.pin_name(self.freq_pin).unwrap_or_default())
+                .width(60.0)
+                .show_ui(ui, |ui| {
+                    for pin in model.pins() {
+                        ui.selectable_value(&mut self.freq_pin, pin, model.pin_name(pin).unwrap_or_default());
                     }
-                }));
+                });
         });
         
-        // Statistics
+        let Some(measurement) = self.simulator.logic_analyzer().measure(self.freq_pin, MEASURE_PERIODS) else {
+            ui.label("No signal: needs two rising edges");
+            return;
+        };
+        let clock = self.simulator.clock();
+        let micros = |cycles: f64| clock.cycles_to_micros(1) * cycles;
+        
+        // A pin that stopped toggling keeps its last reading; flag it
+        let now = self.simulator.stats().cycles_elapsed;
+        let stale = (now - measurement.last_edge) as f64 > 2.0 * measurement.period;
+        let frequency = format_hz(measurement.frequency(clock.cycles_per_second()));
+        let text = egui::RichText::new(frequency).monospace().size(20.0);
+        if stale {
+            ui.label(text.weak()).on_hover_text("No rising edge for more than two periods");
+        } else {
+            ui.label(text.strong());
+        }
+        egui::Grid::new("freq_grid").show(ui, |ui| {
+            ui.label("Period");
+            ui.label(format!("{:.2} µs ({:.1} cycles)", micros(measurement.period), measurement.period));
+            ui.end_row();
+            ui.label("Duty cycle");
+            ui.label(format!("{:.1}%", measurement.duty_cycle * 100.0));
+            ui.end_row();
+            ui.label("High pulse");
+            ui.label(format!("{:.2} µs", micros(measurement.high_width)));
+            ui.end_row();
+            ui.label("Low pulse");
+            ui.label(format!("{:.2} µs", micros(measurement.low_width)));
+            ui.end_row();
+        });
+        ui.label(egui::RichText::new(format!("Average of {} periods", measurement.periods)).weak());
+    }
+    
+    /// Stimulus rule described by the stimulus panel entry
+    fn stimulus_rule(&self) -> StimulusRule {
+        let form = &self.stimulus_form;
+        let clock = self.simulator.clock();
+        let cycles = |value: f64| {
+            let cycles = if form.micros { value * clock.cycles_per_second() / 1_000_000.0 } else { value };
+            cycles.round().max(0.0) as u64
+        };
+        let now = self.simulator.stats().cycles_elapsed;
+        match form.kind {
+            StimulusKind::Constant => StimulusRule::Set { pin: form.pin, level: form.high, at: now, duration: None },
+            StimulusKind::SquareWave => StimulusRule::Toggle {
+                pin: form.pin,
+                period: (cycles(form.period) / 2).max(1),
+                start: now,
+            },
+            StimulusKind::Pulse => StimulusRule::Set {
+                pin: form.pin,
+                level: form.high,
+                at: cycles(form.at),
+                duration: Some(cycles(form.width).max(1)),
+            },
+        }
+    }
+    
+    /// Draw attached external components
+    fn draw_components_panel(&mut self, ui: &mut egui::Ui) {
+        if self.simulator.components().is_empty() {
+            return;
+        }
+        
+        ui.heading("Components");
         ui.add_space(5.0);
-        ui.label(format!("Instructions: {}", self.simulator.stats().instructions_executed));
-        ui.label(format!("Cycles: {}", self.simulator.stats().cycles_elapsed));
         
-        if self.gui_state == GuiSimulatorState::Running {
-            ui.label(format!("Actual: {:.0} Hz", self.actual_frequency));
+        for (_, component) in self.simulator.components_mut().iter_mut() {
+            component.ui(ui);
         }
     }
     
-    /// Draw memory viewer panel
-    fn draw_memory_viewer(&mut self, ui: &mut egui::Ui) {
-        if !self.show_memory_viewer {
+    /// Parse a program address given as a label or hex number
+    fn parse_code_address(&self, s: &str) -> Option<u16> {
+        let s = s.trim();
+        self.simulator.symbols().address_of(s).or_else(|| {
+            let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+            u16::from_str_radix(hex, 16).ok()
+        })
+    }
+    
+    /// Draw profiler panel with a sortable hot-spot table
+    fn draw_profiler_panel(&mut self, ui: &mut egui::Ui) {
+        if !self.show_profiler_panel {
             return;
         }
         
-        ui.heading("Memory Viewer");
+        ui.heading("Profiler");
         ui.add_space(5.0);
         
-        // Address input
         ui.horizontal(|ui| {
-            ui.label("Start Address:");
-            ui.add(egui::DragValue::new(&mut self.memory_view_address)
-                .prefix("0x")
-                .hexadecimal(2, false, true));
+            let mut enabled = self.simulator.profiler().is_enabled();
+            if ui.checkbox(&mut enabled, "Enabled").changed() {
+                self.simulator.profiler_mut().set_enabled(enabled);
+            }
+            if ui.button("Clear").clicked() {
+                self.simulator.profiler_mut().clear();
+            }
         });
         
+        let profiler = self.simulator.profiler();
+        ui.label(format!("Total cycles: {}", profiler.total_cycles()));
         ui.add_space(5.0);
         
-        // Display memory in hex dump format
-        egui::ScrollArea::vertical()
-            .max_height(200.0)
+        let mut spots = profiler.hot_spots();
+        match self.profile_sort {
+            ProfileSortColumn::Address => spots.sort_by_key(|s| s.address),
+            ProfileSortColumn::Hits => spots.sort_by_key(|s| s.hits),
+            ProfileSortColumn::Cycles => spots.sort_by_key(|s| s.cycles),
+        }
+        if self.profile_sort_descending {
+            spots.reverse();
+        }
+        
+        let mut clicked = None;
+        egui::Grid::new("profiler_grid")
+            .striped(true)
             .show(ui, |ui| {
-                ui.horizontal(|ui| {
-                    ui.label("Addr");
-                    ui.label("  +0  +1  +2  +3  +4  +5  +6  +7");
-                });
-                ui.separator();
+                for (column, title) in [
+                    (ProfileSortColumn::Address, "Address"),
+                    (ProfileSortColumn::Hits, "Hits"),
+                    (ProfileSortColumn::Cycles, "Cycles"),
+                ] {
+                    let label = if self.profile_sort != column {
+                        title.to_string()
+                    } else if self.profile_sort_descending {
+                        format!("{} ⏷", title)
+                    } else {
+                        format!("{} ⏶", title)
+                    };
+                    if ui.selectable_label(self.profile_sort == column, label).clicked() {
+                        clicked = Some(column);
+                    }
+                }
+                ui.label("%");
+                ui.end_row();
                 
-                for row in 0..8 {
-                    let addr = self.memory_view_address.saturating_add(row * 8);
-                    ui.horizontal(|ui| {
-                        ui.label(format!("0x{:02X}", addr));
-                        for col in 0..8 {
-                            let byte_addr = addr.saturating_add(col);
-                            let value = self.simulator.cpu().read_register(byte_addr);
-                            ui.label(format!(" {:02X}", value));
-                        }
-                    });
+                for spot in spots.iter().take(50) {
+                    ui.label(egui::RichText::new(format!("0x{:04X}", spot.address)).monospace());
+                    ui.label(format!("{}", spot.hits));
+                    ui.label(format!("{}", spot.cycles));
+                    ui.label(format!("{:.1}", profiler.percent(spot.cycles)));
+                    ui.end_row();
                 }
             });
-    }
-    
-    /// Draw timer panel (TMR0, TMR1)
-    fn draw_timer_panel(&self, ui: &mut egui::Ui) {
-        if !self.show_timer_panel {
-            return;
-        }
-        
-        ui.heading("Timers");
-        ui.add_space(5.0);
-        
-        // Timer0
-        let tmr0 = self.simulator.cpu().read_register(registers::TMR0);
-        ui.label(format!("TMR0: 0x{:02X} ({})", tmr0, tmr0));
-        
-        // Timer1
-        let tmr1l = self.simulator.cpu().read_register(registers::TMR1L);
-        let tmr1h = self.simulator.cpu().read_register(registers::TMR1H);
-        let tmr1 = ((tmr1h as u16) << 8) | (tmr1l as u16);
-        ui.label(format!("TMR1: 0x{:04X} ({})", tmr1, tmr1));
         
-        // T1CON register
-        let t1con = self.simulator.cpu().read_register(registers::T1CON);
-        ui.label(format!("T1CON: 0b{:08b}", t1con));
+        // Clicking the active column flips the order
+        if let Some(column) = clicked {
+            if self.profile_sort == column {
+                self.profile_sort_descending = !self.profile_sort_descending;
+            } else {
+                self.profile_sort = column;
+                self.profile_sort_descending = column != ProfileSortColumn::Address;
+            }
+        }
     }
 }
 
 impl eframe::App for SimulatorApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Err(e) = self.session().save() {
+            tracing::warn!(target: "pic_simulator::gui", "Failed to save session: {}", e);
+        }
+    }
+    
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let Some(rect) = ctx.input(|i| i.viewport().inner_rect) {
+            self.window_size = Some([rect.width(), rect.height()]);
+        }
+        
         // Execute simulator when running
+        let now = std::time::Instant::now();
+        let frame_time = now.duration_since(self.last_update_time).as_secs_f64();
+        self.last_update_time = now;
         if self.gui_state == GuiSimulatorState::Running {
-                let gpio = self.simulator.cpu().gpio().read_gpio();
-                if gpio != self.last_gpio {
-                    println!("GPIO changed: 0b{:06b}", gpio);
-                    self.last_gpio = gpio;
-                }
-            let fps = 60.0;
-            let cycles_per_frame = (self.target_frequency as f32 / fps).max(1.0) as u32;
+            // Earn cycles for the wall time since the last frame; a stalled
+            // window does not make the simulation race to catch up
+            let frame_time = frame_time.min(MAX_FRAME_TIME);
+            self.cycle_budget += self.target_frequency as f64 * frame_time;
+            let start_cycles = self.simulator.stats().cycles_elapsed;
             
-            for _ in 0..cycles_per_frame {
-                if let Err(e) = self.simulator.step() {
-                    eprintln!("Error: {}", e);
+            // Instructions taking two cycles may overdraw the budget; the
+            // next frame pays it back
+            while self.cycle_budget >= 1.0 {
+                match self.simulator.step() {
+                    Ok(cycles) => self.cycle_budget -= f64::from(cycles),
+                    Err(e) => {
+                        self.execution_failed(e);
+                        break;
+                    }
+                }
+                if let Some(hit) = self.simulator.watch_hit() {
+                    self.log(LogLevel::Info, format!("Watchpoint: {}", hit));
+                    self.gui_state = GuiSimulatorState::Paused;
+                    break;
+                }
+                if let Some(reading) = self.simulator.stopwatch_hit() {
+                    self.log(LogLevel::Info, format!("Stopwatch: {} cycles", reading.cycles));
+                    self.gui_state = GuiSimulatorState::Paused;
+                    break;
+                }
+                // Stop before executing a breakpoint; Run resumes past it
+                let pc = self.simulator.cpu().get_pc();
+                if self.simulator.check_breakpoint(pc) {
+                    self.log(LogLevel::Info, format!("Breakpoint at 0x{:04X}", pc));
                     self.gui_state = GuiSimulatorState::Paused;
+                    self.scroll_to_pc = true;
                     break;
                 }
             }
             
-            self.instructions_this_second += cycles_per_frame as u64;
             
             // Update actual frequency measurement
-            let elapsed = self.last_update_time.elapsed().as_secs_f32();
-            if elapsed > 0.0 {
-                self.actual_frequency = cycles_per_frame as f32 / elapsed;
-                self.last_update_time = std::time::Instant::now();
+            self.rate_window_cycles += self.simulator.stats().cycles_elapsed - start_cycles;
+            let elapsed = self.rate_window_start.elapsed().as_secs_f64();
+            if elapsed >= RATE_WINDOW {
+                self.actual_frequency = (self.rate_window_cycles as f64 / elapsed) as f32;
+                self.rate_window_start = now;
+                self.rate_window_cycles = 0;
             }
             
             // Request continuous repaint
             ctx.request_repaint();
         }
+        if self.gui_state != GuiSimulatorState::Running {
+            // Start afresh when execution resumes
+            self.cycle_budget = 0.0;
+            self.rate_window_start = now;
+            self.rate_window_cycles = 0;
+        }
+        
+        // Report events from this frame's execution
+        let events: Vec<SimEvent> = self.events.try_iter().collect();
+        for event in events {
+            match event {
+                SimEvent::GpioChanged { new, .. } => {
+                    self.log(LogLevel::Debug, format!("GPIO changed: 0b{:06b}", new));
+                }
+                SimEvent::WdtReset { cycle, pc } => {
+                    self.log(LogLevel::Warn, format!("WDT timeout at 0x{:04X} - CPU reset", pc));
+                    if self.wdt_resets.len() == MAX_WDT_RESETS {
+                        self.wdt_resets.pop_front();
+                    }
+                    self.wdt_resets.push_back((cycle, pc));
+                }
+                SimEvent::SleepEntered { pc } => self.log(LogLevel::Debug, format!("SLEEP at 0x{:04X}", pc)),
+                SimEvent::Woke { by_interrupt } => {
+                    let cause = if by_interrupt { "interrupt" } else { "WDT timeout" };
+                    self.log(LogLevel::Debug, format!("Woke from sleep by {}", cause));
+                }
+                _ => {}
+            }
+        }
         
         // Get current simulator state
         let pc = self.simulator.cpu().get_pc();
         let w = self.simulator.cpu().read_w();
         let status = self.simulator.cpu().read_register(registers::STATUS);
-        let gpio = self.simulator.cpu().gpio().read_gpio();
-        let trisio = self.simulator.cpu().gpio().read_tris();
         let cycles = self.simulator.stats().cycles_elapsed;
+        let elapsed_ms = self.simulator.elapsed_time().as_secs_f64() * 1000.0;
+        
+        if self.error_dialog.is_none() {
+            self.handle_shortcuts(ctx);
+        }
+        self.load_dropped_files(ctx);
         
         // ==================== Draw UI ====================
         
@@ -456,17 +2743,73 @@ impl eframe::App for SimulatorApp {
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
-                    if ui.button("📂 Load HEX File...").clicked() {
-                        self.load_hex_file();
+                    if ui.add(egui::Button::new("📂 Load Firmware...").shortcut_text(ctx.format_shortcut(&OPEN_SHORTCUT))).clicked() {
+                        self.load_firmware_file();
+                        ui.close_menu();
+                    }
+                    ui.add_enabled_ui(!self.recent_files.is_empty(), |ui| {
+                        ui.menu_button("🕘 Open Recent", |ui| {
+                            let mut chosen = None;
+                            for path in &self.recent_files {
+                                let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+                                if ui.button(name).on_hover_text(path.display().to_string()).clicked() {
+                                    chosen = Some(path.clone());
+                                }
+                            }
+                            if let Some(path) = chosen {
+                                self.load_firmware_path(path);
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    if ui.button("🏷 Load Symbols...").clicked() {
+                        self.load_symbol_file();
+                        ui.close_menu();
+                    }
+                    if ui.button("📈 Load Stimulus...").clicked() {
+                        self.load_stimulus_file();
+                        ui.close_menu();
+                    }
+                    if ui.button("🔌 Load Circuit...").clicked() {
+                        self.load_circuit_file();
                         ui.close_menu();
                     }
                     if ui.button("🧪 Load Test Program").clicked() {
                         self.load_test_program();
                         ui.close_menu();
                     }
-                    if ui.button("🔄 Reset").clicked() {
-                        self.simulator.reset();
-                        self.gui_state = GuiSimulatorState::Paused;
+                    let vcd_label = if self.simulator.is_recording_vcd() {
+                        "⏹ Stop VCD Capture"
+                    } else {
+                        "⏺ Start VCD Capture..."
+                    };
+                    if ui.button(vcd_label).clicked() {
+                        self.toggle_vcd_capture();
+                        ui.close_menu();
+                    }
+                    let record_label = if self.simulator.input_recording().is_some() {
+                        "⏹ Save Input Recording..."
+                    } else {
+                        "⏺ Record Inputs"
+                    };
+                    if ui.button(record_label).clicked() {
+                        self.toggle_input_recording();
+                        ui.close_menu();
+                    }
+                    if ui.button("▶ Replay Inputs...").clicked() {
+                        self.load_replay_file();
+                        ui.close_menu();
+                    }
+                    if ui.button("💾 Save State...").clicked() {
+                        self.save_state_file();
+                        ui.close_menu();
+                    }
+                    if ui.button("📥 Load State...").clicked() {
+                        self.restore_state_file();
+                        ui.close_menu();
+                    }
+                    if ui.button("📊 Export Coverage...").clicked() {
+                        self.export_coverage();
                         ui.close_menu();
                     }
                     ui.separator();
@@ -475,10 +2818,43 @@ impl eframe::App for SimulatorApp {
                     }
                 });
                 
+                ui.menu_button("Debug", |ui| {
+                    let run_text = if self.gui_state == GuiSimulatorState::Running { "⏸ Pause" } else { "▶ Run" };
+                    let items = [
+                        (run_text, RUN_SHORTCUT, Self::toggle_run as fn(&mut Self)),
+                        ("⤵ Step Over", STEP_OVER_SHORTCUT, Self::step_over),
+                        ("⏭ Step Into", STEP_INTO_SHORTCUT, Self::step_into),
+                        ("🔄 Reset", RESET_SHORTCUT, Self::reset_simulator),
+                    ];
+                    for (text, shortcut, action) in items {
+                        if ui.add(egui::Button::new(text).shortcut_text(ctx.format_shortcut(&shortcut))).clicked() {
+                            action(self);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                
                 ui.menu_button("View", |ui| {
                     ui.checkbox(&mut self.show_memory_viewer, "Memory Viewer");
                     ui.checkbox(&mut self.show_timer_panel, "Timer Panel");
                     ui.checkbox(&mut self.show_interrupt_panel, "Interrupt Panel");
+                    ui.checkbox(&mut self.show_wdt_panel, "Watchdog Timer");
+                    ui.checkbox(&mut self.show_call_stack_panel, "Call Stack");
+                    ui.checkbox(&mut self.show_profiler_panel, "Profiler");
+                    ui.checkbox(&mut self.show_source_view, "Source View");
+                    ui.checkbox(&mut self.show_breakpoint_panel, "Breakpoints");
+                    ui.checkbox(&mut self.show_stopwatch_panel, "Stopwatch");
+                    ui.checkbox(&mut self.show_stimulus_panel, "Pin Stimulus");
+                    ui.checkbox(&mut self.show_freq_panel, "Frequency Counter");
+                    ui.checkbox(&mut self.show_trace_panel, "Instruction Trace");
+                    ui.checkbox(&mut self.show_eeprom_panel, "EEPROM");
+                    ui.checkbox(&mut self.show_watch_panel, "Watch");
+                    ui.checkbox(&mut self.show_wave_panel, "Waveforms");
+                    ui.checkbox(&mut self.show_console, "Console");
+                    ui.checkbox(&mut self.show_editor, "Assembly Editor");
+                    if ui.checkbox(&mut self.show_sfr_names, "SFR Names in Disassembly").changed() {
+                        self.update_disassembly_cache();
+                    }
                 });
             });
         });
@@ -486,14 +2862,17 @@ impl eframe::App for SimulatorApp {
         // Top status bar
         egui::TopBottomPanel::top("status_bar").show(ctx, |ui| {
             ui.horizontal(|ui| {
-                ui.label(egui::RichText::new(format!("PC: 0x{:04X}", pc)).strong());
+                self.editable_value(ui, EditTarget::Pc, egui::RichText::new(format!("PC: 0x{:04X}", pc)).strong());
                 ui.separator();
-                ui.label(format!("W: 0x{:02X}", w));
+                self.editable_value(ui, EditTarget::W, format!("W: 0x{:02X}", w));
                 ui.separator();
-                ui.label(format!("STATUS: 0b{:08b}", status));
+                self.editable_value(ui, EditTarget::Register(registers::STATUS), format!("STATUS: 0b{:08b}", status));
                 ui.separator();
                 ui.label(format!("Cycles: {}", cycles));
                 ui.separator();
+                let fosc = self.simulator.clock().fosc_hz();
+                ui.label(format!("t = {:.3} ms @ {}", elapsed_ms, format_hz(fosc as f64)));
+                ui.separator();
                 
                 // Running state indicator
                 let (state_text, state_color) = match self.gui_state {
@@ -502,10 +2881,42 @@ impl eframe::App for SimulatorApp {
                     GuiSimulatorState::Idle => ("⚪ IDLE", egui::Color32::GRAY),
                 };
                 ui.label(egui::RichText::new(state_text).color(state_color));
+                
+                if self.simulator.is_recording_vcd() {
+                    ui.separator();
+                    ui.label(egui::RichText::new("⏺ VCD").color(egui::Color32::RED));
+                }
             });
         });
         
         // Left panel: Code disassembly
+        // Bottom panels: log console, then waveforms above it
+        if self.show_console {
+            egui::TopBottomPanel::bottom("console_panel")
+                .resizable(true)
+                .default_height(120.0)
+                .show(ctx, |ui| {
+                    egui::CollapsingHeader::new("Console")
+                        .default_open(true)
+                        .show(ui, |ui| self.console.ui(ui));
+                });
+        }
+        
+        // Bottom panel: recent instructions
+        if self.show_trace_panel {
+            egui::TopBottomPanel::bottom("trace_panel")
+                .resizable(true)
+                .default_height(160.0)
+                .show(ctx, |ui| self.draw_trace_panel(ui));
+        }
+        
+        // Bottom panel: waveforms
+        if self.show_wave_panel {
+            egui::TopBottomPanel::bottom("wave_panel")
+                .resizable(false)
+                .show(ctx, |ui| self.draw_wave_panel(ui));
+        }
+        
         egui::SidePanel::left("code_panel")
             .default_width(450.0)
             .show(ctx, |ui| {
@@ -521,19 +2932,61 @@ impl eframe::App for SimulatorApp {
                     ui.add_space(10.0);
                     ui.separator();
                     ui.add_space(10.0);
+                    self.draw_watch_panel(ui);
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+                    self.draw_eeprom_panel(ui);
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
                     self.draw_timer_panel(ui);
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+                    self.draw_interrupt_panel(ui);
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+                    self.draw_wdt_panel(ui);
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+                    self.draw_call_stack_panel(ui);
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+                    self.draw_profiler_panel(ui);
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+                    self.draw_breakpoint_panel(ui);
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+                    self.draw_stopwatch_panel(ui);
                 });
             });
         
         // Center panel: GPIO & Control
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
-                self.draw_gpio_panel(ui, gpio, trisio);
+                self.draw_gpio_panel(ui);
+                self.draw_stimulus_panel(ui);
+                self.draw_freq_panel(ui);
                 ui.add_space(20.0);
                 ui.separator();
                 ui.add_space(20.0);
                 self.draw_control_panel(ui);
+                ui.add_space(20.0);
+                self.draw_components_panel(ui);
             });
         });
+        
+        if self.show_editor {
+            self.draw_editor(ctx);
+        }
+        self.draw_overlays(ctx);
+        self.draw_error_dialog(ctx);
     }
 }
\ No newline at end of file
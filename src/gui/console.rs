@@ -0,0 +1,181 @@
+//! Log console panel
+//!
+//! The GUI reports what it does and what the simulator runs into (loaded
+//! files, invalid input, WDT resets, decode errors, pin changes) as log
+//! entries tagged with a level and the cycle they happened at. The console
+//! panel lists them, filtered by minimum level and by text.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use eframe::egui;
+
+/// Entries kept before the oldest are dropped
+pub const MAX_LOG_ENTRIES: usize = 1000;
+
+/// Importance of a log entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error];
+
+    fn color(self) -> egui::Color32 {
+        match self {
+            LogLevel::Debug => egui::Color32::GRAY,
+            LogLevel::Info => egui::Color32::LIGHT_GREEN,
+            LogLevel::Warn => egui::Color32::YELLOW,
+            LogLevel::Error => egui::Color32::from_rgb(255, 100, 100),
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A logged message
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub level: LogLevel,
+    /// Simulator cycle count when the message was logged
+    pub cycle: u64,
+    pub message: String,
+}
+
+/// Log entries and the console's filter settings
+#[derive(Debug, Clone)]
+pub struct Console {
+    entries: VecDeque<LogEntry>,
+    /// Entries below this level are hidden
+    pub min_level: LogLevel,
+    /// Only entries containing this text (ignoring case) are shown
+    pub filter: String,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            min_level: LogLevel::Info,
+            filter: String::new(),
+        }
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn log(&mut self, level: LogLevel, cycle: u64, message: impl Into<String>) {
+        if self.entries.len() == MAX_LOG_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry { level, cycle, message: message.into() });
+    }
+
+    /// All entries, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+
+    /// Entries passing the level and text filters, oldest first
+    pub fn visible(&self) -> impl Iterator<Item = &LogEntry> {
+        let filter = self.filter.to_lowercase();
+        self.entries.iter().filter(move |entry| {
+            entry.level >= self.min_level
+                && (filter.is_empty() || entry.message.to_lowercase().contains(&filter))
+        })
+    }
+
+    /// Count of entries at `level`
+    pub fn count(&self, level: LogLevel) -> usize {
+        self.entries.iter().filter(|entry| entry.level == level).count()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Draw the filter controls and the entry list
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("console_level")
+                .selected_text(format!("≥ {}", self.min_level))
+                .show_ui(ui, |ui| {
+                    for level in LogLevel::ALL {
+                        ui.selectable_value(&mut self.min_level, level, level.to_string());
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut self.filter)
+                .hint_text("Filter")
+                .desired_width(150.0));
+            for level in [LogLevel::Warn, LogLevel::Error] {
+                let count = self.count(level);
+                if count > 0 {
+                    ui.colored_label(level.color(), format!("{} {}", count, level));
+                }
+            }
+            if ui.small_button("Clear").clicked() {
+                self.clear();
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .id_salt("console_entries")
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for entry in self.visible() {
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(format!("{:>10}", entry.cycle)).monospace().weak());
+                        ui.label(egui::RichText::new(format!("{:<5}", entry.level))
+                            .monospace()
+                            .color(entry.level.color()));
+                        ui.label(egui::RichText::new(&entry.message).monospace());
+                    });
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filtering() {
+        let mut console = Console::new();
+        console.log(LogLevel::Debug, 1, "GPIO changed: 0b000001");
+        console.log(LogLevel::Info, 2, "Loaded blink.hex");
+        console.log(LogLevel::Warn, 3, "WDT timeout at 0x0010 - CPU reset");
+        console.log(LogLevel::Error, 4, "Invalid opcode at 0x0020");
+
+        let messages = |console: &Console| console.visible().map(|e| e.cycle).collect::<Vec<_>>();
+        assert_eq!(messages(&console), vec![2, 3, 4]);
+        console.min_level = LogLevel::Debug;
+        console.filter = "0X00".to_string();
+        assert_eq!(messages(&console), vec![3, 4]);
+        assert_eq!(console.count(LogLevel::Warn), 1);
+
+        for cycle in 0..MAX_LOG_ENTRIES as u64 {
+            console.log(LogLevel::Debug, 10 + cycle, "tick");
+        }
+        assert_eq!(console.entries().count(), MAX_LOG_ENTRIES);
+        assert_eq!(console.entries().next().map(|e| e.cycle), Some(10));
+    }
+}
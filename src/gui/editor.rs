@@ -0,0 +1,195 @@
+//! Assembly editor panel
+//!
+//! A plain-text editor for PIC assembly with line numbers. The app
+//! assembles and loads the text when asked and hands any assembler errors
+//! back; the editor marks their lines and lists them under the text.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use eframe::egui;
+
+use crate::error::AsmError;
+
+/// Program the editor starts with
+const EXAMPLE: &str = "\
+; Blink an LED on GP2
+        LIST    P=12F629
+
+        CBLOCK  0x20
+count
+        ENDC
+
+        ORG     0
+start   BANKSEL TRISIO
+        bcf     TRISIO, GP2     ; GP2 is an output
+        BANKSEL GPIO
+loop    movlw   B'00000100'
+        xorwf   GPIO, F         ; toggle GP2
+        call    delay
+        goto    loop
+
+delay   clrf    count
+wait    decfsz  count, F
+        goto    wait
+        return
+
+        END
+";
+
+/// Something the user asked the app to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorAction {
+    Assemble,
+    Open,
+    Save,
+}
+
+/// Editor text, its file and the last assembler errors
+#[derive(Debug, Clone)]
+pub struct Editor {
+    pub source: String,
+    /// File the text was opened from or saved to
+    pub path: Option<PathBuf>,
+    errors: Vec<AsmError>,
+    /// Changed since opened or saved
+    modified: bool,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            source: EXAMPLE.to_string(),
+            path: None,
+            errors: Vec::new(),
+            modified: false,
+        }
+    }
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name the source is assembled under
+    pub fn file_name(&self) -> String {
+        self.path.as_ref()
+            .and_then(|path| path.file_name())
+            .map_or("untitled.asm".to_string(), |name| name.to_string_lossy().into_owned())
+    }
+
+    pub fn open(&mut self, path: &Path) -> Result<(), String> {
+        self.source = fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        self.path = Some(path.to_path_buf());
+        self.errors.clear();
+        self.modified = false;
+        Ok(())
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<(), String> {
+        fs::write(path, &self.source).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+        self.path = Some(path.to_path_buf());
+        self.modified = false;
+        Ok(())
+    }
+
+    pub fn errors(&self) -> &[AsmError] {
+        &self.errors
+    }
+
+    pub fn set_errors(&mut self, errors: Vec<AsmError>) {
+        self.errors = errors;
+    }
+
+    /// Draw the toolbar, text and errors
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<EditorAction> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            if ui.button("▶ Assemble & Load").clicked() {
+                action = Some(EditorAction::Assemble);
+            }
+            if ui.button("📂 Open...").clicked() {
+                action = Some(EditorAction::Open);
+            }
+            if ui.button("💾 Save...").clicked() {
+                action = Some(EditorAction::Save);
+            }
+            let modified = if self.modified { " •" } else { "" };
+            ui.label(format!("{}{}", self.file_name(), modified));
+        });
+        ui.separator();
+
+        let error_lines: Vec<usize> = self.errors.iter().map(|e| e.line).collect();
+        let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| -> Arc<egui::Galley> {
+            let mut job = egui::text::LayoutJob::default();
+            let font = egui::TextStyle::Monospace.resolve(ui.style());
+            for (index, line) in text.split_inclusive('\n').enumerate() {
+                let (code, comment) = line.split_at(line.find(';').unwrap_or(line.len()));
+                let background = if error_lines.contains(&(index + 1)) {
+                    egui::Color32::from_rgb(90, 30, 30)
+                } else {
+                    egui::Color32::TRANSPARENT
+                };
+                for (part, color) in [(code, ui.visuals().text_color()), (comment, egui::Color32::GRAY)] {
+                    job.append(part, 0.0, egui::TextFormat {
+                        font_id: font.clone(),
+                        color,
+                        background,
+                        ..Default::default()
+                    });
+                }
+            }
+            job.wrap.max_width = wrap_width;
+            ui.fonts(|fonts| fonts.layout_job(job))
+        };
+
+        let error_height = if self.errors.is_empty() { 0.0 } else { 80.0 };
+        egui::ScrollArea::vertical()
+            .id_salt("editor_text")
+            .max_height(ui.available_height() - error_height)
+            .show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    let lines = self.source.lines().count().max(1);
+                    let numbers: String = (1..=lines).map(|n| format!("{:>4}\n", n)).collect();
+                    ui.label(egui::RichText::new(numbers.trim_end()).monospace().weak());
+                    let response = ui.add(egui::TextEdit::multiline(&mut self.source)
+                        .code_editor()
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(24)
+                        .layouter(&mut layouter));
+                    if response.changed() {
+                        self.modified = true;
+                    }
+                });
+            });
+
+        if !self.errors.is_empty() {
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .id_salt("editor_errors")
+                .max_height(error_height)
+                .show(ui, |ui| {
+                    for error in &self.errors {
+                        ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error.to_string());
+                    }
+                });
+        }
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler;
+
+    #[test]
+    fn test_example_assembles() {
+        let editor = Editor::new();
+        let assembly = assembler::assemble(&editor.source, &editor.file_name()).unwrap();
+        assert_eq!(assembly.symbols.address_of("loop"), Some(3));
+        assert_eq!(editor.file_name(), "untitled.asm");
+    }
+}
@@ -1,2 +1,8 @@
 pub mod app;
-pub use app::SimulatorApp;
\ No newline at end of file
+pub mod console;
+pub mod editor;
+pub mod session;
+pub use app::SimulatorApp;
+pub use console::{Console, LogEntry, LogLevel};
+pub use editor::{Editor, EditorAction};
+pub use session::Session;
@@ -0,0 +1,160 @@
+//! GUI session persistence
+//!
+//! The GUI saves its layout and debugging state to `session.txt` in the
+//! user's config directory on exit and restores it on the next start.
+//! The file holds one `key = value` setting per line; `recent` and
+//! `breakpoint` lines repeat.
+//!
+//! ```text
+//! window = 1280x720
+//! frequency = 10
+//! memory_view = 0x20
+//! panel.timer = true
+//! recent = /home/me/blink.hex
+//! breakpoint = 0x0010 on [0x20] == 3
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::breakpoint::SavedBreakpoint;
+
+/// Recent files remembered
+pub const MAX_RECENT_FILES: usize = 8;
+
+/// Saved GUI state
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    pub window_size: Option<[f32; 2]>,
+    pub target_frequency: Option<u32>,
+    pub memory_view_address: Option<u8>,
+    /// Panel visibility by panel name
+    pub panels: BTreeMap<String, bool>,
+    /// Most recent first
+    pub recent_files: Vec<PathBuf>,
+    pub breakpoints: Vec<SavedBreakpoint>,
+}
+
+impl Session {
+    /// Path of the session file, if a config directory is known
+    pub fn path() -> Option<PathBuf> {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config.join("pic_simulator").join("session.txt"))
+    }
+
+    /// Load the saved session; a missing or unreadable file gives an empty one
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    /// Write the session file
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+        }
+        fs::write(&path, self.to_text()).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+    }
+
+    /// Parse a session file, skipping lines it does not understand
+    pub fn parse(text: &str) -> Self {
+        let mut session = Self::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "window" => {
+                    session.window_size = value.split_once('x')
+                        .and_then(|(w, h)| Some([w.parse().ok()?, h.parse().ok()?]));
+                }
+                "frequency" => session.target_frequency = value.parse().ok(),
+                "memory_view" => {
+                    session.memory_view_address = value.strip_prefix("0x")
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                }
+                "recent" => session.recent_files.push(PathBuf::from(value)),
+                "breakpoint" => session.breakpoints.extend(SavedBreakpoint::parse(value).ok()),
+                _ => {
+                    if let Some(panel) = key.strip_prefix("panel.")
+                        && let Ok(visible) = value.parse()
+                    {
+                        session.panels.insert(panel.to_string(), visible);
+                    }
+                }
+            }
+        }
+        session.recent_files.truncate(MAX_RECENT_FILES);
+        session
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some([width, height]) = self.window_size {
+            lines.push(format!("window = {}x{}", width.round(), height.round()));
+        }
+        if let Some(frequency) = self.target_frequency {
+            lines.push(format!("frequency = {}", frequency));
+        }
+        if let Some(address) = self.memory_view_address {
+            lines.push(format!("memory_view = 0x{:02X}", address));
+        }
+        for (panel, visible) in &self.panels {
+            lines.push(format!("panel.{} = {}", panel, visible));
+        }
+        for path in &self.recent_files {
+            lines.push(format!("recent = {}", path.display()));
+        }
+        for bp in &self.breakpoints {
+            lines.push(format!("breakpoint = {}", bp));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Move `path` to the front of the recent files
+    pub fn remember_file(recent_files: &mut Vec<PathBuf>, path: PathBuf) {
+        recent_files.retain(|recent| *recent != path);
+        recent_files.insert(0, path);
+        recent_files.truncate(MAX_RECENT_FILES);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut session = Session {
+            window_size: Some([1024.0, 768.0]),
+            target_frequency: Some(1000),
+            memory_view_address: Some(0x48),
+            ..Session::default()
+        };
+        session.panels.insert("timer".to_string(), false);
+        for name in ["a.hex", "b.hex", "a.hex"] {
+            Session::remember_file(&mut session.recent_files, PathBuf::from(name));
+        }
+        session.breakpoints.push(SavedBreakpoint { location: "0x0010".to_string(), enabled: false, condition: None });
+        session.breakpoints.push(SavedBreakpoint {
+            location: "0x002A".to_string(),
+            enabled: true,
+            condition: Some("[0x20] == 3".to_string()),
+        });
+
+        let text = session.to_text();
+        assert!(text.contains("breakpoint = 0x002A on [0x20] == 3\n"));
+        assert_eq!(session.recent_files, vec![PathBuf::from("a.hex"), PathBuf::from("b.hex")]);
+        assert_eq!(Session::parse(&text), session);
+        assert_eq!(Session::parse("window = big\nnonsense\n"), Session::default());
+    }
+}
@@ -0,0 +1,114 @@
+//! Halt conditions
+//!
+//! Several firmware bugs don't stop a real PIC: a SLEEP nothing can wake
+//! from, a watchdog reset, a hardware stack that wraps or a write to an
+//! unimplemented register. `HaltPolicy` selects which of these halt the
+//! simulator instead, so automated runs fail loudly at the faulty
+//! instruction rather than carrying on with corrupted state.
+
+use std::fmt;
+
+use crate::error::HaltConditionError;
+
+/// A condition that can halt execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaltReason {
+    /// SLEEP with the WDT disabled and no interrupt enabled to wake the CPU
+    SleepForever,
+    /// The watchdog timed out while running
+    WdtTimeout,
+    /// CALL or interrupt entry with all 8 stack levels in use
+    StackOverflow,
+    /// RETURN, RETLW or RETFIE with an empty stack
+    StackUnderflow,
+    /// Write to an address with no register or RAM behind it
+    UnimplementedWrite { address: u16 },
+}
+
+impl fmt::Display for HaltReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HaltReason::SleepForever => write!(f, "SLEEP with no wake source"),
+            HaltReason::WdtTimeout => write!(f, "WDT timeout"),
+            HaltReason::StackOverflow => write!(f, "stack overflow"),
+            HaltReason::StackUnderflow => write!(f, "stack underflow"),
+            HaltReason::UnimplementedWrite { address } => {
+                write!(f, "write to unimplemented address 0x{:02X}", address)
+            }
+        }
+    }
+}
+
+/// Which conditions halt the simulator
+///
+/// The default matches the hardware except for unknown opcodes, which
+/// always stopped the simulator: SLEEP waits forever, the WDT resets the
+/// CPU, the stack wraps around and unimplemented writes are ignored. With
+/// `unknown_opcode` off an invalid instruction word executes as a NOP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HaltPolicy {
+    pub sleep_forever: bool,
+    pub wdt_reset: bool,
+    pub stack_overflow: bool,
+    pub stack_underflow: bool,
+    pub unimplemented_write: bool,
+    pub unknown_opcode: bool,
+}
+
+impl Default for HaltPolicy {
+    fn default() -> Self {
+        Self {
+            sleep_forever: false,
+            wdt_reset: false,
+            stack_overflow: false,
+            stack_underflow: false,
+            unimplemented_write: false,
+            unknown_opcode: true,
+        }
+    }
+}
+
+impl HaltPolicy {
+    /// Condition names, as used by `get` and `set`
+    pub const NAMES: [&'static str; 6] = [
+        "sleep", "wdt", "overflow", "underflow", "unimplemented", "opcode",
+    ];
+
+    /// Halt on every condition, for automated runs
+    pub fn strict() -> Self {
+        Self {
+            sleep_forever: true,
+            wdt_reset: true,
+            stack_overflow: true,
+            stack_underflow: true,
+            unimplemented_write: true,
+            unknown_opcode: true,
+        }
+    }
+
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "sleep" => Some(&mut self.sleep_forever),
+            "wdt" => Some(&mut self.wdt_reset),
+            "overflow" => Some(&mut self.stack_overflow),
+            "underflow" => Some(&mut self.stack_underflow),
+            "unimplemented" => Some(&mut self.unimplemented_write),
+            "opcode" => Some(&mut self.unknown_opcode),
+            _ => None,
+        }
+    }
+
+    /// Whether the condition called `name` halts
+    pub fn get(&self, name: &str) -> Option<bool> {
+        let mut policy = *self;
+        policy.flag_mut(name).map(|flag| *flag)
+    }
+
+    /// Enable or disable the condition called `name`
+    pub fn set(&mut self, name: &str, enabled: bool) -> Result<(), HaltConditionError> {
+        let flag = self.flag_mut(name)
+            .ok_or_else(|| HaltConditionError { name: name.to_string() })?;
+        *flag = enabled;
+        Ok(())
+    }
+}
@@ -1,20 +1,30 @@
-/// Intel HEX file format loader
-/// 
-/// Reference: Intel HEX format specification
-/// 
-/// Intel HEX format consists of ASCII text lines with the format:
-/// :LLAAAATT[DD...]CC
-/// 
-/// LL = byte count (number of data bytes)
-/// AAAA = address (16-bit)
-/// TT = record type (00=data, 01=EOF, 04=extended address, etc.)
-/// DD = data bytes
-/// CC = checksum
+//! Intel HEX file format loader and writer
+//! 
+//! Reference: Intel HEX format specification
+//! 
+//! Intel HEX format consists of ASCII text lines with the format:
+//! :LLAAAATT[DD...]CC
+//! 
+//! LL = byte count (number of data bytes)
+//! AAAA = address (16-bit)
+//! TT = record type (00=data, 01=EOF, 04=extended address, etc.)
+//! DD = data bytes
+//! CC = checksum
+//!
+//! Addresses in the file are byte addresses, so each 14-bit word at word
+//! address N occupies bytes 2N (low) and 2N+1 (high). This puts the user
+//! IDs (words 0x2000-0x2003) at 0x4000, the configuration word (0x2007) at
+//! 0x400E and the EEPROM (0x2100) at 0x4200, where MPLAB stores one EEPROM
+//! byte per word with a zero filler byte.
 
+use std::collections::HashSet;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 
+use crate::error::HexError;
+
 /// Record types in Intel HEX format
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RecordType {
@@ -51,47 +61,71 @@ pub struct HexRecord {
 }
 
 impl HexRecord {
+    /// Create a record, computing its byte count and checksum
+    pub fn new(address: u16, record_type: RecordType, data: Vec<u8>) -> Self {
+        let byte_count = data.len() as u8;
+        let mut bytes = vec![byte_count, (address >> 8) as u8, address as u8, record_type as u8];
+        bytes.extend_from_slice(&data);
+        let checksum = Self::calculate_checksum(&bytes);
+        HexRecord {
+            byte_count,
+            address,
+            record_type,
+            data,
+            checksum,
+        }
+    }
+    
     /// Parse a single line of HEX format
-    pub fn parse(line: &str) -> Result<Self, String> {
+    pub fn parse(line: &str) -> Result<Self, HexError> {
+        Self::parse_checked(line, true)
+    }
+    
+    /// Parse a line, optionally accepting a wrong checksum
+    fn parse_checked(line: &str, verify_checksum: bool) -> Result<Self, HexError> {
         let line = line.trim();
         
         // Must start with ':'
         if !line.starts_with(':') {
-            return Err("HEX line must start with ':'".to_string());
+            return Err(HexError::MissingStartCode);
         }
         
         // Remove the ':' prefix
         let line = &line[1..];
         
         // Must have even number of hex digits
-        if line.len() % 2 != 0 {
-            return Err("HEX line must have even number of characters".to_string());
+        if !line.len().is_multiple_of(2) {
+            return Err(HexError::OddLength);
         }
         
         // Parse bytes
         let mut bytes = Vec::new();
-        for i in (0..line.len()).step_by(2) {
-            let byte_str = &line[i..i+2];
-            let byte = u8::from_str_radix(byte_str, 16)
-                .map_err(|_| format!("Invalid hex byte: {}", byte_str))?;
+        for pair in line.as_bytes().chunks(2) {
+            let byte = std::str::from_utf8(pair)
+                .ok()
+                .and_then(|byte_str| u8::from_str_radix(byte_str, 16).ok())
+                .ok_or_else(|| HexError::InvalidByte(String::from_utf8_lossy(pair).into_owned()))?;
             bytes.push(byte);
         }
         
         // Must have at least 5 bytes (count, addr_hi, addr_lo, type, checksum)
         if bytes.len() < 5 {
-            return Err("HEX line too short".to_string());
+            return Err(HexError::TooShort);
         }
         
         let byte_count = bytes[0];
         let address = ((bytes[1] as u16) << 8) | (bytes[2] as u16);
         let record_type = RecordType::from_u8(bytes[3])
-            .ok_or_else(|| format!("Invalid record type: 0x{:02X}", bytes[3]))?;
+            .ok_or(HexError::InvalidRecordType(bytes[3]))?;
         
         // Data bytes
         let data_end = 4 + byte_count as usize;
         if bytes.len() != data_end + 1 {
-            return Err(format!("Byte count mismatch: expected {}, got {}", 
-                byte_count, bytes.len() - 5));
+            return Err(HexError::ByteCountMismatch {
+                address,
+                expected: byte_count,
+                actual: bytes.len() - 5,
+            });
         }
         
         let data = bytes[4..data_end].to_vec();
@@ -99,9 +133,12 @@ impl HexRecord {
         
         // Verify checksum
         let calculated_checksum = Self::calculate_checksum(&bytes[0..data_end]);
-        if calculated_checksum != checksum {
-            return Err(format!("Checksum mismatch: expected 0x{:02X}, got 0x{:02X}",
-                calculated_checksum, checksum));
+        if verify_checksum && calculated_checksum != checksum {
+            return Err(HexError::ChecksumMismatch {
+                address,
+                expected: calculated_checksum,
+                actual: checksum,
+            });
         }
         
         Ok(HexRecord {
@@ -120,6 +157,17 @@ impl HexRecord {
     }
 }
 
+impl fmt::Display for HexRecord {
+    /// Format the record as a line of HEX, without the line ending
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ":{:02X}{:04X}{:02X}", self.byte_count, self.address, self.record_type as u8)?;
+        for byte in &self.data {
+            write!(f, "{:02X}", byte)?;
+        }
+        write!(f, "{:02X}", self.checksum)
+    }
+}
+
 /// Loaded program data
 #[derive(Debug, Clone)]
 pub struct HexProgram {
@@ -132,46 +180,341 @@ pub struct HexProgram {
     /// Configuration word (if present)
     pub config: Option<u16>,
     
-    /// Start address
-    pub start_address: u16,
+    /// User ID locations 0x2000-0x2003 (if present); unprogrammed IDs read 0x3FFF
+    pub id_locations: Option<[u16; 4]>,
+    
+    /// Start address given by the file (S-record termination record, ELF
+    /// entry point); execution starts at the reset vector without one
+    pub start_address: Option<u16>,
+    
+    /// Diagnostics collected while loading
+    pub report: LoadReport,
+}
+
+impl HexProgram {
+    /// Compare with `other`, taking `self` as the expected contents
+    ///
+    /// Program words and EEPROM bytes missing from the shorter side count
+    /// as erased (0x3FFF and 0xFF), so trailing blank memory is not reported.
+    pub fn diff(&self, other: &HexProgram) -> Vec<Difference> {
+        let mut differences = Vec::new();
+        
+        let words = self.program.len().max(other.program.len());
+        for address in 0..words {
+            let expected = self.program.get(address).copied().unwrap_or(ERASED_WORD);
+            let actual = other.program.get(address).copied().unwrap_or(ERASED_WORD);
+            if expected != actual {
+                differences.push(Difference::Program { address: address as u16, expected, actual });
+            }
+        }
+        
+        if self.config != other.config {
+            differences.push(Difference::Config { expected: self.config, actual: other.config });
+        }
+        
+        let erased_ids = [ERASED_WORD; 4];
+        let expected_ids = self.id_locations.unwrap_or(erased_ids);
+        let actual_ids = other.id_locations.unwrap_or(erased_ids);
+        for (index, (&expected, &actual)) in expected_ids.iter().zip(&actual_ids).enumerate() {
+            if expected != actual {
+                differences.push(Difference::IdLocation { index, expected, actual });
+            }
+        }
+        
+        let bytes = self.eeprom.len().max(other.eeprom.len());
+        for address in 0..bytes {
+            let expected = self.eeprom.get(address).copied().unwrap_or(0xFF);
+            let actual = other.eeprom.get(address).copied().unwrap_or(0xFF);
+            if expected != actual {
+                differences.push(Difference::Eeprom { address: address as u16, expected, actual });
+            }
+        }
+        
+        differences
+    }
+}
+
+/// A mismatch found by `HexProgram::diff`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difference {
+    /// Program memory word at `address`
+    Program { address: u16, expected: u16, actual: u16 },
+    
+    /// Configuration word, which either side may lack
+    Config { expected: Option<u16>, actual: Option<u16> },
+    
+    /// User ID location `index` (0-3)
+    IdLocation { index: usize, expected: u16, actual: u16 },
+    
+    /// EEPROM byte at `address`
+    Eeprom { address: u16, expected: u8, actual: u8 },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let word = |value: Option<u16>| value.map_or("none".to_string(), |v| format!("0x{:04X}", v));
+        match self {
+            Difference::Program { address, expected, actual } => {
+                write!(f, "Program 0x{:04X}: expected 0x{:04X}, found 0x{:04X}", address, expected, actual)
+            }
+            Difference::Config { expected, actual } => {
+                write!(f, "Config: expected {}, found {}", word(*expected), word(*actual))
+            }
+            Difference::IdLocation { index, expected, actual } => {
+                write!(f, "ID {}: expected 0x{:04X}, found 0x{:04X}", index, expected, actual)
+            }
+            Difference::Eeprom { address, expected, actual } => {
+                write!(f, "EEPROM 0x{:02X}: expected 0x{:02X}, found 0x{:02X}", address, expected, actual)
+            }
+        }
+    }
+}
+
+/// A problem in a HEX file that did not stop it loading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadWarning {
+    /// A record rewrote bytes set by an earlier record, starting at `address`
+    Overlap { line: usize, address: u32 },
+    
+    /// A record held program words past the end of program memory, starting at `word`
+    BeyondProgramMemory { line: usize, word: u32 },
+    
+    /// A record held data outside program memory, the IDs, config and EEPROM
+    UnknownRegion { line: usize, address: u32 },
+    
+    /// A record had a wrong checksum; its data was loaded anyway
+    ChecksumMismatch { line: usize, expected: u8, actual: u8 },
+    
+    /// A record had an unknown type and was skipped
+    UnknownRecordType { line: usize, record_type: u8 },
+    
+    /// A line could not be parsed as a record and was skipped
+    MalformedRecord { line: usize },
+    
+    /// The file ended without an end-of-file record
+    MissingEndOfFile,
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadWarning::Overlap { line, address } => {
+                write!(f, "Line {}: overlaps earlier data at byte 0x{:04X}", line, address)
+            }
+            LoadWarning::BeyondProgramMemory { line, word } => {
+                write!(f, "Line {}: program word 0x{:04X} is beyond program memory", line, word)
+            }
+            LoadWarning::UnknownRegion { line, address } => {
+                write!(f, "Line {}: data at byte 0x{:04X} is outside any known region", line, address)
+            }
+            LoadWarning::ChecksumMismatch { line, expected, actual } => {
+                write!(f, "Line {}: checksum 0x{:02X} should be 0x{:02X}", line, actual, expected)
+            }
+            LoadWarning::UnknownRecordType { line, record_type } => {
+                write!(f, "Line {}: skipped record of unknown type 0x{:02X}", line, record_type)
+            }
+            LoadWarning::MalformedRecord { line } => {
+                write!(f, "Line {}: skipped malformed record", line)
+            }
+            LoadWarning::MissingEndOfFile => write!(f, "No end-of-file record"),
+        }
+    }
+}
+
+/// Summary of a HEX load
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    /// Program memory words that were given data
+    pub words_loaded: usize,
+    
+    /// Problems found, in file order
+    pub warnings: Vec<LoadWarning>,
+}
+
+impl LoadReport {
+    /// Whether the file loaded without warnings
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+/// How `HexLoader` treats malformed files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Fail on bad checksums, unknown record types, malformed lines or a
+    /// missing end-of-file record
+    #[default]
+    Strict,
+    
+    /// Report those problems as load warnings and keep loading
+    Lenient,
+}
+
+/// Options for `HexLoader::load_file_with` and `HexLoader::load_from_string_with`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub mode: ParseMode,
+    
+    /// Program memory size in words; data past it is reported as a warning
+    pub program_words: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            mode: ParseMode::Strict,
+            program_words: DEFAULT_PROGRAM_WORDS,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Lenient parsing for a 1K word device
+    pub fn lenient() -> Self {
+        ParseOptions { mode: ParseMode::Lenient, ..Self::default() }
+    }
+    
+    /// Use `program_words` words of program memory
+    pub fn with_program_words(mut self, program_words: usize) -> Self {
+        self.program_words = program_words;
+        self
+    }
+}
+
+/// Byte address of the first user ID location (word 0x2000)
+const ID_ADDRESS: u32 = 0x4000;
+
+/// Byte address of the configuration word (word 0x2007)
+const CONFIG_ADDRESS: u32 = 0x400E;
+
+/// Byte address of the first EEPROM byte (word 0x2100)
+const EEPROM_ADDRESS: u32 = 0x4200;
+
+/// End of the EEPROM region (256 bytes on the 12F683)
+const EEPROM_END: u32 = EEPROM_ADDRESS + 2 * 256;
+
+/// Program memory size assumed by `load_file` and `load_from_string` (1K words)
+pub(crate) const DEFAULT_PROGRAM_WORDS: usize = 0x400;
+
+/// Data bytes per record written by `HexLoader::save_to_string`
+const RECORD_SIZE: usize = 16;
+
+/// Erased program memory word; runs of these are left out of saved files
+const ERASED_WORD: u16 = 0x3FFF;
+
+/// Set the low (even `offset`) or high (odd `offset`) byte of a word
+fn set_word_byte(word: &mut u16, offset: u32, byte: u8) {
+    if offset.is_multiple_of(2) {
+        *word = (*word & 0xFF00) | byte as u16;
+    } else {
+        *word = (*word & 0x00FF) | ((byte as u16) << 8);
+    }
+}
+
+/// 1-based column of the part of the record `line` that caused `error`
+fn error_column(line: &str, error: &HexError) -> usize {
+    let length = line.chars().count();
+    match error {
+        HexError::OddLength => length,
+        HexError::InvalidByte(_) => {
+            let digits = line.as_bytes()[1..].chunks(2);
+            let pair = digits.take_while(|pair| pair.iter().all(u8::is_ascii_hexdigit)).count();
+            2 + 2 * pair
+        }
+        HexError::TooShort => length + 1,
+        HexError::ByteCountMismatch { .. } => 2,
+        HexError::InvalidRecordType(_) => 8,
+        HexError::ChecksumMismatch { .. } => length - 1,
+        _ => 1,
+    }
 }
 
 /// HEX file loader
 pub struct HexLoader;
 
 impl HexLoader {
-    /// Load a HEX file from a path
-    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<HexProgram, String> {
-        let file = File::open(path.as_ref())
-            .map_err(|e| format!("Failed to open file: {}", e))?;
+    /// Write `program` to a HEX file at `path`
+    pub fn save_file<P: AsRef<Path>>(program: &HexProgram, path: P) -> Result<(), HexError> {
+        std::fs::write(path, Self::save_to_string(program)).map_err(HexError::Write)
+    }
+    
+    /// Format `program` as Intel HEX
+    ///
+    /// Program memory is written 8 words per record, skipping records
+    /// that are entirely erased (0x3FFF), followed by the user IDs, the
+    /// configuration word, the EEPROM data and the end-of-file record.
+    pub fn save_to_string(program: &HexProgram) -> String {
+        let mut records = vec![HexRecord::new(0, RecordType::ExtendedLinearAddress, vec![0, 0])];
         
-        let reader = BufReader::new(file);
-        let mut lines = Vec::new();
+        let words_per_record = RECORD_SIZE / 2;
+        for (i, chunk) in program.program.chunks(words_per_record).enumerate() {
+            if chunk.iter().all(|&word| word == ERASED_WORD) {
+                continue;
+            }
+            let data = chunk.iter().flat_map(|word| word.to_le_bytes()).collect();
+            records.push(HexRecord::new((i * RECORD_SIZE) as u16, RecordType::Data, data));
+        }
         
-        for line in reader.lines() {
-            let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
-            lines.push(line);
+        if let Some(ids) = program.id_locations {
+            let data = ids.iter().flat_map(|word| word.to_le_bytes()).collect();
+            records.push(HexRecord::new(ID_ADDRESS as u16, RecordType::Data, data));
         }
         
-        Self::load_from_lines(&lines)
+        if let Some(config) = program.config {
+            records.push(HexRecord::new(CONFIG_ADDRESS as u16, RecordType::Data, config.to_le_bytes().to_vec()));
+        }
+        
+        for (i, chunk) in program.eeprom.chunks(words_per_record).enumerate() {
+            let address = EEPROM_ADDRESS as u16 + (i * RECORD_SIZE) as u16;
+            let data = chunk.iter().flat_map(|&byte| [byte, 0x00]).collect();
+            records.push(HexRecord::new(address, RecordType::Data, data));
+        }
+        
+        records.push(HexRecord::new(0, RecordType::EndOfFile, Vec::new()));
+        
+        let mut out = String::new();
+        for record in records {
+            out.push_str(&record.to_string());
+            out.push('\n');
+        }
+        out
+    }
+    
+    /// Load a HEX file from a path, strictly, warning about program data past 1K words
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<HexProgram, HexError> {
+        Self::load_file_with(path, &ParseOptions::default())
     }
     
-    /// Load a HEX file from a string
-    pub fn load_from_string(content: &str) -> Result<HexProgram, String> {
-        let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-        Self::load_from_lines(&lines)
+    /// Load a HEX file from a path with the given parse options
+    pub fn load_file_with<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<HexProgram, HexError> {
+        let file = File::open(path.as_ref())
+            .map_err(HexError::Open)?;
+        
+        Self::load_from_reader(BufReader::new(file), options)
+    }
+    
+    /// Load a HEX file from a string, strictly, warning about program data past 1K words
+    pub fn load_from_string(content: &str) -> Result<HexProgram, HexError> {
+        Self::load_from_string_with(content, &ParseOptions::default())
     }
     
-    /// Load from a collection of lines
-    fn load_from_lines(lines: &[String]) -> Result<HexProgram, String> {
-        let mut program_bytes: Vec<u8> = Vec::new();
-        let mut max_address = 0u32;
+    /// Load a HEX file from a string with the given parse options
+    pub fn load_from_string_with(content: &str, options: &ParseOptions) -> Result<HexProgram, HexError> {
+        Self::load_from_reader(content.as_bytes(), options)
+    }
+    
+    /// Load HEX records from `reader` one line at a time
+    ///
+    /// Parse errors give the line, the column of the offending field and
+    /// the text of the line.
+    pub fn load_from_reader<R: BufRead>(reader: R, options: &ParseOptions) -> Result<HexProgram, HexError> {
+        let mut image = ImageBuilder::new(options.program_words);
         let mut extended_address = 0u32;
-        let mut eeprom_data = Vec::new();
-        let mut config_word = None;
+        let mut end_of_file = false;
         
-        for (line_num, line) in lines.iter().enumerate() {
-            let line = line.trim();
+        for (line_num, text) in reader.lines().enumerate() {
+            let text = text.map_err(HexError::Read)?;
+            let line = text.trim();
             
             // Skip empty lines and comments
             if line.is_empty() || line.starts_with(';') {
@@ -179,72 +522,59 @@ impl HexLoader {
             }
             
             // Parse the record
-            let record = HexRecord::parse(line)
-                .map_err(|e| format!("Line {}: {}", line_num + 1, e))?;
+            let record = match HexRecord::parse(line) {
+                Ok(record) => record,
+                Err(e) if options.mode == ParseMode::Lenient => {
+                    let number = line_num + 1;
+                    let (warning, record) = match e {
+                        HexError::ChecksumMismatch { expected, actual, .. } => (
+                            LoadWarning::ChecksumMismatch { line: number, expected, actual },
+                            HexRecord::parse_checked(line, false).ok(),
+                        ),
+                        HexError::InvalidRecordType(record_type) => {
+                            (LoadWarning::UnknownRecordType { line: number, record_type }, None)
+                        }
+                        _ => (LoadWarning::MalformedRecord { line: number }, None),
+                    };
+                    image.warn(warning);
+                    match record {
+                        Some(record) => record,
+                        None => continue,
+                    }
+                }
+                Err(e) => {
+                    let indent = text.len() - text.trim_start().len();
+                    return Err(HexError::Line {
+                        line: line_num + 1,
+                        column: indent + error_column(line, &e),
+                        text: line.to_string(),
+                        source: Box::new(e),
+                    });
+                }
+            };
             
             match record.record_type {
                 RecordType::Data => {
-                    // Calculate absolute address
-                    let abs_address = extended_address + record.address as u32;
-                    
-                    // Determine if this is program memory, EEPROM, or config
-                    if abs_address >= 0x2100 && abs_address < 0x2180 {
-                        // EEPROM data (0x2100-0x217F)
-                        let eeprom_addr = (abs_address - 0x2100) as usize;
-                        
-                        // Expand EEPROM buffer if needed
-                        if eeprom_addr + record.data.len() > eeprom_data.len() {
-                            eeprom_data.resize(eeprom_addr + record.data.len(), 0xFF);
-                        }
-                        
-                        // Copy data
-                        for (i, &byte) in record.data.iter().enumerate() {
-                            eeprom_data[eeprom_addr + i] = byte;
-                        }
-                    } else if abs_address == 0x2007 {
-                        // Configuration word
-                        if record.data.len() >= 2 {
-                            config_word = Some(
-                                (record.data[0] as u16) | ((record.data[1] as u16) << 8)
-                            );
-                        }
-                    } else {
-                        // Program memory
-                        let prog_addr = abs_address as usize;
-                        
-                        // Expand program buffer if needed
-                        if prog_addr + record.data.len() > program_bytes.len() {
-                            program_bytes.resize(prog_addr + record.data.len(), 0xFF);
-                        }
-                        
-                        // Copy data
-                        for (i, &byte) in record.data.iter().enumerate() {
-                            program_bytes[prog_addr + i] = byte;
-                        }
-                        
-                        max_address = max_address.max(abs_address + record.data.len() as u32);
-                    }
+                    let start = extended_address + record.address as u32;
+                    image.add_data(line_num + 1, start, &record.data);
                 }
-                
+
                 RecordType::EndOfFile => {
                     // End of file - we're done
+                    end_of_file = true;
                     break;
                 }
                 
-                RecordType::ExtendedLinearAddress => {
+                RecordType::ExtendedLinearAddress if record.data.len() >= 2 => {
                     // Extended linear address (upper 16 bits)
-                    if record.data.len() >= 2 {
-                        extended_address =
-                            (((record.data[0] as u32) << 8) | (record.data[1] as u32)) << 16;
-                    }
+                    extended_address =
+                        (((record.data[0] as u32) << 8) | (record.data[1] as u32)) << 16;
                 }
 
-                RecordType::ExtendedSegmentAddress => {
+                RecordType::ExtendedSegmentAddress if record.data.len() >= 2 => {
                     // Extended segment address (upper 16 bits shifted by 4)
-                    if record.data.len() >= 2 {
-                        extended_address =
-                            (((record.data[0] as u32) << 8) | (record.data[1] as u32)) << 4;
-                    }
+                    extended_address =
+                        (((record.data[0] as u32) << 8) | (record.data[1] as u32)) << 4;
                 }
 
                 _ => {
@@ -253,27 +583,142 @@ impl HexLoader {
             }
         }
         
+        if !end_of_file {
+            match options.mode {
+                ParseMode::Strict => return Err(HexError::MissingEndOfFile),
+                ParseMode::Lenient => image.warn(LoadWarning::MissingEndOfFile),
+            }
+        }
+        
+        Ok(image.finish(None))
+    }
+}
+
+/// Builds a `HexProgram` from data bytes at PIC HEX byte addresses
+///
+/// The HEX and S-record loaders both place data this way: program words at
+/// twice their address, then the ID, config and EEPROM regions.
+pub(crate) struct ImageBuilder {
+    program_words: usize,
+    report: LoadReport,
+    written: HashSet<u32>,
+    program_bytes: Vec<u8>,
+    eeprom: Vec<u8>,
+    config: Option<u16>,
+    id_locations: Option<[u16; 4]>,
+}
+
+impl ImageBuilder {
+    /// Create a builder for `program_words` words of program memory
+    pub(crate) fn new(program_words: usize) -> Self {
+        ImageBuilder {
+            program_words,
+            report: LoadReport::default(),
+            written: HashSet::new(),
+            program_bytes: Vec::new(),
+            eeprom: Vec::new(),
+            config: None,
+            id_locations: None,
+        }
+    }
+    
+    /// Place the data of the record on `line`, starting at byte address `start`
+    pub(crate) fn add_data(&mut self, line: usize, start: u32, data: &[u8]) {
+        let (mut overlap, mut beyond, mut unknown) = (None, None, None);
+        
+        for (i, &byte) in data.iter().enumerate() {
+            // Bytes past the top of the 32-bit address space belong to no region
+            let Some(address) = start.checked_add(i as u32) else {
+                unknown.get_or_insert(start);
+                break;
+            };
+            if !self.written.insert(address) {
+                overlap.get_or_insert(address);
+            }
+            
+            if address < ID_ADDRESS {
+                if address / 2 >= self.program_words as u32 {
+                    beyond.get_or_insert(address / 2);
+                }
+                
+                // Program memory, two bytes per word
+                let prog_addr = address as usize;
+                if prog_addr >= self.program_bytes.len() {
+                    self.program_bytes.resize(prog_addr + 1, 0xFF);
+                }
+                self.program_bytes[prog_addr] = byte;
+            } else if (ID_ADDRESS..ID_ADDRESS + 8).contains(&address) {
+                // User ID locations
+                let offset = address - ID_ADDRESS;
+                let ids = self.id_locations.get_or_insert([0x3FFF; 4]);
+                set_word_byte(&mut ids[offset as usize / 2], offset, byte);
+            } else if (CONFIG_ADDRESS..CONFIG_ADDRESS + 2).contains(&address) {
+                // Configuration word
+                let config = self.config.get_or_insert(0x3FFF);
+                set_word_byte(config, address - CONFIG_ADDRESS, byte);
+            } else if (EEPROM_ADDRESS..EEPROM_END).contains(&address) {
+                // EEPROM data, one byte per word with a filler high byte
+                let offset = (address - EEPROM_ADDRESS) as usize;
+                if offset.is_multiple_of(2) {
+                    let eeprom_addr = offset / 2;
+                    if eeprom_addr >= self.eeprom.len() {
+                        self.eeprom.resize(eeprom_addr + 1, 0xFF);
+                    }
+                    self.eeprom[eeprom_addr] = byte;
+                }
+            } else {
+                unknown.get_or_insert(address);
+            }
+        }
+        
+        let warnings = &mut self.report.warnings;
+        if let Some(address) = overlap {
+            warnings.push(LoadWarning::Overlap { line, address });
+        }
+        if let Some(word) = beyond {
+            warnings.push(LoadWarning::BeyondProgramMemory { line, word });
+        }
+        if let Some(address) = unknown {
+            warnings.push(LoadWarning::UnknownRegion { line, address });
+        }
+    }
+    
+    /// Record a problem found outside the data itself
+    pub(crate) fn warn(&mut self, warning: LoadWarning) {
+        self.report.warnings.push(warning);
+    }
+    
+    /// Convert the collected bytes into a program starting at `start_address`
+    pub(crate) fn finish(mut self, start_address: Option<u16>) -> HexProgram {
         // Convert bytes to 14-bit words for program memory
         // PIC uses little-endian: low byte first, then high byte
         let mut program = Vec::new();
-        for i in (0..program_bytes.len()).step_by(2) {
-            if i + 1 < program_bytes.len() {
-                let low = program_bytes[i] as u16;
-                let high = program_bytes[i + 1] as u16;
+        for i in (0..self.program_bytes.len()).step_by(2) {
+            if i + 1 < self.program_bytes.len() {
+                let low = self.program_bytes[i] as u16;
+                let high = self.program_bytes[i + 1] as u16;
                 let word = low | (high << 8);
                 program.push(word & 0x3FFF); // Mask to 14 bits
             } else {
                 // Odd number of bytes - pad with 0xFF
-                program.push(program_bytes[i] as u16);
+                program.push(self.program_bytes[i] as u16);
             }
         }
         
-        Ok(HexProgram {
+        self.report.words_loaded = self.written.iter()
+            .filter(|&&address| address < ID_ADDRESS)
+            .map(|address| address / 2)
+            .collect::<HashSet<_>>()
+            .len();
+        
+        HexProgram {
             program,
-            eeprom: eeprom_data,
-            config: config_word,
-            start_address: 0,
-        })
+            eeprom: self.eeprom,
+            config: self.config.map(|word| word & 0x3FFF),
+            id_locations: self.id_locations.map(|ids| ids.map(|word| word & 0x3FFF)),
+            start_address,
+            report: self.report,
+        }
     }
 }
 
@@ -311,7 +756,7 @@ mod tests {
         
         // Invalid checksum
         let line = ":10000000FF3E00008F0E00000000000000000000E3";
-        assert!(HexRecord::parse(line).is_err());
+        assert!(matches!(HexRecord::parse(line), Err(HexError::ChecksumMismatch { address: 0, .. })));
     }
     
     #[test]
@@ -328,4 +773,167 @@ mod tests {
         assert_eq!(program.program[0], 0x3055); // MOVLW 0x55
         assert_eq!(program.program[1], 0x0020); // MOVWF 0x20
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_save_round_trip() {
+        let mut words = vec![0x3FFF; 24];
+        words[..3].copy_from_slice(&[0x3055, 0x0020, 0x2802]);
+        words[20] = 0x0008;
+        let program = HexProgram {
+            program: words.clone(),
+            eeprom: (0..20).collect(),
+            config: Some(0x3FC4),
+            id_locations: Some([0x0001, 0x0002, 0x0003, 0x0004]),
+            start_address: None,
+            report: LoadReport::default(),
+        };
+        
+        let hex = HexLoader::save_to_string(&program);
+        assert!(hex.starts_with(":020000040000FA\n"));
+        assert!(hex.contains(":10000000553020000228FF3FFF3FFF3FFF3FFF3FEB"));
+        assert!(hex.ends_with(":00000001FF\n"));
+        assert!(hex.contains(":02400E00C43FAD"));
+        assert!(hex.contains(":104200000000010002000300040005000600070092"));
+        // The erased words at 0x008-0x00F are left out
+        assert_eq!(hex.lines().count(), 1 + 2 + 1 + 1 + 3 + 1);
+        
+        let loaded = HexLoader::load_from_string(&hex).unwrap();
+        assert_eq!(loaded.program, words);
+        assert_eq!(loaded.eeprom, program.eeprom);
+        assert_eq!(loaded.config, Some(0x3FC4));
+        assert_eq!(loaded.id_locations, program.id_locations);
+    }
+    
+    #[test]
+    fn test_id_locations() {
+        let hex = ":060000005530200002282B\n:0440000001000200B9\n:00000001FF\n";
+        let program = HexLoader::load_from_string(hex).unwrap();
+        assert_eq!(program.id_locations, Some([0x0001, 0x0002, 0x3FFF, 0x3FFF]));
+        
+        let hex = ":060000005530200002282B\n:00000001FF\n";
+        assert_eq!(HexLoader::load_from_string(hex).unwrap().id_locations, None);
+    }
+    
+    #[test]
+    fn test_load_mpasm_output() {
+        // MPASM output for a 12F675 program with __IDLOCS, __CONFIG and DE "Hi",0
+        let hex = r#"
+:020000040000FA
+:020000000528D1
+:0800080009005530A000072893
+:084000000100020003000400AE
+:02400E008431FB
+:0642000048006900000007
+:00000001FF
+"#;
+        
+        let program = HexLoader::load_from_string(hex).unwrap();
+        assert_eq!(program.program, vec![0x2805, 0x3FFF, 0x3FFF, 0x3FFF, 0x0009, 0x3055, 0x00A0, 0x2807]);
+        assert_eq!(program.id_locations, Some([1, 2, 3, 4]));
+        assert_eq!(program.config, Some(0x3184));
+        assert_eq!(program.eeprom, vec![0x48, 0x69, 0x00]);
+        assert_eq!(program.report.words_loaded, 5);
+        assert!(program.report.is_clean());
+    }
+    
+    #[test]
+    fn test_load_report() {
+        let hex = r#"
+:040000005530200057
+:020002000228D2
+:020800000000F6
+:020000040001F9
+:02000000FFFF00
+:00000001FF
+"#;
+        
+        let program = HexLoader::load_from_string(hex).unwrap();
+        assert_eq!(program.program[1], 0x2802);
+        assert_eq!(program.report.words_loaded, 3);
+        assert_eq!(program.report.warnings, vec![
+            LoadWarning::Overlap { line: 3, address: 0x0002 },
+            LoadWarning::BeyondProgramMemory { line: 4, word: 0x0400 },
+            LoadWarning::UnknownRegion { line: 6, address: 0x10000 },
+        ]);
+        assert_eq!(program.report.warnings[0].to_string(), "Line 3: overlaps earlier data at byte 0x0002");
+        
+        let options = ParseOptions::default().with_program_words(0x800);
+        let program = HexLoader::load_from_string_with(hex, &options).unwrap();
+        assert_eq!(program.report.warnings.len(), 2);
+        
+        // Data running past the top of the address space
+        let hex = ":02000004FFFFFC\n:04FFFE0000000000FF\n:00000001FF\n";
+        let program = HexLoader::load_from_string(hex).unwrap();
+        assert_eq!(program.report.warnings, vec![
+            LoadWarning::UnknownRegion { line: 2, address: 0xFFFF_FFFE },
+        ]);
+    }
+    
+    #[test]
+    fn test_parse_modes() {
+        let hex = r#"
+:04000000553020005A
+:020004060228CA
+:GG
+"#;
+        
+        let err = HexLoader::load_from_string(hex).unwrap_err();
+        assert!(matches!(err.kind(), HexError::ChecksumMismatch { expected: 0x57, actual: 0x5A, .. }));
+        assert!(matches!(
+            HexLoader::load_from_string(":020000000528D1\n"),
+            Err(HexError::MissingEndOfFile)
+        ));
+        
+        let program = HexLoader::load_from_string_with(hex, &ParseOptions::lenient()).unwrap();
+        assert_eq!(program.program, vec![0x3055, 0x0020]);
+        assert_eq!(program.report.warnings, vec![
+            LoadWarning::ChecksumMismatch { line: 2, expected: 0x57, actual: 0x5A },
+            LoadWarning::UnknownRecordType { line: 3, record_type: 0x06 },
+            LoadWarning::MalformedRecord { line: 4 },
+            LoadWarning::MissingEndOfFile,
+        ]);
+    }
+    
+    #[test]
+    fn test_error_location() {
+        let hex = ":020000000528D1\n  :0200020002G8D2\n:00000001FF\n";
+        let err = HexLoader::load_from_reader(hex.as_bytes(), &ParseOptions::default()).unwrap_err();
+        let HexError::Line { line, column, ref text, .. } = err else {
+            panic!("expected a line error, got {:?}", err);
+        };
+        assert_eq!((line, column, text.as_str()), (2, 14, ":0200020002G8D2"));
+        assert_eq!(err.to_string(), "Line 2, column 14: Invalid hex byte: G8 in \":0200020002G8D2\"");
+        
+        let err = HexLoader::load_from_string(":020000000528D2\n").unwrap_err();
+        assert!(err.to_string().starts_with("Line 1, column 14: Checksum mismatch"));
+    }
+    
+    #[test]
+    fn test_diff() {
+        let expected = HexProgram {
+            program: vec![0x3055, 0x0020, 0x3FFF, 0x2802],
+            eeprom: vec![0x01, 0x02],
+            config: Some(0x3FC4),
+            id_locations: None,
+            start_address: None,
+            report: LoadReport::default(),
+        };
+        let mut actual = expected.clone();
+        actual.program.truncate(3);
+        actual.program.push(0x2803);
+        actual.program.extend([0x3FFF; 4]);
+        actual.eeprom = vec![0x01];
+        
+        assert_eq!(expected.diff(&expected), vec![]);
+        assert_eq!(expected.diff(&actual), vec![
+            Difference::Program { address: 3, expected: 0x2802, actual: 0x2803 },
+            Difference::Eeprom { address: 1, expected: 0x02, actual: 0xFF },
+        ]);
+        
+        actual.config = None;
+        actual.id_locations = Some([1, 0x3FFF, 0x3FFF, 0x3FFF]);
+        let differences = expected.diff(&actual);
+        assert_eq!(differences[1].to_string(), "Config: expected 0x3FC4, found none");
+        assert_eq!(differences[2], Difference::IdLocation { index: 0, expected: 0x3FFF, actual: 1 });
+    }
+}
@@ -0,0 +1,185 @@
+//! Execution history for reverse debugging
+//!
+//! The simulator records a full copy of the machine state every few thousand
+//! instructions. Stepping backwards restores the nearest earlier snapshot and
+//! re-executes forward to the requested point, which keeps memory use bounded
+//! while still allowing recent execution to be revisited.
+//!
+//! A snapshot copies the CPU's memories and the attached components, so
+//! the instruction that takes one allocates; every other step reuses its
+//! buffers.
+
+use std::collections::VecDeque;
+
+use crate::callstack::CallStack;
+use crate::component::Components;
+use crate::cpu::Cpu;
+use crate::latency::InterruptTiming;
+use crate::power::PowerMeter;
+use crate::simulator::SimulatorStats;
+
+/// Default number of instructions between snapshots
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 1000;
+
+/// Default number of snapshots kept
+pub const DEFAULT_SNAPSHOT_CAPACITY: usize = 100;
+
+/// Machine state captured at an instruction boundary
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub cpu: Cpu,
+    pub stats: SimulatorStats,
+    pub call_stack: CallStack,
+    pub interrupt_timing: InterruptTiming,
+    pub power: PowerMeter,
+    pub components: Components,
+    /// Events replayed so far, if inputs were being replayed
+    pub replay_position: Option<usize>,
+    /// Length of the input recording, if inputs were being recorded
+    pub input_log_len: Option<usize>,
+}
+
+impl Snapshot {
+    /// Number of instructions executed when the snapshot was taken
+    pub fn instruction(&self) -> u64 {
+        self.stats.instructions_executed
+    }
+}
+
+/// Periodic snapshot store
+#[derive(Debug, Clone)]
+pub struct History {
+    snapshots: VecDeque<Snapshot>,
+    interval: u64,
+    capacity: usize,
+}
+
+impl History {
+    /// Create a history with the default interval and capacity
+    pub fn new() -> Self {
+        Self::with_config(DEFAULT_SNAPSHOT_INTERVAL, DEFAULT_SNAPSHOT_CAPACITY)
+    }
+
+    /// Create a history taking a snapshot every `interval` instructions
+    /// and keeping at most `capacity` of them
+    pub fn with_config(interval: u64, capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            interval: interval.max(1),
+            capacity,
+        }
+    }
+
+    /// Check if a snapshot should be taken at `instruction`
+    pub fn is_due(&self, instruction: u64) -> bool {
+        // Sleep steps do not advance the instruction count, so a snapshot
+        // may already exist for this instruction
+        self.capacity > 0
+            && instruction.is_multiple_of(self.interval)
+            && self.snapshots.back().is_none_or(|s| s.instruction() != instruction)
+    }
+
+    /// Store a snapshot, dropping the oldest one when full
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.snapshots.len() >= self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Latest snapshot taken at or before `instruction`
+    pub fn nearest(&self, instruction: u64) -> Option<&Snapshot> {
+        self.snapshots.iter().rev().find(|s| s.instruction() <= instruction)
+    }
+
+    /// Drop all snapshots taken after `instruction`
+    pub fn truncate_after(&mut self, instruction: u64) {
+        while self.snapshots.back().is_some_and(|s| s.instruction() > instruction) {
+            self.snapshots.pop_back();
+        }
+    }
+
+    /// Instruction count of the oldest snapshot still available
+    pub fn oldest_instruction(&self) -> Option<u64> {
+        self.snapshots.front().map(Snapshot::instruction)
+    }
+
+    /// Remove all snapshots
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    /// Number of snapshots held
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Check if no snapshots are held
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(instructions: u64) -> Snapshot {
+        Snapshot {
+            cpu: Cpu::new(),
+            stats: SimulatorStats {
+                instructions_executed: instructions,
+                cycles_elapsed: instructions,
+            },
+            call_stack: CallStack::new(),
+            interrupt_timing: InterruptTiming::new(),
+            power: PowerMeter::new(),
+            components: Components::new(),
+            replay_position: None,
+            input_log_len: None,
+        }
+    }
+
+    fn record(history: &mut History, instructions: u64) {
+        if history.is_due(instructions) {
+            history.push(snapshot(instructions));
+        }
+    }
+
+    #[test]
+    fn test_records_on_interval() {
+        let mut history = History::with_config(10, 4);
+
+        for i in 0..35 {
+            record(&mut history, i);
+            record(&mut history, i);
+        }
+
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.nearest(25).unwrap().instruction(), 20);
+    }
+
+    #[test]
+    fn test_capacity_and_truncate() {
+        let mut history = History::with_config(1, 3);
+
+        for i in 0..5 {
+            record(&mut history, i);
+        }
+        assert_eq!(history.oldest_instruction(), Some(2));
+
+        history.truncate_after(2);
+        assert_eq!(history.len(), 1);
+        assert!(history.nearest(1).is_none());
+    }
+}
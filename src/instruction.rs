@@ -1,18 +1,50 @@
-/// PIC12F629/675 Instruction Set
-/// 
-/// Reference: Section 10.0 - Instruction Set Summary (Page 71-80)
-/// 
-/// The PIC12F629/675 has 35 instructions divided into three categories:
-/// 1. Byte-oriented operations (14 instructions)
-/// 2. Bit-oriented operations (4 instructions)
-/// 3. Literal and control operations (17 instructions)
-/// 
-/// Instruction Format: 14-bit word
-/// - Byte-oriented: [6-bit opcode][1-bit d][7-bit f]
-/// - Bit-oriented: [4-bit opcode][3-bit b][7-bit f]
-/// - Literal/Control: [6-bit opcode][8-bit k] or [3-bit opcode][11-bit k]
+//! PIC12F629/675 Instruction Set
+//! 
+//! Reference: Section 10.0 - Instruction Set Summary (Page 71-80)
+//! 
+//! The PIC12F629/675 has 35 instructions divided into three categories:
+//! 1. Byte-oriented operations (14 instructions)
+//! 2. Bit-oriented operations (4 instructions)
+//! 3. Literal and control operations (17 instructions)
+//! 
+//! Instruction Format: 14-bit word
+//! - Byte-oriented: [6-bit opcode][1-bit d][7-bit f]
+//! - Bit-oriented: [4-bit opcode][3-bit b][7-bit f]
+//! - Literal/Control: [6-bit opcode][8-bit k] or [3-bit opcode][11-bit k]
+//!
+//! The baseline (12-bit) core of the PIC10F200/202 shares most of these
+//! instructions with 5-bit file addresses, lacks ADDLW, SUBLW, RETURN and
+//! RETFIE, and adds OPTION and TRIS. Reference: PIC10F200/202/204/206 Data
+//! Sheet (DS41239D), Section 10.0 - Instruction Set Summary.
+//! - Byte-oriented: [6-bit opcode][1-bit d][5-bit f]
+//! - Bit-oriented: [4-bit opcode][3-bit b][5-bit f]
+//! - Literal/Control: [4-bit opcode][8-bit k] or [3-bit opcode][9-bit k]
 
-/// Instruction enumeration representing all 35 PIC instructions
+use crate::error::DecodeError;
+
+/// Instruction set of a device's CPU core
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Core {
+    /// 14-bit instruction words (PIC12F6xx, PIC16F84A)
+    #[default]
+    Midrange,
+    /// 12-bit instruction words (PIC10F2xx)
+    Baseline,
+}
+
+impl Core {
+    /// Bits used in a program memory word
+    pub fn word_mask(self) -> u16 {
+        match self {
+            Core::Midrange => 0x3FFF,
+            Core::Baseline => 0x0FFF,
+        }
+    }
+}
+
+/// Instruction enumeration representing all 35 midrange PIC instructions
+/// and the two baseline-only ones
 /// Reference: Table 10-2 - PIC12F629/675 Instruction Set (Page 72)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
@@ -196,6 +228,145 @@ pub enum Instruction {
     /// Operation: (W) XOR k -> W
     /// Flags affected: Z
     XORLW { k: u8 },
+    
+    // ==================== Baseline Operations ====================
+    
+    /// Load OPTION Register (baseline core)
+    /// Operation: (W) -> OPTION
+    /// Flags affected: None
+    OPTION,
+    
+    /// Load TRIS Register (baseline core)
+    /// Operation: (W) -> TRIS register of port f
+    /// Flags affected: None
+    TRIS { f: u8 },
+}
+
+impl Instruction {
+    /// Encode the instruction into its 14-bit instruction word
+    /// Inverse of `InstructionDecoder::decode`; out-of-range fields are masked
+    /// and don't-care bits are encoded as 0
+    pub fn encode(&self) -> u16 {
+        // Byte-oriented: [6-bit opcode][1-bit d][7-bit f]
+        let byte = |opcode: u16, f: u8, d: u8| {
+            (opcode << 8) | (((d & 0x01) as u16) << 7) | (f & 0x7F) as u16
+        };
+        
+        // Bit-oriented: [4-bit opcode][3-bit b][7-bit f]
+        let bit = |opcode: u16, f: u8, b: u8| {
+            (opcode << 10) | (((b & 0x07) as u16) << 7) | (f & 0x7F) as u16
+        };
+        
+        // Literal: [6-bit opcode][8-bit k]
+        let literal = |opcode: u16, k: u8| (opcode << 8) | k as u16;
+        
+        match *self {
+            Instruction::ADDWF { f, d } => byte(0x07, f, d),
+            Instruction::ANDWF { f, d } => byte(0x05, f, d),
+            Instruction::CLRF { f } => byte(0x01, f, 1),
+            Instruction::CLRW => 0x0100,
+            Instruction::COMF { f, d } => byte(0x09, f, d),
+            Instruction::DECF { f, d } => byte(0x03, f, d),
+            Instruction::DECFSZ { f, d } => byte(0x0B, f, d),
+            Instruction::INCF { f, d } => byte(0x0A, f, d),
+            Instruction::INCFSZ { f, d } => byte(0x0F, f, d),
+            Instruction::IORWF { f, d } => byte(0x04, f, d),
+            Instruction::MOVF { f, d } => byte(0x08, f, d),
+            Instruction::MOVWF { f } => byte(0x00, f, 1),
+            Instruction::NOP => 0x0000,
+            Instruction::RLF { f, d } => byte(0x0D, f, d),
+            Instruction::RRF { f, d } => byte(0x0C, f, d),
+            Instruction::SUBWF { f, d } => byte(0x02, f, d),
+            Instruction::SWAPF { f, d } => byte(0x0E, f, d),
+            Instruction::XORWF { f, d } => byte(0x06, f, d),
+            
+            Instruction::BCF { f, b } => bit(0x04, f, b),
+            Instruction::BSF { f, b } => bit(0x05, f, b),
+            Instruction::BTFSC { f, b } => bit(0x06, f, b),
+            Instruction::BTFSS { f, b } => bit(0x07, f, b),
+            
+            Instruction::ADDLW { k } => literal(0x3E, k),
+            Instruction::ANDLW { k } => literal(0x39, k),
+            Instruction::CALL { k } => 0x2000 | (k & 0x7FF),
+            Instruction::CLRWDT => 0x0064,
+            Instruction::GOTO { k } => 0x2800 | (k & 0x7FF),
+            Instruction::IORLW { k } => literal(0x38, k),
+            Instruction::MOVLW { k } => literal(0x30, k),
+            Instruction::RETFIE => 0x0009,
+            Instruction::RETLW { k } => literal(0x34, k),
+            Instruction::RETURN => 0x0008,
+            Instruction::SLEEP => 0x0063,
+            Instruction::SUBLW { k } => literal(0x3C, k),
+            Instruction::XORLW { k } => literal(0x3A, k),
+            
+            // Obsolete midrange encodings; `decode` doesn't accept them
+            Instruction::OPTION => 0x0062,
+            Instruction::TRIS { f } => 0x0060 | (f & 0x07) as u16,
+        }
+    }
+    
+    /// Encode the instruction into its 12-bit baseline instruction word
+    /// Inverse of `InstructionDecoder::decode_baseline`; returns None for
+    /// instructions the baseline core lacks
+    pub fn encode_baseline(&self) -> Option<u16> {
+        // Byte-oriented: [6-bit opcode][1-bit d][5-bit f]
+        let byte = |opcode: u16, f: u8, d: u8| {
+            (opcode << 6) | (((d & 0x01) as u16) << 5) | (f & 0x1F) as u16
+        };
+        
+        // Bit-oriented: [4-bit opcode][3-bit b][5-bit f]
+        let bit = |opcode: u16, f: u8, b: u8| {
+            (opcode << 8) | (((b & 0x07) as u16) << 5) | (f & 0x1F) as u16
+        };
+        
+        // Literal: [4-bit opcode][8-bit k]
+        let literal = |opcode: u16, k: u8| (opcode << 8) | k as u16;
+        
+        let word = match *self {
+            Instruction::ADDWF { f, d } => byte(0x07, f, d),
+            Instruction::ANDWF { f, d } => byte(0x05, f, d),
+            Instruction::CLRF { f } => byte(0x01, f, 1),
+            Instruction::CLRW => 0x040,
+            Instruction::COMF { f, d } => byte(0x09, f, d),
+            Instruction::DECF { f, d } => byte(0x03, f, d),
+            Instruction::DECFSZ { f, d } => byte(0x0B, f, d),
+            Instruction::INCF { f, d } => byte(0x0A, f, d),
+            Instruction::INCFSZ { f, d } => byte(0x0F, f, d),
+            Instruction::IORWF { f, d } => byte(0x04, f, d),
+            Instruction::MOVF { f, d } => byte(0x08, f, d),
+            Instruction::MOVWF { f } => byte(0x00, f, 1),
+            Instruction::NOP => 0x000,
+            Instruction::RLF { f, d } => byte(0x0D, f, d),
+            Instruction::RRF { f, d } => byte(0x0C, f, d),
+            Instruction::SUBWF { f, d } => byte(0x02, f, d),
+            Instruction::SWAPF { f, d } => byte(0x0E, f, d),
+            Instruction::XORWF { f, d } => byte(0x06, f, d),
+            
+            Instruction::BCF { f, b } => bit(0x04, f, b),
+            Instruction::BSF { f, b } => bit(0x05, f, b),
+            Instruction::BTFSC { f, b } => bit(0x06, f, b),
+            Instruction::BTFSS { f, b } => bit(0x07, f, b),
+            
+            Instruction::ANDLW { k } => literal(0x0E, k),
+            Instruction::CALL { k } => 0x900 | (k & 0xFF),
+            Instruction::CLRWDT => 0x004,
+            Instruction::GOTO { k } => 0xA00 | (k & 0x1FF),
+            Instruction::IORLW { k } => literal(0x0D, k),
+            Instruction::MOVLW { k } => literal(0x0C, k),
+            Instruction::RETLW { k } => literal(0x08, k),
+            Instruction::SLEEP => 0x003,
+            Instruction::XORLW { k } => literal(0x0F, k),
+            
+            Instruction::OPTION => 0x002,
+            Instruction::TRIS { f } => (f & 0x07) as u16,
+            
+            Instruction::ADDLW { .. }
+            | Instruction::SUBLW { .. }
+            | Instruction::RETFIE
+            | Instruction::RETURN => return None,
+        };
+        Some(word)
+    }
 }
 
 /// Instruction decoder
@@ -205,7 +376,7 @@ pub struct InstructionDecoder;
 impl InstructionDecoder {
     /// Decode a 14-bit instruction word into an Instruction enum
     /// Reference: Table 10-2 - Instruction opcode mapping
-    pub fn decode(word: u16) -> Result<Instruction, String> {
+    pub fn decode(word: u16) -> Result<Instruction, DecodeError> {
         // Special control instructions are checked first 
         // (Before checking byte operations)
         // CLRWDT, RETFIE, RETURN, SLEEP
@@ -257,7 +428,7 @@ impl InstructionDecoder {
                 0x02 => Ok(Instruction::SUBWF { f, d }),
                 0x0E => Ok(Instruction::SWAPF { f, d }),
                 0x06 => Ok(Instruction::XORWF { f, d }),
-                _ => Err(format!("Unknown byte-oriented instruction: 0x{:04X}", word)),
+                _ => Err(DecodeError { opcode: word }),
             };
         }
         
@@ -272,7 +443,7 @@ impl InstructionDecoder {
                 0x01 => Ok(Instruction::BSF { f, b }),
                 0x02 => Ok(Instruction::BTFSC { f, b }),
                 0x03 => Ok(Instruction::BTFSS { f, b }),
-                _ => Err(format!("Unknown bit-oriented instruction: 0x{:04X}", word)),
+                _ => Err(DecodeError { opcode: word }),
             };
         }
         
@@ -288,7 +459,90 @@ impl InstructionDecoder {
             0x3A => Ok(Instruction::XORLW { k }),
             0x34..=0x37 => Ok(Instruction::RETLW { k }),
             
-            _ => Err(format!("Unknown instruction: 0x{:04X}", word)),
+            _ => Err(DecodeError { opcode: word }),
+        }
+    }
+    
+    /// Decode a 12-bit baseline instruction word
+    /// Reference: DS41239D Table 10-2 - 12-bit Instruction Set Summary
+    pub fn decode_baseline(word: u16) -> Result<Instruction, DecodeError> {
+        // Control instructions without operands, and TRIS on ports 5-7
+        match word {
+            0x1000.. => return Err(DecodeError { opcode: word }),
+            0x000 => return Ok(Instruction::NOP),
+            0x002 => return Ok(Instruction::OPTION),
+            0x003 => return Ok(Instruction::SLEEP),
+            0x004 => return Ok(Instruction::CLRWDT),
+            0x005..=0x007 => return Ok(Instruction::TRIS { f: word as u8 }),
+            0x040 => return Ok(Instruction::CLRW),
+            _ => {}
+        }
+        
+        // GOTO: 101k kkkk kkkk (9-bit address)
+        if (word >> 9) & 0x07 == 0b101 {
+            return Ok(Instruction::GOTO { k: word & 0x1FF });
+        }
+        
+        let f = (word & 0x1F) as u8;
+        
+        // Byte-oriented file register operations
+        // Format: 00oo oodf ffff, with the midrange opcode numbering
+        if word & 0xC00 == 0x000 {
+            let d = ((word >> 5) & 0x01) as u8;
+            
+            return match (word >> 6) & 0x0F {
+                0x00 if d == 1 => Ok(Instruction::MOVWF { f }),
+                0x01 if d == 1 => Ok(Instruction::CLRF { f }),
+                0x02 => Ok(Instruction::SUBWF { f, d }),
+                0x03 => Ok(Instruction::DECF { f, d }),
+                0x04 => Ok(Instruction::IORWF { f, d }),
+                0x05 => Ok(Instruction::ANDWF { f, d }),
+                0x06 => Ok(Instruction::XORWF { f, d }),
+                0x07 => Ok(Instruction::ADDWF { f, d }),
+                0x08 => Ok(Instruction::MOVF { f, d }),
+                0x09 => Ok(Instruction::COMF { f, d }),
+                0x0A => Ok(Instruction::INCF { f, d }),
+                0x0B => Ok(Instruction::DECFSZ { f, d }),
+                0x0C => Ok(Instruction::RRF { f, d }),
+                0x0D => Ok(Instruction::RLF { f, d }),
+                0x0E => Ok(Instruction::SWAPF { f, d }),
+                0x0F => Ok(Instruction::INCFSZ { f, d }),
+                _ => Err(DecodeError { opcode: word }),
+            };
+        }
+        
+        // Bit-oriented file register operations
+        // Format: 01oo bbbf ffff
+        if word & 0xC00 == 0x400 {
+            let b = ((word >> 5) & 0x07) as u8;
+            
+            return match (word >> 8) & 0x03 {
+                0x00 => Ok(Instruction::BCF { f, b }),
+                0x01 => Ok(Instruction::BSF { f, b }),
+                0x02 => Ok(Instruction::BTFSC { f, b }),
+                _ => Ok(Instruction::BTFSS { f, b }),
+            };
+        }
+        
+        // 8-bit literal operations (top 4 bits)
+        let k = (word & 0xFF) as u8;
+        
+        match (word >> 8) & 0x0F {
+            0x08 => Ok(Instruction::RETLW { k }),
+            0x09 => Ok(Instruction::CALL { k: k as u16 }),
+            0x0C => Ok(Instruction::MOVLW { k }),
+            0x0D => Ok(Instruction::IORLW { k }),
+            0x0E => Ok(Instruction::ANDLW { k }),
+            0x0F => Ok(Instruction::XORLW { k }),
+            _ => Err(DecodeError { opcode: word }),
+        }
+    }
+    
+    /// Decode an instruction word of the given core
+    pub fn decode_for(core: Core, word: u16) -> Result<Instruction, DecodeError> {
+        match core {
+            Core::Midrange => Self::decode(word),
+            Core::Baseline => Self::decode_baseline(word),
         }
     }
     
@@ -409,4 +663,113 @@ mod tests {
         assert_eq!(InstructionDecoder::get_cycles(&Instruction::CALL { k: 0 }), 2);
         assert_eq!(InstructionDecoder::get_cycles(&Instruction::RETURN), 2);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_encode() {
+        assert_eq!(Instruction::MOVLW { k: 0x55 }.encode(), 0x3055);
+        assert_eq!(Instruction::MOVWF { f: 0x20 }.encode(), 0x00A0);
+        assert_eq!(Instruction::ADDWF { f: 0x20, d: 1 }.encode(), 0x07A0);
+        assert_eq!(Instruction::BCF { f: 0x05, b: 7 }.encode(), 0x1385);
+        assert_eq!(Instruction::CALL { k: 0x100 }.encode(), 0x2100);
+        assert_eq!(Instruction::GOTO { k: 0x100 }.encode(), 0x2900);
+    }
+    
+    #[test]
+    fn test_decode_encode_round_trip() {
+        // Every decodable word must re-encode to an instruction that
+        // decodes to the same value
+        for word in 0..0x4000u16 {
+            if let Ok(inst) = InstructionDecoder::decode(word) {
+                let encoded = inst.encode();
+                assert!(encoded <= 0x3FFF, "{:?} encodes past 14 bits", inst);
+                assert_eq!(InstructionDecoder::decode(encoded), Ok(inst), "word 0x{:04X}", word);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_encode_decode_round_trip() {
+        // Sweep every field value of every instruction form
+        let mut instructions = vec![
+            Instruction::CLRW,
+            Instruction::NOP,
+            Instruction::CLRWDT,
+            Instruction::RETFIE,
+            Instruction::RETURN,
+            Instruction::SLEEP,
+        ];
+        
+        for f in 0..0x80u8 {
+            instructions.push(Instruction::CLRF { f });
+            instructions.push(Instruction::MOVWF { f });
+            for d in 0..2u8 {
+                instructions.extend([
+                    Instruction::ADDWF { f, d },
+                    Instruction::ANDWF { f, d },
+                    Instruction::COMF { f, d },
+                    Instruction::DECF { f, d },
+                    Instruction::DECFSZ { f, d },
+                    Instruction::INCF { f, d },
+                    Instruction::INCFSZ { f, d },
+                    Instruction::IORWF { f, d },
+                    Instruction::MOVF { f, d },
+                    Instruction::RLF { f, d },
+                    Instruction::RRF { f, d },
+                    Instruction::SUBWF { f, d },
+                    Instruction::SWAPF { f, d },
+                    Instruction::XORWF { f, d },
+                ]);
+            }
+            for b in 0..8u8 {
+                instructions.extend([
+                    Instruction::BCF { f, b },
+                    Instruction::BSF { f, b },
+                    Instruction::BTFSC { f, b },
+                    Instruction::BTFSS { f, b },
+                ]);
+            }
+        }
+        
+        for k in 0..=0xFFu8 {
+            instructions.extend([
+                Instruction::ADDLW { k },
+                Instruction::ANDLW { k },
+                Instruction::IORLW { k },
+                Instruction::MOVLW { k },
+                Instruction::RETLW { k },
+                Instruction::SUBLW { k },
+                Instruction::XORLW { k },
+            ]);
+        }
+        
+        for k in 0..0x800u16 {
+            instructions.push(Instruction::CALL { k });
+            instructions.push(Instruction::GOTO { k });
+        }
+        
+        for inst in instructions {
+            assert_eq!(InstructionDecoder::decode(inst.encode()), Ok(inst));
+        }
+    }
+    
+    #[test]
+    fn test_decode_baseline() {
+        assert_eq!(InstructionDecoder::decode_baseline(0x002), Ok(Instruction::OPTION));
+        assert_eq!(InstructionDecoder::decode_baseline(0x006), Ok(Instruction::TRIS { f: 6 }));
+        assert_eq!(InstructionDecoder::decode_baseline(0xC55), Ok(Instruction::MOVLW { k: 0x55 }));
+        assert_eq!(InstructionDecoder::decode_baseline(0x026), Ok(Instruction::MOVWF { f: 0x06 }));
+        assert_eq!(InstructionDecoder::decode_baseline(0x5A6), Ok(Instruction::BSF { f: 0x06, b: 5 }));
+        assert_eq!(InstructionDecoder::decode_baseline(0x9FF), Ok(Instruction::CALL { k: 0xFF }));
+        assert_eq!(InstructionDecoder::decode_baseline(0xBFF), Ok(Instruction::GOTO { k: 0x1FF }));
+        assert_eq!(InstructionDecoder::decode_baseline(0x001), Err(DecodeError { opcode: 0x001 }));
+        assert_eq!(Instruction::ADDLW { k: 1 }.encode_baseline(), None);
+        
+        // Every decodable word re-encodes to itself or an equivalent word
+        for word in 0..0x1000u16 {
+            if let Ok(inst) = InstructionDecoder::decode_baseline(word) {
+                let encoded = inst.encode_baseline().unwrap();
+                assert_eq!(InstructionDecoder::decode_baseline(encoded), Ok(inst), "word 0x{:03X}", word);
+            }
+        }
+    }
+}
@@ -1,17 +1,24 @@
-/// PIC12F629/675 Interrupt System
-/// 
-/// Reference: Section 8.0 - Interrupts (Page 31-34)
-/// 
-/// Interrupt sources:
-/// - TMR0 overflow
-/// - GPIO pin change
-/// - Comparator output change
-/// - A/D converter (12F675 only)
-/// - EEPROM write complete
-/// - Timer1 overflow
+//! PIC12F629/675 Interrupt System
+//! 
+//! Reference: Section 8.0 - Interrupts (Page 31-34)
+//! 
+//! Interrupt sources:
+//! - TMR0 overflow
+//! - INT pin edge (GP2/INT; RB0/INT on the 16F84A), selected by INTEDG
+//! - GPIO pin change
+//! - Comparator output change
+//! - A/D converter (12F675 only)
+//! - EEPROM write complete
+//! - Timer1 overflow
+//! - Timer2 match with PR2 (12F683 only)
+
+use crate::cpu::registers;
+use crate::error::StateError;
+use crate::state::StateRecord;
 
 /// Interrupt controller
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InterruptController {
     /// Global interrupt enable (saved during interrupt)
     gie_saved: bool,
@@ -21,6 +28,26 @@ pub struct InterruptController {
     
     /// Interrupt vector (always 0x0004 for PIC12F)
     interrupt_vector: u16,
+    
+    /// Per-source counters, indexed like `InterruptSource::ALL`
+    stats: [InterruptStats; InterruptSource::ALL.len()],
+    
+    /// Source of the most recently taken interrupt
+    last_source: Option<InterruptSource>,
+    
+    /// Level of the INT pin when last sampled; kept across resets since
+    /// the pin is driven from outside
+    int_level: bool,
+}
+
+/// How often an interrupt source was taken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InterruptStats {
+    /// Number of times the vector was taken for this source
+    pub count: u64,
+    /// CPU cycle count when it was last taken
+    pub last_cycle: Option<u64>,
 }
 
 impl InterruptController {
@@ -29,21 +56,26 @@ impl InterruptController {
             gie_saved: false,
             interrupt_triggered: false,
             interrupt_vector: 0x0004,
+            stats: [InterruptStats::default(); InterruptSource::ALL.len()],
+            last_source: None,
+            int_level: false,
         }
     }
     
     pub fn reset(&mut self) {
         self.gie_saved = false;
         self.interrupt_triggered = false;
+        self.stats = [InterruptStats::default(); InterruptSource::ALL.len()];
+        self.last_source = None;
     }
     
     /// Check if any interrupt should trigger
-    /// Returns (should_interrupt, interrupt_vector)
-    pub fn check_interrupts(&self, intcon: u8, pie1: u8, pir1: u8) -> (bool, u16) {
+    /// Returns the highest-priority source that is both enabled and flagged
+    pub fn check_interrupts(&self, intcon: u8, pie1: u8, pir1: u8) -> Option<InterruptSource> {
         // Check GIE (Global Interrupt Enable) - bit 7 of INTCON
         let gie = (intcon & 0x80) != 0;
         if !gie {
-            return (false, 0);
+            return None;
         }
         
         // Check each interrupt source
@@ -54,7 +86,7 @@ impl InterruptController {
         let t0ie = (intcon & 0x20) != 0;
         let t0if = (intcon & 0x04) != 0;
         if t0ie && t0if {
-            return (true, self.interrupt_vector);
+            return Some(InterruptSource::Timer0Overflow);
         }
         
         // INT External Interrupt (GP2/INT pin)
@@ -62,7 +94,7 @@ impl InterruptController {
         let inte = (intcon & 0x10) != 0;
         let intf = (intcon & 0x02) != 0;
         if inte && intf {
-            return (true, self.interrupt_vector);
+            return Some(InterruptSource::ExternalInt);
         }
         
         // GPIO Port Change Interrupt
@@ -70,7 +102,7 @@ impl InterruptController {
         let gpie = (intcon & 0x08) != 0;
         let gpif = (intcon & 0x01) != 0;
         if gpie && gpif {
-            return (true, self.interrupt_vector);
+            return Some(InterruptSource::GpioChange);
         }
         
         // Peripheral Interrupts (enabled by PEIE in INTCON bit 6)
@@ -81,7 +113,15 @@ impl InterruptController {
             let tmr1ie = (pie1 & 0x01) != 0;
             let tmr1if = (pir1 & 0x01) != 0;
             if tmr1ie && tmr1if {
-                return (true, self.interrupt_vector);
+                return Some(InterruptSource::Timer1Overflow);
+            }
+            
+            // Timer2 to PR2 Match Interrupt (12F683 only)
+            // PIE1: TMR2IE (bit 1), PIR1: TMR2IF (bit 1)
+            let tmr2ie = (pie1 & 0x02) != 0;
+            let tmr2if = (pir1 & 0x02) != 0;
+            if tmr2ie && tmr2if {
+                return Some(InterruptSource::Timer2Match);
             }
             
             // Comparator Interrupt
@@ -89,7 +129,7 @@ impl InterruptController {
             let cmie = (pie1 & 0x08) != 0;
             let cmif = (pir1 & 0x08) != 0;
             if cmie && cmif {
-                return (true, self.interrupt_vector);
+                return Some(InterruptSource::Comparator);
             }
             
             // A/D Converter Interrupt (12F675 only)
@@ -97,7 +137,7 @@ impl InterruptController {
             let adie = (pie1 & 0x40) != 0;
             let adif = (pir1 & 0x40) != 0;
             if adie && adif {
-                return (true, self.interrupt_vector);
+                return Some(InterruptSource::AdConverter);
             }
             
             // EEPROM Write Complete Interrupt
@@ -105,18 +145,32 @@ impl InterruptController {
             let eeie = (pie1 & 0x80) != 0;
             let eeif = (pir1 & 0x80) != 0;
             if eeie && eeif {
-                return (true, self.interrupt_vector);
+                return Some(InterruptSource::EepromWrite);
             }
         }
         
-        (false, 0)
+        None
+    }
+    
+    /// Sample the INT pin; returns true on the edge selected by INTEDG
+    /// (OPTION_REG bit 6: 1 = rising, 0 = falling)
+    pub fn sample_int_pin(&mut self, level: bool, option_reg: u8) -> bool {
+        let rising = (option_reg & 0x40) != 0;
+        let edge = level != self.int_level && level == rising;
+        self.int_level = level;
+        edge
     }
     
-    /// Enter interrupt service routine
+    /// Enter interrupt service routine for `source` at CPU cycle `cycle`
     /// Saves GIE and clears it
-    pub fn enter_isr(&mut self) {
+    pub fn enter_isr(&mut self, source: InterruptSource, cycle: u64) {
         self.gie_saved = true;
         self.interrupt_triggered = true;
+        
+        let stats = &mut self.stats[source.index()];
+        stats.count += 1;
+        stats.last_cycle = Some(cycle);
+        self.last_source = Some(source);
     }
     
     /// Exit interrupt service routine
@@ -135,6 +189,38 @@ impl InterruptController {
     pub fn get_vector(&self) -> u16 {
         self.interrupt_vector
     }
+    
+    /// Counters for one source
+    pub fn stats(&self, source: InterruptSource) -> InterruptStats {
+        self.stats[source.index()]
+    }
+    
+    /// Source of the most recently taken interrupt
+    pub fn last_source(&self) -> Option<InterruptSource> {
+        self.last_source
+    }
+    
+    /// Total number of interrupts taken
+    pub fn total_count(&self) -> u64 {
+        self.stats.iter().map(|stats| stats.count).sum()
+    }
+
+    /// Save ISR tracking state
+    pub fn save_state(&self, record: &mut StateRecord) {
+        record.set("interrupt.gie_saved", self.gie_saved);
+        record.set("interrupt.interrupt_triggered", self.interrupt_triggered);
+        record.set("interrupt.interrupt_vector", self.interrupt_vector);
+        record.set("interrupt.int_level", self.int_level);
+    }
+    
+    /// Restore state saved with `save_state`
+    pub fn load_state(&mut self, record: &StateRecord) -> Result<(), StateError> {
+        self.gie_saved = record.get("interrupt.gie_saved")?;
+        self.interrupt_triggered = record.get("interrupt.interrupt_triggered")?;
+        self.interrupt_vector = record.get("interrupt.interrupt_vector")?;
+        self.int_level = record.get("interrupt.int_level")?;
+        Ok(())
+    }
 }
 
 impl Default for InterruptController {
@@ -144,18 +230,66 @@ impl Default for InterruptController {
 }
 
 /// Interrupt source enumeration (for debugging/logging)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InterruptSource {
     Timer0Overflow,
     ExternalInt,
     GpioChange,
     Timer1Overflow,
+    Timer2Match,
     Comparator,
     AdConverter,
     EepromWrite,
 }
 
 impl InterruptSource {
+    /// All sources, in priority order
+    pub const ALL: [InterruptSource; 8] = [
+        InterruptSource::Timer0Overflow,
+        InterruptSource::ExternalInt,
+        InterruptSource::GpioChange,
+        InterruptSource::Timer1Overflow,
+        InterruptSource::Timer2Match,
+        InterruptSource::Comparator,
+        InterruptSource::AdConverter,
+        InterruptSource::EepromWrite,
+    ];
+    
+    /// Position in `ALL`
+    pub fn index(self) -> usize {
+        self as usize
+    }
+    
+    /// Register address and bit number of the source's interrupt flag
+    pub fn flag_bit(self) -> (u8, u8) {
+        match self {
+            InterruptSource::Timer0Overflow => (registers::INTCON, 2),
+            InterruptSource::ExternalInt => (registers::INTCON, 1),
+            InterruptSource::GpioChange => (registers::INTCON, 0),
+            InterruptSource::Timer1Overflow => (registers::PIR1, 0),
+            InterruptSource::Timer2Match => (registers::PIR1, 1),
+            InterruptSource::Comparator => (registers::PIR1, 3),
+            InterruptSource::AdConverter => (registers::PIR1, 6),
+            InterruptSource::EepromWrite => (registers::PIR1, 7),
+        }
+    }
+    
+    /// Register address and bit number of the source's interrupt enable
+    /// (PIE1 sources also need PEIE)
+    pub fn enable_bit(self) -> (u8, u8) {
+        match self {
+            InterruptSource::Timer0Overflow => (registers::INTCON, 5),
+            InterruptSource::ExternalInt => (registers::INTCON, 4),
+            InterruptSource::GpioChange => (registers::INTCON, 3),
+            InterruptSource::Timer1Overflow => (registers::PIE1, 0),
+            InterruptSource::Timer2Match => (registers::PIE1, 1),
+            InterruptSource::Comparator => (registers::PIE1, 3),
+            InterruptSource::AdConverter => (registers::PIE1, 6),
+            InterruptSource::EepromWrite => (registers::PIE1, 7),
+        }
+    }
+    
     /// Get human-readable name
     pub fn name(&self) -> &str {
         match self {
@@ -163,6 +297,7 @@ impl InterruptSource {
             InterruptSource::ExternalInt => "External INT",
             InterruptSource::GpioChange => "GPIO Change",
             InterruptSource::Timer1Overflow => "Timer1 Overflow",
+            InterruptSource::Timer2Match => "Timer2 Match",
             InterruptSource::Comparator => "Comparator",
             InterruptSource::AdConverter => "A/D Converter",
             InterruptSource::EepromWrite => "EEPROM Write",
@@ -190,8 +325,7 @@ mod tests {
         let pie1 = 0x00;
         let pir1 = 0x00;
         
-        let (should_int, _) = ic.check_interrupts(intcon, pie1, pir1);
-        assert!(!should_int);
+        assert_eq!(ic.check_interrupts(intcon, pie1, pir1), None);
     }
     
     #[test]
@@ -203,9 +337,7 @@ mod tests {
         let pie1 = 0x00;
         let pir1 = 0x00;
         
-        let (should_int, vec) = ic.check_interrupts(intcon, pie1, pir1);
-        assert!(should_int);
-        assert_eq!(vec, 0x0004);
+        assert_eq!(ic.check_interrupts(intcon, pie1, pir1), Some(InterruptSource::Timer0Overflow));
     }
     
     #[test]
@@ -217,9 +349,7 @@ mod tests {
         let pie1 = 0x01;   // TMR1IE=1
         let pir1 = 0x01;   // TMR1IF=1
         
-        let (should_int, vec) = ic.check_interrupts(intcon, pie1, pir1);
-        assert!(should_int);
-        assert_eq!(vec, 0x0004);
+        assert_eq!(ic.check_interrupts(intcon, pie1, pir1), Some(InterruptSource::Timer1Overflow));
     }
     
     #[test]
@@ -231,8 +361,7 @@ mod tests {
         let pie1 = 0x00;
         let pir1 = 0x00;
         
-        let (should_int, _) = ic.check_interrupts(intcon, pie1, pir1);
-        assert!(!should_int);
+        assert_eq!(ic.check_interrupts(intcon, pie1, pir1), None);
     }
     
     #[test]
@@ -241,10 +370,62 @@ mod tests {
         
         assert!(!ic.in_isr());
         
-        ic.enter_isr();
+        ic.enter_isr(InterruptSource::GpioChange, 120);
         assert!(ic.in_isr());
         
         ic.exit_isr();
         assert!(!ic.in_isr());
     }
+    
+    #[test]
+    fn test_source_stats() {
+        let mut ic = InterruptController::new();
+        
+        // T0IF and GPIF both pending: TMR0 has priority
+        assert_eq!(ic.check_interrupts(0xAD, 0x00, 0x00), Some(InterruptSource::Timer0Overflow));
+        
+        ic.enter_isr(InterruptSource::GpioChange, 10);
+        ic.enter_isr(InterruptSource::GpioChange, 50);
+        ic.enter_isr(InterruptSource::Timer1Overflow, 70);
+        assert_eq!(ic.stats(InterruptSource::GpioChange), InterruptStats { count: 2, last_cycle: Some(50) });
+        assert_eq!(ic.stats(InterruptSource::Comparator).count, 0);
+        assert_eq!(ic.last_source(), Some(InterruptSource::Timer1Overflow));
+        assert_eq!(ic.total_count(), 3);
+        
+        ic.reset();
+        assert_eq!(ic.total_count(), 0);
+    }
+    
+    #[test]
+    fn test_int_pin_edge_select() {
+        let mut ic = InterruptController::new();
+        
+        // INTEDG set: rising edges only
+        assert!(ic.sample_int_pin(true, 0x40));
+        assert!(!ic.sample_int_pin(true, 0x40));
+        assert!(!ic.sample_int_pin(false, 0x40));
+        
+        // INTEDG clear: falling edges only
+        assert!(!ic.sample_int_pin(true, 0x00));
+        assert!(ic.sample_int_pin(false, 0x00));
+    }
+    
+    #[test]
+    fn test_enable_and_flag_bits() {
+        let ic = InterruptController::new();
+        
+        // Each source's enable and flag bits alone (with GIE/PEIE) trigger it
+        for source in InterruptSource::ALL {
+            let mut regs = [0xC0u8, 0x00, 0x00]; // INTCON, PIE1, PIR1
+            for (register, bit) in [source.enable_bit(), source.flag_bit()] {
+                let index = match register {
+                    registers::INTCON => 0,
+                    registers::PIE1 => 1,
+                    _ => 2,
+                };
+                regs[index] |= 1 << bit;
+            }
+            assert_eq!(ic.check_interrupts(regs[0], regs[1], regs[2]), Some(source));
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,145 @@
+//! Interrupt latency and ISR timing
+//!
+//! For every interrupt taken, `InterruptTiming` notes the cycle its flag
+//! was raised, the cycle the vector was taken and the cycle RETFIE
+//! finished, and keeps min/avg/max of the latency (flag to vector) and the
+//! ISR duration (vector to end of RETFIE) per source. Flags raised by a
+//! timer overflow are timestamped at the exact overflow cycle; flags set
+//! any other way (e.g. by firmware) are seen at the next instruction
+//! boundary.
+
+use crate::cpu::registers;
+use crate::interrupt::InterruptSource;
+
+/// Min/avg/max of a set of cycle counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CycleStats {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub total: u64,
+}
+
+impl CycleStats {
+    /// Add a sample
+    pub fn record(&mut self, cycles: u64) {
+        if self.count == 0 || cycles < self.min {
+            self.min = cycles;
+        }
+        self.max = self.max.max(cycles);
+        self.total += cycles;
+        self.count += 1;
+    }
+
+    /// Mean of the samples (0.0 if there are none)
+    pub fn average(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total as f64 / self.count as f64
+        }
+    }
+}
+
+/// Timing of one interrupt source
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceTiming {
+    /// Cycles from the flag being raised to the vector being taken
+    pub latency: CycleStats,
+    /// Cycles from the vector to the end of RETFIE
+    pub isr: CycleStats,
+}
+
+/// Interrupt timing tracker
+#[derive(Debug, Clone, Default)]
+pub struct InterruptTiming {
+    /// Cycle each currently raised flag went up, indexed like `InterruptSource::ALL`
+    raised: [Option<u64>; InterruptSource::ALL.len()],
+    /// Source and entry cycle of the ISR being executed
+    active: Option<(InterruptSource, u64)>,
+    sources: [SourceTiming; InterruptSource::ALL.len()],
+}
+
+impl InterruptTiming {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note that the flag of `source` was raised at `cycle`
+    ///
+    /// Ignored if the flag was already up.
+    pub fn flag_raised(&mut self, source: InterruptSource, cycle: u64) {
+        self.raised[source.index()].get_or_insert(cycle);
+    }
+
+    /// Update the raised flags from INTCON and PIR1 at `cycle`
+    pub fn observe_flags(&mut self, cycle: u64, intcon: u8, pir1: u8) {
+        for source in InterruptSource::ALL {
+            let (register, bit) = source.flag_bit();
+            let value = if register == registers::PIR1 { pir1 } else { intcon };
+            if value & (1 << bit) != 0 {
+                self.flag_raised(source, cycle);
+            } else {
+                self.raised[source.index()] = None;
+            }
+        }
+    }
+
+    /// The vector was taken for `source` at `cycle`
+    pub fn enter(&mut self, source: InterruptSource, cycle: u64) {
+        let raised = self.raised[source.index()].take().unwrap_or(cycle);
+        self.sources[source.index()].latency.record(cycle.saturating_sub(raised));
+        self.active = Some((source, cycle));
+    }
+
+    /// RETFIE finished at `cycle`
+    pub fn exit(&mut self, cycle: u64) {
+        if let Some((source, entered)) = self.active.take() {
+            self.sources[source.index()].isr.record(cycle.saturating_sub(entered));
+        }
+    }
+
+    /// Timing of one source
+    pub fn source(&self, source: InterruptSource) -> &SourceTiming {
+        &self.sources[source.index()]
+    }
+
+    /// Source of the ISR currently executing
+    pub fn active(&self) -> Option<InterruptSource> {
+        self.active.map(|(source, _)| source)
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_and_isr_time() {
+        let mut timing = InterruptTiming::new();
+
+        // T0IF raised at 100, vector at 103, RETFIE done at 120
+        timing.flag_raised(InterruptSource::Timer0Overflow, 100);
+        timing.observe_flags(102, 0x04, 0x00);
+        timing.enter(InterruptSource::Timer0Overflow, 103);
+        assert_eq!(timing.active(), Some(InterruptSource::Timer0Overflow));
+        timing.observe_flags(105, 0x00, 0x00);
+        timing.exit(120);
+
+        // Second occurrence, flag seen by polling only
+        timing.observe_flags(200, 0x04, 0x00);
+        timing.enter(InterruptSource::Timer0Overflow, 201);
+        timing.exit(211);
+
+        let t0 = timing.source(InterruptSource::Timer0Overflow);
+        assert_eq!((t0.latency.min, t0.latency.max, t0.latency.count), (1, 3, 2));
+        assert_eq!(t0.latency.average(), 2.0);
+        assert_eq!((t0.isr.min, t0.isr.max), (10, 17));
+        assert_eq!(timing.source(InterruptSource::GpioChange).isr.count, 0);
+        assert_eq!(timing.active(), None);
+    }
+}
@@ -10,6 +10,9 @@
 //! - GPIO ports
 //! - Timers
 //! - Interrupts
+//! - PIC12F683 support: 2K program memory and CCP PWM output
+//! - PIC16F84A support: PORTA/PORTB and the RB0/INT interrupt pin
+//! - PIC10F200/202 support: the 12-bit baseline instruction set
 //! 
 //! Reference: PIC12F629/675 Data Sheet (DS41190G)
 
@@ -21,21 +24,105 @@ pub mod simulator;
 pub mod debugger;
 pub mod cli;
 pub mod hexloader;
+pub mod srec;
+pub mod elf;
+pub mod coff;
 pub mod gpio;
 pub mod timer;
 pub mod interrupt;
 pub mod wdt;
+pub mod trace;
+pub mod history;
+pub mod callstack;
+pub mod profiler;
+pub mod coverage;
+pub mod symbols;
+pub mod wave;
+pub mod stimulus;
+pub mod logic;
+pub mod watchpoint;
+pub mod breakpoint;
+pub mod expr;
+pub mod stopwatch;
+pub mod state;
+pub mod events;
+pub mod error;
+pub mod clock;
+pub mod board;
+pub mod component;
+pub mod circuit;
+pub mod uart;
+pub mod testing;
+pub mod golden;
+pub mod server;
+pub mod replay;
+pub mod halt;
+pub mod latency;
+pub mod scheduler;
+pub mod power;
+pub mod device;
+pub mod ccp;
+pub mod assembler;
+pub mod batch;
+pub mod completion;
+pub mod tracefile;
+pub mod macros;
+#[cfg(feature = "gui")]
 pub mod gui;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use memory::Memory;
-pub use cpu::Cpu;
-pub use instruction::{Instruction, InstructionDecoder};
+pub use cpu::{Cpu, ResetReason};
+pub use instruction::{Core, Instruction, InstructionDecoder};
 pub use executor::Executor;
-pub use simulator::{Simulator, SimulatorState};
+pub use simulator::{Simulator, SimulatorState, RunOutcome};
 pub use debugger::Debugger;
 pub use cli::Cli;
-pub use hexloader::{HexLoader, HexProgram, HexRecord};
-pub use gpio::{Gpio, PinState};
+pub use hexloader::{Difference, HexLoader, HexProgram, HexRecord, LoadReport, LoadWarning, ParseMode, ParseOptions};
+pub use srec::{SrecLoader, SRecord};
+pub use elf::{ElfLoader, ElfFirmware};
+pub use coff::{CoffLoader, CoffFirmware};
+pub use gpio::{Gpio, PinState, PortSpec};
 pub use timer::{Timer0, Timer1, TimerController};
-pub use interrupt::{InterruptController, InterruptSource};
-pub use wdt::Wdt;
\ No newline at end of file
+pub use interrupt::{InterruptController, InterruptSource, InterruptStats};
+pub use wdt::{Wdt, WdtJitter, WdtDistribution};
+pub use trace::{TraceBuffer, TraceEntry};
+pub use history::History;
+pub use callstack::{CallStack, CallFrame};
+pub use profiler::Profiler;
+pub use coverage::Coverage;
+pub use symbols::{SymbolTable, SourceLocation};
+pub use wave::VcdWriter;
+pub use stimulus::{Stimulus, StimulusRule};
+pub use logic::{LogicAnalyzer, Marker, MarkerKind, PwmMeasurement, Transition};
+pub use watchpoint::{Watchpoint, WatchKind, WatchHit};
+pub use breakpoint::{Breakpoint, SavedBreakpoint};
+pub use expr::Expr;
+pub use stopwatch::{Stopwatch, StopwatchReading};
+pub use state::{MachineState, StateRecord};
+pub use events::{SimEvent, ObserverId};
+pub use error::{SimError, HexError, SrecError, ElfError, CoffError, DecodeError, AsmError};
+pub use error::{SymbolError, StimulusError, CircuitError, StateError, ReplayError};
+pub use error::{GoldenError, BreakpointError, BoardError, HaltConditionError};
+pub use clock::{Clock, ClockSource};
+pub use board::{Board, Net, NetConflict, PinRef};
+pub use component::{Button, Component, ComponentId, Components, Led, Potentiometer, SegmentDrive, SevenSegment};
+pub use circuit::{Circuit, ComponentSpec};
+pub use uart::SoftUart;
+pub use golden::{GoldenTrace, GoldenEntry, Divergence};
+pub use server::Server;
+pub use replay::{InputLog, InputEvent, InputKind};
+pub use halt::{HaltPolicy, HaltReason};
+pub use latency::{InterruptTiming, SourceTiming, CycleStats};
+pub use scheduler::{Scheduler, EventKind};
+pub use power::{PowerMeter, PowerModel, PowerMode};
+pub use device::{Device, DeviceModel, Peripherals};
+pub use ccp::Ccp;
+pub use assembler::Assembly;
+pub use batch::{Batch, BatchOutcome};
+pub use completion::Completions;
+pub use tracefile::TraceFile;
+pub use macros::Macros;
+#[cfg(feature = "wasm")]
+pub use wasm::{WasmSimulator, CpuState};
\ No newline at end of file
@@ -0,0 +1,357 @@
+//! Logic analyzer
+//!
+//! Captures every pin level transition with its cycle timestamp and
+//! provides pulse-width, period and duty-cycle measurements on the capture.
+//! Timer0 overflows and interrupt entries are kept alongside as markers,
+//! so pin activity can be lined up with the events that caused it.
+
+use std::collections::VecDeque;
+
+use crate::interrupt::InterruptSource;
+
+/// Default number of transitions kept
+pub const DEFAULT_CAPTURE_CAPACITY: usize = 65536;
+
+/// Number of pins captured: the first two ports (see `Gpio::read_pins`)
+const PIN_COUNT: u8 = 16;
+
+/// A single level change on a pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub cycle: u64,
+    pub pin: u8,
+    pub level: bool,
+}
+
+/// Kind of event marked in the capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerKind {
+    Timer0Overflow,
+    InterruptEntered(InterruptSource),
+}
+
+/// An event marked in the capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Marker {
+    pub cycle: u64,
+    pub kind: MarkerKind,
+}
+
+/// Frequency and pulse measurements of a pin over its recent periods
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PwmMeasurement {
+    /// Average period in cycles
+    pub period: f64,
+    /// Fraction of the time the pin was high
+    pub duty_cycle: f64,
+    /// Average high and low pulse widths in cycles
+    pub high_width: f64,
+    pub low_width: f64,
+    /// Periods measured
+    pub periods: usize,
+    /// Cycle of the last rising edge measured
+    pub last_edge: u64,
+}
+
+impl PwmMeasurement {
+    /// Frequency in Hz at `cycles_per_second` instruction cycles per second
+    pub fn frequency(&self, cycles_per_second: f64) -> f64 {
+        cycles_per_second / self.period
+    }
+}
+
+/// Pin transition capture buffer
+#[derive(Debug, Clone)]
+pub struct LogicAnalyzer {
+    transitions: VecDeque<Transition>,
+    markers: VecDeque<Marker>,
+    capacity: usize,
+    levels: Option<u16>,
+    start_levels: u16,
+    start_cycle: u64,
+}
+
+impl LogicAnalyzer {
+    /// Create an analyzer with the default capacity
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPTURE_CAPACITY)
+    }
+
+    /// Create an analyzer keeping at most `capacity` transitions
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            transitions: VecDeque::new(),
+            markers: VecDeque::new(),
+            capacity,
+            levels: None,
+            start_levels: 0,
+            start_cycle: 0,
+        }
+    }
+
+    /// Record the pin levels (bit n for pin n) at `cycle`
+    pub fn sample(&mut self, cycle: u64, levels: u16) {
+        let Some(previous) = self.levels else {
+            self.levels = Some(levels);
+            self.start_levels = levels;
+            self.start_cycle = cycle;
+            return;
+        };
+
+        let changed = previous ^ levels;
+        if changed == 0 {
+            return;
+        }
+
+        for pin in (0..PIN_COUNT).filter(|&pin| changed & (1 << pin) != 0) {
+            if self.transitions.len() >= self.capacity {
+                self.transitions.pop_front();
+            }
+            self.transitions.push_back(Transition {
+                cycle,
+                pin,
+                level: levels & (1 << pin) != 0,
+            });
+        }
+        self.levels = Some(levels);
+    }
+
+    /// Mark an event at `cycle`
+    pub fn mark(&mut self, cycle: u64, kind: MarkerKind) {
+        if self.markers.len() >= self.capacity {
+            self.markers.pop_front();
+        }
+        self.markers.push_back(Marker { cycle, kind });
+    }
+
+    /// Drop transitions at or after `cycle` and continue from `levels`
+    /// Used when rewinding execution to an earlier point
+    pub fn rewind(&mut self, cycle: u64, levels: u16) {
+        while self.transitions.back().is_some_and(|t| t.cycle >= cycle) {
+            self.transitions.pop_back();
+        }
+        while self.markers.back().is_some_and(|m| m.cycle >= cycle) {
+            self.markers.pop_back();
+        }
+        if self.transitions.is_empty() && cycle <= self.start_cycle {
+            self.levels = None;
+        } else {
+            self.levels = Some(levels);
+        }
+    }
+
+    /// Remove all captured data
+    pub fn clear(&mut self) {
+        self.transitions.clear();
+        self.markers.clear();
+        self.levels = None;
+    }
+
+    /// All captured transitions, oldest first
+    pub fn transitions(&self) -> impl DoubleEndedIterator<Item = &Transition> {
+        self.transitions.iter()
+    }
+
+    /// All marked events, oldest first
+    pub fn markers(&self) -> impl DoubleEndedIterator<Item = &Marker> {
+        self.markers.iter()
+    }
+
+    /// Cycle of the first sample, if anything has been captured
+    pub fn start_cycle(&self) -> Option<u64> {
+        self.levels.map(|_| self.start_cycle)
+    }
+
+    /// Transitions of a single pin, oldest first
+    pub fn pin_transitions(&self, pin: u8) -> impl DoubleEndedIterator<Item = &Transition> {
+        self.transitions.iter().filter(move |t| t.pin == pin)
+    }
+
+    /// Transitions with `start <= cycle < end`
+    pub fn transitions_between(&self, start: u64, end: u64) -> impl Iterator<Item = &Transition> {
+        self.transitions.iter().filter(move |t| t.cycle >= start && t.cycle < end)
+    }
+
+    /// Level of `pin` at `cycle`, if the capture covers it
+    pub fn level_at(&self, pin: u8, cycle: u64) -> Option<bool> {
+        if self.levels.is_none() || cycle < self.start_cycle {
+            return None;
+        }
+
+        let last = self.pin_transitions(pin).rev().find(|t| t.cycle <= cycle);
+        match last {
+            Some(t) => Some(t.level),
+            None => Some(self.start_levels & (1 << pin) != 0),
+        }
+    }
+
+    /// Widths of completed pulses at `level` on `pin`, in cycles
+    pub fn pulse_widths(&self, pin: u8, level: bool) -> Vec<u64> {
+        let edges: Vec<&Transition> = self.pin_transitions(pin).collect();
+        edges.windows(2)
+            .filter(|pair| pair[0].level == level)
+            .map(|pair| pair[1].cycle - pair[0].cycle)
+            .collect()
+    }
+
+    /// Width of the most recent completed pulse at `level` on `pin`
+    pub fn last_pulse_width(&self, pin: u8, level: bool) -> Option<u64> {
+        self.pulse_widths(pin, level).last().copied()
+    }
+
+    /// Periods between successive rising edges on `pin`, in cycles
+    pub fn periods(&self, pin: u8) -> Vec<u64> {
+        let rising: Vec<u64> = self.pin_transitions(pin)
+            .filter(|t| t.level)
+            .map(|t| t.cycle)
+            .collect();
+        rising.windows(2).map(|pair| pair[1] - pair[0]).collect()
+    }
+
+    /// Average period of `pin` in cycles
+    pub fn average_period(&self, pin: u8) -> Option<f64> {
+        let periods = self.periods(pin);
+        if periods.is_empty() {
+            None
+        } else {
+            Some(periods.iter().sum::<u64>() as f64 / periods.len() as f64)
+        }
+    }
+
+    /// Fraction of time `pin` was high over its completed periods
+    pub fn duty_cycle(&self, pin: u8) -> Option<f64> {
+        let edges: Vec<&Transition> = self.pin_transitions(pin).collect();
+        let first_rise = edges.iter().position(|t| t.level)?;
+        let last_rise = edges.iter().rposition(|t| t.level)?;
+        if last_rise <= first_rise {
+            return None;
+        }
+
+        let span = &edges[first_rise..=last_rise];
+        let high: u64 = span.windows(2)
+            .filter(|pair| pair[0].level)
+            .map(|pair| pair[1].cycle - pair[0].cycle)
+            .sum();
+        let total = span[span.len() - 1].cycle - span[0].cycle;
+        Some(high as f64 / total as f64)
+    }
+
+    /// Measure `pin` over its last `max_periods` complete periods
+    /// (rising edge to rising edge); None until two rising edges are captured
+    pub fn measure(&self, pin: u8, max_periods: usize) -> Option<PwmMeasurement> {
+        let edges: Vec<&Transition> = self.pin_transitions(pin).collect();
+        let rising: Vec<usize> = edges.iter().enumerate()
+            .filter(|(_, t)| t.level)
+            .map(|(index, _)| index)
+            .collect();
+        let oldest = rising.len().saturating_sub(max_periods.max(1) + 1);
+        let periods = rising.len().checked_sub(oldest + 1).filter(|&periods| periods > 0)?;
+
+        let span = &edges[rising[oldest]..=rising[rising.len() - 1]];
+        let high: u64 = span.windows(2)
+            .filter(|pair| pair[0].level)
+            .map(|pair| pair[1].cycle - pair[0].cycle)
+            .sum();
+        let total = span[span.len() - 1].cycle - span[0].cycle;
+        let n = periods as f64;
+        Some(PwmMeasurement {
+            period: total as f64 / n,
+            duty_cycle: high as f64 / total as f64,
+            high_width: high as f64 / n,
+            low_width: (total - high) as f64 / n,
+            periods,
+            last_edge: span[span.len() - 1].cycle,
+        })
+    }
+
+    /// Number of transitions held
+    pub fn len(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Check if no transitions are held
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty()
+    }
+}
+
+impl Default for LogicAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Square wave on GP0: high for 3 cycles, low for 5
+    fn square_wave() -> LogicAnalyzer {
+        let mut analyzer = LogicAnalyzer::new();
+        for cycle in 0..40u64 {
+            let level = if cycle % 8 < 3 { 0x01 } else { 0x00 };
+            analyzer.sample(cycle, level);
+        }
+        analyzer
+    }
+
+    #[test]
+    fn test_measurements() {
+        let analyzer = square_wave();
+
+        assert_eq!(analyzer.pulse_widths(0, true), vec![3, 3, 3, 3]);
+        assert_eq!(analyzer.last_pulse_width(0, false), Some(5));
+        assert_eq!(analyzer.periods(0), vec![8, 8, 8]);
+        assert_eq!(analyzer.average_period(0), Some(8.0));
+        assert_eq!(analyzer.duty_cycle(0), Some(0.375));
+        assert_eq!(analyzer.periods(1), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn test_measure() {
+        let mut analyzer = square_wave();
+        // Then high for 2 cycles, low for 2
+        for cycle in 40..56u64 {
+            analyzer.sample(cycle, if cycle % 4 < 2 { 0x01 } else { 0x00 });
+        }
+
+        let recent = analyzer.measure(0, 2).unwrap();
+        assert_eq!((recent.period, recent.duty_cycle, recent.periods), (4.0, 0.5, 2));
+        assert_eq!((recent.high_width, recent.low_width, recent.last_edge), (2.0, 2.0, 52));
+        assert_eq!(recent.frequency(1_000_000.0), 250_000.0);
+
+        let all = analyzer.measure(0, 100).unwrap();
+        assert_eq!(all.periods, 7);
+        assert_eq!(all.period, 44.0 / 7.0);
+        assert!(analyzer.measure(1, 4).is_none());
+    }
+
+    #[test]
+    fn test_level_at_and_rewind() {
+        let mut analyzer = square_wave();
+
+        assert_eq!(analyzer.level_at(0, 1), Some(true));
+        assert_eq!(analyzer.level_at(0, 12), Some(false));
+        assert_eq!(analyzer.level_at(1, 12), Some(false));
+
+        analyzer.rewind(16, 0x00);
+        assert_eq!(analyzer.pin_transitions(0).last().unwrap().cycle, 11);
+        analyzer.sample(16, 0x01);
+        assert_eq!(analyzer.transitions_between(12, 20).count(), 1);
+    }
+
+    #[test]
+    fn test_markers() {
+        let mut analyzer = square_wave();
+        assert_eq!(analyzer.start_cycle(), Some(0));
+        analyzer.mark(10, MarkerKind::Timer0Overflow);
+        analyzer.mark(12, MarkerKind::InterruptEntered(InterruptSource::Timer0Overflow));
+        assert_eq!(analyzer.markers().count(), 2);
+
+        analyzer.rewind(12, 0x00);
+        let kinds: Vec<MarkerKind> = analyzer.markers().map(|m| m.kind).collect();
+        assert_eq!(kinds, vec![MarkerKind::Timer0Overflow]);
+        analyzer.clear();
+        assert_eq!((analyzer.markers().count(), analyzer.start_cycle()), (0, None));
+    }
+}
@@ -0,0 +1,199 @@
+//! Command aliases and user macros for the CLI
+//!
+//! An alias renames a command line prefix (`alias bl break loop`), so
+//! `bl` runs `break loop` with any further words appended. A macro runs a
+//! list of commands separated by `;`. Both are kept in a plain text file,
+//! one definition per line, in the same syntax as the commands that create
+//! them:
+//!
+//! ```text
+//! # pic_simulator macros
+//! alias bl break loop
+//! define blinkcheck step 10; gpio; reg
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::completion::COMMANDS;
+
+/// First line of a macro file
+pub const MACROS_HEADER: &str = "# pic_simulator macros";
+
+/// Aliases and macros by name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Macros {
+    aliases: BTreeMap<String, String>,
+    macros: BTreeMap<String, Vec<String>>,
+}
+
+/// What a user-defined command name stands for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expansion {
+    /// The command line with the alias replaced
+    Alias(String),
+    /// Commands to run in order
+    Macro(Vec<String>),
+}
+
+impl Macros {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define `name` as an alias for `command`, replacing any alias or
+    /// macro of that name
+    pub fn add_alias(&mut self, name: &str, command: &str) -> Result<(), String> {
+        check_name(name)?;
+        let command = command.trim();
+        if command.is_empty() {
+            return Err(format!("Empty alias: {}", name));
+        }
+        self.macros.remove(name);
+        self.aliases.insert(name.to_string(), command.to_string());
+        Ok(())
+    }
+
+    /// Define `name` as a macro running the `;`-separated `commands`,
+    /// replacing any alias or macro of that name
+    pub fn add_macro(&mut self, name: &str, commands: &str) -> Result<(), String> {
+        check_name(name)?;
+        let commands: Vec<String> = commands.split(';')
+            .map(str::trim)
+            .filter(|command| !command.is_empty())
+            .map(str::to_string)
+            .collect();
+        if commands.is_empty() {
+            return Err(format!("Empty macro: {}", name));
+        }
+        self.aliases.remove(name);
+        self.macros.insert(name.to_string(), commands);
+        Ok(())
+    }
+
+    /// Remove the alias or macro `name`; returns false if there is none
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.aliases.remove(name).is_some() | self.macros.remove(name).is_some()
+    }
+
+    /// Aliases by name
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.aliases.iter().map(|(name, command)| (name.as_str(), command.as_str()))
+    }
+
+    /// Macros by name
+    pub fn macros(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.macros.iter().map(|(name, commands)| (name.as_str(), commands.as_slice()))
+    }
+
+    /// Names of all aliases and macros
+    pub fn names(&self) -> Vec<String> {
+        self.aliases.keys().chain(self.macros.keys()).cloned().collect()
+    }
+
+    /// Expand a command line whose first word is an alias or macro
+    pub fn expand(&self, line: &str) -> Option<Expansion> {
+        let line = line.trim();
+        let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if let Some(command) = self.aliases.get(name) {
+            let rest = rest.trim();
+            return Some(Expansion::Alias(if rest.is_empty() {
+                command.clone()
+            } else {
+                format!("{} {}", command, rest)
+            }));
+        }
+        self.macros.get(name).map(|commands| Expansion::Macro(commands.clone()))
+    }
+
+    /// Parse a macro file
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut macros = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |e: String| format!("Line {}: {}", index + 1, e);
+            let (keyword, rest) = line.split_once(char::is_whitespace)
+                .ok_or_else(|| error(format!("expected a definition, found '{}'", line)))?;
+            let (name, body) = rest.trim_start().split_once(char::is_whitespace)
+                .ok_or_else(|| error(format!("missing body for '{}'", rest.trim())))?;
+            match keyword {
+                "alias" => macros.add_alias(name, body),
+                "define" => macros.add_macro(name, body),
+                _ => Err(format!("unknown keyword '{}'", keyword)),
+            }.map_err(error)?;
+        }
+        Ok(macros)
+    }
+
+    /// Text of a macro file holding these definitions
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", MACROS_HEADER);
+        for (name, command) in self.aliases() {
+            text.push_str(&format!("alias {} {}\n", name, command));
+        }
+        for (name, commands) in self.macros() {
+            text.push_str(&format!("define {} {}\n", name, commands.join("; ")));
+        }
+        text
+    }
+
+    /// Load a macro file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Write the definitions to a macro file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_text())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// Names are single words that do not hide a built-in command
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(format!("Invalid name: {}", name));
+    }
+    if COMMANDS.contains(&name) {
+        return Err(format!("{} is a built-in command", name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_macros() {
+        let mut macros = Macros::new();
+        macros.add_alias("bl", "break loop").unwrap();
+        macros.add_macro("blinkcheck", "step 10; gpio;reg;").unwrap();
+        assert!(macros.add_alias("step", "run").is_err());
+        assert!(macros.add_macro("x y", "reg").is_err());
+
+        assert_eq!(macros.expand("bl"), Some(Expansion::Alias("break loop".to_string())));
+        assert_eq!(macros.expand("bl 2"), Some(Expansion::Alias("break loop 2".to_string())));
+        assert_eq!(macros.expand("blinkcheck"), Some(Expansion::Macro(vec![
+            "step 10".to_string(), "gpio".to_string(), "reg".to_string(),
+        ])));
+        assert_eq!(macros.expand("step"), None);
+
+        let text = macros.to_text();
+        assert_eq!(text, format!("{}\nalias bl break loop\ndefine blinkcheck step 10; gpio; reg\n", MACROS_HEADER));
+        assert_eq!(Macros::parse(&text).unwrap(), macros);
+        assert!(Macros::parse("macro x reg\n").unwrap_err().starts_with("Line 1"));
+
+        assert!(macros.remove("bl"));
+        assert!(!macros.remove("bl"));
+        assert_eq!(macros.names(), vec!["blinkcheck".to_string()]);
+    }
+}
@@ -6,41 +6,222 @@ pub mod simulator;
 pub mod debugger;
 pub mod cli;
 pub mod hexloader;
+pub mod srec;
+pub mod elf;
+pub mod coff;
 pub mod gpio;
 pub mod timer;
 pub mod interrupt;
 pub mod wdt;
+pub mod trace;
+pub mod history;
+pub mod callstack;
+pub mod profiler;
+pub mod coverage;
+pub mod symbols;
+pub mod wave;
+pub mod stimulus;
+pub mod logic;
+pub mod watchpoint;
+pub mod breakpoint;
+pub mod expr;
+pub mod stopwatch;
+pub mod state;
+pub mod events;
+pub mod error;
+pub mod clock;
+pub mod board;
+pub mod component;
+pub mod circuit;
+pub mod uart;
+pub mod testing;
+pub mod golden;
+pub mod server;
+pub mod replay;
+pub mod halt;
+pub mod latency;
+pub mod scheduler;
+pub mod power;
+pub mod device;
+pub mod ccp;
+pub mod assembler;
+pub mod batch;
+pub mod completion;
+pub mod tracefile;
+pub mod macros;
+#[cfg(feature = "gui")]
 pub mod gui;
 
 pub use memory::Memory;
-pub use cpu::Cpu;
-pub use instruction::{Instruction, InstructionDecoder};
+pub use cpu::{Cpu, ResetReason};
+pub use instruction::{Core, Instruction, InstructionDecoder};
 pub use executor::Executor;
-pub use simulator::{Simulator, SimulatorState};
+pub use simulator::{Simulator, SimulatorState, RunOutcome};
 pub use debugger::Debugger;
 pub use cli::Cli;
-pub use hexloader::{HexLoader, HexProgram, HexRecord};
-pub use gpio::{Gpio, PinState};
+pub use hexloader::{Difference, HexLoader, HexProgram, HexRecord, LoadReport, LoadWarning, ParseMode, ParseOptions};
+pub use srec::{SrecLoader, SRecord};
+pub use elf::{ElfLoader, ElfFirmware};
+pub use coff::{CoffLoader, CoffFirmware};
+pub use gpio::{Gpio, PinState, PortSpec};
 pub use timer::{Timer0, Timer1, TimerController};
-pub use interrupt::{InterruptController, InterruptSource};
-pub use wdt::Wdt;
+pub use interrupt::{InterruptController, InterruptSource, InterruptStats};
+pub use wdt::{Wdt, WdtJitter, WdtDistribution};
+pub use trace::{TraceBuffer, TraceEntry};
+pub use history::History;
+pub use callstack::{CallStack, CallFrame};
+pub use profiler::Profiler;
+pub use coverage::Coverage;
+pub use symbols::{SymbolTable, SourceLocation};
+pub use wave::VcdWriter;
+pub use stimulus::{Stimulus, StimulusRule};
+pub use logic::{LogicAnalyzer, Marker, MarkerKind, PwmMeasurement, Transition};
+pub use watchpoint::{Watchpoint, WatchKind, WatchHit};
+pub use breakpoint::{Breakpoint, SavedBreakpoint};
+pub use expr::Expr;
+pub use stopwatch::{Stopwatch, StopwatchReading};
+pub use state::{MachineState, StateRecord};
+pub use events::{SimEvent, ObserverId};
+pub use error::{SimError, HexError, SrecError, ElfError, CoffError, DecodeError, AsmError};
+pub use error::{SymbolError, StimulusError, CircuitError, StateError, ReplayError};
+pub use error::{GoldenError, BreakpointError, BoardError, HaltConditionError};
+pub use clock::{Clock, ClockSource};
+pub use board::{Board, Net, NetConflict, PinRef};
+pub use component::{Button, Component, ComponentId, Components, Led, Potentiometer, SegmentDrive, SevenSegment};
+pub use circuit::{Circuit, ComponentSpec};
+pub use uart::SoftUart;
+pub use golden::{GoldenTrace, GoldenEntry, Divergence};
+pub use server::Server;
+pub use replay::{InputLog, InputEvent, InputKind};
+pub use halt::{HaltPolicy, HaltReason};
+pub use latency::{InterruptTiming, SourceTiming, CycleStats};
+pub use scheduler::{Scheduler, EventKind};
+pub use power::{PowerMeter, PowerModel, PowerMode};
+pub use device::{Device, DeviceModel, Peripherals};
+pub use ccp::Ccp;
+pub use assembler::Assembly;
+pub use batch::{Batch, BatchOutcome};
+pub use completion::Completions;
+pub use tracefile::TraceFile;
+pub use macros::Macros;
 
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Mutex;
+
+use clap::Parser;
+use tracing::level_filters::LevelFilter;
+#[cfg(feature = "gui")]
 use eframe::egui;
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
+/// PIC12F629/675 simulator
+///
+/// Without options, starts the interactive debugger. With --run-cycles or
+/// --dump, runs non-interactively and exits with 0 when the cycles ran, 1
+/// on load or execution errors and 3 when stopped at a breakpoint. With
+/// --script, runs debugger commands from a file instead of the keyboard.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Start the graphical simulator
+    #[arg(long, conflicts_with_all = ["server", "run_cycles", "dump"])]
+    gui: bool,
+
+    /// Serve the remote-control protocol on ADDRESS
+    #[arg(long, value_name = "ADDRESS", num_args = 0..=1, default_missing_value = server::DEFAULT_ADDRESS,
+        conflicts_with_all = ["run_cycles", "dump"])]
+    server: Option<String>,
+
+    /// Firmware to load (HEX, S-record, ELF, COFF or assembly)
+    #[arg(long, value_name = "FILE")]
+    load: Option<PathBuf>,
+
+    /// Run this many instruction cycles, then exit
+    #[arg(long, value_name = "CYCLES")]
+    run_cycles: Option<u64>,
+
+    /// Print data memory after running, e.g. 0x20..0x40
+    #[arg(long, value_name = "RANGE", value_parser = batch::parse_range)]
+    dump: Option<Range<u16>>,
+
+    /// Set a breakpoint at an address or label (repeatable)
+    #[arg(long = "break", value_name = "ADDRESS")]
+    breakpoints: Vec<String>,
+
+    /// Stop at the first breakpoint hit, with exit code 3
+    #[arg(long)]
+    exit_on_breakpoint: bool,
+
+    /// Run REPL commands from FILE, then exit (1 if a command failed)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["gui", "server", "run_cycles", "dump"])]
+    script: Option<PathBuf>,
+
+    /// Print each script command before running it
+    #[arg(long, requires = "script")]
+    echo: bool,
+
+    /// End the script at the first failing command
+    #[arg(long, requires = "script")]
+    stop_on_error: bool,
+
+    /// Print reg, dump, info and gpio output as JSON
+    #[arg(long, conflicts_with_all = ["gui", "server", "run_cycles", "dump"])]
+    json: bool,
+
+    /// Diagnostics to log: off, error, warn, info, debug or trace
+    /// [default: info with --server, warn otherwise]
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<LevelFilter>,
+
+    /// Append log messages to FILE instead of printing them to stderr
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let level = args.log_level.unwrap_or(if args.server.is_some() { LevelFilter::INFO } else { LevelFilter::WARN });
+    if let Err(e) = init_logging(level, args.log_file.as_deref()) {
+        eprintln!("Error: {}", e);
+        return ExitCode::from(2);
+    }
     
-    if args.len() > 1 && args[1] == "--gui" {
-        run_gui();
+    if args.gui {
+        run_gui(args.load);
+    } else if let Some(address) = &args.server {
+        return run_server(address);
+    } else if args.run_cycles.is_some() || args.dump.is_some() {
+        return run_batch(args);
+    } else if let Some(script) = &args.script {
+        return run_script(script, &args);
     } else {
-        run_cli();
+        run_cli(args.load, args.json);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Send the simulator's diagnostics at `level` and above to stderr, or
+/// with timestamps to `file`
+fn init_logging(level: LevelFilter, file: Option<&Path>) -> Result<(), String> {
+    let builder = tracing_subscriber::fmt().with_max_level(level);
+    match file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new().create(true).append(true).open(path)
+                .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            builder.with_ansi(false).with_writer(Mutex::new(file)).init();
+        }
+        None => builder.without_time().with_writer(std::io::stderr).init(),
     }
+    Ok(())
 }
 
-fn run_gui() {
+#[cfg(feature = "gui")]
+fn run_gui(firmware: Option<PathBuf>) {
+    let session = gui::Session::load();
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1280.0, 720.0])
+            .with_inner_size(session.window_size.unwrap_or([1280.0, 720.0]))
             .with_title("PIC12F629/675 Simulator"),
         ..Default::default()
     };
@@ -48,11 +229,70 @@ fn run_gui() {
     let _ = eframe::run_native(
         "pic_simulator",
         options,
-        Box::new(|cc| Ok(Box::new(gui::SimulatorApp::new(cc)))),
+        Box::new(|cc| {
+            let mut app = gui::SimulatorApp::new(cc, session);
+            if let Some(path) = firmware {
+                app.load_firmware_path(path);
+            }
+            Ok(Box::new(app))
+        }),
     );
 }
 
-fn run_cli() {
+#[cfg(not(feature = "gui"))]
+fn run_gui(_firmware: Option<PathBuf>) {
+    eprintln!("This build has no GUI; rebuild with the 'gui' feature");
+}
+
+fn run_server(address: &str) -> ExitCode {
+    let mut sim = Simulator::new();
+    sim.reset();
+    match Server::new(sim).serve(address) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Server error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_batch(args: Args) -> ExitCode {
+    let batch = Batch {
+        load: args.load,
+        run_cycles: args.run_cycles.unwrap_or(0),
+        breakpoints: args.breakpoints,
+        exit_on_breakpoint: args.exit_on_breakpoint,
+        dump: args.dump,
+    };
+    let mut sim = Simulator::new();
+    sim.reset();
+    let outcome = batch.run(&mut sim);
+    if let BatchOutcome::Error(e) = &outcome {
+        eprintln!("Error: {}", e);
+    }
+    ExitCode::from(outcome.exit_code())
+}
+
+fn run_script(script: &Path, args: &Args) -> ExitCode {
     let mut cli = Cli::new();
+    cli.set_json_output(args.json);
+    if let Some(path) = &args.load
+        && !cli.load_firmware(path)
+    {
+        return ExitCode::FAILURE;
+    }
+    if cli.source(script, args.echo, args.stop_on_error) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn run_cli(firmware: Option<PathBuf>, json: bool) {
+    let mut cli = Cli::new();
+    cli.set_json_output(json);
+    if let Some(path) = firmware {
+        cli.load_firmware(&path);
+    }
     cli.run();
-}
\ No newline at end of file
+}
@@ -1,18 +1,31 @@
-/// PIC12F629/675 Memory System
-/// 
-/// Reference: Data Sheet Section 2.0 - Memory Organization (Page 9-20)
-/// 
-/// Memory Architecture:
-/// - Program Memory: 1024 x 14-bit words (Flash)
-/// - Data Memory: 128 bytes (RAM) with Bank switching
-/// - Stack: 8 levels x 13-bit (Hardware stack for PC)
-/// - EEPROM: 128 bytes (Non-volatile data storage)
+//! PIC12F629/675 Memory System
+//! 
+//! Reference: Data Sheet Section 2.0 - Memory Organization (Page 9-20)
+//! 
+//! Memory Architecture:
+//! - Program Memory: 1024 x 14-bit words (Flash); 2048 on the 12F683
+//! - Data Memory: 256 bytes of register file addressed as two banks of 128
+//! - Stack: 8 levels x 13-bit (Hardware stack for PC); 2 on the PIC10F2xx
+//! - EEPROM: 128 bytes (Non-volatile data storage); 256 on the 12F683
+
+use crate::error::{DecodeError, StateError};
+use crate::instruction::{Core, Instruction, InstructionDecoder};
+use crate::state::StateRecord;
 
 /// Program memory size: 1024 words of 14-bit instructions
 pub const PROGRAM_MEMORY_SIZE: usize = 1024;
 
+/// Largest program memory the 13-bit program counter can address
+pub const MAX_PROGRAM_MEMORY_SIZE: usize = 8192;
+
+/// Data memory bank size: 128 bytes (7-bit file addresses)
+pub const BANK_SIZE: usize = 0x80;
+
+/// Data memory banks: 2
+pub const DATA_BANKS: usize = 2;
+
 /// Data memory size: 256 bytes
-pub const DATA_MEMORY_SIZE: usize = 256;
+pub const DATA_MEMORY_SIZE: usize = BANK_SIZE * DATA_BANKS;
 
 /// Stack depth: 8 levels
 pub const STACK_DEPTH: usize = 8;
@@ -20,69 +33,192 @@ pub const STACK_DEPTH: usize = 8;
 /// EEPROM size: 128 bytes
 pub const EEPROM_SIZE: usize = 128;
 
+/// Sizes of a part's memories
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLayout {
+    /// Program memory words (a power of two)
+    pub program_words: usize,
+    /// Data memory banks of 128 bytes (1, 2 or 4)
+    pub banks: usize,
+    /// Hardware stack levels
+    pub stack_depth: usize,
+    /// EEPROM bytes (a power of two, or 0)
+    pub eeprom_bytes: usize,
+}
+
+impl Default for MemoryLayout {
+    /// The PIC12F629/675
+    fn default() -> Self {
+        Self {
+            program_words: PROGRAM_MEMORY_SIZE,
+            banks: DATA_BANKS,
+            stack_depth: STACK_DEPTH,
+            eeprom_bytes: EEPROM_SIZE,
+        }
+    }
+}
+
 /// Memory system for PIC12F629/675
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Memory {
-    /// Program memory (Flash): 1024 x 14-bit instructions
+    /// Program memory (Flash): 14-bit instructions (12-bit on baseline
+    /// parts), a power of two in size
     /// Reference: Section 2.1 Program Memory Organization
-    program_memory: [u16; PROGRAM_MEMORY_SIZE],
+    program_memory: Vec<u16>,
     
-    /// Data memory (RAM): 128 bytes with bank switching
+    /// Instruction set program memory is decoded with
+    core: Core,
+    
+    /// Data memory (register file), indexed by bank-resolved address
     /// Reference: Section 2.2 Data Memory Organization
     /// - Bank 0: 0x00-0x7F
-    /// - Bank 1: 0x80-0xFF (mirrors with different registers)
-    data_memory: [u8; DATA_MEMORY_SIZE],
+    /// - Bank 1: 0x80-0xFF (registers and RAM shared with bank 0 are
+    ///   stored at their bank 0 address)
+    /// - Banks 2 and 3: 0x100-0x1FF on four-bank parts
+    data_memory: Vec<u8>,
     
-    /// Hardware stack: 8 levels of 13-bit addresses
+    /// Hardware stack: 8 levels of 13-bit addresses by default
     /// Reference: Section 2.0 - 8-Level Deep Hardware Stack
-    stack: [u16; STACK_DEPTH],
+    stack: Vec<u16>,
     
-    /// Stack pointer (0-7)
+    /// Stack pointer (0 to the number of levels)
     stack_pointer: usize,
     
-    /// EEPROM data memory: 128 bytes
+    /// EEPROM data memory
     /// Reference: Section 8.0 Data EEPROM Memory
-    eeprom: [u8; EEPROM_SIZE],
+    eeprom: Vec<u8>,
+    
+    /// Decoded instructions parallel to program memory, filled on first
+    /// fetch and invalidated when program memory is written
+    #[cfg_attr(feature = "serde", serde(skip))]
+    decoded: Vec<Option<Instruction>>,
 }
 
 impl Memory {
     /// Create a new memory system with all memory initialized to zero
     pub fn new() -> Self {
+        Self::with_layout(MemoryLayout::default())
+    }
+    
+    /// Create a memory system with the sizes of `layout`
+    pub fn with_layout(layout: MemoryLayout) -> Self {
         Self {
-            program_memory: [0; PROGRAM_MEMORY_SIZE],
-            data_memory: [0; DATA_MEMORY_SIZE],
-            stack: [0; STACK_DEPTH],
+            program_memory: vec![0; layout.program_words],
+            core: Core::Midrange,
+            data_memory: vec![0; layout.banks * BANK_SIZE],
+            stack: vec![0; layout.stack_depth],
             stack_pointer: 0,
-            eeprom: [0; EEPROM_SIZE],
+            eeprom: vec![0; layout.eeprom_bytes],
+            decoded: Vec::new(),
         }
     }
     
+    /// Change the memory sizes, keeping the contents that still fit
+    /// The stack is emptied if its depth changes.
+    pub fn set_layout(&mut self, layout: MemoryLayout) {
+        self.program_memory.resize(layout.program_words, 0);
+        self.data_memory.resize(layout.banks * BANK_SIZE, 0);
+        self.eeprom.resize(layout.eeprom_bytes, 0);
+        if self.stack.len() != layout.stack_depth {
+            self.stack = vec![0; layout.stack_depth];
+            self.stack_pointer = 0;
+        }
+        self.decoded.clear();
+    }
+    
+    /// Current memory sizes
+    pub fn layout(&self) -> MemoryLayout {
+        MemoryLayout {
+            program_words: self.program_memory.len(),
+            banks: self.banks(),
+            stack_depth: self.stack.len(),
+            eeprom_bytes: self.eeprom.len(),
+        }
+    }
+    
+    /// Select the instruction set program memory holds; words are masked
+    /// to its width from now on
+    pub fn set_core(&mut self, core: Core) {
+        self.core = core;
+        self.decoded.clear();
+    }
+    
+    /// Instruction set program memory holds
+    pub fn core(&self) -> Core {
+        self.core
+    }
+    
+    /// Number of hardware stack levels
+    pub fn stack_levels(&self) -> usize {
+        self.stack.len()
+    }
+    
+    /// Program memory size in words
+    pub fn program_size(&self) -> usize {
+        self.program_memory.len()
+    }
+    
+    /// Number of data memory banks
+    pub fn banks(&self) -> usize {
+        self.data_memory.len() / BANK_SIZE
+    }
+    
+    /// EEPROM size in bytes
+    pub fn eeprom_size(&self) -> usize {
+        self.eeprom.len()
+    }
+    
+    /// Wrap an address into program memory
+    fn program_index(&self, address: u16) -> usize {
+        address as usize & (self.program_memory.len() - 1)
+    }
+    
     // ==================== Program Memory ====================
     
     /// Read a 14-bit instruction from program memory
-    /// Address is masked to 13 bits (0x000 - 0x3FF for 1K words)
+    /// Addresses wrap at the memory size (0x000 - 0x3FF for 1K words)
     pub fn read_program(&self, address: u16) -> u16 {
-        let addr = (address as usize) & 0x3FF; // Mask to 10 bits for 1K
-        self.program_memory[addr]
+        self.program_memory[self.program_index(address)]
     }
     
     /// Write a 14-bit instruction to program memory
     /// Used for loading programs (not during normal execution)
     pub fn write_program(&mut self, address: u16, value: u16) {
-        let addr = (address as usize) & 0x3FF;
-        self.program_memory[addr] = value & 0x3FFF; // Mask to 14 bits
+        let addr = self.program_index(address);
+        self.program_memory[addr] = value & self.core.word_mask(); // Mask to the word width
+        if let Some(slot) = self.decoded.get_mut(addr) {
+            *slot = None;
+        }
     }
     
     /// Load a program from a slice of 14-bit instructions
     pub fn load_program(&mut self, program: &[u16]) {
-        let len = program.len().min(PROGRAM_MEMORY_SIZE);
-        for i in 0..len {
-            self.program_memory[i] = program[i] & 0x3FFF;
+        let len = program.len().min(self.program_memory.len());
+        for (slot, &word) in self.program_memory.iter_mut().zip(&program[..len]) {
+            *slot = word & self.core.word_mask();
         }
+        self.decoded.clear();
+    }
+    
+    /// Read and decode the instruction at `address`, using the decode cache
+    pub fn read_instruction(&mut self, address: u16) -> Result<Instruction, DecodeError> {
+        let addr = self.program_index(address);
+        if let Some(Some(instruction)) = self.decoded.get(addr) {
+            return Ok(*instruction);
+        }
+        
+        let instruction = InstructionDecoder::decode_for(self.core, self.program_memory[addr])?;
+        if self.decoded.len() != self.program_memory.len() {
+            self.decoded = vec![None; self.program_memory.len()];
+        }
+        self.decoded[addr] = Some(instruction);
+        Ok(instruction)
     }
     
     // ==================== Data Memory ====================
     
-    /// Read a byte from data memory
+    /// Read a byte from data memory at a bank-resolved address
     /// Reference: Section 2.2 - Data Memory Organization
     /// 
     /// Special addresses:
@@ -90,42 +226,15 @@ impl Memory {
     /// - 0x02: PCL (Program Counter Low)
     /// - 0x03: STATUS
     /// - 0x04: FSR (File Select Register for indirect addressing)
-    pub fn read_data(&self, address: u8) -> u8 {
-        let addr = (address as usize) & 0x7F; // Mask to 7 bits (128 bytes)
-        self.data_memory[addr]
+    pub fn read_data(&self, address: u16) -> u8 {
+        self.data_memory[address as usize % self.data_memory.len()]
     }
     
-    /// Write a byte to data memory
-    pub fn write_data(&mut self, address: u8, value: u8) {
-        let addr = (address as usize) & 0x7F;
-        self.data_memory[addr] = value;
-    }
-    
-    /// Read from data memory with bank selection
-    /// Reference: Section 2.2 - Bank switching via RP0 bit in STATUS register
-    /// 
-    /// Bank 0: RP0 = 0 (addresses 0x00-0x7F)
-    /// Bank 1: RP0 = 1 (addresses 0x80-0xFF, but physically 0x00-0x7F with different mapping)
-    pub fn read_data_banked(&self, address: u8, bank: u8) -> u8 {
-        // For PIC12F629/675, banking affects addresses 0x0C and above
-        // Addresses 0x00-0x0B are common across banks
-        let addr = if address < 0x0C || bank == 0 {
-            address as usize
-        } else {
-            // Bank 1: offset by 0x80 but wrap within our 128-byte array
-            ((address as usize) | 0x80) & 0x7F
-        };
-        self.data_memory[addr]
-    }
-    
-    /// Write to data memory with bank selection
-    pub fn write_data_banked(&mut self, address: u8, value: u8, bank: u8) {
-        let addr = if address < 0x0C || bank == 0 {
-            address as usize
-        } else {
-            ((address as usize) | 0x80) & 0x7F
-        };
-        self.data_memory[addr] = value;
+    /// Write a byte to data memory at a bank-resolved address
+    /// Addresses wrap at the data memory size
+    pub fn write_data(&mut self, address: u16, value: u8) {
+        let len = self.data_memory.len();
+        self.data_memory[address as usize % len] = value;
     }
     
     // ==================== Hardware Stack ====================
@@ -136,14 +245,15 @@ impl Memory {
     /// Note: If stack overflows (>8 levels), oldest value is lost
     pub fn push_stack(&mut self, address: u16) {
         let addr = address & 0x1FFF; // Mask to 13 bits
+        let levels = self.stack.len();
         
-        if self.stack_pointer >= STACK_DEPTH {
+        if self.stack_pointer >= levels {
             // Stack overflow: wrap around (oldest value is lost)
             // Shift all values down
-            for i in 0..STACK_DEPTH-1 {
+            for i in 0..levels-1 {
                 self.stack[i] = self.stack[i+1];
             }
-            self.stack[STACK_DEPTH-1] = addr;
+            self.stack[levels-1] = addr;
         } else {
             self.stack[self.stack_pointer] = addr;
             self.stack_pointer += 1;
@@ -169,7 +279,7 @@ impl Memory {
     
     /// Check if stack is full
     pub fn is_stack_full(&self) -> bool {
-        self.stack_pointer >= STACK_DEPTH
+        self.stack_pointer >= self.stack.len()
     }
     
     /// Get current stack depth
@@ -186,35 +296,63 @@ impl Memory {
     
     /// Read a byte from EEPROM
     /// Reference: Section 8.0 - Data EEPROM Memory
+    /// Parts without EEPROM read 0 and ignore writes
     pub fn read_eeprom(&self, address: u8) -> u8 {
-        let addr = (address as usize) & 0x7F; // 128 bytes
-        self.eeprom[addr]
+        match self.eeprom.len() {
+            0 => 0,
+            len => self.eeprom[address as usize & (len - 1)],
+        }
     }
     
     /// Write a byte to EEPROM
     pub fn write_eeprom(&mut self, address: u8, value: u8) {
-        let addr = (address as usize) & 0x7F;
-        self.eeprom[addr] = value;
+        let len = self.eeprom.len();
+        if len > 0 {
+            self.eeprom[address as usize & (len - 1)] = value;
+        }
     }
     
     // ==================== Utility Functions ====================
     
     /// Reset all memory to initial state
     pub fn reset(&mut self) {
-        self.data_memory = [0; DATA_MEMORY_SIZE];
+        self.data_memory.fill(0);
         self.stack_pointer = 0;
         // Note: Program memory and EEPROM are not cleared on reset
     }
     
     /// Get a view of the entire data memory (for debugging)
-    pub fn get_data_memory(&self) -> &[u8; DATA_MEMORY_SIZE] {
+    pub fn get_data_memory(&self) -> &[u8] {
         &self.data_memory
     }
     
     /// Get a view of the stack (for debugging)
-    pub fn get_stack(&self) -> &[u16; STACK_DEPTH] {
+    pub fn get_stack(&self) -> &[u16] {
         &self.stack
     }
+
+    /// Save memory contents, stack and stack pointer
+    pub fn save_state(&self, record: &mut StateRecord) {
+        record.set_words("memory.program", &self.program_memory);
+        record.set_bytes("memory.data", &self.data_memory);
+        record.set_words("memory.stack", &self.stack);
+        record.set("memory.stack_pointer", self.stack_pointer);
+        record.set_bytes("memory.eeprom", &self.eeprom);
+    }
+    
+    /// Restore state saved with `save_state`
+    pub fn load_state(&mut self, record: &StateRecord) -> Result<(), StateError> {
+        record.get_words("memory.program", &mut self.program_memory)?;
+        self.decoded.clear();
+        record.get_bytes("memory.data", &mut self.data_memory)?;
+        record.get_words("memory.stack", &mut self.stack)?;
+        self.stack_pointer = record.get("memory.stack_pointer")?;
+        record.get_bytes("memory.eeprom", &mut self.eeprom)?;
+        if self.stack_pointer > self.stack.len() {
+            return Err(StateError::InvalidStackPointer(self.stack_pointer));
+        }
+        Ok(())
+    }
 }
 
 impl Default for Memory {
@@ -240,6 +378,22 @@ mod tests {
         assert_eq!(mem.read_program(0x200), 0x3FFF);
     }
     
+    #[test]
+    fn test_decode_cache() {
+        let mut mem = Memory::new();
+        mem.load_program(&[0x3055]);
+        assert_eq!(mem.read_instruction(0x000), Ok(Instruction::MOVLW { k: 0x55 }));
+        assert_eq!(mem.read_instruction(0x400), Ok(Instruction::MOVLW { k: 0x55 }));
+        
+        // Writes invalidate the cached decode
+        mem.write_program(0x000, 0x0008);
+        assert_eq!(mem.read_instruction(0x000), Ok(Instruction::RETURN));
+        mem.load_program(&[0x0000]);
+        assert_eq!(mem.read_instruction(0x000), Ok(Instruction::NOP));
+        mem.write_program(0x001, 0x0001);
+        assert_eq!(mem.read_instruction(0x001), Err(DecodeError { opcode: 0x0001 }));
+    }
+    
     #[test]
     fn test_data_memory() {
         let mut mem = Memory::new();
@@ -249,6 +403,25 @@ mod tests {
         assert_eq!(mem.read_data(0x20), 0xAB);
     }
     
+    #[test]
+    fn test_layout() {
+        let mut mem = Memory::with_layout(MemoryLayout { banks: 4, stack_depth: 2, ..Default::default() });
+        mem.write_data(0x1A0, 0x5A);
+        assert_eq!(mem.read_data(0x1A0), 0x5A);
+        mem.push_stack(0x100);
+        mem.push_stack(0x200);
+        assert!(mem.is_stack_full());
+        
+        // Contents that fit are kept; a new stack depth empties the stack
+        mem.write_program(0x010, 0x3055);
+        mem.set_layout(MemoryLayout { program_words: 2048, ..MemoryLayout::default() });
+        assert_eq!(mem.read_program(0x010), 0x3055);
+        assert_eq!(mem.banks(), 2);
+        assert_eq!(mem.read_data(0x1A0), mem.read_data(0x0A0));
+        assert!(mem.is_stack_empty());
+        assert_eq!(mem.layout().stack_depth, STACK_DEPTH);
+    }
+    
     #[test]
     fn test_stack_operations() {
         let mut mem = Memory::new();
@@ -0,0 +1,186 @@
+//! Power consumption estimate
+//!
+//! Reference: Section 12 - Electrical Specifications, DC Characteristics
+//! (Page 90-94)
+//!
+//! Every simulated cycle is charged the supply current of the mode the
+//! device is in: running (scaling with Fosc) or asleep, plus the WDT, the
+//! ADC and the comparator when they are on. Summed over a run this gives
+//! the total charge drawn and the average current, for comparing firmware
+//! choices on battery-powered designs. The figures are datasheet typicals
+//! at VDD = 5.0 V and 25 °C; pin loads are not included.
+
+use crate::cpu::{registers, Cpu};
+
+/// Supply current of each mode, in microamps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerModel {
+    /// Run current independent of the clock
+    pub run_base_ua: f64,
+    /// Additional run current per MHz of Fosc
+    pub run_ua_per_mhz: f64,
+    /// Sleep current with everything off (IPD base)
+    pub sleep_ua: f64,
+    /// WDT oscillator current
+    pub wdt_ua: f64,
+    /// ADC current while ADON is set
+    pub adc_ua: f64,
+    /// Comparator current while it is in an active mode
+    pub comparator_ua: f64,
+}
+
+impl Default for PowerModel {
+    fn default() -> Self {
+        Self {
+            run_base_ua: 20.0,
+            run_ua_per_mhz: 110.0,
+            sleep_ua: 0.99,
+            wdt_ua: 5.0,
+            adc_ua: 150.0,
+            comparator_ua: 74.0,
+        }
+    }
+}
+
+impl PowerModel {
+    /// Supply current in `mode` at `fosc_hz`
+    pub fn current_ua(&self, mode: PowerMode, fosc_hz: u32) -> f64 {
+        let mut current = if mode.sleeping {
+            self.sleep_ua
+        } else {
+            self.run_base_ua + self.run_ua_per_mhz * fosc_hz as f64 / 1e6
+        };
+        if mode.wdt {
+            current += self.wdt_ua;
+        }
+        if mode.adc {
+            current += self.adc_ua;
+        }
+        if mode.comparator {
+            current += self.comparator_ua;
+        }
+        current
+    }
+}
+
+/// What is drawing current
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PowerMode {
+    pub sleeping: bool,
+    pub wdt: bool,
+    pub adc: bool,
+    pub comparator: bool,
+}
+
+impl PowerMode {
+    /// Read the mode from the CPU
+    ///
+    /// The comparator is off in CM<2:0> = 000 (reset) and 111 (off).
+    pub fn from_cpu(cpu: &Cpu) -> Self {
+        let cm = cpu.read_register(registers::CMCON) & 0x07;
+        Self {
+            sleeping: cpu.is_sleeping(),
+            wdt: cpu.wdt().is_enabled(),
+            adc: cpu.read_register(registers::ADCON0) & 0x01 != 0,
+            comparator: cm != 0b000 && cm != 0b111,
+        }
+    }
+}
+
+/// Charge accumulated over a run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PowerMeter {
+    model: PowerModel,
+    /// Total charge in microcoulombs
+    charge_uc: f64,
+    /// Simulated time in seconds
+    seconds: f64,
+    run_cycles: u64,
+    sleep_cycles: u64,
+}
+
+impl PowerMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Charge `cycles` instruction cycles spent in `mode` at `fosc_hz`
+    pub fn record(&mut self, mode: PowerMode, cycles: u64, fosc_hz: u32) {
+        let seconds = cycles as f64 * 4.0 / fosc_hz as f64;
+        self.charge_uc += self.model.current_ua(mode, fosc_hz) * seconds;
+        self.seconds += seconds;
+        if mode.sleeping {
+            self.sleep_cycles += cycles;
+        } else {
+            self.run_cycles += cycles;
+        }
+    }
+
+    /// Total charge in microcoulombs
+    pub fn charge_uc(&self) -> f64 {
+        self.charge_uc
+    }
+
+    /// Simulated time covered, in seconds
+    pub fn seconds(&self) -> f64 {
+        self.seconds
+    }
+
+    /// Average supply current in microamps (0.0 before any cycle)
+    pub fn average_ua(&self) -> f64 {
+        if self.seconds == 0.0 {
+            0.0
+        } else {
+            self.charge_uc / self.seconds
+        }
+    }
+
+    pub fn run_cycles(&self) -> u64 {
+        self.run_cycles
+    }
+
+    pub fn sleep_cycles(&self) -> u64 {
+        self.sleep_cycles
+    }
+
+    pub fn model(&self) -> &PowerModel {
+        &self.model
+    }
+
+    /// Replace the current figures; the accumulated charge is kept
+    pub fn set_model(&mut self, model: PowerModel) {
+        self.model = model;
+    }
+
+    /// Start a new measurement, keeping the model
+    pub fn clear(&mut self) {
+        *self = Self {
+            model: self.model,
+            ..Self::default()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_average_current() {
+        let mut meter = PowerMeter::new();
+        let run = PowerMode::default();
+        let sleep = PowerMode { sleeping: true, wdt: true, ..PowerMode::default() };
+
+        // 1 ms running at 4 MHz (460 uA), then 9 ms asleep (5.99 uA)
+        meter.record(run, 1_000, 4_000_000);
+        meter.record(sleep, 9_000, 4_000_000);
+        assert_eq!((meter.run_cycles(), meter.sleep_cycles()), (1_000, 9_000));
+        assert!((meter.seconds() - 0.010).abs() < 1e-12);
+        assert!((meter.charge_uc() - (0.460 + 0.05391)).abs() < 1e-9);
+        assert!((meter.average_ua() - 51.391).abs() < 1e-6);
+
+        meter.clear();
+        assert_eq!(meter.average_ua(), 0.0);
+        assert_eq!(*meter.model(), PowerModel::default());
+    }
+}
@@ -0,0 +1,207 @@
+//! Cycle profiler
+//!
+//! Accumulates executed cycles per program address and per subroutine so
+//! hot spots can be located. Subroutines are identified by their entry
+//! address as tracked by the logical call stack; code running outside of
+//! any call frame is attributed to `main`.
+
+use std::collections::HashMap;
+
+use crate::memory::MAX_PROGRAM_MEMORY_SIZE;
+
+/// Profile of a single program address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressProfile {
+    pub address: u16,
+    pub hits: u64,
+    pub cycles: u64,
+}
+
+/// Profile of a single subroutine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubroutineProfile {
+    /// Entry address (`None` for code outside any call frame)
+    pub entry: Option<u16>,
+
+    /// Number of times the routine was entered
+    pub calls: u64,
+
+    /// Cycles spent in the routine itself
+    pub self_cycles: u64,
+
+    /// Cycles spent in the routine including nested calls
+    /// (accumulated when the routine returns)
+    pub total_cycles: u64,
+}
+
+/// Per-address and per-subroutine cycle profiler
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    enabled: bool,
+    hits: Vec<u64>,
+    cycles: Vec<u64>,
+    subroutines: HashMap<Option<u16>, SubroutineProfile>,
+    total_cycles: u64,
+}
+
+impl Profiler {
+    /// Create a disabled profiler
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            hits: vec![0; MAX_PROGRAM_MEMORY_SIZE],
+            cycles: vec![0; MAX_PROGRAM_MEMORY_SIZE],
+            subroutines: HashMap::new(),
+            total_cycles: 0,
+        }
+    }
+
+    /// Enable or disable data collection
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Check if the profiler is collecting data
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Discard all collected data
+    pub fn clear(&mut self) {
+        self.hits.iter_mut().for_each(|h| *h = 0);
+        self.cycles.iter_mut().for_each(|c| *c = 0);
+        self.subroutines.clear();
+        self.total_cycles = 0;
+    }
+
+    /// Account an executed instruction at `pc` to the routine `routine`
+    pub fn record_instruction(&mut self, pc: u16, cycles: u64, routine: Option<u16>) {
+        if !self.enabled {
+            return;
+        }
+
+        let index = pc as usize % MAX_PROGRAM_MEMORY_SIZE;
+        self.hits[index] += 1;
+        self.cycles[index] += cycles;
+        self.total_cycles += cycles;
+        self.subroutine_mut(routine).self_cycles += cycles;
+    }
+
+    /// Account a call into the routine at `entry`
+    pub fn record_call(&mut self, entry: u16) {
+        if self.enabled {
+            self.subroutine_mut(Some(entry)).calls += 1;
+        }
+    }
+
+    /// Account a return from the routine at `entry` that ran for `cycles`
+    pub fn record_return(&mut self, entry: u16, cycles: u64) {
+        if self.enabled {
+            self.subroutine_mut(Some(entry)).total_cycles += cycles;
+        }
+    }
+
+    fn subroutine_mut(&mut self, entry: Option<u16>) -> &mut SubroutineProfile {
+        self.subroutines.entry(entry).or_insert(SubroutineProfile {
+            entry,
+            calls: 0,
+            self_cycles: 0,
+            total_cycles: 0,
+        })
+    }
+
+    /// Total cycles recorded
+    pub fn total_cycles(&self) -> u64 {
+        self.total_cycles
+    }
+
+    /// Profile of a single address
+    pub fn address(&self, address: u16) -> AddressProfile {
+        let index = address as usize % MAX_PROGRAM_MEMORY_SIZE;
+        AddressProfile {
+            address,
+            hits: self.hits[index],
+            cycles: self.cycles[index],
+        }
+    }
+
+    /// All executed addresses, hottest first
+    pub fn hot_spots(&self) -> Vec<AddressProfile> {
+        let mut spots: Vec<AddressProfile> = (0..MAX_PROGRAM_MEMORY_SIZE)
+            .filter(|&i| self.hits[i] > 0)
+            .map(|i| self.address(i as u16))
+            .collect();
+        spots.sort_by(|a, b| b.cycles.cmp(&a.cycles).then(a.address.cmp(&b.address)));
+        spots
+    }
+
+    /// All subroutines, hottest (by self cycles) first
+    pub fn subroutines(&self) -> Vec<SubroutineProfile> {
+        let mut routines: Vec<SubroutineProfile> = self.subroutines.values().copied().collect();
+        routines.sort_by(|a, b| b.self_cycles.cmp(&a.self_cycles).then(a.entry.cmp(&b.entry)));
+        routines
+    }
+
+    /// Share of total cycles, in percent
+    pub fn percent(&self, cycles: u64) -> f64 {
+        if self.total_cycles == 0 {
+            0.0
+        } else {
+            cycles as f64 * 100.0 / self.total_cycles as f64
+        }
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_by_default() {
+        let mut profiler = Profiler::new();
+        profiler.record_instruction(0x10, 1, None);
+
+        assert_eq!(profiler.total_cycles(), 0);
+        assert!(profiler.hot_spots().is_empty());
+    }
+
+    #[test]
+    fn test_hot_spots_sorted() {
+        let mut profiler = Profiler::new();
+        profiler.set_enabled(true);
+
+        profiler.record_instruction(0x01, 1, None);
+        for _ in 0..5 {
+            profiler.record_instruction(0x20, 2, Some(0x20));
+        }
+
+        let spots = profiler.hot_spots();
+        assert_eq!(spots[0].address, 0x20);
+        assert_eq!(spots[0].hits, 5);
+        assert_eq!(spots[0].cycles, 10);
+        assert_eq!(profiler.total_cycles(), 11);
+    }
+
+    #[test]
+    fn test_subroutine_accounting() {
+        let mut profiler = Profiler::new();
+        profiler.set_enabled(true);
+
+        profiler.record_instruction(0x00, 2, None);
+        profiler.record_call(0x10);
+        profiler.record_instruction(0x10, 3, Some(0x10));
+        profiler.record_return(0x10, 3);
+
+        let routines = profiler.subroutines();
+        assert_eq!(routines[0].entry, Some(0x10));
+        assert_eq!(routines[0].calls, 1);
+        assert_eq!(routines[0].total_cycles, 3);
+        assert_eq!(routines[1].entry, None);
+    }
+}
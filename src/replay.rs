@@ -0,0 +1,249 @@
+//! Input recording and replay
+//!
+//! Records every external input applied to the simulator during an
+//! interactive session (pin changes and resets, timestamped in cycles) so
+//! the session can be replayed exactly later. Timestamps count cycles since
+//! the last reset, matching the simulator's cycle counter. Files are plain
+//! text, one event per line:
+//!
+//! ```text
+//! # pic_simulator input replay v1
+//! 1200 GP2 low
+//! 1500 GP2 high
+//! 3000 reset
+//! 4200 reset mclr
+//! ```
+//!
+//! A bare `reset` is a power-on reset; `mclr`, `wdt` and `bor` name the
+//! other reset kinds.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::cpu::ResetReason;
+use crate::error::ReplayError;
+use crate::gpio;
+
+/// First line of a replay file
+pub const REPLAY_HEADER: &str = "# pic_simulator input replay v1";
+
+/// An external input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    /// External level of an input pin
+    Pin { pin: u8, level: bool },
+    /// Simulator reset
+    Reset(ResetReason),
+}
+
+/// An input applied at a cycle count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    pub cycle: u64,
+    pub kind: InputKind,
+}
+
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            InputKind::Pin { pin, level } => {
+                write!(f, "{} {} {}", self.cycle, gpio::pin_label(pin), if level { "high" } else { "low" })
+            }
+            InputKind::Reset(ResetReason::PowerOn) => write!(f, "{} reset", self.cycle),
+            InputKind::Reset(reason) => write!(f, "{} reset {}", self.cycle, reason.name()),
+        }
+    }
+}
+
+impl InputEvent {
+    fn parse(line: &str) -> Result<Self, ReplayError> {
+        let words: Vec<String> = line.split_whitespace()
+            .map(|w| w.to_ascii_lowercase())
+            .collect();
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+        let (cycle, rest) = words.split_first()
+            .ok_or(ReplayError::EmptyEvent)?;
+        let cycle = cycle.parse()
+            .map_err(|_| ReplayError::InvalidCycle(cycle.to_string()))?;
+
+        let kind = match rest {
+            ["reset"] => InputKind::Reset(ResetReason::PowerOn),
+            ["reset", name] => InputKind::Reset(ResetReason::from_name(name)
+                .ok_or_else(|| ReplayError::InvalidReset(name.to_string()))?),
+            [pin, level @ ("high" | "low")] => {
+                let pin = gpio::parse_pin(pin)
+                    .ok_or_else(|| ReplayError::InvalidPin(pin.to_string()))?;
+                InputKind::Pin { pin, level: *level == "high" }
+            }
+            _ => return Err(ReplayError::UnknownEvent(rest.join(" "))),
+        };
+        Ok(Self { cycle, kind })
+    }
+}
+
+/// A sequence of recorded inputs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputLog {
+    events: Vec<InputEvent>,
+}
+
+impl InputLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an event
+    pub fn push(&mut self, cycle: u64, kind: InputKind) {
+        self.events.push(InputEvent { cycle, kind });
+    }
+
+    /// All events in the order they were applied
+    pub fn events(&self) -> &[InputEvent] {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Drop the events after the first `len`
+    pub fn truncate(&mut self, len: usize) {
+        self.events.truncate(len);
+    }
+
+    /// Format as replay file text
+    pub fn to_text(&self) -> String {
+        let mut text = String::from(REPLAY_HEADER);
+        text.push('\n');
+        for event in &self.events {
+            text.push_str(&event.to_string());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parse replay file text
+    pub fn parse(text: &str) -> Result<Self, ReplayError> {
+        let mut log = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let event = InputEvent::parse(line)
+                .map_err(|e| ReplayError::Line { line: index + 1, source: Box::new(e) })?;
+            log.events.push(event);
+        }
+        Ok(log)
+    }
+
+    /// Write the log to a replay file
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        fs::write(path, self.to_text())
+            .map_err(ReplayError::Write)
+    }
+
+    /// Read a replay file
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        let content = fs::read_to_string(path)
+            .map_err(ReplayError::Read)?;
+        Self::parse(&content)
+    }
+}
+
+/// Inputs being replayed, in order
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    events: Vec<InputEvent>,
+    /// Index of the next event to replay
+    position: usize,
+}
+
+impl Replay {
+    pub fn new(log: &InputLog) -> Self {
+        Self {
+            events: log.events.clone(),
+            position: 0,
+        }
+    }
+
+    /// Take the next event if it is due at `cycle`
+    pub fn next_due(&mut self, cycle: u64) -> Option<InputEvent> {
+        let event = *self.events.get(self.position)?;
+        if event.cycle <= cycle {
+            self.position += 1;
+            Some(event)
+        } else {
+            None
+        }
+    }
+
+    /// Cycle of the next event
+    pub fn next_cycle(&self) -> Option<u64> {
+        self.events.get(self.position).map(|event| event.cycle)
+    }
+
+    /// Number of events replayed so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Continue from an earlier `position`, replaying the events after it again
+    pub fn rewind(&mut self, position: usize) {
+        self.position = position.min(self.position);
+    }
+
+    /// Number of events not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.events.len() - self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.remaining() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut log = InputLog::new();
+        log.push(1200, InputKind::Pin { pin: 2, level: false });
+        log.push(3000, InputKind::Reset(ResetReason::PowerOn));
+        log.push(15, InputKind::Pin { pin: 2, level: true });
+        log.push(40, InputKind::Reset(ResetReason::Mclr));
+
+        let text = log.to_text();
+        assert!(text.starts_with(REPLAY_HEADER));
+        assert!(text.contains("1200 GP2 low\n3000 reset\n15 GP2 high\n40 reset mclr\n"));
+        assert_eq!(InputLog::parse(&text).unwrap(), log);
+
+        assert!(matches!(InputLog::parse("10 GP9 high\n"), Err(ReplayError::Line { line: 1, .. })));
+        assert!(InputLog::parse("x reset\n").is_err());
+        assert!(InputLog::parse("10 reset cold\n").is_err());
+    }
+
+    #[test]
+    fn test_replay_order() {
+        let log = InputLog::parse("5 reset\n0 GP1 low\n").unwrap();
+        let mut replay = Replay::new(&log);
+
+        assert_eq!(replay.next_due(4), None);
+        assert_eq!(replay.next_due(5).unwrap().kind, InputKind::Reset(ResetReason::PowerOn));
+        // Cycle counting restarts after the reset
+        assert_eq!(replay.next_due(0).unwrap().kind, InputKind::Pin { pin: 1, level: false });
+        assert!(replay.is_finished());
+
+        replay.rewind(1);
+        assert_eq!(replay.remaining(), 1);
+        assert_eq!(replay.next_cycle(), Some(0));
+    }
+}
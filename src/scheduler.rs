@@ -0,0 +1,114 @@
+//! Peripheral event scheduler
+//!
+//! Instead of ticking the timers and the WDT on every cycle, the
+//! simulator asks each of them when its next event (an overflow or a
+//! timeout) is due and advances them in bulk up to the earliest one.
+//! Stimulus pin changes and replayed inputs are queued alongside, so a
+//! sleeping CPU can fast-forward to whichever comes first.
+//!
+//! Events are kept as absolute cycle counts. The queue stays valid while
+//! the peripherals advance in step with the cycle counter; the simulator
+//! invalidates and rebuilds it when peripheral configuration changes,
+//! when an event fires, and around sleep and interrupt entry. New
+//! peripherals (ADC conversion, EEPROM write completion) plug in as
+//! further `EventKind`s.
+
+/// Something a peripheral or input source will do at a known cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// The tick that times the WDT out
+    WdtTimeout,
+    /// The tick that overflows TMR0
+    Timer0Overflow,
+    /// The tick that overflows TMR1
+    Timer1Overflow,
+    /// The tick that resets TMR2 or changes the PWM output
+    Timer2,
+    /// A scheduled stimulus pin change
+    StimulusChange,
+    /// A replayed input
+    ReplayInput,
+}
+
+/// Pending peripheral and input events, earliest first
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    events: Vec<(u64, EventKind)>,
+    valid: bool,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop all events and mark the queue valid, ready to be refilled
+    pub fn rebuild(&mut self) {
+        self.events.clear();
+        self.valid = true;
+    }
+
+    /// Queue `kind` at `cycle`; events at the same cycle keep their order
+    pub fn schedule(&mut self, cycle: u64, kind: EventKind) {
+        let index = self.events.partition_point(|&(at, _)| at <= cycle);
+        self.events.insert(index, (cycle, kind));
+    }
+
+    /// Mark the queue out of date
+    pub fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    /// Check if the queue matches the current peripheral state
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Earliest pending event
+    pub fn next(&self) -> Option<(u64, EventKind)> {
+        self.events.first().copied()
+    }
+
+    /// Cycle of the earliest pending event
+    pub fn next_cycle(&self) -> Option<u64> {
+        self.next().map(|(cycle, _)| cycle)
+    }
+
+    /// Cycle at which `kind` is next due
+    pub fn due(&self, kind: EventKind) -> Option<u64> {
+        self.events.iter()
+            .find(|(_, k)| *k == kind)
+            .map(|(cycle, _)| *cycle)
+    }
+
+    /// All pending events, earliest first
+    pub fn events(&self) -> &[(u64, EventKind)] {
+        &self.events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ordering() {
+        let mut scheduler = Scheduler::new();
+        assert!(!scheduler.is_valid());
+
+        scheduler.rebuild();
+        scheduler.schedule(500, EventKind::WdtTimeout);
+        scheduler.schedule(120, EventKind::Timer0Overflow);
+        scheduler.schedule(500, EventKind::StimulusChange);
+        assert!(scheduler.is_valid());
+        assert_eq!(scheduler.next(), Some((120, EventKind::Timer0Overflow)));
+        assert_eq!(scheduler.events()[1..], [(500, EventKind::WdtTimeout), (500, EventKind::StimulusChange)]);
+        assert_eq!(scheduler.due(EventKind::StimulusChange), Some(500));
+        assert_eq!(scheduler.due(EventKind::Timer1Overflow), None);
+
+        scheduler.invalidate();
+        assert!(!scheduler.is_valid());
+        scheduler.rebuild();
+        assert_eq!(scheduler.next_cycle(), None);
+    }
+}
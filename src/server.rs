@@ -0,0 +1,317 @@
+//! Remote-control server
+//!
+//! Exposes a simulator over TCP with a simple line protocol so external
+//! tools, CI jobs and test frameworks in other languages can drive it
+//! headlessly. Each request is one line of whitespace-separated words;
+//! each reply is one line starting with `ok` or `err`:
+//!
+//! ```text
+//! > loadhex firmware.hex
+//! < ok words=42 warnings=0
+//! > break 0x010
+//! < ok
+//! > run 100000
+//! < ok stop=breakpoint pc=0x0010 cycles=42
+//! > read 0x20 2
+//! < ok 05 00
+//! > setpin 3 0
+//! < ok
+//! ```
+//!
+//! Numbers are decimal or `0x` hex. Connections are served one at a time.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::cpu::registers;
+use crate::gpio;
+use crate::memory::DATA_MEMORY_SIZE;
+use crate::simulator::{RunOutcome, Simulator};
+use crate::watchpoint::parse_register;
+
+/// Default listen address
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:4711";
+
+/// Simulator served over TCP
+pub struct Server {
+    simulator: Simulator,
+}
+
+impl Server {
+    pub fn new(simulator: Simulator) -> Self {
+        Self { simulator }
+    }
+
+    /// Get the simulator
+    pub fn simulator(&self) -> &Simulator {
+        &self.simulator
+    }
+
+    /// Get mutable reference to the simulator
+    pub fn simulator_mut(&mut self) -> &mut Simulator {
+        &mut self.simulator
+    }
+
+    /// Accept connections forever, serving one client at a time
+    pub fn serve<A: ToSocketAddrs>(&mut self, address: A) -> io::Result<()> {
+        let listener = TcpListener::bind(address)?;
+        tracing::info!(target: "pic_simulator::server", "listening on {}", listener.local_addr()?);
+
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let peer = stream.peer_addr()?;
+            tracing::info!(target: "pic_simulator::server", "client connected: {}", peer);
+            if let Err(e) = self.serve_client(stream) {
+                tracing::warn!(target: "pic_simulator::server", "client {} error: {}", peer, e);
+            }
+            tracing::info!(target: "pic_simulator::server", "client disconnected: {}", peer);
+        }
+        Ok(())
+    }
+
+    /// Handle requests from one client until it sends `quit` or disconnects
+    pub fn serve_client(&mut self, stream: TcpStream) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        let reader = BufReader::new(stream);
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim() == "quit" {
+                writeln!(writer, "ok")?;
+                break;
+            }
+            let reply = self.handle_line(&line);
+            writeln!(writer, "{}", reply)?;
+        }
+        Ok(())
+    }
+
+    /// Execute one request line and return the reply line
+    pub fn handle_line(&mut self, line: &str) -> String {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.is_empty() {
+            return "err empty request".to_string();
+        }
+
+        match self.handle(parts[0], &parts[1..]) {
+            Ok(reply) if reply.is_empty() => "ok".to_string(),
+            Ok(reply) => format!("ok {}", reply),
+            Err(e) => format!("err {}", e),
+        }
+    }
+
+    fn handle(&mut self, command: &str, args: &[&str]) -> Result<String, String> {
+        match (command, args) {
+            ("reset", []) => {
+                self.simulator.reset();
+                Ok(String::new())
+            }
+            ("load", words) if !words.is_empty() => {
+                let program = words.iter()
+                    .map(|w| parse_number(w).map(|n| n as u16))
+                    .collect::<Result<Vec<u16>, _>>()?;
+                self.simulator.load_program(&program);
+                Ok(format!("words={}", program.len()))
+            }
+            ("loadhex", [path]) => {
+                let report = self.simulator.load_firmware_file(path).map_err(|e| e.to_string())?;
+                Ok(format!("words={} warnings={}", report.words_loaded, report.warnings.len()))
+            }
+            ("step", _) => {
+                let count = optional_number(args.first(), 1)?;
+                self.simulator.run_n_instructions(count as u64).map_err(|e| e.to_string())?;
+                Ok(self.position())
+            }
+            ("run", [cycles]) => {
+                let cycles = parse_number(cycles)? as u64;
+                let stop = match self.simulator.run_until(|_| false, cycles) {
+                    RunOutcome::CycleLimit | RunOutcome::PredicateMet => "limit",
+                    RunOutcome::Breakpoint(_) => "breakpoint",
+                    RunOutcome::Watchpoint(_) => "watchpoint",
+                    RunOutcome::Error(e) => return Err(e.to_string()),
+                };
+                Ok(format!("stop={} {}", stop, self.position()))
+            }
+            ("state", []) => {
+                let cpu = self.simulator.cpu();
+                Ok(format!("pc=0x{:04X} w=0x{:02X} status=0x{:02X} gpio=0x{:02X} trisio=0x{:02X} cycles={} instructions={}",
+                    cpu.get_pc(),
+                    cpu.read_w(),
+                    cpu.read_register(registers::STATUS),
+                    cpu.gpio().read_gpio(),
+                    cpu.gpio().read_tris(),
+                    self.simulator.stats().cycles_elapsed,
+                    self.simulator.stats().instructions_executed,
+                ))
+            }
+            ("read", [register, rest @ ..]) if rest.len() <= 1 => {
+                let start = parse_register(register)
+                    .ok_or_else(|| format!("unknown register '{}'", register))?;
+                let count = optional_number(rest.first(), 1)?;
+                if count as usize > DATA_MEMORY_SIZE {
+                    return Err(format!("count out of range: {}", count));
+                }
+                let bytes: Vec<String> = (0..count)
+                    .map(|i| format!("{:02X}", self.simulator.cpu().read_register(start.wrapping_add(i as u8))))
+                    .collect();
+                Ok(bytes.join(" "))
+            }
+            ("write", [register, value]) => {
+                let address = parse_register(register)
+                    .ok_or_else(|| format!("unknown register '{}'", register))?;
+                let value = parse_number(value)?;
+                if value > 0xFF {
+                    return Err(format!("value out of range: {}", value));
+                }
+                self.simulator.cpu_mut().write_register(address, value as u8);
+                Ok(String::new())
+            }
+            ("setpin", [pin, level]) => {
+                let pin = self.pin(pin)?;
+                let level = match *level {
+                    "0" | "low" => false,
+                    "1" | "high" => true,
+                    _ => return Err(format!("invalid level '{}'", level)),
+                };
+                self.simulator.set_external_pin(pin, level);
+                Ok(String::new())
+            }
+            ("getpin", [pin]) => {
+                let pin = self.pin(pin)?;
+                let level = self.simulator.cpu().gpio().pin_level(pin);
+                Ok(if level { "1" } else { "0" }.to_string())
+            }
+            ("break", [address]) => {
+                let address = self.code_address(address)?;
+                self.simulator.add_breakpoint(address);
+                Ok(String::new())
+            }
+            ("delete", [address]) => {
+                let address = self.code_address(address)?;
+                self.simulator.remove_breakpoint(address);
+                Ok(String::new())
+            }
+            ("breakpoints", []) => {
+                let list: Vec<String> = self.simulator.breakpoints().iter()
+                    .map(|bp| format!("0x{:04X}", bp.address))
+                    .collect();
+                Ok(list.join(" "))
+            }
+            _ => Err(format!("unknown request '{}'", [&[command], args].concat().join(" "))),
+        }
+    }
+
+    fn position(&self) -> String {
+        format!("pc=0x{:04X} cycles={}", self.simulator.cpu().get_pc(), self.simulator.stats().cycles_elapsed)
+    }
+
+    fn code_address(&self, s: &str) -> Result<u16, String> {
+        match self.simulator.symbols().address_of(s) {
+            Some(address) => Ok(address),
+            None => parse_number(s).map(|n| n as u16),
+        }
+    }
+
+    fn pin(&self, s: &str) -> Result<u8, String> {
+        let model = self.simulator.device().model();
+        gpio::parse_pin(s)
+            .filter(|&pin| model.has_pin(pin))
+            .ok_or_else(|| format!("invalid pin '{}'", s))
+    }
+}
+
+fn parse_number(s: &str) -> Result<u32, String> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|_| format!("invalid number '{}'", s))
+}
+
+fn optional_number(s: Option<&&str>, default: u32) -> Result<u32, String> {
+    s.map_or(Ok(default), |s| parse_number(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::Device;
+
+    #[test]
+    fn test_requests() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        let mut server = Server::new(sim);
+
+        assert_eq!(server.handle_line("load 0x3055 0x00A0 0x2800"), "ok words=3");
+        assert_eq!(server.handle_line("step 2"), "ok pc=0x0002 cycles=2");
+        assert_eq!(server.handle_line("read 0x20 2"), "ok 55 00");
+        assert_eq!(server.handle_line("write 0x21 7"), "ok");
+        assert_eq!(server.handle_line("read 0x21"), "ok 07");
+        assert_eq!(server.handle_line("read 0x00 256").split(' ').count(), 257);
+        assert_eq!(server.handle_line("read 0x00 4000000000"), "err count out of range: 4000000000");
+
+        assert_eq!(server.handle_line("break 0x001"), "ok");
+        assert_eq!(server.handle_line("breakpoints"), "ok 0x0001");
+        assert_eq!(server.handle_line("run 100"), "ok stop=breakpoint pc=0x0001 cycles=5");
+        assert_eq!(server.handle_line("delete 1"), "ok");
+        assert!(server.handle_line("run 10").starts_with("ok stop=limit"));
+
+        assert_eq!(server.handle_line("setpin GP3 0"), "ok");
+        assert_eq!(server.handle_line("getpin 3"), "ok 0");
+        assert!(server.handle_line("state").contains("w=0x55"));
+
+        assert_eq!(server.handle_line("frobnicate 1"), "err unknown request 'frobnicate 1'");
+        assert_eq!(server.handle_line("setpin 9 1"), "err invalid pin '9'");
+    }
+
+    #[test]
+    fn test_portb_pins() {
+        let mut sim = Simulator::new();
+        sim.set_device(Device::Pic16F84A);
+        let mut server = Server::new(sim);
+
+        // RB0/INT falls: INTF is set
+        assert_eq!(server.handle_line("load 0x2800"), "ok words=1");
+        assert_eq!(server.handle_line("step"), "ok pc=0x0000 cycles=2");
+        assert_eq!(server.handle_line("read INTCON"), "ok 00");
+        assert_eq!(server.handle_line("setpin RB0 0"), "ok");
+        assert_eq!(server.handle_line("step"), "ok pc=0x0000 cycles=4");
+        assert_eq!(server.handle_line("getpin 8"), "ok 0");
+        assert_eq!(server.handle_line("read INTCON"), "ok 02");
+        assert_eq!(server.handle_line("setpin RA5 1"), "err invalid pin 'RA5'");
+    }
+
+    #[test]
+    fn test_setpin_is_recorded() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.start_input_recording();
+        let mut server = Server::new(sim);
+
+        assert_eq!(server.handle_line("setpin GP2 0"), "ok");
+        let log = server.simulator().input_recording().unwrap();
+        assert_eq!(log.to_text().lines().last(), Some("0 GP2 low"));
+    }
+
+    #[test]
+    fn test_tcp_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            writeln!(stream, "load 0x3042\nstep\nquit").unwrap();
+            let reader = BufReader::new(stream);
+            reader.lines().map(Result::unwrap).collect::<Vec<_>>()
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut sim = Simulator::new();
+        sim.reset();
+        let mut server = Server::new(sim);
+        server.serve_client(stream).unwrap();
+
+        assert_eq!(client.join().unwrap(), ["ok words=1", "ok pc=0x0001 cycles=1", "ok"]);
+        assert_eq!(server.simulator().cpu().read_w(), 0x42);
+    }
+}
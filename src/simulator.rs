@@ -1,11 +1,47 @@
-/// PIC12F629/675 Simulator - Main execution loop and control
-/// 
-/// This module provides the main simulator interface that ties together
-/// the CPU, memory, instruction decoder, and executor.
+//! PIC12F629/675 Simulator - Main execution loop and control
+//! 
+//! This module provides the main simulator interface that ties together
+//! the CPU, memory, instruction decoder, and executor.
 
 use crate::{Cpu, InstructionDecoder, Executor};
+use crate::cpu::{AccessKind, RegisterAccess, ResetReason};
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::Path;
-use crate::hexloader::{HexLoader, HexProgram};
+use std::time::Duration;
+use std::sync::mpsc::{self, Receiver};
+use crate::hexloader::{Difference, HexLoader, HexProgram, LoadReport, ParseMode, ParseOptions};
+use crate::srec::{self, SrecLoader, SREC_EXTENSIONS};
+use crate::assembler;
+use crate::elf::{self, ElfLoader};
+use crate::coff::{self, CoffLoader};
+use crate::history::{History, Snapshot};
+use crate::callstack::{CallStack, FrameKind};
+use crate::profiler::Profiler;
+use crate::coverage::Coverage;
+use crate::symbols::SymbolTable;
+use crate::wave::VcdWriter;
+use crate::stimulus::{Stimulus, StimulusRule};
+use crate::logic::{LogicAnalyzer, MarkerKind};
+use crate::watchpoint::{Watchpoint, WatchHit};
+use crate::breakpoint::Breakpoint;
+use crate::expr::Expr;
+use crate::stopwatch::{Stopwatch, StopwatchReading};
+use crate::state::MachineState;
+use crate::error::SimError;
+use crate::clock::Clock;
+use crate::device::Device;
+use crate::events::{Observers, ObserverId, SimEvent};
+use crate::instruction::{Core, Instruction};
+use crate::trace::TraceBuffer;
+use crate::component::{Component, ComponentId, Components};
+use crate::circuit::Circuit;
+use crate::replay::{InputKind, InputLog, Replay};
+use crate::halt::{HaltPolicy, HaltReason};
+use crate::latency::InterruptTiming;
+use crate::power::{PowerMeter, PowerMode};
+use crate::scheduler::{EventKind, Scheduler};
+use crate::interrupt::InterruptSource;
 
 /// Simulator state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,8 +52,24 @@ pub enum SimulatorState {
     Error,
 }
 
+/// Why `Simulator::run_until` stopped
+#[derive(Debug)]
+pub enum RunOutcome {
+    /// The predicate returned true
+    PredicateMet,
+    /// Execution reached a breakpoint at this address
+    Breakpoint(u16),
+    /// A register watchpoint triggered
+    Watchpoint(WatchHit),
+    /// The cycle limit elapsed first
+    CycleLimit,
+    /// A step failed
+    Error(SimError),
+}
+
 /// Simulator statistics
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SimulatorStats {
     pub instructions_executed: u64,
     pub cycles_elapsed: u64,
@@ -28,43 +80,182 @@ pub struct Simulator {
     cpu: Cpu,
     state: SimulatorState,
     stats: SimulatorStats,
-    breakpoints: Vec<u16>,
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    watch_hit: Option<WatchHit>,
+    stopwatch: Stopwatch,
+    stopwatch_hit: Option<StopwatchReading>,
+    clock: Clock,
+    history: History,
+    trace: TraceBuffer,
+    call_stack: CallStack,
+    interrupt_timing: InterruptTiming,
+    power: PowerMeter,
+    profiler: Profiler,
+    coverage: Coverage,
+    symbols: SymbolTable,
+    vcd: Option<VcdWriter<Box<dyn Write>>>,
+    stimulus: Stimulus,
+    logic: LogicAnalyzer,
+    observers: Observers,
+    components: Components,
+    input_log: Option<InputLog>,
+    replay: Option<Replay>,
+    halt_policy: HaltPolicy,
+    scheduler: Scheduler,
+    config_word: Option<u16>,
+    id_locations: Option<[u16; 4]>,
+    hex_parse_mode: ParseMode,
 }
 
 impl Simulator {
     /// Create a new simulator
     pub fn new() -> Self {
+        Self::new_with_device(Device::default())
+    }
+    
+    /// Create a simulator for `device`
+    pub fn new_with_device(device: Device) -> Self {
         Self {
-            cpu: Cpu::new(),
+            cpu: Cpu::with_device(device),
             state: SimulatorState::Paused,
             stats: SimulatorStats {
                 instructions_executed: 0,
                 cycles_elapsed: 0,
             },
             breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            watch_hit: None,
+            stopwatch: Stopwatch::new(),
+            stopwatch_hit: None,
+            clock: Clock::new(),
+            history: History::new(),
+            trace: TraceBuffer::new(),
+            call_stack: CallStack::new(),
+            interrupt_timing: InterruptTiming::new(),
+            power: PowerMeter::new(),
+            profiler: Profiler::new(),
+            coverage: Coverage::new(),
+            symbols: SymbolTable::new(),
+            vcd: None,
+            stimulus: Stimulus::new(),
+            logic: LogicAnalyzer::new(),
+            observers: Observers::new(),
+            components: Components::new(),
+            input_log: None,
+            replay: None,
+            halt_policy: HaltPolicy::default(),
+            scheduler: Scheduler::new(),
+            config_word: None,
+            id_locations: None,
+            hex_parse_mode: ParseMode::default(),
         }
     }
     
     /// Reset the simulator
     pub fn reset(&mut self) {
-        self.cpu.reset();
+        self.reset_with(ResetReason::PowerOn);
+    }
+    
+    /// Reset the simulator as `reason` would, e.g. an MCLR reset keeps RAM
+    pub fn reset_with(&mut self, reason: ResetReason) {
+        if let Some(log) = &mut self.input_log {
+            log.push(self.stats.cycles_elapsed, InputKind::Reset(reason));
+        }
+        tracing::debug!(target: "pic_simulator::reset", "{}", reason);
+        self.cpu.reset_with(reason);
+        // Repeat the same WDT periods on every run from reset
+        self.cpu.wdt_mut().restart_jitter();
         self.state = SimulatorState::Paused;
         self.stats = SimulatorStats {
             instructions_executed: 0,
             cycles_elapsed: 0,
         };
+        self.history.clear();
+        self.trace.clear();
+        self.call_stack.clear();
+        self.interrupt_timing.clear();
+        self.power.clear();
+        self.stimulus.reschedule(0);
+        self.logic.clear();
+        self.stopwatch.restart(self.cpu.get_pc(), 0);
+        self.components.reset();
+        self.scheduler.invalidate();
     }
     
     /// Load a program into memory
     pub fn load_program(&mut self, program: &[u16]) {
         self.cpu.memory_mut().load_program(program);
+        self.history.clear();
+        self.trace.clear();
+        self.call_stack.clear();
+        self.profiler.clear();
+        self.coverage.reset(program.len());
+    }
+    
+    /// Overwrite one program word, returning the word it replaced
+    /// 
+    /// The cached decode of the address is dropped, so the new instruction
+    /// runs the next time it is fetched. Reverse-step history is cleared,
+    /// since its snapshots hold the old program.
+    pub fn patch_program(&mut self, address: u16, word: u16) -> u16 {
+        let old = self.cpu.memory().read_program(address);
+        self.cpu.memory_mut().write_program(address, word);
+        self.history.clear();
+        old
     }
     
     /// Execute a single instruction (step)
-    pub fn step(&mut self) -> Result<u8, String> {
+    /// 
+    /// Stepping does not allocate once the trace and capture buffers have
+    /// grown, except on the instructions where a history snapshot is due
+    /// (every 1000 by default), which copy the machine state.
+    pub fn step(&mut self) -> Result<u8, SimError> {
+        self.apply_replay();
+        let gpio_before = self.cpu.gpio().read_gpio();
+        let result = self.execute_step();
+        if let Ok(cycles) = result {
+            self.components.tick(cycles as u64, &mut self.cpu);
+        }
+        
+        let gpio_after = self.cpu.gpio().read_gpio();
+        if gpio_after != gpio_before {
+            self.emit(SimEvent::GpioChanged { old: gpio_before, new: gpio_after });
+        }
+        
+        result
+    }
+    
+    fn execute_step(&mut self) -> Result<u8, SimError> {
         if self.state == SimulatorState::Halted {
-            return Err("Simulator is halted".to_string());
+            return Err(SimError::Halted);
+        }
+        self.watch_hit = None;
+        self.stopwatch_hit = None;
+        
+        // Record a snapshot for reverse execution if one is due
+        if self.history.is_due(self.stats.instructions_executed) {
+            self.history.push(Snapshot {
+                cpu: self.cpu.clone(),
+                stats: self.stats.clone(),
+                call_stack: self.call_stack.clone(),
+                interrupt_timing: self.interrupt_timing.clone(),
+                power: self.power.clone(),
+                components: self.components.clone(),
+                replay_position: self.replay.as_ref().map(Replay::position),
+                input_log_len: self.input_log.as_ref().map(InputLog::len),
+            });
+        }
+        
+        // Apply scheduled input pin changes
+        if self.stimulus.next_change().is_some_and(|cycle| cycle <= self.stats.cycles_elapsed) {
+            self.stimulus.apply(self.stats.cycles_elapsed, self.cpu.gpio_mut());
+            self.scheduler.invalidate();
         }
+        self.logic.sample(self.stats.cycles_elapsed, self.cpu.gpio().read_pins());
+        
+        // Pin changes since the last instruction may raise INTF
+        self.cpu.sample_int_pin();
         
         // Check if CPU is sleeping
         if self.cpu.is_sleeping() {
@@ -73,58 +264,168 @@ impl Simulator {
             
             if wdt_timeout {
                 // WDT timeout - wake up from sleep
+                self.record_power(1);
                 self.cpu.wake_up(false);
+                tracing::debug!(target: "pic_simulator::sleep", "WDT timeout woke the CPU");
+                self.emit(SimEvent::Woke { by_interrupt: false });
+                self.stats.cycles_elapsed += 1;
                 return Ok(1);
             }
             
             // Check for interrupts to wake up
-            let intcon = self.cpu.read_register(crate::cpu::registers::INTCON);
-            let pie1 = self.cpu.read_register(crate::cpu::registers::PIE1);
-            let pir1 = self.cpu.read_register(crate::cpu::registers::PIR1);
-            
-            let (should_interrupt, _) = self.cpu.interrupts().check_interrupts(intcon, pie1, pir1);
-            
-            if should_interrupt {
+            if self.interrupt_pending() {
                 // Wake up by interrupt
                 self.cpu.wake_up(true);
+                tracing::debug!(target: "pic_simulator::sleep", "interrupt woke the CPU");
+                self.emit(SimEvent::Woke { by_interrupt: true });
                 // Continue to normal execution
             } else {
                 // Still sleeping, just consume 1 cycle
+                self.record_power(1);
                 self.cpu.add_cycles(1);
+                self.stats.cycles_elapsed += 1;
                 return Ok(1);
             }
         }
         
         // Normal execution (not sleeping or just woke up)
         
+        // Conditions the halt policy stops on, checked once the instruction completes
+        let mut halt = None;
+        
         // Check for interrupts BEFORE fetching next instruction
-        let interrupted = self.cpu.check_and_handle_interrupts();
+        self.interrupt_timing.observe_flags(
+            self.stats.cycles_elapsed,
+            self.cpu.read_register(crate::cpu::registers::INTCON),
+            self.cpu.read_sfr(crate::cpu::registers::PIR1),
+        );
+        let interrupted_pc = self.cpu.get_pc();
+        let stack_full = self.cpu.memory().is_stack_full();
+        let interrupt = self.cpu.check_and_handle_interrupts();
+        let interrupted = interrupt.is_some();
+        if let Some(source) = interrupt {
+            self.interrupt_timing.enter(source, self.stats.cycles_elapsed);
+            self.logic.mark(self.stats.cycles_elapsed, MarkerKind::InterruptEntered(source));
+            if stack_full && self.halt_policy.stack_overflow {
+                halt = Some(HaltReason::StackOverflow);
+            }
+            self.call_stack.on_interrupt(interrupted_pc, self.cpu.get_pc(), self.stats.cycles_elapsed);
+            tracing::trace!(target: "pic_simulator::interrupt", "{:?} interrupt at 0x{:04X}", source, interrupted_pc);
+            self.emit(SimEvent::InterruptEntered { pc: interrupted_pc, vector: self.cpu.get_pc(), source });
+        }
+        
+        // Routine the instruction is attributed to by the profiler
+        let routine = self.call_stack.current().map(|frame| frame.callee);
         
         // Fetch instruction
         let pc = self.cpu.get_pc();
         let instruction_word = self.cpu.fetch_instruction();
         
-        // Decode instruction
-        let instruction = InstructionDecoder::decode(instruction_word)
-            .map_err(|e| format!("Decode error at PC=0x{:04X}: {}", pc, e))?;
+        // Decode instruction (cached per program address); unknown words
+        // execute as NOP unless the halt policy stops on them
+        let instruction = match self.cpu.memory_mut().read_instruction(pc) {
+            Ok(instruction) => instruction,
+            Err(_) if !self.halt_policy.unknown_opcode => Instruction::NOP,
+            Err(e) => return Err(SimError::InvalidOpcode { pc, opcode: e.opcode }),
+        };
         
         // Increment PC before execution
         self.cpu.increment_pc();
         
-        // Execute instruction
+        // Execute instruction, recording register accesses if watched
+        let stack_full = self.cpu.memory().is_stack_full();
+        let stack_empty = self.cpu.memory().is_stack_empty();
+        let tracking = !self.watchpoints.is_empty() || !self.observers.is_empty()
+            || self.halt_policy.unimplemented_write;
+        if tracking {
+            self.cpu.begin_access_tracking();
+        }
         let cycles = Executor::execute(&mut self.cpu, instruction);
+        if tracking {
+            let accesses = self.cpu.end_access_tracking();
+            self.check_watchpoints(pc, &accesses);
+            for access in accesses.iter().filter(|a| a.kind == AccessKind::Write) {
+                // Banks 2 and 3 hold RAM only
+                let implemented = u8::try_from(access.address)
+                    .map_or(true, |address| self.cpu.device().model().is_implemented(address));
+                if self.halt_policy.unimplemented_write && !implemented {
+                    halt.get_or_insert(HaltReason::UnimplementedWrite { address: access.address });
+                }
+                self.emit(SimEvent::MemoryWritten { addr: access.address, old: access.old, new: access.new });
+            }
+            self.cpu.recycle_access_log(accesses);
+        }
+        if instruction == Instruction::SLEEP {
+            tracing::debug!(target: "pic_simulator::sleep", "SLEEP at 0x{:04X}", pc);
+            self.emit(SimEvent::SleepEntered { pc });
+            if self.halt_policy.sleep_forever && self.cpu.is_sleeping() && !self.wake_source_enabled() {
+                halt.get_or_insert(HaltReason::SleepForever);
+            }
+        }
+        match instruction {
+            Instruction::CALL { .. } if stack_full && self.halt_policy.stack_overflow => {
+                halt.get_or_insert(HaltReason::StackOverflow);
+            }
+            Instruction::RETURN | Instruction::RETLW { .. } | Instruction::RETFIE
+                if stack_empty && self.halt_policy.stack_underflow => {
+                halt.get_or_insert(HaltReason::StackUnderflow);
+            }
+            _ => {}
+        }
         
-        // Tick timers and WDT for each cycle consumed
-        for _ in 0..cycles {
-            let (tmr0_overflow, tmr1_overflow) = self.cpu.timers_mut().tick();
-            
-            // Tick WDT
-            let wdt_timeout = self.cpu.wdt_mut().tick();
+        // Record coverage, including the outcome of skip instructions
+        self.coverage.record_instruction(pc);
+        if InstructionDecoder::is_skip_instruction(&instruction) {
+            let skipped = self.cpu.get_pc() == (pc.wrapping_add(2) & 0x1FFF);
+            self.coverage.record_branch(pc, skipped);
+        }
+        
+        // Follow calls and returns for the logical call stack
+        let returned = match instruction {
+            Instruction::CALL { .. } => {
+                self.call_stack.on_call(pc, self.cpu.get_pc(), self.stats.cycles_elapsed);
+                self.profiler.record_call(self.cpu.get_pc());
+                None
+            }
+            Instruction::RETURN | Instruction::RETLW { .. } => self.call_stack.on_return(),
+            Instruction::RETFIE => self.call_stack.on_retfie(),
+            _ => None,
+        };
+        
+        // Advance timers and the WDT through the cycles consumed, in bulk
+        // up to each scheduled event
+        let start = self.stats.cycles_elapsed;
+        let end = start + cycles as u64;
+        let mut now = start;
+        self.logic.sample(start + 1, self.cpu.gpio().read_pins());
+        while now < end {
+            self.refresh_schedule(now);
+            // Cycle by cycle while recording a waveform, so it shows every count
+            let limit = if self.vcd.is_some() { now + 1 } else { end };
+            let next = self.scheduler.next_cycle()
+                .map_or(limit, |cycle| cycle.clamp(now + 1, limit));
+            self.cpu.advance_peripherals(next - now - 1);
+            let (tmr0_overflow, tmr1_overflow, tmr2_match, wdt_timeout) = self.cpu.tick_peripherals();
+            now = next;
+            if tmr0_overflow || tmr1_overflow || tmr2_match || wdt_timeout {
+                self.scheduler.invalidate();
+            }
             
             if wdt_timeout && !self.cpu.is_sleeping() {
-                // WDT timeout during normal operation causes reset
-                println!("⚠ WDT timeout - resetting CPU");
-                self.cpu.reset();
+                if self.halt_policy.wdt_reset {
+                    // Keep the state at the timeout for inspection
+                    halt.get_or_insert(HaltReason::WdtTimeout);
+                    break;
+                }
+                
+                // WDT timeout during normal operation causes reset;
+                // front-ends report it from the event
+                let pc = self.cpu.get_pc();
+                tracing::warn!(target: "pic_simulator::wdt", "WDT timeout at 0x{:04X} - resetting CPU", pc);
+                self.cpu.reset_with(ResetReason::Wdt);
+                self.call_stack.clear();
+                self.interrupt_timing.clear();
+                self.emit(SimEvent::WdtReset { cycle: now, pc });
                 return Ok(cycles);
             }
             
@@ -132,45 +433,370 @@ impl Simulator {
             if tmr0_overflow {
                 let intcon = self.cpu.read_register(crate::cpu::registers::INTCON);
                 self.cpu.write_register(crate::cpu::registers::INTCON, intcon | 0x04);
+                self.interrupt_timing.flag_raised(InterruptSource::Timer0Overflow, now);
+                self.logic.mark(now, MarkerKind::Timer0Overflow);
             }
             
             if tmr1_overflow {
                 let pir1 = self.cpu.read_register(crate::cpu::registers::PIR1);
                 self.cpu.write_register(crate::cpu::registers::PIR1, pir1 | 0x01);
+                self.interrupt_timing.flag_raised(InterruptSource::Timer1Overflow, now);
+            }
+            
+            if tmr2_match {
+                let pir1 = self.cpu.read_register(crate::cpu::registers::PIR1);
+                self.cpu.write_register(crate::cpu::registers::PIR1, pir1 | 0x02);
+                self.interrupt_timing.flag_raised(InterruptSource::Timer2Match, now);
+            }
+            
+            if let Some(vcd) = &mut self.vcd {
+                vcd.sample(now, &self.cpu);
             }
         }
         
-        // Add extra cycles if interrupt was serviced
+        // Add extra cycles if interrupt was serviced; the timers don't
+        // count them, so their scheduled events move
         let total_cycles = if interrupted {
+            self.scheduler.invalidate();
             cycles + 2
         } else {
             cycles
         };
         
+        // Report the executed instruction (this also records the trace)
+        self.emit(SimEvent::InstructionExecuted {
+            cycle: self.stats.cycles_elapsed,
+            pc,
+            word: instruction_word,
+            cycles: total_cycles,
+            w: self.cpu.read_w(),
+            status: self.cpu.read_register(crate::cpu::registers::STATUS),
+        });
+        
         // Update statistics
         self.stats.instructions_executed += 1;
         self.stats.cycles_elapsed += total_cycles as u64;
         self.cpu.add_cycles(total_cycles as u64);
+        self.record_power(total_cycles as u64);
+        if instruction == Instruction::RETFIE {
+            self.interrupt_timing.exit(self.stats.cycles_elapsed);
+        }
+        
+        // Update the profile
+        self.profiler.record_instruction(pc, total_cycles as u64, routine);
+        if let Some(frame) = returned.filter(|f| f.kind == FrameKind::Call) {
+            self.profiler.record_return(frame.callee, frame.cycles_spent(self.stats.cycles_elapsed));
+        }
+        
+        // Time the next instruction against the stopwatch points
+        if let Some(reading) = self.stopwatch.update(self.cpu.get_pc(), self.stats.cycles_elapsed) {
+            self.stopwatch_hit = Some(reading);
+            self.pause();
+        }
+        
+        if let Some(reason) = halt {
+            tracing::info!(target: "pic_simulator::halt", "halted at 0x{:04X}: {}", pc, reason);
+            self.state = SimulatorState::Halted;
+            return Err(SimError::HaltCondition { pc, reason });
+        }
         
         Ok(total_cycles)
     }
     
+    /// Charge `cycles` spent in the CPU's current mode to the power meter
+    fn record_power(&mut self, cycles: u64) {
+        let mode = PowerMode::from_cpu(&self.cpu);
+        self.power.record(mode, cycles, self.clock.fosc_hz());
+    }
+    
+    /// Check if an enabled interrupt flag is set (wakes the CPU from sleep)
+    fn interrupt_pending(&self) -> bool {
+        let intcon = self.cpu.read_register(crate::cpu::registers::INTCON);
+        let pie1 = self.cpu.read_sfr(crate::cpu::registers::PIE1);
+        let pir1 = self.cpu.read_sfr(crate::cpu::registers::PIR1);
+        
+        self.cpu.interrupts().check_interrupts(intcon, pie1, pir1).is_some()
+    }
+    
+    /// Check if anything can wake a sleeping CPU: the WDT or an enabled
+    /// GPIO change, INT or peripheral interrupt (Timer0 stops in sleep)
+    fn wake_source_enabled(&self) -> bool {
+        let intcon = self.cpu.read_register(crate::cpu::registers::INTCON);
+        let pie1 = self.cpu.read_sfr(crate::cpu::registers::PIE1);
+        
+        self.cpu.wdt().is_enabled() || intcon & 0x18 != 0 || (intcon & 0x40 != 0 && pie1 != 0)
+    }
+    
+    /// Fast-forward a sleeping CPU by up to `max_cycles` cycles
+    /// 
+    /// While asleep only the WDT counts, so the cycles up to the next wake
+    /// event (WDT timeout, scheduled stimulus change or replayed input) are skipped in one
+    /// call. The event itself is left for the next `step`, which handles
+    /// it exactly as if the skipped cycles had been stepped one by one.
+    /// Attached components are ticked once with all skipped cycles.
+    /// Timer1 does not count during sleep in this model, so it cannot wake
+    /// the CPU. Returns the number of cycles skipped.
+    pub fn skip_sleep(&mut self, max_cycles: u64) -> u64 {
+        if !self.cpu.is_sleeping() || self.state == SimulatorState::Halted || self.interrupt_pending() {
+            return 0;
+        }
+        
+        let now = self.stats.cycles_elapsed;
+        self.refresh_schedule(now);
+        let mut skip = max_cycles;
+        for &(cycle, kind) in self.scheduler.events() {
+            let limit = match kind {
+                // The tick that times out is left for `step`
+                EventKind::WdtTimeout => cycle.saturating_sub(now + 1),
+                EventKind::StimulusChange | EventKind::ReplayInput => cycle.saturating_sub(now),
+                // Timers are stopped during sleep
+                EventKind::Timer0Overflow | EventKind::Timer1Overflow | EventKind::Timer2 => continue,
+            };
+            skip = skip.min(limit);
+        }
+        
+        self.cpu.wdt_mut().advance(skip);
+        self.record_power(skip);
+        self.cpu.add_cycles(skip);
+        self.stats.cycles_elapsed += skip;
+        if skip > 0 {
+            self.components.tick(skip, &mut self.cpu);
+        }
+        skip
+    }
+    
+    /// Apply replayed inputs that are due before the next instruction
+    fn apply_replay(&mut self) {
+        while let Some(event) = self.replay.as_mut().and_then(|r| r.next_due(self.stats.cycles_elapsed)) {
+            match event.kind {
+                InputKind::Pin { pin, level } => self.set_external_pin(pin, level),
+                InputKind::Reset(reason) => self.reset_with(reason),
+            }
+            self.scheduler.invalidate();
+        }
+    }
+    
+    /// Rebuild the event queue if it no longer matches the peripherals,
+    /// scheduling each peripheral's next event relative to `now`
+    fn refresh_schedule(&mut self, now: u64) {
+        if self.cpu.take_peripherals_changed() {
+            self.scheduler.invalidate();
+        }
+        if self.scheduler.is_valid() {
+            return;
+        }
+        
+        self.scheduler.rebuild();
+        if let Some(ticks) = self.cpu.wdt().cycles_until_timeout() {
+            self.scheduler.schedule(now + ticks, EventKind::WdtTimeout);
+        }
+        let timers = self.cpu.timers();
+        if let Some(ticks) = timers.timer0.cycles_until_overflow() {
+            self.scheduler.schedule(now + ticks, EventKind::Timer0Overflow);
+        }
+        if let Some(ticks) = timers.timer1.cycles_until_overflow() {
+            self.scheduler.schedule(now + ticks, EventKind::Timer1Overflow);
+        }
+        if self.cpu.device().model().peripherals.ccp
+            && let Some(ticks) = self.cpu.ccp().cycles_until_event()
+        {
+            self.scheduler.schedule(now + ticks, EventKind::Timer2);
+        }
+        if let Some(cycle) = self.stimulus.next_change() {
+            self.scheduler.schedule(cycle, EventKind::StimulusChange);
+        }
+        if let Some(cycle) = self.replay.as_ref().and_then(Replay::next_cycle) {
+            self.scheduler.schedule(cycle, EventKind::ReplayInput);
+        }
+    }
+    
+    /// Compare the accesses of the instruction at `pc` against the
+    /// watchpoints, pausing execution on the first hit
+    fn check_watchpoints(&mut self, pc: u16, accesses: &[RegisterAccess]) {
+        let hit = accesses.iter().find_map(|access| {
+            self.watchpoints.iter_mut()
+                .find(|wp| wp.enabled && wp.matches(access))
+                .map(|watchpoint| {
+                    watchpoint.hits += 1;
+                    WatchHit { pc, watchpoint: *watchpoint, access: *access }
+                })
+        });
+        
+        if hit.is_some() {
+            self.watch_hit = hit;
+            self.pause();
+        }
+    }
+    
+    /// Register a callback for simulator events
+    pub fn subscribe<F: FnMut(&SimEvent) + 'static>(&mut self, callback: F) -> ObserverId {
+        self.observers.subscribe(Box::new(callback))
+    }
+    
+    /// Remove an event callback; returns false if `id` is unknown
+    pub fn unsubscribe(&mut self, id: ObserverId) -> bool {
+        self.observers.unsubscribe(id)
+    }
+    
+    /// Receive simulator events through a channel
+    /// The subscription stays registered after the receiver is dropped;
+    /// sends to a closed channel are ignored.
+    pub fn event_channel(&mut self) -> Receiver<SimEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.observers.subscribe_channel(sender);
+        receiver
+    }
+    
+    /// Attach an external component, ticked after every step
+    pub fn attach_component(&mut self, mut component: Box<dyn Component>) -> ComponentId {
+        component.set_clock(&self.clock);
+        self.components.attach(component)
+    }
+    
+    /// Detach a component, handing it back
+    pub fn detach_component(&mut self, id: ComponentId) -> Option<Box<dyn Component>> {
+        self.components.detach(id)
+    }
+    
+    /// Get the attached components
+    pub fn components(&self) -> &Components {
+        &self.components
+    }
+    
+    /// Get mutable reference to the attached components
+    pub fn components_mut(&mut self) -> &mut Components {
+        &mut self.components
+    }
+    
+    /// Replace the attached components with those of a circuit
+    /// Returns the number of components attached
+    pub fn load_circuit(&mut self, circuit: &Circuit) -> Result<usize, SimError> {
+        let components = circuit.build()?;
+        self.check_pins(components.iter().flat_map(|component| component.pins()))?;
+        self.components.clear();
+        for mut component in components {
+            component.set_clock(&self.clock);
+            self.components.attach(component);
+        }
+        Ok(self.components.len())
+    }
+    
+    /// Load a circuit description file (see `load_circuit`)
+    pub fn load_circuit_file<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, SimError> {
+        let circuit = Circuit::load_file(path)?;
+        self.load_circuit(&circuit)
+    }
+    
+    /// Get an attached component as its concrete type
+    pub fn component_as<T: Component + 'static>(&self, id: ComponentId) -> Option<&T> {
+        self.components.get(id)?.as_any().downcast_ref()
+    }
+    
+    /// Get mutable reference to an attached component as its concrete type
+    pub fn component_as_mut<T: Component + 'static>(&mut self, id: ComponentId) -> Option<&mut T> {
+        self.components.get_mut(id)?.as_any_mut().downcast_mut()
+    }
+    
+    /// Deliver an event to the trace buffer and all observers
+    fn emit(&mut self, event: SimEvent) {
+        self.trace.on_event(&event);
+        self.observers.emit(&event);
+    }
+    
+    /// Step backwards by `n` instructions
+    /// 
+    /// Restores the nearest snapshot at or before the target instruction and
+    /// re-executes forward to it. Returns the number of instructions actually
+    /// undone, which is less than `n` when the history does not reach back far enough.
+    /// 
+    /// Attached components are rewound along with the CPU; ones attached
+    /// after the restored snapshot return to their power-on state.
+    /// 
+    /// Replayed inputs after the restored snapshot are applied again on the
+    /// way forward, and the input recording is cut back to match.
+    /// 
+    /// Note: changes made from outside the simulator (e.g. external pin toggles)
+    /// after the restored snapshot are not replayed.
+    pub fn step_back(&mut self, n: u64) -> Result<u64, SimError> {
+        let current = self.stats.instructions_executed;
+        if n == 0 || current == 0 {
+            return Ok(0);
+        }
+        
+        let oldest = self.history.oldest_instruction()
+            .ok_or(SimError::NoHistory)?;
+        let target = current.saturating_sub(n).max(oldest);
+        let snapshot = self.history.nearest(target)
+            .cloned()
+            .ok_or(SimError::NoHistory)?;
+        
+        // Rewind to the snapshot and discard everything recorded after it
+        self.history.truncate_after(snapshot.instruction());
+        self.cpu = snapshot.cpu;
+        self.components.restore(&snapshot.components);
+        self.apply_clock();
+        self.stats = snapshot.stats;
+        self.call_stack = snapshot.call_stack;
+        self.interrupt_timing = snapshot.interrupt_timing;
+        self.power = snapshot.power;
+        // Inputs after the snapshot are replayed and recorded again
+        if let (Some(replay), Some(position)) = (&mut self.replay, snapshot.replay_position) {
+            replay.rewind(position);
+        }
+        if let Some(log) = &mut self.input_log {
+            log.truncate(snapshot.input_log_len.unwrap_or(0));
+        }
+        self.trace.truncate_from_cycle(self.stats.cycles_elapsed);
+        self.stimulus.reschedule(self.stats.cycles_elapsed);
+        self.logic.rewind(self.stats.cycles_elapsed, self.cpu.gpio().read_pins());
+        self.stopwatch.rewind(self.stats.cycles_elapsed);
+        self.scheduler.invalidate();
+        self.state = SimulatorState::Paused;
+        
+        // Re-execute forward to the target instruction. The profiler,
+        // coverage, observers and watchpoints have already seen these
+        // instructions, so they are detached while replaying; components were rewound with
+        // the snapshot and stay attached to drive their pins as before.
+        let profiling = self.profiler.is_enabled();
+        self.profiler.set_enabled(false);
+        let covering = self.coverage.is_enabled();
+        self.coverage.set_enabled(false);
+        let observers = std::mem::take(&mut self.observers);
+        let watchpoints = std::mem::take(&mut self.watchpoints);
+        let mut result = Ok(current - target);
+        while self.stats.instructions_executed < target {
+            if let Err(e) = self.step() {
+                result = Err(e);
+                break;
+            }
+        }
+        self.profiler.set_enabled(profiling);
+        self.coverage.set_enabled(covering);
+        self.observers = observers;
+        self.watchpoints = watchpoints;
+        self.watch_hit = None;
+        self.stopwatch_hit = None;
+        
+        result
+    }
+    
     /// Run until breakpoint or error
-    pub fn run(&mut self) -> Result<(), String> {
+    pub fn run(&mut self) -> Result<(), SimError> {
         self.state = SimulatorState::Running;
         
         while self.state == SimulatorState::Running {
             let pc = self.cpu.get_pc();
             
             // Check for breakpoint
-            if self.breakpoints.contains(&pc) {
+            if self.check_breakpoint(pc) {
                 self.state = SimulatorState::Paused;
                 return Ok(());
             }
             
-            // Execute one instruction
+            // Execute one instruction, skipping idle sleep cycles
+            self.skip_sleep(u32::MAX as u64);
             if let Err(e) = self.step() {
-                self.state = SimulatorState::Error;
+                self.fail();
                 return Err(e);
             }
         }
@@ -178,8 +804,81 @@ impl Simulator {
         Ok(())
     }
     
+    /// Run until `predicate` holds, stopping early at breakpoints,
+    /// watchpoints, errors or after `max_cycles` cycles
+    /// 
+    /// The predicate is checked before every instruction, so a condition
+    /// that already holds returns immediately. A breakpoint at the starting
+    /// PC is stepped over, so the run can be resumed after stopping there.
+    /// Idle sleep cycles are skipped in bulk (see `skip_sleep`), so the
+    /// predicate is not evaluated for each of them.
+    pub fn run_until<F>(&mut self, mut predicate: F, max_cycles: u64) -> RunOutcome
+    where
+        F: FnMut(&Simulator) -> bool,
+    {
+        let limit = self.stats.cycles_elapsed.saturating_add(max_cycles);
+        let start_pc = self.cpu.get_pc();
+        let mut first = true;
+        if self.state != SimulatorState::Halted {
+            self.state = SimulatorState::Running;
+        }
+        
+        let outcome = loop {
+            if predicate(self) {
+                break RunOutcome::PredicateMet;
+            }
+            if self.stats.cycles_elapsed >= limit {
+                break RunOutcome::CycleLimit;
+            }
+            
+            let pc = self.cpu.get_pc();
+            if !(first && pc == start_pc) && self.check_breakpoint(pc) {
+                break RunOutcome::Breakpoint(pc);
+            }
+            first = false;
+            
+            if self.skip_sleep(limit - self.stats.cycles_elapsed) > 0 {
+                continue;
+            }
+            if let Err(e) = self.step() {
+                self.fail();
+                return RunOutcome::Error(e);
+            }
+            if let Some(hit) = self.watch_hit {
+                break RunOutcome::Watchpoint(hit);
+            }
+        };
+        
+        self.state = SimulatorState::Paused;
+        outcome
+    }
+    
+    /// Run for `duration` of simulated time at the configured Fosc
+    /// 
+    /// Stops early at breakpoints and watchpoints. Returns the simulated
+    /// time actually executed, which can exceed `duration` by the last
+    /// instruction's extra cycle.
+    pub fn run_for(&mut self, duration: Duration) -> Result<Duration, SimError> {
+        self.run_for_cycles(self.clock.duration_to_cycles(duration))
+    }
+    
+    /// Run for `micros` microseconds of simulated time (see `run_for`)
+    pub fn run_for_micros(&mut self, micros: u64) -> Result<Duration, SimError> {
+        self.run_for_cycles(self.clock.micros_to_cycles(micros))
+    }
+    
+    fn run_for_cycles(&mut self, cycles: u64) -> Result<Duration, SimError> {
+        let start = self.stats.cycles_elapsed;
+        let outcome = self.run_until(|_| false, cycles);
+        let elapsed = self.clock.cycles_to_duration(self.stats.cycles_elapsed - start);
+        match outcome {
+            RunOutcome::Error(e) => Err(e),
+            _ => Ok(elapsed),
+        }
+    }
+    
     /// Run for a specific number of instructions
-    pub fn run_n_instructions(&mut self, n: u64) -> Result<(), String> {
+    pub fn run_n_instructions(&mut self, n: u64) -> Result<(), SimError> {
         for _ in 0..n {
             self.step()?;
         }
@@ -187,11 +886,13 @@ impl Simulator {
     }
     
     /// Run for a specific number of cycles
-    pub fn run_n_cycles(&mut self, n: u64) -> Result<(), String> {
+    pub fn run_n_cycles(&mut self, n: u64) -> Result<(), SimError> {
         let target_cycles = self.stats.cycles_elapsed + n;
         
         while self.stats.cycles_elapsed < target_cycles {
-            self.step()?;
+            if self.skip_sleep(target_cycles - self.stats.cycles_elapsed) == 0 {
+                self.step()?;
+            }
         }
         
         Ok(())
@@ -209,6 +910,41 @@ impl Simulator {
         self.state = SimulatorState::Halted;
     }
     
+    /// Enter the error state after a failed step, unless it halted
+    fn fail(&mut self) {
+        if self.state != SimulatorState::Halted {
+            self.state = SimulatorState::Error;
+        }
+    }
+    
+    /// Conditions that halt execution
+    pub fn halt_policy(&self) -> &HaltPolicy {
+        &self.halt_policy
+    }
+    
+    /// Choose which conditions halt execution
+    pub fn set_halt_policy(&mut self, policy: HaltPolicy) {
+        self.halt_policy = policy;
+    }
+    
+    /// How HEX files are parsed
+    pub fn hex_parse_mode(&self) -> ParseMode {
+        self.hex_parse_mode
+    }
+    
+    /// Choose whether malformed HEX files fail to load or load with warnings
+    pub fn set_hex_parse_mode(&mut self, mode: ParseMode) {
+        self.hex_parse_mode = mode;
+    }
+    
+    /// Parse options for HEX files loaded into this device
+    fn hex_parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            mode: self.hex_parse_mode,
+            program_words: self.cpu.memory().program_size(),
+        }
+    }
+    
     /// Get current state
     pub fn state(&self) -> SimulatorState {
         self.state
@@ -221,6 +957,8 @@ impl Simulator {
     
     /// Get mutable reference to CPU
     pub fn cpu_mut(&mut self) -> &mut Cpu {
+        // The caller may change anything, so reschedule from scratch
+        self.scheduler.invalidate();
         &mut self.cpu
     }
     
@@ -231,14 +969,60 @@ impl Simulator {
     
     /// Add a breakpoint
     pub fn add_breakpoint(&mut self, address: u16) {
-        if !self.breakpoints.contains(&address) {
-            self.breakpoints.push(address);
+        if !self.has_breakpoint(address) {
+            self.breakpoints.push(Breakpoint::new(address));
         }
     }
     
     /// Remove a breakpoint
     pub fn remove_breakpoint(&mut self, address: u16) {
-        self.breakpoints.retain(|&bp| bp != address);
+        self.breakpoints.retain(|bp| bp.address != address);
+    }
+    
+    /// Enable or disable the breakpoint at `address`, if there is one
+    pub fn set_breakpoint_enabled(&mut self, address: u16, enabled: bool) {
+        if let Some(bp) = self.breakpoints.iter_mut().find(|bp| bp.address == address) {
+            bp.enabled = enabled;
+        }
+    }
+    
+    /// Set or clear the condition of the breakpoint at `address`, if there is one
+    pub fn set_breakpoint_condition(&mut self, address: u16, condition: Option<Expr>) {
+        if let Some(bp) = self.breakpoints.iter_mut().find(|bp| bp.address == address) {
+            bp.condition = condition;
+        }
+    }
+    
+    /// Whether an enabled breakpoint at `pc` stops execution here, counting the hit
+    /// 
+    /// A conditional breakpoint only stops when its condition is true.
+    pub fn check_breakpoint(&mut self, pc: u16) -> bool {
+        let Some(index) = self.breakpoints.iter().position(|bp| bp.address == pc && bp.enabled) else {
+            return false;
+        };
+        let stop = self.breakpoints[index].condition.as_ref().is_none_or(|condition| condition.is_true(self));
+        if stop {
+            self.breakpoints[index].hits += 1;
+        }
+        stop
+    }
+    
+    /// Set a breakpoint at `address`, or remove the one already there
+    /// 
+    /// Returns whether a breakpoint is now set.
+    pub fn toggle_breakpoint(&mut self, address: u16) -> bool {
+        if self.has_breakpoint(address) {
+            self.remove_breakpoint(address);
+            false
+        } else {
+            self.add_breakpoint(address);
+            true
+        }
+    }
+    
+    /// Whether a breakpoint, enabled or not, is set at `address`
+    pub fn has_breakpoint(&self, address: u16) -> bool {
+        self.breakpoints.iter().any(|bp| bp.address == address)
     }
     
     /// Clear all breakpoints
@@ -247,119 +1031,657 @@ impl Simulator {
     }
     
     /// Get all breakpoints
-    pub fn breakpoints(&self) -> &[u16] {
+    pub fn breakpoints(&self) -> &[Breakpoint] {
         &self.breakpoints
     }
     
-    /// Print CPU state (for debugging)
-    pub fn print_state(&self) {
-        println!("PC:     0x{:04X}", self.cpu.get_pc());
-        println!("W:      0x{:02X}", self.cpu.read_w());
-        
-        let status = self.cpu.read_register(0x03);
-        println!("STATUS: 0x{:02X} [C={} DC={} Z={}]",
-            status,
-            if status & 0x01 != 0 { "1" } else { "0" },
-            if status & 0x02 != 0 { "1" } else { "0" },
-            if status & 0x04 != 0 { "1" } else { "0" },
-        );
-        
-        println!("Cycles: {}", self.stats.cycles_elapsed);
-        println!("Instructions: {}", self.stats.instructions_executed);
-    }
-
-    /// Load a HEX file
-    pub fn load_hex_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
-        let hex_program = HexLoader::load_file(path)?;
-        self.load_hex_program(hex_program);
-        Ok(())
+    /// Add a register watchpoint, replacing any on the same register
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.retain(|wp| wp.address != watchpoint.address);
+        self.watchpoints.push(watchpoint);
     }
     
-    /// Load a HEX program from string
-    pub fn load_hex_string(&mut self, content: &str) -> Result<(), String> {
-        let hex_program = HexLoader::load_from_string(content)?;
-        self.load_hex_program(hex_program);
-        Ok(())
+    /// Remove the watchpoint on a register
+    pub fn remove_watchpoint(&mut self, address: u8) {
+        self.watchpoints.retain(|wp| wp.address != address);
     }
     
-    /// Load a parsed HEX program
-    fn load_hex_program(&mut self, hex_program: HexProgram) {
-        // Load program memory
-        self.cpu.memory_mut().load_program(&hex_program.program);
-        
-        // Load EEPROM if present
-        if !hex_program.eeprom.is_empty() {
-            for (i, &byte) in hex_program.eeprom.iter().enumerate() {
-                if i < 128 {
-                    self.cpu.memory_mut().write_eeprom(i as u8, byte);
-                }
-            }
+    /// Enable or disable the watchpoint on a register, if there is one
+    pub fn set_watchpoint_enabled(&mut self, address: u8, enabled: bool) {
+        if let Some(wp) = self.watchpoints.iter_mut().find(|wp| wp.address == address) {
+            wp.enabled = enabled;
         }
-        
-        // Set PC to start address
-        self.cpu.set_pc(hex_program.start_address);
     }
     
-}
-
-impl Default for Simulator {
-    fn default() -> Self {
-        Self::new()
+    /// Clear all watchpoints
+    pub fn clear_watchpoints(&mut self) {
+        self.watchpoints.clear();
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
     
-    #[test]
-    fn test_simulator_creation() {
-        let sim = Simulator::new();
-        assert_eq!(sim.state(), SimulatorState::Paused);
+    /// Get all watchpoints
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
     }
     
-    #[test]
-    fn test_load_and_run_simple_program() {
-        let mut sim = Simulator::new();
-        sim.reset();
-        
-        // Program: MOVLW 0x55, MOVWF 0x20, NOP
-        let program = vec![
-            0x3055,  // MOVLW 0x55
-            0x00A0,  // MOVWF 0x20
-            0x0000,  // NOP
-        ];
-        
-        sim.load_program(&program);
-        
-        // Run 3 instructions
-        sim.run_n_instructions(3).unwrap();
-        
-        assert_eq!(sim.cpu().read_w(), 0x55);
-        assert_eq!(sim.cpu().read_register(0x20), 0x55);
-        assert_eq!(sim.stats().instructions_executed, 3);
+    /// Watchpoint triggered by the last executed instruction
+    pub fn watch_hit(&self) -> Option<&WatchHit> {
+        self.watch_hit.as_ref()
     }
     
-    #[test]
-    fn test_breakpoint() {
-        let mut sim = Simulator::new();
-        sim.reset();
-        
-        // Program with loop
-        let program = vec![
-            0x3055,  // 0x000: MOVLW 0x55
-            0x00A0,  // 0x001: MOVWF 0x20
-            0x2800,  // 0x002: GOTO 0x000 (infinite loop)
-        ];
-        
-        sim.load_program(&program);
-        sim.add_breakpoint(0x002);
-        
-        // Run until breakpoint
-        sim.run().unwrap();
-        
-        assert_eq!(sim.cpu().get_pc(), 0x002);
-        assert_eq!(sim.state(), SimulatorState::Paused);
+    /// Arm the stopwatch to time execution from `start` to `stop`
+    pub fn arm_stopwatch(&mut self, start: u16, stop: u16) {
+        self.stopwatch.arm(start, stop, self.cpu.get_pc(), self.stats.cycles_elapsed);
+    }
+    
+    /// Disarm the stopwatch
+    pub fn disarm_stopwatch(&mut self) {
+        self.stopwatch.disarm();
+    }
+    
+    /// Get the stopwatch
+    pub fn stopwatch(&self) -> &Stopwatch {
+        &self.stopwatch
+    }
+    
+    /// Stopwatch measurement completed by the last executed instruction
+    pub fn stopwatch_hit(&self) -> Option<&StopwatchReading> {
+        self.stopwatch_hit.as_ref()
+    }
+    
+    /// Get the part being simulated
+    pub fn device(&self) -> Device {
+        self.cpu.device()
+    }
+    
+    /// Switch to another part and reset; program and EEPROM are kept
+    pub fn set_device(&mut self, device: Device) {
+        self.cpu.set_device(device);
+        self.reset();
+    }
+    
+    /// Get the oscillator clock
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+    
+    /// Replace the oscillator clock
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+        self.apply_clock();
+    }
+    
+    /// Oscillator frequency in Hz
+    pub fn fosc(&self) -> u32 {
+        self.clock.fosc_hz()
+    }
+    
+    /// Set the oscillator frequency in Hz, keeping the oscillator mode
+    pub fn set_fosc(&mut self, hz: u32) {
+        self.clock.set_fosc_hz(hz);
+        self.apply_clock();
+    }
+    
+    /// Simulated time since reset
+    pub fn elapsed_time(&self) -> Duration {
+        self.clock.cycles_to_duration(self.stats.cycles_elapsed)
+    }
+    
+    /// Derive clock-dependent peripheral timing from the clock
+    fn apply_clock(&mut self) {
+        let period = self.clock.wdt_period_cycles();
+        self.cpu.wdt_mut().set_nominal_period(period);
+        self.components.set_clock(&self.clock);
+    }
+    
+    /// Get the recent-instruction trace
+    pub fn trace(&self) -> &TraceBuffer {
+        &self.trace
+    }
+    
+    /// Get interrupt latency and ISR timing
+    pub fn interrupt_timing(&self) -> &InterruptTiming {
+        &self.interrupt_timing
+    }
+    
+    /// Get the estimated power consumption since reset
+    pub fn power(&self) -> &PowerMeter {
+        &self.power
+    }
+    
+    /// Get mutable reference to the power meter
+    pub fn power_mut(&mut self) -> &mut PowerMeter {
+        &mut self.power
+    }
+    
+    /// Get the logical call stack
+    pub fn call_stack(&self) -> &CallStack {
+        &self.call_stack
+    }
+    
+    /// Get the cycle profiler
+    pub fn profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+    
+    /// Get mutable reference to the cycle profiler
+    pub fn profiler_mut(&mut self) -> &mut Profiler {
+        &mut self.profiler
+    }
+    
+    /// Get the code coverage collected so far
+    pub fn coverage(&self) -> &Coverage {
+        &self.coverage
+    }
+    
+    /// Get mutable reference to the code coverage
+    pub fn coverage_mut(&mut self) -> &mut Coverage {
+        &mut self.coverage
+    }
+    
+    /// Get the loaded symbol table
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+    
+    /// Get mutable reference to the symbol table
+    pub fn symbols_mut(&mut self) -> &mut SymbolTable {
+        &mut self.symbols
+    }
+    
+    /// Load a symbol file (.lst or .map), replacing any loaded symbols
+    /// Returns the number of labels loaded
+    pub fn load_symbol_file<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, SimError> {
+        self.symbols = SymbolTable::load_file(path)?;
+        Ok(self.symbols.len())
+    }
+    
+    /// Start recording a VCD waveform to `path`
+    pub fn start_vcd<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SimError> {
+        let file = File::create(path)
+            .map_err(SimError::io("Failed to create VCD file"))?;
+        self.start_vcd_writer(Box::new(BufWriter::new(file)))
+    }
+    
+    /// Start recording a VCD waveform to any writer
+    /// A capture already in progress is finished first
+    pub fn start_vcd_writer(&mut self, writer: Box<dyn Write>) -> Result<(), SimError> {
+        self.stop_vcd()?;
+        
+        let mut vcd = VcdWriter::new(writer, self.cpu.device().model(), &self.clock)
+            .map_err(SimError::io("Failed to write VCD header"))?;
+        vcd.sample(self.stats.cycles_elapsed, &self.cpu);
+        self.vcd = Some(vcd);
+        Ok(())
+    }
+    
+    /// Stop recording and flush the VCD waveform
+    pub fn stop_vcd(&mut self) -> Result<(), SimError> {
+        match self.vcd.take() {
+            Some(vcd) => vcd.finish()
+                .map(|_| ())
+                .map_err(SimError::io("Failed to write VCD file")),
+            None => Ok(()),
+        }
+    }
+    
+    /// Check if a VCD waveform is being recorded
+    pub fn is_recording_vcd(&self) -> bool {
+        self.vcd.is_some()
+    }
+    
+    /// Get the logic analyzer capture
+    pub fn logic_analyzer(&self) -> &LogicAnalyzer {
+        &self.logic
+    }
+    
+    /// Get mutable reference to the logic analyzer
+    pub fn logic_analyzer_mut(&mut self) -> &mut LogicAnalyzer {
+        &mut self.logic
+    }
+    
+    /// Get the pin stimulus
+    pub fn stimulus(&self) -> &Stimulus {
+        &self.stimulus
+    }
+    
+    /// Replace the pin stimulus; rules are timed from cycle 0
+    pub fn set_stimulus(&mut self, stimulus: Stimulus) {
+        self.stimulus = stimulus;
+        self.stimulus.reschedule(self.stats.cycles_elapsed);
+        self.scheduler.invalidate();
+    }
+    
+    /// Load a pin stimulus script, returning the number of rules
+    pub fn load_stimulus_file<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, SimError> {
+        let stimulus = Stimulus::load_file(path)?;
+        self.check_pins(stimulus.rules().iter().map(StimulusRule::pin))?;
+        let count = stimulus.rules().len();
+        self.set_stimulus(stimulus);
+        Ok(count)
+    }
+    
+    /// Add a stimulus rule, timed from cycle 0 like script rules
+    pub fn add_stimulus_rule(&mut self, rule: StimulusRule) {
+        self.stimulus.add_rule(rule);
+        self.stimulus.reschedule(self.stats.cycles_elapsed);
+        self.scheduler.invalidate();
+    }
+    
+    /// Remove the stimulus rule at `index`
+    pub fn remove_stimulus_rule(&mut self, index: usize) -> Option<StimulusRule> {
+        let rule = self.stimulus.remove_rule(index)?;
+        self.stimulus.reschedule(self.stats.cycles_elapsed);
+        self.scheduler.invalidate();
+        Some(rule)
+    }
+    
+    /// Remove all stimulus rules
+    pub fn clear_stimulus(&mut self) {
+        self.stimulus.clear();
+        self.scheduler.invalidate();
+    }
+    
+    /// Check that the device has every pin in `pins`
+    pub fn check_pins(&self, pins: impl IntoIterator<Item = u8>) -> Result<(), SimError> {
+        let device = self.device();
+        match pins.into_iter().find(|&pin| !device.model().has_pin(pin)) {
+            Some(pin) => Err(SimError::NoSuchPin { pin, device }),
+            None => Ok(()),
+        }
+    }
+    
+    /// Drive an external input pin, recording it if inputs are recorded
+    pub fn set_external_pin(&mut self, pin: u8, level: bool) {
+        if let Some(log) = &mut self.input_log {
+            log.push(self.stats.cycles_elapsed, InputKind::Pin { pin, level });
+        }
+        self.cpu.gpio_mut().set_external_pin(pin, level);
+    }
+    
+    /// Start recording external inputs (pin changes and resets)
+    pub fn start_input_recording(&mut self) {
+        self.input_log = Some(InputLog::new());
+    }
+    
+    /// Stop recording inputs, returning the recorded log
+    pub fn stop_input_recording(&mut self) -> Option<InputLog> {
+        self.input_log.take()
+    }
+    
+    /// Inputs recorded so far, if recording
+    pub fn input_recording(&self) -> Option<&InputLog> {
+        self.input_log.as_ref()
+    }
+    
+    /// Reset and replay recorded inputs at their recorded cycles
+    pub fn start_replay(&mut self, log: &InputLog) {
+        self.reset();
+        self.replay = Some(Replay::new(log));
+        self.scheduler.invalidate();
+    }
+    
+    /// Load a replay file and start replaying it, returning the event count
+    pub fn load_replay_file<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, SimError> {
+        let log = InputLog::load_file(path)?;
+        self.check_pins(log.events().iter().filter_map(|event| match event.kind {
+            InputKind::Pin { pin, .. } => Some(pin),
+            InputKind::Reset(_) => None,
+        }))?;
+        self.start_replay(&log);
+        Ok(log.len())
+    }
+    
+    /// Stop replaying inputs
+    pub fn stop_replay(&mut self) {
+        self.replay = None;
+        self.scheduler.invalidate();
+    }
+    
+    /// Inputs still to be replayed, if replaying
+    pub fn replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+    
+    /// Capture the complete machine state
+    pub fn save_state(&self) -> MachineState {
+        MachineState {
+            cpu: self.cpu.clone(),
+            stats: self.stats.clone(),
+            clock: self.clock,
+            config_word: self.config_word,
+            id_locations: self.id_locations,
+            symbols: self.symbols.clone(),
+        }
+    }
+    
+    /// Restore a machine state captured with `save_state`
+    /// 
+    /// The state's clock, configuration word, user IDs and program symbols
+    /// replace the session's. Execution history, trace, call stack, logic
+    /// capture and the power estimate describe the replaced timeline, so
+    /// they are cleared.
+    pub fn restore_state(&mut self, state: MachineState) {
+        self.cpu = state.cpu;
+        self.clock = state.clock;
+        self.apply_clock();
+        self.config_word = state.config_word;
+        self.id_locations = state.id_locations;
+        self.stats = state.stats;
+        self.symbols = state.symbols;
+        self.state = SimulatorState::Paused;
+        self.history.clear();
+        self.trace.clear();
+        self.call_stack.clear();
+        self.power.clear();
+        self.stimulus.reschedule(self.stats.cycles_elapsed);
+        self.logic.clear();
+        self.stopwatch.restart(self.cpu.get_pc(), self.stats.cycles_elapsed);
+        self.scheduler.invalidate();
+        self.watch_hit = None;
+        self.stopwatch_hit = None;
+    }
+    
+    /// Save the machine state to a file
+    pub fn save_state_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SimError> {
+        self.save_state().save_file(path).map_err(SimError::from)
+    }
+    
+    /// Restore the machine state from a file
+    pub fn restore_state_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SimError> {
+        let state = MachineState::load_file(path)?;
+        self.restore_state(state);
+        Ok(())
+    }
+    
+    /// Get the snapshot history used by `step_back`
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+    
+    /// Log CPU state at debug level (for debugging)
+    pub fn log_state(&self) {
+        let status = self.cpu.read_register(0x03);
+        tracing::debug!(
+            target: "pic_simulator::state",
+            "PC=0x{:04X} W=0x{:02X} STATUS=0x{:02X} [C={} DC={} Z={}] cycles={} instructions={}",
+            self.cpu.get_pc(),
+            self.cpu.read_w(),
+            status,
+            status & 0x01,
+            (status >> 1) & 0x01,
+            (status >> 2) & 0x01,
+            self.stats.cycles_elapsed,
+            self.stats.instructions_executed,
+        );
+    }
+
+    /// Load an Intel HEX, S-record, ELF, COFF or assembly source file,
+    /// returning the loader's diagnostics
+    ///
+    /// ELF and COFF files are recognised by their magic number, assembly
+    /// source by its `.asm` extension and S-record files by their extension
+    /// (.s19, .srec, ...) or, failing that, by their first record.
+    pub fn load_firmware_file<P: AsRef<Path>>(&mut self, path: P) -> Result<LoadReport, SimError> {
+        let path = path.as_ref();
+        let result = self.read_firmware_file(path);
+        match &result {
+            Ok(report) => tracing::info!(
+                target: "pic_simulator::load",
+                "loaded {} ({} words, {} warnings)", path.display(), report.words_loaded, report.warnings.len(),
+            ),
+            Err(e) => tracing::info!(target: "pic_simulator::load", "failed to load {}: {}", path.display(), e),
+        }
+        result
+    }
+    
+    fn read_firmware_file(&mut self, path: &Path) -> Result<LoadReport, SimError> {
+        let data = std::fs::read(path).map_err(SimError::io("Failed to read firmware file"))?;
+        if elf::is_elf(&data) {
+            return self.load_elf_bytes(&data);
+        }
+        if coff::is_coff(&data) {
+            let firmware = CoffLoader::load_file_for(path, self.cpu.memory().program_size())?;
+            return Ok(self.load_program_with_symbols(firmware.program, firmware.symbols));
+        }
+        
+        let content = String::from_utf8(data)
+            .map_err(|_| SimError::UnknownFormat(path.to_path_buf()))?;
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        if extension.eq_ignore_ascii_case("asm") {
+            let file = path.file_name().unwrap_or_default().to_string_lossy();
+            return self.load_asm_string(&content, &file);
+        }
+        let srec_extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SREC_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        
+        if srec_extension {
+            self.load_srec_string(&content)
+        } else {
+            self.load_firmware_string(&content)
+        }
+    }
+    
+    /// Load Intel HEX or S-record text, telling them apart by the first record
+    pub fn load_firmware_string(&mut self, content: &str) -> Result<LoadReport, SimError> {
+        if srec::is_srec(content) {
+            self.load_srec_string(content)
+        } else {
+            self.load_hex_string(content)
+        }
+    }
+    
+    /// Load a HEX program from string, returning the loader's diagnostics
+    pub fn load_hex_string(&mut self, content: &str) -> Result<LoadReport, SimError> {
+        let hex_program = HexLoader::load_from_string_with(content, &self.hex_parse_options())?;
+        Ok(self.load_hex_program(hex_program))
+    }
+    
+    /// Assemble and load source text, naming it `file` in the symbol table
+    ///
+    /// The assembler's labels and source lines replace any loaded symbols.
+    /// The assembler emits 14-bit code, so other cores are rejected.
+    pub fn load_asm_string(&mut self, source: &str, file: &str) -> Result<LoadReport, SimError> {
+        if self.device().model().core != Core::Midrange {
+            return Err(SimError::AsmUnsupported(self.device()));
+        }
+        let assembly = assembler::assemble(source, file).map_err(SimError::Assemble)?;
+        self.symbols = assembly.symbols;
+        Ok(self.load_hex_program(assembly.program))
+    }
+    
+    /// Load ELF file contents, returning the loader's diagnostics
+    ///
+    /// Code labels from the ELF symbol table replace any loaded symbols.
+    pub fn load_elf_bytes(&mut self, data: &[u8]) -> Result<LoadReport, SimError> {
+        let firmware = ElfLoader::load_from_bytes_for(data, self.cpu.memory().program_size())?;
+        Ok(self.load_program_with_symbols(firmware.program, firmware.symbols))
+    }
+    
+    /// Load COFF file contents, returning the loader's diagnostics
+    ///
+    /// Labels and source lines from the COFF debug info replace any loaded symbols.
+    pub fn load_coff_bytes(&mut self, data: &[u8]) -> Result<LoadReport, SimError> {
+        let firmware = CoffLoader::load_from_bytes_for(data, self.cpu.memory().program_size())?;
+        Ok(self.load_program_with_symbols(firmware.program, firmware.symbols))
+    }
+    
+    /// Load a program whose file also carried symbols
+    fn load_program_with_symbols(&mut self, program: HexProgram, symbols: SymbolTable) -> LoadReport {
+        if !symbols.is_empty() {
+            self.symbols = symbols;
+        }
+        self.load_hex_program(program)
+    }
+    
+    /// Load S-records from string, returning the loader's diagnostics
+    pub fn load_srec_string(&mut self, content: &str) -> Result<LoadReport, SimError> {
+        let program = SrecLoader::load_from_string_for(content, self.cpu.memory().program_size())?;
+        Ok(self.load_hex_program(program))
+    }
+    
+    /// Load a parsed HEX or S-record program
+    fn load_hex_program(&mut self, hex_program: HexProgram) -> LoadReport {
+        // Load program memory
+        self.load_program(&hex_program.program);
+        
+        // Load EEPROM if present
+        let eeprom_size = self.cpu.memory().eeprom_size();
+        for (i, &byte) in hex_program.eeprom.iter().enumerate().take(eeprom_size) {
+            self.cpu.memory_mut().write_eeprom(i as u8, byte);
+        }
+        
+        // Select the oscillator from the configuration word
+        if let Some(config) = hex_program.config {
+            self.set_clock(Clock::from_config_word(config));
+        }
+        self.config_word = hex_program.config;
+        self.id_locations = hex_program.id_locations;
+        
+        // Start at the file's start address, or the reset vector
+        let start = hex_program.start_address.unwrap_or(self.device().model().reset_vector());
+        self.cpu.set_pc(start);
+        hex_program.report
+    }
+    
+    /// Get the configuration word of the last loaded HEX file
+    pub fn config_word(&self) -> Option<u16> {
+        self.config_word
+    }
+    
+    /// Get the user ID locations of the last loaded HEX file
+    pub fn id_locations(&self) -> Option<[u16; 4]> {
+        self.id_locations
+    }
+    
+    /// Collect program memory, EEPROM, user IDs and the configuration word as a HEX program
+    ///
+    /// Program memory is cut after the last non-zero word, and the EEPROM is
+    /// included in full.
+    pub fn to_hex_program(&self) -> HexProgram {
+        let memory = self.cpu.memory();
+        let end = (0..memory.program_size() as u16)
+            .rposition(|addr| memory.read_program(addr) != 0)
+            .map_or(0, |last| last as u16 + 1);
+        HexProgram {
+            program: (0..end).map(|addr| memory.read_program(addr)).collect(),
+            eeprom: (0..memory.eeprom_size()).map(|i| memory.read_eeprom(i as u8)).collect(),
+            config: self.config_word,
+            id_locations: self.id_locations,
+            start_address: None,
+            report: LoadReport::default(),
+        }
+    }
+    
+    /// Compare program memory, EEPROM, config and IDs against `expected`
+    ///
+    /// Only the program words and EEPROM bytes `expected` covers are
+    /// checked, like a device programmer's verify pass.
+    pub fn verify(&self, expected: &HexProgram) -> Vec<Difference> {
+        let memory = self.cpu.memory();
+        let words = expected.program.len().min(memory.program_size());
+        let bytes = expected.eeprom.len().min(memory.eeprom_size());
+        let actual = HexProgram {
+            program: (0..words as u16).map(|addr| memory.read_program(addr)).collect(),
+            eeprom: (0..bytes).map(|i| memory.read_eeprom(i as u8)).collect(),
+            config: self.config_word,
+            id_locations: self.id_locations,
+            start_address: None,
+            report: LoadReport::default(),
+        };
+        expected.diff(&actual)
+    }
+    
+    /// Compare the simulator against a HEX file, see `verify`
+    pub fn verify_hex_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Difference>, SimError> {
+        let expected = HexLoader::load_file_with(path, &self.hex_parse_options())?;
+        Ok(self.verify(&expected))
+    }
+    
+    /// Write program memory, EEPROM and the configuration word as a HEX file
+    pub fn export_hex<P: AsRef<Path>>(&self, path: P) -> Result<(), SimError> {
+        HexLoader::save_file(&self.to_hex_program(), path)?;
+        Ok(())
+    }
+    
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ClockSource;
+    
+    #[test]
+    fn test_simulator_creation() {
+        let sim = Simulator::new();
+        assert_eq!(sim.state(), SimulatorState::Paused);
+    }
+    
+    #[test]
+    fn test_load_and_run_simple_program() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        // Program: MOVLW 0x55, MOVWF 0x20, NOP
+        let program = vec![
+            0x3055,  // MOVLW 0x55
+            0x00A0,  // MOVWF 0x20
+            0x0000,  // NOP
+        ];
+        
+        sim.load_program(&program);
+        
+        // Run 3 instructions
+        sim.run_n_instructions(3).unwrap();
+        
+        assert_eq!(sim.cpu().read_w(), 0x55);
+        assert_eq!(sim.cpu().read_register(0x20), 0x55);
+        assert_eq!(sim.stats().instructions_executed, 3);
+    }
+    
+    #[test]
+    fn test_breakpoint() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        // Program with loop
+        let program = vec![
+            0x3055,  // 0x000: MOVLW 0x55
+            0x00A0,  // 0x001: MOVWF 0x20
+            0x2800,  // 0x002: GOTO 0x000 (infinite loop)
+        ];
+        
+        sim.load_program(&program);
+        sim.add_breakpoint(0x002);
+        
+        // Run until breakpoint
+        sim.run().unwrap();
+        
+        assert_eq!(sim.cpu().get_pc(), 0x002);
+        assert_eq!(sim.state(), SimulatorState::Paused);
+        
+        assert_eq!(sim.breakpoints()[0].hits, 1);
+        
+        // A disabled or false conditional breakpoint is passed over
+        sim.set_breakpoint_enabled(0x002, false);
+        assert!(!sim.check_breakpoint(0x002));
+        sim.set_breakpoint_enabled(0x002, true);
+        sim.set_breakpoint_condition(0x002, Some(Expr::parse("[0x20] == 0x56", sim.symbols()).unwrap()));
+        assert!(!sim.check_breakpoint(0x002));
+        sim.set_breakpoint_condition(0x002, Some(Expr::parse("[0x20] == 0x55", sim.symbols()).unwrap()));
+        assert!(sim.check_breakpoint(0x002));
+        assert_eq!(sim.breakpoints()[0].to_string(), "0x0002 if [0x20] == 0x55, 2 hits");
+        
+        assert!(!sim.toggle_breakpoint(0x002));
+        assert!(sim.toggle_breakpoint(0x001));
+        assert!(sim.has_breakpoint(0x001) && !sim.has_breakpoint(0x002));
     }
     
     #[test]
@@ -375,4 +1697,955 @@ mod tests {
         assert_eq!(sim.cpu().read_w(), 0x42);
         assert_eq!(sim.stats().instructions_executed, 1);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_step_back() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        // Counter loop: INCF 0x20, F; GOTO 0x000
+        sim.load_program(&[0x0AA0, 0x2800]);
+        sim.run_n_instructions(2500).unwrap();
+        
+        let value = sim.cpu().read_register(0x20);
+        let cycles = sim.stats().cycles_elapsed;
+        
+        sim.run_n_instructions(10).unwrap();
+        assert_eq!(sim.step_back(10).unwrap(), 10);
+        
+        assert_eq!(sim.stats().instructions_executed, 2500);
+        assert_eq!(sim.stats().cycles_elapsed, cycles);
+        assert_eq!(sim.cpu().read_register(0x20), value);
+        assert_eq!(sim.trace().last().unwrap().cycle, cycles - 2);
+    }
+    
+    #[test]
+    fn test_step_back_rewinds_components() {
+        use crate::component::Led;
+        
+        let mut sim = Simulator::new();
+        sim.reset();
+        let led = sim.attach_component(Box::new(Led::new("D1", 0)));
+        
+        // Toggle GP0 as an output: BSF STATUS, RP0; BCF TRISIO, 0;
+        // BCF STATUS, RP0; MOVLW 0x01; XORWF GPIO, F; GOTO 0x004
+        sim.load_program(&[0x1683, 0x1005, 0x1283, 0x3001, 0x0685, 0x2804]);
+        sim.run_n_instructions(2500).unwrap();
+        let state = format!("{:?}", sim.component_as::<Led>(led).unwrap());
+        
+        sim.run_n_instructions(10).unwrap();
+        sim.step_back(10).unwrap();
+        assert_eq!(format!("{:?}", sim.component_as::<Led>(led).unwrap()), state);
+    }
+    
+    #[test]
+    fn test_call_stack_tracking() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        let program = vec![
+            0x2003,  // 0x000: CALL 0x003
+            0x2800,  // 0x001: GOTO 0x000
+            0x0000,  // 0x002: NOP
+            0x2005,  // 0x003: CALL 0x005
+            0x0008,  // 0x004: RETURN
+            0x3400,  // 0x005: RETLW 0x00
+        ];
+        sim.load_program(&program);
+        
+        sim.run_n_instructions(2).unwrap();
+        let frames = sim.call_stack().frames();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].caller_pc, 0x000);
+        assert_eq!(frames[1].callee, 0x005);
+        
+        sim.run_n_instructions(2).unwrap();
+        assert_eq!(sim.call_stack().depth(), 0);
+    }
+    
+    #[test]
+    fn test_step_back_limited_by_history() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(&[0x0000, 0x2800]);
+        
+        sim.run_n_instructions(5).unwrap();
+        assert_eq!(sim.step_back(100).unwrap(), 5);
+        assert_eq!(sim.cpu().get_pc(), 0);
+    }
+    
+    #[test]
+    fn test_profiler() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        let program = vec![
+            0x2003,  // 0x000: CALL 0x003
+            0x2800,  // 0x001: GOTO 0x000
+            0x0000,  // 0x002: NOP
+            0x0000,  // 0x003: NOP
+            0x0008,  // 0x004: RETURN
+        ];
+        sim.load_program(&program);
+        sim.profiler_mut().set_enabled(true);
+        
+        // Two passes through the loop
+        sim.run_n_instructions(8).unwrap();
+        
+        let profiler = sim.profiler();
+        assert_eq!(profiler.total_cycles(), sim.stats().cycles_elapsed);
+        assert_eq!(profiler.address(0x003).hits, 2);
+        
+        let routine = profiler.subroutines().into_iter()
+            .find(|r| r.entry == Some(0x003))
+            .unwrap();
+        assert_eq!(routine.calls, 2);
+        assert_eq!(routine.self_cycles, 6);
+        assert_eq!(routine.total_cycles, 10);
+        
+        // Replaying after a step back does not count twice
+        sim.step_back(3).unwrap();
+        assert_eq!(sim.profiler().total_cycles(), 14);
+    }
+    
+    #[test]
+    fn test_vcd_capture() {
+        let path = std::env::temp_dir().join(format!("pic_sim_test_{}.vcd", std::process::id()));
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        // Make GP0 an output and toggle it
+        let program = vec![
+            0x1683,  // 0x000: BSF STATUS, RP0
+            0x3000,  // 0x001: MOVLW 0x00
+            0x0085,  // 0x002: MOVWF TRISIO
+            0x1283,  // 0x003: BCF STATUS, RP0
+            0x3001,  // 0x004: MOVLW 0x01
+            0x0085,  // 0x005: MOVWF GPIO
+        ];
+        sim.load_program(&program);
+        
+        sim.start_vcd(&path).unwrap();
+        assert!(sim.is_recording_vcd());
+        sim.run_n_instructions(6).unwrap();
+        sim.stop_vcd().unwrap();
+        
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(text.contains("$enddefinitions $end"));
+        assert!(text.contains("#6\n1!\n"));
+    }
+    
+    #[test]
+    fn test_stimulus() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        // Loop copying GPIO into 0x20: MOVF GPIO, W; MOVWF 0x20; GOTO 0x000
+        sim.load_program(&[0x0805, 0x00A0, 0x2800]);
+        sim.set_stimulus(Stimulus::parse("GP2: low at cycle 100 for 50 cycles").unwrap());
+        
+        sim.run_n_cycles(120).unwrap();
+        assert_eq!(sim.cpu().read_register(0x20) & 0x04, 0);
+        
+        sim.run_n_cycles(60).unwrap();
+        assert_eq!(sim.cpu().read_register(0x20) & 0x04, 0x04);
+        
+        // Rewinding re-applies the pulse
+        sim.step_back(30).unwrap();
+        assert!(!sim.cpu().gpio().get_external_pin(2));
+    }
+    
+    #[test]
+    fn test_logic_analyzer() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        let program = vec![
+            0x1683,  // 0x000: BSF STATUS, RP0
+            0x3000,  // 0x001: MOVLW 0x00
+            0x0085,  // 0x002: MOVWF TRISIO
+            0x1283,  // 0x003: BCF STATUS, RP0
+            0x3001,  // 0x004: MOVLW 0x01
+            0x0685,  // 0x005: XORWF GPIO, F (toggle GP0)
+            0x2804,  // 0x006: GOTO 0x004
+        ];
+        sim.load_program(&program);
+        sim.run_n_cycles(100).unwrap();
+        
+        // Each toggle takes 4 cycles
+        let logic = sim.logic_analyzer();
+        assert_eq!(logic.last_pulse_width(0, true), Some(4));
+        assert_eq!(logic.average_period(0), Some(8.0));
+        assert_eq!(logic.duty_cycle(0), Some(0.5));
+    }
+    
+    #[test]
+    fn test_watchpoints() {
+        use crate::cpu::registers;
+        use crate::watchpoint::WatchKind;
+        
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        let program = vec![
+            0x1683,  // 0x000: BSF STATUS, RP0
+            0x3000,  // 0x001: MOVLW 0x00
+            0x0085,  // 0x002: MOVWF TRISIO
+            0x1283,  // 0x003: BCF STATUS, RP0
+            0x3001,  // 0x004: MOVLW 0x01
+            0x0685,  // 0x005: XORWF GPIO, F (toggle GP0)
+            0x2804,  // 0x006: GOTO 0x004
+        ];
+        sim.load_program(&program);
+        sim.add_watchpoint(Watchpoint::new(registers::TRISIO, WatchKind::Write));
+        sim.add_watchpoint(Watchpoint::new(registers::GPIO, WatchKind::Change));
+        
+        sim.run().unwrap();
+        let hit = *sim.watch_hit().unwrap();
+        assert_eq!(sim.state(), SimulatorState::Paused);
+        assert_eq!(hit.pc, 0x002);
+        assert_eq!(hit.watchpoint.address, registers::TRISIO);
+        assert_eq!((hit.access.old, hit.access.new), (0x3F, 0x00));
+        
+        sim.run().unwrap();
+        let hit = *sim.watch_hit().unwrap();
+        assert_eq!(hit.pc, 0x005);
+        assert_eq!(hit.access.new, hit.access.old ^ 0x01);
+        
+        sim.step().unwrap();
+        assert!(sim.watch_hit().is_none());
+        
+        sim.remove_watchpoint(registers::GPIO);
+        sim.run_n_instructions(20).unwrap();
+        assert!(sim.watch_hit().is_none());
+    }
+    
+    #[test]
+    fn test_step_back_keeps_watchpoint_hits() {
+        use crate::watchpoint::WatchKind;
+        
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        // Counter loop: INCF 0x20, F; GOTO 0x000
+        sim.load_program(&[0x0AA0, 0x2800]);
+        sim.add_watchpoint(Watchpoint::new(0x20, WatchKind::Write));
+        for _ in 0..20 {
+            sim.step().unwrap();
+        }
+        assert_eq!(sim.watchpoints()[0].hits, 10);
+        
+        sim.step_back(4).unwrap();
+        assert_eq!(sim.watchpoints()[0].hits, 10);
+        assert!(sim.watch_hit().is_none());
+    }
+    
+    #[test]
+    fn test_run_until() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(&[
+            0x0000,  // 0x000: NOP
+            0x0AA0,  // 0x001: INCF 0x20, F
+            0x2801,  // 0x002: GOTO 0x001
+        ]);
+        
+        let outcome = sim.run_until(|sim| sim.cpu().read_register(0x20) == 3, 1_000);
+        assert!(matches!(outcome, RunOutcome::PredicateMet));
+        assert_eq!(sim.cpu().get_pc(), 0x002);
+        assert_eq!(sim.state(), SimulatorState::Paused);
+        
+        assert!(matches!(sim.run_until(|_| false, 30), RunOutcome::CycleLimit));
+        assert!(sim.stats().cycles_elapsed >= 4 + 30);
+        
+        sim.add_breakpoint(0x001);
+        assert!(matches!(sim.run_until(|_| false, 1_000), RunOutcome::Breakpoint(0x001)));
+        // Resuming steps over the breakpoint it stopped at
+        assert!(matches!(sim.run_until(|_| false, 1_000), RunOutcome::Breakpoint(0x001)));
+        
+        sim.halt();
+        assert!(matches!(sim.run_until(|_| false, 1_000), RunOutcome::Error(SimError::Halted)));
+    }
+    
+    #[test]
+    fn test_event_observers() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(&[
+            0x1683,  // BSF STATUS, RP0
+            0x0185,  // CLRF TRISIO
+            0x1283,  // BCF STATUS, RP0
+            0x3010,  // MOVLW 0x10
+            0x0085,  // MOVWF GPIO
+            0x0063,  // SLEEP
+        ]);
+        
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&events);
+        let id = sim.subscribe(move |event| log.borrow_mut().push(*event));
+        let channel = sim.event_channel();
+        sim.run_n_instructions(6).unwrap();
+        
+        let seen = events.borrow().clone();
+        let executed = seen.iter()
+            .filter(|e| matches!(e, SimEvent::InstructionExecuted { .. }))
+            .count();
+        assert_eq!(executed, 6);
+        assert!(seen.contains(&SimEvent::MemoryWritten { addr: 0x85, old: 0x3F, new: 0x00 }));
+        assert!(seen.contains(&SimEvent::GpioChanged { old: 0x3F, new: 0x08 }));
+        assert!(seen.contains(&SimEvent::GpioChanged { old: 0x08, new: 0x18 }));
+        assert_eq!(seen.last(), Some(&SimEvent::InstructionExecuted {
+            cycle: 5, pc: 0x005, word: 0x0063, cycles: 1,
+            w: 0x10, status: sim.cpu().read_register(crate::cpu::registers::STATUS),
+        }));
+        assert!(seen.contains(&SimEvent::SleepEntered { pc: 0x005 }));
+        assert_eq!(channel.try_iter().count(), seen.len());
+        assert_eq!(sim.trace().iter().count(), 6);
+        
+        // Unsubscribed observers and replayed steps are not notified
+        assert!(sim.unsubscribe(id));
+        sim.step_back(2).unwrap();
+        assert_eq!(channel.try_iter().count(), 0);
+        sim.step().unwrap();
+        assert_eq!(channel.try_iter().collect::<Vec<_>>(), vec![
+            SimEvent::MemoryWritten { addr: 0x05, old: 0x08, new: 0x10 },
+            SimEvent::InstructionExecuted {
+                cycle: 4, pc: 0x004, word: 0x0085, cycles: 1,
+                w: 0x10, status: sim.cpu().read_register(crate::cpu::registers::STATUS),
+            },
+            SimEvent::GpioChanged { old: 0x08, new: 0x18 },
+        ]);
+        assert_eq!(*events.borrow(), seen);
+    }
+    
+    #[test]
+    fn test_interrupt_timing() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(&[
+            0x140B,  // 0x000: BSF INTCON, GPIF
+            0x3089,  // 0x001: MOVLW 0x89
+            0x008B,  // 0x002: MOVWF INTCON (GIE, GPIE)
+            0x2803,  // 0x003: GOTO 0x003
+            0x100B,  // 0x004: BCF INTCON, GPIF
+            0x0000,  // 0x005: NOP
+            0x0009,  // 0x006: RETFIE
+        ]);
+        sim.run_n_instructions(6).unwrap();
+        
+        // Flag seen at cycle 1, vector taken at cycle 3, RETFIE done at cycle 9
+        let gpio = sim.interrupt_timing().source(InterruptSource::GpioChange);
+        assert_eq!((gpio.latency.count, gpio.latency.min, gpio.latency.max), (1, 2, 2));
+        assert_eq!((gpio.isr.count, gpio.isr.min), (1, 6));
+        assert_eq!(sim.cpu().interrupts().stats(InterruptSource::GpioChange).last_cycle, Some(3));
+        
+        // Stepping back into the ISR forgets its completion
+        sim.step_back(1).unwrap();
+        let gpio = sim.interrupt_timing().source(InterruptSource::GpioChange);
+        assert_eq!((gpio.latency.count, gpio.isr.count), (1, 0));
+        assert_eq!(sim.interrupt_timing().active(), Some(InterruptSource::GpioChange));
+    }
+    
+    #[test]
+    fn test_clock() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(&[0x0000, 0x2800]);  // NOP; GOTO 0
+        sim.run_n_cycles(3000).unwrap();
+        assert_eq!(sim.elapsed_time(), Duration::from_millis(3));
+        assert_eq!(sim.cpu().wdt().get_timeout_period(), 18_000);
+        
+        // HS oscillator selected in the configuration word
+        sim.set_clock(Clock::from_config_word(0x3FF2));
+        assert_eq!(sim.clock().source(), ClockSource::Hs);
+        assert_eq!(sim.fosc(), 20_000_000);
+        assert_eq!(sim.cpu().wdt().get_timeout_period(), 90_000);
+        
+        sim.set_fosc(8_000_000);
+        assert_eq!(sim.elapsed_time(), Duration::from_micros(1500));
+        sim.reset();
+        assert_eq!(sim.cpu().wdt().get_timeout_period(), 36_000);
+    }
+    
+    #[test]
+    fn test_run_for() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(&[0x0000, 0x2800]);  // NOP; GOTO 0
+        
+        assert_eq!(sim.run_for(Duration::from_millis(10)).unwrap(), Duration::from_millis(10));
+        assert_eq!(sim.stats().cycles_elapsed, 10_000);
+        
+        sim.set_fosc(20_000_000);
+        assert_eq!(sim.run_for_micros(100).unwrap(), Duration::from_micros(100));
+        assert_eq!(sim.stats().cycles_elapsed, 10_500);
+        
+        // A breakpoint cuts the run short
+        sim.add_breakpoint(0x001);
+        let elapsed = sim.run_for_micros(1_000).unwrap();
+        assert_eq!(elapsed, Duration::from_nanos(200));
+        assert_eq!(sim.cpu().get_pc(), 0x001);
+    }
+    
+    #[test]
+    fn test_halt_policy() {
+        fn halt_reason(sim: &mut Simulator, program: &[u16]) -> Option<HaltReason> {
+            sim.reset();
+            sim.load_program(program);
+            match sim.run_until(|_| false, 100_000) {
+                RunOutcome::Error(SimError::HaltCondition { reason, .. }) => Some(reason),
+                _ => None,
+            }
+        }
+        
+        let mut sim = Simulator::new();
+        // Default policy: the stack wraps and unknown opcodes stop execution
+        assert!(halt_reason(&mut sim, &[0x2000]).is_none());
+        sim.reset();
+        sim.load_program(&[0x0001]);
+        assert!(matches!(sim.step(), Err(SimError::InvalidOpcode { pc: 0, .. })));
+        
+        sim.set_halt_policy(HaltPolicy::strict());
+        // CALL 0x000 recursing past 8 levels
+        assert_eq!(halt_reason(&mut sim, &[0x2000]), Some(HaltReason::StackOverflow));
+        assert_eq!(sim.state(), SimulatorState::Halted);
+        assert_eq!(sim.stats().instructions_executed, 9);
+        assert!(matches!(sim.step(), Err(SimError::Halted)));
+        // RETURN with nothing on the stack
+        assert_eq!(halt_reason(&mut sim, &[0x0008]), Some(HaltReason::StackUnderflow));
+        // MOVWF 0x07 (unimplemented in bank 0)
+        assert_eq!(halt_reason(&mut sim, &[0x0087]), Some(HaltReason::UnimplementedWrite { address: 0x07 }));
+        // GOTO 0x000 without CLRWDT
+        assert_eq!(halt_reason(&mut sim, &[0x2800]), Some(HaltReason::WdtTimeout));
+        
+        // SLEEP with the WDT off and no interrupts enabled
+        sim.reset();
+        sim.cpu_mut().wdt_mut().set_enabled(false);
+        sim.load_program(&[0x0063]);
+        let err = sim.step().unwrap_err();
+        assert_eq!(err.to_string(), "Halted at PC=0x0000: SLEEP with no wake source");
+        
+        // Unknown opcodes run as NOP when not halting on them
+        let mut policy = HaltPolicy::default();
+        policy.set("opcode", false).unwrap();
+        sim.set_halt_policy(policy);
+        sim.reset();
+        sim.load_program(&[0x0001, 0x3042]);
+        sim.run_n_instructions(2).unwrap();
+        assert_eq!(sim.cpu().read_w(), 0x42);
+    }
+    
+    #[test]
+    fn test_skip_sleep() {
+        let program = [
+            0x0063,  // 0x000: SLEEP
+            0x0AA0,  // 0x001: INCF 0x20, F
+            0x2800,  // 0x002: GOTO 0x000
+        ];
+        let mut stepped = Simulator::new();
+        stepped.reset();
+        stepped.load_program(&program);
+        let mut skipped = Simulator::new();
+        skipped.reset();
+        skipped.load_program(&program);
+        skipped.set_stimulus(Stimulus::parse("GP2: high at cycle 5000").unwrap());
+        stepped.set_stimulus(Stimulus::parse("GP2: high at cycle 5000").unwrap());
+        
+        // One call skips to just before the WDT wake-up
+        skipped.step().unwrap();
+        assert_eq!(skipped.skip_sleep(1_000), 1_000);
+        assert_eq!(skipped.skip_sleep(u64::MAX), 5_000 - 1_001);
+        assert_eq!(skipped.skip_sleep(u64::MAX), 0);
+        skipped.step().unwrap();
+        assert!(skipped.cpu().gpio().get_external_pin(2));
+        
+        // Fast-forwarded execution matches cycle-by-cycle stepping
+        while stepped.stats().cycles_elapsed < 40_000 {
+            stepped.step().unwrap();
+        }
+        skipped.run_n_cycles(40_000 - skipped.stats().cycles_elapsed).unwrap();
+        assert_eq!(skipped.stats().cycles_elapsed, stepped.stats().cycles_elapsed);
+        assert_eq!(skipped.cpu().read_register(0x20), 2);
+        assert_eq!(skipped.save_state().to_record(), stepped.save_state().to_record());
+    }
+    
+    #[test]
+    fn test_bulk_timer_advance() {
+        let program = [
+            0x1683,  // 0x000: BSF STATUS, RP0
+            0x3001,  // 0x001: MOVLW 0x01
+            0x0081,  // 0x002: MOVWF OPTION_REG (TMR0 prescaler 1:4)
+            0x1283,  // 0x003: BCF STATUS, RP0
+            0x30F0,  // 0x004: MOVLW 0xF0
+            0x008E,  // 0x005: MOVWF TMR1L
+            0x30FF,  // 0x006: MOVLW 0xFF
+            0x008F,  // 0x007: MOVWF TMR1H
+            0x3001,  // 0x008: MOVLW 0x01
+            0x0090,  // 0x009: MOVWF T1CON
+            0x280A,  // 0x00A: GOTO 0x00A
+        ];
+        
+        // A VCD capture forces cycle-by-cycle ticking
+        let path = std::env::temp_dir().join(format!("pic_sim_bulk_{}.vcd", std::process::id()));
+        let mut ticked = Simulator::new();
+        ticked.reset();
+        ticked.load_program(&program);
+        ticked.start_vcd(&path).unwrap();
+        let mut bulk = Simulator::new();
+        bulk.reset();
+        bulk.load_program(&program);
+        
+        for _ in 0..2_000 {
+            ticked.step().unwrap();
+            bulk.step().unwrap();
+            assert_eq!(bulk.save_state().to_record(), ticked.save_state().to_record());
+        }
+        ticked.stop_vcd().unwrap();
+        std::fs::remove_file(&path).ok();
+        
+        assert_ne!(bulk.cpu().read_register(crate::cpu::registers::PIR1) & 0x01, 0);
+        assert_ne!(bulk.cpu().read_register(crate::cpu::registers::INTCON) & 0x04, 0);
+    }
+    
+    #[test]
+    fn test_power_estimate() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        // SLEEP; GOTO 0x000 - sleeps until each WDT timeout
+        sim.load_program(&[0x0063, 0x2800]);
+        sim.run_n_cycles(100_000).unwrap();
+        
+        let power = sim.power();
+        assert_eq!(power.run_cycles() + power.sleep_cycles(), sim.stats().cycles_elapsed);
+        assert!(power.sleep_cycles() > power.run_cycles());
+        let model = power.model();
+        assert!(power.average_ua() > model.sleep_ua + model.wdt_ua);
+        assert!(power.average_ua() < model.current_ua(PowerMode::default(), 4_000_000));
+        
+        sim.reset();
+        assert_eq!(sim.power().charge_uc(), 0.0);
+    }
+    
+    #[test]
+    fn test_components() {
+        use crate::component::Led;
+        
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(&[
+            0x1683,  // BSF STATUS, RP0
+            0x1005,  // BCF TRISIO, 0
+            0x1283,  // BCF STATUS, RP0
+            0x1405,  // BSF GPIO, 0
+            0x2804,  // GOTO 0x004
+        ]);
+        let id = sim.attach_component(Box::new(Led::new("LED1", 0)));
+        
+        sim.run_n_instructions(3).unwrap();
+        assert!(!sim.component_as::<Led>(id).unwrap().is_on());
+        sim.step().unwrap();
+        assert!(sim.component_as::<Led>(id).unwrap().is_on());
+        
+        sim.reset();
+        assert!(!sim.component_as::<Led>(id).unwrap().is_on());
+        assert!(sim.detach_component(id).is_some());
+        assert!(sim.components().is_empty());
+    }
+    
+    #[test]
+    fn test_input_replay() {
+        // Count GP2 low samples into 0x20
+        let program = [
+            0x1905,  // 0x000: BTFSC GPIO, 2
+            0x2803,  // 0x001: GOTO 0x003
+            0x0AA0,  // 0x002: INCF 0x20, F
+            0x2800,  // 0x003: GOTO 0x000
+        ];
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(&program);
+        
+        sim.start_input_recording();
+        sim.run_n_cycles(100).unwrap();
+        sim.set_external_pin(2, false);
+        sim.run_n_cycles(50).unwrap();
+        sim.reset();
+        sim.run_n_cycles(30).unwrap();
+        sim.set_external_pin(2, true);
+        sim.run_n_cycles(20).unwrap();
+        let log = sim.stop_input_recording().unwrap();
+        assert_eq!(log.len(), 3);
+        let expected = sim.save_state().to_record();
+        let end = sim.stats().cycles_elapsed;
+        
+        sim.start_replay(&log);
+        sim.run_until(|s| s.replay().unwrap().is_finished(), 1_000);
+        while sim.stats().cycles_elapsed < end {
+            sim.step().unwrap();
+        }
+        assert_eq!(sim.save_state().to_record(), expected);
+    }
+    
+    #[test]
+    fn test_step_back_rewinds_replay() {
+        // Count GP2 low samples into 0x20
+        let program = [
+            0x1905,  // 0x000: BTFSC GPIO, 2
+            0x2803,  // 0x001: GOTO 0x003
+            0x0AA0,  // 0x002: INCF 0x20, F
+            0x2800,  // 0x003: GOTO 0x000
+        ];
+        let mut log = InputLog::new();
+        log.push(3000, InputKind::Pin { pin: 2, level: false });
+        let mut sim = Simulator::new();
+        sim.load_program(&program);
+        sim.start_replay(&log);
+        sim.start_input_recording();
+        
+        sim.run_n_instructions(4000).unwrap();
+        assert!(sim.replay().unwrap().is_finished());
+        assert_eq!(sim.input_recording().unwrap().len(), 1);
+        let expected = sim.save_state().to_record();
+        
+        // Back before the pin change: it is pending again and no longer recorded
+        sim.step_back(3000).unwrap();
+        assert_eq!(sim.replay().unwrap().remaining(), 1);
+        assert!(sim.input_recording().unwrap().is_empty());
+        assert_eq!(sim.cpu().read_register(0x20), 0);
+        
+        sim.run_n_instructions(3000).unwrap();
+        assert_eq!(sim.input_recording().unwrap().len(), 1);
+        assert_eq!(sim.save_state().to_record(), expected);
+    }
+    
+    #[test]
+    fn test_stopwatch() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        let program = vec![
+            0x3005,  // 0x000: MOVLW 0x05
+            0x00A0,  // 0x001: MOVWF 0x20
+            0x0BA0,  // 0x002: DECFSZ 0x20, F  (delay loop)
+            0x2802,  // 0x003: GOTO 0x002
+            0x0000,  // 0x004: NOP
+            0x2804,  // 0x005: GOTO 0x004
+        ];
+        sim.load_program(&program);
+        sim.arm_stopwatch(0x002, 0x004);
+        
+        sim.run().unwrap();
+        let reading = *sim.stopwatch_hit().unwrap();
+        assert_eq!(sim.cpu().get_pc(), 0x004);
+        // 4 x (DECFSZ + GOTO) + final skipping DECFSZ
+        assert_eq!(reading.cycles, 4 * 3 + 2);
+        
+        sim.set_fosc(8_000_000);
+        assert_eq!(reading.micros(sim.clock()), 7.0);
+        
+        sim.reset();
+        sim.load_program(&program);
+        sim.disarm_stopwatch();
+        sim.run_n_instructions(20).unwrap();
+        assert!(sim.stopwatch_hit().is_none());
+        assert_eq!(sim.stopwatch().last_reading(), Some(&reading));
+    }
+    
+    #[test]
+    fn test_save_and_restore_state() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        let program = vec![
+            0x0AA0,  // 0x000: INCF 0x20, F
+            0x2800,  // 0x001: GOTO 0x000
+        ];
+        sim.load_program(&program);
+        sim.run_n_instructions(10).unwrap();
+        let saved = sim.save_state();
+        
+        sim.run_n_instructions(10).unwrap();
+        assert_eq!(sim.cpu().read_register(0x20), 10);
+        
+        sim.restore_state(saved);
+        assert_eq!(sim.cpu().read_register(0x20), 5);
+        assert_eq!(sim.stats().instructions_executed, 10);
+        
+        let path = std::env::temp_dir().join(format!("pic_state_test_{}.txt", std::process::id()));
+        sim.save_state_file(&path).unwrap();
+        sim.reset();
+        sim.restore_state_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(sim.cpu().read_register(0x20), 5);
+        assert_eq!(sim.cpu().get_pc(), 0x000);
+        assert_eq!(sim.stats().cycles_elapsed, 15);
+    }
+    
+    #[test]
+    fn test_state_keeps_clock_and_config() {
+        let hex = HexLoader::save_to_string(&HexProgram {
+            program: vec![0x2800],  // GOTO 0
+            eeprom: Vec::new(),
+            config: Some(0x3FC2),   // HS oscillator
+            id_locations: Some([1, 2, 3, 4]),
+            start_address: None,
+            report: LoadReport::default(),
+        });
+        let mut sim = Simulator::new();
+        sim.load_hex_string(&hex).unwrap();
+        let mut clock = *sim.clock();
+        clock.set_fosc_hz(8_000_000);
+        sim.set_clock(clock);
+        
+        let path = std::env::temp_dir().join(format!("pic_state_clock_test_{}.txt", std::process::id()));
+        sim.save_state_file(&path).unwrap();
+        let mut restored = Simulator::new();
+        restored.restore_state_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(restored.clock().source(), ClockSource::Hs);
+        assert_eq!(restored.fosc(), 8_000_000);
+        assert_eq!(restored.config_word(), Some(0x3FC2));
+        assert_eq!(restored.id_locations(), Some([1, 2, 3, 4]));
+        assert_eq!(HexLoader::save_to_string(&restored.to_hex_program()), HexLoader::save_to_string(&sim.to_hex_program()));
+        assert_eq!(restored.cpu().wdt().get_timeout_period(), sim.cpu().wdt().get_timeout_period());
+    }
+    
+    #[test]
+    fn test_export_hex() {
+        let mut sim = Simulator::new();
+        sim.load_hex_string(concat!(
+            ":060000005530200002282B\n",
+            ":044200004200420036\n",
+            ":084000000100020003000400AE\n",
+            ":02400E00843FED\n",
+            ":00000001FF\n",
+        )).unwrap();
+        assert_eq!(sim.config_word(), Some(0x3F84));
+        assert_eq!(sim.id_locations(), Some([1, 2, 3, 4]));
+        
+        let path = std::env::temp_dir().join(format!("pic_export_test_{}.hex", std::process::id()));
+        sim.export_hex(&path).unwrap();
+        let exported = HexLoader::load_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(exported.program, vec![0x3055, 0x0020, 0x2802]);
+        assert_eq!(exported.eeprom.len(), 128);
+        assert_eq!(&exported.eeprom[..3], &[0x42, 0x42, 0x00]);
+        assert_eq!(exported.config, Some(0x3F84));
+        assert_eq!(exported.id_locations, Some([1, 2, 3, 4]));
+        assert!(sim.verify(&exported).is_empty());
+        
+        sim.cpu_mut().memory_mut().write_program(1, 0x00A1);
+        assert_eq!(sim.verify(&exported), vec![Difference::Program { address: 1, expected: 0x0020, actual: 0x00A1 }]);
+    }
+    
+    #[test]
+    fn test_load_firmware_srec() {
+        let mut sim = Simulator::new();
+        let srec = "S10700005530200053\nS10500040228CC\nS105400E8431F7\nS9030000FC\n";
+        let path = std::env::temp_dir().join(format!("pic_srec_test_{}.s19", std::process::id()));
+        std::fs::write(&path, srec).unwrap();
+        let report = sim.load_firmware_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(report.words_loaded, 3);
+        assert_eq!(sim.cpu().memory().read_program(2), 0x2802);
+        assert_eq!(sim.config_word(), Some(0x3184));
+        
+        let report = sim.load_firmware_string(":020000000528D1\n:00000001FF\n").unwrap();
+        assert_eq!(report.words_loaded, 1);
+        assert_eq!(sim.cpu().memory().read_program(0), 0x2805);
+    }
+    
+    #[test]
+    fn test_coverage() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        let program = vec![
+            0x3002,  // 0x000: MOVLW 0x02
+            0x00A0,  // 0x001: MOVWF 0x20
+            0x0BA0,  // 0x002: DECFSZ 0x20, F
+            0x2802,  // 0x003: GOTO 0x002
+            0x2804,  // 0x004: GOTO 0x004
+            0x0000,  // 0x005: NOP (never reached)
+        ];
+        sim.load_program(&program);
+        sim.run_n_instructions(7).unwrap();
+        
+        let coverage = sim.coverage();
+        assert_eq!(coverage.executed_words(), 5);
+        assert_eq!(coverage.uncovered_ranges(), vec![(5, 5)]);
+        
+        let branch = coverage.branches()[&0x002];
+        assert_eq!(branch.taken, 1);
+        assert_eq!(branch.not_taken, 1);
+    }
+    
+    #[test]
+    fn test_step_back_keeps_coverage() {
+        let mut sim = Simulator::new();
+        sim.reset();
+        
+        // BTFSS 0x20, 0 skips on every other pass of the loop
+        sim.load_program(&[
+            0x0AA0,  // 0x000: INCF 0x20, F
+            0x1C20,  // 0x001: BTFSS 0x20, 0
+            0x0000,  // 0x002: NOP
+            0x2800,  // 0x003: GOTO 0x000
+        ]);
+        sim.run_n_instructions(40).unwrap();
+        let before = sim.coverage().branches()[&0x001];
+        
+        // Rewinding replays from the start; the replayed skips must not
+        // be counted again, however often the user steps back
+        for _ in 0..3 {
+            sim.step_back(5).unwrap();
+            assert_eq!(sim.coverage().branches()[&0x001], before);
+        }
+        assert!(sim.coverage().is_enabled());
+    }
+    
+    #[test]
+    fn test_load_hex_baseline() {
+        let mut program = vec![0x3FFF; 256];
+        program[0] = 0xA00;     // 0x000: GOTO 0x000
+        program[0xFF] = 0xC24;  // 0x0FF: MOVLW 0x24 (OSCCAL value)
+        let hex = HexLoader::save_to_string(&HexProgram {
+            program,
+            eeprom: Vec::new(),
+            config: None,
+            id_locations: None,
+            start_address: None,
+            report: LoadReport::default(),
+        });
+        
+        // No start address in the file: execution starts at the reset vector
+        let mut sim = Simulator::new();
+        sim.set_device(Device::Pic10F200);
+        sim.load_hex_string(&hex).unwrap();
+        assert_eq!(sim.cpu().get_pc(), 0x0FF);
+        sim.step().unwrap();
+        assert_eq!(sim.cpu().read_w(), 0x24);
+        
+        let mut sim = Simulator::new();
+        sim.load_hex_string(":02000000FF3FC0\n:00000001FF\n").unwrap();
+        assert_eq!(sim.cpu().get_pc(), 0x000);
+    }
+    
+    #[test]
+    fn test_asm_rejects_baseline() {
+        let mut sim = Simulator::new();
+        sim.set_device(Device::Pic10F200);
+        let err = sim.load_asm_string(" movlw 0x24\n END\n", "test.asm").unwrap_err();
+        assert!(matches!(err, SimError::AsmUnsupported(Device::Pic10F200)));
+        
+        sim.set_device(Device::Pic16F84A);
+        sim.load_asm_string(" movlw 0x24\n END\n", "test.asm").unwrap();
+    }
+    
+    #[test]
+    fn test_baseline_core() {
+        let mut sim = Simulator::new();
+        sim.set_device(Device::Pic10F200);
+        
+        let mut program = vec![0u16; 256];
+        program[..8].copy_from_slice(&[
+            0xC0E,  // 0x000: MOVLW 0x0E
+            0x006,  // 0x001: TRIS GPIO (GP0 output)
+            0xC01,  // 0x002: MOVLW 0x01
+            0x026,  // 0x003: MOVWF GPIO
+            0x910,  // 0x004: CALL 0x10
+            0x030,  // 0x005: MOVWF 0x10
+            0xA06,  // 0x006: GOTO 0x006
+            0x000,
+        ]);
+        program[0x10] = 0x911;  // 0x010: CALL 0x11
+        program[0x11] = 0x912;  // 0x011: CALL 0x12
+        program[0x12] = 0x842;  // 0x012: RETLW 0x42
+        program[0xFF] = 0xC24;  // 0x0FF: MOVLW 0x24 (OSCCAL value)
+        sim.load_program(&program);
+        sim.reset();
+        
+        // Execution starts at the last word and wraps to 0x000
+        assert_eq!(sim.cpu().get_pc(), 0x0FF);
+        sim.step().unwrap();
+        assert_eq!(sim.cpu().read_w(), 0x24);
+        assert_eq!(sim.cpu().get_pc(), 0x000);
+        
+        sim.run_n_instructions(4).unwrap();
+        assert_eq!(sim.cpu().read_register(crate::cpu::registers::TRISIO), 0x0E);
+        assert_eq!(sim.cpu().gpio().get_pin_state(0), crate::gpio::PinState::High);
+        
+        // Three nested calls overflow the 2-level stack and lose the
+        // return address into the main program
+        sim.run_n_instructions(4).unwrap();
+        assert_eq!(sim.cpu().memory().stack_levels(), 2);
+        assert_eq!(sim.cpu().get_pc(), 0x012);
+        assert_eq!(sim.cpu().read_w(), 0x42);
+        sim.run_n_instructions(1).unwrap();
+        assert_eq!(sim.cpu().get_pc(), 0x011);
+        assert!(sim.cpu().memory().is_stack_empty());
+    }
+    
+    #[test]
+    fn test_int_pin_edges() {
+        use crate::cpu::registers::{INTCON, OPTION_REG};
+        
+        // RB0/INT on the 16F84A; OPTION_REG reads 0 after reset here, so
+        // INTEDG selects the falling edge
+        let mut sim = Simulator::new();
+        sim.set_device(Device::Pic16F84A);
+        sim.load_program(&[0x0000, 0x2800]);
+        sim.reset();
+        
+        sim.set_external_pin(8, true);
+        sim.step().unwrap();
+        assert_eq!(sim.cpu().read_register(INTCON) & 0x02, 0x00);
+        sim.set_external_pin(8, false);
+        sim.step().unwrap();
+        assert_eq!(sim.cpu().read_register(INTCON) & 0x02, 0x02);
+        
+        // Rising edge selected
+        sim.cpu_mut().write_register(INTCON, 0x00);
+        sim.cpu_mut().write_register(OPTION_REG, 0x40);
+        sim.set_external_pin(8, true);
+        sim.step().unwrap();
+        assert_eq!(sim.cpu().read_register(INTCON) & 0x02, 0x02);
+        
+        sim.cpu_mut().write_register(INTCON, 0x00);
+        sim.set_external_pin(8, false);
+        sim.step().unwrap();
+        assert_eq!(sim.cpu().read_register(INTCON) & 0x02, 0x00);
+        
+        // GP2/INT on the 12F675, with the interrupt enabled
+        let mut sim = Simulator::new();
+        sim.load_program(&[0x0000, 0x2800, 0x0000, 0x0000, 0x0000, 0x2805]);
+        sim.reset();
+        sim.cpu_mut().write_register(INTCON, 0x90);
+        sim.set_external_pin(2, true);
+        sim.step().unwrap();
+        sim.set_external_pin(2, false);
+        sim.step().unwrap();
+        assert_eq!(sim.cpu().get_pc(), 0x005);
+        assert_eq!(sim.cpu().interrupts().last_source(), Some(InterruptSource::ExternalInt));
+    }
+    
+    #[test]
+    fn test_device_pins() {
+        let circuit = Circuit::parse("[[component]]\ntype = \"led\"\npin = \"RB7\"\n").unwrap();
+        let mut sim = Simulator::new();
+        assert!(matches!(sim.load_circuit(&circuit), Err(SimError::NoSuchPin { pin: 15, .. })));
+        assert!(sim.check_pins([0, 1, 2, 3, 4, 5]).is_ok());
+        
+        sim.set_device(Device::Pic16F84A);
+        assert_eq!(sim.load_circuit(&circuit).unwrap(), 1);
+        assert!(matches!(sim.check_pins([5]), Err(SimError::NoSuchPin { pin: 5, .. })));
+    }
+}
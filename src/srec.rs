@@ -0,0 +1,219 @@
+//! Motorola S-record loader
+//!
+//! S-record files (.s19, .s28, .s37, .srec) are ASCII text lines with the
+//! format:
+//! STLLAAAA[DD...]CC
+//!
+//! T = record type (0 = header, 1-3 = data, 5-6 = count, 7-9 = start)
+//! LL = byte count of the address, data and checksum
+//! AAAA = address (2, 3 or 4 bytes, depending on the type)
+//! DD = data bytes
+//! CC = checksum (ones' complement of the sum of LL, address and data)
+//!
+//! Addresses are byte addresses laid out as in Intel HEX files, so the
+//! result is the same `HexProgram` the HEX loader produces.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::error::SrecError;
+use crate::hexloader::{HexProgram, ImageBuilder, DEFAULT_PROGRAM_WORDS};
+
+/// File extensions used for S-record files
+pub const SREC_EXTENSIONS: &[&str] = &["s19", "s28", "s37", "srec", "mot"];
+
+/// A single S-record
+#[derive(Debug, Clone)]
+pub struct SRecord {
+    /// Record type digit (0-9)
+    pub record_type: u8,
+    pub address: u32,
+    pub data: Vec<u8>,
+}
+
+impl SRecord {
+    /// Parse a single S-record line
+    pub fn parse(line: &str) -> Result<Self, SrecError> {
+        let line = line.trim();
+
+        let Some(rest) = line.strip_prefix('S') else {
+            return Err(SrecError::MissingStartCode);
+        };
+        let mut chars = rest.chars();
+        let type_char = chars.next().ok_or(SrecError::TooShort)?;
+        let address_len = match type_char {
+            '0' | '1' | '5' | '9' => 2,
+            '2' | '6' | '8' => 3,
+            '3' | '7' => 4,
+            other => return Err(SrecError::InvalidRecordType(other)),
+        };
+        let digits = chars.as_str();
+
+        // Must have even number of hex digits
+        if !digits.len().is_multiple_of(2) {
+            return Err(SrecError::OddLength);
+        }
+
+        // Parse bytes
+        let mut bytes = Vec::new();
+        for pair in digits.as_bytes().chunks(2) {
+            let byte = std::str::from_utf8(pair)
+                .ok()
+                .and_then(|byte_str| u8::from_str_radix(byte_str, 16).ok())
+                .ok_or_else(|| SrecError::InvalidByte(String::from_utf8_lossy(pair).into_owned()))?;
+            bytes.push(byte);
+        }
+
+        // Count, address and checksum
+        if bytes.len() < 2 + address_len {
+            return Err(SrecError::TooShort);
+        }
+
+        let byte_count = bytes[0];
+        if bytes.len() != byte_count as usize + 1 {
+            return Err(SrecError::ByteCountMismatch {
+                expected: byte_count,
+                actual: bytes.len() - 1,
+            });
+        }
+
+        let address = bytes[1..=address_len]
+            .iter()
+            .fold(0u32, |acc, &b| (acc << 8) | b as u32);
+        let checksum_index = bytes.len() - 1;
+        let checksum = bytes[checksum_index];
+
+        // Verify checksum
+        let sum = bytes[..checksum_index].iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+        if !sum != checksum {
+            return Err(SrecError::ChecksumMismatch {
+                address,
+                expected: !sum,
+                actual: checksum,
+            });
+        }
+
+        Ok(SRecord {
+            record_type: type_char as u8 - b'0',
+            address,
+            data: bytes[1 + address_len..checksum_index].to_vec(),
+        })
+    }
+}
+
+/// Whether `content` looks like S-records rather than Intel HEX
+pub fn is_srec(content: &str) -> bool {
+    content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .is_some_and(|line| line.starts_with('S') && line[1..].starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// S-record file loader
+pub struct SrecLoader;
+
+impl SrecLoader {
+    /// Load an S-record file, warning about program data past 1K words
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<HexProgram, SrecError> {
+        Self::load_file_for(path, DEFAULT_PROGRAM_WORDS)
+    }
+
+    /// Load an S-record file for a device with `program_words` words of program memory
+    pub fn load_file_for<P: AsRef<Path>>(path: P, program_words: usize) -> Result<HexProgram, SrecError> {
+        let file = File::open(path.as_ref())
+            .map_err(SrecError::Open)?;
+
+        let lines = BufReader::new(file)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(SrecError::Read)?;
+
+        Self::load_from_lines(&lines, program_words)
+    }
+
+    /// Load S-records from a string, warning about program data past 1K words
+    pub fn load_from_string(content: &str) -> Result<HexProgram, SrecError> {
+        Self::load_from_string_for(content, DEFAULT_PROGRAM_WORDS)
+    }
+
+    /// Load S-records from a string for a device with `program_words` words of program memory
+    pub fn load_from_string_for(content: &str, program_words: usize) -> Result<HexProgram, SrecError> {
+        let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
+        Self::load_from_lines(&lines, program_words)
+    }
+
+    /// Load from a collection of lines
+    fn load_from_lines(lines: &[String], program_words: usize) -> Result<HexProgram, SrecError> {
+        let mut image = ImageBuilder::new(program_words);
+        let mut start_address = None;
+
+        for (line_num, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let record = SRecord::parse(line)
+                .map_err(|e| SrecError::Line { line: line_num + 1, source: Box::new(e) })?;
+
+            match record.record_type {
+                1..=3 => image.add_data(line_num + 1, record.address, &record.data),
+                7..=9 => {
+                    // Termination record with the start byte address
+                    start_address = Some((record.address / 2) as u16);
+                    break;
+                }
+                // Header and record counts
+                _ => {}
+            }
+        }
+
+        Ok(image.finish(start_address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_record() {
+        let record = SRecord::parse("S10700005530200053").unwrap();
+        assert_eq!(record.record_type, 1);
+        assert_eq!(record.address, 0);
+        assert_eq!(record.data, vec![0x55, 0x30, 0x20, 0x00]);
+
+        let record = SRecord::parse("S3070000400E8431F5").unwrap();
+        assert_eq!(record.address, 0x400E);
+
+        assert!(matches!(SRecord::parse("S10700005530200054"), Err(SrecError::ChecksumMismatch { .. })));
+        assert!(matches!(SRecord::parse("S40700005530200053"), Err(SrecError::InvalidRecordType('4'))));
+        assert!(matches!(SRecord::parse(":0000"), Err(SrecError::MissingStartCode)));
+        assert!(matches!(SRecord::parse("S1aé0"), Err(SrecError::InvalidByte(_))));
+    }
+
+    #[test]
+    fn test_load_program() {
+        let srec = "\
+S00600004844521B
+S10700005530200053
+S10500040228CC
+S105400E8431F7
+S10742004800690005
+S9030000FC
+";
+        assert!(is_srec(srec));
+        assert!(!is_srec(":00000001FF\n"));
+
+        let program = SrecLoader::load_from_string(srec).unwrap();
+        assert_eq!(program.program, vec![0x3055, 0x0020, 0x2802]);
+        assert_eq!(program.config, Some(0x3184));
+        assert_eq!(program.eeprom, vec![0x48, 0x69]);
+        assert_eq!(program.report.words_loaded, 3);
+
+        let err = SrecLoader::load_from_string("S10700005530200054\n").unwrap_err();
+        assert!(err.to_string().starts_with("Line 1: Checksum mismatch"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Machine state save and restore
+//!
+//! `MachineState` captures everything needed to resume execution: the CPU
+//! with its memory, stack and peripherals, the execution statistics, the
+//! oscillator, the configuration word and user IDs, and the symbols of the
+//! loaded program so a restored session keeps its labels and source view.
+//! States are persisted as plain `key = value` text, one field per line,
+//! so saved files can be inspected and diffed. With the `serde` feature,
+//! `MachineState` also implements `Serialize`/`Deserialize` for use with
+//! JSON, bincode or any other serde format.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write as _};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::Cpu;
+use crate::clock::{Clock, ClockSource};
+use crate::error::StateError;
+use crate::simulator::SimulatorStats;
+use crate::symbols::SymbolTable;
+
+/// First line of a saved state file
+pub const STATE_HEADER: &str = "# pic_simulator machine state v1";
+
+/// Named fields of a saved state
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateRecord {
+    fields: BTreeMap<String, String>,
+}
+
+impl StateRecord {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a scalar value
+    pub fn set<T: Display>(&mut self, key: &str, value: T) {
+        self.fields.insert(key.to_string(), value.to_string());
+    }
+
+    /// Store bytes as space-separated hex
+    pub fn set_bytes(&mut self, key: &str, bytes: &[u8]) {
+        let text: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        self.fields.insert(key.to_string(), text.join(" "));
+    }
+
+    /// Store words as space-separated hex
+    pub fn set_words(&mut self, key: &str, words: &[u16]) {
+        let text: Vec<String> = words.iter().map(|w| format!("{:04X}", w)).collect();
+        self.fields.insert(key.to_string(), text.join(" "));
+    }
+
+    /// Read a scalar value
+    pub fn get<T: FromStr>(&self, key: &str) -> Result<T, StateError> {
+        let value = self.raw(key)?;
+        value.parse()
+            .map_err(|_| invalid_value(key, value))
+    }
+
+    /// Read hex bytes into `out`, which must match the stored length
+    pub fn get_bytes(&self, key: &str, out: &mut [u8]) -> Result<(), StateError> {
+        self.get_hex(key, out, |s| u8::from_str_radix(s, 16).ok())
+    }
+
+    /// Read hex words into `out`, which must match the stored length
+    pub fn get_words(&self, key: &str, out: &mut [u16]) -> Result<(), StateError> {
+        self.get_hex(key, out, |s| u16::from_str_radix(s, 16).ok())
+    }
+
+    fn get_hex<T>(&self, key: &str, out: &mut [T], parse: impl Fn(&str) -> Option<T>) -> Result<(), StateError> {
+        let values: Vec<&str> = self.raw(key)?.split_whitespace().collect();
+        if values.len() != out.len() {
+            return Err(StateError::WrongLength { key: key.to_string(), actual: values.len(), expected: out.len() });
+        }
+        for (slot, text) in out.iter_mut().zip(values) {
+            *slot = parse(text).ok_or_else(|| invalid_value(key, text))?;
+        }
+        Ok(())
+    }
+
+    /// Fields whose key starts with `prefix`, as (rest of key, value) pairs
+    pub fn fields_with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.fields.range(prefix.to_string()..)
+            .map_while(move |(key, value)| key.strip_prefix(prefix).map(|rest| (rest, value.as_str())))
+    }
+
+    /// Check if a field is present
+    pub fn contains(&self, key: &str) -> bool {
+        self.fields.contains_key(key)
+    }
+
+    fn raw(&self, key: &str) -> Result<&str, StateError> {
+        self.fields.get(key)
+            .map(String::as_str)
+            .ok_or_else(|| StateError::MissingField(key.to_string()))
+    }
+
+    /// Format as `key = value` lines
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", STATE_HEADER);
+        for (key, value) in &self.fields {
+            let _ = writeln!(text, "{} = {}", key, value);
+        }
+        text
+    }
+
+    /// Parse `key = value` lines; `#` starts a comment line
+    pub fn parse(text: &str) -> Result<Self, StateError> {
+        let mut record = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .ok_or(StateError::Syntax(index + 1))?;
+            record.fields.insert(key.trim().to_string(), value.trim().to_string());
+        }
+        Ok(record)
+    }
+}
+
+/// The error for a field whose text does not parse
+pub fn invalid_value(key: &str, value: &str) -> StateError {
+    StateError::InvalidValue { key: key.to_string(), value: value.to_string() }
+}
+
+/// Complete machine state
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MachineState {
+    pub cpu: Cpu,
+    pub stats: SimulatorStats,
+    /// Oscillator source and frequency
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub clock: Clock,
+    /// Configuration word of the loaded program
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub config_word: Option<u16>,
+    /// User ID locations of the loaded program
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub id_locations: Option<[u16; 4]>,
+    /// Labels and source lines of the loaded program
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub symbols: SymbolTable,
+}
+
+impl MachineState {
+    /// Store all fields in a record
+    pub fn to_record(&self) -> StateRecord {
+        let mut record = StateRecord::new();
+        self.cpu.save_state(&mut record);
+        record.set("stats.instructions_executed", self.stats.instructions_executed);
+        record.set("stats.cycles_elapsed", self.stats.cycles_elapsed);
+        record.set("clock.source", self.clock.source().name());
+        record.set("clock.fosc_hz", self.clock.fosc_hz());
+        if let Some(config) = self.config_word {
+            record.set_words("config.word", &[config]);
+        }
+        if let Some(ids) = self.id_locations {
+            record.set_words("config.id_locations", &ids);
+        }
+        self.symbols.save_state(&mut record);
+        record
+    }
+
+    /// Rebuild a state from a record
+    pub fn from_record(record: &StateRecord) -> Result<Self, StateError> {
+        let mut cpu = Cpu::new();
+        cpu.load_state(record)?;
+        let stats = SimulatorStats {
+            instructions_executed: record.get("stats.instructions_executed")?,
+            cycles_elapsed: record.get("stats.cycles_elapsed")?,
+        };
+        let source: String = record.get("clock.source")?;
+        let mut clock = Clock::with_source(ClockSource::from_name(&source)
+            .ok_or_else(|| invalid_value("clock.source", &source))?);
+        clock.set_fosc_hz(record.get("clock.fosc_hz")?);
+        // Absent when no HEX file with them was loaded
+        let config_word = if record.contains("config.word") {
+            let mut config = [0];
+            record.get_words("config.word", &mut config)?;
+            Some(config[0])
+        } else {
+            None
+        };
+        let id_locations = if record.contains("config.id_locations") {
+            let mut ids = [0; 4];
+            record.get_words("config.id_locations", &mut ids)?;
+            Some(ids)
+        } else {
+            None
+        };
+        let mut symbols = SymbolTable::new();
+        symbols.load_state(record)?;
+        Ok(Self { cpu, stats, clock, config_word, id_locations, symbols })
+    }
+
+    /// Format as text
+    pub fn to_text(&self) -> String {
+        self.to_record().to_text()
+    }
+
+    /// Parse a state saved with `to_text`
+    pub fn from_text(text: &str) -> Result<Self, StateError> {
+        Self::from_record(&StateRecord::parse(text)?)
+    }
+
+    /// Save to a file
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<(), StateError> {
+        fs::write(path, self.to_text()).map_err(StateError::Write)
+    }
+
+    /// Load from a file
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self, StateError> {
+        let text = fs::read_to_string(path).map_err(StateError::Read)?;
+        Self::from_text(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_round_trip() {
+        let mut record = StateRecord::new();
+        record.set("cpu.w", 0x42u8);
+        record.set("cpu.sleeping", true);
+        record.set_bytes("memory.eeprom", &[0x00, 0xAB]);
+        record.set_words("memory.stack", &[0x0123, 0x3FFF]);
+
+        let parsed = StateRecord::parse(&record.to_text()).unwrap();
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.get::<u8>("cpu.w").unwrap(), 0x42);
+        assert!(parsed.get::<bool>("cpu.sleeping").unwrap());
+
+        let mut words = [0u16; 2];
+        parsed.get_words("memory.stack", &mut words).unwrap();
+        assert_eq!(words, [0x0123, 0x3FFF]);
+
+        let mut bytes = [0u8; 3];
+        assert!(matches!(parsed.get_bytes("memory.eeprom", &mut bytes),
+            Err(StateError::WrongLength { actual: 2, expected: 3, .. })));
+        assert!(matches!(parsed.get::<u8>("cpu.pc"), Err(StateError::MissingField(key)) if key == "cpu.pc"));
+    }
+
+    #[test]
+    fn test_machine_state_round_trip() {
+        let mut cpu = Cpu::new();
+        cpu.reset();
+        cpu.memory_mut().load_program(&[0x3001, 0x0085]);
+        cpu.write_register(0x20, 0x55);
+        cpu.write_w(0x12);
+        cpu.set_pc(0x0001);
+        cpu.memory_mut().push_stack(0x0010);
+        cpu.timers_mut().timer1.write_low(0x34);
+
+        let mut symbols = SymbolTable::new();
+        symbols.add_label("start", 0x0000);
+        symbols.add_label("main", 0x0000);
+        symbols.add_line(0x0001, "my blink.asm", 4);
+        symbols.add_source_line("my blink.asm", 4, "        movwf GPIO ; a = b");
+
+        let state = MachineState {
+            cpu,
+            stats: SimulatorStats { instructions_executed: 7, cycles_elapsed: 9 },
+            clock: Clock::with_source(ClockSource::Hs),
+            config_word: Some(0x3FC2),
+            id_locations: Some([1, 2, 3, 4]),
+            symbols,
+        };
+        let restored = MachineState::from_text(&state.to_text()).unwrap();
+
+        assert_eq!(restored.to_record(), state.to_record());
+        assert_eq!(restored.cpu.read_register(0x20), 0x55);
+        assert_eq!(restored.cpu.read_w(), 0x12);
+        assert_eq!(restored.cpu.get_pc(), 0x0001);
+        assert_eq!(restored.cpu.memory().read_program(1), 0x0085);
+        assert_eq!(restored.cpu.timers().timer1.get_counter(), 0x0034);
+        assert_eq!(restored.stats.cycles_elapsed, 9);
+        assert_eq!(restored.clock, Clock::with_source(ClockSource::Hs));
+        assert_eq!(restored.config_word, Some(0x3FC2));
+        assert_eq!(restored.id_locations, Some([1, 2, 3, 4]));
+        assert_eq!(restored.symbols.label_at(0x0000), Some("start"));
+        assert_eq!(restored.symbols.address_of("main"), Some(0x0000));
+        assert_eq!(restored.symbols.location_of(0x0001).map(|loc| loc.line), Some(4));
+        assert_eq!(restored.symbols.source_line("my blink.asm", 4), Some("        movwf GPIO ; a = b"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut cpu = Cpu::new();
+        cpu.reset();
+        cpu.memory_mut().load_program(&[0x3001, 0x0085]);
+        cpu.write_register(0x20, 0x55);
+        cpu.memory_mut().write_eeprom(0x10, 0xAA);
+
+        let state = MachineState {
+            cpu,
+            stats: SimulatorStats { instructions_executed: 1, cycles_elapsed: 2 },
+            clock: Clock::new(),
+            config_word: None,
+            id_locations: None,
+            symbols: SymbolTable::new(),
+        };
+        let json = serde_json::to_string(&state).unwrap();
+        let restored: MachineState = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.to_record(), state.to_record());
+        assert!(serde_json::from_str::<MachineState>("{}").is_err());
+    }
+}
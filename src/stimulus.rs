@@ -0,0 +1,347 @@
+//! Pin stimulus scripts
+//!
+//! Schedules external input pin changes by cycle count so input-driven
+//! firmware can be tested reproducibly. Scripts are plain text, one rule
+//! per line:
+//!
+//! ```text
+//! # Clock on GP2
+//! GP2: toggle every 1000 cycles
+//! GP5: toggle every 250 cycles starting at 4000
+//! # Pulse GP4 low for 200 cycles
+//! GP4: low at cycle 5000 for 200 cycles
+//! GP1: high at cycle 300
+//! ```
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::error::StimulusError;
+use crate::gpio::{self, Gpio};
+
+/// A single stimulus rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StimulusRule {
+    /// Drive `pin` to `level` at cycle `at`, reverting after `duration` cycles
+    Set { pin: u8, level: bool, at: u64, duration: Option<u64> },
+
+    /// Toggle `pin` every `period` cycles, first at cycle `start + period`
+    Toggle { pin: u8, period: u64, start: u64 },
+}
+
+impl fmt::Display for StimulusRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            StimulusRule::Set { pin, level, at, duration } => {
+                write!(f, "{}: {} at cycle {}", gpio::pin_label(pin), if level { "high" } else { "low" }, at)?;
+                if let Some(duration) = duration {
+                    write!(f, " for {} cycles", duration)?;
+                }
+                Ok(())
+            }
+            StimulusRule::Toggle { pin, period, start } => {
+                write!(f, "{}: toggle every {} cycles", gpio::pin_label(pin), period)?;
+                if start > 0 {
+                    write!(f, " starting at {}", start)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl StimulusRule {
+    /// Pin the rule drives
+    pub fn pin(&self) -> u8 {
+        match *self {
+            StimulusRule::Set { pin, .. } | StimulusRule::Toggle { pin, .. } => pin,
+        }
+    }
+
+    /// Level the rule alone drives at `cycle`, given the pin was at
+    /// `initial` before the rule first acted
+    pub fn level_at(&self, cycle: u64, initial: bool) -> bool {
+        match *self {
+            StimulusRule::Set { level, at, duration, .. } => {
+                if cycle < at {
+                    initial
+                } else if duration.is_some_and(|duration| cycle >= at + duration) {
+                    !level
+                } else {
+                    level
+                }
+            }
+            StimulusRule::Toggle { period, start, .. } => {
+                let toggles = cycle.saturating_sub(start) / period;
+                initial ^ (toggles % 2 == 1)
+            }
+        }
+    }
+}
+
+/// A pending pin change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct ScheduledChange {
+    cycle: u64,
+    pin: u8,
+    change: PinChange,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum PinChange {
+    Level(bool),
+    Toggle { period: u64 },
+}
+
+/// Stimulus rules and their pending pin changes
+#[derive(Debug, Clone, Default)]
+pub struct Stimulus {
+    rules: Vec<StimulusRule>,
+    queue: BinaryHeap<Reverse<ScheduledChange>>,
+}
+
+impl Stimulus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a stimulus script
+    pub fn parse(script: &str) -> Result<Self, StimulusError> {
+        let mut stimulus = Self::new();
+
+        for (index, line) in script.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let rule = Self::parse_rule(line)
+                .map_err(|e| StimulusError::Line { line: index + 1, source: Box::new(e) })?;
+            stimulus.add_rule(rule);
+        }
+
+        Ok(stimulus)
+    }
+
+    /// Load a stimulus script from a file
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self, StimulusError> {
+        let content = fs::read_to_string(path)
+            .map_err(StimulusError::Read)?;
+        Self::parse(&content)
+    }
+
+    fn parse_rule(line: &str) -> Result<StimulusRule, StimulusError> {
+        let (pin, action) = line.split_once(':')
+            .ok_or(StimulusError::MissingAction)?;
+        let pin = parse_pin(pin.trim())?;
+
+        let words: Vec<String> = action.split_whitespace()
+            .map(|w| w.to_ascii_lowercase())
+            .collect();
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+        match words.as_slice() {
+            ["toggle", "every", period, "cycles", rest @ ..] => {
+                let period = parse_cycles(period)?;
+                if period == 0 {
+                    return Err(StimulusError::ZeroPeriod);
+                }
+                let start = match rest {
+                    [] => 0,
+                    ["starting", "at", start] => parse_cycles(start)?,
+                    _ => return Err(StimulusError::Unexpected(rest.join(" "))),
+                };
+                Ok(StimulusRule::Toggle { pin, period, start })
+            }
+            [level @ ("high" | "low"), "at", "cycle", at, rest @ ..] => {
+                let at = parse_cycles(at)?;
+                let duration = match rest {
+                    [] => None,
+                    ["for", duration, "cycles"] => Some(parse_cycles(duration)?),
+                    _ => return Err(StimulusError::Unexpected(rest.join(" "))),
+                };
+                Ok(StimulusRule::Set { pin, level: *level == "high", at, duration })
+            }
+            _ => Err(StimulusError::UnknownAction(action.trim().to_string())),
+        }
+    }
+
+    /// Add a rule and schedule its changes
+    pub fn add_rule(&mut self, rule: StimulusRule) {
+        self.rules.push(rule);
+        self.schedule(rule, 0);
+    }
+
+    /// Remove the rule at `index`; call `reschedule` to drop its pending changes
+    pub fn remove_rule(&mut self, index: usize) -> Option<StimulusRule> {
+        (index < self.rules.len()).then(|| self.rules.remove(index))
+    }
+
+    fn schedule(&mut self, rule: StimulusRule, from_cycle: u64) {
+        match rule {
+            StimulusRule::Set { pin, level, at, duration } => {
+                if at >= from_cycle {
+                    self.push(at, pin, PinChange::Level(level));
+                }
+                if let Some(duration) = duration {
+                    let end = at + duration;
+                    if end >= from_cycle {
+                        self.push(end, pin, PinChange::Level(!level));
+                    }
+                }
+            }
+            StimulusRule::Toggle { pin, period, start } => {
+                // First toggle at or after `from_cycle`
+                let elapsed = from_cycle.saturating_sub(start + 1);
+                let next = start + period * (elapsed / period + 1);
+                self.push(next, pin, PinChange::Toggle { period });
+            }
+        }
+    }
+
+    fn push(&mut self, cycle: u64, pin: u8, change: PinChange) {
+        self.queue.push(Reverse(ScheduledChange { cycle, pin, change }));
+    }
+
+    /// Rebuild the pending changes for execution resuming at `cycle`
+    /// Used after a reset or when rewinding to an earlier point
+    pub fn reschedule(&mut self, cycle: u64) {
+        self.queue.clear();
+        for rule in self.rules.clone() {
+            self.schedule(rule, cycle);
+        }
+    }
+
+    /// Apply all changes due at or before `cycle` to the external pins
+    pub fn apply(&mut self, cycle: u64, gpio: &mut Gpio) {
+        while let Some(&Reverse(change)) = self.queue.peek() {
+            if change.cycle > cycle {
+                break;
+            }
+            self.queue.pop();
+
+            match change.change {
+                PinChange::Level(level) => gpio.set_external_pin(change.pin, level),
+                PinChange::Toggle { period } => {
+                    let level = gpio.get_external_pin(change.pin);
+                    gpio.set_external_pin(change.pin, !level);
+                    self.push(change.cycle + period, change.pin, change.change);
+                }
+            }
+        }
+    }
+
+    /// Cycle of the next pending change
+    pub fn next_change(&self) -> Option<u64> {
+        self.queue.peek().map(|Reverse(change)| change.cycle)
+    }
+
+    /// All rules
+    pub fn rules(&self) -> &[StimulusRule] {
+        &self.rules
+    }
+
+    /// Check if no rules are loaded
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Remove all rules
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.queue.clear();
+    }
+}
+
+/// Parse a pin name such as `GP2` or `RB0` (or just `2`)
+fn parse_pin(s: &str) -> Result<u8, StimulusError> {
+    gpio::parse_pin(s).ok_or_else(|| StimulusError::InvalidPin(s.to_string()))
+}
+
+fn parse_cycles(s: &str) -> Result<u64, StimulusError> {
+    s.replace('_', "").parse()
+        .map_err(|_| StimulusError::InvalidCycles(s.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() {
+        let stimulus = Stimulus::parse(
+            "# comment\n\
+             GP2: toggle every 1000 cycles\n\
+             gp4: LOW at cycle 5000 for 200 cycles  # pulse\n\
+             GP5: toggle every 10 cycles starting at 40\n",
+        ).unwrap();
+
+        assert_eq!(stimulus.rules(), &[
+            StimulusRule::Toggle { pin: 2, period: 1000, start: 0 },
+            StimulusRule::Set { pin: 4, level: false, at: 5000, duration: Some(200) },
+            StimulusRule::Toggle { pin: 5, period: 10, start: 40 },
+        ]);
+        assert_eq!(stimulus.rules()[1].to_string(), "GP4: low at cycle 5000 for 200 cycles");
+
+        let err = Stimulus::parse("GP2: toggle\n").unwrap_err();
+        assert!(matches!(err, StimulusError::Line { line: 1, .. }));
+        assert!(Stimulus::parse("GP8: high at cycle 1\n").is_err());
+        let stimulus = Stimulus::parse("RB0: toggle every 10 cycles\n").unwrap();
+        assert_eq!(stimulus.rules()[0].to_string(), "RB0: toggle every 10 cycles");
+    }
+
+    #[test]
+    fn test_apply_pulse_and_toggle() {
+        let mut gpio = Gpio::new();
+        let mut stimulus = Stimulus::parse(
+            "GP4: low at cycle 50 for 20 cycles\nGP2: toggle every 30 cycles\n",
+        ).unwrap();
+
+        stimulus.apply(29, &mut gpio);
+        assert!(gpio.get_external_pin(2));
+
+        stimulus.apply(30, &mut gpio);
+        assert!(!gpio.get_external_pin(2));
+
+        stimulus.apply(55, &mut gpio);
+        assert!(!gpio.get_external_pin(4));
+
+        stimulus.apply(70, &mut gpio);
+        assert!(gpio.get_external_pin(4));
+        assert!(gpio.get_external_pin(2));
+        assert_eq!(stimulus.next_change(), Some(90));
+    }
+
+    #[test]
+    fn test_reschedule() {
+        let mut stimulus = Stimulus::new();
+        stimulus.add_rule(StimulusRule::Toggle { pin: 0, period: 10, start: 5 });
+        stimulus.add_rule(StimulusRule::Set { pin: 1, level: true, at: 12, duration: None });
+
+        stimulus.reschedule(25);
+        assert_eq!(stimulus.next_change(), Some(25));
+
+        stimulus.reschedule(26);
+        assert_eq!(stimulus.next_change(), Some(35));
+
+        assert!(stimulus.remove_rule(0).is_some());
+        assert!(stimulus.remove_rule(1).is_none());
+        stimulus.reschedule(0);
+        assert_eq!(stimulus.next_change(), Some(12));
+    }
+
+    #[test]
+    fn test_level_at() {
+        let toggle = StimulusRule::Toggle { pin: 2, period: 30, start: 0 };
+        let levels: Vec<bool> = [29, 30, 59, 60].iter().map(|&c| toggle.level_at(c, true)).collect();
+        assert_eq!(levels, vec![true, false, false, true]);
+
+        let pulse = StimulusRule::Set { pin: 4, level: false, at: 50, duration: Some(20) };
+        let levels: Vec<bool> = [49, 50, 69, 70].iter().map(|&c| pulse.level_at(c, true)).collect();
+        assert_eq!(levels, vec![true, false, false, true]);
+        assert_eq!(pulse.pin(), 4);
+    }
+}
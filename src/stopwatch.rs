@@ -0,0 +1,145 @@
+//! Stopwatch between two program points
+//!
+//! The stopwatch starts when execution reaches the start address and stops
+//! (pausing the simulator) when it reaches the stop address, measuring the
+//! cycles in between. This is used to verify delay routines and other
+//! timing-critical code without manual cycle arithmetic.
+
+use crate::clock::Clock;
+
+/// A completed stopwatch measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StopwatchReading {
+    pub start: u16,
+    pub stop: u16,
+    pub cycles: u64,
+}
+
+impl StopwatchReading {
+    /// Measured time in microseconds at the given clock
+    pub fn micros(&self, clock: &Clock) -> f64 {
+        clock.cycles_to_micros(self.cycles)
+    }
+}
+
+/// Stopwatch armed between a start and a stop address
+#[derive(Debug, Clone, Default)]
+pub struct Stopwatch {
+    points: Option<(u16, u16)>,
+    started_at: Option<u64>,
+    last: Option<StopwatchReading>,
+}
+
+impl Stopwatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Arm the stopwatch between `start` and `stop`
+    /// If the PC is already at `start`, timing begins at `cycle`
+    pub fn arm(&mut self, start: u16, stop: u16, pc: u16, cycle: u64) {
+        self.points = Some((start, stop));
+        self.started_at = (pc == start).then_some(cycle);
+    }
+
+    /// Disarm the stopwatch, keeping the last reading
+    pub fn disarm(&mut self) {
+        self.points = None;
+        self.started_at = None;
+    }
+
+    /// Check if the stopwatch is armed
+    pub fn is_armed(&self) -> bool {
+        self.points.is_some()
+    }
+
+    /// Start and stop addresses, if armed
+    pub fn points(&self) -> Option<(u16, u16)> {
+        self.points
+    }
+
+    /// Check if timing is in progress
+    pub fn is_running(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    /// Cycles since the start address was reached, if running
+    pub fn elapsed(&self, cycle: u64) -> Option<u64> {
+        self.started_at.map(|start| cycle.saturating_sub(start))
+    }
+
+    /// Most recent completed measurement
+    pub fn last_reading(&self) -> Option<&StopwatchReading> {
+        self.last.as_ref()
+    }
+
+    /// Track execution reaching `pc` at `cycle`
+    /// Returns the measurement when the stop address is reached. With the
+    /// same start and stop address, successive passes are measured.
+    pub fn update(&mut self, pc: u16, cycle: u64) -> Option<StopwatchReading> {
+        let (start, stop) = self.points?;
+
+        let mut reading = None;
+        if pc == stop && let Some(started_at) = self.started_at.take() {
+            let measured = StopwatchReading { start, stop, cycles: cycle - started_at };
+            self.last = Some(measured);
+            reading = Some(measured);
+        }
+        if pc == start && self.started_at.is_none() {
+            self.started_at = Some(cycle);
+        }
+
+        reading
+    }
+
+    /// Forget a start that lies after `cycle` (used when rewinding)
+    pub fn rewind(&mut self, cycle: u64) {
+        if self.started_at.is_some_and(|start| start > cycle) {
+            self.started_at = None;
+        }
+    }
+
+    /// Re-arm at the same points (used on reset)
+    pub fn restart(&mut self, pc: u16, cycle: u64) {
+        if let Some((start, stop)) = self.points {
+            self.arm(start, stop, pc, cycle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_between_points() {
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.arm(0x010, 0x020, 0x000, 0);
+
+        assert_eq!(stopwatch.update(0x020, 5), None);
+        assert_eq!(stopwatch.update(0x010, 10), None);
+        assert!(stopwatch.is_running());
+        assert_eq!(stopwatch.elapsed(15), Some(5));
+
+        let reading = stopwatch.update(0x020, 1010).unwrap();
+        assert_eq!(reading.cycles, 1000);
+        let mut clock = Clock::new();
+        assert_eq!(reading.micros(&clock), 1000.0);
+        clock.set_fosc_hz(20_000_000);
+        assert_eq!(reading.micros(&clock), 200.0);
+        assert!(!stopwatch.is_running());
+        assert_eq!(stopwatch.last_reading(), Some(&reading));
+    }
+
+    #[test]
+    fn test_same_start_and_stop() {
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.arm(0x004, 0x004, 0x004, 100);
+
+        assert_eq!(stopwatch.update(0x004, 130).map(|r| r.cycles), Some(30));
+        assert_eq!(stopwatch.update(0x004, 170).map(|r| r.cycles), Some(40));
+
+        stopwatch.rewind(150);
+        assert!(!stopwatch.is_running());
+    }
+}
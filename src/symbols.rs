@@ -0,0 +1,654 @@
+//! Symbol tables for symbolic debugging
+//!
+//! Maps program addresses to labels and source file/line information taken
+//! from assembler and linker output:
+//! - `.lst`: MPASM/gpasm listing files (labels, line numbers and source text)
+//! - `.map`: MPLINK and XC8 map files (labels only)
+//! - `.cod`: MPASM/gpasm COD debug files (labels and line numbers)
+//!
+//! A COD file is a sequence of 512-byte blocks. The first block is a
+//! directory giving the first and last block of each table:
+//!
+//! | Table       | Directory offset | Entries                                   |
+//! |-------------|------------------|-------------------------------------------|
+//! | File names  | 427              | 64-byte slots, length byte then the name  |
+//! | Line table  | 431              | file index, mode, line, word address      |
+//! | Long symbols| 460              | length, name, type, big-endian value      |
+//!
+//! The older short symbol table and the program image in the code blocks
+//! are not read; the image comes from the HEX file built alongside.
+//!
+//! Linked ELF and COFF firmware carries its own labels (and, for COFF,
+//! source lines); the `elf` and `coff` loaders fill a table from those.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::error::{StateError, SymbolError};
+use crate::state::{StateRecord, invalid_value};
+
+/// Source position of an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+}
+
+/// Address-to-label and address-to-source mapping
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolTable {
+    /// Primary label of each labelled address
+    labels: BTreeMap<u16, String>,
+
+    /// Every known label (several labels may share an address)
+    addresses: HashMap<String, u16>,
+
+    /// Source location of each instruction
+    lines: BTreeMap<u16, SourceLocation>,
+
+    /// Source text by file and line number
+    sources: HashMap<String, BTreeMap<usize, String>>,
+}
+
+/// Assembler directives that may appear in the label column
+const DIRECTIVES: &[&str] = &[
+    "LIST", "NOLIST", "END", "ORG", "INCLUDE", "PROCESSOR", "RADIX", "TITLE",
+    "ERRORLEVEL", "CBLOCK", "ENDC", "__CONFIG", "__IDLOCS", "__MAXRAM", "__BADRAM",
+    "EXPAND", "NOEXPAND", "PAGE", "SPACE", "SUBTITLE",
+];
+
+/// Size of a COD file block; every table starts on a block boundary
+const COD_BLOCK_SIZE: usize = 512;
+
+/// Directory offsets of the (first, last) block numbers of each table
+const COD_DIR_NAMTAB: usize = 427;
+const COD_DIR_LSTTAB: usize = 431;
+const COD_DIR_LSYMTAB: usize = 460;
+
+/// File name slot size in the name table
+const COD_FILE_SIZE: usize = 64;
+
+/// Line table entry: file index, mode, line (LE16), word address (LE16)
+const COD_LINE_SYM_SIZE: usize = 6;
+
+/// Long symbol type, value and padding after the name
+const COD_LSYMBOL_EXTRA: usize = 6;
+
+/// Long symbol type of program labels (constants are type 47)
+const COD_ST_ADDRESS: u16 = 46;
+
+/// Directives that define a constant rather than a program label
+const EQUATES: &[&str] = &["EQU", "SET", "=", "#DEFINE", "MACRO", "VARIABLE"];
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a symbol file, choosing the parser from the file extension
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self, SymbolError> {
+        let path = path.as_ref();
+        let extension = path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "lst" => {
+                let content = fs::read_to_string(path)
+                    .map_err(SymbolError::Read)?;
+                let source = path.with_extension("asm");
+                let source = source.file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("source.asm");
+                Ok(Self::parse_lst(&content, source))
+            }
+            "map" => {
+                let content = fs::read_to_string(path)
+                    .map_err(SymbolError::Read)?;
+                Ok(Self::parse_map(&content))
+            }
+            "cod" => {
+                let data = fs::read(path)
+                    .map_err(SymbolError::Read)?;
+                Self::parse_cod(&data)
+            }
+            _ => Err(SymbolError::UnknownType(path.to_path_buf())),
+        }
+    }
+
+    /// Parse an MPASM/gpasm listing
+    ///
+    /// The source file name is taken from the listing header when present,
+    /// otherwise `default_file` is used. Included files are followed by
+    /// watching the line numbers restart after an `#include` line.
+    pub fn parse_lst(content: &str, default_file: &str) -> Self {
+        let mut table = Self::new();
+        let mut pending_labels: Vec<String> = Vec::new();
+
+        // Open files with the last line number seen in each
+        let mut files: Vec<(String, usize)> = vec![(default_file.to_string(), 0)];
+
+        for line in content.lines() {
+            // Page header: "MPASM 5.51   BLINK.ASM   1-1-2024 ..."
+            if line.starts_with("MPASM") || line.starts_with("gpasm") {
+                if let Some(name) = line.split_whitespace()
+                    .find(|t| t.to_ascii_lowercase().ends_with(".asm"))
+                {
+                    files[0].0 = name.to_string();
+                }
+                continue;
+            }
+
+            // The symbol table summary at the end also lists equates
+            if line.trim_start().starts_with("SYMBOL TABLE") {
+                break;
+            }
+
+            let Some((address, line_number, source)) = Self::split_lst_line(line) else {
+                continue;
+            };
+
+            // Returning to an including file continues its line numbering
+            let current = files.last().map_or(0, |(_, last)| *last);
+            if line_number != current + 1
+                && let Some(depth) = files.iter().rposition(|(_, last)| *last + 1 == line_number)
+            {
+                files.truncate(depth + 1);
+            }
+            let Some((file, last)) = files.last_mut() else {
+                continue;
+            };
+            *last = line_number;
+            let file = file.clone();
+
+            table.add_source_line(&file, line_number, source);
+
+            if let Some(label) = Self::lst_label(source) {
+                pending_labels.push(label);
+            }
+
+            if let Some(address) = address {
+                for label in pending_labels.drain(..) {
+                    table.add_label(&label, address);
+                }
+                table.add_line(address, &file, line_number);
+            }
+
+            if let Some(include) = Self::lst_include(source) {
+                files.push((include, 0));
+            }
+        }
+
+        table
+    }
+
+    /// Split a listing line into (LOC, line number, source text)
+    fn split_lst_line(line: &str) -> Option<(Option<u16>, usize, &str)> {
+        let mut rest = line;
+        let mut fields: Vec<&str> = Vec::new();
+
+        // Up to two hex fields (LOC and object code, or an equate value)
+        // precede the 5-digit line number
+        for _ in 0..3 {
+            let trimmed = rest.trim_start();
+            let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+            let token = &trimmed[..end];
+            rest = &trimmed[end..];
+
+            if token.len() == 5 && token.chars().all(|c| c.is_ascii_digit()) {
+                let line_number = token.parse().ok()?;
+                let source = rest.strip_prefix(' ').unwrap_or(rest);
+
+                // LOC is a 4-digit address followed by its object code
+                let address = match fields.as_slice() {
+                    [loc, code] if loc.len() == 4 && code.len() == 4 => {
+                        u16::from_str_radix(loc, 16).ok()
+                    }
+                    _ => None,
+                };
+                return Some((address, line_number, source));
+            }
+
+            if token.is_empty() || !token.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            fields.push(token);
+        }
+
+        None
+    }
+
+    /// Label defined by a line of source text, if any
+    fn lst_label(source: &str) -> Option<String> {
+        let first = source.chars().next()?;
+        if !(first.is_ascii_alphabetic() || first == '_') {
+            return None;
+        }
+
+        let mut words = source.split_whitespace();
+        let label = words.next()?.trim_end_matches(':');
+        let next = words.next().unwrap_or("").to_ascii_uppercase();
+
+        if DIRECTIVES.contains(&label.to_ascii_uppercase().as_str())
+            || EQUATES.contains(&next.as_str())
+        {
+            return None;
+        }
+
+        Some(label.to_string())
+    }
+
+    /// File named by an include directive, if any
+    fn lst_include(source: &str) -> Option<String> {
+        let mut words = source.split_whitespace();
+        let directive = words.next()?.to_ascii_lowercase();
+        if directive != "#include" && directive != "include" {
+            return None;
+        }
+
+        let name = words.next()?.trim_matches(|c| c == '<' || c == '>' || c == '"');
+        Some(name.to_string())
+    }
+
+    /// Parse an MPLINK or XC8 map file
+    pub fn parse_map(content: &str) -> Self {
+        let mut table = Self::new();
+        let mut in_xc8_symbols = false;
+
+        for line in content.lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+
+            if line.trim() == "Symbol Table" {
+                in_xc8_symbols = true;
+                continue;
+            }
+
+            match tokens.as_slice() {
+                // MPLINK: "main   0x000004   program   static   main.asm"
+                [name, address, "program", ..] => {
+                    if let Some(address) = address.strip_prefix("0x")
+                        .and_then(|a| u16::from_str_radix(a, 16).ok())
+                    {
+                        table.add_label(name, address);
+                    }
+                }
+                // XC8: "_main   maintext   07F0"
+                [name, psect, address] if in_xc8_symbols => {
+                    let is_code = psect.ends_with("text")
+                        || matches!(*psect, "init" | "reset_vec" | "intentry");
+                    if is_code && let Ok(address) = u16::from_str_radix(address, 16) {
+                        table.add_label(name, address);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        table
+    }
+
+    /// Parse an MPASM/gpasm COD file
+    ///
+    /// Labels come from the long symbol table and source locations from
+    /// the line table; where several lines map to one address (macro
+    /// expansions), the first is kept.
+    pub fn parse_cod(data: &[u8]) -> Result<Self, SymbolError> {
+        let directory = data.get(..COD_BLOCK_SIZE)
+            .ok_or(SymbolError::CodTooShort)?;
+        let blocks = |offset: usize| -> Result<Vec<&[u8]>, SymbolError> {
+            let first = u16::from_le_bytes([directory[offset], directory[offset + 1]]) as usize;
+            let last = u16::from_le_bytes([directory[offset + 2], directory[offset + 3]]) as usize;
+            if first == 0 {
+                return Ok(Vec::new());
+            }
+            (first..=last)
+                .map(|block| data.get(block * COD_BLOCK_SIZE..(block + 1) * COD_BLOCK_SIZE)
+                    .ok_or(SymbolError::CodBlockPastEnd(block)))
+                .collect()
+        };
+
+        let files: Vec<String> = blocks(COD_DIR_NAMTAB)?.iter()
+            .flat_map(|block| block.chunks(COD_FILE_SIZE))
+            .map(|slot| {
+                let len = (slot[0] as usize).min(slot.len() - 1);
+                String::from_utf8_lossy(&slot[1..1 + len]).into_owned()
+            })
+            .collect();
+
+        let mut table = Self::new();
+        for block in blocks(COD_DIR_LSYMTAB)? {
+            let mut offset = 0;
+            while let Some(&len) = block.get(offset) && len != 0 {
+                let len = len as usize;
+                let entry = block.get(offset + 1..offset + 1 + len + COD_LSYMBOL_EXTRA)
+                    .ok_or(SymbolError::CodTruncatedSymbol)?;
+                let kind = u16::from_le_bytes([entry[len], entry[len + 1]]);
+                let value = u32::from_be_bytes([entry[len + 2], entry[len + 3], entry[len + 4], entry[len + 5]]);
+                if kind == COD_ST_ADDRESS && let Ok(address) = u16::try_from(value) {
+                    table.add_label(&String::from_utf8_lossy(&entry[..len]), address);
+                }
+                offset += 1 + len + COD_LSYMBOL_EXTRA;
+            }
+        }
+
+        for block in blocks(COD_DIR_LSTTAB)? {
+            for entry in block.chunks_exact(COD_LINE_SYM_SIZE) {
+                let line = u16::from_le_bytes([entry[2], entry[3]]) as usize;
+                let address = u16::from_le_bytes([entry[4], entry[5]]);
+                // Unused entries are zero-filled; listing lines start at 1
+                let Some(file) = files.get(entry[0] as usize).filter(|_| line != 0) else {
+                    continue;
+                };
+                if table.location_of(address).is_none() {
+                    table.add_line(address, file, line);
+                }
+            }
+        }
+
+        Ok(table)
+    }
+
+    /// Add a label; the first label added for an address is its primary name
+    pub fn add_label(&mut self, name: &str, address: u16) {
+        self.addresses.insert(name.to_string(), address);
+        self.labels.entry(address).or_insert_with(|| name.to_string());
+    }
+
+    /// Record the source location of the instruction at `address`
+    pub fn add_line(&mut self, address: u16, file: &str, line: usize) {
+        self.lines.insert(address, SourceLocation { file: file.to_string(), line });
+    }
+
+    /// Record the text of a source line
+    pub fn add_source_line(&mut self, file: &str, line: usize, text: &str) {
+        self.sources.entry(file.to_string())
+            .or_default()
+            .insert(line, text.trim_end().to_string());
+    }
+
+    /// Primary label at `address`
+    pub fn label_at(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    /// Address of the label `name`
+    pub fn address_of(&self, name: &str) -> Option<u16> {
+        self.addresses.get(name).copied()
+    }
+
+    /// Source location of the instruction at `address`
+    pub fn location_of(&self, address: u16) -> Option<&SourceLocation> {
+        self.lines.get(&address)
+    }
+
+    /// Address of the first instruction generated for `file:line`
+    ///
+    /// Lines without code resolve to the next line that has some, so a
+    /// breakpoint on a comment or label stops at the following instruction.
+    pub fn address_of_line(&self, file: &str, line: usize) -> Option<u16> {
+        self.lines.iter()
+            .filter(|(_, loc)| same_file(&loc.file, file) && loc.line >= line)
+            .min_by_key(|&(&address, loc)| (loc.line, address))
+            .map(|(&address, _)| address)
+    }
+
+    /// Text of `file:line`, if it appeared in the listing
+    pub fn source_line(&self, file: &str, line: usize) -> Option<&str> {
+        self.sources.iter()
+            .find(|(name, _)| same_file(name, file))
+            .and_then(|(_, lines)| lines.get(&line))
+            .map(String::as_str)
+    }
+
+    /// Names of all files with source text
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.sources.keys().map(String::as_str)
+    }
+
+    /// All labelled addresses in address order
+    pub fn labels(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.labels.iter().map(|(&a, n)| (a, n.as_str()))
+    }
+
+    /// Number of labels known
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    /// Check if no labels or line information are loaded
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty() && self.lines.is_empty()
+    }
+
+    /// Remove all symbols
+    pub fn clear(&mut self) {
+        self.labels.clear();
+        self.addresses.clear();
+        self.lines.clear();
+        self.sources.clear();
+    }
+
+    /// Store labels, line information and source text in a state record
+    ///
+    /// File names are numbered so keys stay free of spaces and `=`; source
+    /// text is prefixed with `|` to keep its indentation.
+    pub fn save_state(&self, record: &mut StateRecord) {
+        let files: BTreeSet<&str> = self.lines.values()
+            .map(|loc| loc.file.as_str())
+            .chain(self.files())
+            .collect();
+        let files: Vec<&str> = files.into_iter().collect();
+        let file_index = |name: &str| files.iter().position(|&f| f == name).unwrap_or(0);
+
+        for (index, file) in files.iter().enumerate() {
+            record.set(&format!("symbols.file.{}", index), file);
+        }
+        for (&address, name) in &self.labels {
+            record.set(&format!("symbols.primary.{:04X}", address), name);
+        }
+        for (name, &address) in &self.addresses {
+            record.set(&format!("symbols.label.{}", name), format!("{:04X}", address));
+        }
+        for (&address, loc) in &self.lines {
+            record.set(&format!("symbols.line.{:04X}", address), format!("{}:{}", file_index(&loc.file), loc.line));
+        }
+        for (file, lines) in &self.sources {
+            for (line, text) in lines {
+                record.set(&format!("symbols.source.{}.{}", file_index(file), line), format!("|{}", text));
+            }
+        }
+    }
+
+    /// Restore symbols saved with `save_state`, replacing any present
+    ///
+    /// A record without symbol fields leaves the table empty.
+    pub fn load_state(&mut self, record: &StateRecord) -> Result<(), StateError> {
+        self.clear();
+        let invalid = |key: &str, value: &str| invalid_value(&format!("symbols.{}", key), value);
+        let address = |key: &str, text: &str| u16::from_str_radix(text, 16).map_err(|_| invalid(key, text));
+
+        let mut files = HashMap::new();
+        for (index, name) in record.fields_with_prefix("symbols.file.") {
+            files.insert(index.to_string(), name.to_string());
+        }
+        let file = |key: &str, index: &str| files.get(index).cloned().ok_or_else(|| invalid(key, index));
+
+        for (key, name) in record.fields_with_prefix("symbols.primary.") {
+            self.labels.insert(address(key, key)?, name.to_string());
+        }
+        for (name, value) in record.fields_with_prefix("symbols.label.") {
+            self.addresses.insert(name.to_string(), address(name, value)?);
+        }
+        for (key, value) in record.fields_with_prefix("symbols.line.") {
+            let (index, line) = value.split_once(':').ok_or_else(|| invalid(key, value))?;
+            let line = line.parse().map_err(|_| invalid(key, value))?;
+            self.lines.insert(address(key, key)?, SourceLocation { file: file(key, index)?, line });
+        }
+        for (key, text) in record.fields_with_prefix("symbols.source.") {
+            let (index, line) = key.split_once('.').ok_or_else(|| invalid(key, text))?;
+            let line = line.parse().map_err(|_| invalid(key, text))?;
+            self.sources.entry(file(key, index)?)
+                .or_default()
+                .insert(line, text.strip_prefix('|').unwrap_or(text).to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Compare file names ignoring case and any directory part
+fn same_file(a: &str, b: &str) -> bool {
+    let name = |path: &str| -> String {
+        Path::new(path).file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(path)
+            .to_ascii_lowercase()
+    };
+    name(a) == name(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTING: &str = "\
+MPASM 5.51                     BLINK.ASM   1-1-2024  12:00:00         PAGE  1
+
+
+LOC  OBJECT CODE     LINE SOURCE TEXT
+  VALUE
+
+                      00001         LIST    P=12F629
+  00000020            00002 COUNT   EQU     0x20
+                      00003         ORG     0x000
+0000   1683           00004         BSF     STATUS,RP0
+                      00005 main
+0001   3001           00006         MOVLW   0x01
+0002   2802           00007 loop:   GOTO    loop
+                      00008         END
+
+SYMBOL TABLE
+  LABEL                             VALUE
+
+COUNT                             00000020
+";
+
+    #[test]
+    fn test_parse_lst() {
+        let table = SymbolTable::parse_lst(LISTING, "default.asm");
+
+        assert_eq!(table.address_of("main"), Some(0x0001));
+        assert_eq!(table.address_of("loop"), Some(0x0002));
+        assert_eq!(table.address_of("COUNT"), None);
+        assert_eq!(table.label_at(0x0002), Some("loop"));
+
+        let location = table.location_of(0x0001).unwrap();
+        assert_eq!(location.file, "BLINK.ASM");
+        assert_eq!(location.line, 6);
+    }
+
+    #[test]
+    fn test_source_lines_and_includes() {
+        let listing = "\
+                      00001 #include <p12f629.inc>
+                      00001         LIST
+                      00140         LIST
+                      00002         ORG     0x000
+0000   1683           00003 start   BSF     STATUS,RP0
+                      00004 ; comment
+0001   0008           00005         RETURN
+";
+        let table = SymbolTable::parse_lst(listing, "main.asm");
+
+        assert_eq!(table.source_line("main.asm", 4), Some("; comment"));
+        assert_eq!(table.source_line("p12f629.inc", 140), Some("        LIST"));
+        assert_eq!(table.location_of(0x0000).unwrap().file, "main.asm");
+
+        assert_eq!(table.address_of_line("MAIN.ASM", 3), Some(0x0000));
+        assert_eq!(table.address_of_line("main.asm", 4), Some(0x0001));
+        assert_eq!(table.address_of_line("main.asm", 6), None);
+    }
+
+    #[test]
+    fn test_parse_map() {
+        let mplink = "\
+                    Symbols - Sorted by Name
+                     Name    Address   Location    Storage File
+                ---------  ---------  ---------  --------- ---------
+                    delay   0x00000b    program     static main.asm
+                    count   0x000020       data     static main.asm
+";
+        let table = SymbolTable::parse_map(mplink);
+        assert_eq!(table.address_of("delay"), Some(0x000B));
+        assert_eq!(table.address_of("count"), None);
+
+        let xc8 = "\
+Symbol Table
+
+_main                maintext     07F0
+_counter             bssCOMMON    0070
+";
+        let table = SymbolTable::parse_map(xc8);
+        assert_eq!(table.address_of("_main"), Some(0x07F0));
+        assert_eq!(table.len(), 1);
+    }
+
+    /// A COD file with the directory, name, line and long symbol tables
+    /// in blocks 0-3
+    fn cod_file() -> Vec<u8> {
+        let mut data = vec![0u8; COD_BLOCK_SIZE * 4];
+        for (offset, block) in [(COD_DIR_NAMTAB, 1u16), (COD_DIR_LSTTAB, 2), (COD_DIR_LSYMTAB, 3)] {
+            data[offset..offset + 2].copy_from_slice(&block.to_le_bytes());
+            data[offset + 2..offset + 4].copy_from_slice(&block.to_le_bytes());
+        }
+
+        for (slot, name) in ["BLINK.ASM", "P12F629.INC"].iter().enumerate() {
+            let at = COD_BLOCK_SIZE + slot * COD_FILE_SIZE;
+            data[at] = name.len() as u8;
+            data[at + 1..at + 1 + name.len()].copy_from_slice(name.as_bytes());
+        }
+
+        // (file, line, address); the second entry for 0x0002 comes from a macro
+        let lines = [(0u8, 4u16, 0x0000u16), (0, 6, 0x0001), (0, 7, 0x0002), (1, 90, 0x0002)];
+        for (index, (file, line, address)) in lines.into_iter().enumerate() {
+            let at = COD_BLOCK_SIZE * 2 + index * COD_LINE_SYM_SIZE;
+            data[at] = file;
+            data[at + 2..at + 4].copy_from_slice(&line.to_le_bytes());
+            data[at + 4..at + 6].copy_from_slice(&address.to_le_bytes());
+        }
+
+        let mut at = COD_BLOCK_SIZE * 3;
+        for (name, kind, value) in [("main", COD_ST_ADDRESS, 0x0001u32), ("COUNT", 47, 0x20), ("loop", COD_ST_ADDRESS, 0x0002)] {
+            data[at] = name.len() as u8;
+            data[at + 1..at + 1 + name.len()].copy_from_slice(name.as_bytes());
+            at += 1 + name.len();
+            data[at..at + 2].copy_from_slice(&kind.to_le_bytes());
+            data[at + 2..at + 6].copy_from_slice(&value.to_be_bytes());
+            at += COD_LSYMBOL_EXTRA;
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_cod() {
+        let table = SymbolTable::parse_cod(&cod_file()).unwrap();
+
+        assert_eq!(table.address_of("main"), Some(0x0001));
+        assert_eq!(table.label_at(0x0002), Some("loop"));
+        assert_eq!(table.address_of("COUNT"), None);
+        assert_eq!(table.len(), 2);
+
+        let location = table.location_of(0x0002).unwrap();
+        assert_eq!((location.file.as_str(), location.line), ("BLINK.ASM", 7));
+        assert_eq!(table.address_of_line("blink.asm", 5), Some(0x0001));
+
+        // A table pointing past the end of the file is an error
+        let mut truncated = cod_file();
+        truncated.truncate(COD_BLOCK_SIZE * 3);
+        assert!(SymbolTable::parse_cod(&truncated).is_err());
+        assert!(SymbolTable::parse_cod(&[0; 16]).is_err());
+    }
+}
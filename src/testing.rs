@@ -0,0 +1,429 @@
+//! Firmware test harness
+//!
+//! `Harness` wraps a `Simulator` with a fluent API for running firmware
+//! under `cargo test`: load a program, apply stimuli, run within a cycle
+//! budget and assert on registers, memory, pins and interrupts. A failed
+//! assertion panics with a report of the machine state and the last
+//! executed instructions, pointing at the caller's line.
+//!
+//! ```no_run
+//! use pic_simulator::testing::Harness;
+//!
+//! Harness::new()
+//!     .load_hex_file("blink.hex")
+//!     .stimulus("GP3: low at cycle 1000 for 200 cycles")
+//!     .run_cycles(50_000)
+//!     .assert_register("TRISIO", 0x3E)
+//!     .assert_pin_period(0, 10_000, 100)
+//!     .assert_interrupt_count(5);
+//! ```
+
+use std::cell::Cell;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::Debugger;
+use crate::cpu::registers;
+use crate::events::SimEvent;
+use crate::gpio::pin_label;
+use crate::simulator::{RunOutcome, Simulator};
+use crate::stimulus::Stimulus;
+use crate::watchpoint::parse_register;
+
+/// Cycle budget for `run_until*` when none is set
+pub const DEFAULT_BUDGET: u64 = 1_000_000;
+
+/// Number of trace entries shown in failure reports
+const REPORT_TRACE_LEN: usize = 8;
+
+/// Simulator wrapper with chainable run and assert methods
+pub struct Harness {
+    sim: Simulator,
+    budget: u64,
+    interrupts: Rc<Cell<u64>>,
+}
+
+impl Harness {
+    /// Create a harness around a freshly reset simulator
+    pub fn new() -> Self {
+        let mut sim = Simulator::new();
+        sim.reset();
+
+        let interrupts = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&interrupts);
+        sim.subscribe(move |event| {
+            if let SimEvent::InterruptEntered { .. } = event {
+                counter.set(counter.get() + 1);
+            }
+        });
+
+        Self {
+            sim,
+            budget: DEFAULT_BUDGET,
+            interrupts,
+        }
+    }
+
+    /// Get the simulator
+    pub fn simulator(&self) -> &Simulator {
+        &self.sim
+    }
+
+    /// Get mutable reference to the simulator
+    pub fn simulator_mut(&mut self) -> &mut Simulator {
+        &mut self.sim
+    }
+
+    /// Load program words at address 0
+    pub fn load_program(&mut self, program: &[u16]) -> &mut Self {
+        self.sim.load_program(program);
+        self
+    }
+
+    /// Load an Intel HEX or S-record file
+    #[track_caller]
+    pub fn load_hex_file<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        let path = path.as_ref();
+        if let Err(e) = self.sim.load_firmware_file(path) {
+            self.fail(format!("failed to load {}: {}", path.display(), e));
+        }
+        self
+    }
+
+    /// Load Intel HEX text
+    #[track_caller]
+    pub fn load_hex_str(&mut self, content: &str) -> &mut Self {
+        if let Err(e) = self.sim.load_hex_string(content) {
+            self.fail(format!("failed to load HEX: {}", e));
+        }
+        self
+    }
+
+    /// Add pin stimulus rules (see `stimulus` module for the syntax)
+    #[track_caller]
+    pub fn stimulus(&mut self, script: &str) -> &mut Self {
+        match Stimulus::parse(script) {
+            Ok(parsed) => {
+                let mut stimulus = self.sim.stimulus().clone();
+                for rule in parsed.rules() {
+                    stimulus.add_rule(*rule);
+                }
+                self.sim.set_stimulus(stimulus);
+            }
+            Err(e) => self.fail(format!("invalid stimulus: {}", e)),
+        }
+        self
+    }
+
+    /// Drive an input pin now
+    pub fn set_pin(&mut self, pin: u8, level: bool) -> &mut Self {
+        self.sim.set_external_pin(pin, level);
+        self
+    }
+
+    /// Set the cycle budget for `run_until*`
+    pub fn budget(&mut self, cycles: u64) -> &mut Self {
+        self.budget = cycles;
+        self
+    }
+
+    /// Run for exactly `cycles` cycles (or one instruction more)
+    #[track_caller]
+    pub fn run_cycles(&mut self, cycles: u64) -> &mut Self {
+        if let Err(e) = self.sim.run_n_cycles(cycles) {
+            self.fail(format!("execution failed: {}", e));
+        }
+        self
+    }
+
+    /// Run `count` instructions
+    #[track_caller]
+    pub fn run_instructions(&mut self, count: u64) -> &mut Self {
+        if let Err(e) = self.sim.run_n_instructions(count) {
+            self.fail(format!("execution failed: {}", e));
+        }
+        self
+    }
+
+    /// Run until the PC reaches a label or address, within the budget
+    #[track_caller]
+    pub fn run_until_pc(&mut self, target: &str) -> &mut Self {
+        let address = match self.code_address(target) {
+            Some(address) => address,
+            None => self.fail(format!("unknown program address '{}'", target)),
+        };
+        let description = format!("PC reaches {}", target);
+        self.run_until(&description, move |sim| sim.cpu().get_pc() == address)
+    }
+
+    /// Run until `condition` holds, failing if the budget runs out first
+    #[track_caller]
+    pub fn run_until<F>(&mut self, description: &str, condition: F) -> &mut Self
+    where
+        F: FnMut(&Simulator) -> bool,
+    {
+        match self.sim.run_until(condition, self.budget) {
+            RunOutcome::PredicateMet => {}
+            RunOutcome::CycleLimit => {
+                self.fail(format!("'{}' did not happen within {} cycles", description, self.budget));
+            }
+            RunOutcome::Error(e) => self.fail(format!("execution failed: {}", e)),
+            outcome => self.fail(format!("run stopped early: {:?}", outcome)),
+        }
+        self
+    }
+
+    /// Assert a register value; `register` is an SFR name or hex address
+    #[track_caller]
+    pub fn assert_register(&mut self, register: &str, expected: u8) -> &mut Self {
+        let address = match parse_register(register) {
+            Some(address) => address,
+            None => self.fail(format!("unknown register '{}'", register)),
+        };
+        let actual = self.sim.cpu().read_register(address);
+        if actual != expected {
+            self.fail(format!("{} = 0x{:02X}, expected 0x{:02X}", register, actual, expected));
+        }
+        self
+    }
+
+    /// Assert a single register bit
+    #[track_caller]
+    pub fn assert_bit(&mut self, register: &str, bit: u8, expected: bool) -> &mut Self {
+        let address = match parse_register(register) {
+            Some(address) => address,
+            None => self.fail(format!("unknown register '{}'", register)),
+        };
+        let actual = self.sim.cpu().read_register(address) & (1 << bit) != 0;
+        if actual != expected {
+            let name = registers::bit_name(address, bit)
+                .map(String::from)
+                .unwrap_or_else(|| format!("bit {}", bit));
+            self.fail(format!("{}.{} = {}, expected {}", register, name, actual as u8, expected as u8));
+        }
+        self
+    }
+
+    /// Assert the W register
+    #[track_caller]
+    pub fn assert_w(&mut self, expected: u8) -> &mut Self {
+        let actual = self.sim.cpu().read_w();
+        if actual != expected {
+            self.fail(format!("W = 0x{:02X}, expected 0x{:02X}", actual, expected));
+        }
+        self
+    }
+
+    /// Assert the PC
+    #[track_caller]
+    pub fn assert_pc(&mut self, expected: u16) -> &mut Self {
+        let actual = self.sim.cpu().get_pc();
+        if actual != expected {
+            self.fail(format!("PC = 0x{:04X}, expected 0x{:04X}", actual, expected));
+        }
+        self
+    }
+
+    /// Assert consecutive data memory bytes starting at `start`
+    #[track_caller]
+    pub fn assert_memory(&mut self, start: u8, expected: &[u8]) -> &mut Self {
+        let actual: Vec<u8> = (0..expected.len())
+            .map(|i| self.sim.cpu().read_register(start.wrapping_add(i as u8)))
+            .collect();
+        if let Some(i) = actual.iter().zip(expected).position(|(a, e)| a != e) {
+            self.fail(format!(
+                "memory at 0x{:02X} = {}, expected {} (first difference at 0x{:02X})",
+                start, hex_bytes(&actual), hex_bytes(expected), start as usize + i,
+            ));
+        }
+        self
+    }
+
+    /// Assert consecutive EEPROM bytes starting at `start`
+    #[track_caller]
+    pub fn assert_eeprom(&mut self, start: u8, expected: &[u8]) -> &mut Self {
+        let actual: Vec<u8> = (0..expected.len())
+            .map(|i| self.sim.cpu().memory().read_eeprom(start.wrapping_add(i as u8)))
+            .collect();
+        if actual != expected {
+            self.fail(format!("EEPROM at 0x{:02X} = {}, expected {}",
+                start, hex_bytes(&actual), hex_bytes(expected)));
+        }
+        self
+    }
+
+    /// Assert the current level of a pin
+    #[track_caller]
+    pub fn assert_pin(&mut self, pin: u8, expected: bool) -> &mut Self {
+        let actual = self.sim.cpu().gpio().pin_level(pin);
+        if actual != expected {
+            self.fail(format!("{} is {}, expected {}", pin_label(pin), level_name(actual), level_name(expected)));
+        }
+        self
+    }
+
+    /// Assert that a pin changed level at least `min` times
+    #[track_caller]
+    pub fn assert_pin_toggles(&mut self, pin: u8, min: usize) -> &mut Self {
+        let toggles = self.sim.logic_analyzer().pin_transitions(pin).count();
+        if toggles < min {
+            self.fail(format!("{} changed {} times, expected at least {}", pin_label(pin), toggles, min));
+        }
+        self
+    }
+
+    /// Assert the average period of a pin within `tolerance` cycles
+    #[track_caller]
+    pub fn assert_pin_period(&mut self, pin: u8, period: u64, tolerance: u64) -> &mut Self {
+        match self.sim.logic_analyzer().average_period(pin) {
+            Some(actual) if (actual - period as f64).abs() <= tolerance as f64 => {}
+            Some(actual) => self.fail(format!(
+                "{} period is {:.1} cycles, expected {} ± {}", pin_label(pin), actual, period, tolerance,
+            )),
+            None => self.fail(format!("{} has no complete period", pin_label(pin))),
+        }
+        self
+    }
+
+    /// Assert the width of the last `level` pulse on a pin
+    #[track_caller]
+    pub fn assert_pulse_width(&mut self, pin: u8, level: bool, width: u64, tolerance: u64) -> &mut Self {
+        match self.sim.logic_analyzer().last_pulse_width(pin, level) {
+            Some(actual) if actual.abs_diff(width) <= tolerance => {}
+            Some(actual) => self.fail(format!(
+                "{} {} pulse is {} cycles, expected {} ± {}",
+                pin_label(pin), level_name(level), actual, width, tolerance,
+            )),
+            None => self.fail(format!("{} has no complete {} pulse", pin_label(pin), level_name(level))),
+        }
+        self
+    }
+
+    /// Number of interrupts taken since the harness was created
+    pub fn interrupt_count(&self) -> u64 {
+        self.interrupts.get()
+    }
+
+    /// Assert the number of interrupts taken
+    #[track_caller]
+    pub fn assert_interrupt_count(&mut self, expected: u64) -> &mut Self {
+        let actual = self.interrupt_count();
+        if actual != expected {
+            self.fail(format!("{} interrupts taken, expected {}", actual, expected));
+        }
+        self
+    }
+
+    /// Assert the elapsed cycle count is within `min..=max`
+    #[track_caller]
+    pub fn assert_cycles_between(&mut self, min: u64, max: u64) -> &mut Self {
+        let cycles = self.sim.stats().cycles_elapsed;
+        if cycles < min || cycles > max {
+            self.fail(format!("{} cycles elapsed, expected {}..={}", cycles, min, max));
+        }
+        self
+    }
+
+    /// Machine state and recent instructions, as shown on failure
+    pub fn report(&self) -> String {
+        let cpu = self.sim.cpu();
+        let mut out = String::new();
+        let _ = writeln!(out, "  PC = 0x{:04X}  W = 0x{:02X}  STATUS = 0x{:02X}  GPIO = 0b{:06b}",
+            cpu.get_pc(), cpu.read_w(), cpu.read_register(registers::STATUS), cpu.gpio().read_gpio());
+        let _ = writeln!(out, "  cycles = {}  instructions = {}  interrupts = {}",
+            self.sim.stats().cycles_elapsed, self.sim.stats().instructions_executed, self.interrupt_count());
+
+        let trace = self.sim.trace();
+        if !trace.is_empty() {
+            let _ = writeln!(out, "  last instructions:");
+            let skip = trace.len().saturating_sub(REPORT_TRACE_LEN);
+            for entry in trace.iter().skip(skip) {
+                let _ = writeln!(out, "    {:>8}  0x{:04X}: {}",
+                    entry.cycle, entry.pc, Debugger::disassemble_with_symbols(self.sim.device().core(), entry.word, self.sim.symbols()));
+            }
+        }
+        out
+    }
+
+    #[track_caller]
+    fn fail(&self, message: String) -> ! {
+        panic!("firmware assertion failed: {}\n{}", message, self.report());
+    }
+
+    fn code_address(&self, s: &str) -> Option<u16> {
+        self.sim.symbols().address_of(s).or_else(|| {
+            let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+            u16::from_str_radix(hex, 16).ok()
+        })
+    }
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let text: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("[{}]", text.join(" "))
+}
+
+fn level_name(level: bool) -> &'static str {
+    if level { "high" } else { "low" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// GP0 output toggled every 4 cycles
+    const BLINK: [u16; 7] = [
+        0x1683,  // 0x000: BSF STATUS, RP0
+        0x1005,  // 0x001: BCF TRISIO, 0
+        0x1283,  // 0x002: BCF STATUS, RP0
+        0x3001,  // 0x003: MOVLW 0x01
+        0x0685,  // 0x004: XORWF GPIO, F
+        0x0AA0,  // 0x005: INCF 0x20, F
+        0x2804,  // 0x006: GOTO 0x004
+    ];
+
+    #[test]
+    fn test_harness_passes() {
+        Harness::new()
+            .load_program(&BLINK)
+            .run_until_pc("0x005")
+            .assert_pin(0, true)
+            .assert_register("TRISIO", 0x3E)
+            .assert_bit("STATUS", 5, false)
+            .assert_w(0x01)
+            .run_cycles(400)
+            .assert_pin_toggles(0, 50)
+            .assert_pin_period(0, 8, 0)
+            .assert_pulse_width(0, true, 4, 0)
+            .assert_interrupt_count(0);
+    }
+
+    #[test]
+    fn test_failure_report() {
+        let result = std::panic::catch_unwind(|| {
+            Harness::new()
+                .load_program(&BLINK)
+                .run_instructions(6)
+                .assert_memory(0x20, &[0x02]);
+        });
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("memory at 0x20 = [01], expected [02]"));
+        assert!(message.contains("0x0005: INCF"));
+    }
+
+    #[test]
+    #[should_panic(expected = "did not happen within 100 cycles")]
+    fn test_budget() {
+        Harness::new()
+            .load_program(&BLINK)
+            .budget(100)
+            .run_until_pc("0x003F");
+    }
+}
@@ -1,14 +1,18 @@
-/// PIC12F629/675 Timer Implementation
-/// 
-/// Reference: 
-/// - Section 4.0 - Timer0 Module (Page 19-22)
-/// - Section 5.0 - Timer1 Module (Page 23-28)
-/// 
-/// Timer0: 8-bit timer/counter with prescaler
-/// Timer1: 16-bit timer/counter
+//! PIC12F629/675 Timer Implementation
+//! 
+//! Reference: 
+//! - Section 4.0 - Timer0 Module (Page 19-22)
+//! - Section 5.0 - Timer1 Module (Page 23-28)
+//! 
+//! Timer0: 8-bit timer/counter with prescaler
+//! Timer1: 16-bit timer/counter
+
+use crate::error::StateError;
+use crate::state::StateRecord;
 
 /// Timer0 configuration and state
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Timer0 {
     /// Timer0 counter value (8-bit)
     counter: u8,
@@ -117,10 +121,63 @@ impl Timer0 {
         }
     }
     
+    /// Number of ticks until the one that overflows, or None if the timer
+    /// is clocked externally
+    pub fn cycles_until_overflow(&self) -> Option<u64> {
+        if self.clock_source_external {
+            return None;
+        }
+        
+        let increments = 0x100 - self.counter as u64;
+        if self.prescaler_assigned_to_wdt {
+            Some(increments)
+        } else {
+            let rate = self.prescaler_rate as u64;
+            Some((increments - 1) * rate + (rate - self.prescaler as u64))
+        }
+    }
+    
+    /// Apply `cycles` ticks at once; must be fewer than `cycles_until_overflow`
+    pub fn advance(&mut self, cycles: u64) {
+        if self.clock_source_external {
+            return;
+        }
+        
+        if self.prescaler_assigned_to_wdt {
+            self.counter = self.counter.wrapping_add(cycles as u8);
+        } else {
+            let rate = self.prescaler_rate as u64;
+            let total = self.prescaler as u64 + cycles;
+            self.counter = self.counter.wrapping_add((total / rate) as u8);
+            self.prescaler = (total % rate) as u16;
+        }
+    }
+    
     /// Get current prescaler value (for debugging)
     pub fn get_prescaler(&self) -> u16 {
         self.prescaler
     }
+
+    /// Save counter, prescaler and configuration
+    pub fn save_state(&self, record: &mut StateRecord) {
+        record.set("timer0.counter", self.counter);
+        record.set("timer0.prescaler", self.prescaler);
+        record.set("timer0.prescaler_assigned_to_wdt", self.prescaler_assigned_to_wdt);
+        record.set("timer0.prescaler_rate", self.prescaler_rate);
+        record.set("timer0.clock_source_external", self.clock_source_external);
+        record.set("timer0.edge_select", self.edge_select);
+    }
+    
+    /// Restore state saved with `save_state`
+    pub fn load_state(&mut self, record: &StateRecord) -> Result<(), StateError> {
+        self.counter = record.get("timer0.counter")?;
+        self.prescaler = record.get("timer0.prescaler")?;
+        self.prescaler_assigned_to_wdt = record.get("timer0.prescaler_assigned_to_wdt")?;
+        self.prescaler_rate = record.get("timer0.prescaler_rate")?;
+        self.clock_source_external = record.get("timer0.clock_source_external")?;
+        self.edge_select = record.get("timer0.edge_select")?;
+        Ok(())
+    }
 }
 
 impl Default for Timer0 {
@@ -131,6 +188,7 @@ impl Default for Timer0 {
 
 /// Timer1 configuration and state
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Timer1 {
     /// Timer1 counter value (16-bit)
     counter: u16,
@@ -251,6 +309,30 @@ impl Timer1 {
         false
     }
     
+    /// Number of ticks until the one that overflows, or None if the timer
+    /// is stopped or clocked externally
+    pub fn cycles_until_overflow(&self) -> Option<u64> {
+        if !self.enabled || self.clock_source_external {
+            return None;
+        }
+        
+        let increments = 0x10000 - self.counter as u64;
+        let rate = self.prescaler_rate as u64;
+        Some((increments - 1) * rate + (rate - self.prescaler as u64))
+    }
+    
+    /// Apply `cycles` ticks at once; must be fewer than `cycles_until_overflow`
+    pub fn advance(&mut self, cycles: u64) {
+        if !self.enabled || self.clock_source_external {
+            return;
+        }
+        
+        let rate = self.prescaler_rate as u64;
+        let total = self.prescaler as u64 + cycles;
+        self.counter = self.counter.wrapping_add((total / rate) as u16);
+        self.prescaler = (total % rate) as u16;
+    }
+    
     /// Get current counter value (for debugging)
     pub fn get_counter(&self) -> u16 {
         self.counter
@@ -260,6 +342,29 @@ impl Timer1 {
     pub fn is_enabled(&self) -> bool {
         self.enabled
     }
+
+    /// Save counter, prescaler and configuration
+    pub fn save_state(&self, record: &mut StateRecord) {
+        record.set("timer1.counter", self.counter);
+        record.set("timer1.enabled", self.enabled);
+        record.set("timer1.clock_source_external", self.clock_source_external);
+        record.set("timer1.prescaler_rate", self.prescaler_rate);
+        record.set("timer1.prescaler", self.prescaler);
+        record.set("timer1.oscillator_enabled", self.oscillator_enabled);
+        record.set("timer1.sync_external_clock", self.sync_external_clock);
+    }
+    
+    /// Restore state saved with `save_state`
+    pub fn load_state(&mut self, record: &StateRecord) -> Result<(), StateError> {
+        self.counter = record.get("timer1.counter")?;
+        self.enabled = record.get("timer1.enabled")?;
+        self.clock_source_external = record.get("timer1.clock_source_external")?;
+        self.prescaler_rate = record.get("timer1.prescaler_rate")?;
+        self.prescaler = record.get("timer1.prescaler")?;
+        self.oscillator_enabled = record.get("timer1.oscillator_enabled")?;
+        self.sync_external_clock = record.get("timer1.sync_external_clock")?;
+        Ok(())
+    }
 }
 
 impl Default for Timer1 {
@@ -270,6 +375,7 @@ impl Default for Timer1 {
 
 /// Timer controller managing both Timer0 and Timer1
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TimerController {
     pub timer0: Timer0,
     pub timer1: Timer1,
@@ -295,6 +401,25 @@ impl TimerController {
         let tmr1_overflow = self.timer1.tick();
         (tmr0_overflow, tmr1_overflow)
     }
+    
+    /// Advance both timers by `cycles` cycles, which must end before
+    /// either overflows
+    pub fn advance(&mut self, cycles: u64) {
+        self.timer0.advance(cycles);
+        self.timer1.advance(cycles);
+    }
+
+    /// Save both timers
+    pub fn save_state(&self, record: &mut StateRecord) {
+        self.timer0.save_state(record);
+        self.timer1.save_state(record);
+    }
+    
+    /// Restore state saved with `save_state`
+    pub fn load_state(&mut self, record: &StateRecord) -> Result<(), StateError> {
+        self.timer0.load_state(record)?;
+        self.timer1.load_state(record)
+    }
 }
 
 impl Default for TimerController {
@@ -433,4 +558,33 @@ mod tests {
         assert!(!tmr1.tick());
         assert_eq!(tmr1.get_counter(), 0x0000);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_advance_matches_ticks() {
+        let mut ticked = TimerController::new();
+        ticked.timer0.configure_from_option(0x02); // 1:8
+        ticked.timer1.configure_from_t1con(0x21); // on, 1:4
+        ticked.timer0.write_counter(0xF0);
+        ticked.timer1.write_high(0xFF);
+        let mut advanced = ticked.clone();
+        
+        assert_eq!(ticked.timer0.cycles_until_overflow(), Some(16 * 8));
+        assert_eq!(ticked.timer1.cycles_until_overflow(), Some(256 * 4));
+        for _ in 0..100 {
+            assert_eq!(ticked.tick(), (false, false));
+        }
+        advanced.advance(100);
+        assert_eq!(advanced.timer0.read_counter(), ticked.timer0.read_counter());
+        assert_eq!(advanced.timer0.get_prescaler(), ticked.timer0.get_prescaler());
+        assert_eq!(advanced.timer1.get_counter(), ticked.timer1.get_counter());
+        
+        // The overflow lands on the predicted tick
+        let remaining = advanced.timer0.cycles_until_overflow().unwrap();
+        assert_eq!(remaining, 28);
+        advanced.advance(remaining - 1);
+        assert_eq!(advanced.tick(), (true, false));
+        
+        ticked.timer1.configure_from_t1con(0x00);
+        assert_eq!(ticked.timer1.cycles_until_overflow(), None);
+    }
+}
@@ -0,0 +1,149 @@
+//! Execution trace ring buffer
+//!
+//! Keeps the most recently executed instructions together with the register
+//! state they produced, so the path that led to the current PC can be
+//! inspected after the fact.
+
+use std::collections::VecDeque;
+
+use crate::events::SimEvent;
+
+/// Default number of trace entries kept
+pub const DEFAULT_TRACE_CAPACITY: usize = 1024;
+
+/// A single executed instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Cycle count at which the instruction started
+    pub cycle: u64,
+
+    /// Address the instruction was fetched from
+    pub pc: u16,
+
+    /// Raw 14-bit instruction word
+    pub word: u16,
+
+    /// W register after execution
+    pub w: u8,
+
+    /// STATUS register after execution
+    pub status: u8,
+}
+
+/// Fixed-capacity ring buffer of executed instructions
+#[derive(Debug, Clone)]
+pub struct TraceBuffer {
+    entries: VecDeque<TraceEntry>,
+    capacity: usize,
+}
+
+impl TraceBuffer {
+    /// Create a trace buffer with the default capacity
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_TRACE_CAPACITY)
+    }
+
+    /// Create a trace buffer holding at most `capacity` entries
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Append an entry, dropping the oldest one when full
+    pub fn push(&mut self, entry: TraceEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Record an `InstructionExecuted` event; other events are ignored
+    pub fn on_event(&mut self, event: &SimEvent) {
+        if let SimEvent::InstructionExecuted { cycle, pc, word, w, status, .. } = *event {
+            self.push(TraceEntry { cycle, pc, word, w, status });
+        }
+    }
+
+    /// Remove all entries that started at or after `cycle`
+    /// Used when rewinding execution to an earlier point
+    pub fn truncate_from_cycle(&mut self, cycle: u64) {
+        while self.entries.back().is_some_and(|e| e.cycle >= cycle) {
+            self.entries.pop_back();
+        }
+    }
+
+    /// Remove all entries
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Iterate from oldest to newest
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &TraceEntry> {
+        self.entries.iter()
+    }
+
+    /// Most recently executed instruction
+    pub fn last(&self) -> Option<&TraceEntry> {
+        self.entries.back()
+    }
+
+    /// Number of entries currently held
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Maximum number of entries held
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl Default for TraceBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(cycle: u64) -> TraceEntry {
+        TraceEntry { cycle, pc: cycle as u16, word: 0, w: 0, status: 0 }
+    }
+
+    #[test]
+    fn test_ring_drops_oldest() {
+        let mut trace = TraceBuffer::with_capacity(3);
+        for cycle in 0..5 {
+            trace.push(entry(cycle));
+        }
+
+        assert_eq!(trace.len(), 3);
+        assert_eq!(trace.iter().next().unwrap().cycle, 2);
+        assert_eq!(trace.last().unwrap().cycle, 4);
+    }
+
+    #[test]
+    fn test_truncate_from_cycle() {
+        let mut trace = TraceBuffer::new();
+        for cycle in 0..10 {
+            trace.push(entry(cycle));
+        }
+
+        trace.truncate_from_cycle(6);
+        assert_eq!(trace.len(), 6);
+        assert_eq!(trace.last().unwrap().cycle, 5);
+    }
+}
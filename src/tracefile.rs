@@ -0,0 +1,149 @@
+//! Streaming instruction trace files
+//!
+//! `TraceFile` writes one line per executed instruction as the simulator
+//! reports it through its event hooks, so a long run can be traced to disk
+//! and analyzed offline instead of fitting in the in-memory `TraceBuffer`:
+//!
+//! ```text
+//!      cycle  pc      word  instruction
+//!          0  0x0000  3005  MOVLW 0x05          W 0x00->0x05
+//!          1  0x0001  00A0  MOVWF 0x20          [0x20] 0x00->0x05
+//! ```
+//!
+//! The register columns are only written when register deltas are asked
+//! for: W when it changed, then every register the instruction wrote.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::debugger::Debugger;
+use crate::events::SimEvent;
+use crate::instruction::Core;
+use crate::simulator::Simulator;
+use crate::symbols::SymbolTable;
+use crate::watchpoint;
+
+/// An instruction trace being written to a file
+pub struct TraceFile {
+    writer: BufWriter<File>,
+    /// Instruction set the words are decoded with
+    core: Core,
+    /// Labels for CALL/GOTO targets
+    symbols: SymbolTable,
+    /// Append W and register changes to each line
+    regs: bool,
+    /// W after the previous instruction
+    last_w: u8,
+    /// Register writes of the instruction being executed
+    writes: Vec<(u16, u8, u8)>,
+    lines: u64,
+    /// First write error; later events are ignored
+    error: Option<io::Error>,
+}
+
+impl TraceFile {
+    /// Create `path` and write the column header; labels and the starting
+    /// W value are taken from `simulator`
+    pub fn create<P: AsRef<Path>>(path: P, simulator: &Simulator, regs: bool) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{:>10}  {:<6}  {:<4}  instruction", "cycle", "pc", "word")?;
+        Ok(Self {
+            writer,
+            core: simulator.device().core(),
+            symbols: simulator.symbols().clone(),
+            regs,
+            last_w: simulator.cpu().read_w(),
+            writes: Vec::new(),
+            lines: 0,
+            error: None,
+        })
+    }
+
+    /// Record a simulator event; register writes arrive before the
+    /// instruction that made them
+    pub fn on_event(&mut self, event: &SimEvent) {
+        if self.error.is_some() {
+            return;
+        }
+        match *event {
+            SimEvent::MemoryWritten { addr, old, new } if self.regs && old != new => {
+                self.writes.push((addr, old, new));
+            }
+            SimEvent::InstructionExecuted { cycle, pc, word, w, .. } => {
+                if let Err(e) = self.write_line(cycle, pc, word, w) {
+                    self.error = Some(e);
+                }
+                self.writes.clear();
+                self.last_w = w;
+            }
+            _ => {}
+        }
+    }
+
+    fn write_line(&mut self, cycle: u64, pc: u16, word: u16, w: u8) -> io::Result<()> {
+        let asm = Debugger::disassemble_with_options(self.core, word, &self.symbols, true);
+        write!(self.writer, "{:>10}  0x{:04X}  {:04X}  {:<18}", cycle, pc, word, asm)?;
+        if self.regs {
+            if self.last_w != w {
+                write!(self.writer, "  W 0x{:02X}->0x{:02X}", self.last_w, w)?;
+            }
+            for &(addr, old, new) in &self.writes {
+                let name = match watchpoint::register_name(addr) {
+                    name if name.starts_with("0x") => format!("[{}]", name),
+                    name => name,
+                };
+                write!(self.writer, "  {} 0x{:02X}->0x{:02X}", name, old, new)?;
+            }
+        }
+        writeln!(self.writer)?;
+        self.lines += 1;
+        Ok(())
+    }
+
+    /// Instructions written so far
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+
+    /// Flush the file, returning the number of instructions traced or the
+    /// first write error
+    pub fn finish(mut self) -> io::Result<u64> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.writer.flush()?;
+        Ok(self.lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_trace_file() {
+        let path = std::env::temp_dir().join(format!("pic_trace_test_{}.txt", std::process::id()));
+        let mut sim = Simulator::new();
+        sim.reset();
+        sim.load_program(&[0x3005, 0x00A0, 0x0000]); // MOVLW 5; MOVWF 0x20; NOP
+
+        let trace = Rc::new(RefCell::new(TraceFile::create(&path, &sim, true).unwrap()));
+        let writer = Rc::clone(&trace);
+        let id = sim.subscribe(move |event| writer.borrow_mut().on_event(event));
+        sim.run_n_instructions(3).unwrap();
+        sim.unsubscribe(id);
+
+        let trace = Rc::try_unwrap(trace).ok().unwrap().into_inner();
+        assert_eq!(trace.finish().unwrap(), 3);
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].contains("0x0000  3005  MOVLW 0x05") && lines[1].ends_with("W 0x00->0x05"));
+        assert!(lines[2].contains("MOVWF 0x20") && lines[2].ends_with("[0x20] 0x00->0x05"));
+        assert!(lines[3].trim_end().ends_with("NOP"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
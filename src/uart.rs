@@ -0,0 +1,349 @@
+//! Bit-banged UART terminal
+//!
+//! `SoftUart` is a serial terminal wired to GPIO pins. It decodes 8N1
+//! frames the firmware bit-bangs on its RX pin and clocks queued
+//! characters into the firmware on its TX pin, both at a baud rate timed
+//! against the simulated clock. Lines idle high; TX is driven through the
+//! pin's external level, so the firmware reads it as an input.
+
+use std::any::Any;
+use std::collections::VecDeque;
+
+#[cfg(feature = "gui")]
+use eframe::egui;
+
+use crate::clock::Clock;
+use crate::component::Component;
+use crate::cpu::Cpu;
+use crate::gpio::PinState;
+
+/// Bits in an 8N1 frame: start, 8 data, stop
+const FRAME_BITS: u64 = 10;
+
+/// Receiver progress through a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RxState {
+    /// Waiting for a falling edge
+    Idle,
+    /// Sampling bit `bit` (8 = the stop bit) of a frame whose start bit
+    /// began at cycle `start`
+    Receiving { start: u64, bit: u8, value: u8 },
+}
+
+/// A serial terminal on GPIO pins
+#[derive(Debug, Clone)]
+pub struct SoftUart {
+    name: String,
+    baud: u32,
+    cycles_per_bit: f64,
+    /// Pin the firmware transmits on
+    rx_pin: Option<u8>,
+    /// Pin the firmware receives on
+    tx_pin: Option<u8>,
+    /// Cycles since reset
+    now: u64,
+
+    rx_state: RxState,
+    rx_level: bool,
+    received: Vec<u8>,
+    framing_errors: u64,
+
+    /// Characters waiting to be sent
+    tx_queue: VecDeque<u8>,
+    /// Character being sent and the cycle its start bit began
+    tx_frame: Option<(u8, u64)>,
+
+    /// Line being typed in the terminal panel
+    #[cfg(feature = "gui")]
+    input: String,
+}
+
+impl SoftUart {
+    pub fn new(name: &str, baud: u32) -> Self {
+        let mut uart = Self {
+            name: name.to_string(),
+            baud: baud.max(1),
+            cycles_per_bit: 0.0,
+            rx_pin: None,
+            tx_pin: None,
+            now: 0,
+            rx_state: RxState::Idle,
+            rx_level: true,
+            received: Vec::new(),
+            framing_errors: 0,
+            tx_queue: VecDeque::new(),
+            tx_frame: None,
+            #[cfg(feature = "gui")]
+            input: String::new(),
+        };
+        uart.set_clock(&Clock::new());
+        uart
+    }
+
+    /// Decode what the firmware sends on `pin`
+    pub fn with_rx(mut self, pin: u8) -> Self {
+        self.rx_pin = Some(pin);
+        self
+    }
+
+    /// Send characters to the firmware on `pin`
+    pub fn with_tx(mut self, pin: u8) -> Self {
+        self.tx_pin = Some(pin);
+        self
+    }
+
+    pub fn baud(&self) -> u32 {
+        self.baud
+    }
+
+    /// Bytes received so far
+    pub fn received(&self) -> &[u8] {
+        &self.received
+    }
+
+    /// Received bytes as text, with invalid UTF-8 replaced
+    pub fn received_text(&self) -> String {
+        String::from_utf8_lossy(&self.received).into_owned()
+    }
+
+    /// Frames whose stop bit was low
+    pub fn framing_errors(&self) -> u64 {
+        self.framing_errors
+    }
+
+    pub fn clear_received(&mut self) {
+        self.received.clear();
+        self.framing_errors = 0;
+    }
+
+    /// Queue bytes to send to the firmware
+    pub fn send(&mut self, bytes: &[u8]) {
+        self.tx_queue.extend(bytes);
+    }
+
+    /// Bytes queued or being sent
+    pub fn pending(&self) -> usize {
+        self.tx_queue.len() + usize::from(self.tx_frame.is_some())
+    }
+
+    /// Cycle at which bit `bit` of a frame started at `start` is sampled
+    /// (the middle of the bit)
+    fn sample_cycle(&self, start: u64, bit: u8) -> u64 {
+        start + ((f64::from(bit) + 1.5) * self.cycles_per_bit) as u64
+    }
+
+    fn receive(&mut self, level: bool) {
+        loop {
+            match self.rx_state {
+                RxState::Idle => {
+                    if self.rx_level && !level {
+                        self.rx_state = RxState::Receiving { start: self.now, bit: 0, value: 0 };
+                    }
+                    break;
+                }
+                RxState::Receiving { start, bit, value } => {
+                    if self.now < self.sample_cycle(start, bit) {
+                        break;
+                    }
+                    if bit < 8 {
+                        let value = value | (u8::from(level) << bit);
+                        self.rx_state = RxState::Receiving { start, bit: bit + 1, value };
+                    } else {
+                        if level {
+                            self.received.push(value);
+                        } else {
+                            self.framing_errors += 1;
+                        }
+                        self.rx_state = RxState::Idle;
+                        break;
+                    }
+                }
+            }
+        }
+        self.rx_level = level;
+    }
+
+    /// Line level to drive on the TX pin now
+    fn transmit(&mut self) -> bool {
+        loop {
+            let Some((byte, start)) = self.tx_frame else {
+                let Some(byte) = self.tx_queue.pop_front() else {
+                    return true;
+                };
+                self.tx_frame = Some((byte, self.now));
+                continue;
+            };
+            let bit = ((self.now - start) as f64 / self.cycles_per_bit) as u64;
+            match bit {
+                0 => return false,
+                1..=8 => return byte & (1 << (bit - 1)) != 0,
+                9 => return true,
+                _ => {
+                    // Next character starts where this frame ended
+                    let end = start + (FRAME_BITS as f64 * self.cycles_per_bit) as u64;
+                    self.tx_frame = self.tx_queue.pop_front().map(|byte| (byte, end));
+                    if self.tx_frame.is_none() {
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Component for SoftUart {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn pins(&self) -> Vec<u8> {
+        self.rx_pin.into_iter().chain(self.tx_pin).collect()
+    }
+
+    fn tick(&mut self, cycles: u64, cpu: &mut Cpu) {
+        self.now += cycles;
+        if let Some(pin) = self.rx_pin {
+            // An undriven line idles high
+            self.receive(cpu.gpio().get_pin_state(pin) != PinState::Low);
+        }
+        if let Some(pin) = self.tx_pin {
+            let level = self.transmit();
+            cpu.gpio_mut().set_external_pin(pin, level);
+        }
+    }
+
+    /// The terminal keeps its text across resets; frames in flight are lost
+    fn reset(&mut self) {
+        self.now = 0;
+        self.rx_state = RxState::Idle;
+        self.rx_level = true;
+        self.tx_queue.clear();
+        self.tx_frame = None;
+    }
+
+    fn set_clock(&mut self, clock: &Clock) {
+        self.cycles_per_bit = (clock.cycles_per_second() / f64::from(self.baud)).max(1.0);
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} @ {} baud: {} bytes received, {} queued, \"{}\"",
+            self.name,
+            self.baud,
+            self.received.len(),
+            self.pending(),
+            self.received_text().escape_debug()
+        )
+    }
+
+    #[cfg(feature = "gui")]
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(&self.name).strong());
+            let pin = |pin: Option<u8>| pin.map_or("-".to_string(), crate::gpio::pin_label);
+            ui.label(format!("{} baud, RX {}, TX {}", self.baud, pin(self.rx_pin), pin(self.tx_pin)));
+            if self.framing_errors > 0 {
+                ui.colored_label(egui::Color32::YELLOW, format!("{} framing errors", self.framing_errors));
+            }
+            if ui.small_button("Clear").clicked() {
+                self.clear_received();
+            }
+        });
+
+        let text: String = self.received_text()
+            .chars()
+            .filter(|&c| c != '\r')
+            .map(|c| if c == '\n' || !c.is_control() { c } else { '·' })
+            .collect();
+        egui::Frame::dark_canvas(ui.style()).show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_salt(("uart_terminal", &self.name))
+                .max_height(120.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    ui.set_min_width(ui.available_width());
+                    ui.label(egui::RichText::new(text).monospace().color(egui::Color32::LIGHT_GREEN));
+                });
+        });
+
+        if self.tx_pin.is_some() {
+            // Enter sends the line followed by CR, like a serial terminal
+            ui.horizontal(|ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut self.input)
+                    .hint_text("Type and press Enter")
+                    .desired_width(200.0));
+                let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("Send").clicked() || entered {
+                    let line = std::mem::take(&mut self.input);
+                    self.send(line.as_bytes());
+                    self.send(b"\r");
+                }
+                if self.pending() > 0 {
+                    ui.label(format!("{} queued", self.pending()));
+                }
+            });
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A UART at 9600 baud with 10 cycles per bit
+    fn uart() -> SoftUart {
+        let mut clock = Clock::new();
+        clock.set_fosc_hz(9600 * 10 * 4);
+        let mut uart = SoftUart::new("TERM", 9600).with_rx(0).with_tx(1);
+        uart.set_clock(&clock);
+        uart
+    }
+
+    #[test]
+    fn test_receive_and_send() {
+        let mut cpu = Cpu::new();
+        cpu.gpio_mut().write_tris(0x3E);
+        cpu.gpio_mut().write_gpio(0x01);
+        let mut uart = uart();
+
+        // Firmware sends 'H', then 'i' with a low stop bit
+        for (byte, stop) in [(b'H', 0x01), (b'i', 0x00)] {
+            let mut bits = vec![0x00];
+            bits.extend((0..8).map(|bit| (byte >> bit) & 1));
+            bits.extend([stop, 0x01, 0x01]);
+            for level in bits {
+                cpu.gpio_mut().write_gpio(level);
+                for _ in 0..10 {
+                    uart.tick(1, &mut cpu);
+                }
+            }
+        }
+        assert_eq!(uart.received(), b"H");
+        assert_eq!(uart.framing_errors(), 1);
+
+        // Terminal sends 'A' (0x41): start, 1000 0010, stop
+        uart.send(b"A");
+        let mut levels = Vec::new();
+        for cycle in 0..=100 {
+            uart.tick(1, &mut cpu);
+            if cycle % 10 == 5 && cycle < 100 {
+                levels.push(cpu.gpio().read_gpio() & 0x02 != 0);
+            }
+        }
+        let expected = [false, true, false, false, false, false, false, true, false, true];
+        assert_eq!(levels, expected);
+        assert_eq!(uart.pending(), 0);
+    }
+}
@@ -0,0 +1,143 @@
+//! WebAssembly bindings
+//!
+//! A `wasm-bindgen` wrapper around `Simulator` for browser front-ends.
+//! Build with `--no-default-features --features wasm` for the
+//! `wasm32-unknown-unknown` target. Firmware is passed in as bytes
+//! (e.g. from a file input) since there is no file system; file-based
+//! simulator methods return errors there.
+
+use wasm_bindgen::prelude::*;
+
+use crate::Debugger;
+use crate::cpu::registers;
+use crate::simulator::{RunOutcome, Simulator};
+
+/// Snapshot of the CPU registers, returned to JavaScript as an object
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct CpuState {
+    pub pc: u16,
+    pub w: u8,
+    pub status: u8,
+    pub gpio: u8,
+    pub trisio: u8,
+    pub sleeping: bool,
+    /// Cycles since reset (f64 so it is a plain JS number)
+    pub cycles: f64,
+    pub instructions: f64,
+}
+
+/// Simulator handle for JavaScript
+#[wasm_bindgen]
+pub struct WasmSimulator {
+    sim: Simulator,
+}
+
+#[wasm_bindgen]
+impl WasmSimulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        let mut sim = Simulator::new();
+        sim.reset();
+        Self { sim }
+    }
+
+    /// Load Intel HEX, S-record, ELF or COFF file contents
+    #[wasm_bindgen(js_name = loadHex)]
+    pub fn load_hex(&mut self, bytes: &[u8]) -> Result<(), JsError> {
+        if crate::elf::is_elf(bytes) {
+            self.sim.load_elf_bytes(bytes)?;
+        } else if crate::coff::is_coff(bytes) {
+            self.sim.load_coff_bytes(bytes)?;
+        } else {
+            let text = std::str::from_utf8(bytes)?;
+            self.sim.load_firmware_string(text)?;
+        }
+        Ok(())
+    }
+
+    /// Load program words at address 0
+    #[wasm_bindgen(js_name = loadProgram)]
+    pub fn load_program(&mut self, words: &[u16]) {
+        self.sim.load_program(words);
+    }
+
+    pub fn reset(&mut self) {
+        self.sim.reset();
+    }
+
+    /// Execute one instruction, returning the cycles it took
+    pub fn step(&mut self) -> Result<u8, JsError> {
+        Ok(self.sim.step()?)
+    }
+
+    /// Run up to `cycles` cycles; returns true if a breakpoint stopped it
+    #[wasm_bindgen(js_name = runCycles)]
+    pub fn run_cycles(&mut self, cycles: u32) -> Result<bool, JsError> {
+        match self.sim.run_until(|_| false, cycles as u64) {
+            RunOutcome::Error(e) => Err(e.into()),
+            RunOutcome::Breakpoint(_) | RunOutcome::Watchpoint(_) => Ok(true),
+            _ => Ok(false),
+        }
+    }
+
+    /// Current register state
+    pub fn state(&self) -> CpuState {
+        let cpu = self.sim.cpu();
+        CpuState {
+            pc: cpu.get_pc(),
+            w: cpu.read_w(),
+            status: cpu.read_register(registers::STATUS),
+            gpio: cpu.gpio().read_gpio(),
+            trisio: cpu.gpio().read_tris(),
+            sleeping: cpu.is_sleeping(),
+            cycles: self.sim.stats().cycles_elapsed as f64,
+            instructions: self.sim.stats().instructions_executed as f64,
+        }
+    }
+
+    /// Read a register (bank-resolved like the CPU does)
+    #[wasm_bindgen(js_name = readRegister)]
+    pub fn read_register(&self, address: u8) -> u8 {
+        self.sim.cpu().read_register(address)
+    }
+
+    /// Write a register
+    #[wasm_bindgen(js_name = writeRegister)]
+    pub fn write_register(&mut self, address: u8, value: u8) {
+        self.sim.cpu_mut().write_register(address, value);
+    }
+
+    /// Bank 0 data memory (0x00-0x7F) as a Uint8Array
+    pub fn ram(&self) -> Vec<u8> {
+        (0..0x80).map(|addr| self.sim.cpu().memory().read_data(addr)).collect()
+    }
+
+    /// Drive an external input pin
+    #[wasm_bindgen(js_name = setPin)]
+    pub fn set_pin(&mut self, pin: u8, level: bool) {
+        self.sim.set_external_pin(pin, level);
+    }
+
+    #[wasm_bindgen(js_name = addBreakpoint)]
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.sim.add_breakpoint(address);
+    }
+
+    #[wasm_bindgen(js_name = removeBreakpoint)]
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.sim.remove_breakpoint(address);
+    }
+
+    /// Disassemble the instruction at a program address
+    pub fn disassemble(&self, address: u16) -> String {
+        let word = self.sim.cpu().memory().read_program(address);
+        Debugger::disassemble_with_symbols(self.sim.device().core(), word, self.sim.symbols())
+    }
+}
+
+impl Default for WasmSimulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,160 @@
+//! Register access breakpoints
+//!
+//! Watchpoints stop execution when an instruction reads, writes or changes
+//! a specific register, e.g. "break whenever GPIO is written" or "break
+//! when OPTION_REG changes".
+
+use std::fmt;
+
+use crate::cpu::{registers, AccessKind, RegisterAccess};
+
+/// Accesses a watchpoint triggers on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    /// Any read of the register
+    Read,
+    /// Any write, even one that leaves the value unchanged
+    Write,
+    /// A write that changes the value
+    Change,
+    /// Any read or write
+    Access,
+}
+
+impl WatchKind {
+    /// Parse `read`, `write`, `change` or `access` (or their first letter)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "read" | "r" => Some(WatchKind::Read),
+            "write" | "w" => Some(WatchKind::Write),
+            "change" | "c" => Some(WatchKind::Change),
+            "access" | "a" | "rw" => Some(WatchKind::Access),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WatchKind::Read => "read",
+            WatchKind::Write => "write",
+            WatchKind::Change => "change",
+            WatchKind::Access => "access",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A breakpoint on accesses to one register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    /// Bank-resolved register address (e.g. 0x81 for OPTION_REG)
+    pub address: u8,
+    pub kind: WatchKind,
+    pub enabled: bool,
+
+    /// Times this watchpoint stopped execution
+    pub hits: u64,
+}
+
+impl Watchpoint {
+    pub fn new(address: u8, kind: WatchKind) -> Self {
+        Self { address, kind, enabled: true, hits: 0 }
+    }
+
+    /// Check if `access` triggers this watchpoint
+    pub fn matches(&self, access: &RegisterAccess) -> bool {
+        if access.address != self.address as u16 {
+            return false;
+        }
+        match self.kind {
+            WatchKind::Read => access.kind == AccessKind::Read,
+            WatchKind::Write => access.kind == AccessKind::Write,
+            WatchKind::Change => access.kind == AccessKind::Write && access.old != access.new,
+            WatchKind::Access => true,
+        }
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on {}", self.kind, register_name(self.address as u16))
+    }
+}
+
+/// A triggered watchpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Address of the instruction that made the access
+    pub pc: u16,
+    pub watchpoint: Watchpoint,
+    pub access: RegisterAccess,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = register_name(self.access.address);
+        match self.access.kind {
+            AccessKind::Read => write!(f, "{} read at PC=0x{:04X}: 0x{:02X}",
+                name, self.pc, self.access.new),
+            AccessKind::Write => write!(f, "{} written at PC=0x{:04X}: 0x{:02X} -> 0x{:02X}",
+                name, self.pc, self.access.old, self.access.new),
+        }
+    }
+}
+
+/// Register name, or its hex address if it has none
+pub fn register_name(address: u16) -> String {
+    match u8::try_from(address).ok().and_then(registers::name_of) {
+        Some(name) => name.to_string(),
+        None => format!("0x{:02X}", address),
+    }
+}
+
+/// Parse a register name (`GPIO`, `OPTION_REG`) or hex address
+pub fn parse_register(s: &str) -> Option<u8> {
+    registers::address_of(s).or_else(|| {
+        let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+        u8::from_str_radix(hex, 16).ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(address: u8, old: u8, new: u8) -> RegisterAccess {
+        RegisterAccess { address: address as u16, kind: AccessKind::Write, old, new }
+    }
+
+    #[test]
+    fn test_matches() {
+        let change = Watchpoint::new(registers::OPTION_REG, WatchKind::Change);
+        assert!(change.matches(&write(registers::OPTION_REG, 0xFF, 0x07)));
+        assert!(!change.matches(&write(registers::OPTION_REG, 0x07, 0x07)));
+        assert!(!change.matches(&write(registers::TMR0, 0x00, 0x07)));
+
+        let read = Watchpoint::new(registers::GPIO, WatchKind::Read);
+        let access = RegisterAccess { address: registers::GPIO as u16, kind: AccessKind::Read, old: 1, new: 1 };
+        assert!(read.matches(&access));
+        assert!(!read.matches(&write(registers::GPIO, 0, 1)));
+        assert!(Watchpoint::new(registers::GPIO, WatchKind::Access).matches(&access));
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(parse_register("gpio"), Some(registers::GPIO));
+        assert_eq!(parse_register("0x20"), Some(0x20));
+        assert_eq!(parse_register("bogus"), None);
+        assert_eq!(WatchKind::parse("W"), Some(WatchKind::Write));
+
+        let hit = WatchHit {
+            pc: 0x12,
+            watchpoint: Watchpoint::new(registers::GPIO, WatchKind::Write),
+            access: write(registers::GPIO, 0x00, 0x01),
+        };
+        assert_eq!(hit.to_string(), "GPIO written at PC=0x0012: 0x00 -> 0x01");
+        assert_eq!(hit.watchpoint.to_string(), "write on GPIO");
+    }
+}
@@ -0,0 +1,315 @@
+//! Value Change Dump (VCD) waveform export
+//!
+//! Samples pin levels, timer counters and interrupt flags every instruction
+//! cycle and streams the changes to a VCD file that can be opened in GTKWave.
+//! Timestamps are simulated time at the oscillator frequency the capture
+//! started with, in the coarsest unit that holds a whole instruction cycle
+//! (1 µs at 4 MHz, 1 ns at 20 MHz). Timer counters advance every cycle without a register write, so the
+//! writer samples the CPU directly instead of consuming `SimEvent`s.
+//!
+//! The signals follow the device: every pin of its ports, its timers and
+//! the interrupt flags of the peripherals it has.
+
+use std::io::{self, Write};
+
+use crate::Cpu;
+use crate::clock::Clock;
+use crate::cpu::registers;
+use crate::device::DeviceModel;
+
+/// Where a recorded signal's value is read from
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    /// Pin `bit` of port `index`
+    Pin(usize, u8),
+    Timer0,
+    Timer1,
+    /// A whole special function register
+    Register(u8),
+    /// One bit of a special function register
+    Flag(u8, u8),
+}
+
+/// A recorded signal
+#[derive(Debug, Clone)]
+struct Signal {
+    id: String,
+    name: String,
+    width: u32,
+    source: Source,
+}
+
+/// Signals recorded for `model`, with VCD identifiers assigned
+fn signals(model: &DeviceModel) -> Vec<Signal> {
+    let mut sources: Vec<(String, u32, Source)> = Vec::new();
+    for (index, port) in model.ports.iter().enumerate() {
+        for bit in (0..8).filter(|bit| port.pins & (1 << bit) != 0) {
+            sources.push((format!("{}{}", port.pin_prefix, bit), 1, Source::Pin(index, bit)));
+        }
+    }
+
+    sources.push(("TMR0".to_string(), 8, Source::Timer0));
+    if model.peripherals.timer1 {
+        sources.push(("TMR1".to_string(), 16, Source::Timer1));
+    }
+    if model.peripherals.ccp {
+        sources.push(("TMR2".to_string(), 8, Source::Register(registers::TMR2)));
+        sources.push(("CCPR1L".to_string(), 8, Source::Register(registers::CCPR1L)));
+    }
+
+    let mut flags = Vec::new();
+    if model.has_sfr(registers::INTCON) {
+        flags.extend([(registers::INTCON, 7), (registers::INTCON, 2), (registers::INTCON, 1), (registers::INTCON, 0)]);
+    }
+    if model.peripherals.timer1 {
+        flags.push((registers::PIR1, 0));
+    }
+    if model.peripherals.ccp {
+        flags.extend([(registers::PIR1, 1), (registers::PIR1, 5)]);
+    }
+    for (register, bit) in flags {
+        if let Some(name) = registers::bit_name(register, bit) {
+            sources.push((name.to_string(), 1, Source::Flag(register, bit)));
+        }
+    }
+
+    sources.into_iter()
+        .enumerate()
+        .map(|(index, (name, width, source))| Signal { id: identifier(index), name, width, source })
+        .collect()
+}
+
+/// Conversion from instruction cycles to VCD time
+#[derive(Debug, Clone, Copy)]
+struct Timescale {
+    unit: &'static str,
+    units_per_second: u64,
+    fosc_hz: u64,
+}
+
+impl Timescale {
+    fn new(clock: &Clock) -> Self {
+        let fosc_hz = clock.fosc_hz() as u64;
+        // A cycle is 4 oscillator periods; fall back to rounding to 1 ns
+        let (unit, units_per_second) = [("1us", 1_000_000), ("1ns", 1_000_000_000), ("1ps", 1_000_000_000_000)]
+            .into_iter()
+            .find(|&(_, per_second)| 4 * per_second % fosc_hz == 0)
+            .unwrap_or(("1ns", 1_000_000_000));
+        Self { unit, units_per_second, fosc_hz }
+    }
+
+    /// VCD time of the start of `cycle`
+    fn time(&self, cycle: u64) -> u64 {
+        let time = cycle as u128 * 4 * self.units_per_second as u128 / self.fosc_hz as u128;
+        time.min(u64::MAX as u128) as u64
+    }
+}
+
+/// VCD identifier of the `index`th signal, from the printable characters
+fn identifier(mut index: usize) -> String {
+    const FIRST: u8 = b'!';
+    const COUNT: usize = (b'~' - b'!') as usize + 1;
+    let mut id = String::new();
+    loop {
+        id.push((FIRST + (index % COUNT) as u8) as char);
+        index /= COUNT;
+        if index == 0 {
+            return id;
+        }
+        index -= 1;
+    }
+}
+
+/// Streaming VCD writer
+pub struct VcdWriter<W: Write> {
+    writer: W,
+    signals: Vec<Signal>,
+    timescale: Timescale,
+    last_values: Option<Vec<u16>>,
+    last_cycle: Option<u64>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// Create a writer for `model`'s signals timed by `clock` and emit the VCD header
+    pub fn new(mut writer: W, model: &DeviceModel, clock: &Clock) -> io::Result<Self> {
+        let signals = signals(model);
+        let timescale = Timescale::new(clock);
+        writeln!(writer, "$version pic_simulator {} $end", env!("CARGO_PKG_VERSION"))?;
+        writeln!(writer, "$timescale {} $end", timescale.unit)?;
+        writeln!(writer, "$scope module pic{} $end", model.name.to_ascii_lowercase())?;
+        for signal in &signals {
+            let kind = if signal.width == 1 { "wire" } else { "reg" };
+            writeln!(writer, "$var {} {} {} {} $end", kind, signal.width, signal.id, signal.name)?;
+        }
+        writeln!(writer, "$upscope $end")?;
+        writeln!(writer, "$enddefinitions $end")?;
+
+        Ok(Self {
+            writer,
+            signals,
+            timescale,
+            last_values: None,
+            last_cycle: None,
+            error: None,
+        })
+    }
+
+    /// Record the state of `cpu` at `cycle`
+    ///
+    /// Samples at or before the last recorded cycle (e.g. while replaying
+    /// after a step back) are ignored, as VCD time must increase.
+    /// Write errors are kept and reported by `finish`.
+    pub fn sample(&mut self, cycle: u64, cpu: &Cpu) {
+        if self.error.is_some() || self.last_cycle.is_some_and(|last| cycle <= last) {
+            return;
+        }
+
+        let values = self.signal_values(cpu);
+        if let Err(e) = self.write_changes(cycle, &values) {
+            self.error = Some(e);
+        }
+        self.last_values = Some(values);
+        self.last_cycle = Some(cycle);
+    }
+
+    fn signal_values(&self, cpu: &Cpu) -> Vec<u16> {
+        self.signals.iter()
+            .map(|signal| match signal.source {
+                Source::Pin(index, bit) => ((cpu.gpio().read_port(index) >> bit) & 1) as u16,
+                Source::Timer0 => cpu.timers().timer0.read_counter() as u16,
+                Source::Timer1 => cpu.timers().timer1.get_counter(),
+                Source::Register(address) => cpu.read_sfr(address) as u16,
+                Source::Flag(address, bit) => ((cpu.read_sfr(address) >> bit) & 1) as u16,
+            })
+            .collect()
+    }
+
+    fn write_changes(&mut self, cycle: u64, values: &[u16]) -> io::Result<()> {
+        let first = self.last_values.is_none();
+        let changed: Vec<usize> = (0..self.signals.len())
+            .filter(|&i| self.last_values.as_ref().is_none_or(|last| last[i] != values[i]))
+            .collect();
+
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(self.writer, "#{}", self.timescale.time(cycle))?;
+        if first {
+            writeln!(self.writer, "$dumpvars")?;
+        }
+        for i in changed {
+            let signal = &self.signals[i];
+            if signal.width == 1 {
+                writeln!(self.writer, "{}{}", values[i], signal.id)?;
+            } else {
+                writeln!(self.writer, "b{:b} {}", values[i], signal.id)?;
+            }
+        }
+        if first {
+            writeln!(self.writer, "$end")?;
+        }
+
+        Ok(())
+    }
+
+    /// Flush and return the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        if let Some(cycle) = self.last_cycle {
+            // Close the final interval so the last values are visible
+            writeln!(self.writer, "#{}", self.timescale.time(cycle + 1))?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ClockSource;
+    use crate::device::Device;
+
+    fn output(writer: VcdWriter<Vec<u8>>) -> String {
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_header_and_initial_dump() {
+        let mut vcd = VcdWriter::new(Vec::new(), Device::Pic12F629.model(), &Clock::new()).unwrap();
+        vcd.sample(0, &Cpu::new());
+
+        let text = output(vcd);
+        assert!(text.contains("$var wire 1 ! GP0 $end"));
+        assert!(text.contains("$var reg 16 ( TMR1 $end"));
+        // Undriven inputs read high
+        assert!(text.contains("#0\n$dumpvars\n1!"));
+    }
+
+    #[test]
+    fn test_only_changes_are_written() {
+        let mut cpu = Cpu::new();
+        cpu.reset();
+        let mut vcd = VcdWriter::new(Vec::new(), cpu.device().model(), &Clock::new()).unwrap();
+
+        vcd.sample(0, &cpu);
+        vcd.sample(1, &cpu);
+        cpu.write_register(registers::TRISIO, 0x00);
+        cpu.write_register(registers::GPIO, 0x00);
+        vcd.sample(2, &cpu);
+        vcd.sample(1, &cpu);
+
+        let text = output(vcd);
+        assert!(!text.contains("#1\n"));
+        assert!(text.contains("#2\n0!\n"));
+        assert!(text.ends_with("#3\n"));
+    }
+
+    #[test]
+    fn test_signals_follow_device() {
+        let text = output(VcdWriter::new(Vec::new(), Device::Pic16F84A.model(), &Clock::new()).unwrap());
+        assert!(text.contains("$scope module pic16f84a $end"));
+        assert!(text.contains(" RB7 $end") && text.contains(" RA4 $end"));
+        assert!(!text.contains(" GP0 ") && !text.contains(" TMR1 "));
+
+        let text = output(VcdWriter::new(Vec::new(), Device::Pic12F683.model(), &Clock::new()).unwrap());
+        assert!(text.contains(" TMR2 $end") && text.contains(" CCPR1L $end"));
+        assert!(text.contains(" TMR2IF $end") && text.contains(" CCP1IF $end"));
+
+        let text = output(VcdWriter::new(Vec::new(), Device::Pic10F200.model(), &Clock::new()).unwrap());
+        assert!(!text.contains(" GIE "));
+
+        assert_eq!(identifier(0), "!");
+        assert_eq!(identifier(93), "~");
+        assert_eq!(identifier(94), "!!");
+    }
+
+    #[test]
+    fn test_timescale_follows_clock() {
+        let cpu = Cpu::new();
+        let mut vcd = VcdWriter::new(Vec::new(), cpu.device().model(), &Clock::new()).unwrap();
+        vcd.sample(0, &cpu);
+        let text = output(vcd);
+        assert!(text.contains("$timescale 1us $end"));
+        assert!(text.ends_with("#1\n"));
+
+        let mut clock = Clock::with_source(ClockSource::Hs);
+        clock.set_fosc_hz(20_000_000);
+        let mut vcd = VcdWriter::new(Vec::new(), cpu.device().model(), &clock).unwrap();
+        vcd.sample(5, &cpu);
+        let text = output(vcd);
+        assert!(text.contains("$timescale 1ns $end"));
+        assert!(text.contains("#1000\n$dumpvars"));
+        assert!(text.ends_with("#1200\n"));
+
+        // 32.768 kHz: 122070.3125 ns per cycle, rounded down
+        clock.set_fosc_hz(32_768);
+        let timescale = Timescale::new(&clock);
+        assert_eq!(timescale.unit, "1ns");
+        assert_eq!(timescale.time(2), 244_140);
+    }
+}
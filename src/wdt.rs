@@ -1,14 +1,163 @@
-/// PIC12F629/675 Watchdog Timer (WDT)
-/// 
-/// Reference: Section 9.8 - Watchdog Timer (WDT) (Page 49-50)
-/// 
-/// The WDT is a free-running on-chip RC oscillator which does not require
-/// any external components. During normal operation, a WDT timeout generates
-/// a device RESET. If the device is in SLEEP mode, a WDT timeout causes the
-/// device to wake-up and continue with normal operation.
+//! PIC12F629/675 Watchdog Timer (WDT)
+//! 
+//! Reference: Section 9.8 - Watchdog Timer (WDT) (Page 49-50)
+//! 
+//! The WDT is a free-running on-chip RC oscillator which does not require
+//! any external components. During normal operation, a WDT timeout generates
+//! a device RESET. If the device is in SLEEP mode, a WDT timeout causes the
+//! device to wake-up and continue with normal operation.
+//!
+//! The RC oscillator is far from exact: the period without prescaler is
+//! anywhere from 7 ms to 33 ms over voltage and temperature (parameter 31,
+//! TWDT). By default every period is the nominal 18 ms; with a `WdtJitter`
+//! set, each period is drawn from a distribution over that range using a
+//! seeded generator, so firmware can be tested against worst-case or
+//! randomised timing and the run still repeats exactly.
+
+use std::fmt;
+
+use crate::clock::WDT_NOMINAL_PERIOD_US;
+use crate::error::StateError;
+use crate::state::{StateRecord, invalid_value};
+
+/// Shortest WDT period without prescaler (TWDT min), in microseconds
+pub const WDT_MIN_PERIOD_US: u32 = 7_000;
+
+/// Longest WDT period without prescaler (TWDT max), in microseconds
+pub const WDT_MAX_PERIOD_US: u32 = 33_000;
+
+/// How WDT periods are picked from the tolerance range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WdtDistribution {
+    /// Always the minimum
+    Shortest,
+    /// Always the maximum
+    Longest,
+    /// Any period in the range equally likely
+    Uniform,
+    /// Centred on the middle of the range, tailing off to the limits
+    Triangular,
+}
+
+impl WdtDistribution {
+    pub const ALL: [WdtDistribution; 4] = [
+        WdtDistribution::Shortest,
+        WdtDistribution::Longest,
+        WdtDistribution::Uniform,
+        WdtDistribution::Triangular,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WdtDistribution::Shortest => "shortest",
+            WdtDistribution::Longest => "longest",
+            WdtDistribution::Uniform => "uniform",
+            WdtDistribution::Triangular => "triangular",
+        }
+    }
+
+    /// Look up a distribution by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for WdtDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Seeded source of WDT periods
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WdtJitter {
+    pub distribution: WdtDistribution,
+    /// Shortest period in microseconds
+    pub min_us: u32,
+    /// Longest period in microseconds
+    pub max_us: u32,
+    seed: u64,
+    /// Generator state (splitmix64)
+    state: u64,
+}
+
+impl WdtJitter {
+    /// Periods over the datasheet range, generated from `seed`
+    pub fn new(distribution: WdtDistribution, seed: u64) -> Self {
+        Self {
+            distribution,
+            min_us: WDT_MIN_PERIOD_US,
+            max_us: WDT_MAX_PERIOD_US,
+            seed,
+            state: seed,
+        }
+    }
+
+    /// Use a custom range instead of the datasheet limits
+    pub fn with_range(mut self, min_us: u32, max_us: u32) -> Self {
+        self.min_us = min_us.max(1);
+        self.max_us = max_us.max(self.min_us);
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Start the sequence of periods again from the seed
+    pub fn restart(&mut self) {
+        self.state = self.seed;
+    }
+
+    fn next_random(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Draw the next period in microseconds
+    pub fn next_period_us(&mut self) -> u32 {
+        let span = (self.max_us - self.min_us) as u64 + 1;
+        let offset = match self.distribution {
+            WdtDistribution::Shortest => 0,
+            WdtDistribution::Longest => span - 1,
+            WdtDistribution::Uniform => self.next_random() % span,
+            WdtDistribution::Triangular => {
+                (self.next_random() % span + self.next_random() % span) / 2
+            }
+        };
+        self.min_us + offset as u32
+    }
+
+    /// Parse `<distribution> <min_us> <max_us> <seed> <state>` as saved in a state file
+    fn parse(text: &str) -> Option<Self> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let [distribution, min_us, max_us, seed, state] = words[..] else {
+            return None;
+        };
+        Some(Self {
+            distribution: WdtDistribution::from_name(distribution)?,
+            min_us: min_us.parse().ok()?,
+            max_us: max_us.parse().ok()?,
+            seed: seed.parse().ok()?,
+            state: state.parse().ok()?,
+        })
+    }
+}
+
+impl fmt::Display for WdtJitter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {} {}", self.distribution, self.min_us, self.max_us, self.seed, self.state)
+    }
+}
 
 /// Watchdog Timer controller
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Wdt {
     /// WDT counter (18-bit)
     counter: u32,
@@ -25,13 +174,22 @@ pub struct Wdt {
     /// Prescaler assigned to WDT (not Timer0)
     prescaler_assigned: bool,
     
+    /// Cycles in the nominal 18ms period without prescaler (depends on Fosc)
+    nominal_period: u32,
+    
+    /// Length of the current period without prescaler, in microseconds
+    period_us: u32,
+    
+    /// Period tolerance; None for exactly the nominal period
+    jitter: Option<WdtJitter>,
+    
     /// WDT timeout period (nominal 18ms without prescaler)
     /// With maximum prescaler (1:128), timeout is ~2.3 seconds
     timeout_period: u32,
 }
 
 impl Wdt {
-    /// Nominal WDT period without prescaler (in instruction cycles)
+    /// Default nominal WDT period without prescaler (in instruction cycles)
     /// Assuming 4MHz Fosc: 18ms / (1us/cycle) = 18000 cycles
     const NOMINAL_PERIOD: u32 = 18000;
     
@@ -42,17 +200,70 @@ impl Wdt {
             prescaler: 0,
             prescaler_rate: 1,
             prescaler_assigned: false,
+            nominal_period: Self::NOMINAL_PERIOD,
+            period_us: WDT_NOMINAL_PERIOD_US as u32,
+            jitter: None,
             timeout_period: Self::NOMINAL_PERIOD,
         }
     }
     
+    /// Device reset; a new period starts, continuing the jitter sequence
     pub fn reset(&mut self) {
         self.counter = 0;
         self.enabled = true;
         self.prescaler = 0;
         self.prescaler_rate = 1;
         self.prescaler_assigned = false;
-        self.timeout_period = Self::NOMINAL_PERIOD;
+        self.next_period();
+    }
+    
+    /// Set the nominal period in instruction cycles (see `Clock::wdt_period_cycles`)
+    /// The oscillator is not affected by a device reset, so this is kept across `reset`
+    pub fn set_nominal_period(&mut self, cycles: u32) {
+        self.nominal_period = cycles.max(1);
+        self.update_timeout();
+    }
+    
+    /// Set the period tolerance (None for the exact nominal period)
+    /// 
+    /// The sequence starts from the seed and the current period is redrawn.
+    /// Like the nominal period, this is kept across `reset`.
+    pub fn set_jitter(&mut self, jitter: Option<WdtJitter>) {
+        self.jitter = jitter;
+        self.restart_jitter();
+    }
+    
+    pub fn jitter(&self) -> Option<&WdtJitter> {
+        self.jitter.as_ref()
+    }
+    
+    /// Restart the jitter sequence from its seed and redraw the current period
+    pub fn restart_jitter(&mut self) {
+        if let Some(jitter) = &mut self.jitter {
+            jitter.restart();
+        }
+        self.next_period();
+    }
+    
+    /// Length of the current period without prescaler, in microseconds
+    pub fn period_us(&self) -> u32 {
+        self.period_us
+    }
+    
+    /// Pick the length of the period that is starting
+    fn next_period(&mut self) {
+        self.period_us = match &mut self.jitter {
+            Some(jitter) => jitter.next_period_us(),
+            None => WDT_NOMINAL_PERIOD_US as u32,
+        };
+        self.update_timeout();
+    }
+    
+    /// Derive the timeout from the current period, clock and prescaler
+    fn update_timeout(&mut self) {
+        let period = self.nominal_period as u64 * self.period_us as u64 / WDT_NOMINAL_PERIOD_US;
+        let period = period.clamp(1, u32::MAX as u64) as u32;
+        self.timeout_period = period.saturating_mul(self.prescaler_rate as u32);
     }
     
     /// Clear WDT counter (CLRWDT instruction)
@@ -82,12 +293,10 @@ impl Wdt {
                 7 => 128,
                 _ => 1,
             };
-            
-            self.timeout_period = Self::NOMINAL_PERIOD * (self.prescaler_rate as u32);
         } else {
             self.prescaler_rate = 1;
-            self.timeout_period = Self::NOMINAL_PERIOD;
         }
+        self.update_timeout();
         
         // Clear prescaler when assignment changes
         self.prescaler = 0;
@@ -126,12 +335,44 @@ impl Wdt {
         if self.counter >= self.timeout_period {
             self.counter = 0;
             self.prescaler = 0;
+            self.next_period();
             return true; // WDT timeout - should cause reset or wake-up
         }
         
         false
     }
     
+    /// Number of ticks until the one that times out, or None if disabled
+    pub fn cycles_until_timeout(&self) -> Option<u64> {
+        if !self.enabled {
+            return None;
+        }
+        
+        let remaining = self.timeout_period.saturating_sub(self.counter).max(1) as u64;
+        if self.prescaler_assigned && self.prescaler_rate > 1 {
+            let rate = self.prescaler_rate as u64;
+            Some((remaining - 1) * rate + (rate - self.prescaler as u64))
+        } else {
+            Some(remaining)
+        }
+    }
+    
+    /// Apply `cycles` ticks at once; must be fewer than `cycles_until_timeout`
+    pub fn advance(&mut self, cycles: u64) {
+        if !self.enabled {
+            return;
+        }
+        
+        if self.prescaler_assigned && self.prescaler_rate > 1 {
+            let rate = self.prescaler_rate as u64;
+            let total = self.prescaler as u64 + cycles;
+            self.counter += (total / rate) as u32;
+            self.prescaler = (total % rate) as u16;
+        } else {
+            self.counter += cycles as u32;
+        }
+    }
+    
     /// Get current counter value (for debugging)
     pub fn get_counter(&self) -> u32 {
         self.counter
@@ -141,6 +382,51 @@ impl Wdt {
     pub fn get_timeout_period(&self) -> u32 {
         self.timeout_period
     }
+    
+    /// Check if the shared prescaler is assigned to the WDT
+    pub fn is_prescaler_assigned(&self) -> bool {
+        self.prescaler_assigned
+    }
+    
+    /// Prescaler division (1 when assigned to Timer0)
+    pub fn prescaler_rate(&self) -> u16 {
+        self.prescaler_rate
+    }
+
+    /// Save counter, prescaler and timeout
+    pub fn save_state(&self, record: &mut StateRecord) {
+        record.set("wdt.counter", self.counter);
+        record.set("wdt.enabled", self.enabled);
+        record.set("wdt.prescaler", self.prescaler);
+        record.set("wdt.prescaler_rate", self.prescaler_rate);
+        record.set("wdt.prescaler_assigned", self.prescaler_assigned);
+        record.set("wdt.nominal_period", self.nominal_period);
+        record.set("wdt.timeout_period", self.timeout_period);
+        record.set("wdt.period_us", self.period_us);
+        match &self.jitter {
+            Some(jitter) => record.set("wdt.jitter", jitter),
+            None => record.set("wdt.jitter", "off"),
+        }
+    }
+    
+    /// Restore state saved with `save_state`
+    pub fn load_state(&mut self, record: &StateRecord) -> Result<(), StateError> {
+        self.counter = record.get("wdt.counter")?;
+        self.enabled = record.get("wdt.enabled")?;
+        self.prescaler = record.get("wdt.prescaler")?;
+        self.prescaler_rate = record.get("wdt.prescaler_rate")?;
+        self.prescaler_assigned = record.get("wdt.prescaler_assigned")?;
+        self.nominal_period = record.get("wdt.nominal_period")?;
+        self.timeout_period = record.get("wdt.timeout_period")?;
+        self.period_us = record.get("wdt.period_us")?;
+        let jitter: String = record.get("wdt.jitter")?;
+        self.jitter = match jitter.as_str() {
+            "off" => None,
+            text => Some(WdtJitter::parse(text)
+                .ok_or_else(|| invalid_value("wdt.jitter", text))?),
+        };
+        Ok(())
+    }
 }
 
 impl Default for Wdt {
@@ -201,5 +487,67 @@ mod tests {
         wdt.configure_prescaler(option_reg);
         
         assert_eq!(wdt.get_timeout_period(), Wdt::NOMINAL_PERIOD * 4);
+        assert!(wdt.is_prescaler_assigned());
+        assert_eq!(wdt.prescaler_rate(), 4);
+        
+        // Assigned to Timer0, the WDT runs undivided
+        wdt.configure_prescaler(0x02);
+        assert!(!wdt.is_prescaler_assigned());
+        assert_eq!(wdt.prescaler_rate(), 1);
+    }
+    
+    #[test]
+    fn test_wdt_advance() {
+        let mut wdt = Wdt::new();
+        wdt.configure_prescaler(0x0A); // 1:4
+        wdt.tick();
+        
+        let remaining = wdt.cycles_until_timeout().unwrap();
+        assert_eq!(remaining, wdt.get_timeout_period() as u64 * 4 - 1);
+        wdt.advance(remaining - 1);
+        assert_eq!(wdt.cycles_until_timeout(), Some(1));
+        assert!(wdt.tick());
+        
+        wdt.set_enabled(false);
+        assert_eq!(wdt.cycles_until_timeout(), None);
+    }
+    
+    #[test]
+    fn test_wdt_jitter() {
+        let mut wdt = Wdt::new();
+        wdt.set_jitter(Some(WdtJitter::new(WdtDistribution::Shortest, 0)));
+        assert_eq!(wdt.get_timeout_period(), 7_000);
+        wdt.set_jitter(Some(WdtJitter::new(WdtDistribution::Longest, 0)));
+        assert_eq!(wdt.get_timeout_period(), 33_000);
+        
+        // Seeded periods vary within the range and repeat after a restart
+        wdt.set_jitter(Some(WdtJitter::new(WdtDistribution::Uniform, 42)));
+        let mut periods = Vec::new();
+        for _ in 0..20 {
+            periods.push(wdt.get_timeout_period());
+            let ticks = wdt.cycles_until_timeout().unwrap();
+            wdt.advance(ticks - 1);
+            assert!(wdt.tick());
+        }
+        assert!(periods.iter().all(|p| (7_000..=33_000).contains(p)));
+        assert!(periods.iter().any(|&p| p != periods[0]));
+        wdt.restart_jitter();
+        assert_eq!(wdt.get_timeout_period(), periods[0]);
+        
+        wdt.set_jitter(None);
+        assert_eq!(wdt.get_timeout_period(), Wdt::NOMINAL_PERIOD);
+    }
+    
+    #[test]
+    fn test_wdt_nominal_period() {
+        let mut wdt = Wdt::new();
+        wdt.configure_prescaler(0x0A); // 1:4
+        
+        // 20 MHz: 18ms = 90000 instruction cycles
+        wdt.set_nominal_period(90_000);
+        assert_eq!(wdt.get_timeout_period(), 360_000);
+        
+        wdt.reset();
+        assert_eq!(wdt.get_timeout_period(), 90_000);
     }
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+//! Allocation check for `Simulator::step`
+//!
+//! Installing a counting global allocator affects every test in the
+//! binary, so this check lives in its own integration test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use pic_simulator::Simulator;
+
+thread_local! {
+    /// Allocations made by the current test thread
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// System allocator counting allocations per thread
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_step_does_not_allocate() {
+    let mut sim = Simulator::new();
+    sim.reset();
+    // MOVLW 5; MOVWF 0x20; INCF 0x20, F; GOTO 0
+    sim.load_program(&[0x3005, 0x00A0, 0x0AA0, 0x2800]);
+    // Observers turn on register access tracking
+    sim.subscribe(|_| {});
+    // Warm up past a history snapshot (taken every 1000 instructions)
+    // while the capture buffers grow toward their capacity
+    sim.run_n_instructions(2100).unwrap();
+
+    // Stop short of the next snapshot, which copies the machine state
+    let before = ALLOCATIONS.with(Cell::get);
+    for _ in 0..500 {
+        sim.step().unwrap();
+    }
+    assert_eq!(ALLOCATIONS.with(Cell::get) - before, 0);
+}